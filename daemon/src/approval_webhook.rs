@@ -0,0 +1,328 @@
+// Approval webhook - with approval_webhook.url set, every permission dialog
+// that needs a person (session:approval_requested; policy-approved ones never
+// get here) is posted to that Slack-style incoming webhook with Approve and
+// Deny links, so an unattended session can be supervised from a phone. The
+// links go to the HTTP API:
+//   GET  /approval/{approval_id}/{decision}?exp=<unix>&sig=<hex>
+// shows a page with a button that POSTs to the same URL, which answers the
+// dialog (a GET alone never does, so chat link previews can't). They need no
+// bearer token: sig is HMAC-SHA256 over "approval_id|decision|exp" keyed with
+// approval.secret in the data dir, and the link stops working at exp. The
+// answer goes through session.approve / session.deny, so whichever of the GUI,
+// the terminal or a link answers first wins and the rest are told it's gone.
+
+use anyhow::Result;
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sha2::Sha256;
+use shared::{ApprovalRequestedData, Event};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tracing::warn;
+use uuid::Uuid;
+
+use crate::config::{ApprovalWebhookConfig, SharedConfig};
+use crate::egress::{self, Egress};
+use crate::hook_manager::{decode_hex, to_hex};
+use crate::state::SharedState;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Longest part of the dialog's summary put in the message
+const MAX_SUMMARY_CHARS: usize = 500;
+
+/// The answer a link gives
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Decision {
+    Approve,
+    Deny,
+}
+
+impl Decision {
+    /// As it appears in links
+    pub fn name(&self) -> &'static str {
+        match self {
+            Decision::Approve => "approve",
+            Decision::Deny => "deny",
+        }
+    }
+
+    /// The method that gives this answer
+    pub fn method(&self) -> &'static str {
+        match self {
+            Decision::Approve => "session.approve",
+            Decision::Deny => "session.deny",
+        }
+    }
+}
+
+/// Why a link was refused
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum LinkError {
+    #[error("the link's signature is invalid")]
+    InvalidSignature,
+    #[error("the link has expired")]
+    Expired,
+}
+
+/// Signs and checks approval links
+pub struct ApprovalLinks {
+    secret: String,
+}
+
+impl ApprovalLinks {
+    pub fn new(secret: String) -> Self {
+        Self { secret }
+    }
+
+    fn mac(&self, approval_id: Uuid, decision: Decision, exp: u64) -> HmacSha256 {
+        let mut mac =
+            HmacSha256::new_from_slice(self.secret.as_bytes()).expect("HMAC accepts any key");
+        mac.update(format!("{}|{}|{}", approval_id, decision.name(), exp).as_bytes());
+        mac
+    }
+
+    /// The link under `base` that answers `approval_id` with `decision` until `exp`
+    pub fn link(&self, base: &str, approval_id: Uuid, decision: Decision, exp: u64) -> String {
+        let sig = to_hex(&self.mac(approval_id, decision, exp).finalize().into_bytes());
+        format!(
+            "{}/approval/{}/{}?exp={}&sig={}",
+            base.trim_end_matches('/'),
+            approval_id,
+            decision.name(),
+            exp,
+            sig
+        )
+    }
+
+    /// Check a link's signature and expiry against the current time
+    pub fn verify(
+        &self,
+        approval_id: Uuid,
+        decision: Decision,
+        exp: u64,
+        sig: &str,
+    ) -> Result<(), LinkError> {
+        self.verify_at(approval_id, decision, exp, sig, unix_now())
+    }
+
+    fn verify_at(
+        &self,
+        approval_id: Uuid,
+        decision: Decision,
+        exp: u64,
+        sig: &str,
+        now: u64,
+    ) -> Result<(), LinkError> {
+        let bytes = decode_hex(sig).ok_or(LinkError::InvalidSignature)?;
+        // verify_slice compares in constant time
+        self.mac(approval_id, decision, exp)
+            .verify_slice(&bytes)
+            .map_err(|_| LinkError::InvalidSignature)?;
+        if now > exp {
+            return Err(LinkError::Expired);
+        }
+        Ok(())
+    }
+}
+
+fn unix_now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Escape the characters Slack's message format treats as markup
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// The webhook body for a dialog in session `name`
+fn message(
+    links: &ApprovalLinks,
+    settings: &ApprovalWebhookConfig,
+    base: &str,
+    name: &str,
+    data: &ApprovalRequestedData,
+    now: u64,
+) -> serde_json::Value {
+    let exp = now + settings.link_ttl_mins * 60;
+    let mut summary: String = data.summary.chars().take(MAX_SUMMARY_CHARS).collect();
+    if summary.len() < data.summary.len() {
+        summary.push('…');
+    }
+    let mut text = format!(
+        "*{}* is asking to use {}: {}",
+        escape(name),
+        escape(&data.tool),
+        escape(&data.question)
+    );
+    if !summary.is_empty() {
+        text += &format!("\n```{}```", escape(&summary));
+    }
+    text += &format!(
+        "\n<{}|Approve>  ·  <{}|Deny>  (links work for {} min)",
+        links.link(base, data.approval_id, Decision::Approve, exp),
+        links.link(base, data.approval_id, Decision::Deny, exp),
+        settings.link_ttl_mins
+    );
+    json!({ "text": text })
+}
+
+/// Posts permission dialogs to approval_webhook.url
+pub struct ApprovalWebhook {
+    state: SharedState,
+    config: SharedConfig,
+    links: Arc<ApprovalLinks>,
+    event_tx: broadcast::Sender<Event>,
+}
+
+impl ApprovalWebhook {
+    pub fn new(
+        state: SharedState,
+        config: SharedConfig,
+        links: Arc<ApprovalLinks>,
+        event_tx: broadcast::Sender<Event>,
+    ) -> Self {
+        Self {
+            state,
+            config,
+            links,
+            event_tx,
+        }
+    }
+
+    /// Post each dialog as it opens
+    pub async fn run(self) {
+        let mut event_rx = self.event_tx.subscribe();
+        loop {
+            let data = match event_rx.recv().await {
+                Ok(Event::ApprovalRequested(data)) => data,
+                Ok(_) => continue,
+                Err(broadcast::error::RecvError::Lagged(n)) => {
+                    warn!("Approval webhook lagged, skipped {} events", n);
+                    continue;
+                }
+                Err(broadcast::error::RecvError::Closed) => return,
+            };
+
+            let settings = self.config.read().await.approval_webhook.clone();
+            let Some(url) = settings.url.clone() else {
+                continue;
+            };
+            let Some(base) = settings.link_base.clone() else {
+                warn!("approval_webhook.url is set without link_base; not posting");
+                continue;
+            };
+            let name = self
+                .state
+                .read()
+                .await
+                .sessions
+                .get(&data.session_id)
+                .map_or_else(|| data.session_id.to_string(), |s| s.name.clone());
+            let body = message(&self.links, &settings, &base, &name, &data, unix_now());
+
+            let config = self.config.clone();
+            tokio::spawn(async move {
+                if let Err(e) = post(&config, &url, &body).await {
+                    warn!(
+                        "Approval webhook for session {} failed: {}",
+                        data.session_id, e
+                    );
+                }
+            });
+        }
+    }
+}
+
+async fn post(config: &SharedConfig, url: &str, body: &serde_json::Value) -> Result<()> {
+    egress::check(&*config.read().await, Egress::ApprovalWebhook)?;
+    reqwest::Client::builder()
+        .timeout(Duration::from_secs(30))
+        .build()?
+        .post(url)
+        .json(body)
+        .send()
+        .await?
+        .error_for_status()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_link_signature() {
+        let links = ApprovalLinks::new("secret".to_string());
+        let id = Uuid::new_v4();
+        let link = links.link("https://host:7878/", id, Decision::Approve, 1000);
+        let prefix = format!("https://host:7878/approval/{}/approve?exp=1000&sig=", id);
+        let sig = link.strip_prefix(&prefix).expect(&link);
+
+        assert_eq!(
+            links.verify_at(id, Decision::Approve, 1000, sig, 999),
+            Ok(())
+        );
+        // The signature doesn't carry over to another decision, approval or expiry
+        assert_eq!(
+            links.verify_at(id, Decision::Deny, 1000, sig, 999),
+            Err(LinkError::InvalidSignature)
+        );
+        assert_eq!(
+            links.verify_at(Uuid::new_v4(), Decision::Approve, 1000, sig, 999),
+            Err(LinkError::InvalidSignature)
+        );
+        assert_eq!(
+            links.verify_at(id, Decision::Approve, 5000, sig, 999),
+            Err(LinkError::InvalidSignature)
+        );
+        assert_eq!(
+            links.verify_at(id, Decision::Approve, 1000, "zz", 999),
+            Err(LinkError::InvalidSignature)
+        );
+        // Nor to another install
+        let other = ApprovalLinks::new("other".to_string());
+        assert_eq!(
+            other.verify_at(id, Decision::Approve, 1000, sig, 999),
+            Err(LinkError::InvalidSignature)
+        );
+
+        assert_eq!(
+            links.verify_at(id, Decision::Approve, 1000, sig, 1001),
+            Err(LinkError::Expired)
+        );
+    }
+
+    #[test]
+    fn test_message() {
+        let links = ApprovalLinks::new("secret".to_string());
+        let data = ApprovalRequestedData {
+            session_id: Uuid::new_v4(),
+            approval_id: Uuid::new_v4(),
+            tool: "Bash".to_string(),
+            title: "Bash command".to_string(),
+            summary: "echo <hi> && rm -rf build".to_string(),
+            question: "Do you want to proceed?".to_string(),
+            path: None,
+            command: Some("rm -rf build".to_string()),
+        };
+        let settings = ApprovalWebhookConfig::default();
+        let body = message(&links, &settings, "https://host", "api", &data, 100);
+        let text = body["text"].as_str().unwrap();
+
+        assert!(text.starts_with("*api* is asking to use Bash: Do you want to proceed?"));
+        assert!(text.contains("echo &lt;hi&gt; &amp;&amp; rm -rf build"));
+        let approve = links.link("https://host", data.approval_id, Decision::Approve, 3700);
+        let deny = links.link("https://host", data.approval_id, Decision::Deny, 3700);
+        assert!(text.contains(&format!("<{}|Approve>", approve)));
+        assert!(text.contains(&format!("<{}|Deny>", deny)));
+    }
+}
@@ -0,0 +1,443 @@
+// Tool approvals - Claude's permission dialog ("Do you want to proceed?") is
+// found in the bottom of the session's screen and surfaced as a
+// session:approval_requested event, with the tool and its file or command from
+// the PreToolUse hook when it reported them. session.approve / session.deny
+// answer it with the dialog's keys, so a client can offer buttons instead of
+// the raw terminal.
+//
+// PreToolUse also fires for tools claude runs without asking, and its event can
+// arrive after the dialog is drawn (or not at all), so the last hook input is
+// only used when the dialog shows that tool and its command or file; a dialog
+// without one is only ever answered by a person.
+
+use regex::Regex;
+use shared::{ApprovalRequestedData, ApprovalResolvedData, Event};
+use std::collections::HashMap;
+use std::sync::{LazyLock, Mutex};
+use uuid::Uuid;
+
+/// Screen rows searched for a permission dialog
+pub const DIALOG_LINES: usize = 20;
+
+/// Selects "1. Yes" in the dialog
+pub const APPROVE_KEYS: &[u8] = b"1";
+/// "No, and tell Claude what to do differently (esc)"
+pub const DENY_KEYS: &[u8] = b"\x1b";
+
+static QUESTION: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?i)^do you want to").unwrap());
+static YES_OPTION: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^[❯>]?\s*1\.\s+Yes\b").unwrap());
+
+/// A permission dialog as drawn on screen
+#[derive(Debug, PartialEq, Eq)]
+pub struct Dialog {
+    /// First line of the dialog, e.g. "Bash command"
+    pub title: String,
+    /// What the tool will do, e.g. the command and its description
+    pub summary: String,
+    pub question: String,
+}
+
+/// Find a permission dialog in the bottom lines of a screen
+pub fn parse_dialog(bottom: &str) -> Option<Dialog> {
+    // Without the box drawn around the dialog
+    let lines: Vec<&str> = bottom
+        .lines()
+        .map(|line| line.trim().trim_matches('│').trim())
+        .collect();
+    let q = lines.iter().rposition(|line| QUESTION.is_match(line))?;
+    if !lines[q + 1..].iter().any(|line| YES_OPTION.is_match(line)) {
+        return None;
+    }
+
+    // The dialog starts below its top border
+    let start = lines[..q]
+        .iter()
+        .rposition(|line| {
+            line.starts_with('╭') || (!line.is_empty() && line.chars().all(|c| c == '─'))
+        })
+        .map_or(0, |i| i + 1);
+    let mut content = lines[start..q].iter().filter(|line| !line.is_empty());
+    let title = content.next()?.to_string();
+    let summary = content.copied().collect::<Vec<_>>().join("\n");
+    Some(Dialog {
+        title,
+        summary,
+        question: lines[q].to_string(),
+    })
+}
+
+/// A status detail for a tool waiting to be allowed, e.g. "permission for
+/// Bash(rm -rf build)"; `input` is its command or file, first line only
+pub fn permission_detail(tool: &str, input: Option<&str>) -> String {
+    match input.and_then(|input| input.lines().next()) {
+        Some(input) => format!("permission for {}({})", tool, input.trim()),
+        None => format!("permission for {}", tool),
+    }
+}
+
+/// What the last PreToolUse hook of a session said about the tool
+#[derive(Debug, Clone, Default)]
+pub struct HookInput {
+    pub tool: Option<String>,
+    pub path: Option<String>,
+    pub command: Option<String>,
+}
+
+/// Words in the dialog title of tools whose name isn't in it
+const TOOL_TITLES: &[(&str, &str)] = &[
+    ("Write", "create"),
+    ("MultiEdit", "edit"),
+    ("NotebookEdit", "notebook"),
+    ("WebFetch", "fetch"),
+    ("WebSearch", "search"),
+];
+
+/// Lowercase without whitespace, so text wrapped across dialog lines compares
+/// equal to the original
+fn squeeze(text: &str) -> String {
+    text.chars()
+        .filter(|c| !c.is_whitespace())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// Whether `hook` is about the tool `dialog` asks for: the dialog names the
+/// tool and shows its command (the start of it) or file, if it has one
+fn describes(hook: &HookInput, dialog: &Dialog) -> bool {
+    let Some(tool) = hook.tool.as_deref() else {
+        return false;
+    };
+    let title = dialog.title.to_lowercase();
+    let shown = squeeze(&format!("{}\n{}", dialog.title, dialog.summary));
+    // MCP tools are shown by their own name, without the mcp__server__ prefix
+    let name = tool.rsplit("__").next().unwrap_or(tool);
+    let names_tool = title.contains(&tool.to_lowercase())
+        || shown.contains(&squeeze(name))
+        || TOOL_TITLES
+            .iter()
+            .any(|(t, word)| *t == tool && title.contains(word));
+    if !names_tool {
+        return false;
+    }
+    if let Some(command) = hook.command.as_deref() {
+        let start: String = squeeze(command.lines().next().unwrap_or_default())
+            .chars()
+            .take(80)
+            .collect();
+        return shown.contains(&start);
+    }
+    if let Some(path) = hook.path.as_deref() {
+        let file = std::path::Path::new(path)
+            .file_name()
+            .map_or(path.into(), |name| name.to_string_lossy());
+        return shown.contains(&squeeze(&file));
+    }
+    true
+}
+
+struct Pending {
+    approval_id: Uuid,
+    /// The hook input describes this dialog, so a policy may answer it
+    from_hook: bool,
+    /// What the dialog asks, for the session's status detail
+    detail: String,
+    /// Answered through session.approve/deny; the dialog may not have closed yet
+    answered: bool,
+}
+
+/// Open permission dialogs by session
+#[derive(Default)]
+pub struct Approvals {
+    pending: Mutex<HashMap<Uuid, Pending>>,
+    /// Input of the session's last PreToolUse hook, until a dialog uses it
+    hook_inputs: Mutex<HashMap<Uuid, HookInput>>,
+}
+
+impl Approvals {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn hook_input(&self, session_id: Uuid, input: HookInput) {
+        self.hook_inputs.lock().unwrap().insert(session_id, input);
+    }
+
+    /// Forget the session's hook input once its tool has run or the turn ended
+    pub fn clear_hook_input(&self, session_id: Uuid) {
+        self.hook_inputs.lock().unwrap().remove(&session_id);
+    }
+
+    /// Follow the session's screen; returns the event to send when a dialog
+    /// opens, or closes without having been answered through IPC
+    pub fn update(&self, session_id: Uuid, bottom: &str) -> Option<Event> {
+        let dialog = parse_dialog(bottom);
+        let mut pending = self.pending.lock().unwrap();
+        match (dialog, pending.contains_key(&session_id)) {
+            (Some(dialog), false) => {
+                let approval_id = Uuid::new_v4();
+                // Input about another tool is stale either way
+                let hook = self
+                    .hook_inputs
+                    .lock()
+                    .unwrap()
+                    .remove(&session_id)
+                    .filter(|hook| describes(hook, &dialog));
+                let from_hook = hook.is_some();
+                let hook = hook.unwrap_or_default();
+                let tool = hook.tool.unwrap_or_else(|| dialog.title.clone());
+                pending.insert(
+                    session_id,
+                    Pending {
+                        approval_id,
+                        from_hook,
+                        detail: permission_detail(
+                            &tool,
+                            hook.command.as_deref().or(hook.path.as_deref()),
+                        ),
+                        answered: false,
+                    },
+                );
+                Some(Event::ApprovalRequested(ApprovalRequestedData {
+                    session_id,
+                    approval_id,
+                    tool,
+                    title: dialog.title,
+                    summary: dialog.summary,
+                    question: dialog.question,
+                    path: hook.path,
+                    command: hook.command,
+                }))
+            }
+            (None, true) => {
+                let closed = pending.remove(&session_id)?;
+                (!closed.answered).then_some(Event::ApprovalResolved(ApprovalResolvedData {
+                    session_id,
+                    approval_id: closed.approval_id,
+                    approved: None,
+                }))
+            }
+            _ => None,
+        }
+    }
+
+    /// Whether the session shows a dialog nobody has answered yet
+    pub fn is_pending(&self, session_id: Uuid) -> bool {
+        self.pending
+            .lock()
+            .unwrap()
+            .get(&session_id)
+            .is_some_and(|pending| !pending.answered)
+    }
+
+    /// What the session's unanswered dialog asks, e.g. "permission for Bash(cargo test)"
+    pub fn detail(&self, session_id: Uuid) -> Option<String> {
+        self.pending
+            .lock()
+            .unwrap()
+            .get(&session_id)
+            .filter(|pending| !pending.answered)
+            .map(|pending| pending.detail.clone())
+    }
+
+    /// Mark the session's open dialog as answered; false if `approval_id`
+    /// isn't the dialog waiting for an answer
+    pub fn answer(&self, session_id: Uuid, approval_id: Uuid) -> bool {
+        match self.pending.lock().unwrap().get_mut(&session_id) {
+            Some(pending) if pending.approval_id == approval_id && !pending.answered => {
+                pending.answered = true;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Whether the tool and input of dialog `approval_id` came from the hook,
+    /// rather than only from the screen
+    pub fn hook_described(&self, session_id: Uuid, approval_id: Uuid) -> bool {
+        self.pending
+            .lock()
+            .unwrap()
+            .get(&session_id)
+            .is_some_and(|pending| pending.approval_id == approval_id && pending.from_hook)
+    }
+
+    /// The session whose unanswered dialog is `approval_id`
+    pub fn session_of(&self, approval_id: Uuid) -> Option<Uuid> {
+        self.pending
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|(_, pending)| pending.approval_id == approval_id && !pending.answered)
+            .map(|(session_id, _)| *session_id)
+    }
+
+    /// Forget the session's dialog, e.g. when its process exits
+    pub fn remove(&self, session_id: Uuid) {
+        self.pending.lock().unwrap().remove(&session_id);
+        self.hook_inputs.lock().unwrap().remove(&session_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DIALOG: &str = "╭──────────────────────────────╮
+│ Bash command                 │
+│                              │
+│   cargo test                 │
+│   Run the tests              │
+│                              │
+│ Do you want to proceed?      │
+│ ❯ 1. Yes                     │
+│   2. No, and tell Claude what to do differently (esc) │
+╰──────────────────────────────╯";
+
+    #[test]
+    fn test_parse_dialog() {
+        let dialog = parse_dialog(&format!("some output\n{}", DIALOG)).unwrap();
+        assert_eq!(dialog.title, "Bash command");
+        assert_eq!(dialog.summary, "cargo test\nRun the tests");
+        assert_eq!(dialog.question, "Do you want to proceed?");
+
+        // A question without the choices is just output
+        assert!(parse_dialog("Do you want to proceed?\n> ").is_none());
+    }
+
+    #[test]
+    fn test_approval_lifecycle() {
+        let id = Uuid::new_v4();
+        let approvals = Approvals::new();
+        approvals.hook_input(
+            id,
+            HookInput {
+                tool: Some("Bash".to_string()),
+                command: Some("cargo test".to_string()),
+                ..Default::default()
+            },
+        );
+
+        let Some(Event::ApprovalRequested(data)) = approvals.update(id, DIALOG) else {
+            panic!("expected session:approval_requested");
+        };
+        assert_eq!(data.tool, "Bash");
+        assert_eq!(data.command.as_deref(), Some("cargo test"));
+        assert!(approvals.hook_described(id, data.approval_id));
+        // Still open: nothing new
+        assert!(approvals.update(id, DIALOG).is_none());
+
+        assert!(!approvals.answer(id, Uuid::new_v4()));
+        assert!(approvals.is_pending(id));
+        assert_eq!(approvals.session_of(data.approval_id), Some(id));
+        assert_eq!(
+            approvals.detail(id).as_deref(),
+            Some("permission for Bash(cargo test)")
+        );
+        assert!(approvals.answer(id, data.approval_id));
+        assert!(!approvals.is_pending(id));
+        assert!(approvals.detail(id).is_none());
+        assert!(approvals.session_of(data.approval_id).is_none());
+        assert!(!approvals.answer(id, data.approval_id));
+        // Answered through IPC, so closing it sends nothing
+        assert!(approvals.update(id, "│ > │").is_none());
+
+        // Answered in the terminal instead
+        let Some(Event::ApprovalRequested(data)) = approvals.update(id, DIALOG) else {
+            panic!("expected session:approval_requested");
+        };
+        match approvals.update(id, "│ > │") {
+            Some(Event::ApprovalResolved(resolved)) => {
+                assert_eq!(resolved.approval_id, data.approval_id);
+                assert_eq!(resolved.approved, None);
+            }
+            other => panic!("expected session:approval_resolved, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_stale_hook_input() {
+        let id = Uuid::new_v4();
+        let approvals = Approvals::new();
+        let bash = |command: &str| HookInput {
+            tool: Some("Bash".to_string()),
+            command: Some(command.to_string()),
+            ..Default::default()
+        };
+        let edit = DIALOG
+            .replace("Bash command", "Edit file   ")
+            .replace("cargo test", "src/main.rs");
+
+        // A PreToolUse event with no dialog (claude didn't ask), then a dialog
+        // for another tool: it's described by the screen only
+        approvals.hook_input(id, bash("cargo test"));
+        let Some(Event::ApprovalRequested(data)) = approvals.update(id, &edit) else {
+            panic!("expected session:approval_requested");
+        };
+        assert_eq!(data.tool, "Edit file");
+        assert!(data.command.is_none());
+        assert!(!approvals.hook_described(id, data.approval_id));
+        approvals.update(id, "│ > │");
+
+        // The same tool with another command
+        approvals.hook_input(id, bash("cargo build"));
+        let Some(Event::ApprovalRequested(data)) = approvals.update(id, DIALOG) else {
+            panic!("expected session:approval_requested");
+        };
+        assert!(data.command.is_none());
+        assert!(!approvals.hook_described(id, data.approval_id));
+        approvals.update(id, "│ > │");
+
+        // Input cleared once its tool ran, and a dialog with no hook event at all
+        approvals.hook_input(id, bash("cargo test"));
+        approvals.clear_hook_input(id);
+        let Some(Event::ApprovalRequested(data)) = approvals.update(id, DIALOG) else {
+            panic!("expected session:approval_requested");
+        };
+        assert_eq!(data.tool, "Bash command");
+        assert!(!approvals.hook_described(id, data.approval_id));
+    }
+
+    #[test]
+    fn test_describes() {
+        let dialog = |title: &str, summary: &str| Dialog {
+            title: title.to_string(),
+            summary: summary.to_string(),
+            question: "Do you want to proceed?".to_string(),
+        };
+        let hook = |tool: &str, command: Option<&str>, path: Option<&str>| HookInput {
+            tool: Some(tool.to_string()),
+            command: command.map(str::to_string),
+            path: path.map(str::to_string),
+        };
+
+        // A long command wrapped over several lines
+        let wrapped = dialog(
+            "Bash command",
+            "cargo test --workspace --all-\ntargets\nRun every test",
+        );
+        assert!(describes(
+            &hook("Bash", Some("cargo test --workspace --all-targets"), None),
+            &wrapped
+        ));
+        assert!(!describes(&hook("Bash", Some("rm -rf /"), None), &wrapped));
+        assert!(!describes(&HookInput::default(), &wrapped));
+
+        let create = dialog("Create file", "src/new.rs\nfn main() {}");
+        assert!(describes(
+            &hook("Write", None, Some("/repo/src/new.rs")),
+            &create
+        ));
+        assert!(!describes(
+            &hook("Write", None, Some("/repo/src/old.rs")),
+            &create
+        ));
+        assert!(!describes(
+            &hook("Edit", None, Some("/repo/src/new.rs")),
+            &create
+        ));
+        assert!(describes(
+            &hook("mcp__github__create_issue", None, None),
+            &dialog("Tool use", "github - create_issue(title: \"x\") (MCP)")
+        ));
+    }
+}
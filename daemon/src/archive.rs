@@ -0,0 +1,122 @@
+// Session archive - a compact record of every deleted session, kept as JSON
+// lines next to the state files so past work stays searchable via history.list
+
+use anyhow::Result;
+use chrono::Utc;
+use shared::{ArchivedSession, HistoryEntry, HistoryKind, Session, SessionUsage};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use tracing::warn;
+
+use crate::config::get_state_dir;
+use crate::history;
+use crate::transcript;
+
+fn archive_path() -> Result<PathBuf> {
+    Ok(get_state_dir()?.join("archive.jsonl"))
+}
+
+/// Prompt and turn totals from a session timeline
+fn timeline_usage(entries: &[HistoryEntry]) -> SessionUsage {
+    let mut usage = SessionUsage::default();
+    for entry in entries {
+        match &entry.kind {
+            HistoryKind::PromptSent { .. } => usage.prompts += 1,
+            HistoryKind::Turn(metrics) => {
+                usage.turns += 1;
+                usage.busy_ms += metrics.duration_ms;
+            }
+            _ => {}
+        }
+    }
+    usage
+}
+
+fn build(session: &Session) -> ArchivedSession {
+    let mut usage = timeline_usage(&history::load_blocking(session.id).unwrap_or_default());
+    let transcript_path = session
+        .claude_session_id
+        .as_deref()
+        .and_then(transcript::find_transcript);
+    if let Some(content) = transcript_path
+        .as_ref()
+        .and_then(|path| fs::read_to_string(path).ok())
+    {
+        (usage.input_tokens, usage.output_tokens) = transcript::token_usage(&content);
+    }
+
+    ArchivedSession {
+        id: session.id,
+        name: session.name.clone(),
+        working_dir: session.working_dir.clone(),
+        claude_session_id: session.claude_session_id.clone(),
+        transcript_path: transcript_path.map(|p| p.to_string_lossy().to_string()),
+        created_at: session.created_at,
+        last_activity: session.last_activity,
+        deleted_at: Utc::now(),
+        usage,
+    }
+}
+
+/// Archive a session that is being deleted
+/// The archive is best-effort: failures are logged, never propagated
+pub fn record(session: &Session) {
+    let result = (|| -> Result<()> {
+        let line = serde_json::to_string(&build(session))? + "\n";
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(archive_path()?)?;
+        file.write_all(line.as_bytes())?;
+        Ok(())
+    })();
+    if let Err(e) = result {
+        warn!("Failed to archive session {}: {}", session.id, e);
+    }
+}
+
+/// Archived sessions, most recently deleted first
+pub fn list(working_dir: Option<&Path>, limit: Option<usize>) -> Result<Vec<ArchivedSession>> {
+    let path = archive_path()?;
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let content = fs::read_to_string(path)?;
+    Ok(content
+        .lines()
+        .rev()
+        .filter_map(|line| serde_json::from_str::<ArchivedSession>(line).ok())
+        .filter(|session| working_dir.is_none_or(|dir| session.working_dir.starts_with(dir)))
+        .take(limit.unwrap_or(usize::MAX))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shared::TurnMetrics;
+
+    #[test]
+    fn test_timeline_usage() {
+        let entries = vec![
+            HistoryEntry::new(HistoryKind::PromptSent { text: None }),
+            HistoryEntry::new(HistoryKind::Turn(TurnMetrics {
+                first_output_ms: Some(100),
+                duration_ms: 1500,
+            })),
+            HistoryEntry::new(HistoryKind::PromptSent {
+                text: Some("again".to_string()),
+            }),
+            HistoryEntry::new(HistoryKind::Turn(TurnMetrics {
+                first_output_ms: None,
+                duration_ms: 500,
+            })),
+            HistoryEntry::new(HistoryKind::ApprovalRequested),
+        ];
+        let usage = timeline_usage(&entries);
+        assert_eq!(usage.prompts, 2);
+        assert_eq!(usage.turns, 2);
+        assert_eq!(usage.busy_ms, 2000);
+    }
+}
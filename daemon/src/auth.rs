@@ -0,0 +1,422 @@
+// Client authentication - the daemon socket is created owner-only (0600) and
+// connections from processes of other users are dropped by peer UID. With
+// daemon.require_token, a connection must also send daemon.auth with the token
+// from daemon.token (new on every start, readable only by the owner) before it
+// can call anything or receive events. The HTTP API has a token of its own,
+// kept across restarts so scripts can hold on to it.
+//
+// Both tokens are admin tokens. The [auth] config can add more, each with a
+// role (viewer, operator or admin) that limits which methods it may call, so
+// a dashboard or a phone can be given a token that can't stop the daemon or
+// type into sessions. process_request checks the role of every call.
+//
+// Connections through the tunnel port (tunnel.rs) come from other machines, so
+// they always have to authenticate: with the token in tunnel.token, kept
+// across restarts like the HTTP one, or one from auth.tokens.
+
+use anyhow::Result;
+use interprocess::local_socket::tokio::Stream;
+use rand::RngCore;
+use shared::Role;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use tracing::info;
+
+use crate::config::TokenConfig;
+
+/// Methods that only read, so any role may call them
+const VIEWER_METHODS: &[&str] = &[
+    "daemon.ping",
+    "daemon.hello",
+    "daemon.auth",
+    "daemon.diagnostics",
+    "daemon.stats",
+    "daemon.metrics",
+    "daemon.claude_info",
+    "daemon.clients",
+    "client.identify",
+    "events.subscribe",
+    "events.since",
+    "ui.theme",
+    "notifications.get",
+    "session.list",
+    "state.sync",
+    "session.view",
+    "session.tasks.list",
+    "session.transcript",
+    "session.turn_stats",
+    "session.stats",
+    "session.history",
+    "session.input_history",
+    "session.tail_log",
+    "session.get_screen",
+    "session.search_output",
+    "session.replay",
+    "session.replay_cancel",
+    "session.recording.list",
+    "queue.list",
+    "history.list",
+    "search.query",
+    "schedule.list",
+    "snippet.list",
+    "claude.capabilities",
+    "mcp.list",
+    "tree.snapshot",
+    "summary.get",
+    "group.list",
+];
+
+/// Methods that control the daemon, its config, approval policies or other
+/// clients' traffic, or write files wherever the caller says, for admins only
+const ADMIN_METHODS: &[&str] = &[
+    "daemon.shutdown",
+    "daemon.restart",
+    "daemon.logs.tail",
+    "daemon.network_audit",
+    "daemon.orphans",
+    "daemon.bootstrap",
+    "daemon.keep_awake",
+    "debug.tap",
+    "policy.set",
+    "mcp.add",
+    "mcp.remove",
+    "mcp.set_enabled",
+    "state.export",
+    "state.import",
+    "session.export_output",
+    "session.recording.export",
+];
+
+/// The least role that may call `method`; anything not listed (including
+/// methods added later) needs an operator
+pub fn required_role(method: &str) -> Role {
+    if VIEWER_METHODS.contains(&method) {
+        Role::Viewer
+    } else if ADMIN_METHODS.contains(&method) {
+        Role::Admin
+    } else {
+        Role::Operator
+    }
+}
+
+pub fn allows(role: Role, method: &str) -> bool {
+    role >= required_role(method)
+}
+
+/// The auth.tokens entry `token` matches, compared in constant time
+pub fn configured<'a>(tokens: &'a [TokenConfig], token: &str) -> Option<&'a TokenConfig> {
+    tokens
+        .iter()
+        .find(|configured| tokens_match(&configured.token, token))
+}
+
+/// Shared-token check behind daemon.auth
+pub struct TokenAuth {
+    /// None when daemon.require_token is off
+    token: Option<String>,
+    /// Token for tunnel connections, None when tunnel.enabled is off
+    tunnel_token: Option<String>,
+}
+
+impl TokenAuth {
+    /// Write a fresh token file if `required`, otherwise remove a stale one;
+    /// with `tunnel`, load (or create) the tunnel token as well
+    pub fn init(required: bool, tunnel: bool) -> Result<Self> {
+        let tunnel_token = if tunnel { Some(tunnel_token()?) } else { None };
+        let path = shared::get_token_path()?;
+        if !required {
+            match fs::remove_file(&path) {
+                Ok(()) => info!("Removed stale token file {:?}", path),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => return Err(e.into()),
+            }
+            return Ok(Self {
+                token: None,
+                tunnel_token,
+            });
+        }
+
+        let token = new_token();
+        write_private(&path, &token)?;
+        info!("Clients must authenticate with the token in {:?}", path);
+        Ok(Self {
+            token: Some(token),
+            tunnel_token,
+        })
+    }
+
+    /// Auth with the given tokens, without touching the token files
+    #[cfg(test)]
+    pub fn with_tokens(token: Option<&str>, tunnel_token: Option<&str>) -> Self {
+        Self {
+            token: token.map(str::to_string),
+            tunnel_token: tunnel_token.map(str::to_string),
+        }
+    }
+
+    /// Whether connections have to send daemon.auth first
+    pub fn required(&self) -> bool {
+        self.token.is_some()
+    }
+
+    /// The role a token sent with daemon.auth gets: admin for the one in
+    /// daemon.token, its own for one from auth.tokens, None if it's wrong.
+    /// Without require_token any other token is let in as admin.
+    pub fn role(&self, token: &str, tokens: &[TokenConfig]) -> Option<Role> {
+        let daemon_token = self
+            .token
+            .as_deref()
+            .is_some_and(|expected| tokens_match(expected, token));
+        if daemon_token {
+            return Some(Role::Admin);
+        }
+        if let Some(configured) = configured(tokens, token) {
+            info!(
+                "Token {:?} authenticated as {:?}",
+                configured.name, configured.role
+            );
+            return Some(configured.role);
+        }
+        (!self.required()).then_some(Role::Admin)
+    }
+
+    /// The role a token sent with daemon.auth over the tunnel gets: admin for
+    /// the one in tunnel.token, its own for one from auth.tokens
+    pub fn tunnel_role(&self, token: &str, tokens: &[TokenConfig]) -> Option<Role> {
+        let tunnel_token = self
+            .tunnel_token
+            .as_deref()
+            .is_some_and(|expected| tokens_match(expected, token));
+        if tunnel_token {
+            return Some(Role::Admin);
+        }
+        configured(tokens, token).map(|configured| {
+            info!(
+                "Token {:?} authenticated as {:?} over the tunnel",
+                configured.name, configured.role
+            );
+            configured.role
+        })
+    }
+}
+
+fn new_token() -> String {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Compare tokens in constant time
+pub fn tokens_match(expected: &str, token: &str) -> bool {
+    expected.len() == token.len()
+        && expected
+            .bytes()
+            .zip(token.bytes())
+            .fold(0u8, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+/// The bearer token for the HTTP API, created on first use
+pub fn http_token() -> Result<String> {
+    kept_token("http.token")
+}
+
+/// The token for connections through the tunnel port, created on first use
+pub fn tunnel_token() -> Result<String> {
+    kept_token("tunnel.token")
+}
+
+/// The key approval webhook links are signed with, created on first use
+pub fn approval_link_secret() -> Result<String> {
+    kept_token("approval.secret")
+}
+
+/// A token kept in `file_name` in the data dir across restarts
+fn kept_token(file_name: &str) -> Result<String> {
+    let path = shared::get_data_dir()?.join(file_name);
+    match fs::read_to_string(&path) {
+        Ok(token) if !token.trim().is_empty() => return Ok(token.trim().to_string()),
+        Ok(_) => {}
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => return Err(e.into()),
+    }
+    let token = new_token();
+    write_private(&path, &token)?;
+    info!("Created token in {:?}", path);
+    Ok(token)
+}
+
+/// Write `contents` to a new file that only the owner can read
+pub fn write_private(path: &Path, contents: &str) -> Result<()> {
+    // Replaced rather than truncated, so an existing file's mode isn't kept
+    let _ = fs::remove_file(path);
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    options.open(path)?.write_all(contents.as_bytes())?;
+    Ok(())
+}
+
+/// Refuse connections from processes running as another user
+pub fn check_peer(stream: &Stream) -> Result<()> {
+    #[cfg(unix)]
+    {
+        let peer = peer_uid(stream)?;
+        // SAFETY: getuid has no preconditions and cannot fail
+        let own = unsafe { libc::getuid() };
+        if peer != own {
+            anyhow::bail!(
+                "Rejected connection from uid {} (daemon runs as {})",
+                peer,
+                own
+            );
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = stream;
+    Ok(())
+}
+
+/// UID of the process on the other end of a connection
+#[cfg(unix)]
+fn peer_uid(stream: &Stream) -> std::io::Result<u32> {
+    use std::os::fd::{AsFd, AsRawFd};
+
+    let Stream::UdSocket(socket) = stream;
+    let fd = socket.as_fd().as_raw_fd();
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    {
+        let mut cred = libc::ucred {
+            pid: 0,
+            uid: 0,
+            gid: 0,
+        };
+        let mut len = std::mem::size_of::<libc::ucred>() as libc::socklen_t;
+        // SAFETY: cred and len are valid for writes and sized for SO_PEERCRED
+        let rc = unsafe {
+            libc::getsockopt(
+                fd,
+                libc::SOL_SOCKET,
+                libc::SO_PEERCRED,
+                &mut cred as *mut libc::ucred as *mut libc::c_void,
+                &mut len,
+            )
+        };
+        if rc != 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        Ok(cred.uid)
+    }
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    {
+        let mut uid = 0;
+        let mut gid = 0;
+        // SAFETY: uid and gid are valid for writes
+        if unsafe { libc::getpeereid(fd, &mut uid, &mut gid) } != 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        Ok(uid)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_verify_token() {
+        let tokens = [TokenConfig {
+            name: "dashboard".to_string(),
+            token: "viewer-token-0123".to_string(),
+            role: Role::Viewer,
+        }];
+        let auth = TokenAuth {
+            token: Some("0123abcd".to_string()),
+            tunnel_token: Some("tunnel-token-4567".to_string()),
+        };
+        assert!(auth.required());
+        assert_eq!(auth.role("0123abcd", &tokens), Some(Role::Admin));
+        assert_eq!(auth.role("viewer-token-0123", &tokens), Some(Role::Viewer));
+        assert_eq!(auth.role("0123abce", &tokens), None);
+        assert_eq!(auth.role("0123abc", &tokens), None);
+        assert_eq!(auth.role("", &tokens), None);
+
+        let open = TokenAuth {
+            token: None,
+            tunnel_token: Some("tunnel-token-4567".to_string()),
+        };
+        assert!(!open.required());
+        assert_eq!(open.role("", &tokens), Some(Role::Admin));
+        assert_eq!(open.role("viewer-token-0123", &tokens), Some(Role::Viewer));
+
+        // The tunnel never lets a wrong token in, and has a token of its own
+        assert_eq!(open.tunnel_role("", &tokens), None);
+        assert_eq!(auth.tunnel_role("0123abcd", &tokens), None);
+        assert_eq!(
+            open.tunnel_role("tunnel-token-4567", &tokens),
+            Some(Role::Admin)
+        );
+        assert_eq!(
+            open.tunnel_role("viewer-token-0123", &tokens),
+            Some(Role::Viewer)
+        );
+    }
+
+    #[test]
+    fn test_roles() {
+        assert!(allows(Role::Viewer, "session.list"));
+        assert!(!allows(Role::Viewer, "session.input"));
+        assert!(allows(Role::Operator, "session.input"));
+        assert!(!allows(Role::Operator, "daemon.shutdown"));
+        assert!(!allows(Role::Operator, "debug.tap"));
+        assert!(allows(Role::Admin, "daemon.shutdown"));
+        // Unknown methods need an operator
+        assert_eq!(required_role("session.future_thing"), Role::Operator);
+        // Clearing unread output changes every client's badges, so viewers can't
+        assert!(!allows(Role::Viewer, "session.mark_viewed"));
+        assert!(allows(Role::Operator, "session.mark_viewed"));
+        assert!(allows(Role::Viewer, "session.view"));
+        // Exports write to any path the daemon can
+        for method in ["session.export_output", "session.recording.export"] {
+            assert!(!allows(Role::Operator, method));
+            assert!(allows(Role::Admin, method));
+        }
+
+        // Every listed method exists, so a rename can't quietly open it up
+        for method in VIEWER_METHODS.iter().chain(ADMIN_METHODS) {
+            assert!(
+                shared::METHODS.contains(method),
+                "{} isn't a method",
+                method
+            );
+        }
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_peer_uid() {
+        use interprocess::local_socket::{
+            tokio::prelude::*, GenericFilePath, ListenerOptions, ToFsName,
+        };
+
+        let path = std::env::temp_dir().join(format!("cm-peer-{}.sock", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let name = path.clone().to_fs_name::<GenericFilePath>().unwrap();
+        let listener = ListenerOptions::new().name(name).create_tokio().unwrap();
+
+        let name = path.clone().to_fs_name::<GenericFilePath>().unwrap();
+        let (client, server) = tokio::join!(Stream::connect(name), listener.accept());
+        let (_client, server) = (client.unwrap(), server.unwrap());
+
+        // SAFETY: getuid has no preconditions and cannot fail
+        assert_eq!(peer_uid(&server).unwrap(), unsafe { libc::getuid() });
+        assert!(check_peer(&server).is_ok());
+        let _ = fs::remove_file(&path);
+    }
+}
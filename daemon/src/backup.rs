@@ -0,0 +1,240 @@
+// State export/import - state.export writes the sessions and groups (and, if asked,
+// their output logs) to one JSON file; state.import merges such a file into this
+// daemon's state. For backups and for moving a setup to another machine.
+
+use anyhow::{Context, Result};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use shared::{Event, Group, Session, SessionStatus, StateExportResult, StateImportResult};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use tokio::sync::broadcast;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::output_log;
+use crate::state::{save_state, AppState, SharedState};
+
+/// Bumped when the file layout changes incompatibly
+const BUNDLE_VERSION: u32 = 1;
+
+/// The file written by state.export
+#[derive(Debug, Serialize, Deserialize)]
+struct Bundle {
+    version: u32,
+    daemon_version: String,
+    exported_at: DateTime<Utc>,
+    sessions: Vec<Session>,
+    groups: Vec<Group>,
+    /// Raw output log per session, base64 encoded (only with include_logs)
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    logs: HashMap<Uuid, String>,
+}
+
+/// Write every session and group to `path`
+pub async fn export(
+    state: &SharedState,
+    path: &Path,
+    include_logs: bool,
+) -> Result<StateExportResult> {
+    let (sessions, groups): (Vec<Session>, Vec<Group>) = {
+        let s = state.read().await;
+        (
+            s.sessions.values().cloned().collect(),
+            s.groups.values().cloned().collect(),
+        )
+    };
+
+    let mut logs = HashMap::new();
+    if include_logs {
+        for session in &sessions {
+            match output_log::read_all(session.id) {
+                Ok(raw) if !raw.is_empty() => {
+                    logs.insert(session.id, BASE64.encode(raw));
+                }
+                Ok(_) => {}
+                Err(e) => warn!("Skipping output log of session {}: {}", session.id, e),
+            }
+        }
+    }
+
+    let result = StateExportResult {
+        path: path.to_string_lossy().into_owned(),
+        sessions: sessions.len(),
+        groups: groups.len(),
+        logs: logs.len(),
+        bytes: 0,
+    };
+    let bundle = Bundle {
+        version: BUNDLE_VERSION,
+        daemon_version: env!("CARGO_PKG_VERSION").to_string(),
+        exported_at: Utc::now(),
+        sessions,
+        groups,
+        logs,
+    };
+    let json = serde_json::to_string_pretty(&bundle)?;
+    fs::write(path, &json)?;
+    info!(
+        "Exported {} sessions and {} groups to {:?}",
+        result.sessions, result.groups, path
+    );
+
+    Ok(StateExportResult {
+        bytes: json.len(),
+        ..result
+    })
+}
+
+/// Add the sessions and groups in an export at `path`, skipping ids that
+/// already exist, and restore their output logs where none exist yet
+pub async fn import(
+    state: &SharedState,
+    event_tx: &broadcast::Sender<Event>,
+    path: &Path,
+) -> Result<StateImportResult> {
+    let content = fs::read_to_string(path)?;
+    let mut bundle: Bundle =
+        serde_json::from_str(&content).context("Not a Claude Master state export")?;
+    if bundle.version > BUNDLE_VERSION {
+        anyhow::bail!(
+            "Export format {} is newer than this daemon supports ({})",
+            bundle.version,
+            BUNDLE_VERSION
+        );
+    }
+
+    let logs = std::mem::take(&mut bundle.logs);
+    let (groups, sessions, skipped) = merge(&mut *state.write().await, bundle);
+    save_state(state).await?;
+
+    let mut restored_logs = 0;
+    for session in &sessions {
+        let Some(data) = logs.get(&session.id) else {
+            continue;
+        };
+        let log = output_log::log_path(session.id)?;
+        if log.exists() {
+            continue;
+        }
+        match BASE64.decode(data) {
+            Ok(raw) => {
+                fs::write(&log, raw)?;
+                restored_logs += 1;
+            }
+            Err(e) => warn!("Skipping output log of session {}: {}", session.id, e),
+        }
+    }
+
+    info!(
+        "Imported {} sessions and {} groups from {:?} ({} already present)",
+        sessions.len(),
+        groups.len(),
+        path,
+        skipped
+    );
+    let result = StateImportResult {
+        sessions: sessions.len(),
+        groups: groups.len(),
+        logs: restored_logs,
+        skipped,
+    };
+    for group in groups {
+        let _ = event_tx.send(Event::GroupCreated(group));
+    }
+    for session in sessions {
+        let _ = event_tx.send(Event::SessionCreated(session));
+    }
+    Ok(result)
+}
+
+/// Add the bundle's new groups and sessions to `s`, returning them and how many
+/// were skipped because their id is taken
+fn merge(s: &mut AppState, bundle: Bundle) -> (Vec<Group>, Vec<Session>, usize) {
+    let mut skipped = 0;
+
+    let mut groups = Vec::new();
+    for group in bundle.groups {
+        if s.groups.contains_key(&group.id) {
+            skipped += 1;
+            continue;
+        }
+        s.groups.insert(group.id, group.clone());
+        groups.push(group);
+    }
+    // Parents left out of the export put the group at the root
+    for group in &mut groups {
+        if group
+            .parent_id
+            .is_some_and(|id| !s.groups.contains_key(&id))
+        {
+            group.parent_id = None;
+            s.groups.insert(group.id, group.clone());
+        }
+    }
+
+    let mut sessions = Vec::new();
+    for mut session in bundle.sessions {
+        if s.sessions.contains_key(&session.id) {
+            skipped += 1;
+            continue;
+        }
+        // Processes stay with the machine (or run) that exported them
+        session.status = SessionStatus::Stopped;
+        session.pid = None;
+        session.pid_start_time = None;
+        session.detached = false;
+        if session
+            .group_id
+            .is_some_and(|id| !s.groups.contains_key(&id))
+        {
+            session.group_id = None;
+        }
+        s.sessions.insert(session.id, session.clone());
+        sessions.push(session);
+    }
+
+    (groups, sessions, skipped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_merge() {
+        let existing = Group::new("existing".to_string(), None);
+        let mut s = AppState::default();
+        s.groups.insert(existing.id, existing.clone());
+
+        let child = Group::new("child".to_string(), Some(Uuid::new_v4()));
+        let mut running = Session::new("api".to_string(), PathBuf::from("/"), Some(child.id));
+        running.status = SessionStatus::Running;
+        running.pid = Some(42);
+        let stray = Session::new("web".to_string(), PathBuf::from("/"), Some(Uuid::new_v4()));
+        let bundle = Bundle {
+            version: BUNDLE_VERSION,
+            daemon_version: String::new(),
+            exported_at: Utc::now(),
+            sessions: vec![running.clone(), stray.clone()],
+            groups: vec![existing.clone(), child.clone()],
+            logs: HashMap::new(),
+        };
+
+        let (groups, sessions, skipped) = merge(&mut s, bundle);
+        assert_eq!(skipped, 1);
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].parent_id, None);
+        assert_eq!(s.groups[&child.id].parent_id, None);
+
+        assert_eq!(sessions.len(), 2);
+        let api = &s.sessions[&running.id];
+        assert_eq!(api.status, SessionStatus::Stopped);
+        assert_eq!(api.pid, None);
+        assert_eq!(api.group_id, Some(child.id));
+        assert_eq!(s.sessions[&stray.id].group_id, None);
+    }
+}
@@ -0,0 +1,197 @@
+// First-run bootstrap - checks for claude, optionally installs it, installs hooks
+// and creates a starter group, reporting each step as daemon:bootstrap_progress
+// Keeps environment logic in the daemon so the GUI wizard only renders progress
+
+use anyhow::Result;
+use shared::{
+    BootstrapProgressData, BootstrapResult, BootstrapStep, BootstrapStepState, Event, Group,
+};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tokio::process::Command;
+use tokio::sync::broadcast;
+use tracing::{info, warn};
+
+use crate::claude_resolver::ClaudeResolver;
+use crate::config::SharedConfig;
+use crate::egress::{self, Egress};
+use crate::hook_manager::HookManager;
+use crate::session_manager::SessionManager;
+use crate::state::SharedState;
+
+/// npm command that installs Claude Code globally
+pub const INSTALL_COMMAND: &str = "npm install -g @anthropic-ai/claude-code";
+
+const DEFAULT_STARTER_GROUP: &str = "Sessions";
+
+/// Set while a bootstrap is in progress so concurrent runs are rejected
+static RUNNING: AtomicBool = AtomicBool::new(false);
+
+/// Claim the bootstrap slot, returning false if a bootstrap is already running
+pub fn try_start() -> bool {
+    RUNNING
+        .compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst)
+        .is_ok()
+}
+
+fn progress(
+    event_tx: &broadcast::Sender<Event>,
+    step: BootstrapStep,
+    state: BootstrapStepState,
+    message: Option<String>,
+) {
+    let event = Event::BootstrapProgress(BootstrapProgressData {
+        step,
+        state,
+        message,
+    });
+    let _ = event_tx.send(event);
+}
+
+/// Run `npm install -g` for Claude Code using the same PATH sessions get
+async fn install_claude(config: &SharedConfig) -> Result<()> {
+    egress::check(&*config.read().await, Egress::ClaudeInstall)?;
+    let env = ClaudeResolver::new().build_env();
+    let mut parts = INSTALL_COMMAND.split_whitespace();
+    let program = parts.next().unwrap_or("npm");
+    let mut cmd = Command::new(program);
+    cmd.args(parts);
+    if let Some(path) = env.get("PATH") {
+        cmd.env("PATH", path);
+    }
+
+    let output = cmd.output().await?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let tail: Vec<&str> = stderr.trim().lines().rev().take(5).collect();
+        anyhow::bail!(
+            "{} failed: {}",
+            INSTALL_COMMAND,
+            tail.into_iter().rev().collect::<Vec<_>>().join("\n")
+        );
+    }
+    Ok(())
+}
+
+/// Run the bootstrap flow (the caller must have claimed the slot with `try_start`)
+/// Emits daemon:bootstrap_complete with the BootstrapResult when finished.
+pub async fn run(
+    state: SharedState,
+    event_tx: broadcast::Sender<Event>,
+    hook_manager: Arc<HookManager>,
+    config: SharedConfig,
+    install: bool,
+    starter_group: Option<String>,
+) {
+    use BootstrapStep::*;
+    use BootstrapStepState::*;
+
+    info!("Bootstrap started (install_claude: {})", install);
+
+    // Claude installation
+    progress(&event_tx, CheckClaude, Running, None);
+    let mut claude_path = ClaudeResolver::new().claude_path().cloned();
+    match &claude_path {
+        Some(path) => progress(
+            &event_tx,
+            CheckClaude,
+            Done,
+            Some(path.display().to_string()),
+        ),
+        None => progress(
+            &event_tx,
+            CheckClaude,
+            Failed,
+            Some(format!(
+                "claude not found - install with `{}`",
+                INSTALL_COMMAND
+            )),
+        ),
+    }
+
+    if claude_path.is_none() && install {
+        progress(
+            &event_tx,
+            InstallClaude,
+            Running,
+            Some(INSTALL_COMMAND.to_string()),
+        );
+        match install_claude(&config).await {
+            Ok(()) => {
+                claude_path = ClaudeResolver::new().claude_path().cloned();
+                match &claude_path {
+                    Some(path) => progress(
+                        &event_tx,
+                        InstallClaude,
+                        Done,
+                        Some(path.display().to_string()),
+                    ),
+                    None => progress(
+                        &event_tx,
+                        InstallClaude,
+                        Failed,
+                        Some("Install finished but claude is still not on PATH".to_string()),
+                    ),
+                }
+            }
+            Err(e) => {
+                warn!("Bootstrap: claude install failed: {}", e);
+                progress(&event_tx, InstallClaude, Failed, Some(e.to_string()));
+            }
+        }
+    } else {
+        progress(&event_tx, InstallClaude, Skipped, None);
+    }
+
+    // Hook script
+    progress(&event_tx, InstallHooks, Running, None);
+    let hooks_installed = match hook_manager.ensure_hook_script() {
+        Ok(path) => {
+            progress(
+                &event_tx,
+                InstallHooks,
+                Done,
+                Some(path.display().to_string()),
+            );
+            true
+        }
+        Err(e) => {
+            warn!("Bootstrap: hook install failed: {}", e);
+            progress(&event_tx, InstallHooks, Failed, Some(e.to_string()));
+            false
+        }
+    };
+
+    // Starter group, only on a fresh install
+    let has_groups = !state.read().await.groups.is_empty();
+    let group: Option<Group> = if has_groups {
+        progress(&event_tx, CreateGroup, Skipped, None);
+        None
+    } else {
+        progress(&event_tx, CreateGroup, Running, None);
+        let name = starter_group.unwrap_or_else(|| DEFAULT_STARTER_GROUP.to_string());
+        match SessionManager::create_group(&state, &event_tx, name, None).await {
+            Ok(group) => {
+                progress(&event_tx, CreateGroup, Done, Some(group.name.clone()));
+                Some(group)
+            }
+            Err(e) => {
+                warn!("Bootstrap: failed to create starter group: {}", e);
+                progress(&event_tx, CreateGroup, Failed, Some(e.to_string()));
+                None
+            }
+        }
+    };
+
+    let result = BootstrapResult {
+        claude_path: claude_path.map(|p| p.to_string_lossy().to_string()),
+        install_command: INSTALL_COMMAND.to_string(),
+        hooks_installed,
+        group,
+    };
+    let event = Event::BootstrapComplete(result);
+    let _ = event_tx.send(event);
+
+    RUNNING.store(false, Ordering::SeqCst);
+    info!("Bootstrap finished");
+}
@@ -3,6 +3,13 @@ use shared::SessionStatus;
 use std::sync::LazyLock;
 use tracing::debug;
 
+/// Stops Claude's current turn
+pub const INTERRUPT_KEYS: &[u8] = b"\x1b";
+/// Ctrl+C, for when Escape doesn't get through
+pub const CANCEL_KEYS: &[u8] = b"\x03";
+/// Longest error line kept as a session's status detail
+const MAX_DETAIL_CHARS: usize = 120;
+
 /// Patterns for detecting Claude Code's current state
 pub struct StatusDetector {
     running_patterns: Vec<(Regex, &'static str)>,
@@ -10,6 +17,10 @@ pub struct StatusDetector {
     /// Patterns that indicate Claude is in a transitional state (running hooks)
     /// These override running detection because hooks run AFTER Claude finishes work
     hook_patterns: Vec<Regex>,
+    /// What Claude's input box and permission dialogs look like on screen
+    waiting_patterns: Vec<(Regex, &'static str)>,
+    /// Claude's status line while it works
+    screen_busy_pattern: Regex,
     session_id_pattern: Regex,
     ansi_strip: Regex,
 }
@@ -59,6 +70,19 @@ impl StatusDetector {
                 Regex::new(r"(?i)stop\s+hook").unwrap(),          // "stop hook"
                 Regex::new(r"(?i)pre-?commit").unwrap(),          // "pre-commit hook"
             ],
+            // Only looked for in the bottom lines of the rendered screen
+            waiting_patterns: vec![
+                // The input box: "│ > " inside its border, or a bare "> " line
+                (Regex::new(r"(?m)^\s*│?\s*>(\s|$)").unwrap(), "input_box"),
+                // Permission dialogs
+                (Regex::new(r"(?i)do you want to").unwrap(), "do_you_want"),
+                (
+                    Regex::new(r"(?m)^\s*[│❯>]?\s*[❯>]?\s*\d+\.\s+(Yes|No)\b").unwrap(),
+                    "numbered_choice",
+                ),
+                (Regex::new(r"(?i)\[y/n\]").unwrap(), "yes_no"),
+            ],
+            screen_busy_pattern: Regex::new(r"(?i)esc to (interrupt|stop)").unwrap(),
             // Match session ID from Claude output (appears at startup or in status)
             session_id_pattern: Regex::new(r"session[:\s]+([a-f0-9-]{36})").unwrap(),
             // Pattern to strip ANSI escape codes for cleaner matching
@@ -147,6 +171,44 @@ impl StatusDetector {
         Some(SessionStatus::Waiting)
     }
 
+    /// Detect status from the bottom lines of the rendered screen
+    ///
+    /// Unlike a chunk of output, the screen shows where Claude actually is: its
+    /// status line while working, the input box or a permission dialog when it
+    /// needs the user. Output that merely scrolls past (a diff full of question
+    /// marks, say) leaves neither, so this returns None rather than Waiting.
+    pub fn detect_screen_status(&self, bottom: &str) -> Option<SessionStatus> {
+        let in_hook_phase = self.hook_patterns.iter().any(|p| p.is_match(bottom));
+        if !in_hook_phase && self.screen_busy_pattern.is_match(bottom) {
+            debug!("Screen status: Running (status line)");
+            return Some(SessionStatus::Running);
+        }
+        for (pattern, name) in &self.waiting_patterns {
+            if pattern.is_match(bottom) {
+                debug!("Screen status: Waiting (pattern: {})", name);
+                return Some(SessionStatus::Waiting);
+            }
+        }
+        None
+    }
+
+    /// The line of a chunk that detect_status took for an error, e.g.
+    /// "APIError: rate limited", shortened to MAX_DETAIL_CHARS
+    pub fn error_detail(&self, text: &str) -> Option<String> {
+        let clean_text = self.strip_ansi(text);
+        let line = clean_text.lines().map(str::trim).find(|line| {
+            self.error_patterns
+                .iter()
+                .any(|(pattern, _)| pattern.is_match(line))
+        })?;
+        if line.chars().count() <= MAX_DETAIL_CHARS {
+            return Some(line.to_string());
+        }
+        let mut detail: String = line.chars().take(MAX_DETAIL_CHARS - 1).collect();
+        detail.push('…');
+        Some(detail)
+    }
+
     /// Extract Claude session ID from terminal output
     pub fn extract_session_id(&self, text: &str) -> Option<String> {
         self.session_id_pattern
@@ -167,6 +229,20 @@ pub fn detect_status(text: &str) -> Option<SessionStatus> {
     DETECTOR.detect_status(text)
 }
 
+/// Convenience function to detect status from the bottom of the screen
+pub fn detect_screen_status(bottom: &str) -> Option<SessionStatus> {
+    DETECTOR.detect_screen_status(bottom)
+}
+
+/// Convenience function to strip ANSI escape codes
+pub fn error_detail(text: &str) -> Option<String> {
+    detector().error_detail(text)
+}
+
+pub fn strip_ansi(text: &str) -> String {
+    DETECTOR.strip_ansi(text)
+}
+
 /// Convenience function to extract session ID
 pub fn extract_session_id(text: &str) -> Option<String> {
     DETECTOR.extract_session_id(text)
@@ -244,6 +320,19 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_error_detail() {
+        assert_eq!(
+            error_detail("\x1b[31m  APIError: rate limited\x1b[0m\r\n> ").as_deref(),
+            Some("APIError: rate limited")
+        );
+        let long = format!("Error: {}", "x".repeat(200));
+        let detail = error_detail(&long).unwrap();
+        assert_eq!(detail.chars().count(), MAX_DETAIL_CHARS);
+        assert!(detail.ends_with('…'));
+        assert!(error_detail("all good").is_none());
+    }
+
     #[test]
     fn test_hook_phase_not_running() {
         // When Claude is running hooks, it still shows "esc to interrupt" but
@@ -277,6 +366,39 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_detect_screen_status() {
+        // Input box, with or without its border
+        let input_box = "╭──────────────╮\n│ >            │\n╰──────────────╯\n  ? for shortcuts";
+        assert_eq!(
+            detect_screen_status(input_box),
+            Some(SessionStatus::Waiting)
+        );
+        assert_eq!(
+            detect_screen_status("────\n> \n────"),
+            Some(SessionStatus::Waiting)
+        );
+        // Permission dialog
+        assert_eq!(
+            detect_screen_status("Do you want to make this edit?\n❯ 1. Yes\n  2. No"),
+            Some(SessionStatus::Waiting)
+        );
+        // The status line wins over the input box shown below it
+        assert_eq!(
+            detect_screen_status("✻ Thinking… (esc to interrupt)\n│ >            │"),
+            Some(SessionStatus::Running)
+        );
+        assert_eq!(
+            detect_screen_status("✻ Ruminating… (esc to interrupt · running stop hook)\n│ > │"),
+            Some(SessionStatus::Waiting)
+        );
+        // A diff scrolling past says nothing about the status
+        assert_eq!(
+            detect_screen_status("  12 + let ok = value?;\n  13 - if x == y ? a : b"),
+            None
+        );
+    }
+
     #[test]
     fn test_extract_session_id() {
         let text = "Resuming session: a1b2c3d4-e5f6-7890-abcd-ef1234567890";
@@ -1,11 +1,56 @@
 // Claude binary resolver - finds the Claude Code binary and builds its environment
 // Avoids shell wrapper noise by spawning claude directly
+// Besides the detected binary, config.toml can name others (`[claude] binaries`)
+// that sessions pick with `claude_binary`
+// Its version is checked at startup and by daemon.claude_info, raising
+// daemon:claude_warning when sessions are likely to fail to start
 
+use chrono::Utc;
+use regex::Regex;
+use shared::{ClaudeCapabilities, ClaudeInfo, Event};
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::Command;
+use std::sync::LazyLock;
+use std::time::Duration;
+use tokio::sync::{broadcast, RwLock};
 use tracing::{debug, info, warn};
 
+use crate::config::{ClaudeConfig, SharedConfig};
+use crate::updates;
+
+/// How long `claude --help` and `--version` may take (node has to start up)
+const PROBE_TIMEOUT: Duration = Duration::from_secs(10);
+
+static HELP_FLAG: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?m)^\s+(?:-\w,\s+)?(--[a-z][a-z0-9-]*)").unwrap());
+static PERMISSION_CHOICES: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"(?s)--permission-mode[^(]*\(choices:([^)]*)\)"#).unwrap());
+static QUOTED: LazyLock<Regex> = LazyLock::new(|| Regex::new(r#""([^"]+)""#).unwrap());
+
+/// Flags and permission modes listed in `claude --help`
+fn parse_help(help: &str) -> (Vec<String>, Vec<String>) {
+    let flags = HELP_FLAG
+        .captures_iter(help)
+        .map(|c| c[1].to_string())
+        .collect();
+    let permission_modes = PERMISSION_CHOICES
+        .captures(help)
+        .map(|c| {
+            QUOTED
+                .captures_iter(&c[1])
+                .map(|q| q[1].to_string())
+                .collect()
+        })
+        .unwrap_or_default();
+    (flags, permission_modes)
+}
+
+/// A session asked for a binary that isn't in `[claude] binaries`
+#[derive(Debug, thiserror::Error)]
+#[error("No claude binary named '{0}' in config.toml")]
+pub struct UnknownClaudeBinary(pub String);
+
 /// Resolves the path to the Claude Code binary and provides environment setup
 pub struct ClaudeResolver {
     claude_path: Option<PathBuf>,
@@ -23,11 +68,88 @@ impl ClaudeResolver {
         Self { claude_path }
     }
 
+    /// A resolver for the binary `name`; None means `default_binary`, or the
+    /// detected binary if that isn't set either
+    pub fn for_binary(name: Option<&str>, config: &ClaudeConfig) -> anyhow::Result<Self> {
+        match Self::named(name, config)? {
+            Some(path) => Ok(Self {
+                claude_path: Some(path),
+            }),
+            None => Ok(Self::new()),
+        }
+    }
+
+    /// Path of the binary `name` (or `default_binary`) from the config; None
+    /// if neither is set, so the detected binary applies
+    pub fn named(name: Option<&str>, config: &ClaudeConfig) -> anyhow::Result<Option<PathBuf>> {
+        let Some(name) = name.or(config.default_binary.as_deref()) else {
+            return Ok(None);
+        };
+        let path = config
+            .binaries
+            .get(name)
+            .ok_or_else(|| UnknownClaudeBinary(name.to_string()))?;
+        if !path.exists() {
+            anyhow::bail!("Claude binary '{}' not found at {}", name, path.display());
+        }
+        Ok(Some(path.clone()))
+    }
+
     /// Get the resolved claude binary path
     pub fn claude_path(&self) -> Option<&PathBuf> {
         self.claude_path.as_ref()
     }
 
+    /// What the resolved binary supports, from its --help and --version
+    pub async fn capabilities(&self) -> ClaudeCapabilities {
+        let mut capabilities = ClaudeCapabilities {
+            path: self.claude_path.clone(),
+            version: None,
+            flags: Vec::new(),
+            permission_modes: Vec::new(),
+            binaries: Vec::new(),
+        };
+        let Some(path) = &self.claude_path else {
+            return capabilities;
+        };
+        capabilities.version = self.version().await;
+        if let Some(help) = self.probe(path, "--help").await {
+            (capabilities.flags, capabilities.permission_modes) = parse_help(&help);
+        }
+        capabilities
+    }
+
+    /// Version from `claude --version` ("2.0.1 (Claude Code)"), e.g. "2.0.1"
+    pub async fn version(&self) -> Option<String> {
+        let output = self.probe(self.claude_path.as_ref()?, "--version").await?;
+        output.split_whitespace().next().map(str::to_string)
+    }
+
+    async fn probe(&self, path: &Path, arg: &str) -> Option<String> {
+        let output = tokio::process::Command::new(path)
+            .arg(arg)
+            .envs(self.build_env())
+            .kill_on_drop(true)
+            .output();
+        match tokio::time::timeout(PROBE_TIMEOUT, output).await {
+            Ok(Ok(output)) if output.status.success() => {
+                Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+            }
+            Ok(Ok(output)) => {
+                warn!("claude {} exited with {}", arg, output.status);
+                None
+            }
+            Ok(Err(e)) => {
+                warn!("Failed to run claude {}: {}", arg, e);
+                None
+            }
+            Err(_) => {
+                warn!("claude {} timed out", arg);
+                None
+            }
+        }
+    }
+
     /// Check if claude was found
     #[allow(dead_code)]
     pub fn is_available(&self) -> bool {
@@ -47,7 +169,8 @@ impl ClaudeResolver {
         let common_paths: Vec<PathBuf> = [
             // npm global installations
             home.as_ref().map(|h| h.join(".npm-global/bin/claude")),
-            home.as_ref().map(|h| h.join(".nvm/versions/node").join("*").join("bin/claude")),
+            home.as_ref()
+                .map(|h| h.join(".nvm/versions/node").join("*").join("bin/claude")),
             // Homebrew on macOS
             Some(PathBuf::from("/opt/homebrew/bin/claude")),
             Some(PathBuf::from("/usr/local/bin/claude")),
@@ -83,7 +206,7 @@ impl ClaudeResolver {
     }
 
     /// Expand glob pattern to find claude (for nvm-style paths)
-    fn expand_glob(pattern: &PathBuf) -> Option<PathBuf> {
+    fn expand_glob(pattern: &Path) -> Option<PathBuf> {
         let pattern_str = pattern.to_string_lossy();
         if let Ok(entries) = glob::glob(&pattern_str) {
             for entry in entries.flatten() {
@@ -202,6 +325,83 @@ impl Default for ClaudeResolver {
     }
 }
 
+/// Why sessions are likely to fail with this binary, if they are
+fn claude_warning(found: bool, version: Option<&str>, min_version: Option<&str>) -> Option<String> {
+    if !found {
+        return Some("Claude Code was not found; install it or add it to PATH".to_string());
+    }
+    let min_version = min_version?;
+    match version {
+        None => Some(format!(
+            "Couldn't read the Claude Code version; {} or newer is required",
+            min_version
+        )),
+        Some(version) if updates::is_newer(min_version, version) => Some(format!(
+            "Claude Code {} is older than the required {}",
+            version, min_version
+        )),
+        Some(_) => None,
+    }
+}
+
+/// The last check of the claude binary, for daemon.claude_info and daemon.diagnostics
+pub struct ClaudeCheck {
+    config: SharedConfig,
+    event_tx: broadcast::Sender<Event>,
+    latest: RwLock<Option<ClaudeInfo>>,
+}
+
+impl ClaudeCheck {
+    pub fn new(config: SharedConfig, event_tx: broadcast::Sender<Event>) -> Self {
+        Self {
+            config,
+            event_tx,
+            latest: RwLock::new(None),
+        }
+    }
+
+    pub async fn latest(&self) -> Option<ClaudeInfo> {
+        self.latest.read().await.clone()
+    }
+
+    /// Find the binary again and read its version, emitting
+    /// daemon:claude_warning if there's a problem
+    pub async fn check(&self) -> ClaudeInfo {
+        let (claude, min_version) = {
+            let config = self.config.read().await;
+            (
+                config.claude.clone(),
+                config.daemon.min_claude_version.clone(),
+            )
+        };
+        // A broken default_binary is reported and the detected binary checked instead
+        let (resolver, default_problem) = match ClaudeResolver::for_binary(None, &claude) {
+            Ok(resolver) => (resolver, None),
+            Err(e) => (ClaudeResolver::new(), Some(e.to_string())),
+        };
+        let version = resolver.version().await;
+        let info = ClaudeInfo {
+            path: resolver.claude_path().cloned(),
+            warning: default_problem.or_else(|| {
+                claude_warning(
+                    resolver.claude_path().is_some(),
+                    version.as_deref(),
+                    min_version.as_deref(),
+                )
+            }),
+            version,
+            min_version,
+            checked_at: Utc::now(),
+        };
+        *self.latest.write().await = Some(info.clone());
+        if let Some(warning) = &info.warning {
+            warn!("{}", warning);
+            let _ = self.event_tx.send(Event::ClaudeWarning(info.clone()));
+        }
+        info
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -218,6 +418,69 @@ mod tests {
         assert_eq!(env.get("TERM"), Some(&"xterm-256color".to_string()));
     }
 
+    #[test]
+    fn test_parse_help() {
+        let help = r#"Usage: claude [options] [command] [prompt]
+
+Options:
+  -d, --debug [filter]             Enable debug mode
+  --model <model>                  Model for the current session
+  --permission-mode <mode>         Permission mode to use for the session
+                                   (choices: "acceptEdits", "bypassPermissions",
+                                   "default", "plan")
+  -h, --help                       Display help for command
+"#;
+        let (flags, modes) = parse_help(help);
+        assert_eq!(flags, ["--debug", "--model", "--permission-mode", "--help"]);
+        assert_eq!(
+            modes,
+            ["acceptEdits", "bypassPermissions", "default", "plan"]
+        );
+        assert_eq!(parse_help("no flags here"), (vec![], vec![]));
+    }
+
+    #[test]
+    fn test_named_binaries() {
+        let existing = std::env::current_exe().unwrap();
+        let config = ClaudeConfig {
+            binaries: HashMap::from([
+                ("stable".to_string(), existing.clone()),
+                ("gone".to_string(), PathBuf::from("/nonexistent/claude")),
+            ]),
+            default_binary: None,
+        };
+        assert_eq!(ClaudeResolver::named(None, &config).unwrap(), None);
+        assert_eq!(
+            ClaudeResolver::named(Some("stable"), &config).unwrap(),
+            Some(existing.clone())
+        );
+        let err = ClaudeResolver::named(Some("nightly"), &config).unwrap_err();
+        assert!(err.is::<UnknownClaudeBinary>());
+        assert!(ClaudeResolver::named(Some("gone"), &config).is_err());
+
+        let config = ClaudeConfig {
+            default_binary: Some("stable".to_string()),
+            ..config
+        };
+        assert_eq!(
+            ClaudeResolver::named(None, &config).unwrap(),
+            Some(existing)
+        );
+    }
+
+    #[test]
+    fn test_claude_warning() {
+        assert!(claude_warning(false, None, None).is_some());
+        assert_eq!(claude_warning(true, Some("2.0.1"), None), None);
+        assert_eq!(claude_warning(true, Some("2.0.1"), Some("2.0.0")), None);
+        assert_eq!(claude_warning(true, Some("2.0.1"), Some("2.0.1")), None);
+        assert_eq!(
+            claude_warning(true, Some("1.9.5"), Some("2.0.0")).as_deref(),
+            Some("Claude Code 1.9.5 is older than the required 2.0.0")
+        );
+        assert!(claude_warning(true, None, Some("2.0.0")).is_some());
+    }
+
     #[test]
     fn test_env_vars_to_remove() {
         let vars = ClaudeResolver::env_vars_to_remove();
@@ -0,0 +1,220 @@
+// Clients - every IPC connection gets an id and may say what it is with
+// client.identify (GUI, CLI, web, MCP) and which session's terminal it shows
+// with session.view. daemon.clients lists them, and session:presence tells
+// everyone who is viewing a session and who just typed into it, so a client
+// can show "someone else is typing here".
+
+use chrono::Utc;
+use shared::{ClientIdentifyParams, ClientInfo, Event, PresenceData, Request};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast;
+use uuid::Uuid;
+
+/// Methods that count as a client typing into the session they name
+const INPUT_METHODS: &[&str] = &["session.input", "session.send_key", "session.send_snippet"];
+
+/// Least time between session:presence events for one client typing in a session
+const TYPING_EVENT_INTERVAL: Duration = Duration::from_secs(2);
+
+struct Client {
+    info: ClientInfo,
+    /// Session and time of the last typing event sent for this client
+    typing_event: Option<(Uuid, Instant)>,
+}
+
+pub struct Clients {
+    clients: Mutex<HashMap<u64, Client>>,
+    event_tx: broadcast::Sender<Event>,
+}
+
+impl Clients {
+    pub fn new(event_tx: broadcast::Sender<Event>) -> Self {
+        Self {
+            clients: Mutex::new(HashMap::new()),
+            event_tx,
+        }
+    }
+
+    pub fn connected(&self, client_id: u64) {
+        let info = ClientInfo {
+            client_id,
+            kind: Default::default(),
+            name: None,
+            connected_at: Utc::now(),
+            viewing: None,
+            last_input_at: None,
+        };
+        let client = Client {
+            info,
+            typing_event: None,
+        };
+        self.clients.lock().unwrap().insert(client_id, client);
+    }
+
+    /// Forget the client, telling the viewers of the session it showed
+    pub fn disconnected(&self, client_id: u64) {
+        let event = {
+            let mut clients = self.clients.lock().unwrap();
+            let viewing = clients
+                .remove(&client_id)
+                .and_then(|client| client.info.viewing);
+            viewing.map(|session_id| presence(&clients, session_id, None))
+        };
+        if let Some(event) = event {
+            let _ = self.event_tx.send(event);
+        }
+    }
+
+    pub fn identify(&self, client_id: u64, params: ClientIdentifyParams) -> Option<ClientInfo> {
+        let mut clients = self.clients.lock().unwrap();
+        let client = clients.get_mut(&client_id)?;
+        client.info.kind = params.kind;
+        client.info.name = params.name;
+        Some(client.info.clone())
+    }
+
+    /// Record which session's terminal the client shows, telling the viewers
+    /// of the session it left and the one it joined
+    pub fn view(&self, client_id: u64, session_id: Option<Uuid>) -> Option<ClientInfo> {
+        let (info, events) = {
+            let mut clients = self.clients.lock().unwrap();
+            let client = clients.get_mut(&client_id)?;
+            let left = std::mem::replace(&mut client.info.viewing, session_id);
+            let info = client.info.clone();
+            let events: Vec<Event> = if left == session_id {
+                Vec::new()
+            } else {
+                [left, session_id]
+                    .into_iter()
+                    .flatten()
+                    .map(|id| presence(&clients, id, None))
+                    .collect()
+            };
+            (info, events)
+        };
+        for event in events {
+            let _ = self.event_tx.send(event);
+        }
+        Some(info)
+    }
+
+    /// Note a request from the client; input to a session is sent to its
+    /// viewers as typing, at most every TYPING_EVENT_INTERVAL
+    pub fn request(&self, client_id: u64, request: &Request) {
+        if !INPUT_METHODS.contains(&request.method.as_str()) {
+            return;
+        }
+        let Some(session_id) = request
+            .params
+            .get("session_id")
+            .and_then(|id| id.as_str())
+            .and_then(|id| Uuid::parse_str(id).ok())
+        else {
+            return;
+        };
+
+        let event = {
+            let mut clients = self.clients.lock().unwrap();
+            let Some(client) = clients.get_mut(&client_id) else {
+                return;
+            };
+            client.info.last_input_at = Some(Utc::now());
+            let recent = client
+                .typing_event
+                .is_some_and(|(id, at)| id == session_id && at.elapsed() < TYPING_EVENT_INTERVAL);
+            if recent {
+                return;
+            }
+            client.typing_event = Some((session_id, Instant::now()));
+            presence(&clients, session_id, Some(client_id))
+        };
+        let _ = self.event_tx.send(event);
+    }
+
+    /// Every connected client, by id
+    pub fn list(&self) -> Vec<ClientInfo> {
+        let mut clients: Vec<ClientInfo> = self
+            .clients
+            .lock()
+            .unwrap()
+            .values()
+            .map(|client| client.info.clone())
+            .collect();
+        clients.sort_by_key(|info| info.client_id);
+        clients
+    }
+}
+
+fn presence(clients: &HashMap<u64, Client>, session_id: Uuid, typing: Option<u64>) -> Event {
+    let mut viewers: Vec<ClientInfo> = clients
+        .values()
+        .filter(|client| client.info.viewing == Some(session_id))
+        .map(|client| client.info.clone())
+        .collect();
+    viewers.sort_by_key(|info| info.client_id);
+    Event::Presence(PresenceData {
+        session_id,
+        viewers,
+        typing,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shared::ClientKind;
+
+    fn presence_of(rx: &mut broadcast::Receiver<Event>) -> PresenceData {
+        match rx.try_recv() {
+            Ok(Event::Presence(data)) => data,
+            other => panic!("expected session:presence, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_presence() {
+        let (event_tx, mut rx) = broadcast::channel(16);
+        let clients = Clients::new(event_tx);
+        let session = Uuid::new_v4();
+        clients.connected(1);
+        clients.connected(2);
+        let info = clients
+            .identify(
+                1,
+                ClientIdentifyParams {
+                    kind: ClientKind::Gui,
+                    name: None,
+                },
+            )
+            .unwrap();
+        assert_eq!(info.kind, ClientKind::Gui);
+
+        clients.view(1, Some(session));
+        clients.view(2, Some(session));
+        presence_of(&mut rx);
+        assert_eq!(presence_of(&mut rx).viewers.len(), 2);
+        // Viewing it again changes nothing
+        clients.view(2, Some(session));
+        assert!(rx.try_recv().is_err());
+
+        let input = Request {
+            id: 1,
+            method: "session.input".to_string(),
+            params: serde_json::json!({ "session_id": session, "input": "hi" }),
+        };
+        clients.request(2, &input);
+        assert_eq!(presence_of(&mut rx).typing, Some(2));
+        // Throttled while it keeps typing
+        clients.request(2, &input);
+        assert!(rx.try_recv().is_err());
+        assert!(clients.list()[1].last_input_at.is_some());
+
+        clients.disconnected(1);
+        let data = presence_of(&mut rx);
+        assert_eq!(data.viewers.len(), 1);
+        assert_eq!(data.viewers[0].client_id, 2);
+        assert_eq!(clients.list().len(), 1);
+    }
+}
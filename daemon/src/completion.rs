@@ -0,0 +1,238 @@
+// Completion watches - group.set_completion_watch asks for one summary when a
+// group's work is done: once every session under it (subgroups included) is
+// Idle, Stopped or in Error after any of them was working, group:completed is
+// emitted with each session's outcome (the GUI shows an OS notification) and,
+// if the watch has a webhook_url, POSTed there. It fires again only after the
+// group's sessions have gone back to work.
+
+use anyhow::Result;
+use shared::{CompletionWatch, Event, Group, GroupCompletedData, SessionOutcome, SessionStatus};
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::config::SharedConfig;
+use crate::egress::{self, Egress};
+use crate::notifications::Notifier;
+use crate::state::{save_state, AppState, GroupNotFound, SharedState};
+
+/// Watch `group_id` for its sessions finishing, or stop watching with None
+pub async fn set(
+    state: &SharedState,
+    event_tx: &broadcast::Sender<Event>,
+    group_id: Uuid,
+    watch: Option<CompletionWatch>,
+) -> Result<Group> {
+    let group = {
+        let mut s = state.write().await;
+        let group = s.groups.get_mut(&group_id).ok_or(GroupNotFound(group_id))?;
+        group.completion_watch = watch;
+        group.clone()
+    };
+    save_state(state).await?;
+    let _ = event_tx.send(Event::GroupUpdated(group.clone()));
+    Ok(group)
+}
+
+fn finished(status: SessionStatus) -> bool {
+    matches!(
+        status,
+        SessionStatus::Idle | SessionStatus::Stopped | SessionStatus::Error
+    )
+}
+
+/// The sessions under `group_id`, including those in its subgroups
+fn outcomes(s: &AppState, group_id: Uuid) -> Vec<SessionOutcome> {
+    let mut outcomes: Vec<SessionOutcome> = s
+        .sessions
+        .values()
+        .filter(|session| {
+            let mut current = session.group_id;
+            // Bounded in case of a parent cycle
+            for _ in 0..=s.groups.len() {
+                match current {
+                    Some(id) if id == group_id => return true,
+                    Some(id) => current = s.groups.get(&id).and_then(|g| g.parent_id),
+                    None => return false,
+                }
+            }
+            false
+        })
+        .map(|session| SessionOutcome {
+            session_id: session.id,
+            name: session.name.clone(),
+            status: session.status,
+        })
+        .collect();
+    outcomes.sort_by(|a, b| a.name.cmp(&b.name));
+    outcomes
+}
+
+/// Watched groups with a session still working, and those whose sessions
+/// have all finished (with at least one session)
+fn partition(s: &AppState) -> (HashSet<Uuid>, Vec<GroupCompletedData>) {
+    let mut working = HashSet::new();
+    let mut done = Vec::new();
+    for group in s.groups.values() {
+        if group.completion_watch.is_none() {
+            continue;
+        }
+        let sessions = outcomes(s, group.id);
+        if sessions.iter().any(|session| !finished(session.status)) {
+            working.insert(group.id);
+        } else if !sessions.is_empty() {
+            done.push(GroupCompletedData {
+                group_id: group.id,
+                name: group.name.clone(),
+                sessions,
+                muted: false,
+            });
+        }
+    }
+    (working, done)
+}
+
+pub struct CompletionWatcher {
+    state: SharedState,
+    config: SharedConfig,
+    notifier: Arc<Notifier>,
+    event_tx: broadcast::Sender<Event>,
+}
+
+impl CompletionWatcher {
+    pub fn new(
+        state: SharedState,
+        config: SharedConfig,
+        notifier: Arc<Notifier>,
+        event_tx: broadcast::Sender<Event>,
+    ) -> Self {
+        Self {
+            state,
+            config,
+            notifier,
+            event_tx,
+        }
+    }
+
+    /// Report watched groups as their sessions finish
+    pub async fn run(self) {
+        let mut event_rx = self.event_tx.subscribe();
+        // Only groups seen working can complete, so nothing fires at startup
+        let (mut working, _) = partition(&*self.state.read().await);
+
+        loop {
+            match event_rx.recv().await {
+                Ok(
+                    Event::StatusChanged(_)
+                    | Event::SessionCreated(_)
+                    | Event::SessionDeleted { .. }
+                    | Event::SessionMoved(_)
+                    | Event::GroupUpdated(_)
+                    | Event::GroupDeleted { .. },
+                ) => {}
+                Ok(_) => continue,
+                Err(broadcast::error::RecvError::Lagged(n)) => {
+                    warn!("Completion watcher lagged, skipped {} events", n);
+                }
+                Err(broadcast::error::RecvError::Closed) => return,
+            }
+
+            let (now_working, done) = partition(&*self.state.read().await);
+            for data in done {
+                if working.contains(&data.group_id) {
+                    self.complete(data).await;
+                }
+            }
+            working = now_working;
+        }
+    }
+
+    async fn complete(&self, mut data: GroupCompletedData) {
+        let settings = self.notifier.settings().await;
+        data.muted = !settings.enabled || settings.muted_groups.contains(&data.group_id);
+        info!(
+            "Every session in group {} has finished ({} sessions)",
+            data.group_id,
+            data.sessions.len()
+        );
+        let webhook_url = self
+            .state
+            .read()
+            .await
+            .groups
+            .get(&data.group_id)
+            .and_then(|group| group.completion_watch.as_ref()?.webhook_url.clone());
+        let _ = self.event_tx.send(Event::GroupCompleted(data.clone()));
+
+        if let Some(url) = webhook_url {
+            let config = self.config.clone();
+            tokio::spawn(async move {
+                if let Err(e) = post(&config, &url, &data).await {
+                    warn!(
+                        "Completion webhook for group {} failed: {}",
+                        data.group_id, e
+                    );
+                }
+            });
+        }
+    }
+}
+
+async fn post(config: &SharedConfig, url: &str, data: &GroupCompletedData) -> Result<()> {
+    egress::check(&*config.read().await, Egress::CompletionWebhook)?;
+    reqwest::Client::builder()
+        .timeout(Duration::from_secs(30))
+        .build()?
+        .post(url)
+        .json(data)
+        .send()
+        .await?
+        .error_for_status()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shared::Session;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_partition() {
+        let mut s = AppState::default();
+        let mut parent = Group::new("parent".to_string(), None);
+        parent.completion_watch = Some(CompletionWatch::default());
+        let child = Group::new("child".to_string(), Some(parent.id));
+        let mut empty = Group::new("empty".to_string(), None);
+        empty.completion_watch = Some(CompletionWatch::default());
+        let mut sessions = Vec::new();
+        for (group_id, status) in [
+            (parent.id, SessionStatus::Idle),
+            (child.id, SessionStatus::Running),
+        ] {
+            let mut session = Session::new("s".into(), PathBuf::from("/"), Some(group_id));
+            session.status = status;
+            sessions.push(session.id);
+            s.sessions.insert(session.id, session);
+        }
+        for group in [parent.clone(), child, empty] {
+            s.groups.insert(group.id, group);
+        }
+
+        // A session in a subgroup is still working
+        let (working, done) = partition(&s);
+        assert_eq!(working, HashSet::from([parent.id]));
+        assert!(done.is_empty());
+
+        s.sessions.get_mut(&sessions[1]).unwrap().status = SessionStatus::Error;
+        let (working, done) = partition(&s);
+        assert!(working.is_empty());
+        // Groups without sessions never complete
+        assert_eq!(done.len(), 1);
+        assert_eq!(done[0].group_id, parent.id);
+        assert_eq!(done[0].sessions.len(), 2);
+    }
+}
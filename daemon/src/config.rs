@@ -1,15 +1,108 @@
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
+use shared::{
+    NotificationSettings, OrphanPolicy, PtyPolicy, Role, SessionStatus, SpawnMode, StatusColors,
+    ThemeResult,
+};
+use std::collections::HashMap;
 use std::fs;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use tracing_subscriber::EnvFilter;
 
 // Re-export shared path utilities
 pub use shared::{get_config_path, get_socket_path, get_state_dir};
 
+/// Live config, replaced in place when config.toml is edited
+pub type SharedConfig = Arc<RwLock<Config>>;
+
+/// Shortest token accepted in auth.tokens, so none is easy to guess
+const MIN_TOKEN_CHARS: usize = 16;
+
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 #[serde(default)]
 pub struct Config {
     pub daemon: DaemonConfig,
     pub ui: UiConfig,
+    pub updates: UpdatesConfig,
+    pub notifications: NotificationSettings,
+    pub session_logs: SessionLogConfig,
+    pub claude: ClaudeConfig,
+    pub http: HttpConfig,
+    pub auth: AuthConfig,
+    pub tunnel: TunnelConfig,
+    pub approval_webhook: ApprovalWebhookConfig,
+}
+
+/// Permission dialogs posted to a Slack-style incoming webhook with signed
+/// Approve and Deny links, which the HTTP API answers (needs http.enabled)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ApprovalWebhookConfig {
+    /// Incoming webhook to post to (None = off)
+    pub url: Option<String>,
+    /// Where the links send the browser: the HTTP API as the phone reaches it,
+    /// e.g. "https://devbox.example.ts.net:7878"
+    pub link_base: Option<String>,
+    /// How long the links work
+    pub link_ttl_mins: u64,
+}
+
+/// The socket protocol on a loopback TCP port, for a GUI on another machine
+/// that reaches it through an SSH tunnel (`--tunnel` prints how); connections
+/// authenticate with the token in tunnel.token (off by default, needs a restart)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TunnelConfig {
+    pub enabled: bool,
+    /// Port on 127.0.0.1; never bound on other addresses
+    pub port: u16,
+}
+
+/// Extra tokens for daemon.auth and the HTTP API, each limited to a role
+/// e.g. `tokens = [{ name = "dashboard", token = "...", role = "viewer" }]`
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct AuthConfig {
+    pub tokens: Vec<TokenConfig>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TokenConfig {
+    /// Who the token is for, shown in the log when it is used
+    pub name: String,
+    pub token: String,
+    pub role: Role,
+}
+
+/// REST API over HTTP, for scripts and other machines; requests need the
+/// bearer token in http.token (off by default, needs a restart)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HttpConfig {
+    pub enabled: bool,
+    /// Address to listen on; anything but loopback exposes the daemon to the network
+    pub addr: String,
+    /// Serve HTTPS; without tls_cert and tls_key a self-signed certificate is
+    /// made in the data dir (tls.crt) and kept, and its fingerprint logged
+    pub tls: bool,
+    /// PEM certificate chain to serve instead of the self-signed one
+    pub tls_cert: Option<PathBuf>,
+    /// PEM private key of tls_cert
+    pub tls_key: Option<PathBuf>,
+}
+
+/// Claude installations sessions can choose between
+/// e.g. `binaries = { stable = "/usr/local/bin/claude", nightly = "/opt/claude-nightly/bin/claude" }`
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct ClaudeConfig {
+    /// Binaries by name
+    pub binaries: HashMap<String, PathBuf>,
+    /// Name of the binary used by sessions that don't pick one (None = the
+    /// claude found on PATH or in the usual install locations)
+    pub default_binary: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -17,7 +110,55 @@ pub struct Config {
 pub struct DaemonConfig {
     pub socket_timeout_ms: u64,
     pub output_buffer_kb: usize,
+    /// PTY output queued for processing before readers pause (needs a restart)
+    pub output_queue_kb: usize,
+    /// Filter for the daemon's log, as EnvFilter directives; set per module
+    /// with e.g. `info,claude_master_daemon::pty=debug`
     pub log_level: String,
+    /// Rotate logs/daemon.jsonl when it reaches this size (it also rotates daily)
+    pub log_max_size_kb: u64,
+    /// Rotated daemon logs kept
+    pub log_max_files: usize,
+    /// Write panic backtraces to the logs dir (minidumps of native crashes are not captured)
+    pub crash_reports: bool,
+    /// Input sent to a stopped session restarts it (with --resume) instead of failing
+    pub auto_start_on_input: bool,
+    /// Emit session:slow_turn for turns longer than this (0 = never)
+    pub slow_turn_secs: u64,
+    /// Mark a session Idle after this long with no output or input (0 = never)
+    pub idle_timeout_mins: u64,
+    /// Also stop the claude process of a session that hit idle_timeout_mins
+    pub idle_stop: bool,
+    /// How long a start waits for claude's first output before reporting the
+    /// session Running anyway
+    pub start_timeout_secs: u64,
+    /// Emit session:resource_usage with the CPU and memory of running sessions
+    /// this often (0 = never; session.stats still works)
+    pub resource_interval_secs: u64,
+    /// What to do with running sessions on SIGTERM/SIGINT
+    pub shutdown_pty_policy: PtyPolicy,
+    /// Most sessions the prompt queue lets run at once (0 = unlimited)
+    pub max_concurrent_prompts: usize,
+    /// Most sessions Starting, Running or Waiting before session.enqueue_start queues
+    /// further starts (0 = unlimited)
+    pub max_concurrent_running: usize,
+    /// Kill or adopt claude processes left running by a previous daemon
+    pub orphan_policy: OrphanPolicy,
+    /// Put new sessions without a group into a top-level group named after their repository
+    pub auto_group_by_repo: bool,
+    /// How sessions launch claude unless they override it
+    pub spawn_mode: SpawnMode,
+    /// Block every outbound integration (update checks, claude install) regardless
+    /// of their own settings; see daemon.network_audit
+    pub local_only: bool,
+    /// Keep the host from sleeping while a session is Running or has queued work
+    pub keep_awake: bool,
+    /// Clients must send daemon.auth with the token in daemon.token before
+    /// anything else (needs a restart)
+    pub require_token: bool,
+    /// Oldest claude version sessions are expected to work with, e.g. "1.0.30";
+    /// an older binary raises daemon:claude_warning
+    pub min_claude_version: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -26,6 +167,70 @@ pub struct UiConfig {
     pub theme: String,
     pub font_family: String,
     pub font_size: u16,
+    /// Base palette for status colors
+    pub status_palette: StatusPalette,
+    /// Per-status color overrides applied on top of the palette
+    /// e.g. `status_colors = { running = "#00ff00" }`
+    pub status_colors: HashMap<SessionStatus, String>,
+}
+
+/// Update checks are opt-in: nothing contacts the network unless enabled
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UpdatesConfig {
+    pub enabled: bool,
+    pub check_interval_hours: u64,
+    /// GitHub "latest release" API endpoint
+    pub release_url: String,
+}
+
+/// Raw PTY output logs per session (off by default; they can hold secrets)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SessionLogConfig {
+    pub enabled: bool,
+    /// Rotate a session's log when it reaches this size
+    pub max_size_kb: u64,
+    /// Rotated logs kept per session
+    pub max_files: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum StatusPalette {
+    #[default]
+    Default,
+    /// Okabe-Ito colors, distinguishable with the common forms of color blindness
+    ColorBlind,
+}
+
+impl StatusPalette {
+    pub fn name(&self) -> &'static str {
+        match self {
+            StatusPalette::Default => "default",
+            StatusPalette::ColorBlind => "color_blind",
+        }
+    }
+
+    pub fn colors(&self) -> StatusColors {
+        let (running, waiting, idle, error, queued, starting, stopped) = match self {
+            StatusPalette::Default => (
+                "#60a5fa", "#fbbf24", "#9ca3af", "#f87171", "#a78bfa", "#22d3ee", "#6b7280",
+            ),
+            StatusPalette::ColorBlind => (
+                "#0072b2", "#e69f00", "#999999", "#cc79a7", "#56b4e9", "#009e73", "#666666",
+            ),
+        };
+        StatusColors {
+            running: running.to_string(),
+            waiting: waiting.to_string(),
+            idle: idle.to_string(),
+            error: error.to_string(),
+            queued: queued.to_string(),
+            starting: starting.to_string(),
+            stopped: stopped.to_string(),
+        }
+    }
 }
 
 impl Default for DaemonConfig {
@@ -33,7 +238,79 @@ impl Default for DaemonConfig {
         Self {
             socket_timeout_ms: 5000,
             output_buffer_kb: 10,
+            output_queue_kb: 4096,
             log_level: "info".to_string(),
+            log_max_size_kb: 10 * 1024,
+            log_max_files: 5,
+            crash_reports: false,
+            auto_start_on_input: false,
+            slow_turn_secs: 300,
+            idle_timeout_mins: 0,
+            idle_stop: false,
+            start_timeout_secs: 10,
+            resource_interval_secs: 30,
+            shutdown_pty_policy: PtyPolicy::Kill,
+            max_concurrent_prompts: 0,
+            max_concurrent_running: 0,
+            orphan_policy: OrphanPolicy::Kill,
+            auto_group_by_repo: false,
+            spawn_mode: SpawnMode::Direct,
+            local_only: false,
+            keep_awake: false,
+            require_token: false,
+            min_claude_version: None,
+        }
+    }
+}
+
+impl Default for HttpConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            addr: "127.0.0.1:7878".to_string(),
+            tls: false,
+            tls_cert: None,
+            tls_key: None,
+        }
+    }
+}
+
+impl Default for ApprovalWebhookConfig {
+    fn default() -> Self {
+        Self {
+            url: None,
+            link_base: None,
+            link_ttl_mins: 60,
+        }
+    }
+}
+
+impl Default for TunnelConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            port: 7879,
+        }
+    }
+}
+
+impl Default for SessionLogConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_size_kb: 10 * 1024,
+            max_files: 3,
+        }
+    }
+}
+
+impl Default for UpdatesConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            check_interval_hours: 24,
+            release_url: "https://api.github.com/repos/brannon-bowden/ClaudeMaster/releases/latest"
+                .to_string(),
         }
     }
 }
@@ -44,10 +321,78 @@ impl Default for UiConfig {
             theme: "dark".to_string(),
             font_family: "monospace".to_string(),
             font_size: 14,
+            status_palette: StatusPalette::Default,
+            status_colors: HashMap::new(),
         }
     }
 }
 
+impl UiConfig {
+    /// Resolve the palette and overrides into the `ui.theme` result
+    pub fn theme(&self) -> ThemeResult {
+        let mut colors = self.status_palette.colors();
+        for (status, color) in &self.status_colors {
+            let slot = match status {
+                SessionStatus::Running => &mut colors.running,
+                SessionStatus::Waiting => &mut colors.waiting,
+                SessionStatus::Idle => &mut colors.idle,
+                SessionStatus::Error => &mut colors.error,
+                SessionStatus::Queued => &mut colors.queued,
+                SessionStatus::Starting => &mut colors.starting,
+                SessionStatus::Stopped => &mut colors.stopped,
+            };
+            *slot = color.clone();
+        }
+
+        ThemeResult {
+            theme: self.theme.clone(),
+            font_family: self.font_family.clone(),
+            font_size: self.font_size,
+            palette: self.status_palette.name().to_string(),
+            status_colors: colors,
+        }
+    }
+}
+
+impl Config {
+    /// Reject values that parse but can't be applied
+    pub fn validate(&self) -> Result<()> {
+        EnvFilter::try_new(&self.daemon.log_level)
+            .map_err(|e| anyhow::anyhow!("Invalid daemon.log_level: {}", e))?;
+        if self.ui.font_size == 0 {
+            anyhow::bail!("ui.font_size must be greater than 0");
+        }
+        if self.updates.check_interval_hours == 0 {
+            anyhow::bail!("updates.check_interval_hours must be greater than 0");
+        }
+        if self.session_logs.max_size_kb == 0 {
+            anyhow::bail!("session_logs.max_size_kb must be greater than 0");
+        }
+        if self.http.enabled {
+            self.http
+                .addr
+                .parse::<std::net::SocketAddr>()
+                .map_err(|e| anyhow::anyhow!("Invalid http.addr: {}", e))?;
+        }
+        if self.http.tls_cert.is_some() != self.http.tls_key.is_some() {
+            anyhow::bail!("http.tls_cert and http.tls_key must be set together");
+        }
+        if let Some(token) = self
+            .auth
+            .tokens
+            .iter()
+            .find(|token| token.token.len() < MIN_TOKEN_CHARS)
+        {
+            anyhow::bail!(
+                "auth.tokens: the token for {:?} must be at least {} characters",
+                token.name,
+                MIN_TOKEN_CHARS
+            );
+        }
+        Ok(())
+    }
+}
+
 pub fn load_config() -> Result<Config> {
     let config_path = get_config_path()?;
     if config_path.exists() {
@@ -58,3 +403,62 @@ pub fn load_config() -> Result<Config> {
         Ok(Config::default())
     }
 }
+
+pub fn save_config(config: &Config) -> Result<()> {
+    let config_path = get_config_path()?;
+    if let Some(parent) = config_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(&config_path, toml::to_string_pretty(config)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_theme_palette_and_overrides() {
+        let config: Config = toml::from_str(
+            r##"
+            [ui]
+            status_palette = "color_blind"
+            status_colors = { error = "#ff0000" }
+            "##,
+        )
+        .unwrap();
+
+        let theme = config.ui.theme();
+        assert_eq!(theme.palette, "color_blind");
+        assert_eq!(theme.status_colors.running, "#0072b2");
+        assert_eq!(theme.status_colors.error, "#ff0000");
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(Config::default().validate().is_ok());
+
+        let mut config = Config::default();
+        config.daemon.log_level = "debug,claude_master_daemon=trace".to_string();
+        assert!(config.validate().is_ok());
+
+        config.daemon.log_level = "loud[".to_string();
+        assert!(config.validate().is_err());
+
+        let mut config = Config::default();
+        config.ui.font_size = 0;
+        assert!(config.validate().is_err());
+
+        let config: Config = toml::from_str(
+            r#"
+            [[auth.tokens]]
+            name = "phone"
+            token = "short"
+            role = "viewer"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.auth.tokens[0].role, Role::Viewer);
+        assert!(config.validate().is_err());
+    }
+}
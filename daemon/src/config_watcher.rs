@@ -0,0 +1,109 @@
+// Config watcher - reloads config.toml when it is edited and applies the changes
+// without a daemon restart, then emits daemon:config_reloaded
+// Update check settings are read once at startup and still need a restart
+
+use anyhow::Result;
+use notify::{EventKind, RecursiveMode, Watcher};
+use shared::{ConfigReloadedData, Event};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{broadcast, mpsc};
+use tracing::{info, warn};
+use tracing_subscriber::{reload, EnvFilter, Registry};
+
+use crate::config::{get_config_path, load_config, SharedConfig};
+use crate::notifications::Notifier;
+
+/// Handle for swapping the log filter at runtime
+pub type LogReloadHandle = reload::Handle<EnvFilter, Registry>;
+
+/// Editors often write a file in several steps; wait for them to settle
+const DEBOUNCE_MS: u64 = 300;
+
+pub struct ConfigWatcher {
+    config: SharedConfig,
+    notifier: Arc<Notifier>,
+    /// None when RUST_LOG is set, which takes precedence over the config
+    log_handle: Option<LogReloadHandle>,
+}
+
+impl ConfigWatcher {
+    pub fn new(
+        config: SharedConfig,
+        notifier: Arc<Notifier>,
+        log_handle: Option<LogReloadHandle>,
+    ) -> Self {
+        Self {
+            config,
+            notifier,
+            log_handle,
+        }
+    }
+
+    /// Watch config.toml and reload it on every change
+    pub async fn run(self, event_tx: broadcast::Sender<Event>) -> Result<()> {
+        let path = get_config_path()?;
+        let dir = path
+            .parent()
+            .ok_or_else(|| anyhow::anyhow!("Config path has no parent directory"))?
+            .to_path_buf();
+        std::fs::create_dir_all(&dir)?;
+
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let file_name = path.file_name().map(|n| n.to_owned());
+        // Watch the directory rather than the file so atomic saves (write to a
+        // temp file, then rename over config.toml) are picked up too
+        let mut watcher =
+            notify::recommended_watcher(move |res: notify::Result<notify::Event>| {
+                if let Ok(event) = res {
+                    // Ignore access events, or our own reads would trigger reloads
+                    let changed = matches!(
+                        event.kind,
+                        EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_)
+                    );
+                    if changed
+                        && event
+                            .paths
+                            .iter()
+                            .any(|p| p.file_name() == file_name.as_deref())
+                    {
+                        let _ = tx.send(());
+                    }
+                }
+            })?;
+        watcher.watch(&dir, RecursiveMode::NonRecursive)?;
+        info!("Watching {:?} for config changes", path);
+
+        while rx.recv().await.is_some() {
+            tokio::time::sleep(Duration::from_millis(DEBOUNCE_MS)).await;
+            while rx.try_recv().is_ok() {}
+
+            let error = self.reload().await.err().map(|e| e.to_string());
+            if let Some(ref e) = error {
+                warn!("Config reload failed, keeping previous config: {}", e);
+            }
+            let event = Event::ConfigReloaded(ConfigReloadedData { error });
+            let _ = event_tx.send(event);
+        }
+        Ok(())
+    }
+
+    /// Load, validate and apply the config file
+    async fn reload(&self) -> Result<()> {
+        let new = load_config()?;
+        new.validate()?;
+
+        let mut config = self.config.write().await;
+        if let Some(handle) = &self.log_handle {
+            if new.daemon.log_level != config.daemon.log_level {
+                handle.reload(EnvFilter::try_new(&new.daemon.log_level)?)?;
+                info!("Log level set to {}", new.daemon.log_level);
+            }
+        }
+        self.notifier.replace(new.notifications.clone()).await;
+        *config = new;
+
+        info!("Config reloaded");
+        Ok(())
+    }
+}
@@ -0,0 +1,167 @@
+// Crash reporting - records panics with backtraces under the logs dir
+// The most recent report is surfaced via daemon.diagnostics after a restart,
+// and renamed to *.json.reported so the restart after that doesn't show it again.
+
+use anyhow::Result;
+use chrono::Utc;
+use shared::CrashReport;
+use std::backtrace::Backtrace;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::warn;
+
+/// Most reports (reported or not) kept in the crash dir; older ones are removed
+const MAX_CRASH_REPORTS: usize = 20;
+
+/// Directory where crash reports are written
+pub fn crash_dir() -> Result<PathBuf> {
+    let dir = shared::get_logs_dir()?.join("crashes");
+    fs::create_dir_all(&dir)?;
+    Ok(dir)
+}
+
+/// Install a panic hook that writes a JSON crash report to `dir`
+/// The previous hook still runs, so panics keep appearing on stderr.
+pub fn install_panic_hook(dir: PathBuf) {
+    let previous = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let message = if let Some(s) = info.payload().downcast_ref::<&str>() {
+            s.to_string()
+        } else if let Some(s) = info.payload().downcast_ref::<String>() {
+            s.clone()
+        } else {
+            "unknown panic payload".to_string()
+        };
+
+        let report = CrashReport {
+            timestamp: Utc::now(),
+            message,
+            location: info.location().map(|l| l.to_string()),
+            thread: std::thread::current().name().map(str::to_string),
+            backtrace: Backtrace::force_capture().to_string(),
+            // The runtime catches panics in spawned tasks; only the main
+            // thread's take the daemon down
+            fatal: std::thread::current().name() == Some("main"),
+        };
+
+        // Best effort - a failing crash handler must not mask the original panic
+        let path = dir.join(format!(
+            "crash-{}.json",
+            report.timestamp.format("%Y%m%dT%H%M%S%.3f")
+        ));
+        if let Ok(json) = serde_json::to_string_pretty(&report) {
+            let _ = fs::write(path, json);
+        }
+
+        previous(info);
+    }));
+}
+
+/// Load the most recent unreported crash report in `dir`, if any
+/// Every unreported report is marked reported, and the oldest beyond
+/// MAX_CRASH_REPORTS are removed.
+pub fn last_crash(dir: &Path) -> Option<CrashReport> {
+    let unreported = reports(dir, "json");
+    let report = unreported.last().and_then(|newest| read_report(newest));
+    for path in &unreported {
+        let mut reported = path.clone().into_os_string();
+        reported.push(".reported");
+        if let Err(e) = fs::rename(path, &reported) {
+            warn!("Failed to mark crash report {:?} reported: {}", path, e);
+        }
+    }
+
+    let reported = reports(dir, "reported");
+    let excess = reported.len().saturating_sub(MAX_CRASH_REPORTS);
+    for path in &reported[..excess] {
+        if let Err(e) = fs::remove_file(path) {
+            warn!("Failed to remove old crash report {:?}: {}", path, e);
+        }
+    }
+    report
+}
+
+/// Files in `dir` with extension `ext`, oldest first
+fn reports(dir: &Path, ext: &str) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut paths: Vec<PathBuf> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|e| e == ext))
+        .collect();
+    // Timestamped names sort chronologically
+    paths.sort();
+    paths
+}
+
+fn read_report(path: &Path) -> Option<CrashReport> {
+    match fs::read_to_string(path)
+        .map_err(anyhow::Error::from)
+        .and_then(|content| Ok(serde_json::from_str(&content)?))
+    {
+        Ok(report) => Some(report),
+        Err(e) => {
+            warn!("Failed to read crash report {:?}: {}", path, e);
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_report(dir: &Path, name: &str, message: &str) {
+        let report = CrashReport {
+            timestamp: Utc::now(),
+            message: message.to_string(),
+            location: None,
+            thread: None,
+            backtrace: String::new(),
+            fatal: true,
+        };
+        fs::write(dir.join(name), serde_json::to_string(&report).unwrap()).unwrap();
+    }
+
+    #[test]
+    fn test_last_crash_picks_newest() {
+        let dir = std::env::temp_dir().join(format!("crash-test-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        assert!(last_crash(&dir).is_none());
+
+        write_report(&dir, "crash-20260101T000000.000.json", "old");
+        write_report(&dir, "crash-20260102T000000.000.json", "new");
+        assert_eq!(last_crash(&dir).unwrap().message, "new");
+
+        // Both are reported now, and not shown again
+        assert!(dir.join("crash-20260101T000000.000.json.reported").exists());
+        assert!(dir.join("crash-20260102T000000.000.json.reported").exists());
+        assert!(last_crash(&dir).is_none());
+
+        write_report(&dir, "crash-20260103T000000.000.json", "newer");
+        assert_eq!(last_crash(&dir).unwrap().message, "newer");
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_old_reports_removed() {
+        let dir = std::env::temp_dir().join(format!("crash-prune-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        for day in 1..=MAX_CRASH_REPORTS + 5 {
+            let name = format!("crash-202601{:02}T000000.000.json", day);
+            write_report(&dir, &name, &day.to_string());
+        }
+
+        let newest = (MAX_CRASH_REPORTS + 5).to_string();
+        assert_eq!(last_crash(&dir).unwrap().message, newest);
+        let mut left = reports(&dir, "reported");
+        assert_eq!(left.len(), MAX_CRASH_REPORTS);
+        // The oldest went
+        assert!(left
+            .remove(0)
+            .ends_with("crash-20260106T000000.000.json.reported"));
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,228 @@
+// Session dependencies - session.set_dependency makes a session wait for
+// another: once the parent goes Idle or Stopped without having hit an Error,
+// the child is started through the run queue and its prompt, if any, goes to
+// the prompt queue. A dependency fires once and is then cleared, so chains of
+// them make simple multi-stage pipelines.
+
+use anyhow::Result;
+use shared::{Event, HistoryKind, PromptPriority, Session, SessionDependency, SessionStatus};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use tokio::sync::broadcast;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::history;
+use crate::prompt_queue::PromptQueue;
+use crate::run_queue::RunQueue;
+use crate::state::{save_state, AppState, SessionNotFound, SharedState};
+
+/// Whether making `child` wait for `parent` would close a loop
+fn creates_cycle(sessions: &HashMap<Uuid, Session>, child: Uuid, parent: Uuid) -> bool {
+    let mut current = Some(parent);
+    // Bounded in case the stored chain already loops
+    for _ in 0..=sessions.len() {
+        match current {
+            Some(id) if id == child => return true,
+            Some(id) => {
+                current = sessions
+                    .get(&id)
+                    .and_then(|session| session.dependency.as_ref())
+                    .map(|dependency| dependency.parent_id);
+            }
+            None => return false,
+        }
+    }
+    true
+}
+
+/// Make `session_id` start after `dependency.parent_id` finishes, or clear its
+/// dependency with None
+pub async fn set(
+    state: &SharedState,
+    event_tx: &broadcast::Sender<Event>,
+    session_id: Uuid,
+    dependency: Option<SessionDependency>,
+) -> Result<Session> {
+    let session = {
+        let mut s = state.write().await;
+        if let Some(dependency) = &dependency {
+            check(&s, session_id, dependency.parent_id)?;
+        }
+        let session = s
+            .sessions
+            .get_mut(&session_id)
+            .ok_or(SessionNotFound(session_id))?;
+        session.dependency = dependency;
+        session.clone()
+    };
+    save_state(state).await?;
+    let _ = event_tx.send(Event::SessionUpdated(session.clone()));
+    Ok(session)
+}
+
+fn check(s: &AppState, child: Uuid, parent: Uuid) -> Result<()> {
+    if !s.sessions.contains_key(&parent) {
+        return Err(SessionNotFound(parent).into());
+    }
+    if child == parent {
+        anyhow::bail!("A session can't wait for itself");
+    }
+    if creates_cycle(&s.sessions, child, parent) {
+        anyhow::bail!("The sessions would wait for each other");
+    }
+    Ok(())
+}
+
+pub struct Dependencies {
+    state: SharedState,
+    event_tx: broadcast::Sender<Event>,
+    run_queue: Arc<RunQueue>,
+    prompt_queue: Arc<PromptQueue>,
+}
+
+impl Dependencies {
+    pub fn new(
+        state: SharedState,
+        event_tx: broadcast::Sender<Event>,
+        run_queue: Arc<RunQueue>,
+        prompt_queue: Arc<PromptQueue>,
+    ) -> Self {
+        Self {
+            state,
+            event_tx,
+            run_queue,
+            prompt_queue,
+        }
+    }
+
+    /// Start children as their parents finish
+    pub async fn run(self) {
+        let mut event_rx = self.event_tx.subscribe();
+        // Sessions that errored since they last ran; they don't release children
+        let mut errored = HashSet::new();
+
+        loop {
+            let event = match event_rx.recv().await {
+                Ok(event) => event,
+                Err(broadcast::error::RecvError::Lagged(n)) => {
+                    warn!("Dependencies lagged, skipped {} events", n);
+                    continue;
+                }
+                Err(broadcast::error::RecvError::Closed) => return,
+            };
+            match event {
+                Event::StatusChanged(data) => match data.status {
+                    SessionStatus::Error => {
+                        errored.insert(data.session_id);
+                    }
+                    SessionStatus::Running => {
+                        errored.remove(&data.session_id);
+                    }
+                    SessionStatus::Idle | SessionStatus::Stopped
+                        if !errored.contains(&data.session_id) =>
+                    {
+                        self.release(data.session_id, true).await;
+                    }
+                    _ => {}
+                },
+                // Children of a deleted session would wait forever
+                Event::SessionDeleted { session_id } => {
+                    errored.remove(&session_id);
+                    self.release(session_id, false).await;
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Clear the dependencies on `parent_id`, starting the children if `start`
+    async fn release(&self, parent_id: Uuid, start: bool) {
+        let children: Vec<(Session, SessionDependency)> = {
+            let mut s = self.state.write().await;
+            s.sessions
+                .values_mut()
+                .filter(|session| {
+                    session
+                        .dependency
+                        .as_ref()
+                        .is_some_and(|dependency| dependency.parent_id == parent_id)
+                })
+                .filter_map(|session| {
+                    let dependency = session.dependency.take()?;
+                    Some((session.clone(), dependency))
+                })
+                .collect()
+        };
+        if children.is_empty() {
+            return;
+        }
+        if let Err(e) = save_state(&self.state).await {
+            warn!("Failed to save state after releasing dependencies: {}", e);
+        }
+
+        for (child, dependency) in children {
+            let _ = self.event_tx.send(Event::SessionUpdated(child.clone()));
+            if start {
+                self.start(child, dependency).await;
+            }
+        }
+    }
+
+    async fn start(&self, child: Session, dependency: SessionDependency) {
+        info!(
+            "Session {} finished, starting dependent session {}",
+            dependency.parent_id, child.id
+        );
+        history::record(
+            child.id,
+            HistoryKind::DependencyMet {
+                parent_id: dependency.parent_id,
+            },
+        );
+        let resume = child.claude_session_id.is_some();
+        if let Err(e) = self.run_queue.enqueue(child.id, None, resume).await {
+            warn!("Could not start dependent session {}: {}", child.id, e);
+            return;
+        }
+        if let Some(prompt) = dependency.prompt {
+            if let Err(e) = self
+                .prompt_queue
+                .enqueue(child.id, prompt, PromptPriority::Scheduled)
+                .await
+            {
+                warn!("Could not queue prompt for session {}: {}", child.id, e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_creates_cycle() {
+        let session = |dependency: Option<Uuid>| {
+            let mut session = Session::new("s".to_string(), PathBuf::from("/tmp"), None);
+            session.dependency = dependency.map(|parent_id| SessionDependency {
+                parent_id,
+                prompt: None,
+            });
+            session
+        };
+        let a = session(None);
+        let b = session(Some(a.id));
+        let c = session(Some(b.id));
+        let (a_id, b_id, c_id) = (a.id, b.id, c.id);
+        let sessions: HashMap<Uuid, Session> = [a, b, c].into_iter().map(|s| (s.id, s)).collect();
+
+        assert!(!creates_cycle(&sessions, Uuid::new_v4(), c_id));
+        assert!(creates_cycle(&sessions, a_id, c_id));
+        assert!(creates_cycle(&sessions, a_id, a_id));
+        // Re-pointing an existing child is fine
+        assert!(!creates_cycle(&sessions, c_id, a_id));
+        assert!(!creates_cycle(&sessions, b_id, a_id));
+    }
+}
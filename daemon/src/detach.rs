@@ -0,0 +1,168 @@
+// Detached sessions - session.detach keeps a session's claude running but stops
+// sending its output to clients; it is spooled to detached/<id>.out in the data
+// dir instead, so a closed laptop lid doesn't cost anything. session.attach
+// resumes pty:output and hands back what was missed: the spooled output, or the
+// session's screen when there's more of it than a client should replay.
+
+use anyhow::Result;
+use shared::Session;
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tracing::warn;
+use uuid::Uuid;
+
+/// Spooled output session.attach returns as is; past this it sends the screen
+pub const MAX_BACKFILL_BYTES: u64 = 4 * 1024 * 1024;
+
+/// What a client needs to catch up on attach
+#[derive(Debug, PartialEq)]
+pub enum Backfill {
+    /// Everything the session wrote while detached
+    Output(Vec<u8>),
+    /// Too much (or unreadable): redraw from the session's screen instead
+    Screen,
+}
+
+struct Spool {
+    file: File,
+    bytes: u64,
+}
+
+/// Output spools of detached sessions
+pub struct Detached {
+    dir: PathBuf,
+    spools: Mutex<HashMap<Uuid, Spool>>,
+}
+
+impl Detached {
+    pub fn new(dir: PathBuf) -> Self {
+        Self {
+            dir,
+            spools: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Keep spooling for sessions that were detached when the daemon stopped
+    pub fn load<'a>(dir: PathBuf, sessions: impl Iterator<Item = &'a Session>) -> Self {
+        let detached = Self::new(dir);
+        for session in sessions.filter(|session| session.detached) {
+            if let Err(e) = detached.open(session.id, false) {
+                warn!("Failed to reopen spool of session {}: {}", session.id, e);
+            }
+        }
+        detached
+    }
+
+    /// Start spooling the session's output; false if it already is detached
+    pub fn detach(&self, session_id: Uuid) -> Result<bool> {
+        if self.is_detached(session_id) {
+            return Ok(false);
+        }
+        self.open(session_id, true)?;
+        Ok(true)
+    }
+
+    fn spool_path(&self, session_id: Uuid) -> PathBuf {
+        self.dir.join(format!("{}.out", session_id))
+    }
+
+    fn open(&self, session_id: Uuid, truncate: bool) -> Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        let file = OpenOptions::new()
+            .create(true)
+            .append(!truncate)
+            .write(true)
+            .truncate(truncate)
+            .open(self.spool_path(session_id))?;
+        let bytes = file.metadata()?.len();
+        self.spools
+            .lock()
+            .unwrap()
+            .insert(session_id, Spool { file, bytes });
+        Ok(())
+    }
+
+    pub fn is_detached(&self, session_id: Uuid) -> bool {
+        self.spools.lock().unwrap().contains_key(&session_id)
+    }
+
+    /// Spool output if the session is detached; false means send it to clients
+    pub fn spool(&self, session_id: Uuid, data: &[u8]) -> bool {
+        let mut spools = self.spools.lock().unwrap();
+        let Some(spool) = spools.get_mut(&session_id) else {
+            return false;
+        };
+        match spool.file.write_all(data) {
+            Ok(()) => spool.bytes += data.len() as u64,
+            Err(e) => warn!("Failed to spool output of session {}: {}", session_id, e),
+        }
+        true
+    }
+
+    /// Stop spooling; None if the session wasn't detached
+    /// Output after this goes to clients again, so nothing falls in between.
+    pub fn attach(&self, session_id: Uuid) -> Option<Backfill> {
+        let spool = self.spools.lock().unwrap().remove(&session_id)?;
+        let path = self.spool_path(session_id);
+        let backfill = if spool.bytes > MAX_BACKFILL_BYTES {
+            Backfill::Screen
+        } else {
+            match read_spool(&path) {
+                Ok(data) => Backfill::Output(data),
+                Err(e) => {
+                    warn!("Failed to read spool of session {}: {}", session_id, e);
+                    Backfill::Screen
+                }
+            }
+        };
+        let _ = fs::remove_file(&path);
+        Some(backfill)
+    }
+
+    /// Drop the session's spool, e.g. when it is deleted
+    pub fn remove(&self, session_id: Uuid) {
+        if self.spools.lock().unwrap().remove(&session_id).is_some() {
+            let _ = fs::remove_file(self.spool_path(session_id));
+        }
+    }
+}
+
+fn read_spool(path: &Path) -> Result<Vec<u8>> {
+    let mut data = Vec::new();
+    File::open(path)?.read_to_end(&mut data)?;
+    Ok(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detach_and_attach() {
+        let id = Uuid::new_v4();
+        let dir = std::env::temp_dir().join(format!("detach-test-{}", Uuid::new_v4()));
+        let detached = Detached::new(dir.clone());
+        assert!(!detached.spool(id, b"live"));
+        assert_eq!(detached.attach(id), None);
+
+        assert!(detached.detach(id).unwrap());
+        assert!(!detached.detach(id).unwrap());
+        assert!(detached.spool(id, b"while "));
+        assert!(detached.spool(id, b"away"));
+        assert_eq!(
+            detached.attach(id),
+            Some(Backfill::Output(b"while away".to_vec()))
+        );
+        assert!(!detached.is_detached(id));
+        assert!(!detached.spool_path(id).exists());
+
+        // Too much to replay: the caller sends the screen instead
+        detached.detach(id).unwrap();
+        detached.spool(id, &vec![b'x'; MAX_BACKFILL_BYTES as usize + 1]);
+        assert_eq!(detached.attach(id), Some(Backfill::Screen));
+        let _ = fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,121 @@
+// Network egress gate - every daemon subsystem that can reach the network asks
+// here before connecting, so daemon.local_only switches them all off in one place
+// daemon.network_audit lists the same subsystems and whether each may connect
+
+use anyhow::Result;
+use shared::{EgressSubsystem, NetworkAuditResult};
+
+use crate::bootstrap::INSTALL_COMMAND;
+use crate::config::Config;
+
+/// Outbound integrations owned by the daemon
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Egress {
+    /// GitHub release lookups by the update checker
+    UpdateCheck,
+    /// `npm install` of claude during daemon.bootstrap
+    ClaudeInstall,
+    /// Summaries POSTed by group completion watches with a webhook_url
+    CompletionWebhook,
+    /// Permission dialogs posted to approval_webhook.url
+    ApprovalWebhook,
+}
+
+impl Egress {
+    fn name(&self) -> &'static str {
+        match self {
+            Egress::UpdateCheck => "update_check",
+            Egress::ClaudeInstall => "claude_install",
+            Egress::CompletionWebhook => "completion_webhook",
+            Egress::ApprovalWebhook => "approval_webhook",
+        }
+    }
+}
+
+/// Fail if `egress` may not open connections under the current config
+pub fn check(config: &Config, egress: Egress) -> Result<()> {
+    if config.daemon.local_only {
+        anyhow::bail!("{} is blocked by daemon.local_only", egress.name());
+    }
+    Ok(())
+}
+
+/// Every egress-capable subsystem and whether it may currently connect
+pub fn audit(config: &Config) -> NetworkAuditResult {
+    let local_only = config.daemon.local_only;
+    let gated = |egress: Egress, description: &str, destination: &str, enabled: bool| {
+        let allowed = enabled && check(config, egress).is_ok();
+        EgressSubsystem {
+            name: egress.name().to_string(),
+            description: description.to_string(),
+            destination: destination.to_string(),
+            enabled,
+            gated: true,
+            allowed,
+        }
+    };
+
+    let subsystems = vec![
+        gated(
+            Egress::UpdateCheck,
+            "Periodic check for new daemon releases ([updates] enabled)",
+            &config.updates.release_url,
+            config.updates.enabled,
+        ),
+        gated(
+            Egress::ClaudeInstall,
+            "Installs claude from npm when daemon.bootstrap is called with install_claude",
+            INSTALL_COMMAND,
+            true,
+        ),
+        gated(
+            Egress::CompletionWebhook,
+            "Posts a summary when every session in a group with a completion watch finishes",
+            "webhook_url of group completion watches",
+            true,
+        ),
+        gated(
+            Egress::ApprovalWebhook,
+            "Posts permission dialogs with approve/deny links ([approval_webhook] url)",
+            config.approval_webhook.url.as_deref().unwrap_or_default(),
+            config.approval_webhook.url.is_some(),
+        ),
+        // The daemon only spawns claude; its API traffic is not ours to block
+        EgressSubsystem {
+            name: "claude_sessions".to_string(),
+            description: "Sessions run the claude CLI, which talks to the Anthropic API"
+                .to_string(),
+            destination: "claude CLI".to_string(),
+            enabled: true,
+            gated: false,
+            allowed: true,
+        },
+    ];
+
+    NetworkAuditResult {
+        local_only,
+        subsystems,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_local_only_blocks_gated_subsystems() {
+        let mut config = Config::default();
+        config.updates.enabled = true;
+        assert!(check(&config, Egress::UpdateCheck).is_ok());
+
+        config.daemon.local_only = true;
+        assert!(check(&config, Egress::UpdateCheck).is_err());
+        assert!(check(&config, Egress::ClaudeInstall).is_err());
+
+        let audit = audit(&config);
+        assert!(audit.local_only);
+        for subsystem in &audit.subsystems {
+            assert_eq!(subsystem.allowed, !subsystem.gated, "{}", subsystem.name);
+        }
+    }
+}
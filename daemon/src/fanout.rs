@@ -0,0 +1,382 @@
+// Event fan-out - one task reads the event broadcast and copies each event into
+// a bounded queue per client connection, so a slow client only ever backs up
+// its own queue. Consecutive pty:output chunks of a session are merged while
+// they wait. When a queue is full, output is dropped and the session gets a
+// pty:resync marker before its next output, so the client knows its terminal
+// missed data instead of rendering it corrupted. Other events are never dropped:
+// a client too far behind for those is disconnected (and catches up on reconnect
+// with events.since). Events are numbered by the journal as they come through.
+// If this task itself falls behind the broadcast, the events it missed reach
+// neither the clients nor the journal: the journal records a gap and every
+// client is disconnected, so each reloads what it holds instead of carrying on
+// with a hole it can't see.
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use shared::{Event, PtyOutputData, SequencedEvent};
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex, Weak};
+use tokio::sync::{broadcast, Notify};
+use tracing::warn;
+use uuid::Uuid;
+
+use crate::flow::FlowControl;
+use crate::journal::EventJournal;
+
+/// Encoded pty:output a client may have queued before output is dropped
+const MAX_QUEUED_OUTPUT_BYTES: usize = 4 * 1024 * 1024;
+/// Queued events (merged output counts once) before the client is disconnected
+const MAX_QUEUED_EVENTS: usize = 4096;
+
+/// Copies every event to the queue of each connected client
+pub struct EventFanout {
+    clients: Mutex<Vec<Weak<ClientQueue>>>,
+    flow: Arc<FlowControl>,
+    journal: Arc<EventJournal>,
+}
+
+impl EventFanout {
+    pub fn new(flow: Arc<FlowControl>, journal: Arc<EventJournal>) -> Self {
+        Self {
+            clients: Mutex::new(Vec::new()),
+            flow,
+            journal,
+        }
+    }
+
+    /// Queue for a new connection; it stops receiving events once dropped
+    pub fn register(&self) -> Arc<ClientQueue> {
+        let queue = Arc::new(ClientQueue::default());
+        self.clients.lock().unwrap().push(Arc::downgrade(&queue));
+        queue
+    }
+
+    pub async fn run(self: Arc<Self>, event_tx: broadcast::Sender<Event>) {
+        let mut event_rx = event_tx.subscribe();
+        loop {
+            match event_rx.recv().await {
+                Ok(event) => self.publish(event),
+                // Only queue pushes happen here, so this takes a flood of events
+                Err(broadcast::error::RecvError::Lagged(n)) => {
+                    warn!(
+                        "Event fan-out lagged, missed {} events; disconnecting clients",
+                        n
+                    );
+                    self.flow.client_lagged(n);
+                    self.journal.mark_gap();
+                    for queue in self.queues() {
+                        queue.disconnect();
+                    }
+                }
+                Err(broadcast::error::RecvError::Closed) => return,
+            }
+        }
+    }
+
+    fn publish(&self, event: Event) {
+        let seq = self.journal.record(&event);
+        for queue in self.queues() {
+            let dropped = queue.push(event.clone(), seq);
+            if dropped > 0 {
+                self.flow.client_lagged(dropped);
+            }
+        }
+    }
+
+    /// Live queues, forgetting those of closed connections
+    fn queues(&self) -> Vec<Arc<ClientQueue>> {
+        let mut clients = self.clients.lock().unwrap();
+        clients.retain(|client| client.strong_count() > 0);
+        clients.iter().filter_map(Weak::upgrade).collect()
+    }
+}
+
+/// Events waiting to be written to one client
+#[derive(Default)]
+pub struct ClientQueue {
+    state: Mutex<QueueState>,
+    notify: Notify,
+}
+
+#[derive(Default)]
+struct QueueState {
+    items: VecDeque<Item>,
+    output_bytes: usize,
+    /// Sessions whose output was dropped, in the order it happened
+    resync: Vec<Uuid>,
+    /// Missed events that can't be dropped
+    overflowed: bool,
+}
+
+enum Item {
+    Event(Box<SequencedEvent>),
+    /// Consecutive base64 pty:output chunks of one session
+    Output {
+        session_id: Uuid,
+        chunks: Vec<String>,
+    },
+}
+
+impl ClientQueue {
+    /// Next event to send, or None once the client has to be disconnected
+    pub async fn next(&self) -> Option<SequencedEvent> {
+        loop {
+            {
+                let mut state = self.state.lock().unwrap();
+                if state.overflowed {
+                    return None;
+                }
+                if let Some(event) = state.pop() {
+                    return Some(event);
+                }
+            }
+            self.notify.notified().await;
+        }
+    }
+
+    /// Queue an event, returning how many events had to be dropped for it
+    fn push(&self, event: Event, seq: Option<u64>) -> u64 {
+        let dropped = self.state.lock().unwrap().push(event, seq);
+        self.notify.notify_one();
+        dropped
+    }
+
+    /// Have the client disconnected once it next waits for an event
+    fn disconnect(&self) {
+        self.state.lock().unwrap().overflowed = true;
+        self.notify.notify_one();
+    }
+}
+
+impl QueueState {
+    fn push(&mut self, event: Event, seq: Option<u64>) -> u64 {
+        let Event::PtyOutput(data) = event else {
+            let mut dropped = 0;
+            // Make room by dropping output rather than events the client needs
+            while self.items.len() >= MAX_QUEUED_EVENTS {
+                if !self.drop_oldest_output() {
+                    self.overflowed = true;
+                    return 0;
+                }
+                dropped += 1;
+            }
+            self.items
+                .push_back(Item::Event(Box::new(SequencedEvent { seq, event })));
+            return dropped;
+        };
+
+        let session_id = data.session_id;
+        let len = data.output.len();
+        if self.output_bytes + len > MAX_QUEUED_OUTPUT_BYTES
+            || self.items.len() >= MAX_QUEUED_EVENTS
+        {
+            self.mark_resync(session_id);
+            return 1;
+        }
+
+        // The marker goes right before the output that follows the gap
+        if let Some(i) = self.resync.iter().position(|id| *id == session_id) {
+            self.resync.remove(i);
+            let marker = Event::PtyResync { session_id };
+            self.items.push_back(Item::Event(Box::new(marker.into())));
+        }
+        self.output_bytes += len;
+        match self.items.back_mut() {
+            Some(Item::Output {
+                session_id: last,
+                chunks,
+            }) if *last == session_id => chunks.push(data.output),
+            _ => self.items.push_back(Item::Output {
+                session_id,
+                chunks: vec![data.output],
+            }),
+        }
+        0
+    }
+
+    fn pop(&mut self) -> Option<SequencedEvent> {
+        let event = match self.items.pop_front() {
+            Some(Item::Event(event)) => return Some(*event),
+            Some(Item::Output { session_id, chunks }) => {
+                self.output_bytes -= chunks.iter().map(String::len).sum::<usize>();
+                Event::PtyOutput(PtyOutputData {
+                    session_id,
+                    output: merge_chunks(chunks),
+                })
+            }
+            // Sessions whose output stopped after a gap still get their marker
+            None if !self.resync.is_empty() => Event::PtyResync {
+                session_id: self.resync.remove(0),
+            },
+            None => return None,
+        };
+        Some(event.into())
+    }
+
+    /// Drop the oldest queued output; false if there is none
+    fn drop_oldest_output(&mut self) -> bool {
+        let Some(i) = self
+            .items
+            .iter()
+            .position(|item| matches!(item, Item::Output { .. }))
+        else {
+            return false;
+        };
+        if let Some(Item::Output { session_id, chunks }) = self.items.remove(i) {
+            self.output_bytes -= chunks.iter().map(String::len).sum::<usize>();
+            self.mark_resync(session_id);
+        }
+        true
+    }
+
+    /// Drop the session's queued output too, since the client resets its terminal
+    fn mark_resync(&mut self, session_id: Uuid) {
+        let mut freed = 0;
+        self.items.retain(|item| match item {
+            Item::Output {
+                session_id: id,
+                chunks,
+            } if *id == session_id => {
+                freed += chunks.iter().map(String::len).sum::<usize>();
+                false
+            }
+            _ => true,
+        });
+        self.output_bytes -= freed;
+        if !self.resync.contains(&session_id) {
+            self.resync.push(session_id);
+        }
+    }
+}
+
+/// One base64 string with the bytes of all `chunks`
+fn merge_chunks(mut chunks: Vec<String>) -> String {
+    if chunks.len() == 1 {
+        return chunks.pop().unwrap_or_default();
+    }
+    let mut raw = Vec::new();
+    for chunk in &chunks {
+        match BASE64.decode(chunk) {
+            Ok(bytes) => raw.extend(bytes),
+            Err(e) => warn!("Skipping undecodable output chunk: {}", e),
+        }
+    }
+    BASE64.encode(raw)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn output(session_id: Uuid, data: &[u8]) -> Event {
+        Event::PtyOutput(PtyOutputData {
+            session_id,
+            output: BASE64.encode(data),
+        })
+    }
+
+    impl QueueState {
+        fn pop_event(&mut self) -> Option<Event> {
+            self.pop().map(|sequenced| sequenced.event)
+        }
+    }
+
+    fn decoded(event: Option<Event>) -> (Uuid, Vec<u8>) {
+        match event {
+            Some(Event::PtyOutput(data)) => (data.session_id, BASE64.decode(data.output).unwrap()),
+            other => panic!("expected pty:output, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_coalesce_output() {
+        let (a, b) = (Uuid::new_v4(), Uuid::new_v4());
+        let mut queue = QueueState::default();
+        queue.push(output(a, b"he"), None);
+        queue.push(output(a, b"llo"), None);
+        queue.push(output(b, b"x"), None);
+        queue.push(output(a, b"!"), None);
+
+        assert_eq!(decoded(queue.pop_event()), (a, b"hello".to_vec()));
+        assert_eq!(decoded(queue.pop_event()), (b, b"x".to_vec()));
+        assert_eq!(decoded(queue.pop_event()), (a, b"!".to_vec()));
+        assert!(queue.pop_event().is_none());
+        assert_eq!(queue.output_bytes, 0);
+    }
+
+    #[test]
+    fn test_drop_and_resync() {
+        let (a, b) = (Uuid::new_v4(), Uuid::new_v4());
+        let mut queue = QueueState::default();
+        let big = vec![b'x'; MAX_QUEUED_OUTPUT_BYTES / 4 * 3 - 6];
+        queue.push(output(b, b"kept"), None);
+        queue.push(output(a, &big), None);
+        // Over the byte budget: dropped along with a's queued output
+        assert_eq!(queue.push(output(a, b"lost"), None), 1);
+        queue.push(Event::SessionDeleted { session_id: b }, Some(5));
+        queue.push(output(a, b"after"), None);
+
+        assert_eq!(decoded(queue.pop_event()), (b, b"kept".to_vec()));
+        let deleted = queue.pop().unwrap();
+        assert!(matches!(deleted.event, Event::SessionDeleted { .. }));
+        assert_eq!(deleted.seq, Some(5));
+        assert!(
+            matches!(queue.pop_event(), Some(Event::PtyResync { session_id }) if session_id == a)
+        );
+        assert_eq!(decoded(queue.pop_event()), (a, b"after".to_vec()));
+        assert!(queue.pop_event().is_none());
+
+        // A marker without output to follow is still delivered
+        queue.mark_resync(b);
+        assert!(
+            matches!(queue.pop_event(), Some(Event::PtyResync { session_id }) if session_id == b)
+        );
+    }
+
+    #[test]
+    fn test_overflow_disconnects() {
+        let a = Uuid::new_v4();
+        let mut queue = QueueState::default();
+        queue.push(output(a, b"x"), None);
+        for _ in 0..MAX_QUEUED_EVENTS - 1 {
+            queue.push(Event::SessionDeleted { session_id: a }, None);
+        }
+        // Room is made by dropping the output first
+        assert_eq!(queue.push(Event::SessionDeleted { session_id: a }, None), 1);
+        assert!(!queue.overflowed);
+        queue.push(Event::SessionDeleted { session_id: a }, None);
+        assert!(queue.overflowed);
+    }
+
+    #[tokio::test]
+    async fn test_lag_disconnects_and_breaks_the_journal() {
+        let dir = std::env::temp_dir().join(format!("cm-fanout-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let journal = Arc::new(EventJournal::open(dir.join("events.jsonl")));
+        let fanout = Arc::new(EventFanout::new(
+            Arc::new(FlowControl::new(1024)),
+            journal.clone(),
+        ));
+        let queue = fanout.register();
+        let (event_tx, _) = broadcast::channel(4);
+        tokio::spawn(fanout.run(event_tx.clone()));
+        // Let it subscribe, then send more than the channel holds
+        tokio::task::yield_now().await;
+        let deleted = || Event::SessionDeleted {
+            session_id: Uuid::new_v4(),
+        };
+        event_tx.send(deleted()).unwrap();
+        tokio::task::yield_now().await;
+        assert_eq!(queue.next().await.and_then(|e| e.seq), Some(1));
+        for _ in 0..10 {
+            event_tx.send(deleted()).unwrap();
+        }
+
+        assert!(queue.next().await.is_none());
+        let result = journal.since(1);
+        assert!(!result.complete);
+        assert_eq!(result.events.len(), 4);
+        assert!(journal.since(result.latest - 4).complete);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
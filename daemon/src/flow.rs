@@ -0,0 +1,175 @@
+// PTY flow control - PTY output goes through a queue sized in bytes; when it is
+// full the reader thread stops reading, so the kernel PTY buffer fills and the
+// child blocks on write instead of the daemon buffering a runaway command's output
+// Pauses and client lag are counted here and reported by daemon.stats
+
+use shared::SessionFlowStats;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use uuid::Uuid;
+
+/// Largest chunk a PTY reader sends in one message
+pub const READ_CHUNK_BYTES: usize = 4096;
+
+/// Counters for one session's PTY reader
+#[derive(Default)]
+pub struct SessionFlow {
+    bytes_read: AtomicU64,
+    pauses: AtomicU64,
+    paused_ms: AtomicU64,
+    paused: AtomicBool,
+}
+
+impl SessionFlow {
+    pub fn read(&self, bytes: usize) {
+        self.bytes_read.fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+
+    /// The output queue was full; the reader is blocked until it drains
+    pub fn pause_started(&self) {
+        self.pauses.fetch_add(1, Ordering::Relaxed);
+        self.paused.store(true, Ordering::Relaxed);
+    }
+
+    pub fn pause_ended(&self, paused_for: Duration) {
+        self.paused_ms
+            .fetch_add(paused_for.as_millis() as u64, Ordering::Relaxed);
+        self.paused.store(false, Ordering::Relaxed);
+    }
+
+    /// The reader is blocked on a full output queue
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::Relaxed)
+    }
+}
+
+pub struct FlowControl {
+    queue_capacity: usize,
+    sessions: Mutex<HashMap<Uuid, Arc<SessionFlow>>>,
+    clients: AtomicUsize,
+    client_lags: AtomicU64,
+    events_dropped: AtomicU64,
+    /// Output read by sessions that have since been removed, for daemon.metrics
+    removed_bytes_read: AtomicU64,
+}
+
+impl FlowControl {
+    /// Flow control for an output queue holding about `queue_kb` of output
+    pub fn new(queue_kb: usize) -> Self {
+        Self {
+            queue_capacity: (queue_kb * 1024 / READ_CHUNK_BYTES).max(1),
+            sessions: Mutex::new(HashMap::new()),
+            clients: AtomicUsize::new(0),
+            client_lags: AtomicU64::new(0),
+            events_dropped: AtomicU64::new(0),
+            removed_bytes_read: AtomicU64::new(0),
+        }
+    }
+
+    /// Capacity of the PTY output channel, in chunks
+    pub fn queue_capacity(&self) -> usize {
+        self.queue_capacity
+    }
+
+    /// Counters for a session's reader (kept across restarts of the session)
+    pub fn session(&self, session_id: Uuid) -> Arc<SessionFlow> {
+        self.sessions
+            .lock()
+            .unwrap()
+            .entry(session_id)
+            .or_default()
+            .clone()
+    }
+
+    pub fn remove_session(&self, session_id: Uuid) {
+        if let Some(flow) = self.sessions.lock().unwrap().remove(&session_id) {
+            self.removed_bytes_read
+                .fetch_add(flow.bytes_read.load(Ordering::Relaxed), Ordering::Relaxed);
+        }
+    }
+
+    /// PTY output read by every session since the daemon started
+    pub fn total_bytes_read(&self) -> u64 {
+        let live: u64 = self
+            .sessions
+            .lock()
+            .unwrap()
+            .values()
+            .map(|flow| flow.bytes_read.load(Ordering::Relaxed))
+            .sum();
+        live + self.removed_bytes_read.load(Ordering::Relaxed)
+    }
+
+    pub fn client_connected(&self) {
+        self.clients.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn client_disconnected(&self) {
+        self.clients.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    /// A client fell behind the event channel and missed `missed` events
+    pub fn client_lagged(&self, missed: u64) {
+        self.client_lags.fetch_add(1, Ordering::Relaxed);
+        self.events_dropped.fetch_add(missed, Ordering::Relaxed);
+    }
+
+    pub fn clients(&self) -> usize {
+        self.clients.load(Ordering::Relaxed)
+    }
+
+    /// (times a client lagged, events clients missed)
+    pub fn client_lag(&self) -> (u64, u64) {
+        (
+            self.client_lags.load(Ordering::Relaxed),
+            self.events_dropped.load(Ordering::Relaxed),
+        )
+    }
+
+    pub fn session_stats(&self) -> Vec<SessionFlowStats> {
+        self.sessions
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(session_id, flow)| SessionFlowStats {
+                session_id: *session_id,
+                bytes_read: flow.bytes_read.load(Ordering::Relaxed),
+                pauses: flow.pauses.load(Ordering::Relaxed),
+                paused_ms: flow.paused_ms.load(Ordering::Relaxed),
+                paused: flow.paused.load(Ordering::Relaxed),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_queue_capacity_and_pauses() {
+        assert_eq!(FlowControl::new(4096).queue_capacity(), 1024);
+        // Never zero, or the channel couldn't be created
+        assert_eq!(FlowControl::new(0).queue_capacity(), 1);
+
+        let flow = FlowControl::new(64);
+        let id = Uuid::new_v4();
+        let session = flow.session(id);
+        session.read(100);
+        session.pause_started();
+        assert!(flow.session_stats()[0].paused);
+        session.pause_ended(Duration::from_millis(250));
+
+        let stats = &flow.session_stats()[0];
+        assert_eq!(stats.bytes_read, 100);
+        assert_eq!(stats.pauses, 1);
+        assert_eq!(stats.paused_ms, 250);
+        assert!(!stats.paused);
+
+        flow.session(Uuid::new_v4()).read(50);
+        flow.remove_session(id);
+        assert_eq!(flow.total_bytes_read(), 150);
+    }
+}
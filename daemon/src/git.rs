@@ -0,0 +1,101 @@
+// Git helpers - runs git commands and summarizes repository state for sessions
+
+use anyhow::{Context, Result};
+use shared::GitStatus;
+use std::path::{Path, PathBuf};
+use tokio::process::Command;
+
+/// Run a git command in `dir`, returning trimmed stdout on success
+pub async fn run(dir: &Path, args: &[&str]) -> Result<String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .output()
+        .await
+        .context("Failed to run git")?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("git {} failed: {}", args.join(" "), stderr.trim());
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Root of the repository containing `dir`, or None if it isn't inside one
+pub async fn toplevel(dir: &Path) -> Option<PathBuf> {
+    run(dir, &["rev-parse", "--show-toplevel"])
+        .await
+        .ok()
+        .map(PathBuf::from)
+}
+
+/// Get the git status of `dir`, or None if it isn't inside a git repository
+pub async fn status(dir: &Path) -> Option<GitStatus> {
+    if !dir.exists() {
+        return None;
+    }
+    run(dir, &["status", "--porcelain=v2", "--branch"])
+        .await
+        .ok()
+        .map(|out| parse_status(&out))
+}
+
+/// Parse `git status --porcelain=v2 --branch` output
+fn parse_status(output: &str) -> GitStatus {
+    let mut status = GitStatus::default();
+
+    for line in output.lines() {
+        if let Some(head) = line.strip_prefix("# branch.head ") {
+            // "(detached)" when HEAD isn't on a branch
+            if head != "(detached)" {
+                status.branch = Some(head.to_string());
+            }
+        } else if let Some(ab) = line.strip_prefix("# branch.ab ") {
+            for part in ab.split_whitespace() {
+                if let Some(n) = part.strip_prefix('+') {
+                    status.ahead = n.parse().unwrap_or(0);
+                } else if let Some(n) = part.strip_prefix('-') {
+                    status.behind = n.parse().unwrap_or(0);
+                }
+            }
+        } else if !line.starts_with('#') && !line.is_empty() {
+            // Changed (1), renamed (2), unmerged (u) or untracked (?) entry
+            status.dirty += 1;
+        }
+    }
+
+    status
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_status_clean() {
+        let out = "# branch.oid abc123\n# branch.head main\n# branch.upstream origin/main\n# branch.ab +0 -0";
+        let status = parse_status(out);
+        assert_eq!(status.branch.as_deref(), Some("main"));
+        assert_eq!(status.dirty, 0);
+        assert_eq!(status.ahead, 0);
+        assert_eq!(status.behind, 0);
+    }
+
+    #[test]
+    fn test_parse_status_dirty_and_diverged() {
+        let out = "# branch.oid abc123\n# branch.head feature\n# branch.ab +2 -3\n1 .M N... 100644 100644 100644 a b src/lib.rs\n? new.txt";
+        let status = parse_status(out);
+        assert_eq!(status.branch.as_deref(), Some("feature"));
+        assert_eq!(status.dirty, 2);
+        assert_eq!(status.ahead, 2);
+        assert_eq!(status.behind, 3);
+    }
+
+    #[test]
+    fn test_parse_status_detached() {
+        let status = parse_status("# branch.oid abc123\n# branch.head (detached)");
+        assert_eq!(status.branch, None);
+    }
+}
@@ -0,0 +1,137 @@
+// Group status - each group gets the most urgent status among the sessions in
+// it and its subgroups, plus how many are in each status. group.list includes
+// them, and group:status_changed is emitted whenever one changes, so a client
+// can badge a collapsed group without tracking its sessions itself.
+
+use shared::{Event, GroupStatus, SessionStatus};
+use std::collections::HashMap;
+use tokio::sync::broadcast;
+use tracing::warn;
+use uuid::Uuid;
+
+use crate::state::{AppState, SharedState};
+
+/// Higher is more urgent
+fn urgency(status: SessionStatus) -> u8 {
+    match status {
+        SessionStatus::Error => 6,
+        SessionStatus::Waiting => 5,
+        SessionStatus::Running => 4,
+        SessionStatus::Starting => 3,
+        SessionStatus::Queued => 2,
+        SessionStatus::Idle => 1,
+        SessionStatus::Stopped => 0,
+    }
+}
+
+/// The status of every group
+pub fn group_statuses(s: &AppState) -> HashMap<Uuid, GroupStatus> {
+    let mut statuses: HashMap<Uuid, GroupStatus> = s
+        .groups
+        .keys()
+        .map(|&group_id| {
+            let status = GroupStatus {
+                group_id,
+                status: SessionStatus::Stopped,
+                total: 0,
+                counts: HashMap::new(),
+            };
+            (group_id, status)
+        })
+        .collect();
+
+    for session in s.sessions.values() {
+        let mut group_id = session.group_id;
+        // Bounded in case of a parent cycle
+        for _ in 0..s.groups.len() {
+            let Some(status) = group_id.and_then(|id| statuses.get_mut(&id)) else {
+                break;
+            };
+            status.total += 1;
+            *status.counts.entry(session.status).or_default() += 1;
+            if urgency(session.status) > urgency(status.status) {
+                status.status = session.status;
+            }
+            group_id = s.groups.get(&status.group_id).and_then(|g| g.parent_id);
+        }
+    }
+    statuses
+}
+
+/// Emit group:status_changed as sessions change status or move between groups
+pub async fn run(state: SharedState, event_tx: broadcast::Sender<Event>) {
+    let mut event_rx = event_tx.subscribe();
+    let mut last = group_statuses(&*state.read().await);
+
+    loop {
+        match event_rx.recv().await {
+            Ok(
+                Event::StatusChanged(_)
+                | Event::SessionCreated(_)
+                | Event::SessionUpdated(_)
+                | Event::SessionDeleted { .. }
+                | Event::SessionMoved(_)
+                | Event::GroupCreated(_)
+                | Event::GroupUpdated(_)
+                | Event::GroupDeleted { .. },
+            ) => {}
+            Ok(_) => continue,
+            Err(broadcast::error::RecvError::Lagged(n)) => {
+                warn!("Group status lagged, skipped {} events", n);
+            }
+            Err(broadcast::error::RecvError::Closed) => return,
+        }
+
+        let current = group_statuses(&*state.read().await);
+        for (group_id, status) in &current {
+            if last.get(group_id) != Some(status) {
+                let _ = event_tx.send(Event::GroupStatusChanged(status.clone()));
+            }
+        }
+        last = current;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shared::{Group, Session};
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_group_statuses() {
+        let mut s = AppState::default();
+        let parent = Group::new("parent".to_string(), None);
+        let child = Group::new("child".to_string(), Some(parent.id));
+        let empty = Group::new("empty".to_string(), None);
+        for (group_id, status) in [
+            (parent.id, SessionStatus::Idle),
+            (child.id, SessionStatus::Waiting),
+            (child.id, SessionStatus::Running),
+            (child.id, SessionStatus::Running),
+        ] {
+            let mut session = Session::new("s".into(), PathBuf::from("/"), Some(group_id));
+            session.status = status;
+            s.sessions.insert(session.id, session);
+        }
+        for group in [parent.clone(), child.clone(), empty.clone()] {
+            s.groups.insert(group.id, group);
+        }
+
+        let statuses = group_statuses(&s);
+        let child_status = &statuses[&child.id];
+        assert_eq!(child_status.status, SessionStatus::Waiting);
+        assert_eq!(child_status.total, 3);
+        assert_eq!(child_status.counts[&SessionStatus::Running], 2);
+        assert!(!child_status.counts.contains_key(&SessionStatus::Idle));
+
+        // Subgroups count towards their parents
+        let parent_status = &statuses[&parent.id];
+        assert_eq!(parent_status.status, SessionStatus::Waiting);
+        assert_eq!(parent_status.total, 4);
+        assert_eq!(parent_status.counts[&SessionStatus::Idle], 1);
+
+        assert_eq!(statuses[&empty.id].status, SessionStatus::Stopped);
+        assert_eq!(statuses[&empty.id].total, 0);
+    }
+}
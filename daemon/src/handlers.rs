@@ -0,0 +1,2323 @@
+// IPC method handlers - one `RpcHandler` per method, routed by `router()`
+// daemon.auth, debug.tap, events.subscribe, client.identify and session.view
+// change or read the calling connection, so ipc.rs handles those five itself.
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use shared::{
+    ApprovalParams, ApprovalPolicy, ApprovalResolvedData, ArchivedSession, BootstrapParams,
+    BootstrapStartedResult, CancelledResult, ClaudeBinaryParams, ClaudeCapabilities, ClaudeInfo,
+    ClientsResult, CreateGroupParams, CreateScheduleParams, CreateSessionParams,
+    CreateSnippetParams, CreateWorktreeSessionParams, DaemonLogsTailParams, DaemonLogsTailResult,
+    DaemonMetrics, DaemonStats, DeleteGroupParams, DeleteSessionParams, DiagnosticsResult,
+    EnqueuePromptParams, EnqueueStartParams, ErrorCode, Event, EventsSinceParams,
+    EventsSinceResult, ExportOutputParams, ExportOutputResult, ForkSessionParams, Group,
+    GroupCreatedResult, GroupDeleteMode, GroupDeleteResult, GroupListResult, GroupReorder,
+    HelloResult, HistoryEntry, HistoryKind, HistoryListParams, InputHistoryEntry,
+    InputHistoryParams, KeepAwakeParams, KeepAwakeStatus, Key, McpAddParams, McpListParams,
+    McpListResult, McpServerParams, McpSetEnabledParams, MetricsFormat, MetricsParams,
+    MoveToGroupParams, NetworkAuditResult, NotificationSettings, OrphanInfo, PolicySetParams,
+    QueueCancelParams, QueueListParams, QueuedPrompt, RecordingExportParams, RecordingInfo,
+    RecordingListParams, ReplayCancelParams, ReplayStartedResult, RunTaskParams,
+    ScanProjectsParams, ScanProjectsResult, Schedule, ScheduleIdParams, ScheduleListResult,
+    SearchOutputParams, SearchOutputResult, SearchQueryParams, SearchQueryResult, SendKeyParams,
+    SendSnippetParams, SendSnippetResult, Session, SessionAttachResult, SessionContinueParams,
+    SessionCreatedResult, SessionDependency, SessionHistoryParams, SessionIdParams,
+    SessionInputParams, SessionInputResult, SessionListParams, SessionListResult, SessionReorder,
+    SessionReplayParams, SessionResizeParams, SessionRestartParams, SessionScreen,
+    SessionStatsParams, SessionStatsResult, SessionStatus, SessionTagParams,
+    SetCompletionWatchParams, SetDependencyParams, SetMuteParams, ShutdownParams, Snippet,
+    SnippetIdParams, SnippetListResult, StateExportParams, StateExportResult, StateImportParams,
+    StateImportResult, StateSyncParams, StateSyncResult, StatusResult, StopSessionParams,
+    StopSessionResult, SuccessResult, SummaryResult, TailLogParams, TailLogResult, TaskInfo,
+    TaskListResult, TerminalSize, ThemeResult, TranscriptParams, TranscriptResult, TreeSnapshot,
+    TurnStats, TurnStatsParams, UpdateGroupParams, UpdateSessionParams, UpdateSnippetParams,
+    WatchParams, WatchesResult, CAPABILITIES, METHODS, PROTOCOL_VERSION,
+};
+use std::path::{Path, PathBuf};
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+use crate::approvals;
+use crate::archive;
+use crate::backup;
+use crate::bootstrap;
+use crate::claude;
+use crate::claude_resolver::{ClaudeResolver, UnknownClaudeBinary};
+use crate::completion;
+use crate::dependencies;
+use crate::detach::Backfill;
+use crate::egress;
+use crate::group_status;
+use crate::history;
+use crate::input_history;
+use crate::ipc::IpcContext;
+use crate::keys;
+use crate::logging;
+use crate::mcp;
+use crate::metrics;
+use crate::notifications::MuteTarget;
+use crate::output_log;
+use crate::process_group::KILL_GRACE;
+use crate::projects;
+use crate::pty::PtyDead;
+use crate::rpc::{NoParams, Router, RpcError, RpcHandler, RpcResult};
+use crate::scheduler::{self, Cron};
+use crate::search;
+use crate::session_manager::SessionManager;
+use crate::snippets;
+use crate::state::{self, Placement};
+use crate::tasks;
+use crate::transcript;
+use crate::turns;
+use crate::updates::CURRENT_VERSION;
+use crate::watches::WatchTarget;
+
+/// Pause after typing `!` so claude has switched to bash mode before the command
+const BASH_MODE_DELAY_MS: u64 = 100;
+
+/// How long an interrupted session may keep running before it gets Ctrl+C
+const INTERRUPT_GRACE_MS: u64 = 3000;
+/// Sent by session.continue when no prompt is given
+const CONTINUE_PROMPT: &str = "continue";
+
+/// Every method answered through the router
+pub fn router() -> Router<IpcContext> {
+    Router::new()
+        .route("daemon.ping", DaemonPing)
+        .route("daemon.shutdown", DaemonShutdown { restart: false })
+        .route("daemon.restart", DaemonShutdown { restart: true })
+        .route("daemon.hello", DaemonHello)
+        .route("daemon.diagnostics", DaemonDiagnostics)
+        .route("daemon.stats", DaemonStatsHandler)
+        .route("daemon.metrics", DaemonMetricsHandler)
+        .route("daemon.logs.tail", DaemonLogsTail)
+        .route("daemon.network_audit", DaemonNetworkAudit)
+        .route("daemon.orphans", DaemonOrphans)
+        .route("daemon.bootstrap", DaemonBootstrap)
+        .route("daemon.keep_awake", DaemonKeepAwake)
+        .route("daemon.clients", DaemonClients)
+        .route("events.since", EventsSince)
+        .route("ui.theme", UiTheme)
+        .route("notifications.get", NotificationsGet)
+        .route("notifications.set_mute", NotificationsSetMute)
+        .route("session.list", SessionList)
+        .route("session.create", SessionCreate)
+        .route("session.create_worktree", SessionCreateWorktree)
+        .route("session.stop", SessionStop)
+        .route("session.delete", SessionDelete)
+        .route("session.update", SessionUpdate)
+        .route("session.add_tag", SessionEditTag { add: true })
+        .route("session.remove_tag", SessionEditTag { add: false })
+        .route("session.tasks.list", SessionTasksList)
+        .route("session.tasks.run", SessionTasksRun)
+        .route("watch.add", WatchEdit { add: true })
+        .route("watch.remove", WatchEdit { add: false })
+        .route("policy.set", PolicySet)
+        .route("session.move", SessionMove)
+        .route("session.transcript", SessionTranscript)
+        .route("session.turn_stats", SessionTurnStats)
+        .route("session.stats", SessionStats)
+        .route("queue.enqueue", QueueEnqueue)
+        .route("queue.list", QueueList)
+        .route("queue.cancel", QueueCancel)
+        .route("session.history", SessionHistory)
+        .route("session.input_history", SessionInputHistory)
+        .route("history.list", HistoryList)
+        .route("search.query", SearchQuery)
+        .route("session.tail_log", SessionTailLog)
+        .route("session.get_screen", SessionGetScreen)
+        .route("session.search_output", SessionSearchOutput)
+        .route("session.export_output", SessionExportOutput)
+        .route("session.replay", SessionReplay)
+        .route("session.replay_cancel", SessionReplayCancel)
+        .route("session.recording.start", RecordingStart)
+        .route("session.recording.stop", RecordingStop)
+        .route("session.recording.list", RecordingList)
+        .route("session.recording.export", RecordingExport)
+        .route("session.input", SessionInput)
+        .route("session.interrupt", SessionInterrupt)
+        .route("session.send_key", SessionSendKey)
+        .route("session.detach", SessionDetach)
+        .route("session.attach", SessionAttach)
+        .route("session.mark_viewed", SessionMarkViewed)
+        .route("session.continue", SessionContinue)
+        .route("session.approve", SessionApprove)
+        .route("session.deny", SessionDeny)
+        .route("session.resize", SessionResize)
+        .route("session.restart", SessionRestart)
+        .route("session.enqueue_start", SessionEnqueueStart)
+        .route("session.set_dependency", SessionSetDependency)
+        .route("session.fork", SessionFork)
+        .route("schedule.create", ScheduleCreate)
+        .route("schedule.list", ScheduleList)
+        .route("schedule.delete", ScheduleDelete)
+        .route("snippet.list", SnippetList)
+        .route("snippet.create", SnippetCreate)
+        .route("snippet.update", SnippetUpdate)
+        .route("snippet.delete", SnippetDelete)
+        .route("session.send_snippet", SessionSendSnippet)
+        .route("projects.scan", ProjectsScan)
+        .route("claude.capabilities", ClaudeCapabilitiesHandler)
+        .route("daemon.claude_info", DaemonClaudeInfo)
+        .route("mcp.list", McpList)
+        .route("mcp.add", McpAdd)
+        .route("mcp.remove", McpRemove)
+        .route("mcp.set_enabled", McpSetEnabled)
+        .route("tree.snapshot", TreeSnapshotHandler)
+        .route("summary.get", SummaryGet)
+        .route("group.list", GroupList)
+        .route("group.create", GroupCreate)
+        .route("group.delete", GroupDelete)
+        .route("group.update", GroupUpdate)
+        .route("session.reorder", SessionReorderHandler)
+        .route("group.reorder", GroupReorderHandler)
+        .route("group.set_completion_watch", GroupSetCompletionWatch)
+        .route("state.export", StateExport)
+        .route("state.import", StateImport)
+        .route("state.sync", StateSyncHandler)
+}
+
+/// A session's working directory, if the session exists
+async fn session_dir(ctx: &IpcContext, session_id: Uuid) -> Option<PathBuf> {
+    ctx.state
+        .read()
+        .await
+        .sessions
+        .get(&session_id)
+        .map(|session| session.working_dir.clone())
+}
+
+/// Group for a new session that was created without one, if auto_group_by_repo is on
+async fn auto_group(ctx: &IpcContext, dir: &Path) -> Option<Uuid> {
+    if !ctx.config.read().await.daemon.auto_group_by_repo {
+        return None;
+    }
+    SessionManager::repo_group(&ctx.state, &ctx.event_tx, dir)
+        .await
+        .unwrap_or_else(|e| {
+            warn!("Failed to find repository group for {:?}: {}", dir, e);
+            None
+        })
+}
+
+fn session_not_found() -> RpcError {
+    RpcError::new(ErrorCode::SessionNotFound, "Session not found")
+}
+
+// --- Daemon ---
+
+struct DaemonPing;
+
+impl RpcHandler<IpcContext> for DaemonPing {
+    type Params = NoParams;
+    type Output = StatusResult;
+
+    async fn handle(&self, _: &IpcContext, _: NoParams) -> RpcResult<StatusResult> {
+        Ok(StatusResult {
+            status: "ok".to_string(),
+        })
+    }
+}
+
+/// daemon.shutdown, and daemon.restart which always restarts
+struct DaemonShutdown {
+    restart: bool,
+}
+
+impl RpcHandler<IpcContext> for DaemonShutdown {
+    type Params = ShutdownParams;
+    type Output = StatusResult;
+
+    async fn handle(
+        &self,
+        ctx: &IpcContext,
+        mut params: ShutdownParams,
+    ) -> RpcResult<StatusResult> {
+        params.restart |= self.restart;
+
+        info!(
+            "{} requested via IPC ({:?})",
+            if params.restart {
+                "Restart"
+            } else {
+                "Shutdown"
+            },
+            params.pty_policy
+        );
+        let status = if params.restart {
+            "restarting"
+        } else {
+            "shutting_down"
+        };
+        ctx.shutdown_tx.send_replace(Some(params));
+        Ok(StatusResult {
+            status: status.to_string(),
+        })
+    }
+}
+
+struct DaemonHello;
+
+impl RpcHandler<IpcContext> for DaemonHello {
+    type Params = NoParams;
+    type Output = HelloResult;
+
+    async fn handle(&self, ctx: &IpcContext, _: NoParams) -> RpcResult<HelloResult> {
+        Ok(HelloResult {
+            version: CURRENT_VERSION.to_string(),
+            pid: std::process::id(),
+            instance_id: ctx.instance_id,
+            started_at: ctx.started_at,
+            protocol_version: PROTOCOL_VERSION,
+            methods: METHODS.iter().map(|m| m.to_string()).collect(),
+            capabilities: CAPABILITIES.iter().map(|c| c.to_string()).collect(),
+            profile: shared::current_profile(),
+            journal_id: Some(ctx.journal.id()),
+        })
+    }
+}
+
+struct DaemonDiagnostics;
+
+impl RpcHandler<IpcContext> for DaemonDiagnostics {
+    type Params = NoParams;
+    type Output = DiagnosticsResult;
+
+    async fn handle(&self, ctx: &IpcContext, _: NoParams) -> RpcResult<DiagnosticsResult> {
+        Ok(DiagnosticsResult {
+            version: CURRENT_VERSION.to_string(),
+            hook_script_current: ctx.hook_manager.hook_script_current(),
+            update: ctx.update_checker.latest().await,
+            last_crash: ctx.last_crash.clone(),
+            claude: ctx.claude_check.latest().await,
+        })
+    }
+}
+
+struct DaemonStatsHandler;
+
+impl RpcHandler<IpcContext> for DaemonStatsHandler {
+    type Params = NoParams;
+    type Output = DaemonStats;
+
+    async fn handle(&self, ctx: &IpcContext, _: NoParams) -> RpcResult<DaemonStats> {
+        let (client_lags, events_dropped) = ctx.flow.client_lag();
+        Ok(DaemonStats {
+            output_queue_capacity: ctx.output_tx.max_capacity(),
+            output_queue_len: ctx.output_tx.max_capacity() - ctx.output_tx.capacity(),
+            clients: ctx.flow.clients(),
+            client_lags,
+            events_dropped,
+            sessions: ctx.flow.session_stats(),
+        })
+    }
+}
+
+struct DaemonMetricsHandler;
+
+impl RpcHandler<IpcContext> for DaemonMetricsHandler {
+    type Params = MetricsParams;
+    type Output = DaemonMetrics;
+
+    async fn handle(&self, ctx: &IpcContext, params: MetricsParams) -> RpcResult<DaemonMetrics> {
+        let uptime_secs = (chrono::Utc::now() - ctx.started_at).num_seconds();
+        let mut result = ctx.metrics.snapshot(&ctx.flow, uptime_secs);
+        if params.format == MetricsFormat::Prometheus {
+            result.prometheus = Some(metrics::prometheus(&result));
+        }
+        Ok(result)
+    }
+}
+
+struct DaemonNetworkAudit;
+
+impl RpcHandler<IpcContext> for DaemonNetworkAudit {
+    type Params = NoParams;
+    type Output = NetworkAuditResult;
+
+    async fn handle(&self, ctx: &IpcContext, _: NoParams) -> RpcResult<NetworkAuditResult> {
+        Ok(egress::audit(&*ctx.config.read().await))
+    }
+}
+
+struct DaemonOrphans;
+
+impl RpcHandler<IpcContext> for DaemonOrphans {
+    type Params = NoParams;
+    type Output = Vec<OrphanInfo>;
+
+    async fn handle(&self, ctx: &IpcContext, _: NoParams) -> RpcResult<Vec<OrphanInfo>> {
+        Ok(ctx.orphans.list().await)
+    }
+}
+
+struct DaemonBootstrap;
+
+impl RpcHandler<IpcContext> for DaemonBootstrap {
+    type Params = BootstrapParams;
+    type Output = BootstrapStartedResult;
+
+    async fn handle(
+        &self,
+        ctx: &IpcContext,
+        params: BootstrapParams,
+    ) -> RpcResult<BootstrapStartedResult> {
+        if !bootstrap::try_start() {
+            return Err(RpcError::new(
+                ErrorCode::DaemonBusy,
+                "Bootstrap already running",
+            ));
+        }
+
+        // Installing claude can take minutes, so run in the background and
+        // report through daemon:bootstrap_progress / daemon:bootstrap_complete
+        tokio::spawn(bootstrap::run(
+            ctx.state.clone(),
+            ctx.event_tx.clone(),
+            ctx.hook_manager.clone(),
+            ctx.config.clone(),
+            params.install_claude,
+            params.starter_group,
+        ));
+
+        Ok(BootstrapStartedResult { started: true })
+    }
+}
+
+/// Without `enabled` this only reports the status
+struct DaemonKeepAwake;
+
+impl RpcHandler<IpcContext> for DaemonKeepAwake {
+    type Params = KeepAwakeParams;
+    type Output = KeepAwakeStatus;
+
+    async fn handle(
+        &self,
+        ctx: &IpcContext,
+        params: KeepAwakeParams,
+    ) -> RpcResult<KeepAwakeStatus> {
+        match params.enabled {
+            Some(enabled) => Ok(ctx.keep_awake.set_enabled(enabled).await?),
+            None => Ok(ctx.keep_awake.status().await),
+        }
+    }
+}
+
+struct DaemonClients;
+
+impl RpcHandler<IpcContext> for DaemonClients {
+    type Params = NoParams;
+    type Output = ClientsResult;
+
+    async fn handle(&self, ctx: &IpcContext, _: NoParams) -> RpcResult<ClientsResult> {
+        Ok(ClientsResult {
+            clients: ctx.clients.list(),
+        })
+    }
+}
+
+struct EventsSince;
+
+impl RpcHandler<IpcContext> for EventsSince {
+    type Params = EventsSinceParams;
+    type Output = EventsSinceResult;
+
+    async fn handle(
+        &self,
+        ctx: &IpcContext,
+        params: EventsSinceParams,
+    ) -> RpcResult<EventsSinceResult> {
+        Ok(ctx.journal.since(params.seq))
+    }
+}
+
+struct UiTheme;
+
+impl RpcHandler<IpcContext> for UiTheme {
+    type Params = NoParams;
+    type Output = ThemeResult;
+
+    async fn handle(&self, ctx: &IpcContext, _: NoParams) -> RpcResult<ThemeResult> {
+        Ok(ctx.config.read().await.ui.theme())
+    }
+}
+
+// --- Notifications ---
+
+struct NotificationsGet;
+
+impl RpcHandler<IpcContext> for NotificationsGet {
+    type Params = NoParams;
+    type Output = NotificationSettings;
+
+    async fn handle(&self, ctx: &IpcContext, _: NoParams) -> RpcResult<NotificationSettings> {
+        Ok(ctx.notifier.settings().await)
+    }
+}
+
+struct NotificationsSetMute;
+
+impl RpcHandler<IpcContext> for NotificationsSetMute {
+    type Params = SetMuteParams;
+    type Output = NotificationSettings;
+
+    async fn handle(
+        &self,
+        ctx: &IpcContext,
+        params: SetMuteParams,
+    ) -> RpcResult<NotificationSettings> {
+        let target = match (params.session_id, params.group_id) {
+            (Some(id), None) => MuteTarget::Session(id),
+            (None, Some(id)) => MuteTarget::Group(id),
+            _ => {
+                return Err(RpcError::invalid_params(
+                    "exactly one of session_id or group_id is required",
+                ))
+            }
+        };
+
+        Ok(ctx.notifier.set_mute(target, params.muted).await?)
+    }
+}
+
+// --- Sessions ---
+
+struct SessionList;
+
+impl RpcHandler<IpcContext> for SessionList {
+    type Params = SessionListParams;
+    type Output = SessionListResult;
+
+    async fn handle(
+        &self,
+        ctx: &IpcContext,
+        params: SessionListParams,
+    ) -> RpcResult<SessionListResult> {
+        let s = ctx.state.read().await;
+        let sessions = s
+            .sessions
+            .values()
+            .filter(|session| {
+                params
+                    .filter
+                    .as_ref()
+                    .is_none_or(|filter| filter.matches(session))
+            })
+            .cloned()
+            .collect();
+        Ok(SessionListResult { sessions })
+    }
+}
+
+struct SessionCreate;
+
+impl RpcHandler<IpcContext> for SessionCreate {
+    type Params = CreateSessionParams;
+    type Output = SessionCreatedResult;
+
+    async fn handle(
+        &self,
+        ctx: &IpcContext,
+        params: CreateSessionParams,
+    ) -> RpcResult<SessionCreatedResult> {
+        info!("session.create: name={} dir={}", params.name, params.dir);
+        let dir = PathBuf::from(params.dir);
+        if let Some(name) = &params.claude_binary {
+            if !ctx.config.read().await.claude.binaries.contains_key(name) {
+                return Err(RpcError::invalid_params(UnknownClaudeBinary(name.clone())));
+            }
+        }
+        let group_id = match params.group_id {
+            Some(id) => Some(id),
+            None => auto_group(ctx, &dir).await,
+        };
+        let session = SessionManager::create_session(
+            &ctx.state,
+            &ctx.pty_manager,
+            ctx.output_tx.clone(),
+            &ctx.event_tx,
+            params.name,
+            dir,
+            group_id,
+            params.spawn_mode,
+            params.claude_binary,
+            params.flags,
+            params.create_dir,
+            params.size,
+        )
+        .await
+        .map_err(RpcError::context("Failed to create session"))?;
+        Ok(SessionCreatedResult { session })
+    }
+}
+
+struct SessionCreateWorktree;
+
+impl RpcHandler<IpcContext> for SessionCreateWorktree {
+    type Params = CreateWorktreeSessionParams;
+    type Output = SessionCreatedResult;
+
+    async fn handle(
+        &self,
+        ctx: &IpcContext,
+        params: CreateWorktreeSessionParams,
+    ) -> RpcResult<SessionCreatedResult> {
+        info!(
+            "session.create_worktree: name={} repo={} branch={}",
+            params.name, params.repo_path, params.branch
+        );
+        let repo_path = PathBuf::from(params.repo_path);
+        let group_id = match params.group_id {
+            Some(id) => Some(id),
+            None => auto_group(ctx, &repo_path).await,
+        };
+        let session = SessionManager::create_worktree_session(
+            &ctx.state,
+            &ctx.event_tx,
+            params.name,
+            repo_path,
+            params.branch,
+            params.worktree_path.map(PathBuf::from),
+            group_id,
+        )
+        .await
+        .map_err(RpcError::context("Failed to create worktree session"))?;
+        Ok(SessionCreatedResult { session })
+    }
+}
+
+struct SessionStop;
+
+impl RpcHandler<IpcContext> for SessionStop {
+    type Params = StopSessionParams;
+    type Output = StopSessionResult;
+
+    async fn handle(
+        &self,
+        ctx: &IpcContext,
+        params: StopSessionParams,
+    ) -> RpcResult<StopSessionResult> {
+        let grace = params
+            .timeout_ms
+            .map_or(KILL_GRACE, std::time::Duration::from_millis);
+        let outcome = SessionManager::stop_session(
+            &ctx.state,
+            &ctx.pty_manager,
+            &ctx.event_tx,
+            params.session_id,
+            grace,
+            params.force,
+        )
+        .await
+        .map_err(RpcError::context("Failed to stop session"))?;
+        Ok(StopSessionResult {
+            success: true,
+            outcome,
+        })
+    }
+}
+
+struct SessionDelete;
+
+impl RpcHandler<IpcContext> for SessionDelete {
+    type Params = DeleteSessionParams;
+    type Output = SuccessResult;
+
+    async fn handle(
+        &self,
+        ctx: &IpcContext,
+        params: DeleteSessionParams,
+    ) -> RpcResult<SuccessResult> {
+        SessionManager::delete_session(
+            &ctx.state,
+            &ctx.pty_manager,
+            &ctx.event_tx,
+            params.session_id,
+            params.remove_worktree,
+        )
+        .await
+        .map_err(RpcError::context("Failed to delete session"))?;
+
+        forget_session(ctx, params.session_id);
+        Ok(SuccessResult { success: true })
+    }
+}
+
+/// Drop what the daemon keeps about a deleted session outside the state
+fn forget_session(ctx: &IpcContext, session_id: Uuid) {
+    // Finish any recording; the cast file is kept
+    let _ = ctx.recorder.stop(session_id);
+    ctx.screens.remove(session_id);
+    ctx.approvals.remove(session_id);
+    ctx.detached.remove(session_id);
+    ctx.flow.remove_session(session_id);
+}
+
+struct SessionUpdate;
+
+impl RpcHandler<IpcContext> for SessionUpdate {
+    type Params = UpdateSessionParams;
+    type Output = Session;
+
+    async fn handle(&self, ctx: &IpcContext, params: UpdateSessionParams) -> RpcResult<Session> {
+        SessionManager::update_session(
+            &ctx.state,
+            &ctx.event_tx,
+            params.session_id,
+            params.name,
+            params.group_id,
+            params.spawn_mode,
+        )
+        .await
+        .map_err(RpcError::context("Failed to update session"))
+    }
+}
+
+/// session.add_tag and session.remove_tag
+struct SessionEditTag {
+    add: bool,
+}
+
+impl RpcHandler<IpcContext> for SessionEditTag {
+    type Params = SessionTagParams;
+    type Output = Session;
+
+    async fn handle(&self, ctx: &IpcContext, params: SessionTagParams) -> RpcResult<Session> {
+        if self.add {
+            SessionManager::add_tag(&ctx.state, &ctx.event_tx, params.session_id, &params.tag)
+                .await
+                .map_err(RpcError::context("Failed to add tag"))
+        } else {
+            SessionManager::remove_tag(&ctx.state, &ctx.event_tx, params.session_id, &params.tag)
+                .await
+                .map_err(RpcError::context("Failed to remove tag"))
+        }
+    }
+}
+
+struct SessionTasksList;
+
+impl RpcHandler<IpcContext> for SessionTasksList {
+    type Params = SessionIdParams;
+    type Output = TaskListResult;
+
+    async fn handle(&self, ctx: &IpcContext, params: SessionIdParams) -> RpcResult<TaskListResult> {
+        let working_dir = session_dir(ctx, params.session_id)
+            .await
+            .ok_or_else(session_not_found)?;
+        Ok(TaskListResult {
+            tasks: tasks::detect(&working_dir),
+        })
+    }
+}
+
+struct SessionTasksRun;
+
+impl RpcHandler<IpcContext> for SessionTasksRun {
+    type Params = RunTaskParams;
+    type Output = TaskInfo;
+
+    async fn handle(&self, ctx: &IpcContext, params: RunTaskParams) -> RpcResult<TaskInfo> {
+        let working_dir = session_dir(ctx, params.session_id)
+            .await
+            .ok_or_else(session_not_found)?;
+        let task = tasks::find(&working_dir, &params.name, params.runner)?;
+
+        if params.headless {
+            let (session_id, event_tx) = (params.session_id, ctx.event_tx.clone());
+            let started = task.clone();
+            tokio::spawn(async move {
+                tasks::run_headless(session_id, started, &working_dir, event_tx).await;
+            });
+            return Ok(task);
+        }
+
+        // `!` switches claude's prompt to bash mode before the command is typed
+        let mut result = ctx.pty_manager.write(params.session_id, b"!").await;
+        if result.is_ok() {
+            tokio::time::sleep(std::time::Duration::from_millis(BASH_MODE_DELAY_MS)).await;
+            let input = format!("{}\r", task.command);
+            result = ctx
+                .pty_manager
+                .write(params.session_id, input.as_bytes())
+                .await;
+        }
+        result.map_err(RpcError::context("Failed to run task"))?;
+
+        history::record(
+            params.session_id,
+            HistoryKind::TaskRun {
+                name: task.name.clone(),
+                command: task.command.clone(),
+                headless: false,
+                exit_code: None,
+                output: None,
+            },
+        );
+        Ok(task)
+    }
+}
+
+/// watch.add and watch.remove
+struct WatchEdit {
+    add: bool,
+}
+
+impl RpcHandler<IpcContext> for WatchEdit {
+    type Params = WatchParams;
+    type Output = WatchesResult;
+
+    async fn handle(&self, ctx: &IpcContext, params: WatchParams) -> RpcResult<WatchesResult> {
+        let target = match (params.session_id, params.group_id) {
+            (Some(id), None) => WatchTarget::Session(id),
+            (None, Some(id)) => WatchTarget::Group(id),
+            _ => {
+                return Err(RpcError::invalid_params(
+                    "exactly one of session_id or group_id is required",
+                ))
+            }
+        };
+
+        let watches = if self.add {
+            SessionManager::add_watch(&ctx.state, &ctx.event_tx, target, &params.expr).await
+        } else {
+            SessionManager::remove_watch(&ctx.state, &ctx.event_tx, target, &params.expr).await
+        }
+        .map_err(RpcError::context("Failed to update watches"))?;
+        Ok(WatchesResult { watches })
+    }
+}
+
+struct PolicySet;
+
+impl RpcHandler<IpcContext> for PolicySet {
+    type Params = PolicySetParams;
+    type Output = ApprovalPolicy;
+
+    async fn handle(&self, ctx: &IpcContext, params: PolicySetParams) -> RpcResult<ApprovalPolicy> {
+        let target = match (params.session_id, params.group_id) {
+            (Some(id), None) => WatchTarget::Session(id),
+            (None, Some(id)) => WatchTarget::Group(id),
+            _ => {
+                return Err(RpcError::invalid_params(
+                    "exactly one of session_id or group_id is required",
+                ))
+            }
+        };
+        SessionManager::set_policy(&ctx.state, &ctx.event_tx, target, params.policy)
+            .await
+            .map_err(RpcError::context("Failed to set approval policy"))
+    }
+}
+
+struct SessionMove;
+
+impl RpcHandler<IpcContext> for SessionMove {
+    type Params = MoveToGroupParams;
+    type Output = SessionListResult;
+
+    async fn handle(
+        &self,
+        ctx: &IpcContext,
+        params: MoveToGroupParams,
+    ) -> RpcResult<SessionListResult> {
+        let mut session_ids = params.session_ids;
+        if let Some(id) = params.session_id {
+            if !session_ids.contains(&id) {
+                session_ids.insert(0, id);
+            }
+        }
+
+        let sessions =
+            SessionManager::move_sessions(&ctx.state, &ctx.event_tx, session_ids, params.group_id)
+                .await
+                .map_err(RpcError::context("Failed to move sessions"))?;
+        Ok(SessionListResult { sessions })
+    }
+}
+
+struct SessionTranscript;
+
+impl RpcHandler<IpcContext> for SessionTranscript {
+    type Params = TranscriptParams;
+    type Output = TranscriptResult;
+
+    async fn handle(
+        &self,
+        ctx: &IpcContext,
+        params: TranscriptParams,
+    ) -> RpcResult<TranscriptResult> {
+        let claude_session_id = ctx
+            .state
+            .read()
+            .await
+            .sessions
+            .get(&params.session_id)
+            .ok_or_else(session_not_found)?
+            .claude_session_id
+            .clone();
+
+        let path = claude_session_id
+            .as_deref()
+            .and_then(transcript::find_transcript);
+        let (mut messages, state) = path
+            .as_ref()
+            .and_then(|p| std::fs::read_to_string(p).ok())
+            .map(|content| transcript::parse(&content))
+            .unwrap_or_default();
+        if let Some(limit) = params.limit {
+            let skip = messages.len().saturating_sub(limit);
+            messages.drain(..skip);
+        }
+
+        Ok(TranscriptResult {
+            path: path.map(|p| p.to_string_lossy().to_string()),
+            state,
+            messages,
+        })
+    }
+}
+
+struct SessionTurnStats;
+
+impl RpcHandler<IpcContext> for SessionTurnStats {
+    type Params = TurnStatsParams;
+    type Output = TurnStats;
+
+    async fn handle(&self, _: &IpcContext, params: TurnStatsParams) -> RpcResult<TurnStats> {
+        let entries = history::load(params.session_id)
+            .await
+            .map_err(RpcError::context("Failed to load history"))?;
+        Ok(turns::stats(&entries, params.limit.unwrap_or(50)))
+    }
+}
+
+struct SessionStats;
+
+impl RpcHandler<IpcContext> for SessionStats {
+    type Params = SessionStatsParams;
+    type Output = SessionStatsResult;
+
+    async fn handle(
+        &self,
+        ctx: &IpcContext,
+        params: SessionStatsParams,
+    ) -> RpcResult<SessionStatsResult> {
+        if let Some(session_id) = params.session_id {
+            if !ctx.state.read().await.sessions.contains_key(&session_id) {
+                return Err(session_not_found());
+            }
+        }
+        Ok(SessionStatsResult {
+            sessions: ctx.resources.sample(params.session_id).await,
+        })
+    }
+}
+
+// --- Prompt queue ---
+
+struct QueueEnqueue;
+
+impl RpcHandler<IpcContext> for QueueEnqueue {
+    type Params = EnqueuePromptParams;
+    type Output = QueuedPrompt;
+
+    async fn handle(
+        &self,
+        ctx: &IpcContext,
+        params: EnqueuePromptParams,
+    ) -> RpcResult<QueuedPrompt> {
+        ctx.prompt_queue
+            .enqueue(params.session_id, params.prompt, params.priority)
+            .await
+            .map_err(RpcError::context("Failed to queue prompt"))
+    }
+}
+
+/// Without a session_id this lists the whole queue
+struct QueueList;
+
+impl RpcHandler<IpcContext> for QueueList {
+    type Params = QueueListParams;
+    type Output = Vec<QueuedPrompt>;
+
+    async fn handle(
+        &self,
+        ctx: &IpcContext,
+        params: QueueListParams,
+    ) -> RpcResult<Vec<QueuedPrompt>> {
+        Ok(ctx.prompt_queue.list(params.session_id).await)
+    }
+}
+
+struct QueueCancel;
+
+impl RpcHandler<IpcContext> for QueueCancel {
+    type Params = QueueCancelParams;
+    type Output = CancelledResult;
+
+    async fn handle(
+        &self,
+        ctx: &IpcContext,
+        params: QueueCancelParams,
+    ) -> RpcResult<CancelledResult> {
+        Ok(CancelledResult {
+            cancelled: ctx.prompt_queue.cancel(params.id).await,
+        })
+    }
+}
+
+// --- History and search ---
+
+struct SessionHistory;
+
+impl RpcHandler<IpcContext> for SessionHistory {
+    type Params = SessionHistoryParams;
+    type Output = Vec<HistoryEntry>;
+
+    async fn handle(
+        &self,
+        _: &IpcContext,
+        params: SessionHistoryParams,
+    ) -> RpcResult<Vec<HistoryEntry>> {
+        let mut entries = history::load(params.session_id)
+            .await
+            .map_err(RpcError::context("Failed to load history"))?;
+        let skip = params
+            .limit
+            .map_or(0, |limit| entries.len().saturating_sub(limit));
+        entries.drain(..skip);
+        Ok(entries)
+    }
+}
+
+struct SessionInputHistory;
+
+impl RpcHandler<IpcContext> for SessionInputHistory {
+    type Params = InputHistoryParams;
+    type Output = Vec<InputHistoryEntry>;
+
+    async fn handle(
+        &self,
+        ctx: &IpcContext,
+        params: InputHistoryParams,
+    ) -> RpcResult<Vec<InputHistoryEntry>> {
+        Ok(input_history::query(&*ctx.state.read().await, &params))
+    }
+}
+
+/// Without params this lists the whole archive
+struct HistoryList;
+
+impl RpcHandler<IpcContext> for HistoryList {
+    type Params = HistoryListParams;
+    type Output = Vec<ArchivedSession>;
+
+    async fn handle(
+        &self,
+        _: &IpcContext,
+        params: HistoryListParams,
+    ) -> RpcResult<Vec<ArchivedSession>> {
+        let working_dir = params.working_dir.map(PathBuf::from);
+        archive::list(working_dir.as_deref(), params.limit)
+            .map_err(RpcError::context("Failed to read session archive"))
+    }
+}
+
+struct SearchQuery;
+
+impl RpcHandler<IpcContext> for SearchQuery {
+    type Params = SearchQueryParams;
+    type Output = SearchQueryResult;
+
+    async fn handle(
+        &self,
+        ctx: &IpcContext,
+        params: SearchQueryParams,
+    ) -> RpcResult<SearchQueryResult> {
+        let matcher = search::matcher(&params).map_err(|e| {
+            RpcError::new(ErrorCode::InvalidParams, format!("Invalid query: {}", e))
+        })?;
+
+        // Live sessions by most recent activity, then the archive, newest first
+        let session_ids = params.session_ids.clone();
+        let wanted = move |id: &Uuid| session_ids.as_ref().is_none_or(|ids| ids.contains(id));
+        let mut live: Vec<Session> = ctx
+            .state
+            .read()
+            .await
+            .sessions
+            .values()
+            .filter(|session| wanted(&session.id))
+            .cloned()
+            .collect();
+        live.sort_by_key(|session| std::cmp::Reverse(session.last_activity));
+
+        tokio::task::spawn_blocking(move || {
+            let mut targets: Vec<search::Target> = live
+                .into_iter()
+                .map(|session| search::Target {
+                    session_id: session.id,
+                    transcript: session
+                        .claude_session_id
+                        .as_deref()
+                        .and_then(transcript::find_transcript),
+                    name: session.name,
+                    archived: false,
+                })
+                .collect();
+            let archived = archive::list(None, None).unwrap_or_default();
+            targets.extend(
+                archived
+                    .into_iter()
+                    .filter(|session| wanted(&session.id))
+                    .map(|session| search::Target {
+                        session_id: session.id,
+                        transcript: session.transcript_path.map(PathBuf::from).or_else(|| {
+                            session
+                                .claude_session_id
+                                .as_deref()
+                                .and_then(transcript::find_transcript)
+                        }),
+                        name: session.name,
+                        archived: true,
+                    }),
+            );
+            let (hits, truncated) = search::run(&targets, &params, &matcher);
+            SearchQueryResult { hits, truncated }
+        })
+        .await
+        .map_err(|e| RpcError::failed(format!("Search failed: {}", e)))
+    }
+}
+
+// --- Output logs, replays and recordings ---
+
+struct DaemonLogsTail;
+
+impl RpcHandler<IpcContext> for DaemonLogsTail {
+    type Params = DaemonLogsTailParams;
+    type Output = DaemonLogsTailResult;
+
+    async fn handle(
+        &self,
+        _: &IpcContext,
+        params: DaemonLogsTailParams,
+    ) -> RpcResult<DaemonLogsTailResult> {
+        let level = params
+            .level
+            .map(|level| level.parse::<tracing::Level>())
+            .transpose()
+            .map_err(RpcError::invalid_params)?;
+        let lines = params.lines.unwrap_or(logging::DEFAULT_TAIL_LINES);
+        let (path, records, truncated) =
+            logging::tail(lines, level).map_err(RpcError::context("Failed to read daemon log"))?;
+        Ok(DaemonLogsTailResult {
+            path: path.to_string_lossy().to_string(),
+            records,
+            truncated,
+        })
+    }
+}
+
+struct SessionTailLog;
+
+impl RpcHandler<IpcContext> for SessionTailLog {
+    type Params = TailLogParams;
+    type Output = TailLogResult;
+
+    async fn handle(&self, _: &IpcContext, params: TailLogParams) -> RpcResult<TailLogResult> {
+        let max_bytes = params.max_bytes.unwrap_or(output_log::DEFAULT_TAIL_BYTES);
+        let (path, output, truncated) = output_log::tail(params.session_id, max_bytes)
+            .map_err(RpcError::context("Failed to read output log"))?;
+        Ok(TailLogResult {
+            path: path.to_string_lossy().to_string(),
+            output: BASE64.encode(output),
+            truncated,
+        })
+    }
+}
+
+struct SessionGetScreen;
+
+impl RpcHandler<IpcContext> for SessionGetScreen {
+    type Params = SessionIdParams;
+    type Output = SessionScreen;
+
+    async fn handle(&self, ctx: &IpcContext, params: SessionIdParams) -> RpcResult<SessionScreen> {
+        ctx.screens
+            .get(params.session_id)
+            .ok_or_else(session_not_found)
+    }
+}
+
+struct SessionSearchOutput;
+
+impl RpcHandler<IpcContext> for SessionSearchOutput {
+    type Params = SearchOutputParams;
+    type Output = SearchOutputResult;
+
+    async fn handle(
+        &self,
+        _: &IpcContext,
+        params: SearchOutputParams,
+    ) -> RpcResult<SearchOutputResult> {
+        let limit = params.limit.unwrap_or(output_log::DEFAULT_SEARCH_LIMIT);
+        let (matches, truncated) = output_log::search(
+            params.session_id,
+            &params.query,
+            params.case_sensitive,
+            limit,
+        )
+        .map_err(RpcError::context("Failed to search output log"))?;
+        Ok(SearchOutputResult { matches, truncated })
+    }
+}
+
+struct SessionExportOutput;
+
+impl RpcHandler<IpcContext> for SessionExportOutput {
+    type Params = ExportOutputParams;
+    type Output = ExportOutputResult;
+
+    async fn handle(
+        &self,
+        _: &IpcContext,
+        params: ExportOutputParams,
+    ) -> RpcResult<ExportOutputResult> {
+        let bytes = output_log::export(params.session_id, Path::new(&params.dest), params.raw)
+            .map_err(RpcError::context("Failed to export output log"))?;
+        Ok(ExportOutputResult {
+            path: params.dest,
+            bytes,
+        })
+    }
+}
+
+struct SessionReplay;
+
+impl RpcHandler<IpcContext> for SessionReplay {
+    type Params = SessionReplayParams;
+    type Output = ReplayStartedResult;
+
+    async fn handle(
+        &self,
+        ctx: &IpcContext,
+        params: SessionReplayParams,
+    ) -> RpcResult<ReplayStartedResult> {
+        if params
+            .speed
+            .is_some_and(|speed| !speed.is_finite() || speed <= 0.0)
+        {
+            return Err(RpcError::invalid_params("speed must be greater than 0"));
+        }
+        if !ctx
+            .state
+            .read()
+            .await
+            .sessions
+            .contains_key(&params.session_id)
+        {
+            return Err(session_not_found());
+        }
+        let segments = output_log::segments(params.session_id)
+            .map_err(RpcError::context("Failed to read output log"))?;
+        if segments.is_empty() {
+            return Err(RpcError::failed(
+                "No output log for this session (is [session_logs] enabled?)",
+            ));
+        }
+
+        Ok(ReplayStartedResult {
+            replay_id: ctx.replays.start(params).await,
+        })
+    }
+}
+
+struct SessionReplayCancel;
+
+impl RpcHandler<IpcContext> for SessionReplayCancel {
+    type Params = ReplayCancelParams;
+    type Output = CancelledResult;
+
+    async fn handle(
+        &self,
+        ctx: &IpcContext,
+        params: ReplayCancelParams,
+    ) -> RpcResult<CancelledResult> {
+        Ok(CancelledResult {
+            cancelled: ctx.replays.cancel(params.replay_id).await,
+        })
+    }
+}
+
+struct RecordingStart;
+
+impl RpcHandler<IpcContext> for RecordingStart {
+    type Params = SessionIdParams;
+    type Output = RecordingInfo;
+
+    async fn handle(&self, ctx: &IpcContext, params: SessionIdParams) -> RpcResult<RecordingInfo> {
+        let (name, size) = ctx
+            .state
+            .read()
+            .await
+            .sessions
+            .get(&params.session_id)
+            .map(|session| (session.name.clone(), session.size.unwrap_or_default()))
+            .ok_or_else(session_not_found)?;
+        // Stopped sessions are recorded at the size they will be spawned with
+        let (rows, cols) = ctx
+            .pty_manager
+            .size(params.session_id)
+            .await
+            .unwrap_or((size.rows, size.cols));
+
+        ctx.recorder
+            .start(params.session_id, rows, cols, &name)
+            .map_err(RpcError::context("Failed to start recording"))
+    }
+}
+
+struct RecordingStop;
+
+impl RpcHandler<IpcContext> for RecordingStop {
+    type Params = SessionIdParams;
+    type Output = RecordingInfo;
+
+    async fn handle(&self, ctx: &IpcContext, params: SessionIdParams) -> RpcResult<RecordingInfo> {
+        ctx.recorder
+            .stop(params.session_id)
+            .map_err(RpcError::context("Failed to stop recording"))
+    }
+}
+
+struct RecordingList;
+
+impl RpcHandler<IpcContext> for RecordingList {
+    type Params = RecordingListParams;
+    type Output = Vec<RecordingInfo>;
+
+    async fn handle(
+        &self,
+        ctx: &IpcContext,
+        params: RecordingListParams,
+    ) -> RpcResult<Vec<RecordingInfo>> {
+        ctx.recorder
+            .list(params.session_id)
+            .map_err(RpcError::context("Failed to list recordings"))
+    }
+}
+
+struct RecordingExport;
+
+impl RpcHandler<IpcContext> for RecordingExport {
+    type Params = RecordingExportParams;
+    type Output = RecordingInfo;
+
+    async fn handle(
+        &self,
+        ctx: &IpcContext,
+        params: RecordingExportParams,
+    ) -> RpcResult<RecordingInfo> {
+        ctx.recorder
+            .export(params.recording_id, Path::new(&params.dest))
+            .map_err(RpcError::context("Failed to export recording"))
+    }
+}
+
+// --- Terminal ---
+
+/// Write input to a session's PTY, as a bracketed paste if `paste`
+async fn write_input(
+    ctx: &IpcContext,
+    session_id: Uuid,
+    data: &[u8],
+    paste: bool,
+) -> anyhow::Result<()> {
+    if paste {
+        ctx.pty_manager.paste(session_id, data).await
+    } else {
+        ctx.pty_manager.write(session_id, data).await
+    }
+}
+
+struct SessionInput;
+
+impl RpcHandler<IpcContext> for SessionInput {
+    type Params = SessionInputParams;
+    type Output = SessionInputResult;
+
+    async fn handle(
+        &self,
+        ctx: &IpcContext,
+        params: SessionInputParams,
+    ) -> RpcResult<SessionInputResult> {
+        // Try to decode as base64, fall back to raw bytes
+        let data = BASE64
+            .decode(&params.input)
+            .unwrap_or_else(|_| params.input.into_bytes());
+
+        let mut restarted = false;
+        let mut result = write_input(ctx, params.session_id, &data, params.paste).await;
+        if result.as_ref().is_err_and(|e| e.is::<PtyDead>())
+            && ctx.config.read().await.daemon.auto_start_on_input
+        {
+            info!(
+                "Auto-starting stopped session {} for input",
+                params.session_id
+            );
+            result = match SessionManager::restart_session(
+                &ctx.state,
+                &ctx.pty_manager,
+                ctx.output_tx.clone(),
+                &ctx.event_tx,
+                &ctx.hook_manager,
+                params.session_id,
+                TerminalSize::from_parts(params.rows, params.cols),
+                true,
+            )
+            .await
+            {
+                Ok(_) => {
+                    restarted = true;
+                    write_input(ctx, params.session_id, &data, params.paste).await
+                }
+                Err(e) => Err(e),
+            };
+        }
+        result.map_err(RpcError::context("Failed to write to session"))?;
+
+        // Enter submits a prompt, which starts a timed turn
+        if data.contains(&b'\r') {
+            let text = history::prompt_text(&data);
+            if let Some(text) = text.clone() {
+                input_history::record(&mut *ctx.state.write().await, params.session_id, text);
+                if let Err(e) = state::save_state(&ctx.state).await {
+                    warn!("Failed to save input history: {}", e);
+                }
+            }
+            ctx.turn_tracker.prompt_sent(params.session_id, text).await;
+        }
+
+        Ok(SessionInputResult {
+            success: true,
+            restarted,
+        })
+    }
+}
+
+/// Most presses one session.send_key may send
+const MAX_KEY_REPEAT: u32 = 100;
+
+struct SessionSendKey;
+
+impl RpcHandler<IpcContext> for SessionSendKey {
+    type Params = SendKeyParams;
+    type Output = SuccessResult;
+
+    async fn handle(&self, ctx: &IpcContext, params: SendKeyParams) -> RpcResult<SuccessResult> {
+        let repeat = params.repeat.unwrap_or(1);
+        if !(1..=MAX_KEY_REPEAT).contains(&repeat) {
+            return Err(RpcError::invalid_params(format!(
+                "repeat must be between 1 and {}",
+                MAX_KEY_REPEAT
+            )));
+        }
+        let application_cursor = ctx.screens.application_cursor(params.session_id);
+        let data = keys::bytes(params.key, application_cursor).repeat(repeat as usize);
+        ctx.pty_manager
+            .write(params.session_id, &data)
+            .await
+            .map_err(RpcError::context("Failed to send key"))?;
+
+        // Enter submits what was typed, which starts a timed turn
+        if params.key == Key::Enter {
+            ctx.turn_tracker.prompt_sent(params.session_id, None).await;
+        }
+        Ok(SuccessResult { success: true })
+    }
+}
+
+struct SessionInterrupt;
+
+impl RpcHandler<IpcContext> for SessionInterrupt {
+    type Params = SessionIdParams;
+    type Output = SuccessResult;
+
+    async fn handle(&self, ctx: &IpcContext, params: SessionIdParams) -> RpcResult<SuccessResult> {
+        let session_id = params.session_id;
+        let status = ctx
+            .state
+            .read()
+            .await
+            .sessions
+            .get(&session_id)
+            .map(|session| session.status)
+            .ok_or_else(session_not_found)?;
+        // Escape in an idle session would open claude's rewind menu instead
+        let running = status == SessionStatus::Running;
+        if !running && !ctx.approvals.is_pending(session_id) {
+            return Err(RpcError::invalid_params("session is not generating"));
+        }
+
+        ctx.pty_manager
+            .write(session_id, claude::INTERRUPT_KEYS)
+            .await
+            .map_err(RpcError::context("Failed to interrupt session"))?;
+        info!("Interrupted session {}", session_id);
+        history::record(session_id, HistoryKind::Interrupted);
+
+        // Escape can go unanswered, e.g. by a tool holding the terminal
+        if running {
+            let state = ctx.state.clone();
+            let pty_manager = ctx.pty_manager.clone();
+            tokio::spawn(async move {
+                tokio::time::sleep(std::time::Duration::from_millis(INTERRUPT_GRACE_MS)).await;
+                let still_running = state
+                    .read()
+                    .await
+                    .sessions
+                    .get(&session_id)
+                    .is_some_and(|session| session.status == SessionStatus::Running);
+                if still_running {
+                    info!("Session {} still running, sending Ctrl+C", session_id);
+                    if let Err(e) = pty_manager.write(session_id, claude::CANCEL_KEYS).await {
+                        warn!("Failed to send Ctrl+C to session {}: {}", session_id, e);
+                    }
+                }
+            });
+        }
+        Ok(SuccessResult { success: true })
+    }
+}
+
+struct SessionContinue;
+
+impl RpcHandler<IpcContext> for SessionContinue {
+    type Params = SessionContinueParams;
+    type Output = SuccessResult;
+
+    async fn handle(
+        &self,
+        ctx: &IpcContext,
+        params: SessionContinueParams,
+    ) -> RpcResult<SuccessResult> {
+        let session_id = params.session_id;
+        let status = ctx
+            .state
+            .read()
+            .await
+            .sessions
+            .get(&session_id)
+            .map(|session| session.status)
+            .ok_or_else(session_not_found)?;
+        if status == SessionStatus::Running {
+            return Err(RpcError::invalid_params("session is still generating"));
+        }
+
+        let prompt = params
+            .prompt
+            .filter(|prompt| !prompt.trim().is_empty())
+            .unwrap_or_else(|| CONTINUE_PROMPT.to_string());
+        ctx.pty_manager
+            .write(session_id, format!("{}\r", prompt).as_bytes())
+            .await
+            .map_err(RpcError::context("Failed to continue session"))?;
+        ctx.turn_tracker.prompt_sent(session_id, Some(prompt)).await;
+        Ok(SuccessResult { success: true })
+    }
+}
+
+struct SessionDetach;
+
+impl RpcHandler<IpcContext> for SessionDetach {
+    type Params = SessionIdParams;
+    type Output = Session;
+
+    async fn handle(&self, ctx: &IpcContext, params: SessionIdParams) -> RpcResult<Session> {
+        let session_id = params.session_id;
+        if !ctx.state.read().await.sessions.contains_key(&session_id) {
+            return Err(session_not_found());
+        }
+        let detached = ctx
+            .detached
+            .detach(session_id)
+            .map_err(RpcError::context("Failed to detach session"))?;
+        if !detached {
+            return Err(RpcError::invalid_params("session is already detached"));
+        }
+        info!("Detached session {}", session_id);
+        SessionManager::set_detached(&ctx.state, &ctx.event_tx, session_id, true)
+            .await
+            .map_err(RpcError::context("Failed to detach session"))
+    }
+}
+
+struct SessionAttach;
+
+impl RpcHandler<IpcContext> for SessionAttach {
+    type Params = SessionIdParams;
+    type Output = SessionAttachResult;
+
+    async fn handle(
+        &self,
+        ctx: &IpcContext,
+        params: SessionIdParams,
+    ) -> RpcResult<SessionAttachResult> {
+        let session_id = params.session_id;
+        let backfill = ctx
+            .detached
+            .attach(session_id)
+            .ok_or_else(|| RpcError::invalid_params("session is not detached"))?;
+        info!("Attached session {}", session_id);
+        let session = SessionManager::set_detached(&ctx.state, &ctx.event_tx, session_id, false)
+            .await
+            .map_err(RpcError::context("Failed to attach session"))?;
+
+        let (output, from_screen) = match backfill {
+            Backfill::Output(data) => (BASE64.encode(data), false),
+            Backfill::Screen => {
+                let screen = ctx.screens.get(session_id);
+                (
+                    screen.map(|screen| screen.formatted).unwrap_or_default(),
+                    true,
+                )
+            }
+        };
+        Ok(SessionAttachResult {
+            session,
+            output,
+            from_screen,
+        })
+    }
+}
+
+struct SessionMarkViewed;
+
+impl RpcHandler<IpcContext> for SessionMarkViewed {
+    type Params = SessionIdParams;
+    type Output = Session;
+
+    async fn handle(&self, ctx: &IpcContext, params: SessionIdParams) -> RpcResult<Session> {
+        SessionManager::mark_viewed(&ctx.state, &ctx.event_tx, params.session_id)
+            .await
+            .map_err(RpcError::context("Failed to mark session viewed"))
+    }
+}
+
+struct SessionApprove;
+
+impl RpcHandler<IpcContext> for SessionApprove {
+    type Params = ApprovalParams;
+    type Output = SuccessResult;
+
+    async fn handle(&self, ctx: &IpcContext, params: ApprovalParams) -> RpcResult<SuccessResult> {
+        answer_approval(ctx, params, true).await
+    }
+}
+
+struct SessionDeny;
+
+impl RpcHandler<IpcContext> for SessionDeny {
+    type Params = ApprovalParams;
+    type Output = SuccessResult;
+
+    async fn handle(&self, ctx: &IpcContext, params: ApprovalParams) -> RpcResult<SuccessResult> {
+        answer_approval(ctx, params, false).await
+    }
+}
+
+/// Answer an open permission dialog with its keys
+async fn answer_approval(
+    ctx: &IpcContext,
+    params: ApprovalParams,
+    approved: bool,
+) -> RpcResult<SuccessResult> {
+    if !ctx.approvals.answer(params.session_id, params.approval_id) {
+        return Err(RpcError::invalid_params("approval is not pending"));
+    }
+    let keys = if approved {
+        approvals::APPROVE_KEYS
+    } else {
+        approvals::DENY_KEYS
+    };
+    ctx.pty_manager
+        .write(params.session_id, keys)
+        .await
+        .map_err(RpcError::context("Failed to answer approval"))?;
+    let _ = ctx
+        .event_tx
+        .send(Event::ApprovalResolved(ApprovalResolvedData {
+            session_id: params.session_id,
+            approval_id: params.approval_id,
+            approved: Some(approved),
+        }));
+    Ok(SuccessResult { success: true })
+}
+
+struct SessionResize;
+
+impl RpcHandler<IpcContext> for SessionResize {
+    type Params = SessionResizeParams;
+    type Output = SuccessResult;
+
+    async fn handle(
+        &self,
+        ctx: &IpcContext,
+        params: SessionResizeParams,
+    ) -> RpcResult<SuccessResult> {
+        ctx.pty_manager
+            .resize(params.session_id, params.rows, params.cols)
+            .await
+            .map_err(RpcError::context("Failed to resize session"))?;
+        ctx.recorder
+            .resize(params.session_id, params.rows, params.cols);
+        ctx.screens
+            .resize(params.session_id, params.rows, params.cols);
+
+        // Remembered so the next start spawns at this size
+        let size = TerminalSize {
+            rows: params.rows,
+            cols: params.cols,
+        };
+        let changed = ctx
+            .state
+            .write()
+            .await
+            .sessions
+            .get_mut(&params.session_id)
+            .is_some_and(|session| session.size.replace(size) != Some(size));
+        if changed {
+            state::save_state(&ctx.state)
+                .await
+                .map_err(RpcError::context("Failed to save session size"))?;
+        }
+        Ok(SuccessResult { success: true })
+    }
+}
+
+struct SessionRestart;
+
+impl RpcHandler<IpcContext> for SessionRestart {
+    type Params = SessionRestartParams;
+    type Output = SessionCreatedResult;
+
+    async fn handle(
+        &self,
+        ctx: &IpcContext,
+        params: SessionRestartParams,
+    ) -> RpcResult<SessionCreatedResult> {
+        let size = TerminalSize::from_parts(params.rows, params.cols);
+        info!(
+            "session.restart for session_id: {} with size {:?}",
+            params.session_id, size
+        );
+        let session = SessionManager::restart_session(
+            &ctx.state,
+            &ctx.pty_manager,
+            ctx.output_tx.clone(),
+            &ctx.event_tx,
+            &ctx.hook_manager,
+            params.session_id,
+            size,
+            false,
+        )
+        .await
+        .map_err(RpcError::context("Failed to restart session"))?;
+        Ok(SessionCreatedResult { session })
+    }
+}
+
+struct SessionEnqueueStart;
+
+impl RpcHandler<IpcContext> for SessionEnqueueStart {
+    type Params = EnqueueStartParams;
+    type Output = SessionCreatedResult;
+
+    async fn handle(
+        &self,
+        ctx: &IpcContext,
+        params: EnqueueStartParams,
+    ) -> RpcResult<SessionCreatedResult> {
+        let session = ctx
+            .run_queue
+            .enqueue(
+                params.session_id,
+                TerminalSize::from_parts(params.rows, params.cols),
+                params.resume,
+            )
+            .await
+            .map_err(RpcError::context("Failed to enqueue session start"))?;
+        Ok(SessionCreatedResult { session })
+    }
+}
+
+struct SessionSetDependency;
+
+impl RpcHandler<IpcContext> for SessionSetDependency {
+    type Params = SetDependencyParams;
+    type Output = Session;
+
+    async fn handle(&self, ctx: &IpcContext, params: SetDependencyParams) -> RpcResult<Session> {
+        let dependency = params.parent_id.map(|parent_id| SessionDependency {
+            parent_id,
+            prompt: params.prompt.filter(|prompt| !prompt.trim().is_empty()),
+        });
+        dependencies::set(&ctx.state, &ctx.event_tx, params.session_id, dependency)
+            .await
+            .map_err(RpcError::context("Failed to set dependency"))
+    }
+}
+
+struct SessionFork;
+
+impl RpcHandler<IpcContext> for SessionFork {
+    type Params = ForkSessionParams;
+    type Output = SessionCreatedResult;
+
+    async fn handle(
+        &self,
+        ctx: &IpcContext,
+        params: ForkSessionParams,
+    ) -> RpcResult<SessionCreatedResult> {
+        let size = TerminalSize::from_parts(params.rows, params.cols);
+        info!(
+            "session.fork for session_id: {} with size {:?}",
+            params.session_id, size
+        );
+        let session = SessionManager::fork_session(
+            &ctx.state,
+            &ctx.pty_manager,
+            ctx.output_tx.clone(),
+            &ctx.event_tx,
+            &ctx.hook_manager,
+            params.session_id,
+            params.new_name,
+            params.group_id,
+            size,
+            params.worktree_branch,
+            params.worktree_path.map(PathBuf::from),
+        )
+        .await
+        .map_err(RpcError::context("Failed to fork session"))?;
+        Ok(SessionCreatedResult { session })
+    }
+}
+
+// --- Groups and ordering ---
+
+// --- Schedules ---
+
+struct ScheduleCreate;
+
+impl RpcHandler<IpcContext> for ScheduleCreate {
+    type Params = CreateScheduleParams;
+    type Output = Schedule;
+
+    async fn handle(&self, ctx: &IpcContext, params: CreateScheduleParams) -> RpcResult<Schedule> {
+        info!("schedule.create: name={} cron={}", params.name, params.cron);
+        Cron::parse(&params.cron).map_err(RpcError::invalid_params)?;
+        scheduler::create(&ctx.state, params)
+            .await
+            .map_err(RpcError::context("Failed to create schedule"))
+    }
+}
+
+struct ScheduleList;
+
+impl RpcHandler<IpcContext> for ScheduleList {
+    type Params = NoParams;
+    type Output = ScheduleListResult;
+
+    async fn handle(&self, ctx: &IpcContext, _: NoParams) -> RpcResult<ScheduleListResult> {
+        let mut schedules: Vec<Schedule> =
+            ctx.state.read().await.schedules.values().cloned().collect();
+        schedules.sort_by_key(|schedule| schedule.created_at);
+        Ok(ScheduleListResult { schedules })
+    }
+}
+
+struct ScheduleDelete;
+
+impl RpcHandler<IpcContext> for ScheduleDelete {
+    type Params = ScheduleIdParams;
+    type Output = SuccessResult;
+
+    async fn handle(&self, ctx: &IpcContext, params: ScheduleIdParams) -> RpcResult<SuccessResult> {
+        scheduler::delete(&ctx.state, params.schedule_id)
+            .await
+            .map_err(RpcError::context("Failed to delete schedule"))?;
+        Ok(SuccessResult { success: true })
+    }
+}
+
+/// A snippet's name and text, checked before saving
+fn check_snippet(name: Option<&str>, text: Option<&str>) -> RpcResult<()> {
+    if name.is_some_and(|name| name.trim().is_empty()) {
+        return Err(RpcError::invalid_params("name must not be empty"));
+    }
+    if text.is_some_and(|text| text.trim().is_empty()) {
+        return Err(RpcError::invalid_params("text must not be empty"));
+    }
+    Ok(())
+}
+
+struct SnippetList;
+
+impl RpcHandler<IpcContext> for SnippetList {
+    type Params = NoParams;
+    type Output = SnippetListResult;
+
+    async fn handle(&self, ctx: &IpcContext, _: NoParams) -> RpcResult<SnippetListResult> {
+        let mut snippets: Vec<Snippet> =
+            ctx.state.read().await.snippets.values().cloned().collect();
+        snippets.sort_by_cached_key(|snippet| snippet.name.to_lowercase());
+        Ok(SnippetListResult { snippets })
+    }
+}
+
+struct SnippetCreate;
+
+impl RpcHandler<IpcContext> for SnippetCreate {
+    type Params = CreateSnippetParams;
+    type Output = Snippet;
+
+    async fn handle(&self, ctx: &IpcContext, params: CreateSnippetParams) -> RpcResult<Snippet> {
+        check_snippet(Some(&params.name), Some(&params.text))?;
+        snippets::create(&ctx.state, params)
+            .await
+            .map_err(RpcError::context("Failed to create snippet"))
+    }
+}
+
+struct SnippetUpdate;
+
+impl RpcHandler<IpcContext> for SnippetUpdate {
+    type Params = UpdateSnippetParams;
+    type Output = Snippet;
+
+    async fn handle(&self, ctx: &IpcContext, params: UpdateSnippetParams) -> RpcResult<Snippet> {
+        check_snippet(params.name.as_deref(), params.text.as_deref())?;
+        snippets::update(&ctx.state, params)
+            .await
+            .map_err(RpcError::context("Failed to update snippet"))
+    }
+}
+
+struct SnippetDelete;
+
+impl RpcHandler<IpcContext> for SnippetDelete {
+    type Params = SnippetIdParams;
+    type Output = SuccessResult;
+
+    async fn handle(&self, ctx: &IpcContext, params: SnippetIdParams) -> RpcResult<SuccessResult> {
+        snippets::delete(&ctx.state, params.snippet_id)
+            .await
+            .map_err(RpcError::context("Failed to delete snippet"))?;
+        Ok(SuccessResult { success: true })
+    }
+}
+
+/// Fills in the snippet's variables and submits it as a prompt
+struct SessionSendSnippet;
+
+impl RpcHandler<IpcContext> for SessionSendSnippet {
+    type Params = SendSnippetParams;
+    type Output = SendSnippetResult;
+
+    async fn handle(
+        &self,
+        ctx: &IpcContext,
+        params: SendSnippetParams,
+    ) -> RpcResult<SendSnippetResult> {
+        let text = {
+            let s = ctx.state.read().await;
+            let snippet = s.snippets.get(&params.snippet_id).ok_or_else(|| {
+                RpcError::invalid_params(format!("Snippet not found: {}", params.snippet_id))
+            })?;
+            let session = s
+                .sessions
+                .get(&params.session_id)
+                .ok_or_else(session_not_found)?;
+            let mut vars = snippets::session_vars(&s, session);
+            vars.extend(params.vars);
+            snippets::expand(&snippet.text, &vars).map_err(RpcError::invalid_params)?
+        };
+
+        ctx.pty_manager
+            .write(params.session_id, format!("{}\r", text).as_bytes())
+            .await
+            .map_err(RpcError::context("Failed to send snippet"))?;
+        ctx.turn_tracker
+            .prompt_sent(params.session_id, Some(text.clone()))
+            .await;
+        Ok(SendSnippetResult { text })
+    }
+}
+
+struct ProjectsScan;
+
+impl RpcHandler<IpcContext> for ProjectsScan {
+    type Params = ScanProjectsParams;
+    type Output = ScanProjectsResult;
+
+    async fn handle(
+        &self,
+        ctx: &IpcContext,
+        params: ScanProjectsParams,
+    ) -> RpcResult<ScanProjectsResult> {
+        let projects = projects::scan(&ctx.state, params.limit).await;
+        Ok(ScanProjectsResult { projects })
+    }
+}
+
+/// The project directory of an mcp.* request
+fn mcp_dir(dir: String) -> RpcResult<PathBuf> {
+    let dir = PathBuf::from(dir);
+    if !dir.is_dir() {
+        return Err(RpcError::invalid_params(format!(
+            "{} is not a directory",
+            dir.display()
+        )));
+    }
+    Ok(dir)
+}
+
+struct McpList;
+
+impl RpcHandler<IpcContext> for McpList {
+    type Params = McpListParams;
+    type Output = McpListResult;
+
+    async fn handle(&self, _: &IpcContext, params: McpListParams) -> RpcResult<McpListResult> {
+        let servers = mcp::list(&mcp_dir(params.dir)?)
+            .map_err(RpcError::context("Failed to read MCP servers"))?;
+        Ok(McpListResult { servers })
+    }
+}
+
+struct McpAdd;
+
+impl RpcHandler<IpcContext> for McpAdd {
+    type Params = McpAddParams;
+    type Output = SuccessResult;
+
+    async fn handle(&self, _: &IpcContext, params: McpAddParams) -> RpcResult<SuccessResult> {
+        if params.name.trim().is_empty() {
+            return Err(RpcError::invalid_params("name is empty"));
+        }
+        mcp::add(&mcp_dir(params.dir)?, params.name.trim(), params.config)
+            .map_err(RpcError::context("Failed to add MCP server"))?;
+        Ok(SuccessResult { success: true })
+    }
+}
+
+struct McpRemove;
+
+impl RpcHandler<IpcContext> for McpRemove {
+    type Params = McpServerParams;
+    type Output = SuccessResult;
+
+    async fn handle(&self, _: &IpcContext, params: McpServerParams) -> RpcResult<SuccessResult> {
+        mcp::remove(&mcp_dir(params.dir)?, &params.name)
+            .map_err(RpcError::context("Failed to remove MCP server"))?;
+        Ok(SuccessResult { success: true })
+    }
+}
+
+struct McpSetEnabled;
+
+impl RpcHandler<IpcContext> for McpSetEnabled {
+    type Params = McpSetEnabledParams;
+    type Output = SuccessResult;
+
+    async fn handle(
+        &self,
+        _: &IpcContext,
+        params: McpSetEnabledParams,
+    ) -> RpcResult<SuccessResult> {
+        let dir = mcp_dir(params.dir)?;
+        if !mcp::list(&dir)
+            .map_err(RpcError::context("Failed to read MCP servers"))?
+            .iter()
+            .any(|server| server.name == params.name)
+        {
+            return Err(RpcError::invalid_params(format!(
+                "No MCP server named '{}'",
+                params.name
+            )));
+        }
+        mcp::set_enabled(&dir, &params.name, params.enabled)
+            .map_err(RpcError::context("Failed to update MCP server"))?;
+        Ok(SuccessResult { success: true })
+    }
+}
+
+struct DaemonClaudeInfo;
+
+impl RpcHandler<IpcContext> for DaemonClaudeInfo {
+    type Params = NoParams;
+    type Output = ClaudeInfo;
+
+    async fn handle(&self, ctx: &IpcContext, _: NoParams) -> RpcResult<ClaudeInfo> {
+        Ok(ctx.claude_check.check().await)
+    }
+}
+
+struct ClaudeCapabilitiesHandler;
+
+impl RpcHandler<IpcContext> for ClaudeCapabilitiesHandler {
+    type Params = ClaudeBinaryParams;
+    type Output = ClaudeCapabilities;
+
+    async fn handle(
+        &self,
+        ctx: &IpcContext,
+        params: ClaudeBinaryParams,
+    ) -> RpcResult<ClaudeCapabilities> {
+        let claude = ctx.config.read().await.claude.clone();
+        let resolver = ClaudeResolver::for_binary(params.binary.as_deref(), &claude)
+            .map_err(RpcError::invalid_params)?;
+        let mut capabilities = resolver.capabilities().await;
+        capabilities.binaries = claude.binaries.into_keys().collect();
+        capabilities.binaries.sort();
+        Ok(capabilities)
+    }
+}
+
+struct TreeSnapshotHandler;
+
+impl RpcHandler<IpcContext> for TreeSnapshotHandler {
+    type Params = NoParams;
+    type Output = TreeSnapshot;
+
+    async fn handle(&self, ctx: &IpcContext, _: NoParams) -> RpcResult<TreeSnapshot> {
+        Ok(state::tree_snapshot(&*ctx.state.read().await))
+    }
+}
+
+struct SummaryGet;
+
+impl RpcHandler<IpcContext> for SummaryGet {
+    type Params = NoParams;
+    type Output = SummaryResult;
+
+    async fn handle(&self, ctx: &IpcContext, _: NoParams) -> RpcResult<SummaryResult> {
+        Ok(state::summary(&*ctx.state.read().await))
+    }
+}
+
+struct GroupList;
+
+impl RpcHandler<IpcContext> for GroupList {
+    type Params = NoParams;
+    type Output = GroupListResult;
+
+    async fn handle(&self, ctx: &IpcContext, _: NoParams) -> RpcResult<GroupListResult> {
+        let s = ctx.state.read().await;
+        let groups = s.groups.values().cloned().collect();
+        let statuses = group_status::group_statuses(&s).into_values().collect();
+        Ok(GroupListResult { groups, statuses })
+    }
+}
+
+struct GroupCreate;
+
+impl RpcHandler<IpcContext> for GroupCreate {
+    type Params = CreateGroupParams;
+    type Output = GroupCreatedResult;
+
+    async fn handle(
+        &self,
+        ctx: &IpcContext,
+        params: CreateGroupParams,
+    ) -> RpcResult<GroupCreatedResult> {
+        let group =
+            SessionManager::create_group(&ctx.state, &ctx.event_tx, params.name, params.parent_id)
+                .await
+                .map_err(RpcError::context("Failed to create group"))?;
+        Ok(GroupCreatedResult { group })
+    }
+}
+
+struct GroupSetCompletionWatch;
+
+impl RpcHandler<IpcContext> for GroupSetCompletionWatch {
+    type Params = SetCompletionWatchParams;
+    type Output = Group;
+
+    async fn handle(&self, ctx: &IpcContext, params: SetCompletionWatchParams) -> RpcResult<Group> {
+        let url = params.watch.as_ref().and_then(|w| w.webhook_url.as_deref());
+        if url.is_some_and(|url| !url.starts_with("http://") && !url.starts_with("https://")) {
+            return Err(RpcError::invalid_params(
+                "webhook_url must be an http:// or https:// URL",
+            ));
+        }
+        completion::set(&ctx.state, &ctx.event_tx, params.group_id, params.watch)
+            .await
+            .map_err(RpcError::context("Failed to set completion watch"))
+    }
+}
+
+struct GroupDelete;
+
+impl RpcHandler<IpcContext> for GroupDelete {
+    type Params = DeleteGroupParams;
+    type Output = GroupDeleteResult;
+
+    async fn handle(
+        &self,
+        ctx: &IpcContext,
+        params: DeleteGroupParams,
+    ) -> RpcResult<GroupDeleteResult> {
+        info!(
+            "group.delete: group_id={} mode={:?} dry_run={}",
+            params.group_id, params.mode, params.dry_run
+        );
+        let result = SessionManager::delete_group(
+            &ctx.state,
+            &ctx.pty_manager,
+            &ctx.event_tx,
+            params.group_id,
+            params.mode,
+            params.dry_run,
+        )
+        .await
+        .map_err(RpcError::context("Failed to delete group"))?;
+        if params.mode == GroupDeleteMode::Cascade && !result.dry_run {
+            for &session_id in &result.sessions {
+                forget_session(ctx, session_id);
+            }
+        }
+        Ok(result)
+    }
+}
+
+struct GroupUpdate;
+
+impl RpcHandler<IpcContext> for GroupUpdate {
+    type Params = UpdateGroupParams;
+    type Output = Group;
+
+    async fn handle(&self, ctx: &IpcContext, params: UpdateGroupParams) -> RpcResult<Group> {
+        SessionManager::update_group(
+            &ctx.state,
+            &ctx.event_tx,
+            params.group_id,
+            params.name,
+            params.parent_id,
+        )
+        .await
+        .map_err(RpcError::context("Failed to update group"))
+    }
+}
+
+struct SessionReorderHandler;
+
+impl RpcHandler<IpcContext> for SessionReorderHandler {
+    type Params = SessionReorder;
+    type Output = Vec<Session>;
+
+    async fn handle(&self, ctx: &IpcContext, params: SessionReorder) -> RpcResult<Vec<Session>> {
+        let (group_id, placement) = match params {
+            SessionReorder::Order(order) => (order.group_id, Placement::Order(order.session_ids)),
+            SessionReorder::Move(mv) => (
+                mv.group_id,
+                Placement::After(mv.session_id, mv.after_session_id),
+            ),
+        };
+        let sessions = state::reorder_sessions(&ctx.state, group_id, placement)
+            .await
+            .map_err(RpcError::context("Failed to reorder sessions"))?;
+
+        if let Err(e) = state::save_state(&ctx.state).await {
+            error!("Failed to save state after session reorder: {}", e);
+        }
+        // Renumbered siblings too, so every client ends up with the same order
+        for session in &sessions {
+            let _ = ctx.event_tx.send(Event::SessionUpdated(session.clone()));
+        }
+        Ok(sessions)
+    }
+}
+
+struct GroupReorderHandler;
+
+impl RpcHandler<IpcContext> for GroupReorderHandler {
+    type Params = GroupReorder;
+    type Output = Vec<Group>;
+
+    async fn handle(&self, ctx: &IpcContext, params: GroupReorder) -> RpcResult<Vec<Group>> {
+        let (parent_id, placement) = match params {
+            GroupReorder::Order(order) => (order.parent_id, Placement::Order(order.group_ids)),
+            GroupReorder::Move(mv) => (
+                mv.parent_id,
+                Placement::After(mv.group_id, mv.after_group_id),
+            ),
+        };
+        let groups = state::reorder_groups(&ctx.state, parent_id, placement)
+            .await
+            .map_err(RpcError::context("Failed to reorder groups"))?;
+
+        if let Err(e) = state::save_state(&ctx.state).await {
+            error!("Failed to save state after group reorder: {}", e);
+        }
+        for group in &groups {
+            let _ = ctx.event_tx.send(Event::GroupUpdated(group.clone()));
+        }
+        Ok(groups)
+    }
+}
+
+// --- Export and import ---
+
+struct StateExport;
+
+impl RpcHandler<IpcContext> for StateExport {
+    type Params = StateExportParams;
+    type Output = StateExportResult;
+
+    async fn handle(
+        &self,
+        ctx: &IpcContext,
+        params: StateExportParams,
+    ) -> RpcResult<StateExportResult> {
+        backup::export(&ctx.state, Path::new(&params.path), params.include_logs)
+            .await
+            .map_err(RpcError::context("Failed to export state"))
+    }
+}
+
+struct StateSyncHandler;
+
+impl RpcHandler<IpcContext> for StateSyncHandler {
+    type Params = StateSyncParams;
+    type Output = StateSyncResult;
+
+    async fn handle(
+        &self,
+        ctx: &IpcContext,
+        params: StateSyncParams,
+    ) -> RpcResult<StateSyncResult> {
+        let s = ctx.state.read().await;
+        Ok(ctx.sync_tracker.sync(&s, params.since, params.tracker_id))
+    }
+}
+
+struct StateImport;
+
+impl RpcHandler<IpcContext> for StateImport {
+    type Params = StateImportParams;
+    type Output = StateImportResult;
+
+    async fn handle(
+        &self,
+        ctx: &IpcContext,
+        params: StateImportParams,
+    ) -> RpcResult<StateImportResult> {
+        backup::import(&ctx.state, &ctx.event_tx, Path::new(&params.path))
+            .await
+            .map_err(RpcError::context("Failed to import state"))
+    }
+}
@@ -0,0 +1,237 @@
+// Session history - append-only per-session timeline persisted as JSON lines
+// Lives next to the state files so it survives daemon restarts. Entries are
+// written by a background task, so recording never blocks the caller on disk,
+// and each session keeps its newest MAX_HISTORY_ENTRIES: a file is rewritten
+// with just those once it holds twice as many.
+
+use anyhow::Result;
+use shared::{HistoryEntry, HistoryKind};
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use tokio::sync::mpsc;
+use tracing::warn;
+use uuid::Uuid;
+
+use crate::config::get_state_dir;
+
+/// Longest prompt text kept in the timeline
+const MAX_PROMPT_CHARS: usize = 2000;
+
+/// Entries kept per session
+const MAX_HISTORY_ENTRIES: usize = 1000;
+
+/// Entries written together at most
+const WRITE_BATCH: usize = 256;
+
+/// Queue of the writer task; unset until it starts, and entries recorded
+/// without it (only in tests) are dropped
+static WRITER: OnceLock<mpsc::UnboundedSender<(Uuid, HistoryEntry)>> = OnceLock::new();
+
+fn history_dir() -> Result<PathBuf> {
+    let dir = get_state_dir()?.join("history");
+    fs::create_dir_all(&dir)?;
+    Ok(dir)
+}
+
+fn history_path(dir: &Path, session_id: Uuid) -> PathBuf {
+    dir.join(format!("{}.jsonl", session_id))
+}
+
+/// Lines in each session's file, counted when the writer first appends to it
+type LineCounts = HashMap<Uuid, usize>;
+
+/// Append `entries` to their sessions' files, trimming files that grew to
+/// twice MAX_HISTORY_ENTRIES
+fn write_batch(dir: &Path, counts: &mut LineCounts, entries: Vec<(Uuid, HistoryEntry)>) {
+    let mut by_session: HashMap<Uuid, String> = HashMap::new();
+    let mut added: HashMap<Uuid, usize> = HashMap::new();
+    for (session_id, entry) in entries {
+        match serde_json::to_string(&entry) {
+            Ok(line) => {
+                let lines = by_session.entry(session_id).or_default();
+                lines.push_str(&line);
+                lines.push('\n');
+                *added.entry(session_id).or_default() += 1;
+            }
+            Err(e) => warn!("Failed to encode history for session {}: {}", session_id, e),
+        }
+    }
+
+    for (session_id, lines) in by_session {
+        let path = history_path(dir, session_id);
+        let result = (|| -> Result<()> {
+            let count = counts
+                .entry(session_id)
+                .or_insert_with(|| read_lines(&path).map_or(0, |lines| lines.len()));
+            OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&path)?
+                .write_all(lines.as_bytes())?;
+            *count += added[&session_id];
+            if *count >= MAX_HISTORY_ENTRIES * 2 {
+                *count = trim(&path)?;
+            }
+            Ok(())
+        })();
+        if let Err(e) = result {
+            warn!("Failed to record history for session {}: {}", session_id, e);
+            counts.remove(&session_id);
+        }
+    }
+}
+
+/// Rewrite the file with its newest MAX_HISTORY_ENTRIES lines; returns how many are left
+fn trim(path: &Path) -> Result<usize> {
+    let lines = read_lines(path)?;
+    let kept = &lines[lines.len().saturating_sub(MAX_HISTORY_ENTRIES)..];
+    let tmp = path.with_extension("jsonl.tmp");
+    fs::write(
+        &tmp,
+        kept.iter()
+            .map(|line| format!("{}\n", line))
+            .collect::<String>(),
+    )?;
+    fs::rename(&tmp, path)?;
+    Ok(kept.len())
+}
+
+fn read_lines(path: &Path) -> Result<Vec<String>> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    Ok(fs::read_to_string(path)?
+        .lines()
+        .map(str::to_string)
+        .collect())
+}
+
+/// Start the task that writes recorded entries
+pub fn spawn_writer() {
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    if WRITER.set(tx).is_err() {
+        return;
+    }
+    tokio::spawn(async move {
+        let dir = match history_dir() {
+            Ok(dir) => dir,
+            Err(e) => {
+                warn!("Session history is off, no history dir: {}", e);
+                return;
+            }
+        };
+        let mut counts = LineCounts::new();
+        let mut batch = Vec::new();
+        while rx.recv_many(&mut batch, WRITE_BATCH).await > 0 {
+            let entries = std::mem::take(&mut batch);
+            let dir = dir.clone();
+            let mut taken = std::mem::take(&mut counts);
+            counts = tokio::task::spawn_blocking(move || {
+                write_batch(&dir, &mut taken, entries);
+                taken
+            })
+            .await
+            .unwrap_or_default();
+        }
+    });
+}
+
+fn read(dir: &Path, session_id: Uuid) -> Result<Vec<HistoryEntry>> {
+    Ok(read_lines(&history_path(dir, session_id))?
+        .iter()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect())
+}
+
+/// Load a session's timeline, oldest first (skipping unreadable lines)
+pub async fn load(session_id: Uuid) -> Result<Vec<HistoryEntry>> {
+    tokio::task::spawn_blocking(move || read(&history_dir()?, session_id)).await?
+}
+
+/// Load a session's timeline from a thread that may block
+pub fn load_blocking(session_id: Uuid) -> Result<Vec<HistoryEntry>> {
+    read(&history_dir()?, session_id)
+}
+
+/// Record an entry in a session's timeline
+/// History is best-effort: failures are logged, never propagated
+pub fn record(session_id: Uuid, kind: HistoryKind) {
+    if let Some(writer) = WRITER.get() {
+        let _ = writer.send((session_id, HistoryEntry::new(kind)));
+    }
+}
+
+/// Text submitted by a write to a session's PTY, for PromptSent entries
+/// Returns None when only Enter was sent (the prompt was typed key by key).
+pub fn prompt_text(data: &[u8]) -> Option<String> {
+    let end = data.iter().rposition(|&b| b == b'\r')?;
+    let text = String::from_utf8_lossy(&data[..end]);
+    let text = text.trim();
+    (!text.is_empty()).then(|| text.chars().take(MAX_PROMPT_CHARS).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prompt_text() {
+        assert_eq!(prompt_text(b"\r"), None);
+        assert_eq!(prompt_text(b"abc"), None);
+        assert_eq!(
+            prompt_text(b"fix the tests\r"),
+            Some("fix the tests".to_string())
+        );
+        let long = "x".repeat(MAX_PROMPT_CHARS + 10) + "\r";
+        assert_eq!(
+            prompt_text(long.as_bytes()).map(|t| t.len()),
+            Some(MAX_PROMPT_CHARS)
+        );
+    }
+
+    #[test]
+    fn test_write_batch_trims() {
+        let dir = std::env::temp_dir().join(format!("cm-history-{}", Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let (a, b) = (Uuid::new_v4(), Uuid::new_v4());
+        let prompt = |i: usize| HistoryKind::PromptSent {
+            text: Some(i.to_string()),
+        };
+        let mut counts = LineCounts::new();
+
+        write_batch(
+            &dir,
+            &mut counts,
+            vec![
+                (a, HistoryEntry::new(prompt(0))),
+                (b, HistoryEntry::new(prompt(0))),
+            ],
+        );
+        assert_eq!(read(&dir, a).unwrap().len(), 1);
+        assert_eq!(read(&dir, b).unwrap().len(), 1);
+
+        // Reaching twice the cap leaves the newest MAX_HISTORY_ENTRIES
+        let entries = (1..MAX_HISTORY_ENTRIES * 2)
+            .map(|i| (a, HistoryEntry::new(prompt(i))))
+            .collect();
+        write_batch(&dir, &mut counts, entries);
+        let kept = read(&dir, a).unwrap();
+        assert_eq!(kept.len(), MAX_HISTORY_ENTRIES);
+        assert_eq!(
+            kept.last().unwrap().kind,
+            prompt(MAX_HISTORY_ENTRIES * 2 - 1)
+        );
+        assert_eq!(counts[&a], MAX_HISTORY_ENTRIES);
+        assert_eq!(read(&dir, b).unwrap().len(), 1);
+
+        // A writer that starts over counts the lines already there
+        let mut counts = LineCounts::new();
+        write_batch(&dir, &mut counts, vec![(a, HistoryEntry::new(prompt(0)))]);
+        assert_eq!(counts[&a], MAX_HISTORY_ENTRIES + 1);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
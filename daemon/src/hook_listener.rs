@@ -4,11 +4,14 @@
 use anyhow::Result;
 use serde::Deserialize;
 use std::path::PathBuf;
+use std::sync::Arc;
 use tokio::io::AsyncReadExt;
-use tokio::net::UnixListener;
+use tokio::net::{UnixListener, UnixStream};
 use tokio::sync::mpsc;
 use tracing::{debug, error, info, warn};
 
+use crate::hook_manager::{HookAuthenticator, SignedEvent};
+
 /// Event sent by Claude hooks
 #[derive(Debug, Clone, Deserialize)]
 pub struct HookEvent {
@@ -18,20 +21,53 @@ pub struct HookEvent {
     pub state: String,
     /// The hook event type (tool_approval, tool_complete, stopped)
     pub event: String,
+    /// Tool about to run, for tool_approval
+    #[serde(default)]
+    pub tool: Option<String>,
+    /// Its file_path input, for file tools
+    #[serde(default)]
+    pub path: Option<String>,
+    /// Its command input, for Bash
+    #[serde(default)]
+    pub command: Option<String>,
     /// Unix timestamp when the event occurred
-    #[allow(dead_code)]
     pub ts: u64,
+    /// HMAC of "session_id|ts|event|state\ntool\npath\ncommand" keyed with
+    /// the session's AGENT_DECK_TOKEN (see HookAuthenticator)
+    #[serde(default)]
+    pub mac: String,
+}
+
+/// Longest hook event accepted (tool_approval events carry the Bash command)
+const MAX_EVENT_BYTES: usize = 64 * 1024;
+
+/// Read one newline-terminated event. Not read to EOF: some nc builds keep
+/// their end open after sending.
+async fn read_event(stream: &mut UnixStream) -> std::io::Result<Vec<u8>> {
+    let mut data = Vec::new();
+    let mut buf = [0u8; 4096];
+    loop {
+        let n = stream.read(&mut buf).await?;
+        data.extend_from_slice(&buf[..n]);
+        if n == 0 || buf[..n].contains(&b'\n') || data.len() >= MAX_EVENT_BYTES {
+            return Ok(data);
+        }
+    }
 }
 
 /// Listens for hook events on a Unix socket
 pub struct HookListener {
     socket_path: PathBuf,
+    authenticator: Arc<HookAuthenticator>,
 }
 
 impl HookListener {
-    /// Create a new hook listener
-    pub fn new(socket_path: PathBuf) -> Self {
-        Self { socket_path }
+    /// Create a new hook listener that only accepts events signed by `authenticator`
+    pub fn new(socket_path: PathBuf, authenticator: Arc<HookAuthenticator>) -> Self {
+        Self {
+            socket_path,
+            authenticator,
+        }
     }
 
     /// Start listening for hook events
@@ -47,17 +83,33 @@ impl HookListener {
             match listener.accept().await {
                 Ok((mut stream, _)) => {
                     let tx = tx.clone();
+                    let authenticator = self.authenticator.clone();
 
                     tokio::spawn(async move {
-                        let mut buf = vec![0u8; 1024];
-                        match stream.read(&mut buf).await {
-                            Ok(0) => {
+                        match read_event(&mut stream).await {
+                            Ok(data) if data.is_empty() => {
                                 // Connection closed
                             }
-                            Ok(n) => {
-                                let data = &buf[..n];
+                            Ok(data) => {
+                                let data = &data[..];
                                 match serde_json::from_slice::<HookEvent>(data) {
                                     Ok(event) => {
+                                        let signed = SignedEvent {
+                                            session_id: &event.session_id,
+                                            ts: event.ts,
+                                            event: &event.event,
+                                            state: &event.state,
+                                            tool: event.tool.as_deref(),
+                                            path: event.path.as_deref(),
+                                            command: event.command.as_deref(),
+                                        };
+                                        if let Err(e) = authenticator.verify(&signed, &event.mac) {
+                                            warn!(
+                                                "Rejected hook event for session {}: {}",
+                                                event.session_id, e
+                                            );
+                                            return;
+                                        }
                                         debug!(
                                             "Hook event: session={} state={} event={}",
                                             event.session_id, event.state, event.event
@@ -102,12 +154,20 @@ mod tests {
 
     #[test]
     fn test_hook_event_deserialize() {
-        let json = r#"{"session_id":"abc-123","state":"waiting","event":"tool_approval","ts":1704067200}"#;
+        let json = r#"{"session_id":"abc-123","state":"waiting","event":"tool_approval","ts":1704067200,"mac":"ab12"}"#;
         let event: HookEvent = serde_json::from_str(json).unwrap();
 
         assert_eq!(event.session_id, "abc-123");
         assert_eq!(event.state, "waiting");
         assert_eq!(event.event, "tool_approval");
         assert_eq!(event.ts, 1704067200);
+        assert_eq!(event.mac, "ab12");
+    }
+
+    #[test]
+    fn test_hook_event_without_mac() {
+        let json = r#"{"session_id":"abc-123","state":"idle","event":"stopped","ts":1704067200}"#;
+        let event: HookEvent = serde_json::from_str(json).unwrap();
+        assert!(event.mac.is_empty());
     }
 }
@@ -2,8 +2,20 @@
 // Hooks provide authoritative status information via lifecycle events
 
 use anyhow::Result;
-use std::path::PathBuf;
-use tracing::info;
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tracing::{info, warn};
+
+use crate::auth;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Maximum allowed difference between a hook event timestamp and the daemon clock
+const MAX_CLOCK_SKEW_SECS: u64 = 30;
 
 /// The hook script content - embedded in the binary
 const HOOK_SCRIPT: &str = r#"#!/bin/bash
@@ -12,27 +24,56 @@ const HOOK_SCRIPT: &str = r#"#!/bin/bash
 
 SESSION_ID="${AGENT_DECK_SESSION_ID}"
 SOCKET_PATH="${AGENT_DECK_SOCKET}"
+TOKEN="${AGENT_DECK_TOKEN}"
 
 # Silently exit if not in an Agent Deck session
-if [ -z "$SESSION_ID" ] || [ -z "$SOCKET_PATH" ]; then
+if [ -z "$SESSION_ID" ] || [ -z "$SOCKET_PATH" ] || [ -z "$TOKEN" ]; then
     exit 0
 fi
 
-# Report state to daemon
+# Without openssl events go out unsigned, and the daemon drops them
+if ! command -v openssl >/dev/null 2>&1; then
+    echo "agent-deck-hook: openssl not found, session status can't be reported" >&2
+fi
+
+# Report state to daemon, signed with the session's token (which is never sent)
+# The tool, path and command ($3-$5) are JSON-escaped already; empty ones are
+# left out of the event but still signed, as empty lines
 report_state() {
     local state="$1"
     local event="$2"
+    local tool="$3"
+    local path="$4"
+    local command="$5"
     if [ -S "$SOCKET_PATH" ]; then
-        echo "{\"session_id\":\"$SESSION_ID\",\"state\":\"$state\",\"event\":\"$event\",\"ts\":$(date +%s)}" \
+        local ts
+        ts=$(date +%s)
+        local extra=""
+        [ -n "$tool" ] && extra="$extra,\"tool\":\"$tool\""
+        [ -n "$path" ] && extra="$extra,\"path\":\"$path\""
+        [ -n "$command" ] && extra="$extra,\"command\":\"$command\""
+        local mac
+        mac=$(printf '%s\n%s\n%s\n%s' "$SESSION_ID|$ts|$event|$state" "$tool" "$path" "$command" \
+            | openssl dgst -sha256 -hmac "$TOKEN" -r 2>/dev/null | cut -d' ' -f1)
+        echo "{\"session_id\":\"$SESSION_ID\",\"state\":\"$state\",\"event\":\"$event\"$extra,\"ts\":$ts,\"mac\":\"$mac\"}" \
             | nc -U "$SOCKET_PATH" 2>/dev/null || true
     fi
 }
 
+# Print a string field of the hook input, still JSON-escaped
+input_field() {
+    printf '%s' "$INPUT" | sed -nE "s/.*\"$1\" *: *\"(([^\"\\\\]|\\\\.)*)\".*/\\1/p" | head -n 1
+}
+
 # Handle hook events
 case "$1" in
     "PreToolUse")
-        # About to run a tool - needs approval
-        report_state "waiting" "tool_approval"
+        # About to run a tool - needs approval. The hook input (JSON on stdin)
+        # names the tool and its file or command; the values are copied still
+        # JSON-escaped, so they can go into the event as they are.
+        INPUT=$(cat)
+        report_state "waiting" "tool_approval" \
+            "$(input_field tool_name)" "$(input_field file_path)" "$(input_field command)"
         ;;
     "PostToolUse")
         # Tool completed - back to working
@@ -51,10 +92,168 @@ esac
 exit 0
 "#;
 
+/// Why a hook event was rejected
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum HookAuthError {
+    #[error("missing or invalid signature")]
+    InvalidToken,
+    #[error("timestamp skewed by {0}s")]
+    StaleTimestamp(u64),
+}
+
+/// Issues per-session hook tokens and verifies the events signed with them
+///
+/// A session's token is HMAC-SHA256(secret, session_id), handed to its hook
+/// script in AGENT_DECK_TOKEN. The script doesn't send it: it signs each event
+/// with HMAC-SHA256(token, "session_id|ts|event|state\ntool\npath\ncommand"),
+/// so a captured event can't be replayed with a fresh timestamp or another
+/// command. The secret is kept in the state dir, so sessions that outlive a
+/// daemon restart (detached or adopted orphans) keep working.
+pub struct HookAuthenticator {
+    secret: [u8; 32],
+}
+
+/// The parts of a hook event its signature covers
+pub struct SignedEvent<'a> {
+    pub session_id: &'a str,
+    pub ts: u64,
+    pub event: &'a str,
+    pub state: &'a str,
+    pub tool: Option<&'a str>,
+    pub path: Option<&'a str>,
+    pub command: Option<&'a str>,
+}
+
+impl SignedEvent<'_> {
+    fn message(&self) -> String {
+        // The script signs the tool input as it was copied, JSON-escaped, so
+        // escape it again the same way
+        let escaped = |value: Option<&str>| {
+            value
+                .map(|v| {
+                    let quoted = serde_json::to_string(v).unwrap_or_default();
+                    quoted[1..quoted.len() - 1].to_string()
+                })
+                .unwrap_or_default()
+        };
+        format!(
+            "{}|{}|{}|{}\n{}\n{}\n{}",
+            self.session_id,
+            self.ts,
+            self.event,
+            self.state,
+            escaped(self.tool),
+            escaped(self.path),
+            escaped(self.command)
+        )
+    }
+}
+
+fn hmac(key: &[u8], message: &[u8]) -> HmacSha256 {
+    let mut mac = HmacSha256::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(message);
+    mac
+}
+
+pub(crate) fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+impl HookAuthenticator {
+    /// Create an authenticator with a random secret
+    pub fn new() -> Self {
+        let mut secret = [0u8; 32];
+        rand::thread_rng().fill_bytes(&mut secret);
+        Self { secret }
+    }
+
+    /// The authenticator with the secret kept at `path`, made if missing or unreadable
+    pub fn load(path: &Path) -> Result<Self> {
+        if let Ok(text) = std::fs::read_to_string(path) {
+            match decode_hex(text.trim()).and_then(|bytes| <[u8; 32]>::try_from(bytes).ok()) {
+                Some(secret) => return Ok(Self { secret }),
+                None => warn!("Replacing malformed hook secret {:?}", path),
+            }
+        }
+        let authenticator = Self::new();
+        auth::write_private(path, &to_hex(&authenticator.secret))?;
+        info!("Created hook secret in {:?}", path);
+        Ok(authenticator)
+    }
+
+    /// Get the token a session's hook script signs its events with
+    pub fn token_for(&self, session_id: &str) -> String {
+        to_hex(
+            &hmac(&self.secret, session_id.as_bytes())
+                .finalize()
+                .into_bytes(),
+        )
+    }
+
+    /// The signature a hook script sends with `event`
+    #[cfg(test)]
+    fn sign(&self, event: &SignedEvent) -> String {
+        let token = self.token_for(event.session_id);
+        to_hex(
+            &hmac(token.as_bytes(), event.message().as_bytes())
+                .finalize()
+                .into_bytes(),
+        )
+    }
+
+    /// Verify an event's signature and timestamp against the current time
+    pub fn verify(&self, event: &SignedEvent, mac: &str) -> Result<(), HookAuthError> {
+        static UNSIGNED_WARNED: AtomicBool = AtomicBool::new(false);
+        if mac.is_empty() && !UNSIGNED_WARNED.swap(true, Ordering::Relaxed) {
+            warn!("Hook events arrive unsigned; is openssl installed where Claude runs?");
+        }
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        self.verify_at(event, mac, now)
+    }
+
+    fn verify_at(&self, event: &SignedEvent, mac: &str, now: u64) -> Result<(), HookAuthError> {
+        let Some(bytes) = decode_hex(mac) else {
+            return Err(HookAuthError::InvalidToken);
+        };
+        let token = self.token_for(event.session_id);
+        // verify_slice compares in constant time
+        hmac(token.as_bytes(), event.message().as_bytes())
+            .verify_slice(&bytes)
+            .map_err(|_| HookAuthError::InvalidToken)?;
+        let ts = event.ts;
+
+        let skew = now.abs_diff(ts);
+        if skew > MAX_CLOCK_SKEW_SECS {
+            return Err(HookAuthError::StaleTimestamp(skew));
+        }
+        Ok(())
+    }
+}
+
+impl Default for HookAuthenticator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub(crate) fn decode_hex(s: &str) -> Option<Vec<u8>> {
+    if !s.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(s.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
 /// Manages Claude Code hook installation and configuration
 pub struct HookManager {
     hooks_dir: PathBuf,
     socket_path: PathBuf,
+    authenticator: Arc<HookAuthenticator>,
 }
 
 impl HookManager {
@@ -63,21 +262,30 @@ impl HookManager {
         Self {
             hooks_dir,
             socket_path,
+            authenticator: Arc::new(HookAuthenticator::new()),
         }
     }
 
-    /// Initialize the hook manager using default paths
+    /// Initialize the hook manager using default paths and the kept secret
     pub fn init() -> Result<Self> {
         let hooks_dir = shared::get_hooks_dir()?;
         let socket_path = shared::get_hook_socket_path()?;
-        Ok(Self::new(hooks_dir, socket_path))
+        let secret_path = shared::get_state_dir()?.join("hook.secret");
+        Ok(Self {
+            authenticator: Arc::new(HookAuthenticator::load(&secret_path)?),
+            ..Self::new(hooks_dir, socket_path)
+        })
+    }
+
+    fn script_path(&self) -> PathBuf {
+        self.hooks_dir.join("agent-deck-hook.sh")
     }
 
     /// Ensure the hook script is installed and up-to-date
     pub fn ensure_hook_script(&self) -> Result<PathBuf> {
         std::fs::create_dir_all(&self.hooks_dir)?;
 
-        let script_path = self.hooks_dir.join("agent-deck-hook.sh");
+        let script_path = self.script_path();
 
         // Write or update the script
         std::fs::write(&script_path, HOOK_SCRIPT)?;
@@ -93,6 +301,15 @@ impl HookManager {
         Ok(script_path)
     }
 
+    /// Check whether the installed hook script matches the embedded one
+    /// Returns false if the script is missing or was modified/replaced
+    pub fn hook_script_current(&self) -> bool {
+        match std::fs::read(self.script_path()) {
+            Ok(contents) => Sha256::digest(&contents) == Sha256::digest(HOOK_SCRIPT.as_bytes()),
+            Err(_) => false,
+        }
+    }
+
     /// Get environment variables needed for Claude to use our hooks
     /// These should be passed to the PTY when spawning Claude
     pub fn get_env_vars(&self, session_id: &str) -> Vec<(String, String)> {
@@ -108,9 +325,18 @@ impl HookManager {
                 "AGENT_DECK_SOCKET".to_string(),
                 self.socket_path.to_string_lossy().to_string(),
             ),
+            (
+                "AGENT_DECK_TOKEN".to_string(),
+                self.authenticator.token_for(session_id),
+            ),
         ]
     }
 
+    /// Get the authenticator used to verify hook events
+    pub fn authenticator(&self) -> Arc<HookAuthenticator> {
+        self.authenticator.clone()
+    }
+
     /// Get the hooks directory path
     pub fn hooks_dir(&self) -> &PathBuf {
         &self.hooks_dir
@@ -140,13 +366,158 @@ mod tests {
 
         let vars = manager.get_env_vars("test-session-id");
 
-        assert_eq!(vars.len(), 3);
-        assert!(vars.iter().any(|(k, v)| k == "CLAUDE_HOOKS_DIR"
-            && v == hooks_dir.to_string_lossy().as_ref()));
+        assert_eq!(vars.len(), 4);
+        assert!(vars
+            .iter()
+            .any(|(k, v)| k == "CLAUDE_HOOKS_DIR" && v == hooks_dir.to_string_lossy().as_ref()));
         assert!(vars
             .iter()
             .any(|(k, v)| k == "AGENT_DECK_SESSION_ID" && v == "test-session-id"));
-        assert!(vars.iter().any(|(k, v)| k == "AGENT_DECK_SOCKET"
-            && v == socket_path.to_string_lossy().as_ref()));
+        assert!(vars
+            .iter()
+            .any(|(k, v)| k == "AGENT_DECK_SOCKET" && v == socket_path.to_string_lossy().as_ref()));
+        let token = manager.authenticator().token_for("test-session-id");
+        assert!(vars
+            .iter()
+            .any(|(k, v)| k == "AGENT_DECK_TOKEN" && *v == token));
+    }
+
+    #[test]
+    fn test_hook_script_current() {
+        let hooks_dir = std::env::temp_dir().join(format!("hooks-test-{}", uuid::Uuid::new_v4()));
+        let manager = HookManager::new(hooks_dir.clone(), PathBuf::from("/tmp/test.sock"));
+
+        assert!(!manager.hook_script_current());
+        manager.ensure_hook_script().unwrap();
+        assert!(manager.hook_script_current());
+        std::fs::write(manager.script_path(), "#!/bin/bash\nexit 0\n").unwrap();
+        assert!(!manager.hook_script_current());
+
+        std::fs::remove_dir_all(hooks_dir).unwrap();
+    }
+
+    fn event(session_id: &str, ts: u64) -> SignedEvent<'_> {
+        SignedEvent {
+            session_id,
+            ts,
+            event: "tool_approval",
+            state: "waiting",
+            tool: Some("Bash"),
+            path: None,
+            command: Some("echo \"hi\""),
+        }
+    }
+
+    #[test]
+    fn test_authenticator_accepts_valid_signature() {
+        let auth = HookAuthenticator::new();
+        let mac = auth.sign(&event("session-a", 1000));
+        assert_eq!(
+            auth.verify_at(&event("session-a", 1000), &mac, 1010),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn test_authenticator_rejects_bad_signatures() {
+        let auth = HookAuthenticator::new();
+        let mac = auth.sign(&event("session-a", 1000));
+
+        // Signature for a different session
+        assert_eq!(
+            auth.verify_at(&event("session-b", 1000), &mac, 1000),
+            Err(HookAuthError::InvalidToken)
+        );
+        // Missing and malformed signatures
+        assert_eq!(
+            auth.verify_at(&event("session-a", 1000), "", 1000),
+            Err(HookAuthError::InvalidToken)
+        );
+        assert_eq!(
+            auth.verify_at(&event("session-a", 1000), "zz", 1000),
+            Err(HookAuthError::InvalidToken)
+        );
+        // The bare token isn't a signature
+        let token = auth.token_for("session-a");
+        assert_eq!(
+            auth.verify_at(&event("session-a", 1000), &token, 1000),
+            Err(HookAuthError::InvalidToken)
+        );
+        // Signed by another secret
+        let other = HookAuthenticator::new().sign(&event("session-a", 1000));
+        assert_eq!(
+            auth.verify_at(&event("session-a", 1000), &other, 1000),
+            Err(HookAuthError::InvalidToken)
+        );
+        // A different state under the same signature
+        let mut changed = event("session-a", 1000);
+        changed.state = "idle";
+        assert_eq!(
+            auth.verify_at(&changed, &mac, 1000),
+            Err(HookAuthError::InvalidToken)
+        );
+        // A different command, or the command moved to the path
+        let mut changed = event("session-a", 1000);
+        changed.command = Some("rm -rf ~");
+        assert_eq!(
+            auth.verify_at(&changed, &mac, 1000),
+            Err(HookAuthError::InvalidToken)
+        );
+        let mut changed = event("session-a", 1000);
+        changed.path = changed.command.take();
+        assert_eq!(
+            auth.verify_at(&changed, &mac, 1000),
+            Err(HookAuthError::InvalidToken)
+        );
+    }
+
+    #[test]
+    fn test_authenticator_rejects_replayed_timestamp() {
+        let auth = HookAuthenticator::new();
+        let mac = auth.sign(&event("session-a", 1000));
+        // A captured event resent later with a fresh ts doesn't verify
+        assert_eq!(
+            auth.verify_at(&event("session-a", 2000), &mac, 2000),
+            Err(HookAuthError::InvalidToken)
+        );
+    }
+
+    #[test]
+    fn test_authenticator_rejects_stale_timestamp() {
+        let auth = HookAuthenticator::new();
+        let mac = auth.sign(&event("session-a", 1000));
+        assert_eq!(
+            auth.verify_at(
+                &event("session-a", 1000),
+                &mac,
+                1000 + MAX_CLOCK_SKEW_SECS + 1
+            ),
+            Err(HookAuthError::StaleTimestamp(MAX_CLOCK_SKEW_SECS + 1))
+        );
+    }
+
+    #[test]
+    fn test_secret_kept_across_restarts() {
+        let dir = std::env::temp_dir().join(format!("hook-secret-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("hook.secret");
+
+        let token = HookAuthenticator::load(&path)
+            .unwrap()
+            .token_for("session-a");
+        assert_eq!(
+            HookAuthenticator::load(&path)
+                .unwrap()
+                .token_for("session-a"),
+            token
+        );
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+
+        std::fs::remove_dir_all(dir).unwrap();
     }
 }
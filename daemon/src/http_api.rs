@@ -0,0 +1,456 @@
+// HTTP API - with http.enabled the daemon also serves its JSON-RPC methods as
+// REST routes on http.addr, for scripts, CI jobs and phone shortcuts. Every
+// request needs `Authorization: Bearer <token>` with the token from http.token
+// in the data dir (an admin), or one from auth.tokens, whose role limits the
+// methods it can reach. The common calls have resource routes:
+//   GET    /summary                summary.get (names, statuses, unread counts)
+//   GET    /sessions               session.list
+//   POST   /sessions               session.create
+//   DELETE /sessions/{id}          session.delete
+//   GET    /sessions/{id}/screen   session.get_screen
+//   POST   /sessions/{id}/input    session.input
+//   POST   /sessions/{id}/prompt   queue.enqueue
+//   POST   /sessions/{id}/stop     session.stop (optional body: timeout_ms, force)
+//   GET    /groups                 group.list
+//   POST   /groups                 group.create
+//   DELETE /groups/{id}            group.delete
+// and POST /rpc/{method} calls any other method with the body as its params.
+// GET/POST /approval/{approval_id}/{decision} serve the signed links of
+// approval webhook messages, which stand in for a token (approval_webhook.rs).
+// With http.tls the routes are served over HTTPS (see tls.rs).
+
+use anyhow::Result;
+use axum::body::Bytes;
+use axum::extract::{Path, Query, State};
+use axum::http::{header, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::{Html, IntoResponse};
+use axum::routing::{delete, get, post};
+use axum::{Extension, Json, Router};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use shared::{ErrorCode, Request, Role};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::approval_webhook::Decision;
+use crate::auth;
+use crate::ipc::{process_request, IpcContext};
+use crate::tls::{self, TlsListener};
+
+#[derive(Clone)]
+struct ApiState {
+    ctx: Arc<IpcContext>,
+    token: Arc<str>,
+}
+
+type ApiResponse = (StatusCode, Json<Value>);
+
+/// Serve the API on `addr` until the daemon shuts down
+pub async fn serve(addr: SocketAddr, ctx: Arc<IpcContext>) -> Result<()> {
+    let mut shutdown_rx = ctx.shutdown_tx.subscribe();
+    let http = ctx.config.read().await.http.clone();
+    let state = ApiState {
+        ctx,
+        token: auth::http_token()?.into(),
+    };
+    let app = Router::new()
+        .route("/summary", get(summary))
+        .route("/sessions", get(list_sessions).post(create_session))
+        .route("/sessions/{id}", delete(delete_session))
+        .route("/sessions/{id}/screen", get(session_screen))
+        .route("/sessions/{id}/input", post(session_input))
+        .route("/sessions/{id}/prompt", post(session_prompt))
+        .route("/sessions/{id}/stop", post(stop_session))
+        .route("/groups", get(list_groups).post(create_group))
+        .route("/groups/{id}", delete(delete_group))
+        .route("/rpc/{method}", post(rpc))
+        .layer(middleware::from_fn_with_state(state.clone(), require_token))
+        // Added after the token layer: the link's signature is checked instead
+        .route(
+            "/approval/{approval_id}/{decision}",
+            get(approval_page).post(approval_answer),
+        )
+        .with_state(state);
+
+    let shutdown = async move {
+        let _ = shutdown_rx.wait_for(Option::is_some).await;
+    };
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    if http.tls {
+        let (config, fingerprint) = tls::server_config(&http)?;
+        info!(
+            "HTTP API listening on https://{} (certificate SHA-256 {})",
+            addr, fingerprint
+        );
+        let listener = TlsListener::new(listener, config)?;
+        axum::serve(listener, app)
+            .with_graceful_shutdown(shutdown)
+            .await?;
+    } else {
+        info!("HTTP API listening on http://{}", addr);
+        axum::serve(listener, app)
+            .with_graceful_shutdown(shutdown)
+            .await?;
+    }
+    Ok(())
+}
+
+async fn require_token(
+    State(api): State<ApiState>,
+    mut request: axum::extract::Request,
+    next: Next,
+) -> axum::response::Response {
+    let token = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(str::trim);
+    let role = match token {
+        Some(token) if auth::tokens_match(&api.token, token) => Some(Role::Admin),
+        Some(token) => {
+            let config = api.ctx.config.read().await;
+            auth::configured(&config.auth.tokens, token).map(|configured| configured.role)
+        }
+        None => None,
+    };
+    let Some(role) = role else {
+        warn!(
+            "Refused HTTP {} {} without a valid token",
+            request.method(),
+            request.uri()
+        );
+        return error(ErrorCode::Unauthorized, "A valid bearer token is required").into_response();
+    };
+    request.extensions_mut().insert(role);
+    next.run(request).await
+}
+
+/// Run `method` through the same handlers as the socket, as `role`
+async fn call(api: &ApiState, role: Role, method: &str, params: Value) -> ApiResponse {
+    let request = Request {
+        id: 0,
+        method: method.to_string(),
+        params,
+    };
+    let response = process_request(request, &api.ctx, role).await;
+    match response.error {
+        Some(error) => (status(error.code), Json(json!({ "error": error }))),
+        None => (StatusCode::OK, Json(response.result.unwrap_or(Value::Null))),
+    }
+}
+
+fn error(code: ErrorCode, message: &str) -> ApiResponse {
+    (
+        status(code.code()),
+        Json(json!({ "error": { "code": code.code(), "message": message } })),
+    )
+}
+
+/// The HTTP status for a JSON-RPC error code
+fn status(code: i32) -> StatusCode {
+    match ErrorCode::from_code(code) {
+        Some(
+            ErrorCode::ParseError
+            | ErrorCode::InvalidRequest
+            | ErrorCode::InvalidParams
+            | ErrorCode::InvalidWorkingDir,
+        ) => StatusCode::BAD_REQUEST,
+        Some(ErrorCode::MethodNotFound | ErrorCode::SessionNotFound | ErrorCode::GroupNotFound) => {
+            StatusCode::NOT_FOUND
+        }
+        Some(ErrorCode::Unauthorized) => StatusCode::UNAUTHORIZED,
+        Some(ErrorCode::Forbidden) => StatusCode::FORBIDDEN,
+        Some(ErrorCode::PtyDead) => StatusCode::CONFLICT,
+        Some(ErrorCode::DaemonBusy) => StatusCode::SERVICE_UNAVAILABLE,
+        Some(ErrorCode::Failed | ErrorCode::SpawnFailed) | None => {
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    }
+}
+
+/// A request body with the id from the path added under `key`
+fn with_id(mut body: Value, key: &str, id: Uuid) -> Value {
+    if body.is_null() {
+        body = json!({});
+    }
+    if let Some(object) = body.as_object_mut() {
+        object.insert(key.to_string(), json!(id));
+    }
+    body
+}
+
+/// An optional JSON body; empty means no params
+fn parse_body(body: &Bytes) -> Result<Value, ApiResponse> {
+    if body.is_empty() {
+        return Ok(Value::Null);
+    }
+    serde_json::from_slice(body).map_err(|e| {
+        error(
+            ErrorCode::ParseError,
+            &format!("The body is not valid JSON: {}", e),
+        )
+    })
+}
+
+async fn summary(State(api): State<ApiState>, Extension(role): Extension<Role>) -> ApiResponse {
+    call(&api, role, "summary.get", json!({})).await
+}
+
+async fn list_sessions(
+    State(api): State<ApiState>,
+    Extension(role): Extension<Role>,
+) -> ApiResponse {
+    call(&api, role, "session.list", json!({})).await
+}
+
+async fn create_session(
+    State(api): State<ApiState>,
+    Extension(role): Extension<Role>,
+    body: Bytes,
+) -> ApiResponse {
+    match parse_body(&body) {
+        Ok(params) => call(&api, role, "session.create", params).await,
+        Err(response) => response,
+    }
+}
+
+async fn delete_session(
+    State(api): State<ApiState>,
+    Extension(role): Extension<Role>,
+    Path(id): Path<Uuid>,
+) -> ApiResponse {
+    call(&api, role, "session.delete", json!({ "session_id": id })).await
+}
+
+async fn session_screen(
+    State(api): State<ApiState>,
+    Extension(role): Extension<Role>,
+    Path(id): Path<Uuid>,
+) -> ApiResponse {
+    call(
+        &api,
+        role,
+        "session.get_screen",
+        json!({ "session_id": id }),
+    )
+    .await
+}
+
+async fn session_input(
+    State(api): State<ApiState>,
+    Extension(role): Extension<Role>,
+    Path(id): Path<Uuid>,
+    body: Bytes,
+) -> ApiResponse {
+    match parse_body(&body) {
+        Ok(params) => {
+            call(
+                &api,
+                role,
+                "session.input",
+                with_id(params, "session_id", id),
+            )
+            .await
+        }
+        Err(response) => response,
+    }
+}
+
+async fn session_prompt(
+    State(api): State<ApiState>,
+    Extension(role): Extension<Role>,
+    Path(id): Path<Uuid>,
+    body: Bytes,
+) -> ApiResponse {
+    match parse_body(&body) {
+        Ok(params) => {
+            call(
+                &api,
+                role,
+                "queue.enqueue",
+                with_id(params, "session_id", id),
+            )
+            .await
+        }
+        Err(response) => response,
+    }
+}
+
+async fn stop_session(
+    State(api): State<ApiState>,
+    Extension(role): Extension<Role>,
+    Path(id): Path<Uuid>,
+    body: Bytes,
+) -> ApiResponse {
+    match parse_body(&body) {
+        Ok(params) => {
+            call(
+                &api,
+                role,
+                "session.stop",
+                with_id(params, "session_id", id),
+            )
+            .await
+        }
+        Err(response) => response,
+    }
+}
+
+async fn list_groups(State(api): State<ApiState>, Extension(role): Extension<Role>) -> ApiResponse {
+    call(&api, role, "group.list", json!({})).await
+}
+
+async fn create_group(
+    State(api): State<ApiState>,
+    Extension(role): Extension<Role>,
+    body: Bytes,
+) -> ApiResponse {
+    match parse_body(&body) {
+        Ok(params) => call(&api, role, "group.create", params).await,
+        Err(response) => response,
+    }
+}
+
+async fn delete_group(
+    State(api): State<ApiState>,
+    Extension(role): Extension<Role>,
+    Path(id): Path<Uuid>,
+) -> ApiResponse {
+    call(&api, role, "group.delete", json!({ "group_id": id })).await
+}
+
+async fn rpc(
+    State(api): State<ApiState>,
+    Extension(role): Extension<Role>,
+    Path(method): Path<String>,
+    body: Bytes,
+) -> ApiResponse {
+    match parse_body(&body) {
+        Ok(Value::Null) => call(&api, role, &method, json!({})).await,
+        Ok(params) => call(&api, role, &method, params).await,
+        Err(response) => response,
+    }
+}
+
+/// The query of an approval link
+#[derive(Deserialize)]
+struct ApprovalLink {
+    exp: u64,
+    sig: String,
+}
+
+/// A confirmation page whose button POSTs the link back; opening the link
+/// alone (or a chat app previewing it) doesn't answer anything
+async fn approval_page(
+    State(api): State<ApiState>,
+    Path((approval_id, decision)): Path<(Uuid, Decision)>,
+    Query(link): Query<ApprovalLink>,
+) -> (StatusCode, Html<String>) {
+    if let Err(e) = api
+        .ctx
+        .approval_links
+        .verify(approval_id, decision, link.exp, &link.sig)
+    {
+        return (
+            StatusCode::FORBIDDEN,
+            page(&format!("Refused: {}.", e), None),
+        );
+    }
+    let action = format!(
+        "/approval/{}/{}?exp={}&sig={}",
+        approval_id,
+        decision.name(),
+        link.exp,
+        link.sig
+    );
+    let label = match decision {
+        Decision::Approve => "Approve",
+        Decision::Deny => "Deny",
+    };
+    (StatusCode::OK, page(label, Some(&action)))
+}
+
+/// Answer the dialog through session.approve / session.deny
+async fn approval_answer(
+    State(api): State<ApiState>,
+    Path((approval_id, decision)): Path<(Uuid, Decision)>,
+    Query(link): Query<ApprovalLink>,
+) -> (StatusCode, Html<String>) {
+    if let Err(e) = api
+        .ctx
+        .approval_links
+        .verify(approval_id, decision, link.exp, &link.sig)
+    {
+        warn!("Refused approval link for {}: {}", approval_id, e);
+        return (
+            StatusCode::FORBIDDEN,
+            page(&format!("Refused: {}.", e), None),
+        );
+    }
+    let gone = (
+        StatusCode::GONE,
+        page("This request was already answered or has closed.", None),
+    );
+    let Some(session_id) = api.ctx.approvals.session_of(approval_id) else {
+        return gone;
+    };
+    let params = json!({ "session_id": session_id, "approval_id": approval_id });
+    let (status, _) = call(&api, Role::Operator, decision.method(), params).await;
+    if status != StatusCode::OK {
+        return gone;
+    }
+    info!(
+        "Answered {} with {:?} from an approval link",
+        approval_id, decision
+    );
+    let done = match decision {
+        Decision::Approve => "Approved.",
+        Decision::Deny => "Denied.",
+    };
+    (StatusCode::OK, page(done, None))
+}
+
+/// A minimal page with `text`, and a button posting to `action` if given
+fn page(text: &str, action: Option<&str>) -> Html<String> {
+    let body = match action {
+        Some(action) => format!(
+            r#"<form method="post" action="{}"><button type="submit">{}</button></form>"#,
+            action, text
+        ),
+        None => format!("<p>{}</p>", text),
+    };
+    Html(format!(
+        r#"<!doctype html><meta name="viewport" content="width=device-width"><title>ClaudeMaster</title>{}"#,
+        body
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_status_and_with_id() {
+        assert_eq!(
+            status(ErrorCode::SessionNotFound.code()),
+            StatusCode::NOT_FOUND
+        );
+        assert_eq!(
+            status(ErrorCode::InvalidParams.code()),
+            StatusCode::BAD_REQUEST
+        );
+        assert_eq!(status(-1), StatusCode::INTERNAL_SERVER_ERROR);
+
+        let id = Uuid::new_v4();
+        let params = with_id(json!({ "prompt": "hi" }), "session_id", id);
+        assert_eq!(params, json!({ "prompt": "hi", "session_id": id }));
+        assert_eq!(
+            with_id(Value::Null, "session_id", id),
+            json!({ "session_id": id })
+        );
+    }
+}
@@ -0,0 +1,109 @@
+// Input history - the prompts written to each session with session.input,
+// kept in the state (input_history.json) so the GUI can recall them with the
+// up arrow or offer them across sessions as a prompt library. Only submitted
+// text is kept, not keystrokes, and each text once per session.
+
+use chrono::Utc;
+use shared::{InputHistoryEntry, InputHistoryParams};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+use crate::state::AppState;
+
+/// Most prompts kept per session; the least recently used go first
+const MAX_PER_SESSION: usize = 200;
+
+/// Record `text` as sent to `session_id`, moving it to the end if it was sent before
+pub fn record(s: &mut AppState, session_id: Uuid, text: String) {
+    let entries = s.input_history.entry(session_id).or_default();
+    let uses = match entries.iter().position(|e| e.text == text) {
+        Some(pos) => entries.remove(pos).uses + 1,
+        None => 1,
+    };
+    entries.push(InputHistoryEntry {
+        session_id,
+        text,
+        last_used: Utc::now(),
+        uses,
+    });
+    let excess = entries.len().saturating_sub(MAX_PER_SESSION);
+    entries.drain(..excess);
+}
+
+/// Matching prompts, most recently used first; across sessions a text sent to
+/// several is listed once, under the session it was last sent to
+pub fn query(s: &AppState, params: &InputHistoryParams) -> Vec<InputHistoryEntry> {
+    let mut entries: Vec<InputHistoryEntry> = match params.session_id {
+        Some(id) => s.input_history.get(&id).cloned().unwrap_or_default(),
+        None => {
+            let mut by_text: HashMap<&str, InputHistoryEntry> = HashMap::new();
+            for entry in s.input_history.values().flatten() {
+                by_text
+                    .entry(&entry.text)
+                    .and_modify(|merged| {
+                        merged.uses += entry.uses;
+                        if entry.last_used > merged.last_used {
+                            merged.session_id = entry.session_id;
+                            merged.last_used = entry.last_used;
+                        }
+                    })
+                    .or_insert_with(|| entry.clone());
+            }
+            by_text.into_values().collect()
+        }
+    };
+    if let Some(query) = params.query.as_deref().map(str::to_lowercase) {
+        entries.retain(|e| e.text.to_lowercase().contains(&query));
+    }
+    entries.sort_by_key(|e| std::cmp::Reverse(e.last_used));
+    if let Some(limit) = params.limit {
+        entries.truncate(limit);
+    }
+    entries
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_and_query() {
+        let mut s = AppState::default();
+        let (a, b) = (Uuid::new_v4(), Uuid::new_v4());
+        record(&mut s, a, "fix the tests".into());
+        record(&mut s, a, "run clippy".into());
+        record(&mut s, b, "Fix the tests".into());
+        record(&mut s, b, "run clippy".into());
+        record(&mut s, a, "fix the tests".into());
+
+        // Distinct per session, most recent first
+        let params = InputHistoryParams {
+            session_id: Some(a),
+            ..Default::default()
+        };
+        let entries = query(&s, &params);
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].text, "fix the tests");
+        assert_eq!(entries[0].uses, 2);
+
+        // Across sessions each text once, with the uses added up
+        let entries = query(&s, &InputHistoryParams::default());
+        assert_eq!(entries.len(), 3);
+        let clippy = entries.iter().find(|e| e.text == "run clippy").unwrap();
+        assert_eq!(clippy.uses, 2);
+        assert_eq!(clippy.session_id, b);
+
+        let params = InputHistoryParams {
+            query: Some("FIX".into()),
+            limit: Some(1),
+            ..Default::default()
+        };
+        assert_eq!(query(&s, &params).len(), 1);
+
+        for i in 0..MAX_PER_SESSION + 5 {
+            record(&mut s, b, format!("prompt {}", i));
+        }
+        assert_eq!(s.input_history[&b].len(), MAX_PER_SESSION);
+        assert_eq!(s.input_history[&b][0].text, "prompt 5");
+    }
+}
@@ -1,37 +1,115 @@
 use anyhow::Result;
-use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use chrono::{DateTime, Utc};
 use interprocess::local_socket::{
     tokio::{prelude::*, Stream},
     GenericFilePath, ListenerOptions,
 };
+#[cfg(unix)]
+use interprocess::os::unix::local_socket::ListenerOptionsExt;
 use shared::{
-    CreateGroupParams, CreateSessionParams, ErrorInfo, Event, ForkSessionParams,
-    ReorderGroupParams, ReorderSessionParams, Request, Response, SessionIdParams,
-    SessionInputParams, SessionResizeParams, SessionRestartParams, UpdateGroupParams,
-    UpdateSessionParams,
+    response_frames, AuthParams, AuthResult, ClientIdentifyParams, CrashReport, DebugTapParams,
+    ErrorCode, ErrorInfo, Event, EventSubscription, Request, Response, Role, SessionViewParams,
+    ShutdownParams, TapDirection, TapRecord, MAX_FRAME_BYTES,
 };
-use std::path::{Path, PathBuf};
-use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::Arc;
-use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
-use tokio::sync::{broadcast, mpsc};
+use std::path::Path;
+use std::sync::{Arc, LazyLock};
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::sync::{broadcast, mpsc, watch};
 use tracing::{error, info, warn};
 use uuid::Uuid;
 
+use crate::approval_webhook::ApprovalLinks;
+use crate::approvals::Approvals;
+use crate::auth::{self, TokenAuth};
+use crate::claude_resolver::ClaudeCheck;
+use crate::clients::Clients;
+use crate::config::{SharedConfig, TokenConfig};
+use crate::detach::Detached;
+use crate::fanout::EventFanout;
+use crate::flow::FlowControl;
+use crate::handlers;
 use crate::hook_manager::HookManager;
+use crate::journal::EventJournal;
+use crate::keep_awake::KeepAwake;
+use crate::metrics::Metrics;
+use crate::notifications::Notifier;
+use crate::orphans::Orphans;
+use crate::prompt_queue::PromptQueue;
 use crate::pty::PtyManager;
-use crate::session_manager::SessionManager;
+use crate::recording::Recorder;
+use crate::replay::Replays;
+use crate::resources::ResourceMonitor;
+use crate::rpc::Router;
+use crate::run_queue::RunQueue;
+use crate::screen::Screens;
 use crate::state::SharedState;
+use crate::state_sync::SyncTracker;
+use crate::tap::{self, Tap};
+use crate::turns::TurnTracker;
+use crate::updates::UpdateChecker;
 
 pub type EventSender = broadcast::Sender<Event>;
 
+/// Handlers for every method except the per-connection daemon.auth, debug.tap,
+/// events.subscribe, client.identify and session.view
+static ROUTER: LazyLock<Router<IpcContext>> = LazyLock::new(handlers::router);
+
 pub struct IpcContext {
     pub state: SharedState,
     pub pty_manager: Arc<PtyManager>,
     pub output_tx: mpsc::Sender<(Uuid, Vec<u8>)>,
     pub event_tx: EventSender,
-    pub shutdown_flag: Arc<AtomicBool>,
+    /// Set once a shutdown or restart has been requested
+    pub shutdown_tx: watch::Sender<Option<ShutdownParams>>,
     pub hook_manager: Arc<HookManager>,
+    pub config: SharedConfig,
+    pub update_checker: Arc<UpdateChecker>,
+    /// Last check of the claude binary
+    pub claude_check: Arc<ClaudeCheck>,
+    pub notifier: Arc<Notifier>,
+    pub turn_tracker: Arc<TurnTracker>,
+    pub prompt_queue: Arc<PromptQueue>,
+    /// Session starts waiting for daemon.max_concurrent_running
+    pub run_queue: Arc<RunQueue>,
+    /// Host sleep inhibitor for daemon.keep_awake
+    pub keep_awake: Arc<KeepAwake>,
+    /// CPU and memory sampler for session.stats
+    pub resources: Arc<ResourceMonitor>,
+    /// Claude processes from a previous run found at startup
+    pub orphans: Arc<Orphans>,
+    /// Session recordings in progress
+    pub recorder: Arc<Recorder>,
+    /// Screen model of each session's terminal
+    pub screens: Arc<Screens>,
+    /// Permission dialogs waiting for session.approve / session.deny
+    pub approvals: Arc<Approvals>,
+    /// Signs and checks the links approval webhook messages carry
+    pub approval_links: Arc<ApprovalLinks>,
+    /// Output spools of detached sessions
+    pub detached: Arc<Detached>,
+    /// Output log playbacks started with session.replay
+    pub replays: Arc<Replays>,
+    /// PTY output queue and client lag counters for daemon.stats
+    pub flow: Arc<FlowControl>,
+    /// Counters for daemon.metrics
+    pub metrics: Arc<Metrics>,
+    /// Per-connection event queues
+    pub fanout: Arc<EventFanout>,
+    /// Numbered events for events.since
+    pub journal: Arc<EventJournal>,
+    /// What each state.sync generation changed
+    pub sync_tracker: Arc<SyncTracker>,
+    /// Request/response/event mirror for debug.tap inspectors
+    pub tap: Arc<Tap>,
+    /// Connected clients and the sessions they view, for daemon.clients
+    pub clients: Arc<Clients>,
+    /// Token connections send with daemon.auth (if daemon.require_token is on)
+    pub token_auth: Arc<TokenAuth>,
+    /// Most recent crash report found at startup
+    pub last_crash: Option<CrashReport>,
+    /// Identity reported by daemon.hello (set by the GUI that launched us)
+    pub instance_id: Option<Uuid>,
+    pub started_at: DateTime<Utc>,
 }
 
 pub async fn start_server(socket_path: &Path, ctx: Arc<IpcContext>) -> Result<()> {
@@ -41,18 +119,25 @@ pub async fn start_server(socket_path: &Path, ctx: Arc<IpcContext>) -> Result<()
     }
 
     let name = socket_path.to_fs_name::<GenericFilePath>()?;
-    let listener = ListenerOptions::new().name(name).create_tokio()?;
+    let options = ListenerOptions::new().name(name);
+    // Only our user may connect; peers are also checked per connection
+    #[cfg(unix)]
+    let options = options.mode(0o600);
+    let listener = options.create_tokio()?;
 
     info!("IPC server listening on {:?}", socket_path);
 
+    let mut shutdown_rx = ctx.shutdown_tx.subscribe();
     loop {
-        // Check shutdown flag
-        if ctx.shutdown_flag.load(Ordering::Relaxed) {
-            info!("Shutdown requested, stopping IPC server");
-            break;
-        }
+        let accepted = tokio::select! {
+            accepted = listener.accept() => accepted,
+            _ = shutdown_rx.wait_for(Option::is_some) => {
+                info!("Shutdown requested, stopping IPC server");
+                break;
+            }
+        };
 
-        match listener.accept().await {
+        match accepted {
             Ok(stream) => {
                 let ctx = ctx.clone();
                 tokio::spawn(async move {
@@ -70,28 +155,152 @@ pub async fn start_server(socket_path: &Path, ctx: Arc<IpcContext>) -> Result<()
     Ok(())
 }
 
+/// Where a connection came from, which decides how it authenticates
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Origin {
+    /// The owner-only daemon socket
+    Socket,
+    /// The tunnel port, reached from another machine
+    Tunnel,
+}
+
+impl Origin {
+    /// The role a connection from here has before daemon.auth: admin on the
+    /// socket when daemon.require_token is off (it's owner-only), else none
+    pub fn initial_role(self, token_auth: &TokenAuth) -> Option<Role> {
+        (self == Origin::Socket && !token_auth.required()).then_some(Role::Admin)
+    }
+
+    /// The role daemon.auth with `token` gives a connection from here
+    pub fn token_role(
+        self,
+        token_auth: &TokenAuth,
+        token: &str,
+        tokens: &[TokenConfig],
+    ) -> Option<Role> {
+        match self {
+            Origin::Socket => token_auth.role(token, tokens),
+            Origin::Tunnel => token_auth.tunnel_role(token, tokens),
+        }
+    }
+}
+
 async fn handle_connection(stream: Stream, ctx: Arc<IpcContext>) -> Result<()> {
-    info!("New client connected");
+    auth::check_peer(&stream)?;
+    serve_connection(stream, &ctx, Origin::Socket).await
+}
 
-    let (reader, mut writer) = stream.split();
-    let mut reader = BufReader::new(reader);
-    let mut event_rx = ctx.event_tx.subscribe();
+/// Serve one client until it disconnects
+pub async fn serve_connection<S>(stream: S, ctx: &IpcContext, origin: Origin) -> Result<()>
+where
+    S: AsyncRead + AsyncWrite + Send,
+{
+    let client_id = ctx.tap.client_id();
+    info!("New client connected ({}, {:?})", client_id, origin);
+    ctx.flow.client_connected();
+    ctx.clients.connected(client_id);
+    let result = serve_client(stream, ctx, client_id, origin).await;
+    ctx.clients.disconnected(client_id);
+    ctx.flow.client_disconnected();
+    result
+}
 
-    let mut line = String::new();
+async fn serve_client<S>(stream: S, ctx: &IpcContext, client_id: u64, origin: Origin) -> Result<()>
+where
+    S: AsyncRead + AsyncWrite + Send,
+{
+    let (reader, mut writer) = tokio::io::split(stream);
+    let mut reader = BufReader::new(reader);
+    let events = ctx.fanout.register();
+    // Set by debug.tap: this connection is an inspector
+    let mut tap_rx: Option<broadcast::Receiver<TapRecord>> = None;
+    let mut tap_output = false;
+    // Set by events.subscribe: which events this connection wants
+    let mut subscription = EventSubscription::default();
+    // What the connection may call: set by daemon.auth when daemon.require_token
+    // is on or the client is on the tunnel, otherwise admin (the socket is
+    // owner-only) unless it sends a token from auth.tokens
+    let mut role = origin.initial_role(&ctx.token_auth);
+
+    // Bytes rather than a String: read_until keeps a partial line if an event
+    // wins the select below, where read_line would drop it
+    let mut line = Vec::new();
+    // Skipping the rest of a request line that was over the frame limit
+    let mut discarding = false;
 
     loop {
+        // Reads stop one byte past the limit, so a runaway line can't grow unbounded
+        let mut bounded = (&mut reader).take((MAX_FRAME_BYTES + 1 - line.len()) as u64);
         tokio::select! {
             // Handle incoming requests
-            result = reader.read_line(&mut line) => {
+            result = bounded.read_until(b'\n', &mut line) => {
                 match result {
                     Ok(0) => {
                         info!("Client disconnected");
                         break;
                     }
-                    Ok(_) => {
-                        let response = process_request(&line, &ctx).await;
+                    Ok(_) if discarding => {
+                        discarding = !line.ends_with(b"\n");
+                        line.clear();
+                    }
+                    Ok(_) if line.len() > MAX_FRAME_BYTES => {
+                        warn!("Request over {} bytes, dropping it", MAX_FRAME_BYTES);
+                        discarding = true;
+                        line.clear();
+                        let response = Response {
+                            id: 0,
+                            result: None,
+                            error: Some(ErrorInfo::new(
+                                ErrorCode::InvalidRequest,
+                                format!(
+                                    "Request exceeds max frame size ({} bytes)",
+                                    MAX_FRAME_BYTES
+                                ),
+                            )),
+                        };
                         let response_json = serde_json::to_string(&response)? + "\n";
                         writer.write_all(response_json.as_bytes()).await?;
+                    }
+                    Ok(_) => {
+                        let response = match parse_request(&line) {
+                            Ok(request) => {
+                                ctx.tap.record(client_id, TapDirection::Request, &request);
+                                ctx.metrics.request(&request.method);
+                                match (request.method.as_str(), role) {
+                                    ("daemon.auth", _) => {
+                                        authenticate(request, ctx, origin, &mut role).await
+                                    }
+                                    (_, None) => unauthorized(&request, origin),
+                                    ("debug.tap", Some(role))
+                                        if !auth::allows(role, "debug.tap") =>
+                                    {
+                                        forbidden(&request, role)
+                                    }
+                                    ("debug.tap", _) => {
+                                        set_tap(request, ctx, &mut tap_rx, &mut tap_output)
+                                    }
+                                    ("events.subscribe", _) => {
+                                        set_subscription(request, &mut subscription)
+                                    }
+                                    ("client.identify", _) => identify(request, ctx, client_id),
+                                    ("session.view", _) => {
+                                        view_session(request, ctx, client_id).await
+                                    }
+                                    (_, Some(role)) => {
+                                        if auth::allows(role, &request.method) {
+                                            ctx.clients.request(client_id, &request);
+                                        }
+                                        process_request(request, ctx, role).await
+                                    }
+                                }
+                            }
+                            Err(response) => response,
+                        };
+                        ctx.tap.record(client_id, TapDirection::Response, &response);
+                        // Oversized results go out as chunks the client reassembles
+                        for frame in response_frames(&response)? {
+                            writer.write_all((frame + "\n").as_bytes()).await?;
+                        }
                         line.clear();
                     }
                     Err(e) => {
@@ -102,20 +311,55 @@ async fn handle_connection(stream: Stream, ctx: Arc<IpcContext>) -> Result<()> {
             }
 
             // Forward events to client
-            result = event_rx.recv() => {
+            result = events.next() => {
                 match result {
-                    Ok(event) => {
-                        let event_json = serde_json::to_string(&event)? + "\n";
+                    Some(_) if role.is_none() => continue,
+                    Some(sequenced) => {
+                        let event = &sequenced.event;
+                        let mut event_json = String::new();
+                        if subscribed(&subscription, event, ctx).await {
+                            event_json = serde_json::to_string(&sequenced)? + "\n";
+                        }
+                        // Inspectors see every event, whatever they subscribed to
+                        if tap_rx.is_some() && (tap_output || !tap::is_output_event(event)) {
+                            let record = tap::record(None, TapDirection::Event, event);
+                            event_json += &(serde_json::to_string(&Event::DebugTap(record))? + "\n");
+                        }
+                        if event_json.is_empty() {
+                            continue;
+                        }
                         if let Err(e) = writer.write_all(event_json.as_bytes()).await {
                             warn!("Failed to send event: {}", e);
                             break;
                         }
                     }
+                    None => {
+                        warn!("Client missed events it can't do without, disconnecting");
+                        break;
+                    }
+                }
+            }
+
+            // Mirror other clients' traffic to an inspector
+            result = async {
+                match tap_rx.as_mut() {
+                    Some(rx) => rx.recv().await,
+                    None => std::future::pending().await,
+                }
+            } => {
+                match result {
+                    Ok(record) => {
+                        let tap_json = serde_json::to_string(&Event::DebugTap(record))? + "\n";
+                        if let Err(e) = writer.write_all(tap_json.as_bytes()).await {
+                            warn!("Failed to send tap record: {}", e);
+                            break;
+                        }
+                    }
                     Err(broadcast::error::RecvError::Lagged(n)) => {
-                        warn!("Client lagged, missed {} events", n);
+                        warn!("Inspector lagged, missed {} tap records", n);
                     }
                     Err(broadcast::error::RecvError::Closed) => {
-                        break;
+                        tap_rx = None;
                     }
                 }
             }
@@ -125,614 +369,295 @@ async fn handle_connection(stream: Stream, ctx: Arc<IpcContext>) -> Result<()> {
     Ok(())
 }
 
-async fn process_request(line: &str, ctx: &IpcContext) -> Response {
-    let request: Request = match serde_json::from_str(line.trim()) {
-        Ok(r) => r,
+/// Handle daemon.auth, which lets the calling connection in with the role of
+/// its token
+async fn authenticate(
+    request: Request,
+    ctx: &IpcContext,
+    origin: Origin,
+    role: &mut Option<Role>,
+) -> Response {
+    let params: AuthParams = match serde_json::from_value(request.params) {
+        Ok(p) => p,
         Err(e) => {
-            error!("IPC parse error: {}", e);
             return Response {
-                id: 0,
+                id: request.id,
                 result: None,
-                error: Some(ErrorInfo {
-                    code: -32700,
-                    message: format!("Parse error: {}", e),
-                }),
+                error: Some(ErrorInfo::new(
+                    ErrorCode::InvalidParams,
+                    format!("Invalid params: {}", e),
+                )),
             };
         }
     };
 
-    info!("IPC request: {} (id={})", request.method, request.id);
-
-    match request.method.as_str() {
-        "daemon.ping" => Response {
+    let tokens = ctx.config.read().await.auth.tokens.clone();
+    let Some(token_role) = origin.token_role(&ctx.token_auth, &params.token, &tokens) else {
+        warn!("Rejected daemon.auth with a wrong token");
+        return Response {
             id: request.id,
-            result: Some(serde_json::json!({"status": "ok"})),
-            error: None,
-        },
-
-        "daemon.shutdown" => {
-            info!("Shutdown requested via IPC");
-            ctx.shutdown_flag.store(true, Ordering::Relaxed);
-            Response {
-                id: request.id,
-                result: Some(serde_json::json!({"status": "shutting_down"})),
-                error: None,
-            }
-        }
-
-        "session.list" => {
-            let s = ctx.state.read().await;
-            let sessions: Vec<_> = s.sessions.values().cloned().collect();
-            Response {
-                id: request.id,
-                result: Some(serde_json::json!({"sessions": sessions})),
-                error: None,
-            }
-        }
-
-        "session.create" => {
-            info!("Processing session.create request");
-            let params: CreateSessionParams = match serde_json::from_value(request.params) {
-                Ok(p) => p,
-                Err(e) => {
-                    error!("session.create invalid params: {}", e);
-                    return Response {
-                        id: request.id,
-                        result: None,
-                        error: Some(ErrorInfo {
-                            code: -32602,
-                            message: format!("Invalid params: {}", e),
-                        }),
-                    };
-                }
-            };
-
-            info!("session.create: name={} dir={}", params.name, params.dir);
-            match SessionManager::create_session(
-                &ctx.state,
-                &ctx.pty_manager,
-                ctx.output_tx.clone(),
-                &ctx.event_tx,
-                params.name,
-                PathBuf::from(params.dir),
-                params.group_id,
-            )
-            .await
-            {
-                Ok(session) => Response {
-                    id: request.id,
-                    result: Some(serde_json::json!({"session": session})),
-                    error: None,
-                },
-                Err(e) => Response {
-                    id: request.id,
-                    result: None,
-                    error: Some(ErrorInfo {
-                        code: -32000,
-                        message: format!("Failed to create session: {}", e),
-                    }),
-                },
-            }
-        }
-
-        "session.stop" => {
-            let params: SessionIdParams = match serde_json::from_value(request.params) {
-                Ok(p) => p,
-                Err(e) => {
-                    return Response {
-                        id: request.id,
-                        result: None,
-                        error: Some(ErrorInfo {
-                            code: -32602,
-                            message: format!("Invalid params: {}", e),
-                        }),
-                    };
-                }
-            };
-
-            match SessionManager::stop_session(
-                &ctx.state,
-                &ctx.pty_manager,
-                &ctx.event_tx,
-                params.session_id,
-            )
-            .await
-            {
-                Ok(()) => Response {
-                    id: request.id,
-                    result: Some(serde_json::json!({"success": true})),
-                    error: None,
-                },
-                Err(e) => Response {
-                    id: request.id,
-                    result: None,
-                    error: Some(ErrorInfo {
-                        code: -32000,
-                        message: format!("Failed to stop session: {}", e),
-                    }),
-                },
-            }
-        }
-
-        "session.delete" => {
-            let params: SessionIdParams = match serde_json::from_value(request.params) {
-                Ok(p) => p,
-                Err(e) => {
-                    return Response {
-                        id: request.id,
-                        result: None,
-                        error: Some(ErrorInfo {
-                            code: -32602,
-                            message: format!("Invalid params: {}", e),
-                        }),
-                    };
-                }
-            };
-
-            match SessionManager::delete_session(
-                &ctx.state,
-                &ctx.pty_manager,
-                &ctx.event_tx,
-                params.session_id,
-            )
-            .await
-            {
-                Ok(()) => Response {
-                    id: request.id,
-                    result: Some(serde_json::json!({"success": true})),
-                    error: None,
-                },
-                Err(e) => Response {
-                    id: request.id,
-                    result: None,
-                    error: Some(ErrorInfo {
-                        code: -32000,
-                        message: format!("Failed to delete session: {}", e),
-                    }),
-                },
-            }
-        }
+            result: None,
+            error: Some(ErrorInfo::new(ErrorCode::Unauthorized, "Invalid token")),
+        };
+    };
+    *role = Some(token_role);
+    let result = AuthResult {
+        authenticated: true,
+        role: token_role,
+    };
+    Response {
+        id: request.id,
+        result: serde_json::to_value(result).ok(),
+        error: None,
+    }
+}
 
-        "session.update" => {
-            let params: UpdateSessionParams = match serde_json::from_value(request.params) {
-                Ok(p) => p,
-                Err(e) => {
-                    return Response {
-                        id: request.id,
-                        result: None,
-                        error: Some(ErrorInfo {
-                            code: -32602,
-                            message: format!("Invalid params: {}", e),
-                        }),
-                    };
-                }
-            };
+fn forbidden(request: &Request, role: Role) -> Response {
+    warn!(
+        "Refused {} from a client with the {:?} role",
+        request.method, role
+    );
+    let required = format!("{:?}", auth::required_role(&request.method)).to_lowercase();
+    Response {
+        id: request.id,
+        result: None,
+        error: Some(ErrorInfo::new(
+            ErrorCode::Forbidden,
+            format!(
+                "{} needs a token with the {} role",
+                request.method, required
+            ),
+        )),
+    }
+}
 
-            match SessionManager::update_session(
-                &ctx.state,
-                &ctx.event_tx,
-                params.session_id,
-                params.name,
-                params.group_id,
-            )
-            .await
-            {
-                Ok(session) => Response {
-                    id: request.id,
-                    result: Some(serde_json::to_value(session).unwrap()),
-                    error: None,
-                },
-                Err(e) => Response {
-                    id: request.id,
-                    result: None,
-                    error: Some(ErrorInfo {
-                        code: -32000,
-                        message: format!("Failed to update session: {}", e),
-                    }),
-                },
-            }
-        }
+fn unauthorized(request: &Request, origin: Origin) -> Response {
+    warn!("Refused {} from an unauthenticated client", request.method);
+    let token_file = match origin {
+        Origin::Socket => "daemon.token",
+        Origin::Tunnel => "tunnel.token",
+    };
+    Response {
+        id: request.id,
+        result: None,
+        error: Some(ErrorInfo::new(
+            ErrorCode::Unauthorized,
+            format!(
+                "Authentication required: send daemon.auth with the token in {}",
+                token_file
+            ),
+        )),
+    }
+}
 
-        "session.input" => {
-            let params: SessionInputParams = match serde_json::from_value(request.params) {
-                Ok(p) => p,
-                Err(e) => {
-                    return Response {
-                        id: request.id,
-                        result: None,
-                        error: Some(ErrorInfo {
-                            code: -32602,
-                            message: format!("Invalid params: {}", e),
-                        }),
-                    };
-                }
+/// Handle debug.tap, which turns the calling connection into an inspector
+fn set_tap(
+    request: Request,
+    ctx: &IpcContext,
+    tap_rx: &mut Option<broadcast::Receiver<TapRecord>>,
+    tap_output: &mut bool,
+) -> Response {
+    let params: DebugTapParams = match serde_json::from_value(request.params) {
+        Ok(p) => p,
+        Err(e) => {
+            return Response {
+                id: request.id,
+                result: None,
+                error: Some(ErrorInfo::new(
+                    ErrorCode::InvalidParams,
+                    format!("Invalid params: {}", e),
+                )),
             };
-
-            // Try to decode as base64, fall back to raw bytes
-            let data = BASE64
-                .decode(&params.input)
-                .unwrap_or_else(|_| params.input.into_bytes());
-
-            match ctx.pty_manager.write(params.session_id, &data).await {
-                Ok(()) => Response {
-                    id: request.id,
-                    result: Some(serde_json::json!({"success": true})),
-                    error: None,
-                },
-                Err(e) => Response {
-                    id: request.id,
-                    result: None,
-                    error: Some(ErrorInfo {
-                        code: -32000,
-                        message: format!("Failed to write to session: {}", e),
-                    }),
-                },
-            }
         }
+    };
 
-        "session.resize" => {
-            let params: SessionResizeParams = match serde_json::from_value(request.params) {
-                Ok(p) => p,
-                Err(e) => {
-                    return Response {
-                        id: request.id,
-                        result: None,
-                        error: Some(ErrorInfo {
-                            code: -32602,
-                            message: format!("Invalid params: {}", e),
-                        }),
-                    };
-                }
-            };
-
-            match ctx
-                .pty_manager
-                .resize(params.session_id, params.rows, params.cols)
-                .await
-            {
-                Ok(()) => Response {
-                    id: request.id,
-                    result: Some(serde_json::json!({"success": true})),
-                    error: None,
-                },
-                Err(e) => Response {
-                    id: request.id,
-                    result: None,
-                    error: Some(ErrorInfo {
-                        code: -32000,
-                        message: format!("Failed to resize session: {}", e),
-                    }),
-                },
-            }
+    info!(
+        "Debug tap {}",
+        if params.enabled {
+            "enabled"
+        } else {
+            "disabled"
         }
+    );
+    *tap_rx = params.enabled.then(|| ctx.tap.subscribe());
+    *tap_output = params.include_output;
+    Response {
+        id: request.id,
+        result: Some(serde_json::json!({"enabled": params.enabled})),
+        error: None,
+    }
+}
 
-        "session.restart" => {
-            info!("Processing session.restart request");
-            let params: SessionRestartParams = match serde_json::from_value(request.params) {
-                Ok(p) => p,
-                Err(e) => {
-                    error!("session.restart invalid params: {}", e);
-                    return Response {
-                        id: request.id,
-                        result: None,
-                        error: Some(ErrorInfo {
-                            code: -32602,
-                            message: format!("Invalid params: {}", e),
-                        }),
-                    };
-                }
-            };
-
-            info!(
-                "session.restart for session_id: {} with size {}x{}",
-                params.session_id, params.cols, params.rows
-            );
-            match SessionManager::restart_session(
-                &ctx.state,
-                &ctx.pty_manager,
-                ctx.output_tx.clone(),
-                &ctx.event_tx,
-                &ctx.hook_manager,
-                params.session_id,
-                params.rows,
-                params.cols,
-            )
-            .await
-            {
-                Ok(session) => Response {
-                    id: request.id,
-                    result: Some(serde_json::json!({"session": session})),
-                    error: None,
-                },
-                Err(e) => Response {
-                    id: request.id,
-                    result: None,
-                    error: Some(ErrorInfo {
-                        code: -32000,
-                        message: format!("Failed to restart session: {}", e),
-                    }),
-                },
+fn set_subscription(request: Request, subscription: &mut EventSubscription) -> Response {
+    // No params resets the connection to every event
+    let params = if request.params.is_null() {
+        Ok(EventSubscription::default())
+    } else {
+        serde_json::from_value::<EventSubscription>(request.params)
+    };
+    match params {
+        Ok(params) => {
+            info!("Event subscription: {:?}", params);
+            *subscription = params;
+            Response {
+                id: request.id,
+                result: Some(serde_json::to_value(&*subscription).unwrap()),
+                error: None,
             }
         }
+        Err(e) => Response {
+            id: request.id,
+            result: None,
+            error: Some(ErrorInfo::new(
+                ErrorCode::InvalidParams,
+                format!("Invalid params: {}", e),
+            )),
+        },
+    }
+}
 
-        "session.fork" => {
-            let params: ForkSessionParams = match serde_json::from_value(request.params) {
-                Ok(p) => p,
-                Err(e) => {
-                    return Response {
-                        id: request.id,
-                        result: None,
-                        error: Some(ErrorInfo {
-                            code: -32602,
-                            message: format!("Invalid params: {}", e),
-                        }),
-                    };
-                }
-            };
-
+/// Handle client.identify, which names what kind of client the connection is
+fn identify(request: Request, ctx: &IpcContext, client_id: u64) -> Response {
+    let params = if request.params.is_null() {
+        Ok(ClientIdentifyParams::default())
+    } else {
+        serde_json::from_value::<ClientIdentifyParams>(request.params)
+    };
+    match params {
+        Ok(params) => {
             info!(
-                "session.fork for session_id: {} with size {}x{}",
-                params.session_id, params.cols, params.rows
+                "Client {} is {:?} {:?}",
+                client_id, params.kind, params.name
             );
-            match SessionManager::fork_session(
-                &ctx.state,
-                &ctx.pty_manager,
-                ctx.output_tx.clone(),
-                &ctx.event_tx,
-                &ctx.hook_manager,
-                params.session_id,
-                params.new_name,
-                params.group_id,
-                params.rows,
-                params.cols,
-            )
-            .await
-            {
-                Ok(session) => Response {
-                    id: request.id,
-                    result: Some(serde_json::json!({"session": session})),
-                    error: None,
-                },
-                Err(e) => Response {
-                    id: request.id,
-                    result: None,
-                    error: Some(ErrorInfo {
-                        code: -32000,
-                        message: format!("Failed to fork session: {}", e),
-                    }),
-                },
-            }
-        }
-
-        "group.list" => {
-            let s = ctx.state.read().await;
-            let groups: Vec<_> = s.groups.values().cloned().collect();
+            let info = ctx.clients.identify(client_id, params);
             Response {
                 id: request.id,
-                result: Some(serde_json::json!({"groups": groups})),
+                result: Some(serde_json::to_value(info).unwrap()),
                 error: None,
             }
         }
+        Err(e) => Response {
+            id: request.id,
+            result: None,
+            error: Some(ErrorInfo::new(
+                ErrorCode::InvalidParams,
+                format!("Invalid params: {}", e),
+            )),
+        },
+    }
+}
 
-        "group.create" => {
-            let params: CreateGroupParams = match serde_json::from_value(request.params) {
-                Ok(p) => p,
-                Err(e) => {
-                    return Response {
-                        id: request.id,
-                        result: None,
-                        error: Some(ErrorInfo {
-                            code: -32602,
-                            message: format!("Invalid params: {}", e),
-                        }),
-                    };
-                }
+/// Handle session.view, which records the session whose terminal the
+/// connection shows
+async fn view_session(request: Request, ctx: &IpcContext, client_id: u64) -> Response {
+    let params = if request.params.is_null() {
+        Ok(SessionViewParams::default())
+    } else {
+        serde_json::from_value::<SessionViewParams>(request.params)
+    };
+    let params = match params {
+        Ok(params) => params,
+        Err(e) => {
+            return Response {
+                id: request.id,
+                result: None,
+                error: Some(ErrorInfo::new(
+                    ErrorCode::InvalidParams,
+                    format!("Invalid params: {}", e),
+                )),
             };
-
-            match SessionManager::create_group(
-                &ctx.state,
-                &ctx.event_tx,
-                params.name,
-                params.parent_id,
-            )
-            .await
-            {
-                Ok(group) => Response {
-                    id: request.id,
-                    result: Some(serde_json::json!({"group": group})),
-                    error: None,
-                },
-                Err(e) => Response {
-                    id: request.id,
-                    result: None,
-                    error: Some(ErrorInfo {
-                        code: -32000,
-                        message: format!("Failed to create group: {}", e),
-                    }),
-                },
-            }
         }
-
-        "group.delete" => {
-            let params: SessionIdParams = match serde_json::from_value(request.params) {
-                Ok(p) => p,
-                Err(e) => {
-                    return Response {
-                        id: request.id,
-                        result: None,
-                        error: Some(ErrorInfo {
-                            code: -32602,
-                            message: format!("Invalid params: {}", e),
-                        }),
-                    };
-                }
+    };
+    if let Some(session_id) = params.session_id {
+        if !ctx.state.read().await.sessions.contains_key(&session_id) {
+            return Response {
+                id: request.id,
+                result: None,
+                error: Some(ErrorInfo::new(
+                    ErrorCode::SessionNotFound,
+                    "Session not found",
+                )),
             };
-
-            // Reuse session_id field for group_id
-            match SessionManager::delete_group(&ctx.state, &ctx.event_tx, params.session_id).await {
-                Ok(()) => Response {
-                    id: request.id,
-                    result: Some(serde_json::json!({"success": true})),
-                    error: None,
-                },
-                Err(e) => Response {
-                    id: request.id,
-                    result: None,
-                    error: Some(ErrorInfo {
-                        code: -32000,
-                        message: format!("Failed to delete group: {}", e),
-                    }),
-                },
-            }
         }
+    }
+    let info = ctx.clients.view(client_id, params.session_id);
+    Response {
+        id: request.id,
+        result: Some(serde_json::to_value(info).unwrap()),
+        error: None,
+    }
+}
 
-        "group.update" => {
-            let params: UpdateGroupParams = match serde_json::from_value(request.params) {
-                Ok(p) => p,
-                Err(e) => {
-                    return Response {
-                        id: request.id,
-                        result: None,
-                        error: Some(ErrorInfo {
-                            code: -32602,
-                            message: format!("Invalid params: {}", e),
-                        }),
-                    };
-                }
-            };
+/// Whether a connection's subscription lets `event` through
+async fn subscribed(subscription: &EventSubscription, event: &Event, ctx: &IpcContext) -> bool {
+    if subscription.is_all() {
+        return true;
+    }
+    if !subscription.wants_event(event.name()) {
+        return false;
+    }
+    if subscription.session_ids.is_empty() && subscription.group_ids.is_empty() {
+        return true;
+    }
 
-            match SessionManager::update_group(
-                &ctx.state,
-                &ctx.event_tx,
-                params.group_id,
-                params.name,
-                params.parent_id,
-            )
-            .await
-            {
-                Ok(group) => Response {
-                    id: request.id,
-                    result: Some(serde_json::to_value(group).unwrap()),
-                    error: None,
-                },
-                Err(e) => Response {
-                    id: request.id,
-                    result: None,
-                    error: Some(ErrorInfo {
-                        code: -32000,
-                        message: format!("Failed to update group: {}", e),
-                    }),
-                },
-            }
+    let Some(session_id) = event.session_id() else {
+        return true;
+    };
+    let group_id = match ctx.state.read().await.sessions.get(&session_id) {
+        Some(session) => session.group_id,
+        // Already deleted, so only the session filter can be checked
+        None => {
+            return subscription.session_ids.is_empty()
+                || subscription.session_ids.contains(&session_id)
         }
+    };
+    subscription.wants_session(session_id, group_id)
+}
 
-        "session.reorder" => {
-            let params: ReorderSessionParams = match serde_json::from_value(request.params) {
-                Ok(p) => p,
-                Err(e) => {
-                    return Response {
-                        id: request.id,
-                        result: None,
-                        error: Some(ErrorInfo {
-                            code: -32602,
-                            message: format!("Invalid params: {}", e),
-                        }),
-                    };
-                }
-            };
-
-            match crate::state::reorder_session(
-                &ctx.state,
-                params.session_id,
-                params.group_id,
-                params.after_session_id,
-            )
-            .await
-            {
-                Ok(session) => {
-                    // Save state after reorder
-                    if let Err(e) = crate::state::save_state(&ctx.state).await {
-                        error!("Failed to save state after session reorder: {}", e);
-                    }
-                    // Emit event so UI updates
-                    let _ = ctx.event_tx.send(Event {
-                        event: "session:updated".to_string(),
-                        data: serde_json::to_value(&session).unwrap(),
-                    });
-                    Response {
-                        id: request.id,
-                        result: Some(serde_json::to_value(&session).unwrap()),
-                        error: None,
-                    }
-                }
-                Err(e) => Response {
-                    id: request.id,
-                    result: None,
-                    error: Some(ErrorInfo {
-                        code: -32000,
-                        message: format!("Failed to reorder session: {}", e),
-                    }),
-                },
-            }
+fn parse_request(line: &[u8]) -> Result<Request, Response> {
+    serde_json::from_slice(line).map_err(|e| {
+        error!("IPC parse error: {}", e);
+        Response {
+            id: 0,
+            result: None,
+            error: Some(ErrorInfo::new(
+                ErrorCode::ParseError,
+                format!("Parse error: {}", e),
+            )),
         }
+    })
+}
 
-        "group.reorder" => {
-            let params: ReorderGroupParams = match serde_json::from_value(request.params) {
-                Ok(p) => p,
-                Err(e) => {
-                    return Response {
-                        id: request.id,
-                        result: None,
-                        error: Some(ErrorInfo {
-                            code: -32602,
-                            message: format!("Invalid params: {}", e),
-                        }),
-                    };
-                }
-            };
-
-            match crate::state::reorder_group(
-                &ctx.state,
-                params.group_id,
-                params.parent_id,
-                params.after_group_id,
-            )
-            .await
-            {
-                Ok(group) => {
-                    // Save state after reorder
-                    if let Err(e) = crate::state::save_state(&ctx.state).await {
-                        error!("Failed to save state after group reorder: {}", e);
-                    }
-                    // Emit event so UI updates
-                    let _ = ctx.event_tx.send(Event {
-                        event: "group:updated".to_string(),
-                        data: serde_json::to_value(&group).unwrap(),
-                    });
-                    Response {
-                        id: request.id,
-                        result: Some(serde_json::to_value(&group).unwrap()),
-                        error: None,
-                    }
-                }
-                Err(e) => Response {
-                    id: request.id,
-                    result: None,
-                    error: Some(ErrorInfo {
-                        code: -32000,
-                        message: format!("Failed to reorder group: {}", e),
-                    }),
-                },
-            }
-        }
+/// Run a request through the method handlers (the HTTP API calls this too),
+/// if `role` may call its method
+pub async fn process_request(request: Request, ctx: &IpcContext, role: Role) -> Response {
+    info!("IPC request: {} (id={})", request.method, request.id);
+    if !auth::allows(role, &request.method) {
+        return forbidden(&request, role);
+    }
+    ROUTER.dispatch(ctx, request).await
+}
 
-        _ => Response {
-            id: request.id,
-            result: None,
-            error: Some(ErrorInfo {
-                code: -32601,
-                message: format!("Method not found: {}", request.method),
-            }),
-        },
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shared::METHODS;
+
+    /// METHODS (sent in daemon.hello) must list exactly the methods handled here
+    #[test]
+    fn test_methods_match_handlers() {
+        let mut handled = ROUTER.methods();
+        handled.extend([
+            "daemon.auth",
+            "debug.tap",
+            "events.subscribe",
+            "client.identify",
+            "session.view",
+        ]);
+        handled.sort_unstable();
+
+        let mut listed = METHODS.to_vec();
+        listed.sort_unstable();
+        assert_eq!(handled, listed);
     }
 }
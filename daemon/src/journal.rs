@@ -0,0 +1,297 @@
+// Event journal - every event worth catching up on (session lifecycle and
+// status, approvals, groups, schedules, daemon state) gets the next sequence
+// number, which goes out with it as "seq", and is kept in a bounded journal
+// (events.jsonl in the state dir). events.since returns the ones after a
+// sequence number, so a client that reconnects catches up on what it missed
+// instead of reloading everything. Terminal output and other transient events
+// aren't numbered: output has pty:resync, and the rest is stale by the time a
+// client is back. Numbering continues across restarts from the journal's last.
+// Events that never reached the journal (the event broadcast lagged) leave a
+// gap: a number is skipped and everything before it is dropped, so catching up
+// from before the gap reports complete: false.
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use shared::{Event, EventsSinceResult, SequencedEvent};
+use std::collections::VecDeque;
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tracing::warn;
+use uuid::Uuid;
+
+/// Events kept for events.since
+const MAX_JOURNAL_EVENTS: usize = 2000;
+
+/// First line of the journal file; a new id means old sequence numbers are void
+#[derive(Serialize, Deserialize)]
+struct Header {
+    journal_id: Uuid,
+    /// Events up to this one were dropped before the file was written
+    #[serde(default)]
+    dropped_through: u64,
+}
+
+pub struct EventJournal {
+    id: Uuid,
+    path: PathBuf,
+    state: Mutex<JournalState>,
+}
+
+struct JournalState {
+    events: VecDeque<SequencedEvent>,
+    next_seq: u64,
+    /// Events up to this one have been dropped from the journal
+    dropped_through: u64,
+    /// None once the file couldn't be written; the journal goes on in memory
+    file: Option<File>,
+    /// Events in the file, which is rewritten at twice MAX_JOURNAL_EVENTS
+    file_events: usize,
+}
+
+/// Whether the event is numbered and kept for events.since
+fn journaled(event: &Event) -> bool {
+    !matches!(
+        event,
+        Event::PtyOutput(_)
+            | Event::PtyResync { .. }
+            | Event::ResourceUsage(_)
+            | Event::Presence(_)
+            | Event::ReplayOutput(_)
+            | Event::ReplayFinished(_)
+            | Event::BootstrapProgress(_)
+            | Event::DebugTap(_)
+    )
+}
+
+impl EventJournal {
+    /// The journal at `path`, continuing from the events already in it
+    pub fn open(path: PathBuf) -> Self {
+        let (header, mut events) = match read(&path) {
+            Ok(Some(loaded)) => loaded,
+            Ok(None) => (new_header(), VecDeque::new()),
+            Err(e) => {
+                warn!(
+                    "Starting a new event journal, {:?} is unreadable: {}",
+                    path, e
+                );
+                (new_header(), VecDeque::new())
+            }
+        };
+        while events.len() > MAX_JOURNAL_EVENTS {
+            events.pop_front();
+        }
+        let next_seq = events
+            .back()
+            .and_then(|e| e.seq)
+            .map_or(1, |seq| seq + 1)
+            .max(header.dropped_through + 1);
+        let dropped_through = events
+            .front()
+            .and_then(|e| e.seq)
+            .map_or(next_seq - 1, |seq| seq - 1)
+            .max(header.dropped_through);
+        let id = header.journal_id;
+
+        let journal = Self {
+            id,
+            path,
+            state: Mutex::new(JournalState {
+                events,
+                next_seq,
+                dropped_through,
+                file: None,
+                file_events: 0,
+            }),
+        };
+        journal.rewrite(&mut journal.state.lock().unwrap());
+        journal
+    }
+
+    pub fn id(&self) -> Uuid {
+        self.id
+    }
+
+    /// Number the event and keep it, if it is one the journal keeps
+    pub fn record(&self, event: &Event) -> Option<u64> {
+        if !journaled(event) {
+            return None;
+        }
+        let mut state = self.state.lock().unwrap();
+        let seq = state.next_seq;
+        state.next_seq += 1;
+        let entry = SequencedEvent {
+            seq: Some(seq),
+            event: event.clone(),
+        };
+
+        if state.file_events >= MAX_JOURNAL_EVENTS * 2 {
+            self.rewrite(&mut state);
+        }
+        if let Some(file) = state.file.as_mut() {
+            let written = serde_json::to_string(&entry)
+                .map_err(anyhow::Error::from)
+                .and_then(|line| Ok(file.write_all((line + "\n").as_bytes())?));
+            match written {
+                Ok(()) => state.file_events += 1,
+                Err(e) => {
+                    warn!("Failed to write event journal {:?}: {}", self.path, e);
+                    state.file = None;
+                }
+            }
+        }
+
+        state.events.push_back(entry);
+        if state.events.len() > MAX_JOURNAL_EVENTS {
+            state.events.pop_front();
+            state.dropped_through = state.events.front().and_then(|e| e.seq).unwrap_or(seq) - 1;
+        }
+        Some(seq)
+    }
+
+    /// Skip a number for events that were lost before reaching the journal,
+    /// so no client can catch up across them
+    pub fn mark_gap(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.next_seq += 1;
+        state.dropped_through = state.next_seq - 1;
+        state.events.clear();
+        self.rewrite(&mut state);
+    }
+
+    /// Journaled events after `seq`, oldest first
+    pub fn since(&self, seq: u64) -> EventsSinceResult {
+        let state = self.state.lock().unwrap();
+        let latest = state.next_seq - 1;
+        EventsSinceResult {
+            events: state
+                .events
+                .iter()
+                .skip_while(|e| e.seq.is_none_or(|s| s <= seq))
+                .cloned()
+                .collect(),
+            latest,
+            complete: seq >= state.dropped_through && seq <= latest,
+        }
+    }
+
+    /// Replace the file with the header and the events in memory, then keep
+    /// it open for appending
+    fn rewrite(&self, state: &mut JournalState) {
+        let result = (|| -> Result<File> {
+            let mut content = serde_json::to_string(&Header {
+                journal_id: self.id,
+                dropped_through: state.dropped_through,
+            })? + "\n";
+            for event in &state.events {
+                content += &(serde_json::to_string(event)? + "\n");
+            }
+            let tmp = self.path.with_extension("jsonl.tmp");
+            fs::write(&tmp, content)?;
+            fs::rename(&tmp, &self.path)?;
+            Ok(OpenOptions::new().append(true).open(&self.path)?)
+        })();
+        match result {
+            Ok(file) => {
+                state.file = Some(file);
+                state.file_events = state.events.len();
+            }
+            Err(e) => {
+                warn!("Failed to write event journal {:?}: {}", self.path, e);
+                state.file = None;
+            }
+        }
+    }
+}
+
+fn new_header() -> Header {
+    Header {
+        journal_id: Uuid::new_v4(),
+        dropped_through: 0,
+    }
+}
+
+/// The header and events in the file, None if there is no file
+fn read(path: &Path) -> Result<Option<(Header, VecDeque<SequencedEvent>)>> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    let content = fs::read_to_string(path)?;
+    let mut lines = content.lines();
+    let header: Header = serde_json::from_str(lines.next().unwrap_or_default())?;
+    // Lines this daemon can't parse (a newer daemon's events) are skipped
+    let events = lines
+        .filter_map(|line| serde_json::from_str::<SequencedEvent>(line).ok())
+        .filter(|e| e.seq.is_some())
+        .collect();
+    Ok(Some((header, events)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shared::PtyOutputData;
+
+    fn deleted() -> Event {
+        Event::SessionDeleted {
+            session_id: Uuid::new_v4(),
+        }
+    }
+
+    #[test]
+    fn test_journal_since() {
+        let dir = std::env::temp_dir().join(format!("cm-journal-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("events.jsonl");
+
+        let journal = EventJournal::open(path.clone());
+        assert_eq!(journal.record(&deleted()), Some(1));
+        let output = Event::PtyOutput(PtyOutputData {
+            session_id: Uuid::new_v4(),
+            output: String::new(),
+        });
+        assert_eq!(journal.record(&output), None);
+        assert_eq!(journal.record(&deleted()), Some(2));
+
+        let result = journal.since(1);
+        assert!(result.complete);
+        assert_eq!(result.latest, 2);
+        assert_eq!(result.events.len(), 1);
+        assert_eq!(result.events[0].seq, Some(2));
+        // A number the journal never handed out means the client is confused
+        assert!(!journal.since(3).complete);
+
+        // Numbering carries on after a restart
+        let id = journal.id();
+        drop(journal);
+        let journal = EventJournal::open(path.clone());
+        assert_eq!(journal.id(), id);
+        assert_eq!(journal.since(0).events.len(), 2);
+        assert_eq!(journal.record(&deleted()), Some(3));
+
+        // Once old events are dropped, catching up from before them can't work
+        for _ in 0..MAX_JOURNAL_EVENTS {
+            journal.record(&deleted());
+        }
+        let result = journal.since(1);
+        assert!(!result.complete);
+        assert_eq!(result.events.len(), MAX_JOURNAL_EVENTS);
+        assert!(journal.since(result.latest - 10).complete);
+
+        // Lost events: nobody can catch up across the gap, even after a restart
+        let before = result.latest;
+        journal.mark_gap();
+        let after = journal.record(&deleted()).unwrap();
+        assert_eq!(after, before + 2);
+        assert!(!journal.since(before).complete);
+        assert!(journal.since(after - 1).complete);
+        drop(journal);
+        let journal = EventJournal::open(path.clone());
+        assert!(!journal.since(before).complete);
+        assert_eq!(journal.since(after - 1).events.len(), 1);
+        assert_eq!(journal.record(&deleted()), Some(after + 1));
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,194 @@
+// Keep-awake - while daemon.keep_awake is on and any session is Running or has
+// queued work (a queued start or prompt), holds an OS sleep inhibitor so a laptop
+// doesn't sleep in the middle of an unattended run. The inhibitor is a helper
+// process (caffeinate, systemd-inhibit or PowerShell) that is killed to release
+// it, and that also exits when the daemon does.
+
+use anyhow::Result;
+use shared::{Event, KeepAwakeStatus, SessionStatus};
+use std::process::Stdio;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::process::{Child, Command};
+use tokio::sync::{broadcast, Mutex};
+use tracing::{info, warn};
+
+use crate::config::{load_config, save_config, SharedConfig};
+use crate::prompt_queue::PromptQueue;
+use crate::state::SharedState;
+
+/// Safety net for changes without an event (enqueued prompts, a helper that exited)
+const CHECK_INTERVAL_SECS: u64 = 30;
+
+#[derive(Default)]
+struct Inner {
+    inhibitor: Option<Child>,
+    /// Acquiring failed; not retried until the fleet is idle again
+    failed: bool,
+    status: KeepAwakeStatus,
+}
+
+pub struct KeepAwake {
+    state: SharedState,
+    config: SharedConfig,
+    prompt_queue: Arc<PromptQueue>,
+    event_tx: broadcast::Sender<Event>,
+    inner: Mutex<Inner>,
+}
+
+impl KeepAwake {
+    pub fn new(
+        state: SharedState,
+        config: SharedConfig,
+        prompt_queue: Arc<PromptQueue>,
+        event_tx: broadcast::Sender<Event>,
+    ) -> Self {
+        Self {
+            state,
+            config,
+            prompt_queue,
+            event_tx,
+            inner: Mutex::new(Inner::default()),
+        }
+    }
+
+    pub async fn status(&self) -> KeepAwakeStatus {
+        self.inner.lock().await.status
+    }
+
+    /// Turn keep-awake on or off and persist the change to config
+    pub async fn set_enabled(&self, enabled: bool) -> Result<KeepAwakeStatus> {
+        // Re-read the file so other sections edited since startup are kept
+        let mut file = load_config()?;
+        file.daemon.keep_awake = enabled;
+        save_config(&file)?;
+        self.config.write().await.daemon.keep_awake = enabled;
+
+        Ok(self.apply().await)
+    }
+
+    /// Re-check whenever a status or the config changes
+    pub async fn run(self: Arc<Self>) {
+        let mut event_rx = self.event_tx.subscribe();
+        let mut interval = tokio::time::interval(Duration::from_secs(CHECK_INTERVAL_SECS));
+
+        loop {
+            tokio::select! {
+                event = event_rx.recv() => match event {
+                    Ok(
+                        Event::StatusChanged(_)
+                        | Event::SessionDeleted { .. }
+                        | Event::ConfigReloaded(_),
+                    ) => {}
+                    Ok(_) => continue,
+                    Err(broadcast::error::RecvError::Lagged(n)) => {
+                        warn!("Keep-awake lagged, skipped {} events", n);
+                    }
+                    Err(broadcast::error::RecvError::Closed) => return,
+                },
+                _ = interval.tick() => {}
+            }
+
+            self.apply().await;
+        }
+    }
+
+    /// Acquire or release the inhibitor to match the sessions, emitting
+    /// daemon:keep_awake when the status changes
+    async fn apply(&self) -> KeepAwakeStatus {
+        let enabled = self.config.read().await.daemon.keep_awake;
+        let wanted = enabled && self.busy().await;
+
+        let mut inner = self.inner.lock().await;
+        if let Some(child) = inner.inhibitor.as_mut() {
+            if !matches!(child.try_wait(), Ok(None)) {
+                warn!("Sleep inhibitor exited; the host may sleep");
+                inner.inhibitor = None;
+                inner.failed = true;
+            }
+        }
+
+        if wanted && inner.inhibitor.is_none() && !inner.failed {
+            match spawn_inhibitor() {
+                Ok(child) => {
+                    info!("Keeping the host awake while sessions are running");
+                    inner.inhibitor = Some(child);
+                }
+                Err(e) => {
+                    warn!("Failed to keep the host awake: {}", e);
+                    inner.failed = true;
+                }
+            }
+        } else if !wanted {
+            if let Some(mut child) = inner.inhibitor.take() {
+                info!("Allowing the host to sleep");
+                let _ = child.start_kill();
+            }
+            inner.failed = false;
+        }
+
+        let status = KeepAwakeStatus {
+            enabled,
+            active: inner.inhibitor.is_some(),
+        };
+        if status != inner.status {
+            inner.status = status;
+            let event = Event::KeepAwake(status);
+            let _ = self.event_tx.send(event);
+        }
+        status
+    }
+
+    /// Whether any session is Running or waiting to run something
+    async fn busy(&self) -> bool {
+        let running = self.state.read().await.sessions.values().any(|session| {
+            matches!(
+                session.status,
+                SessionStatus::Running | SessionStatus::Starting | SessionStatus::Queued
+            )
+        });
+        running || !self.prompt_queue.list(None).await.is_empty()
+    }
+}
+
+/// Start the platform's sleep inhibitor, tied to this process's lifetime
+fn spawn_inhibitor() -> Result<Child> {
+    let pid = std::process::id().to_string();
+    let mut cmd = if cfg!(target_os = "macos") {
+        // -i: prevent idle sleep; -w: until the daemon exits
+        let mut cmd = Command::new("caffeinate");
+        cmd.args(["-i", "-w", &pid]);
+        cmd
+    } else if cfg!(windows) {
+        let mut cmd = Command::new("powershell");
+        cmd.args([
+            "-NoProfile",
+            "-NonInteractive",
+            "-Command",
+            &format!(
+                "$k = Add-Type -Name Power -Namespace Win32 -PassThru -MemberDefinition \
+                 '[DllImport(\"kernel32.dll\")] public static extern uint SetThreadExecutionState(uint f);'; \
+                 [void]$k::SetThreadExecutionState([uint32]2147483649); \
+                 Wait-Process -Id {}",
+                pid
+            ),
+        ]);
+        cmd
+    } else {
+        // cat holds the lock until its stdin, our end of a pipe, closes
+        let mut cmd = Command::new("systemd-inhibit");
+        cmd.args([
+            "--what=sleep:idle",
+            "--who=claude-master",
+            "--why=Claude sessions are running",
+            "--mode=block",
+            "cat",
+        ]);
+        cmd
+    };
+    cmd.stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .kill_on_drop(true);
+    Ok(cmd.spawn()?)
+}
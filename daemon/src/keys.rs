@@ -0,0 +1,50 @@
+// Special keys - session.send_key takes a key by name and writes the bytes a
+// terminal sends for it, so clients don't hard-code escape sequences. Arrow
+// and Home/End keys follow the session's cursor key mode, like a real terminal.
+
+use shared::Key;
+
+/// What a terminal sends for `key`; `application_cursor` is whether the program
+/// has switched the cursor keys to application mode (DECCKM)
+pub fn bytes(key: Key, application_cursor: bool) -> &'static [u8] {
+    match (key, application_cursor) {
+        (Key::Enter, _) => b"\r",
+        (Key::Esc, _) => b"\x1b",
+        (Key::Tab, _) => b"\t",
+        (Key::ShiftTab, _) => b"\x1b[Z",
+        (Key::Backspace, _) => b"\x7f",
+        (Key::Delete, _) => b"\x1b[3~",
+        (Key::Up, false) => b"\x1b[A",
+        (Key::Up, true) => b"\x1bOA",
+        (Key::Down, false) => b"\x1b[B",
+        (Key::Down, true) => b"\x1bOB",
+        (Key::Right, false) => b"\x1b[C",
+        (Key::Right, true) => b"\x1bOC",
+        (Key::Left, false) => b"\x1b[D",
+        (Key::Left, true) => b"\x1bOD",
+        (Key::Home, false) => b"\x1b[H",
+        (Key::Home, true) => b"\x1bOH",
+        (Key::End, false) => b"\x1b[F",
+        (Key::End, true) => b"\x1bOF",
+        (Key::PageUp, _) => b"\x1b[5~",
+        (Key::PageDown, _) => b"\x1b[6~",
+        (Key::CtrlC, _) => b"\x03",
+        (Key::CtrlD, _) => b"\x04",
+        (Key::CtrlL, _) => b"\x0c",
+        (Key::CtrlR, _) => b"\x12",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bytes() {
+        assert_eq!(bytes(Key::Up, false), b"\x1b[A");
+        assert_eq!(bytes(Key::Up, true), b"\x1bOA");
+        assert_eq!(bytes(Key::ShiftTab, true), b"\x1b[Z");
+        let key: Key = serde_json::from_value(serde_json::json!("ctrl_c")).unwrap();
+        assert_eq!(bytes(key, false), b"\x03");
+    }
+}
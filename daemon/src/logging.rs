@@ -0,0 +1,276 @@
+// Daemon log file - every tracing event that passes daemon.log_level (directives
+// like `info,claude_master_daemon::pty=debug` set levels per module) is also
+// written as one JSON object per line to logs/daemon.jsonl. The file is rotated
+// daily and when it reaches daemon.log_max_size_kb, keeping daemon.log_max_files
+// old files; daemon.logs.tail reads it back for the GUI's debug panel.
+
+use anyhow::Result;
+use chrono::{DateTime, Local, NaiveDate, Utc};
+use shared::LogRecord;
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use tracing::field::{Field, Visit};
+use tracing::{Level, Subscriber};
+use tracing_subscriber::layer::{Context, Layer};
+
+use crate::output_log::{rotate, rotated_path};
+
+/// Records daemon.logs.tail returns when the client doesn't say
+pub const DEFAULT_TAIL_LINES: usize = 200;
+
+/// The daemon's JSON log file
+pub fn log_path() -> Result<PathBuf> {
+    Ok(shared::get_logs_dir()?.join("daemon.jsonl"))
+}
+
+/// Open log file, shared by the tracing layer and main (which applies the config)
+pub struct DaemonLog {
+    file: Mutex<LogFile>,
+}
+
+struct LogFile {
+    path: PathBuf,
+    file: File,
+    size: u64,
+    /// Local date the current file was started on
+    day: NaiveDate,
+    max_bytes: u64,
+    max_files: usize,
+}
+
+impl DaemonLog {
+    /// Open (or continue) logs/daemon.jsonl with the default limits
+    pub fn open() -> Result<Self> {
+        Ok(Self {
+            file: Mutex::new(LogFile::open(log_path()?)?),
+        })
+    }
+
+    /// Apply daemon.log_max_size_kb and daemon.log_max_files
+    pub fn set_limits(&self, max_size_kb: u64, max_files: usize) {
+        let mut file = self.file.lock().unwrap_or_else(|e| e.into_inner());
+        file.max_bytes = max_size_kb * 1024;
+        file.max_files = max_files;
+    }
+
+    fn write(&self, line: &str) {
+        let mut file = self.file.lock().unwrap_or_else(|e| e.into_inner());
+        // Not logged, which would come straight back here
+        if let Err(e) = file.write(line, Local::now().date_naive()) {
+            eprintln!("Failed to write daemon log: {}", e);
+        }
+    }
+}
+
+impl LogFile {
+    fn open(path: PathBuf) -> Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let metadata = file.metadata()?;
+        let day = metadata
+            .modified()
+            .map(|time| DateTime::<Local>::from(time).date_naive())
+            .unwrap_or_else(|_| Local::now().date_naive());
+        Ok(Self {
+            path,
+            file,
+            size: metadata.len(),
+            day,
+            max_bytes: 10 * 1024 * 1024,
+            max_files: 5,
+        })
+    }
+
+    /// Append a line, rotating first on a new day or when it would exceed max_bytes
+    fn write(&mut self, line: &str, today: NaiveDate) -> Result<()> {
+        let len = line.len() as u64 + 1;
+        if self.size > 0 && (today != self.day || self.size + len > self.max_bytes) {
+            rotate(&self.path, self.max_files)?;
+            self.file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&self.path)?;
+            self.size = 0;
+        }
+        if self.size == 0 {
+            self.day = today;
+        }
+        writeln!(self.file, "{}", line)?;
+        self.size += len;
+        Ok(())
+    }
+}
+
+/// Tracing layer writing events to a `DaemonLog`
+pub struct JsonLayer {
+    log: Arc<DaemonLog>,
+}
+
+impl JsonLayer {
+    pub fn new(log: Arc<DaemonLog>) -> Self {
+        Self { log }
+    }
+}
+
+impl<S: Subscriber> Layer<S> for JsonLayer {
+    fn on_event(&self, event: &tracing::Event<'_>, _: Context<'_, S>) {
+        let mut fields = FieldVisitor::default();
+        event.record(&mut fields);
+        let metadata = event.metadata();
+        let record = LogRecord {
+            timestamp: Utc::now(),
+            level: metadata.level().to_string(),
+            target: metadata.target().to_string(),
+            message: fields.message,
+            fields: fields.fields,
+        };
+        if let Ok(line) = serde_json::to_string(&record) {
+            self.log.write(&line);
+        }
+    }
+}
+
+/// Collects an event's message and its other fields as JSON values
+#[derive(Default)]
+struct FieldVisitor {
+    message: String,
+    fields: serde_json::Map<String, serde_json::Value>,
+}
+
+impl FieldVisitor {
+    fn insert(&mut self, field: &Field, value: serde_json::Value) {
+        self.fields.insert(field.name().to_string(), value);
+    }
+}
+
+impl Visit for FieldVisitor {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == "message" {
+            self.message = format!("{:?}", value);
+        } else {
+            self.insert(field, format!("{:?}", value).into());
+        }
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            self.message = value.to_string();
+        } else {
+            self.insert(field, value.into());
+        }
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.insert(field, value.into());
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.insert(field, value.into());
+    }
+
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.insert(field, value.into());
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.insert(field, value.into());
+    }
+}
+
+/// The last `lines` records at `level` or more severe, oldest first, reading
+/// into rotated files as needed. Also returns the log's path and whether older
+/// matching records were left out.
+pub fn tail(lines: usize, level: Option<Level>) -> Result<(PathBuf, Vec<LogRecord>, bool)> {
+    let path = log_path()?;
+    let (records, truncated) = tail_file(&path, lines, level);
+    Ok((path, records, truncated))
+}
+
+fn tail_file(path: &Path, lines: usize, level: Option<Level>) -> (Vec<LogRecord>, bool) {
+    let mut records = Vec::new();
+    let mut truncated = false;
+    let mut n = 0;
+    'files: loop {
+        let file = if n == 0 {
+            path.to_path_buf()
+        } else {
+            rotated_path(path, n)
+        };
+        let Ok(content) = fs::read_to_string(&file) else {
+            break;
+        };
+        for line in content.lines().rev() {
+            let Ok(record) = serde_json::from_str::<LogRecord>(line) else {
+                continue;
+            };
+            // Levels order by verbosity, so a match is no more verbose than `level`
+            let matches = level.is_none_or(|level| {
+                Level::from_str(&record.level).is_ok_and(|record_level| record_level <= level)
+            });
+            if !matches {
+                continue;
+            }
+            if records.len() == lines {
+                truncated = true;
+                break 'files;
+            }
+            records.push(record);
+        }
+        n += 1;
+    }
+    records.reverse();
+    (records, truncated)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    fn record(level: &str, message: &str) -> String {
+        serde_json::to_string(&LogRecord {
+            timestamp: Utc::now(),
+            level: level.to_string(),
+            target: "claude_master_daemon".to_string(),
+            message: message.to_string(),
+            fields: serde_json::Map::new(),
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_rotation_and_tail() {
+        let dir = std::env::temp_dir().join(format!("daemon-log-test-{}", Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("daemon.jsonl");
+        let mut file = LogFile::open(path.clone()).unwrap();
+        file.max_bytes = 400;
+        file.max_files = 2;
+
+        let today = Local::now().date_naive();
+        for (i, level) in ["INFO", "WARN", "ERROR", "INFO"].iter().enumerate() {
+            file.write(&record(level, &format!("line {}", i)), today)
+                .unwrap();
+        }
+        // A new day starts a new file even when there is room
+        let tomorrow = today.succ_opt().unwrap();
+        file.write(&record("DEBUG", "line 4"), tomorrow).unwrap();
+        assert!(rotated_path(&path, 1).exists());
+        assert_eq!(fs::read_to_string(&path).unwrap().lines().count(), 1);
+
+        let (records, truncated) = tail_file(&path, 10, None);
+        let messages: Vec<_> = records.iter().map(|r| r.message.as_str()).collect();
+        assert!(!truncated);
+        assert_eq!(messages.last(), Some(&"line 4"));
+
+        let (records, truncated) = tail_file(&path, 1, Some(Level::WARN));
+        assert!(truncated);
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].message, "line 2");
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
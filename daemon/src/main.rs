@@ -1,41 +1,202 @@
+mod approval_webhook;
+mod approvals;
+mod archive;
+mod auth;
+mod backup;
+mod bootstrap;
 mod claude;
 mod claude_resolver;
+mod clients;
+mod completion;
 mod config;
+mod config_watcher;
+mod crash;
+mod dependencies;
+mod detach;
+mod egress;
+mod fanout;
+mod flow;
+mod git;
+mod group_status;
+mod handlers;
+mod history;
 mod hook_listener;
 mod hook_manager;
+mod http_api;
+mod input_history;
 mod ipc;
+mod journal;
+mod keep_awake;
+mod keys;
+mod logging;
+mod mcp;
+mod mcp_server;
+mod metrics;
+mod notifications;
+mod orphans;
+mod output_log;
+mod policy;
+mod process_group;
+mod projects;
+mod prompt_queue;
 mod pty;
+mod recording;
+mod replay;
+mod resources;
+mod rpc;
+mod run_queue;
+mod scheduler;
+mod screen;
+mod search;
+mod service;
 mod session_manager;
+mod snippets;
 mod state;
+mod state_sync;
 mod status_tracker;
+mod tap;
+mod tasks;
+mod tls;
+mod transcript;
+mod tunnel;
+mod turns;
+mod updates;
+mod watches;
+mod worktree;
 
 use anyhow::Result;
-use shared::Event;
-use std::sync::atomic::AtomicBool;
+use chrono::Utc;
+use shared::{Event, PtyPolicy, ShutdownParams, INSTANCE_ID_ENV};
 use std::sync::Arc;
-use tokio::sync::{broadcast, mpsc};
+use std::time::Duration;
+use tokio::sync::{broadcast, mpsc, watch, RwLock};
 use tracing::{error, info, warn};
-use tracing_subscriber::EnvFilter;
+use tracing_subscriber::prelude::*;
+use tracing_subscriber::{fmt, reload, EnvFilter};
+use uuid::Uuid;
 
-use crate::config::{get_socket_path, load_config};
+use crate::approval_webhook::{ApprovalLinks, ApprovalWebhook};
+use crate::approvals::Approvals;
+use crate::auth::TokenAuth;
+use crate::claude_resolver::ClaudeCheck;
+use crate::clients::Clients;
+use crate::completion::CompletionWatcher;
+use crate::config::{get_socket_path, get_state_dir, load_config, SharedConfig};
+use crate::config_watcher::ConfigWatcher;
+use crate::dependencies::Dependencies;
+use crate::detach::Detached;
+use crate::fanout::EventFanout;
+use crate::flow::FlowControl;
 use crate::hook_listener::HookListener;
 use crate::hook_manager::HookManager;
 use crate::ipc::{start_server, IpcContext};
+use crate::journal::EventJournal;
+use crate::keep_awake::KeepAwake;
+use crate::logging::{DaemonLog, JsonLayer};
+use crate::metrics::Metrics;
+use crate::notifications::Notifier;
+use crate::orphans::Orphans;
+use crate::output_log::OutputLogger;
+use crate::prompt_queue::PromptQueue;
+use crate::recording::Recorder;
+use crate::replay::Replays;
+use crate::resources::ResourceMonitor;
+use crate::run_queue::RunQueue;
+use crate::scheduler::Scheduler;
+use crate::screen::Screens;
+use crate::service::ServiceAction;
 use crate::session_manager::SessionManager;
-use crate::state::{load_state, new_shared_state};
+use crate::state::{flush_state, load_state, new_shared_state, save_state, spawn_persister};
+use crate::state_sync::SyncTracker;
+use crate::tap::Tap;
+use crate::turns::TurnTracker;
+use crate::updates::UpdateChecker;
+use crate::watches::Watcher;
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    // Initialize logging with sensible defaults
-    // Default to info level if RUST_LOG is not set
-    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+    // Everything below (config, state, sockets) lives in the profile's data dir
+    let Args {
+        profile,
+        service,
+        mcp,
+        tunnel,
+    } = parse_args(std::env::args().skip(1))?;
+    shared::set_profile(profile.as_deref())?;
+    if let Some(action) = service {
+        return service::run(action, profile.as_deref());
+    }
+    if mcp {
+        return mcp_server::run().await;
+    }
+    if tunnel {
+        return tunnel::print_recipe();
+    }
 
-    tracing_subscriber::fmt().with_env_filter(filter).init();
+    // Initialize logging with sensible defaults
+    // RUST_LOG wins; otherwise start at info and switch to the config's log_level
+    // once it is loaded (and again whenever the config is edited)
+    let env_filter = EnvFilter::try_from_default_env().ok();
+    let log_from_env = env_filter.is_some();
+    let (filter, log_handle) =
+        reload::Layer::new(env_filter.unwrap_or_else(|| EnvFilter::new("info")));
+    // The JSON log file is best effort; stdout logging works without it
+    let daemon_log = match DaemonLog::open() {
+        Ok(log) => Some(Arc::new(log)),
+        Err(e) => {
+            eprintln!("Failed to open daemon log file: {}", e);
+            None
+        }
+    };
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(fmt::layer())
+        .with(daemon_log.clone().map(JsonLayer::new))
+        .init();
 
     info!("Claude Master daemon starting...");
+    if let Some(ref profile) = profile {
+        info!("Profile {}", profile);
+    }
+    let started_at = Utc::now();
+    let instance_id = std::env::var(INSTANCE_ID_ENV)
+        .ok()
+        .and_then(|id| Uuid::parse_str(&id).ok());
+    if let Some(id) = instance_id {
+        info!("Instance id {}", id);
+    }
 
-    let _config = load_config()?;
+    let config = load_config()?;
     info!("Config loaded");
+    if let Some(ref log) = daemon_log {
+        log.set_limits(config.daemon.log_max_size_kb, config.daemon.log_max_files);
+    }
+    if let Err(e) = config.validate() {
+        warn!("Config problem: {}", e);
+    } else if !log_from_env {
+        log_handle.reload(EnvFilter::try_new(&config.daemon.log_level)?)?;
+    }
+
+    // Report the last panic since the previous start, then arm the handler
+    let crash_dir = crash::crash_dir()?;
+    let last_crash = crash::last_crash(&crash_dir);
+    if let Some(ref report) = last_crash {
+        if report.fatal {
+            warn!(
+                "Last recorded crash at {}: {}",
+                report.timestamp, report.message
+            );
+        } else {
+            warn!(
+                "Last recorded panic at {} (the daemon carried on): {}",
+                report.timestamp, report.message
+            );
+        }
+    }
+    if config.daemon.crash_reports {
+        crash::install_panic_hook(crash_dir);
+        info!("Crash reporting enabled");
+    }
 
     let state = new_shared_state();
     load_state(&state).await?;
@@ -49,8 +210,18 @@ async fn main() -> Result<()> {
         );
     }
 
+    // Deal with claude processes that outlived the previous daemon
+    let orphans = Arc::new(Orphans::reap(&state, config.daemon.orphan_policy).await);
+    save_state(&state).await?;
+    spawn_persister(state.clone());
+    history::spawn_writer();
+
     let (event_tx, _) = broadcast::channel::<Event>(100);
     let socket_path = get_socket_path()?;
+    let token_auth = Arc::new(TokenAuth::init(
+        config.daemon.require_token,
+        config.tunnel.enabled,
+    )?);
 
     // Initialize hook manager and ensure hook script is installed
     let hook_manager = Arc::new(HookManager::init()?);
@@ -60,12 +231,183 @@ async fn main() -> Result<()> {
         info!("Hook script installed at {:?}", hook_manager.hooks_dir());
     }
 
+    // Turn Waiting/Error transitions into session:needs_attention events
+    let notifier = Arc::new(Notifier::new(config.notifications.clone()));
+    tokio::spawn(notifier.clone().run(state.clone(), event_tx.clone()));
+
+    // Apply config.toml edits without a restart
+    let config: SharedConfig = Arc::new(RwLock::new(config));
+    let config_watcher = ConfigWatcher::new(
+        config.clone(),
+        notifier.clone(),
+        (!log_from_env).then_some(log_handle),
+    );
+    let watcher_event_tx = event_tx.clone();
+    tokio::spawn(async move {
+        if let Err(e) = config_watcher.run(watcher_event_tx).await {
+            error!("Config watcher error: {}", e);
+        }
+    });
+
+    // Opt-in update checks (no network access unless enabled in config)
+    let update_checker = Arc::new(UpdateChecker::new(config.clone()));
+    tokio::spawn(
+        update_checker
+            .clone()
+            .run(hook_manager.clone(), event_tx.clone()),
+    );
+
+    // Check the claude binary once the daemon is up, warning if it's missing or too old
+    let claude_check = Arc::new(ClaudeCheck::new(config.clone(), event_tx.clone()));
+    tokio::spawn({
+        let claude_check = claude_check.clone();
+        async move {
+            claude_check.check().await;
+        }
+    });
+
+    // Time each prompt/response turn
+    let turn_tracker = Arc::new(TurnTracker::new(config.clone()));
+    tokio::spawn(turn_tracker.clone().run(event_tx.clone()));
+
+    // Byte budget for queued PTY output; readers pause when it is used up
+    let flow = Arc::new(FlowControl::new(config.read().await.daemon.output_queue_kb));
+
+    // Numbers events and keeps them for clients catching up after a reconnect
+    let journal = Arc::new(EventJournal::open(get_state_dir()?.join("events.jsonl")));
+
+    // Copies events to each client's queue, so slow clients only hold up themselves
+    let fanout = Arc::new(EventFanout::new(flow.clone(), journal.clone()));
+    tokio::spawn(fanout.clone().run(event_tx.clone()));
+
+    // Counters for daemon.metrics
+    let metrics = Arc::new(Metrics::new());
+    tokio::spawn(metrics.clone().run(state.clone(), event_tx.clone()));
+
+    // Emit group:status_changed as the sessions in a group change
+    tokio::spawn(group_status::run(state.clone(), event_tx.clone()));
+
+    // asciinema recordings started with session.recording.start
+    let recorder = Arc::new(Recorder::new());
+
+    // What each session's terminal shows, for session.get_screen
+    let screens = Arc::new(Screens::new());
+
+    // Claude permission dialogs open on screen, for session.approve / session.deny
+    let approvals = Arc::new(Approvals::new());
+
+    // Output spools of sessions detached with session.detach
+    let detached = Arc::new(Detached::load(
+        shared::get_data_dir()?.join("detached"),
+        state.read().await.sessions.values(),
+    ));
+
     // Create session manager with hook manager
-    let (session_manager, output_rx) =
-        SessionManager::new(state.clone(), event_tx.clone(), hook_manager.clone());
+    let (session_manager, output_rx) = SessionManager::new(
+        state.clone(),
+        event_tx.clone(),
+        hook_manager.clone(),
+        turn_tracker.clone(),
+        Arc::new(OutputLogger::new(config.clone())),
+        recorder.clone(),
+        screens.clone(),
+        approvals.clone(),
+        detached.clone(),
+        config.clone(),
+        flow.clone(),
+    );
+
+    // Reap exited session processes, even those whose PTY stays open
+    tokio::spawn(session_manager.pty_manager().run_reaper());
+
+    // Feed queued prompts to sessions as they free up
+    let prompt_queue = Arc::new(PromptQueue::new(
+        state.clone(),
+        config.clone(),
+        session_manager.pty_manager(),
+        turn_tracker.clone(),
+    ));
+    tokio::spawn(prompt_queue.clone().run(event_tx.clone()));
+
+    // Hold session starts past daemon.max_concurrent_running until a slot frees
+    let run_queue = Arc::new(RunQueue::new(
+        state.clone(),
+        config.clone(),
+        session_manager.pty_manager(),
+        session_manager.output_tx(),
+        event_tx.clone(),
+        hook_manager.clone(),
+    ));
+    tokio::spawn(run_queue.clone().run());
+
+    // Hold off host sleep while sessions run, if daemon.keep_awake is on
+    let keep_awake = Arc::new(KeepAwake::new(
+        state.clone(),
+        config.clone(),
+        prompt_queue.clone(),
+        event_tx.clone(),
+    ));
+    tokio::spawn(keep_awake.clone().run());
+
+    // Report each running session's CPU and memory every daemon.resource_interval_secs
+    let resources = Arc::new(ResourceMonitor::new(
+        state.clone(),
+        config.clone(),
+        event_tx.clone(),
+    ));
+    tokio::spawn(resources.clone().run());
+
+    // Raise attention when a session's or group's watch expression turns true
+    let watcher = Watcher::new(
+        state.clone(),
+        session_manager.pty_manager(),
+        notifier.clone(),
+        event_tx.clone(),
+    );
+    tokio::spawn(watcher.run());
 
-    // Create shutdown flag for graceful termination
-    let shutdown_flag = Arc::new(AtomicBool::new(false));
+    // Start sessions whose session.set_dependency parent has finished
+    let dependencies = Dependencies::new(
+        state.clone(),
+        event_tx.clone(),
+        run_queue.clone(),
+        prompt_queue.clone(),
+    );
+    tokio::spawn(dependencies.run());
+
+    // Summarize groups with a completion watch once all their sessions finish
+    let completion_watcher = CompletionWatcher::new(
+        state.clone(),
+        config.clone(),
+        notifier.clone(),
+        event_tx.clone(),
+    );
+    tokio::spawn(completion_watcher.run());
+
+    // Post permission dialogs to approval_webhook.url with signed approve/deny links
+    let approval_links = Arc::new(ApprovalLinks::new(auth::approval_link_secret()?));
+    let approval_webhook = ApprovalWebhook::new(
+        state.clone(),
+        config.clone(),
+        approval_links.clone(),
+        event_tx.clone(),
+    );
+    tokio::spawn(approval_webhook.run());
+
+    // Create and start the sessions of schedule.create schedules as they come due
+    let scheduler = Scheduler::new(
+        state.clone(),
+        session_manager.pty_manager(),
+        session_manager.output_tx(),
+        event_tx.clone(),
+        run_queue.clone(),
+        prompt_queue.clone(),
+        notifier.clone(),
+    );
+    tokio::spawn(scheduler.run());
+
+    // Set by daemon.shutdown / daemon.restart; stops the IPC server
+    let (shutdown_tx, mut shutdown_rx) = watch::channel(None);
 
     // Create IPC context
     let ctx = Arc::new(IpcContext {
@@ -73,13 +415,71 @@ async fn main() -> Result<()> {
         pty_manager: session_manager.pty_manager(),
         output_tx: session_manager.output_tx(),
         event_tx: event_tx.clone(),
-        shutdown_flag,
+        shutdown_tx,
         hook_manager: hook_manager.clone(),
+        config,
+        update_checker,
+        claude_check,
+        notifier,
+        turn_tracker,
+        prompt_queue,
+        run_queue,
+        keep_awake,
+        resources,
+        orphans,
+        recorder,
+        screens,
+        approvals,
+        approval_links,
+        detached,
+        replays: Arc::new(Replays::new(event_tx.clone())),
+        flow,
+        metrics,
+        fanout,
+        journal,
+        sync_tracker: Arc::new(SyncTracker::new()),
+        tap: Arc::new(Tap::new()),
+        clients: Arc::new(Clients::new(event_tx.clone())),
+        token_auth,
+        last_crash,
+        instance_id,
+        started_at,
     });
 
+    // REST routes for scripts and other machines, if http.enabled
+    let http = ctx.config.read().await.http.clone();
+    if http.enabled {
+        match http.addr.parse() {
+            Ok(addr) => {
+                let http_ctx = ctx.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = http_api::serve(addr, http_ctx).await {
+                        error!("HTTP API error: {}", e);
+                    }
+                });
+            }
+            Err(e) => error!("Invalid http.addr {:?}: {}", http.addr, e),
+        }
+    }
+
+    // The socket protocol on loopback for GUIs on other machines, if tunnel.enabled
+    let tunnel = ctx.config.read().await.tunnel.clone();
+    if tunnel.enabled {
+        let tunnel_ctx = ctx.clone();
+        let port = tunnel.port;
+        tokio::spawn(async move {
+            if let Err(e) = tunnel::serve(port, tunnel_ctx).await {
+                error!("Tunnel error: {}", e);
+            }
+        });
+    }
+
     // Start hook listener for authoritative status events
     let (hook_tx, hook_rx) = mpsc::channel(100);
-    let hook_listener = HookListener::new(hook_manager.socket_path().clone());
+    let hook_listener = HookListener::new(
+        hook_manager.socket_path().clone(),
+        hook_manager.authenticator(),
+    );
     tokio::spawn(async move {
         if let Err(e) = hook_listener.run(hook_tx).await {
             error!("Hook listener error: {}", e);
@@ -91,8 +491,129 @@ async fn main() -> Result<()> {
         session_manager.run(output_rx, hook_rx).await;
     });
 
-    // Start IPC server (blocks forever)
-    start_server(&socket_path, ctx).await?;
+    // Shut down cleanly on SIGTERM (system shutdown, launchctl unload) and Ctrl-C,
+    // so no stale socket or orphaned claude processes are left behind
+    let signal_ctx = ctx.clone();
+    tokio::spawn(async move {
+        if let Err(e) = wait_for_signal().await {
+            error!("Failed to listen for signals: {}", e);
+            return;
+        }
+        let pty_policy = signal_ctx.config.read().await.daemon.shutdown_pty_policy;
+        info!("Termination signal received");
+        signal_ctx.shutdown_tx.send_replace(Some(ShutdownParams {
+            pty_policy,
+            restart: false,
+        }));
+    });
+
+    // Start IPC server (blocks until shutdown is requested)
+    start_server(&socket_path, ctx.clone()).await?;
+
+    let request = shutdown_rx.borrow_and_update().clone().unwrap_or_default();
+    // Let in-flight responses (including the shutdown reply) reach clients
+    tokio::time::sleep(Duration::from_millis(200)).await;
+
+    match request.pty_policy {
+        PtyPolicy::Kill => {
+            SessionManager::stop_all(&ctx.state, &ctx.pty_manager, &ctx.event_tx).await
+        }
+        PtyPolicy::Detach => info!("Leaving session processes running"),
+    }
+    if let Err(e) = flush_state(&ctx.state).await {
+        error!("Failed to save state on shutdown: {}", e);
+    }
+    for path in [&socket_path, hook_manager.socket_path()] {
+        if let Err(e) = std::fs::remove_file(path) {
+            warn!("Failed to remove socket {:?}: {}", path, e);
+        }
+    }
+
+    if request.restart {
+        restart_self()?;
+    }
+    info!("Daemon stopped");
+    Ok(())
+}
+
+/// The daemon's command line
+struct Args {
+    profile: Option<String>,
+    /// Set by --install-service or --uninstall-service, which run instead of the daemon
+    service: Option<ServiceAction>,
+    /// Set by --mcp, which serves MCP on stdio for the running daemon instead
+    mcp: bool,
+    /// Set by --tunnel, which prints how to attach a remote GUI instead
+    tunnel: bool,
+}
+
+/// Read `--profile <name>` (or `--profile=<name>`), `--install-service`,
+/// `--uninstall-service`, `--mcp` and `--tunnel` from the command line
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Args> {
+    let mut profile = None;
+    let mut service = None;
+    let mut mcp = false;
+    let mut tunnel = false;
+    while let Some(arg) = args.next() {
+        if arg == "--profile" {
+            profile = Some(
+                args.next()
+                    .ok_or_else(|| anyhow::anyhow!("--profile needs a name"))?,
+            );
+        } else if let Some(name) = arg.strip_prefix("--profile=") {
+            profile = Some(name.to_string());
+        } else if arg == "--install-service" {
+            service = Some(ServiceAction::Install);
+        } else if arg == "--uninstall-service" {
+            service = Some(ServiceAction::Uninstall);
+        } else if arg == "--mcp" {
+            mcp = true;
+        } else if arg == "--tunnel" {
+            tunnel = true;
+        } else {
+            anyhow::bail!("Unknown argument: {}", arg);
+        }
+    }
+    Ok(Args {
+        profile,
+        service,
+        mcp,
+        tunnel,
+    })
+}
 
+/// Wait for SIGTERM or SIGINT
+async fn wait_for_signal() -> Result<()> {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        let mut terminate = signal(SignalKind::terminate())?;
+        tokio::select! {
+            _ = terminate.recv() => {}
+            result = tokio::signal::ctrl_c() => result?,
+        }
+    }
+    #[cfg(not(unix))]
+    tokio::signal::ctrl_c().await?;
     Ok(())
 }
+
+/// Replace this process with a fresh copy of the daemon binary
+/// exec keeps the PID, so a supervising LaunchAgent doesn't see an exit
+fn restart_self() -> Result<()> {
+    let exe = std::env::current_exe()?;
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    info!("Restarting daemon ({:?})", exe);
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        let err = std::process::Command::new(&exe).args(&args).exec();
+        Err(anyhow::anyhow!("Failed to restart daemon: {}", err))
+    }
+    #[cfg(not(unix))]
+    {
+        std::process::Command::new(&exe).args(&args).spawn()?;
+        Ok(())
+    }
+}
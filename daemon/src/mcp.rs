@@ -0,0 +1,202 @@
+// MCP servers - reads and edits a project's MCP configuration the way Claude
+// Code keeps it: servers in <dir>/.mcp.json under "mcpServers", and whether
+// each may run in <dir>/.claude/settings.local.json ("enabledMcpjsonServers" /
+// "disabledMcpjsonServers"). Claude reads both when a session starts, so a
+// change applies to sessions started (or restarted) afterwards.
+
+use anyhow::{Context, Result};
+use serde_json::{Map, Value};
+use shared::McpServer;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const SERVERS_KEY: &str = "mcpServers";
+const ENABLED_KEY: &str = "enabledMcpjsonServers";
+const DISABLED_KEY: &str = "disabledMcpjsonServers";
+const ENABLE_ALL_KEY: &str = "enableAllProjectMcpServers";
+
+fn mcp_json(dir: &Path) -> PathBuf {
+    dir.join(".mcp.json")
+}
+
+fn local_settings(dir: &Path) -> PathBuf {
+    dir.join(".claude").join("settings.local.json")
+}
+
+/// A JSON object file; missing counts as empty
+fn read_object(path: &Path) -> Result<Map<String, Value>> {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Map::new()),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+    };
+    match serde_json::from_str(&content) {
+        Ok(Value::Object(object)) => Ok(object),
+        Ok(_) => anyhow::bail!("{} is not a JSON object", path.display()),
+        Err(e) => Err(e).with_context(|| format!("{} is not valid JSON", path.display())),
+    }
+}
+
+/// Replace `path` through a temp file, so Claude never reads half of it
+fn write_object(path: &Path, object: Map<String, Value>) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut json = serde_json::to_string_pretty(&Value::Object(object))?;
+    json.push('\n');
+    let tmp = path.with_extension("json.tmp");
+    fs::write(&tmp, json)?;
+    fs::rename(&tmp, path).with_context(|| format!("Failed to write {}", path.display()))
+}
+
+fn names(settings: &Map<String, Value>, key: &str) -> Vec<String> {
+    settings
+        .get(key)
+        .and_then(Value::as_array)
+        .map(|names| {
+            names
+                .iter()
+                .filter_map(|name| name.as_str().map(str::to_string))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Add or take `name` out of the list under `key`
+fn set_listed(settings: &mut Map<String, Value>, key: &str, name: &str, listed: bool) {
+    let mut list = names(settings, key);
+    list.retain(|n| n != name);
+    if listed {
+        list.push(name.to_string());
+    }
+    if list.is_empty() {
+        settings.remove(key);
+    } else {
+        settings.insert(key.to_string(), list.into());
+    }
+}
+
+/// The MCP servers of the project in `dir`, by name
+pub fn list(dir: &Path) -> Result<Vec<McpServer>> {
+    let config = read_object(&mcp_json(dir))?;
+    let settings = read_object(&local_settings(dir))?;
+    let enabled = names(&settings, ENABLED_KEY);
+    let disabled = names(&settings, DISABLED_KEY);
+    let enable_all = settings
+        .get(ENABLE_ALL_KEY)
+        .and_then(Value::as_bool)
+        .unwrap_or(false);
+
+    let mut servers: Vec<McpServer> = config
+        .get(SERVERS_KEY)
+        .and_then(Value::as_object)
+        .into_iter()
+        .flatten()
+        .map(|(name, config)| McpServer {
+            name: name.clone(),
+            config: config.clone(),
+            enabled: !disabled.contains(name) && (enable_all || enabled.contains(name)),
+        })
+        .collect();
+    servers.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(servers)
+}
+
+/// Add a server to the project's .mcp.json and enable it
+pub fn add(dir: &Path, name: &str, server: Value) -> Result<()> {
+    if !server.is_object() {
+        anyhow::bail!("The server config must be a JSON object");
+    }
+    let path = mcp_json(dir);
+    let mut config = read_object(&path)?;
+    let servers = config
+        .entry(SERVERS_KEY)
+        .or_insert_with(|| Value::Object(Map::new()))
+        .as_object_mut()
+        .with_context(|| format!("{} in {} is not an object", SERVERS_KEY, path.display()))?;
+    if servers.contains_key(name) {
+        anyhow::bail!("An MCP server named '{}' already exists", name);
+    }
+    servers.insert(name.to_string(), server);
+    write_object(&path, config)?;
+    set_enabled(dir, name, true)
+}
+
+/// Remove a server from the project's .mcp.json and its settings
+pub fn remove(dir: &Path, name: &str) -> Result<()> {
+    let path = mcp_json(dir);
+    let mut config = read_object(&path)?;
+    let removed = config
+        .get_mut(SERVERS_KEY)
+        .and_then(Value::as_object_mut)
+        .and_then(|servers| servers.remove(name));
+    if removed.is_none() {
+        anyhow::bail!("No MCP server named '{}'", name);
+    }
+    write_object(&path, config)?;
+
+    let settings_path = local_settings(dir);
+    let mut settings = read_object(&settings_path)?;
+    if names(&settings, ENABLED_KEY)
+        .iter()
+        .chain(&names(&settings, DISABLED_KEY))
+        .any(|n| n == name)
+    {
+        set_listed(&mut settings, ENABLED_KEY, name, false);
+        set_listed(&mut settings, DISABLED_KEY, name, false);
+        write_object(&settings_path, settings)?;
+    }
+    Ok(())
+}
+
+/// Let a server run in the project's sessions, or keep it from running
+pub fn set_enabled(dir: &Path, name: &str, enabled: bool) -> Result<()> {
+    let path = local_settings(dir);
+    let mut settings = read_object(&path)?;
+    set_listed(&mut settings, ENABLED_KEY, name, enabled);
+    set_listed(&mut settings, DISABLED_KEY, name, !enabled);
+    write_object(&path, settings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use uuid::Uuid;
+
+    #[test]
+    fn test_add_toggle_remove() {
+        let dir = std::env::temp_dir().join(format!("mcp-test-{}", Uuid::new_v4()));
+        fs::create_dir_all(dir.join(".claude")).unwrap();
+        // Unrelated settings are kept
+        fs::write(
+            local_settings(&dir),
+            r#"{"permissions": {"allow": ["Bash(ls)"]}}"#,
+        )
+        .unwrap();
+        assert!(list(&dir).unwrap().is_empty());
+
+        add(&dir, "fs", json!({"command": "npx", "args": ["mcp-fs"]})).unwrap();
+        assert!(add(&dir, "fs", json!({"command": "other"})).is_err());
+        assert!(add(&dir, "bad", json!("npx")).is_err());
+        let servers = list(&dir).unwrap();
+        assert_eq!(servers.len(), 1);
+        assert_eq!(servers[0].config["command"], "npx");
+        assert!(servers[0].enabled);
+
+        set_enabled(&dir, "fs", false).unwrap();
+        assert!(!list(&dir).unwrap()[0].enabled);
+        let settings = read_object(&local_settings(&dir)).unwrap();
+        assert_eq!(names(&settings, DISABLED_KEY), ["fs"]);
+        assert!(!settings.contains_key(ENABLED_KEY));
+        assert!(settings.contains_key("permissions"));
+
+        remove(&dir, "fs").unwrap();
+        assert!(list(&dir).unwrap().is_empty());
+        assert!(!read_object(&local_settings(&dir))
+            .unwrap()
+            .contains_key(DISABLED_KEY));
+        assert!(remove(&dir, "fs").is_err());
+        let _ = fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,476 @@
+// MCP server - `--mcp` serves the Model Context Protocol on stdin/stdout
+// instead of running the daemon, so an orchestrating Claude can drive the
+// deck: list sessions, create them, queue prompts, read their screens and
+// transcripts, and stop them. It talks to the running daemon over its socket
+// like any other client, e.g. for the current profile:
+//   claude mcp add claude-master -- claude-master-daemon --mcp
+
+use anyhow::{Context, Result};
+use interprocess::local_socket::{
+    tokio::{prelude::*, RecvHalf, SendHalf, Stream},
+    GenericFilePath,
+};
+use serde_json::{json, Map, Value};
+use shared::{ChunkAssembler, ClientKind, Request, Response, ResponseChunk};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+/// The MCP revision spoken; clients asking for another get this one back
+const PROTOCOL_VERSION: &str = "2024-11-05";
+
+/// JSON-RPC error codes
+const METHOD_NOT_FOUND: i32 = -32601;
+const INVALID_PARAMS: i32 = -32602;
+
+/// The fields of a session from session.list that list_sessions shows
+const SESSION_FIELDS: &[&str] = &[
+    "id",
+    "name",
+    "status",
+    "working_dir",
+    "group_id",
+    "model",
+    "last_activity",
+];
+
+/// A connection to the daemon, used one request at a time
+struct DaemonConnection {
+    reader: BufReader<RecvHalf>,
+    writer: SendHalf,
+    next_id: u64,
+    chunks: ChunkAssembler,
+}
+
+impl DaemonConnection {
+    async fn connect() -> Result<Self> {
+        let socket_path = shared::get_socket_path()?;
+        let name = socket_path.as_path().to_fs_name::<GenericFilePath>()?;
+        let stream = Stream::connect(name).await.with_context(|| {
+            format!("Is the daemon running? No connection to {:?}", socket_path)
+        })?;
+        let (reader, writer) = stream.split();
+        let mut connection = Self {
+            reader: BufReader::new(reader),
+            writer,
+            next_id: 1,
+            chunks: ChunkAssembler::new(),
+        };
+
+        // A daemon with daemon.require_token refuses everything until it gets it
+        if let Ok(token) = std::fs::read_to_string(shared::get_token_path()?) {
+            let response = connection
+                .request("daemon.auth", json!({ "token": token.trim() }))
+                .await?;
+            if let Some(error) = response.error {
+                anyhow::bail!("The daemon rejected the auth token: {}", error.message);
+            }
+        }
+        // Shows up as MCP in daemon.clients (older daemons answer with an error)
+        connection
+            .request("client.identify", json!({ "kind": ClientKind::Mcp }))
+            .await?;
+        // Requests only; events would just be read and skipped
+        connection
+            .request("events.subscribe", json!({ "events": [] }))
+            .await?;
+        Ok(connection)
+    }
+
+    /// Send a request and wait for its response, skipping events
+    async fn request(&mut self, method: &str, params: Value) -> Result<Response> {
+        let id = self.next_id;
+        self.next_id += 1;
+        let request = Request {
+            id,
+            method: method.to_string(),
+            params,
+        };
+        let mut line = serde_json::to_string(&request)?;
+        line.push('\n');
+        self.writer.write_all(line.as_bytes()).await?;
+
+        let mut line = String::new();
+        loop {
+            line.clear();
+            if self.reader.read_line(&mut line).await? == 0 {
+                anyhow::bail!("The daemon closed the connection");
+            }
+            // Checked first since a chunk line would also parse as a Response
+            if let Ok(chunk) = serde_json::from_str::<ResponseChunk>(&line) {
+                if chunk.id != id {
+                    continue;
+                }
+                if let Some(response) = self.chunks.push(chunk) {
+                    return Ok(response?);
+                }
+            } else if let Ok(response) = serde_json::from_str::<Response>(&line) {
+                if response.id == id {
+                    return Ok(response);
+                }
+            }
+        }
+    }
+}
+
+/// Serves one MCP client; connects to the daemon on the first tool call and
+/// again after the connection is lost
+#[derive(Default)]
+struct McpServer {
+    daemon: Option<DaemonConnection>,
+}
+
+impl McpServer {
+    /// The reply to one JSON-RPC message; None for notifications
+    async fn handle(&mut self, message: Value) -> Option<Value> {
+        let id = message.get("id").cloned()?;
+        let method = message.get("method").and_then(Value::as_str).unwrap_or("");
+        let params = message.get("params").cloned().unwrap_or(Value::Null);
+
+        let result = match method {
+            "initialize" => Ok(json!({
+                "protocolVersion": PROTOCOL_VERSION,
+                "capabilities": { "tools": {} },
+                "serverInfo": {
+                    "name": "claude-master",
+                    "version": env!("CARGO_PKG_VERSION"),
+                },
+            })),
+            "ping" => Ok(json!({})),
+            "tools/list" => Ok(json!({ "tools": tools() })),
+            "tools/call" => self.call_tool(params).await,
+            _ => Err((METHOD_NOT_FOUND, format!("Unknown method: {}", method))),
+        };
+        Some(match result {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err((code, message)) => json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "error": { "code": code, "message": message },
+            }),
+        })
+    }
+
+    /// Run a tool; failures are reported to the model in the result
+    async fn call_tool(&mut self, params: Value) -> Result<Value, (i32, String)> {
+        let name = params.get("name").and_then(Value::as_str).unwrap_or("");
+        if !tools().iter().any(|tool| tool["name"] == name) {
+            return Err((INVALID_PARAMS, format!("Unknown tool: {}", name)));
+        }
+        let args = params.get("arguments").cloned().unwrap_or(json!({}));
+        let (text, is_error) = match self.run_tool(name, &args).await {
+            Ok(Value::String(text)) => (text, false),
+            Ok(value) => (
+                serde_json::to_string_pretty(&value).unwrap_or_default(),
+                false,
+            ),
+            Err(e) => (format!("{:#}", e), true),
+        };
+        Ok(json!({
+            "content": [{ "type": "text", "text": text }],
+            "isError": is_error,
+        }))
+    }
+
+    async fn run_tool(&mut self, name: &str, args: &Value) -> Result<Value> {
+        match name {
+            "list_sessions" => {
+                let result = self.call("session.list", json!({})).await?;
+                Ok(result["sessions"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .map(session_summary)
+                    .collect())
+            }
+            "create_session" => {
+                let mut params = json!({
+                    "name": required(args, "name")?,
+                    "dir": required(args, "working_dir")?,
+                });
+                for key in ["model", "permission_mode"] {
+                    if let Some(value) = args.get(key) {
+                        params[key] = value.clone();
+                    }
+                }
+                let result = self.call("session.create", params).await?;
+                // Created sessions wait for a client to start them
+                let result = self
+                    .call(
+                        "session.enqueue_start",
+                        json!({ "session_id": result["session"]["id"] }),
+                    )
+                    .await?;
+                let session = session_summary(&result["session"]);
+                if let Some(prompt) = args.get("prompt").and_then(Value::as_str) {
+                    self.call(
+                        "queue.enqueue",
+                        json!({ "session_id": session["id"], "prompt": prompt }),
+                    )
+                    .await?;
+                }
+                Ok(session)
+            }
+            "send_prompt" => {
+                self.call(
+                    "queue.enqueue",
+                    json!({
+                        "session_id": required(args, "session_id")?,
+                        "prompt": required(args, "prompt")?,
+                    }),
+                )
+                .await
+            }
+            "read_screen" => {
+                let screen = self
+                    .call(
+                        "session.get_screen",
+                        json!({ "session_id": required(args, "session_id")? }),
+                    )
+                    .await?;
+                Ok(screen["contents"].clone())
+            }
+            "read_transcript" => {
+                self.call(
+                    "session.transcript",
+                    json!({
+                        "session_id": required(args, "session_id")?,
+                        "limit": args.get("limit").cloned().unwrap_or(json!(20)),
+                    }),
+                )
+                .await
+            }
+            "send_key" => {
+                self.call(
+                    "session.send_key",
+                    json!({
+                        "session_id": required(args, "session_id")?,
+                        "key": required(args, "key")?,
+                        "repeat": args.get("repeat"),
+                    }),
+                )
+                .await?;
+                Ok(json!("Sent"))
+            }
+            "stop_session" => {
+                let result = self
+                    .call(
+                        "session.stop",
+                        json!({
+                            "session_id": required(args, "session_id")?,
+                            "force": args.get("force").and_then(Value::as_bool).unwrap_or(false),
+                        }),
+                    )
+                    .await?;
+                Ok(json!(format!(
+                    "Stopped ({})",
+                    result["outcome"].as_str().unwrap_or("?")
+                )))
+            }
+            _ => anyhow::bail!("Unknown tool: {}", name),
+        }
+    }
+
+    /// Call the daemon, dropping the connection if it failed
+    async fn call(&mut self, method: &str, params: Value) -> Result<Value> {
+        let daemon = match &mut self.daemon {
+            Some(daemon) => daemon,
+            None => self.daemon.insert(DaemonConnection::connect().await?),
+        };
+        match daemon.request(method, params).await {
+            Ok(Response {
+                error: Some(error), ..
+            }) => anyhow::bail!("{}", error.message),
+            Ok(response) => Ok(response.result.unwrap_or(Value::Null)),
+            Err(e) => {
+                self.daemon = None;
+                Err(e)
+            }
+        }
+    }
+}
+
+fn required<'a>(args: &'a Value, key: &str) -> Result<&'a Value> {
+    args.get(key)
+        .filter(|value| !value.is_null())
+        .with_context(|| format!("{} is required", key))
+}
+
+/// The fields of a session worth showing the model
+fn session_summary(session: &Value) -> Value {
+    let summary: Map<String, Value> = SESSION_FIELDS
+        .iter()
+        .filter_map(|&field| Some((field.to_string(), session.get(field)?.clone())))
+        .collect();
+    Value::Object(summary)
+}
+
+fn tools() -> Vec<Value> {
+    let session_id = json!({ "type": "string", "description": "The session's id" });
+    vec![
+        json!({
+            "name": "list_sessions",
+            "description": "List the Claude sessions the deck manages, with their status \
+                (running, waiting for input, idle, stopped, error or queued)",
+            "inputSchema": { "type": "object", "properties": {} },
+        }),
+        json!({
+            "name": "create_session",
+            "description": "Start a new Claude session in a directory, optionally with a \
+                first prompt",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "name": { "type": "string" },
+                    "working_dir": { "type": "string", "description": "Absolute path" },
+                    "prompt": { "type": "string", "description": "Sent once the session is ready" },
+                    "model": { "type": "string" },
+                    "permission_mode": { "type": "string" },
+                },
+                "required": ["name", "working_dir"],
+            },
+        }),
+        json!({
+            "name": "send_prompt",
+            "description": "Queue a prompt for a session; it is sent when the session is \
+                waiting for input",
+            "inputSchema": {
+                "type": "object",
+                "properties": { "session_id": session_id, "prompt": { "type": "string" } },
+                "required": ["session_id", "prompt"],
+            },
+        }),
+        json!({
+            "name": "read_screen",
+            "description": "What a session's terminal shows right now",
+            "inputSchema": {
+                "type": "object",
+                "properties": { "session_id": session_id },
+                "required": ["session_id"],
+            },
+        }),
+        json!({
+            "name": "read_transcript",
+            "description": "The latest messages of a session's conversation",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "session_id": session_id,
+                    "limit": { "type": "integer", "description": "How many messages (default 20)" },
+                },
+                "required": ["session_id"],
+            },
+        }),
+        json!({
+            "name": "send_key",
+            "description": "Press a key in a session, e.g. to pick an option in a menu Claude shows",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "session_id": session_id,
+                    "key": {
+                        "type": "string",
+                        "enum": [
+                            "enter", "esc", "tab", "shift_tab", "backspace", "delete", "up",
+                            "down", "left", "right", "home", "end", "page_up", "page_down",
+                            "ctrl_c", "ctrl_d", "ctrl_l", "ctrl_r",
+                        ],
+                    },
+                    "repeat": { "type": "integer", "description": "How many presses (default 1)" },
+                },
+                "required": ["session_id", "key"],
+            },
+        }),
+        json!({
+            "name": "stop_session",
+            "description": "Stop a session's Claude process, letting it save its \
+                conversation first unless forced",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "session_id": session_id,
+                    "force": { "type": "boolean", "description": "Kill it at once" },
+                },
+                "required": ["session_id"],
+            },
+        }),
+    ]
+}
+
+/// Serve MCP on stdin/stdout until stdin closes
+pub async fn run() -> Result<()> {
+    let mut server = McpServer::default();
+    let mut stdin = BufReader::new(tokio::io::stdin()).lines();
+    let mut stdout = tokio::io::stdout();
+
+    while let Some(line) = stdin.next_line().await? {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let reply = match serde_json::from_str::<Value>(&line) {
+            Ok(message) => server.handle(message).await,
+            Err(e) => Some(json!({
+                "jsonrpc": "2.0",
+                "id": null,
+                "error": { "code": -32700, "message": format!("Parse error: {}", e) },
+            })),
+        };
+        if let Some(reply) = reply {
+            let mut reply = serde_json::to_string(&reply)?;
+            reply.push('\n');
+            stdout.write_all(reply.as_bytes()).await?;
+            stdout.flush().await?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_handle() {
+        let mut server = McpServer::default();
+        let reply = server
+            .handle(json!({ "jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {} }))
+            .await
+            .unwrap();
+        assert_eq!(reply["id"], 1);
+        assert_eq!(reply["result"]["protocolVersion"], PROTOCOL_VERSION);
+
+        // Notifications get no reply
+        let notification = json!({ "jsonrpc": "2.0", "method": "notifications/initialized" });
+        assert!(server.handle(notification).await.is_none());
+
+        let reply = server
+            .handle(json!({ "jsonrpc": "2.0", "id": "a", "method": "tools/list" }))
+            .await
+            .unwrap();
+        assert_eq!(reply["id"], "a");
+        assert_eq!(reply["result"]["tools"].as_array().unwrap().len(), 7);
+
+        let reply = server
+            .handle(json!({
+                "jsonrpc": "2.0",
+                "id": 2,
+                "method": "tools/call",
+                "params": { "name": "nope" },
+            }))
+            .await
+            .unwrap();
+        assert_eq!(reply["error"]["code"], INVALID_PARAMS);
+        let reply = server
+            .handle(json!({ "jsonrpc": "2.0", "id": 3, "method": "resources/list" }))
+            .await
+            .unwrap();
+        assert_eq!(reply["error"]["code"], METHOD_NOT_FOUND);
+    }
+
+    #[test]
+    fn test_session_summary() {
+        let session = json!({ "id": "x", "name": "a", "status": "idle", "scrollback": "..." });
+        assert_eq!(
+            session_summary(&session),
+            json!({ "id": "x", "name": "a", "status": "idle" })
+        );
+    }
+}
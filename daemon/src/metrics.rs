@@ -0,0 +1,204 @@
+// Daemon metrics - counters since the daemon started, for daemon.metrics:
+// sessions created, PTY output, events clients missed, IPC requests by method
+// and session status transitions. Output and dropped events come from the flow
+// control counters; sessions and transitions are followed through events.
+
+use shared::{DaemonMetrics, Event, SessionStatus, StatusTransitionCount, METHODS};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::broadcast;
+use tracing::warn;
+use uuid::Uuid;
+
+use crate::flow::FlowControl;
+use crate::state::SharedState;
+
+#[derive(Default)]
+pub struct Metrics {
+    sessions_created: AtomicU64,
+    requests: Mutex<BTreeMap<String, u64>>,
+    transitions: Mutex<HashMap<(SessionStatus, SessionStatus), u64>>,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Count an IPC request; methods the daemon doesn't know share one counter
+    pub fn request(&self, method: &str) {
+        let method = if METHODS.contains(&method) {
+            method
+        } else {
+            "other"
+        };
+        *self
+            .requests
+            .lock()
+            .unwrap()
+            .entry(method.to_string())
+            .or_default() += 1;
+    }
+
+    /// Count created sessions and status transitions
+    pub async fn run(self: Arc<Self>, state: SharedState, event_tx: broadcast::Sender<Event>) {
+        let mut event_rx = event_tx.subscribe();
+        // Last status seen per session, so a change knows where it came from
+        let mut statuses: HashMap<Uuid, SessionStatus> = state
+            .read()
+            .await
+            .sessions
+            .values()
+            .map(|session| (session.id, session.status))
+            .collect();
+
+        loop {
+            let event = match event_rx.recv().await {
+                Ok(event) => event,
+                Err(broadcast::error::RecvError::Lagged(n)) => {
+                    warn!("Metrics lagged, skipped {} events", n);
+                    continue;
+                }
+                Err(broadcast::error::RecvError::Closed) => return,
+            };
+            match event {
+                Event::SessionCreated(session) => {
+                    self.sessions_created.fetch_add(1, Ordering::Relaxed);
+                    statuses.insert(session.id, session.status);
+                }
+                Event::SessionDeleted { session_id } => {
+                    statuses.remove(&session_id);
+                }
+                Event::StatusChanged(data) => {
+                    let from = statuses.insert(data.session_id, data.status);
+                    if let Some(from) = from.filter(|from| *from != data.status) {
+                        *self
+                            .transitions
+                            .lock()
+                            .unwrap()
+                            .entry((from, data.status))
+                            .or_default() += 1;
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    pub fn snapshot(&self, flow: &FlowControl, uptime_secs: i64) -> DaemonMetrics {
+        let mut status_transitions: Vec<_> = self
+            .transitions
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(&(from, to), &count)| StatusTransitionCount { from, to, count })
+            .collect();
+        status_transitions.sort_by_key(|t| (status_name(t.from), status_name(t.to)));
+
+        DaemonMetrics {
+            uptime_secs,
+            sessions_created: self.sessions_created.load(Ordering::Relaxed),
+            pty_output_bytes: flow.total_bytes_read(),
+            events_dropped: flow.client_lag().1,
+            requests: self.requests.lock().unwrap().clone(),
+            status_transitions,
+            prometheus: None,
+        }
+    }
+}
+
+fn status_name(status: SessionStatus) -> String {
+    format!("{:?}", status).to_lowercase()
+}
+
+/// Render metrics in the Prometheus text exposition format
+pub fn prometheus(metrics: &DaemonMetrics) -> String {
+    let mut out = String::new();
+    let mut counter = |name: &str, help: &str, samples: Vec<(String, u64)>| {
+        let _ = writeln!(out, "# HELP claude_master_{} {}", name, help);
+        let _ = writeln!(out, "# TYPE claude_master_{} counter", name);
+        for (labels, value) in samples {
+            let _ = writeln!(out, "claude_master_{}{} {}", name, labels, value);
+        }
+    };
+
+    counter(
+        "sessions_created_total",
+        "Sessions created since the daemon started",
+        vec![(String::new(), metrics.sessions_created)],
+    );
+    counter(
+        "pty_output_bytes_total",
+        "Bytes of PTY output read from sessions",
+        vec![(String::new(), metrics.pty_output_bytes)],
+    );
+    counter(
+        "events_dropped_total",
+        "Events clients missed by falling behind",
+        vec![(String::new(), metrics.events_dropped)],
+    );
+    counter(
+        "ipc_requests_total",
+        "IPC requests by method",
+        metrics
+            .requests
+            .iter()
+            .map(|(method, count)| (format!("{{method=\"{}\"}}", method), *count))
+            .collect(),
+    );
+    counter(
+        "status_transitions_total",
+        "Session status changes",
+        metrics
+            .status_transitions
+            .iter()
+            .map(|t| {
+                let labels = format!(
+                    "{{from=\"{}\",to=\"{}\"}}",
+                    status_name(t.from),
+                    status_name(t.to)
+                );
+                (labels, t.count)
+            })
+            .collect(),
+    );
+
+    let _ = writeln!(
+        out,
+        "# HELP claude_master_uptime_seconds Seconds since the daemon started"
+    );
+    let _ = writeln!(out, "# TYPE claude_master_uptime_seconds gauge");
+    let _ = writeln!(out, "claude_master_uptime_seconds {}", metrics.uptime_secs);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_metrics() {
+        let metrics = Metrics::new();
+        metrics.request("session.list");
+        metrics.request("session.list");
+        metrics.request("no.such.method");
+        metrics
+            .transitions
+            .lock()
+            .unwrap()
+            .insert((SessionStatus::Running, SessionStatus::Waiting), 3);
+
+        let snapshot = metrics.snapshot(&FlowControl::new(64), 42);
+        assert_eq!(snapshot.requests["session.list"], 2);
+        assert_eq!(snapshot.requests["other"], 1);
+
+        let text = prometheus(&snapshot);
+        assert!(text.contains("claude_master_ipc_requests_total{method=\"session.list\"} 2\n"));
+        assert!(text.contains(
+            "claude_master_status_transitions_total{from=\"running\",to=\"waiting\"} 3\n"
+        ));
+        assert!(text.contains("claude_master_uptime_seconds 42\n"));
+    }
+}
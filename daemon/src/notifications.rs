@@ -0,0 +1,110 @@
+// Notifications - turns status transitions that need the user into
+// session:needs_attention events, honouring per-session/per-group mutes from config
+// Clients decide how to surface them (the GUI shows OS notifications)
+
+use anyhow::Result;
+use shared::{Event, NeedsAttentionData, NotificationSettings, SessionStatus};
+use std::sync::Arc;
+use tokio::sync::{broadcast, RwLock};
+use tracing::{debug, warn};
+use uuid::Uuid;
+
+use crate::config::{load_config, save_config};
+use crate::state::SharedState;
+
+/// Which sessions or groups are muted, shared between IPC and the event task
+pub struct Notifier {
+    settings: RwLock<NotificationSettings>,
+}
+
+/// What a mute applies to
+pub enum MuteTarget {
+    Session(Uuid),
+    Group(Uuid),
+}
+
+impl Notifier {
+    pub fn new(settings: NotificationSettings) -> Self {
+        Self {
+            settings: RwLock::new(settings),
+        }
+    }
+
+    pub async fn settings(&self) -> NotificationSettings {
+        self.settings.read().await.clone()
+    }
+
+    /// Swap in settings from a reloaded config file
+    pub async fn replace(&self, settings: NotificationSettings) {
+        *self.settings.write().await = settings;
+    }
+
+    /// Mute or unmute a session or group and persist the change to config
+    pub async fn set_mute(&self, target: MuteTarget, muted: bool) -> Result<NotificationSettings> {
+        let mut settings = self.settings.write().await;
+        let (list, id) = match target {
+            MuteTarget::Session(id) => (&mut settings.muted_sessions, id),
+            MuteTarget::Group(id) => (&mut settings.muted_groups, id),
+        };
+        list.retain(|&existing| existing != id);
+        if muted {
+            list.push(id);
+        }
+
+        // Re-read the file so other sections edited since startup are kept
+        let mut config = load_config()?;
+        config.notifications = settings.clone();
+        save_config(&config)?;
+
+        Ok(settings.clone())
+    }
+
+    /// Watch status changes and emit session:needs_attention for Waiting/Error
+    pub async fn run(self: Arc<Self>, state: SharedState, event_tx: broadcast::Sender<Event>) {
+        let mut event_rx = event_tx.subscribe();
+
+        loop {
+            let event = match event_rx.recv().await {
+                Ok(event) => event,
+                Err(broadcast::error::RecvError::Lagged(n)) => {
+                    warn!("Notifier lagged, skipped {} events", n);
+                    continue;
+                }
+                Err(broadcast::error::RecvError::Closed) => return,
+            };
+            let Event::StatusChanged(data) = event else {
+                continue;
+            };
+            if !matches!(data.status, SessionStatus::Waiting | SessionStatus::Error) {
+                continue;
+            }
+
+            let (name, group_id) = {
+                let s = state.read().await;
+                match s.sessions.get(&data.session_id) {
+                    Some(session) => (session.name.clone(), session.group_id),
+                    None => continue,
+                }
+            };
+            let muted = self
+                .settings
+                .read()
+                .await
+                .is_muted(data.session_id, group_id);
+            debug!(
+                "Session {} needs attention ({:?}, muted: {})",
+                data.session_id, data.status, muted
+            );
+
+            let event = Event::NeedsAttention(NeedsAttentionData {
+                session_id: data.session_id,
+                name,
+                group_id,
+                status: data.status,
+                muted,
+                watch: None,
+            });
+            let _ = event_tx.send(event);
+        }
+    }
+}
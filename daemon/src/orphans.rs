@@ -0,0 +1,202 @@
+// Orphan reaper - finds claude processes spawned by a previous daemon run that
+// outlived it (a crash, or a shutdown with pty_policy detach) and kills or adopts them
+// Adopted processes have no terminal; they stay listed by daemon.orphans and are
+// killed when their session is restarted. Killing takes the orphan's whole
+// process group, so the tools and servers claude started go with it.
+
+use chrono::{TimeZone, Utc};
+use shared::{OrphanAction, OrphanInfo, OrphanPolicy, StopOutcome};
+use sysinfo::{Pid, ProcessRefreshKind, ProcessesToUpdate, System};
+use tokio::sync::Mutex;
+use tracing::{info, warn};
+
+use crate::process_group::{ProcessGroup, KILL_GRACE};
+use crate::state::SharedState;
+
+/// Start time of a running process in seconds since the epoch
+pub fn start_time(pid: u32) -> Option<u64> {
+    let pid = Pid::from_u32(pid);
+    let mut system = System::new();
+    system.refresh_processes_specifics(
+        ProcessesToUpdate::Some(&[pid]),
+        true,
+        ProcessRefreshKind::nothing(),
+    );
+    system.process(pid).map(|process| process.start_time())
+}
+
+/// Whether `pid` is still the process that was started at `started`
+fn is_same_process(pid: u32, started: u64) -> bool {
+    // Allow a second of rounding between readings
+    start_time(pid).is_some_and(|now| now.abs_diff(started) <= 1)
+}
+
+/// Stop `pid` and everything it started if it is still the process started at
+/// `started`: its process group gets SIGTERM, then SIGKILL after KILL_GRACE.
+/// Where the group can't be found (Windows) the process tree is killed instead.
+/// Returns false if it was already gone or could not be signalled.
+pub async fn kill(pid: u32, started: u64) -> bool {
+    if !is_same_process(pid, started) {
+        return false;
+    }
+    match ProcessGroup::adopt(pid) {
+        // Not our child, so there is nothing to reap
+        Some(group) => group.stop(KILL_GRACE, false, || {}).await != StopOutcome::NotRunning,
+        None => kill_tree(Pid::from_u32(pid)),
+    }
+}
+
+/// Kill `root` and its descendants, youngest first
+fn kill_tree(root: Pid) -> bool {
+    let mut system = System::new();
+    system.refresh_processes_specifics(ProcessesToUpdate::All, true, ProcessRefreshKind::nothing());
+    let mut tree = vec![root];
+    let mut i = 0;
+    while i < tree.len() {
+        let parent = tree[i];
+        let children: Vec<Pid> = system
+            .processes()
+            .iter()
+            .filter(|(_, process)| process.parent() == Some(parent))
+            .map(|(pid, _)| *pid)
+            .collect();
+        // A reused parent pid could otherwise loop
+        for child in children {
+            if !tree.contains(&child) {
+                tree.push(child);
+            }
+        }
+        i += 1;
+    }
+    for pid in tree.iter().skip(1).rev() {
+        if let Some(process) = system.process(*pid) {
+            process.kill();
+        }
+    }
+    system.process(root).is_some_and(|process| process.kill())
+}
+
+/// Orphans found at startup, for daemon.orphans
+pub struct Orphans {
+    found: Mutex<Vec<OrphanInfo>>,
+}
+
+impl Orphans {
+    /// Check every session's recorded pid and deal with the ones still running
+    /// Pids that are gone (or reused by another process) are cleared.
+    pub async fn reap(state: &SharedState, policy: OrphanPolicy) -> Self {
+        let mut found = Vec::new();
+        let mut s = state.write().await;
+
+        for session in s.sessions.values_mut() {
+            let Some(pid) = session.pid else {
+                continue;
+            };
+            let started = match session.pid_start_time {
+                Some(started) if is_same_process(pid, started) => started,
+                _ => {
+                    session.pid = None;
+                    session.pid_start_time = None;
+                    continue;
+                }
+            };
+
+            let action = match policy {
+                OrphanPolicy::Adopt => OrphanAction::Adopted,
+                OrphanPolicy::Kill if kill(pid, started).await => OrphanAction::Killed,
+                OrphanPolicy::Kill => OrphanAction::KillFailed,
+            };
+            warn!(
+                "Orphaned claude process {} for session {} ({:?})",
+                pid, session.id, action
+            );
+            if action == OrphanAction::Killed {
+                session.pid = None;
+                session.pid_start_time = None;
+            }
+
+            found.push(OrphanInfo {
+                session_id: session.id,
+                name: session.name.clone(),
+                pid,
+                started_at: Utc
+                    .timestamp_opt(started as i64, 0)
+                    .single()
+                    .unwrap_or_else(Utc::now),
+                action,
+                alive: action != OrphanAction::Killed,
+            });
+        }
+
+        if !found.is_empty() {
+            info!("Found {} orphaned claude processes", found.len());
+        }
+        Self {
+            found: Mutex::new(found),
+        }
+    }
+
+    /// Orphans found at startup, with `alive` refreshed
+    pub async fn list(&self) -> Vec<OrphanInfo> {
+        let mut found = self.found.lock().await;
+        for orphan in found.iter_mut().filter(|orphan| orphan.alive) {
+            orphan.alive = is_same_process(orphan.pid, orphan.started_at.timestamp() as u64);
+        }
+        found.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_same_process() {
+        let pid = std::process::id();
+        let started = start_time(pid).unwrap();
+        assert!(is_same_process(pid, started));
+        // Same pid but a different start time is a reused pid
+        assert!(!is_same_process(pid, started.saturating_sub(3600)));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_kill_takes_the_group() {
+        use std::io::{BufRead, BufReader};
+        use std::os::unix::process::CommandExt;
+        use std::process::{Command, Stdio};
+        use sysinfo::ProcessStatus;
+
+        // A session leader like a PTY child, with a tool running under it
+        let mut leader = unsafe {
+            Command::new("sh")
+                .args(["-c", "sleep 60 & echo $!; wait"])
+                .stdout(Stdio::piped())
+                .pre_exec(|| {
+                    libc::setsid();
+                    Ok(())
+                })
+                .spawn()
+                .unwrap()
+        };
+        let mut line = String::new();
+        BufReader::new(leader.stdout.take().unwrap())
+            .read_line(&mut line)
+            .unwrap();
+        let child = Pid::from_u32(line.trim().parse().unwrap());
+        let pid = leader.id();
+        let started = start_time(pid).unwrap();
+        // The daemon isn't an orphan's parent; reap it here so it doesn't linger
+        let waiter = std::thread::spawn(move || leader.wait());
+
+        assert!(!kill(pid, started.saturating_sub(3600)).await);
+        assert!(kill(pid, started).await);
+        assert!(waiter.join().unwrap().is_ok());
+        std::thread::sleep(std::time::Duration::from_millis(100));
+        let mut system = System::new();
+        system.refresh_processes(ProcessesToUpdate::Some(&[child]), true);
+        assert!(system
+            .process(child)
+            .is_none_or(|process| process.status() == ProcessStatus::Zombie));
+    }
+}
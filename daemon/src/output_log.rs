@@ -0,0 +1,435 @@
+// Session output logs - optional raw PTY output per session under
+// logs/sessions/<id>.log, rotated by size, so output outlives GUI and daemon restarts
+// Each write is also timed in <id>.timing as `<unix_ms> <bytes>` lines for session.replay
+// Enabled with [session_logs] in config; read back with session.tail_log, and as
+// plain text with session.search_output / session.export_output
+
+use anyhow::Result;
+use chrono::Utc;
+use regex::Regex;
+use shared::OutputMatch;
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{LazyLock, Mutex};
+use tracing::warn;
+use uuid::Uuid;
+
+use crate::claude;
+use crate::config::{SessionLogConfig, SharedConfig};
+
+/// How much session.tail_log returns when the client doesn't say
+pub const DEFAULT_TAIL_BYTES: usize = 64 * 1024;
+/// Most matches session.search_output returns when the client doesn't say
+pub const DEFAULT_SEARCH_LIMIT: usize = 200;
+
+/// Cursor moves within a line (`ESC[5G`, `ESC[2C`); claude lays out words with
+/// these instead of spaces, so they become a space rather than nothing
+static CURSOR_MOVE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\x1b\[\d*[CG]").unwrap());
+/// Short escapes left after stripping ANSI sequences, like `ESC 7` (save cursor)
+static SHORT_ESCAPE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\x1b[\x20-\x2f]*[\x30-\x7e]").unwrap());
+
+struct LogFile {
+    file: File,
+    timing: File,
+    size: u64,
+}
+
+/// A run of output from one log write; `at` is None for logs written without timing
+#[derive(Debug, PartialEq)]
+pub struct TimedChunk {
+    pub at: Option<i64>,
+    pub data: Vec<u8>,
+}
+
+/// Open log files for sessions that have produced output
+pub struct OutputLogger {
+    config: SharedConfig,
+    files: Mutex<HashMap<Uuid, LogFile>>,
+}
+
+/// Current log file for a session (older output is in `<id>.log.1`, `.2`, ...)
+pub fn log_path(session_id: Uuid) -> Result<PathBuf> {
+    let dir = shared::get_logs_dir()?.join("sessions");
+    fs::create_dir_all(&dir)?;
+    Ok(dir.join(format!("{}.log", session_id)))
+}
+
+/// Timing file for a log file (`<id>.log.2` is timed by `<id>.timing.2`)
+fn timing_path(path: &Path) -> PathBuf {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    path.with_file_name(name.replacen(".log", ".timing", 1))
+}
+
+pub fn rotated_path(path: &Path, n: usize) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(format!(".{}", n));
+    PathBuf::from(name)
+}
+
+/// Rotate a log file and its timing file together
+fn rotate_log(path: &Path, max_files: usize) -> Result<()> {
+    rotate(path, max_files)?;
+    let timing = timing_path(path);
+    if timing.exists() {
+        rotate(&timing, max_files)?;
+    }
+    Ok(())
+}
+
+/// Shift `path` to `path.1`, `path.1` to `path.2`, ... keeping `max_files` old files
+pub fn rotate(path: &Path, max_files: usize) -> Result<()> {
+    if max_files == 0 {
+        fs::remove_file(path)?;
+        return Ok(());
+    }
+    let _ = fs::remove_file(rotated_path(path, max_files));
+    for n in (1..max_files).rev() {
+        let from = rotated_path(path, n);
+        if from.exists() {
+            fs::rename(&from, rotated_path(path, n + 1))?;
+        }
+    }
+    fs::rename(path, rotated_path(path, 1))?;
+    Ok(())
+}
+
+/// Last `max_bytes` of the log at `path`, continuing into `path.1` if the
+/// current file is shorter. Returns the bytes and whether older output was left out.
+fn tail_file(path: &Path, max_bytes: usize) -> Result<(Vec<u8>, bool)> {
+    let mut parts = Vec::new();
+    let mut remaining = max_bytes as u64;
+    let mut truncated = false;
+
+    let mut read = 0;
+    for candidate in [path.to_path_buf(), rotated_path(path, 1)] {
+        let Ok(mut file) = File::open(&candidate) else {
+            break;
+        };
+        read += 1;
+        let len = file.metadata()?.len();
+        let take = len.min(remaining);
+        file.seek(SeekFrom::Start(len - take))?;
+        let mut buf = Vec::with_capacity(take as usize);
+        file.take(take).read_to_end(&mut buf)?;
+        parts.push(buf);
+
+        remaining -= take;
+        if take < len {
+            truncated = true;
+            break;
+        }
+        if remaining == 0 {
+            break;
+        }
+    }
+    // Whole files were read but older rotations remain
+    if !truncated && read > 0 {
+        truncated = rotated_path(path, read).exists();
+    }
+
+    parts.reverse();
+    Ok((parts.concat(), truncated))
+}
+
+/// Split a log file's contents into chunks using its timing lines
+/// Output not covered by the timing (a log written before timing existed, or a
+/// failed timing write) is returned as one untimed chunk.
+fn split_timed(data: Vec<u8>, timing: &str) -> Vec<TimedChunk> {
+    let mut chunks = Vec::new();
+    let mut offset = 0;
+    for line in timing.lines() {
+        let Some((at, len)) = line.split_once(' ') else {
+            continue;
+        };
+        let (Ok(at), Ok(len)) = (at.parse::<i64>(), len.parse::<usize>()) else {
+            continue;
+        };
+        let end = (offset + len).min(data.len());
+        chunks.push(TimedChunk {
+            at: Some(at),
+            data: data[offset..end].to_vec(),
+        });
+        offset = end;
+    }
+    if offset < data.len() {
+        chunks.push(TimedChunk {
+            at: None,
+            data: data[offset..].to_vec(),
+        });
+    }
+    chunks
+}
+
+/// A session's log files, oldest first (`.N` ... `.1`, then the current file)
+pub fn segments(session_id: Uuid) -> Result<Vec<PathBuf>> {
+    let path = log_path(session_id)?;
+    let mut segments = Vec::new();
+    let mut n = 1;
+    while rotated_path(&path, n).exists() {
+        segments.push(rotated_path(&path, n));
+        n += 1;
+    }
+    segments.reverse();
+    if path.exists() {
+        segments.push(path);
+    }
+    Ok(segments)
+}
+
+/// Read one log file as timed chunks
+pub fn read_segment(path: &Path) -> Result<Vec<TimedChunk>> {
+    let data = fs::read(path)?;
+    let timing = fs::read_to_string(timing_path(path)).unwrap_or_default();
+    Ok(split_timed(data, &timing))
+}
+
+/// Raw terminal output as plain text: escape sequences and carriage returns removed
+fn to_text(raw: &[u8]) -> String {
+    let text = String::from_utf8_lossy(raw);
+    let text = CURSOR_MOVE.replace_all(&text, " ");
+    SHORT_ESCAPE
+        .replace_all(&claude::strip_ansi(&text), "")
+        .chars()
+        .filter(|c| !c.is_control() || *c == '\n' || *c == '\t')
+        .collect()
+}
+
+/// Everything kept in a session's output log, oldest first
+pub fn read_all(session_id: Uuid) -> Result<Vec<u8>> {
+    let mut raw = Vec::new();
+    for path in segments(session_id)? {
+        raw.extend(fs::read(path)?);
+    }
+    Ok(raw)
+}
+
+/// Lines of `text` containing `query`, numbered from 1. Returns at most `limit`
+/// matches and whether more were left out.
+fn search_text(
+    text: &str,
+    query: &str,
+    case_sensitive: bool,
+    limit: usize,
+) -> (Vec<OutputMatch>, bool) {
+    let query = if case_sensitive {
+        query.to_string()
+    } else {
+        query.to_lowercase()
+    };
+    let mut matches = text.lines().enumerate().filter(|(_, line)| {
+        if case_sensitive {
+            line.contains(&query)
+        } else {
+            line.to_lowercase().contains(&query)
+        }
+    });
+
+    let found = matches
+        .by_ref()
+        .take(limit)
+        .map(|(n, line)| OutputMatch {
+            line: n + 1,
+            text: line.trim_end().to_string(),
+        })
+        .collect();
+    (found, matches.next().is_some())
+}
+
+/// A session's logged output as plain-text lines, each with the time (unix ms)
+/// of the write it started in; None for output logged without timing
+pub fn timed_lines(session_id: Uuid) -> Result<Vec<(Option<i64>, String)>> {
+    let mut lines = Vec::new();
+    let mut current: Option<(Option<i64>, String)> = None;
+    for path in segments(session_id)? {
+        for chunk in read_segment(&path)? {
+            for (i, part) in to_text(&chunk.data).split('\n').enumerate() {
+                if i > 0 {
+                    lines.push(current.take().unwrap_or((chunk.at, String::new())));
+                }
+                let (_, line) = current.get_or_insert_with(|| (chunk.at, String::new()));
+                line.push_str(part);
+            }
+        }
+    }
+    lines.extend(current.filter(|(_, line)| !line.is_empty()));
+    Ok(lines)
+}
+
+/// Search a session's logged output as plain text
+pub fn search(
+    session_id: Uuid,
+    query: &str,
+    case_sensitive: bool,
+    limit: usize,
+) -> Result<(Vec<OutputMatch>, bool)> {
+    let text = to_text(&read_all(session_id)?);
+    Ok(search_text(&text, query, case_sensitive, limit))
+}
+
+/// Write a session's logged output to `dest`, as plain text unless `raw`.
+/// Returns the number of bytes written.
+pub fn export(session_id: Uuid, dest: &Path, raw: bool) -> Result<usize> {
+    let output = read_all(session_id)?;
+    let output = if raw {
+        output
+    } else {
+        to_text(&output).into_bytes()
+    };
+    fs::write(dest, &output)?;
+    Ok(output.len())
+}
+
+/// Read the end of a session's output log
+pub fn tail(session_id: Uuid, max_bytes: usize) -> Result<(PathBuf, Vec<u8>, bool)> {
+    let path = log_path(session_id)?;
+    let (output, truncated) = tail_file(&path, max_bytes)?;
+    Ok((path, output, truncated))
+}
+
+impl OutputLogger {
+    pub fn new(config: SharedConfig) -> Self {
+        Self {
+            config,
+            files: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Append PTY output to the session's log if logging is enabled
+    /// Logging is best-effort: failures are logged, never propagated
+    pub async fn write(&self, session_id: Uuid, data: &[u8]) {
+        let settings = self.config.read().await.session_logs.clone();
+        if !settings.enabled {
+            return;
+        }
+
+        let mut files = self.files.lock().unwrap();
+        if let Err(e) = Self::append(&mut files, session_id, data, &settings) {
+            warn!(
+                "Failed to write output log for session {}: {}",
+                session_id, e
+            );
+            files.remove(&session_id);
+        }
+    }
+
+    fn append(
+        files: &mut HashMap<Uuid, LogFile>,
+        session_id: Uuid,
+        data: &[u8],
+        settings: &SessionLogConfig,
+    ) -> Result<()> {
+        let max_size = settings.max_size_kb * 1024;
+        let full = files
+            .get(&session_id)
+            .is_some_and(|log| log.size > 0 && log.size + data.len() as u64 > max_size);
+        if full {
+            files.remove(&session_id);
+            rotate_log(&log_path(session_id)?, settings.max_files)?;
+        }
+
+        let log = match files.entry(session_id) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => {
+                let path = log_path(session_id)?;
+                let file = OpenOptions::new().create(true).append(true).open(&path)?;
+                let timing = OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(timing_path(&path))?;
+                let size = file.metadata()?.len();
+                entry.insert(LogFile { file, timing, size })
+            }
+        };
+        log.file.write_all(data)?;
+        log.timing
+            .write_all(format!("{} {}\n", Utc::now().timestamp_millis(), data.len()).as_bytes())?;
+        log.size += data.len() as u64;
+        Ok(())
+    }
+
+    /// Close a session's log file (it is reopened on the next output)
+    pub fn close(&self, session_id: Uuid) {
+        self.files.lock().unwrap().remove(&session_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rotate_and_tail() {
+        let dir = std::env::temp_dir().join(format!("output-log-test-{}", Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("session.log");
+
+        for chunk in ["aaaa", "bbbb", "cccc"] {
+            if path.exists() {
+                rotate(&path, 1).unwrap();
+            }
+            fs::write(&path, chunk).unwrap();
+        }
+        // Only one rotated file is kept
+        assert!(rotated_path(&path, 1).exists());
+        assert!(!rotated_path(&path, 2).exists());
+
+        let (output, truncated) = tail_file(&path, 6).unwrap();
+        assert_eq!(output, b"bbcccc");
+        assert!(truncated);
+
+        let (output, truncated) = tail_file(&path, 100).unwrap();
+        assert_eq!(output, b"bbbbcccc");
+        assert!(!truncated);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_to_text_and_search() {
+        let raw = b"\x1b7\x1b[r\x1b8\x1b[32mWelcome\x1b[9Gto\x1b[0m Claude\r\r\nBuild \x1b[1mFAILED\x1b[0m\r\nbuild ok\r\n";
+        let text = to_text(raw);
+        assert_eq!(text, "Welcome to Claude\nBuild FAILED\nbuild ok\n");
+
+        let (matches, truncated) = search_text(&text, "build", false, 10);
+        assert_eq!(matches.len(), 2);
+        assert_eq!(matches[0].line, 2);
+        assert_eq!(matches[0].text, "Build FAILED");
+        assert!(!truncated);
+
+        let (matches, truncated) = search_text(&text, "build", true, 10);
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].line, 3);
+        assert!(!truncated);
+
+        let (matches, truncated) = search_text(&text, "build", false, 1);
+        assert_eq!(matches.len(), 1);
+        assert!(truncated);
+    }
+
+    #[test]
+    fn test_split_timed() {
+        let chunks = split_timed(b"helloworld!!".to_vec(), "100 5\n250 5\n");
+        assert_eq!(
+            chunks,
+            vec![
+                TimedChunk {
+                    at: Some(100),
+                    data: b"hello".to_vec()
+                },
+                TimedChunk {
+                    at: Some(250),
+                    data: b"world".to_vec()
+                },
+                // Not covered by the timing file
+                TimedChunk {
+                    at: None,
+                    data: b"!!".to_vec()
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,162 @@
+// Approval policies - sessions and groups carry an allowlist of tools, file
+// globs and Bash command regexes (set with policy.set). When Claude shows a
+// permission dialog the session manager checks the session's policy and those
+// of the groups above it, and answers a match itself, emitting
+// session:auto_approved and recording it in the session's history.
+
+use anyhow::{Context, Result};
+use glob::Pattern;
+use regex::Regex;
+use shared::{ApprovalPolicy, Session};
+use std::path::{Component, Path};
+
+use crate::state::AppState;
+
+/// Command regexes only approve a single command: anything that chains,
+/// substitutes or redirects could hide more behind an approved prefix
+const SHELL_CONTROL: &[&str] = &[";", "&", "|", "`", "$(", ">", "<", "\n"];
+
+/// Tools whose permission dialogs are about a file
+const FILE_TOOLS: &[&str] = &["Edit", "MultiEdit", "Write", "Read", "NotebookEdit"];
+
+/// What a permission dialog asks for
+pub struct ApprovalRequest<'a> {
+    pub tool: &'a str,
+    /// The Bash command, for Bash
+    pub command: Option<&'a str>,
+    /// The file, for file tools
+    pub path: Option<&'a str>,
+}
+
+/// Reject globs and regexes that don't parse, before they are saved
+pub fn validate(policy: &ApprovalPolicy) -> Result<()> {
+    for glob in &policy.paths {
+        Pattern::new(glob).with_context(|| format!("Invalid path glob '{}'", glob))?;
+    }
+    for pattern in &policy.commands {
+        Regex::new(pattern).with_context(|| format!("Invalid command pattern '{}'", pattern))?;
+    }
+    Ok(())
+}
+
+/// The session's policy followed by those of its groups, innermost first
+pub fn effective(state: &AppState, session: &Session) -> Vec<ApprovalPolicy> {
+    let mut policies = vec![session.approval_policy.clone()];
+    let mut group_id = session.group_id;
+    // Bounded in case of a parent cycle
+    for _ in 0..state.groups.len() {
+        let Some(group) = group_id.and_then(|id| state.groups.get(&id)) else {
+            break;
+        };
+        policies.push(group.approval_policy.clone());
+        group_id = group.parent_id;
+    }
+    policies.retain(|policy| !policy.is_empty());
+    policies
+}
+
+/// The first policy entry that approves `request`, as "kind:entry"
+pub fn matching_rule(
+    policies: &[ApprovalPolicy],
+    request: &ApprovalRequest,
+    working_dir: &Path,
+) -> Option<String> {
+    // Never approve a path that climbs out with ..
+    let path = request
+        .path
+        .filter(|_| FILE_TOOLS.contains(&request.tool))
+        .map(Path::new)
+        .filter(|path| !path.components().any(|c| c == Component::ParentDir));
+    let relative = path.and_then(|path| path.strip_prefix(working_dir).ok());
+
+    for policy in policies {
+        if let Some(tool) = policy
+            .tools
+            .iter()
+            .find(|tool| *tool == "*" || tool.eq_ignore_ascii_case(request.tool))
+        {
+            return Some(format!("tool:{}", tool));
+        }
+
+        let command = request.command.filter(|command| {
+            request.tool == "Bash" && !SHELL_CONTROL.iter().any(|op| command.contains(op))
+        });
+        if let Some(command) = command {
+            let matched = policy.commands.iter().find(|pattern| {
+                Regex::new(pattern).is_ok_and(|regex| regex.is_match(command.trim()))
+            });
+            if let Some(pattern) = matched {
+                return Some(format!("command:{}", pattern));
+            }
+        }
+
+        if let Some(path) = path {
+            let matched = policy.paths.iter().find(|glob| {
+                Pattern::new(glob).is_ok_and(|pattern| {
+                    pattern.matches_path(path) || relative.is_some_and(|r| pattern.matches_path(r))
+                })
+            });
+            if let Some(glob) = matched {
+                return Some(format!("path:{}", glob));
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(tools: &[&str], paths: &[&str], commands: &[&str]) -> ApprovalPolicy {
+        let strings = |items: &[&str]| items.iter().map(|s| s.to_string()).collect();
+        ApprovalPolicy {
+            tools: strings(tools),
+            paths: strings(paths),
+            commands: strings(commands),
+        }
+    }
+
+    #[test]
+    fn test_matching_rule() {
+        let dir = Path::new("/repo");
+        let policies = vec![
+            policy(&[], &["src/**"], &[r"^cargo (test|build)\b"]),
+            policy(&["read"], &[], &[]),
+        ];
+        let rule = |tool, command, path| {
+            matching_rule(
+                &policies,
+                &ApprovalRequest {
+                    tool,
+                    command,
+                    path,
+                },
+                dir,
+            )
+        };
+
+        assert_eq!(
+            rule("Bash", Some("cargo test --workspace"), None).as_deref(),
+            Some(r"command:^cargo (test|build)\b")
+        );
+        assert_eq!(rule("Bash", Some("rm -rf target"), None), None);
+        assert_eq!(rule("Bash", Some("cargo test && rm -rf ~"), None), None);
+        assert_eq!(rule("Bash", Some("cargo build $(curl x)"), None), None);
+        assert_eq!(
+            rule("Edit", None, Some("/repo/src/main.rs")).as_deref(),
+            Some("path:src/**")
+        );
+        assert_eq!(rule("Edit", None, Some("/repo/src/../.env")), None);
+        assert_eq!(rule("Write", None, Some("/etc/passwd")), None);
+        // The group's policy applies too, matching tool names in any case
+        assert_eq!(
+            rule("Read", None, Some("/etc/hosts")).as_deref(),
+            Some("tool:read")
+        );
+
+        assert!(validate(&policy(&[], &["src/[*"], &[])).is_err());
+        assert!(validate(&policy(&[], &[], &["(unclosed"])).is_err());
+        assert!(validate(&policies[0]).is_ok());
+    }
+}
@@ -0,0 +1,214 @@
+// Process groups - a session's claude and everything it starts (node, bash,
+// test runners, dev servers) are killed together. On Unix the PTY spawn makes
+// claude a session leader (setsid), so its pid is the process group its tools
+// inherit; on Windows the child is put in a job object right after it is spawned.
+// Stopping asks the group to exit with SIGTERM, so claude can flush its
+// transcript, and SIGKILLs what is left after a grace period (KILL_GRACE unless
+// session.stop gives a timeout_ms); Windows has no polite signal, so the job is
+// terminated at once.
+
+use shared::StopOutcome;
+use std::time::Duration;
+
+/// How long a group gets to exit after SIGTERM before it is SIGKILLed, by default
+pub const KILL_GRACE: Duration = Duration::from_secs(3);
+const KILL_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+#[cfg(unix)]
+pub struct ProcessGroup {
+    pgid: libc::pid_t,
+}
+
+#[cfg(unix)]
+impl ProcessGroup {
+    /// The group a just-spawned PTY child leads
+    pub fn new(child: &(dyn portable_pty::Child + Send + Sync)) -> Option<Self> {
+        let pgid = libc::pid_t::try_from(child.process_id()?).ok()?;
+        Some(Self { pgid })
+    }
+
+    /// The group `pid` still leads, e.g. an orphaned session from an earlier
+    /// daemon (None if it has left it or is gone)
+    pub fn adopt(pid: u32) -> Option<Self> {
+        let pgid = libc::pid_t::try_from(pid).ok()?;
+        // SAFETY: getpgid only looks the process up
+        (unsafe { libc::getpgid(pgid) } == pgid).then_some(Self { pgid })
+    }
+
+    fn signal(&self, signal: libc::c_int) -> bool {
+        // SAFETY: killpg only sends a signal; pgid is a group we created
+        unsafe { libc::killpg(self.pgid, signal) == 0 }
+    }
+
+    /// Whether any process of the group is left (zombies included)
+    fn is_alive(&self) -> bool {
+        self.signal(0)
+    }
+
+    /// SIGTERM the group, then SIGKILL it if anything is left after `grace`
+    /// (or SIGKILL it at once with `force`); `reap` is called while waiting so
+    /// the exited leader doesn't linger as a zombie
+    pub async fn stop(&self, grace: Duration, force: bool, mut reap: impl FnMut()) -> StopOutcome {
+        if force {
+            self.signal(libc::SIGKILL);
+            reap();
+            return StopOutcome::Forced;
+        }
+        if !self.signal(libc::SIGTERM) {
+            return StopOutcome::NotRunning;
+        }
+        let started = std::time::Instant::now();
+        while started.elapsed() < grace {
+            tokio::time::sleep(KILL_POLL_INTERVAL.min(grace)).await;
+            reap();
+            if !self.is_alive() {
+                return StopOutcome::Exited;
+            }
+        }
+        tracing::warn!(
+            "Process group {} still running {:?} after SIGTERM, sending SIGKILL",
+            self.pgid,
+            grace
+        );
+        self.signal(libc::SIGKILL);
+        reap();
+        StopOutcome::Killed
+    }
+}
+
+#[cfg(windows)]
+pub struct ProcessGroup {
+    job: windows_sys::Win32::Foundation::HANDLE,
+}
+
+// SAFETY: a job object handle can be used from any thread
+#[cfg(windows)]
+unsafe impl Send for ProcessGroup {}
+#[cfg(windows)]
+unsafe impl Sync for ProcessGroup {}
+
+#[cfg(windows)]
+impl ProcessGroup {
+    /// A job object holding a just-spawned PTY child; processes it starts from
+    /// now on are put in the job too
+    pub fn new(child: &(dyn portable_pty::Child + Send + Sync)) -> Option<Self> {
+        use windows_sys::Win32::Foundation::CloseHandle;
+        use windows_sys::Win32::System::JobObjects::{AssignProcessToJobObject, CreateJobObjectW};
+
+        let process = child.as_raw_handle()?;
+        // SAFETY: an unnamed job with default security; the process handle is
+        // owned by `child`, which outlives this call
+        unsafe {
+            let job = CreateJobObjectW(std::ptr::null(), std::ptr::null());
+            if job.is_null() {
+                return None;
+            }
+            if AssignProcessToJobObject(job, process as _) == 0 {
+                CloseHandle(job);
+                return None;
+            }
+            Some(Self { job })
+        }
+    }
+
+    /// The job of an earlier daemon's session closed with that daemon, so
+    /// there is no group to find again
+    pub fn adopt(_pid: u32) -> Option<Self> {
+        None
+    }
+
+    /// Terminate every process in the job; there is nothing to wait for
+    pub async fn stop(
+        &self,
+        _grace: Duration,
+        _force: bool,
+        mut reap: impl FnMut(),
+    ) -> StopOutcome {
+        use windows_sys::Win32::System::JobObjects::TerminateJobObject;
+
+        // SAFETY: the job handle stays open until drop
+        unsafe {
+            TerminateJobObject(self.job, 1);
+        }
+        reap();
+        StopOutcome::Forced
+    }
+}
+
+#[cfg(windows)]
+impl Drop for ProcessGroup {
+    fn drop(&mut self) {
+        // Closing the handle leaves the processes running (no KILL_ON_JOB_CLOSE),
+        // so sessions detached on shutdown survive the daemon
+        // SAFETY: the handle came from CreateJobObjectW and is closed once
+        unsafe {
+            windows_sys::Win32::Foundation::CloseHandle(self.job);
+        }
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader};
+    use std::os::unix::process::{CommandExt, ExitStatusExt};
+    use std::process::{Command, Stdio};
+    use sysinfo::{Pid, ProcessStatus, ProcessesToUpdate, System};
+
+    fn spawn_leader(script: &str) -> std::process::Child {
+        unsafe {
+            Command::new("sh")
+                .args(["-c", script])
+                .stdout(Stdio::piped())
+                .pre_exec(|| {
+                    libc::setsid();
+                    Ok(())
+                })
+                .spawn()
+                .unwrap()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_stop_group() {
+        let mut leader = spawn_leader("exec sleep 60");
+        let group = ProcessGroup {
+            pgid: leader.id() as libc::pid_t,
+        };
+        let outcome = group
+            .stop(KILL_GRACE, false, || {
+                let _ = leader.try_wait();
+            })
+            .await;
+        assert_eq!(outcome, StopOutcome::Exited);
+
+        // A leader that ignores SIGTERM and a child that inherits its group
+        let mut leader = spawn_leader("trap '' TERM; sleep 60 & echo $!; wait");
+        let group = ProcessGroup {
+            pgid: leader.id() as libc::pid_t,
+        };
+        let mut line = String::new();
+        BufReader::new(leader.stdout.take().unwrap())
+            .read_line(&mut line)
+            .unwrap();
+        let child = Pid::from_u32(line.trim().parse().unwrap());
+        assert!(group.is_alive());
+
+        let outcome = group
+            .stop(Duration::from_millis(300), false, || {
+                let _ = leader.try_wait();
+            })
+            .await;
+        assert_eq!(outcome, StopOutcome::Killed);
+        // It outlasted SIGTERM, so the whole group got SIGKILL
+        let status = leader.wait().unwrap();
+        assert_eq!(status.signal(), Some(libc::SIGKILL));
+        // The child is gone too (a zombie if nothing has reaped it yet)
+        std::thread::sleep(Duration::from_millis(100));
+        let mut system = System::new();
+        system.refresh_processes(ProcessesToUpdate::Some(&[child]), true);
+        assert!(system
+            .process(child)
+            .is_none_or(|process| process.status() == ProcessStatus::Zombie));
+    }
+}
@@ -0,0 +1,175 @@
+// Project discovery - projects.scan suggests working directories for a new
+// session: the projects in Claude Code's own registry (~/.claude/projects),
+// the working directories of existing sessions, and the git repositories
+// containing them, most recently used first.
+
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+use shared::{ProjectCandidate, ProjectSource};
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, Read};
+use std::path::{Path, PathBuf};
+
+use crate::git;
+use crate::state::SharedState;
+
+/// How much of a transcript is searched for the directory it was written in
+const CWD_SEARCH_BYTES: u64 = 64 * 1024;
+
+/// The newest transcript of a Claude project directory and when it was written
+fn latest_transcript(project: &Path) -> Option<(PathBuf, DateTime<Utc>)> {
+    fs::read_dir(project)
+        .ok()?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "jsonl"))
+        .filter_map(|path| {
+            let modified = fs::metadata(&path).and_then(|m| m.modified()).ok()?;
+            Some((path, DateTime::<Utc>::from(modified)))
+        })
+        .max_by_key(|(_, modified)| *modified)
+}
+
+/// The working directory a transcript was written in
+/// Claude's project names replace every non-alphanumeric with '-', so the
+/// path can't be recovered from them; the entries record it as `cwd`.
+fn transcript_cwd(path: &Path) -> Option<PathBuf> {
+    let reader = BufReader::new(File::open(path).ok()?.take(CWD_SEARCH_BYTES));
+    reader.lines().map_while(Result::ok).find_map(|line| {
+        let entry: Value = serde_json::from_str(&line).ok()?;
+        entry.get("cwd")?.as_str().map(PathBuf::from)
+    })
+}
+
+/// Projects in Claude Code's registry under `projects_dir`
+fn claude_projects(projects_dir: &Path) -> Vec<ProjectCandidate> {
+    let Ok(entries) = fs::read_dir(projects_dir) else {
+        return Vec::new();
+    };
+    entries
+        .flatten()
+        .filter_map(|entry| {
+            let (transcript, last_used) = latest_transcript(&entry.path())?;
+            Some(ProjectCandidate {
+                path: transcript_cwd(&transcript)?,
+                last_used,
+                source: ProjectSource::Claude,
+            })
+        })
+        .collect()
+}
+
+/// Keep one candidate per directory, the most recently used, dropping
+/// directories that no longer exist; newest first, at most `limit`
+fn merge(candidates: Vec<ProjectCandidate>, limit: usize) -> Vec<ProjectCandidate> {
+    let mut by_path: HashMap<PathBuf, ProjectCandidate> = HashMap::new();
+    for candidate in candidates {
+        match by_path.get(&candidate.path) {
+            Some(existing) if existing.last_used >= candidate.last_used => {}
+            _ => {
+                by_path.insert(candidate.path.clone(), candidate);
+            }
+        }
+    }
+    let mut candidates: Vec<ProjectCandidate> = by_path
+        .into_values()
+        .filter(|candidate| candidate.path.is_dir())
+        .collect();
+    candidates.sort_by_key(|candidate| Reverse(candidate.last_used));
+    candidates.truncate(limit);
+    candidates
+}
+
+/// Candidate working directories, most recently used first
+pub async fn scan(state: &SharedState, limit: usize) -> Vec<ProjectCandidate> {
+    let mut candidates = match dirs::home_dir() {
+        Some(home) => {
+            let projects_dir = home.join(".claude").join("projects");
+            tokio::task::spawn_blocking(move || claude_projects(&projects_dir))
+                .await
+                .unwrap_or_default()
+        }
+        None => Vec::new(),
+    };
+    candidates.extend(
+        state
+            .read()
+            .await
+            .sessions
+            .values()
+            .map(|session| ProjectCandidate {
+                path: session.working_dir.clone(),
+                last_used: session.last_activity,
+                source: ProjectSource::Session,
+            }),
+    );
+
+    // The repositories they are in, e.g. the root of a session started in a subdirectory
+    let mut roots: HashMap<PathBuf, DateTime<Utc>> = HashMap::new();
+    for candidate in &candidates {
+        if let Some(root) = git::toplevel(&candidate.path).await {
+            let last_used = roots.entry(root).or_insert(candidate.last_used);
+            *last_used = (*last_used).max(candidate.last_used);
+        }
+    }
+    candidates.extend(
+        roots
+            .into_iter()
+            .filter(|(root, _)| !candidates.iter().any(|c| &c.path == root))
+            .map(|(path, last_used)| ProjectCandidate {
+                path,
+                last_used,
+                source: ProjectSource::Git,
+            })
+            .collect::<Vec<_>>(),
+    );
+
+    merge(candidates, limit)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    #[test]
+    fn test_claude_projects() {
+        let dir = std::env::temp_dir().join(format!("projects-test-{}", Uuid::new_v4()));
+        let workdir = dir.join("my.project");
+        let project = dir.join("projects").join("-tmp-my-project");
+        fs::create_dir_all(&workdir).unwrap();
+        fs::create_dir_all(&project).unwrap();
+        fs::write(
+            project.join("a.jsonl"),
+            format!(
+                "{{\"type\":\"summary\"}}\n{{\"type\":\"user\",\"cwd\":{:?}}}\n",
+                workdir.to_string_lossy()
+            ),
+        )
+        .unwrap();
+        // A project without transcripts isn't a candidate
+        fs::create_dir_all(dir.join("projects").join("-empty")).unwrap();
+
+        let found = claude_projects(&dir.join("projects"));
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].path, workdir);
+        assert_eq!(found[0].source, ProjectSource::Claude);
+
+        // The newest use of a directory wins and missing directories are dropped
+        let older = ProjectCandidate {
+            path: workdir.clone(),
+            last_used: found[0].last_used - chrono::Duration::days(1),
+            source: ProjectSource::Session,
+        };
+        let gone = ProjectCandidate {
+            path: dir.join("gone"),
+            last_used: Utc::now(),
+            source: ProjectSource::Session,
+        };
+        let merged = merge(vec![older, found[0].clone(), gone], 10);
+        assert_eq!(merged, found);
+        let _ = fs::remove_dir_all(dir);
+    }
+}
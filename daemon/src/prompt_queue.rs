@@ -0,0 +1,229 @@
+// Prompt queue - holds prompts until their session is ready for input and a
+// global concurrency slot is free, then types them into the PTY
+// Lanes are served interactive > scheduled > batch, oldest first within a lane.
+// The queue lives in memory only; queued prompts are lost on daemon restart.
+
+use anyhow::Result;
+use chrono::Utc;
+use shared::{Event, PromptPriority, QueuedPrompt, SessionStatus};
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{broadcast, Mutex, Notify};
+use tracing::{debug, info, warn};
+use uuid::Uuid;
+
+use crate::config::SharedConfig;
+use crate::pty::PtyManager;
+use crate::state::{SessionNotFound, SharedState};
+use crate::turns::TurnTracker;
+
+/// Safety net in case a status event was missed
+const RETRY_INTERVAL_SECS: u64 = 2;
+
+#[derive(Default)]
+struct Inner {
+    items: Vec<QueuedPrompt>,
+    /// Sessions sent a prompt that haven't reported Running yet; they hold a slot
+    dispatched: HashSet<Uuid>,
+}
+
+pub struct PromptQueue {
+    state: SharedState,
+    config: SharedConfig,
+    pty_manager: Arc<PtyManager>,
+    turn_tracker: Arc<TurnTracker>,
+    inner: Mutex<Inner>,
+    wake: Notify,
+}
+
+impl PromptQueue {
+    pub fn new(
+        state: SharedState,
+        config: SharedConfig,
+        pty_manager: Arc<PtyManager>,
+        turn_tracker: Arc<TurnTracker>,
+    ) -> Self {
+        Self {
+            state,
+            config,
+            pty_manager,
+            turn_tracker,
+            inner: Mutex::new(Inner::default()),
+            wake: Notify::new(),
+        }
+    }
+
+    pub async fn enqueue(
+        &self,
+        session_id: Uuid,
+        prompt: String,
+        priority: PromptPriority,
+    ) -> Result<QueuedPrompt> {
+        if !self.state.read().await.sessions.contains_key(&session_id) {
+            return Err(SessionNotFound(session_id).into());
+        }
+        let item = QueuedPrompt {
+            id: Uuid::new_v4(),
+            session_id,
+            prompt,
+            priority,
+            enqueued_at: Utc::now(),
+        };
+        self.inner.lock().await.items.push(item.clone());
+        self.wake.notify_one();
+        Ok(item)
+    }
+
+    /// Remove a queued prompt, returning whether it was found
+    pub async fn cancel(&self, id: Uuid) -> bool {
+        let mut inner = self.inner.lock().await;
+        let before = inner.items.len();
+        inner.items.retain(|item| item.id != id);
+        inner.items.len() != before
+    }
+
+    /// Queued prompts in the order they will be dispatched
+    pub async fn list(&self, session_id: Option<Uuid>) -> Vec<QueuedPrompt> {
+        let mut items: Vec<QueuedPrompt> = self
+            .inner
+            .lock()
+            .await
+            .items
+            .iter()
+            .filter(|item| session_id.is_none_or(|id| item.session_id == id))
+            .cloned()
+            .collect();
+        sort_for_dispatch(&mut items);
+        items
+    }
+
+    /// Dispatch prompts whenever a session frees up or something is enqueued
+    pub async fn run(self: Arc<Self>, event_tx: broadcast::Sender<Event>) {
+        let mut event_rx = event_tx.subscribe();
+
+        loop {
+            tokio::select! {
+                event = event_rx.recv() => match event {
+                    Ok(Event::StatusChanged(data)) => {
+                        // Running (or dead) means the dispatched prompt no longer holds a slot
+                        if data.status != SessionStatus::Waiting {
+                            self.inner.lock().await.dispatched.remove(&data.session_id);
+                        }
+                    }
+                    Ok(_) => continue,
+                    Err(broadcast::error::RecvError::Lagged(n)) => {
+                        warn!("Prompt queue lagged, skipped {} events", n);
+                    }
+                    Err(broadcast::error::RecvError::Closed) => return,
+                },
+                _ = self.wake.notified() => {}
+                _ = tokio::time::sleep(Duration::from_secs(RETRY_INTERVAL_SECS)) => {}
+            }
+
+            self.dispatch().await;
+        }
+    }
+
+    /// Send as many queued prompts as the ready sessions and free slots allow
+    async fn dispatch(&self) {
+        let max_concurrent = self.config.read().await.daemon.max_concurrent_prompts;
+        let mut inner = self.inner.lock().await;
+        if inner.items.is_empty() {
+            return;
+        }
+        sort_for_dispatch(&mut inner.items);
+
+        let (ready, mut busy): (HashSet<Uuid>, usize) = {
+            let s = self.state.read().await;
+            let ready = s
+                .sessions
+                .values()
+                .filter(|session| {
+                    matches!(session.status, SessionStatus::Waiting | SessionStatus::Idle)
+                })
+                .map(|session| session.id)
+                .filter(|id| !inner.dispatched.contains(id))
+                .collect();
+            let running = s
+                .sessions
+                .values()
+                .filter(|session| session.status == SessionStatus::Running)
+                .count();
+            (ready, running + inner.dispatched.len())
+        };
+
+        let mut sent = HashSet::new();
+        let mut index = 0;
+        while index < inner.items.len() {
+            if max_concurrent > 0 && busy >= max_concurrent {
+                break;
+            }
+            let session_id = inner.items[index].session_id;
+            if !ready.contains(&session_id) || sent.contains(&session_id) {
+                index += 1;
+                continue;
+            }
+
+            let item = inner.items.remove(index);
+            let input = format!("{}\r", item.prompt);
+            match self.pty_manager.write(session_id, input.as_bytes()).await {
+                Ok(()) => {
+                    debug!(
+                        "Dispatched {:?} prompt {} to session {}",
+                        item.priority, item.id, session_id
+                    );
+                    self.turn_tracker
+                        .prompt_sent(session_id, Some(item.prompt.clone()))
+                        .await;
+                    inner.dispatched.insert(session_id);
+                    sent.insert(session_id);
+                    busy += 1;
+                }
+                Err(e) => {
+                    // Keep the prompt; the session may be restarted later
+                    info!("Could not dispatch prompt to session {}: {}", session_id, e);
+                    inner.items.insert(index, item);
+                    sent.insert(session_id);
+                    index += 1;
+                }
+            }
+        }
+    }
+}
+
+/// Highest priority lane first, then first-in first-out
+fn sort_for_dispatch(items: &mut [QueuedPrompt]) {
+    items.sort_by_key(|item| (item.priority, item.enqueued_at));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    #[test]
+    fn test_sort_for_dispatch() {
+        let now = Utc::now();
+        let item = |priority, secs: i64| QueuedPrompt {
+            id: Uuid::new_v4(),
+            session_id: Uuid::nil(),
+            prompt: format!("{:?}-{}", priority, secs),
+            priority,
+            enqueued_at: now + Duration::seconds(secs),
+        };
+        let mut items = vec![
+            item(PromptPriority::Batch, 0),
+            item(PromptPriority::Scheduled, 1),
+            item(PromptPriority::Interactive, 3),
+            item(PromptPriority::Interactive, 2),
+        ];
+        sort_for_dispatch(&mut items);
+
+        let order: Vec<&str> = items.iter().map(|i| i.prompt.as_str()).collect();
+        assert_eq!(
+            order,
+            vec!["Interactive-2", "Interactive-3", "Scheduled-1", "Batch-0"]
+        );
+    }
+}
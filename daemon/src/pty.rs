@@ -1,47 +1,119 @@
 use anyhow::Result;
-use portable_pty::{native_pty_system, CommandBuilder, PtyPair, PtySize};
+use chrono::{DateTime, Utc};
+use portable_pty::{native_pty_system, CommandBuilder, MasterPty, PtySize};
 use std::collections::HashMap;
 use std::io::{Read, Write};
 use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicI64, AtomicU64, Ordering};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tokio::sync::{mpsc, Mutex, RwLock};
 use tracing::{error, info, warn};
 use uuid::Uuid;
 
+use shared::{SpawnMode, StopOutcome};
+
 use crate::claude_resolver::ClaudeResolver;
+use crate::config::SharedConfig;
+use crate::flow::{FlowControl, READ_CHUNK_BYTES};
+use crate::process_group::{ProcessGroup, KILL_GRACE};
 
 pub struct PtyInstance {
-    pub pair: PtyPair,
+    pub master: Box<dyn MasterPty + Send>,
     pub child: Box<dyn portable_pty::Child + Send + Sync>,
     pub writer: Box<dyn Write + Send>,
+    /// The child and the processes it starts, killed together
+    pub group: Option<ProcessGroup>,
+    /// Unix ms of the last output read or input written, for the idle timeout
+    pub last_io: Arc<AtomicI64>,
+    /// Bytes of output since input was last written, for watch expressions
+    pub unread: Arc<AtomicU64>,
+    /// Any output has been read, for wait_started
+    output_seen: Arc<AtomicBool>,
+    /// The exit has been sent on exit_tx, by the reader or the reaper
+    exit_reported: Arc<AtomicBool>,
+    /// The reaper found the process exited; reported on its next pass if the
+    /// reader hasn't by then
+    exited: bool,
+}
+
+/// The session has no running process to receive input
+#[derive(Debug, thiserror::Error)]
+#[error("Session {0} is not running")]
+pub struct PtyDead(pub Uuid);
+
+/// A session's process exited (sent once its PTY reaches EOF)
+#[derive(Debug)]
+pub struct PtyExit {
+    pub session_id: Uuid,
+    /// Pid of the process that exited, to tell it apart from a restarted one
+    pub pid: Option<u32>,
+    pub exit_code: Option<u32>,
+}
+
+/// Bracketed paste markers: the TUI takes what's between them as pasted text,
+/// newlines included, rather than as keys
+const PASTE_START: &[u8] = b"\x1b[200~";
+const PASTE_END: &[u8] = b"\x1b[201~";
+/// A paste is written in chunks this size, PASTE_CHUNK_DELAY apart, so a large
+/// prompt doesn't overrun the TUI's input handling
+const PASTE_CHUNK_BYTES: usize = 1024;
+const PASTE_CHUNK_DELAY: Duration = Duration::from_millis(5);
+/// Longest a paste waits for a session's paused output before going on
+const PASTE_MAX_WAIT: Duration = Duration::from_secs(5);
+
+/// How long the reader waits for the process to be reapable after EOF
+const EXIT_WAIT_ATTEMPTS: u32 = 20;
+const EXIT_WAIT_INTERVAL_MS: u64 = 100;
+
+/// How long a process must stay up after its first output to count as started,
+/// so e.g. a shell printing "claude: not found" is a failed start
+const START_SETTLE: Duration = Duration::from_millis(250);
+const START_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// How a spawned process came up, from `PtyManager::wait_started`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Started {
+    Output,
+    /// Still running but silent after daemon.start_timeout_secs
+    TimedOut,
+    /// Exited (with its exit code, if known) before it started
+    Exited(Option<u32>),
 }
 
+/// How often exited children are reaped, for those whose PTY never reaches EOF
+/// (a background process they started still holds it open)
+const REAP_INTERVAL: Duration = Duration::from_secs(5);
+
 pub struct PtyManager {
     instances: RwLock<HashMap<Uuid, Arc<Mutex<PtyInstance>>>>,
     claude_resolver: ClaudeResolver,
+    config: SharedConfig,
+    exit_tx: mpsc::UnboundedSender<PtyExit>,
+    flow: Arc<FlowControl>,
+}
+
+/// Quote `s` for a POSIX shell command line
+fn shell_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', "'\\''"))
 }
 
 impl PtyManager {
-    pub fn new() -> Self {
+    pub fn new(
+        config: SharedConfig,
+        exit_tx: mpsc::UnboundedSender<PtyExit>,
+        flow: Arc<FlowControl>,
+    ) -> Self {
         Self {
             instances: RwLock::new(HashMap::new()),
             claude_resolver: ClaudeResolver::new(),
+            config,
+            exit_tx,
+            flow,
         }
     }
 
-    pub async fn spawn(
-        &self,
-        session_id: Uuid,
-        working_dir: &Path,
-        rows: u16,
-        cols: u16,
-        output_tx: mpsc::Sender<(Uuid, Vec<u8>)>,
-        extra_env: Vec<(String, String)>,
-    ) -> Result<()> {
-        self.spawn_with_resume(session_id, working_dir, rows, cols, output_tx, None, extra_env)
-            .await
-    }
-
+    #[allow(clippy::too_many_arguments)]
     pub async fn spawn_with_resume(
         &self,
         session_id: Uuid,
@@ -50,8 +122,11 @@ impl PtyManager {
         cols: u16,
         output_tx: mpsc::Sender<(Uuid, Vec<u8>)>,
         resume_session_id: Option<&str>,
+        args: &[String],
         extra_env: Vec<(String, String)>,
-    ) -> Result<()> {
+        spawn_mode: Option<SpawnMode>,
+        claude_binary: Option<&str>,
+    ) -> Result<Option<u32>> {
         let pty_system = native_pty_system();
 
         let pair = pty_system.openpty(PtySize {
@@ -61,41 +136,88 @@ impl PtyManager {
             pixel_height: 0,
         })?;
 
-        // Try direct Claude execution first, fall back to shell wrapper if needed
-        let cmd = if let Some(claude_path) = self.claude_resolver.claude_path() {
-            self.build_direct_command(claude_path, working_dir, resume_session_id, &extra_env)?
-        } else {
-            warn!("Claude binary not found, falling back to shell wrapper");
-            self.build_shell_command(working_dir, resume_session_id, &extra_env)?
+        let (spawn_mode, named) = {
+            let config = self.config.read().await;
+            let named = ClaudeResolver::named(claude_binary, &config.claude)?;
+            (spawn_mode.unwrap_or(config.daemon.spawn_mode), named)
+        };
+
+        // Direct execution unless the shell wrapper was asked for, falling back
+        // to the wrapper if the binary can't be resolved
+        let claude_path = named.as_ref().or(self.claude_resolver.claude_path());
+        let cmd = match (spawn_mode, claude_path) {
+            (SpawnMode::Direct, Some(claude_path)) => self.build_direct_command(
+                claude_path,
+                working_dir,
+                resume_session_id,
+                args,
+                &extra_env,
+            )?,
+            (SpawnMode::Direct, None) => {
+                warn!("Claude binary not found, falling back to shell wrapper");
+                self.build_shell_command(None, working_dir, resume_session_id, args, &extra_env)?
+            }
+            (SpawnMode::Shell, _) => self.build_shell_command(
+                named.as_deref(),
+                working_dir,
+                resume_session_id,
+                args,
+                &extra_env,
+            )?,
         };
 
         info!("PTY spawn: executing spawn_command...");
         let child = pair.slave.spawn_command(cmd)?;
-        info!("PTY spawn: process spawned successfully");
+        // Only the child may hold the slave, or the reader never sees EOF when it exits
+        drop(pair.slave);
+        let master = pair.master;
+        let pid = child.process_id();
+        info!("PTY spawn: process spawned successfully (pid {:?})", pid);
+        let group = ProcessGroup::new(child.as_ref());
+        if group.is_none() {
+            warn!(
+                "No process group for session {}; only its direct child will be killed",
+                session_id
+            );
+        }
 
-        let writer = pair.master.take_writer()?;
-        let mut reader = pair.master.try_clone_reader()?;
+        let writer = master.take_writer()?;
+        let mut reader = master.try_clone_reader()?;
         info!(
             "PTY spawn: writer/reader obtained for session {}",
             session_id
         );
 
+        let last_io = Arc::new(AtomicI64::new(Utc::now().timestamp_millis()));
+        let unread = Arc::new(AtomicU64::new(0));
+        let exit_reported = Arc::new(AtomicBool::new(false));
+        let output_seen = Arc::new(AtomicBool::new(false));
         let instance = Arc::new(Mutex::new(PtyInstance {
-            pair,
+            master,
             child,
             writer,
+            group,
+            last_io: last_io.clone(),
+            unread: unread.clone(),
+            output_seen: output_seen.clone(),
+            exit_reported: exit_reported.clone(),
+            exited: false,
         }));
 
         {
             let mut instances = self.instances.write().await;
-            instances.insert(session_id, instance);
+            instances.insert(session_id, instance.clone());
         }
+        let exit_tx = self.exit_tx.clone();
+        let flow = self.flow.session(session_id);
+        // Weak, so a killed session's PTY is still closed when it leaves the map
+        let instance = Arc::downgrade(&instance);
 
         // Spawn reader task in a dedicated thread since PTY read is blocking I/O
         // Capture the tokio runtime handle before spawning
         let rt_handle = tokio::runtime::Handle::current();
         std::thread::spawn(move || {
-            let mut buf = [0u8; 4096];
+            let mut buf = [0u8; READ_CHUNK_BYTES];
             let mut total_bytes = 0usize;
             loop {
                 match reader.read(&mut buf) {
@@ -108,12 +230,25 @@ impl PtyManager {
                     }
                     Ok(n) => {
                         total_bytes += n;
+                        flow.read(n);
+                        output_seen.store(true, Ordering::Relaxed);
+                        last_io.store(Utc::now().timestamp_millis(), Ordering::Relaxed);
+                        unread.fetch_add(n as u64, Ordering::Relaxed);
                         let data = buf[..n].to_vec();
-                        // Use the captured runtime handle to send asynchronously
-                        if rt_handle
-                            .block_on(output_tx.send((session_id, data)))
-                            .is_err()
-                        {
+                        let sent = match output_tx.try_send((session_id, data)) {
+                            Ok(()) => Ok(()),
+                            Err(mpsc::error::TrySendError::Full(message)) => {
+                                // Stop reading until the queue drains; the child
+                                // blocks once the kernel PTY buffer is full
+                                flow.pause_started();
+                                let started = Instant::now();
+                                let sent = rt_handle.block_on(output_tx.send(message));
+                                flow.pause_ended(started.elapsed());
+                                sent.map_err(|_| ())
+                            }
+                            Err(mpsc::error::TrySendError::Closed(_)) => Err(()),
+                        };
+                        if sent.is_err() {
                             info!(
                                 "PTY reader for {} channel closed after {} bytes",
                                 session_id, total_bytes
@@ -134,9 +269,34 @@ impl PtyManager {
                 "PTY reader for {} exited (total {} bytes read)",
                 session_id, total_bytes
             );
+
+            // EOF usually arrives just before the process can be reaped
+            let mut exit_code = None;
+            for _ in 0..EXIT_WAIT_ATTEMPTS {
+                let Some(instance) = instance.upgrade() else {
+                    break;
+                };
+                match rt_handle.block_on(async { instance.lock().await.child.try_wait() }) {
+                    Ok(Some(status)) => {
+                        exit_code = Some(status.exit_code());
+                        break;
+                    }
+                    Ok(None) => {
+                        std::thread::sleep(std::time::Duration::from_millis(EXIT_WAIT_INTERVAL_MS))
+                    }
+                    Err(_) => break,
+                }
+            }
+            if !exit_reported.swap(true, Ordering::SeqCst) {
+                let _ = exit_tx.send(PtyExit {
+                    session_id,
+                    pid,
+                    exit_code,
+                });
+            }
         });
 
-        Ok(())
+        Ok(pid)
     }
 
     /// Build command for direct Claude binary execution (preferred method)
@@ -146,6 +306,7 @@ impl PtyManager {
         claude_path: &std::path::PathBuf,
         working_dir: &Path,
         resume_session_id: Option<&str>,
+        args: &[String],
         extra_env: &[(String, String)],
     ) -> Result<CommandBuilder> {
         info!(
@@ -158,6 +319,7 @@ impl PtyManager {
             cmd.arg("--resume");
             cmd.arg(claude_session_id);
         }
+        cmd.args(args);
         cmd.cwd(working_dir);
 
         // Set environment from resolver
@@ -178,19 +340,31 @@ impl PtyManager {
         Ok(cmd)
     }
 
-    /// Build command using shell wrapper (fallback method)
-    /// Used when Claude binary path cannot be resolved directly
+    /// Build command using shell wrapper
+    /// Used when Claude binary path cannot be resolved directly, or for sessions
+    /// that need the user's rc files; claude is found through the shell's PATH
+    /// unless a named binary was chosen
     fn build_shell_command(
         &self,
+        program: Option<&Path>,
         working_dir: &Path,
         resume_session_id: Option<&str>,
+        args: &[String],
         extra_env: &[(String, String)],
     ) -> Result<CommandBuilder> {
-        let claude_cmd = if let Some(claude_session_id) = resume_session_id {
-            format!("claude --resume {}", claude_session_id)
-        } else {
-            "claude".to_string()
-        };
+        // exec so the session's pid (and exit code) is claude's, not the shell's
+        let program = program.map_or("claude".to_string(), |path| {
+            shell_quote(&path.to_string_lossy())
+        });
+        let mut claude_cmd = format!("exec {}", program);
+        if let Some(claude_session_id) = resume_session_id {
+            claude_cmd.push_str(" --resume ");
+            claude_cmd.push_str(&shell_quote(claude_session_id));
+        }
+        for arg in args {
+            claude_cmd.push(' ');
+            claude_cmd.push_str(&shell_quote(arg));
+        }
 
         // Get home directory
         let home_dir = std::env::var("HOME")
@@ -246,13 +420,54 @@ impl PtyManager {
         Ok(cmd)
     }
 
+    /// Write input to a session's PTY
+    /// Fails with `PtyDead` if the session has no PTY or its process has exited.
     pub async fn write(&self, session_id: Uuid, data: &[u8]) -> Result<()> {
         let instances = self.instances.read().await;
-        if let Some(instance) = instances.get(&session_id) {
-            let mut inst = instance.lock().await;
-            inst.writer.write_all(data)?;
+        let instance = instances.get(&session_id).ok_or(PtyDead(session_id))?;
+        let mut inst = instance.lock().await;
+        if !matches!(inst.child.try_wait(), Ok(None)) {
+            return Err(PtyDead(session_id).into());
+        }
+        inst.writer.write_all(data)?;
+        inst.writer.flush()?;
+        inst.last_io
+            .store(Utc::now().timestamp_millis(), Ordering::Relaxed);
+        inst.unread.store(0, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// Write `data` as a bracketed paste, in chunks; a trailing Enter is sent
+    /// after the paste so it still submits. Other writes wait until it's done.
+    pub async fn paste(&self, session_id: Uuid, data: &[u8]) -> Result<()> {
+        // Pasting can take seconds; don't hold up spawns and kills meanwhile
+        let instance = self
+            .instances
+            .read()
+            .await
+            .get(&session_id)
+            .cloned()
+            .ok_or(PtyDead(session_id))?;
+        let mut inst = instance.lock().await;
+        if !matches!(inst.child.try_wait(), Ok(None)) {
+            return Err(PtyDead(session_id).into());
+        }
+        let flow = self.flow.session(session_id);
+        let started = Instant::now();
+        for (i, chunk) in paste_chunks(data).iter().enumerate() {
+            if i > 0 {
+                tokio::time::sleep(PASTE_CHUNK_DELAY).await;
+            }
+            // Output backed up means the TUI is behind; let it catch up first
+            while flow.is_paused() && started.elapsed() < PASTE_MAX_WAIT {
+                tokio::time::sleep(PASTE_CHUNK_DELAY).await;
+            }
+            inst.writer.write_all(chunk)?;
             inst.writer.flush()?;
         }
+        inst.last_io
+            .store(Utc::now().timestamp_millis(), Ordering::Relaxed);
+        inst.unread.store(0, Ordering::Relaxed);
         Ok(())
     }
 
@@ -260,7 +475,7 @@ impl PtyManager {
         let instances = self.instances.read().await;
         if let Some(instance) = instances.get(&session_id) {
             let inst = instance.lock().await;
-            inst.pair.master.resize(PtySize {
+            inst.master.resize(PtySize {
                 rows,
                 cols,
                 pixel_width: 0,
@@ -270,13 +485,144 @@ impl PtyManager {
         Ok(())
     }
 
+    /// Current terminal size as (rows, cols), if the session has a PTY
+    pub async fn size(&self, session_id: Uuid) -> Option<(u16, u16)> {
+        let instances = self.instances.read().await;
+        let inst = instances.get(&session_id)?.lock().await;
+        let size = inst.master.get_size().ok()?;
+        Some((size.rows, size.cols))
+    }
+
+    /// When the session's process last wrote output or was sent input, if it has a PTY
+    pub async fn last_io(&self, session_id: Uuid) -> Option<DateTime<Utc>> {
+        let instances = self.instances.read().await;
+        let inst = instances.get(&session_id)?.lock().await;
+        DateTime::from_timestamp_millis(inst.last_io.load(Ordering::Relaxed))
+    }
+
+    /// Bytes the session has output since it was last sent input (0 without a PTY)
+    pub async fn unread(&self, session_id: Uuid) -> u64 {
+        let instances = self.instances.read().await;
+        match instances.get(&session_id) {
+            Some(instance) => instance.lock().await.unread.load(Ordering::Relaxed),
+            None => 0,
+        }
+    }
+
+    /// Kill the session's process and everything it started, waiting up to
+    /// KILL_GRACE for them to exit on their own first
     pub async fn kill(&self, session_id: Uuid) -> Result<()> {
-        let mut instances = self.instances.write().await;
-        if let Some(instance) = instances.remove(&session_id) {
-            let mut inst = instance.lock().await;
-            inst.child.kill()?;
+        self.stop(session_id, KILL_GRACE, false).await.map(|_| ())
+    }
+
+    /// Ask the session's process and everything it started to exit, and kill
+    /// them after `grace` (or at once with `force`)
+    pub async fn stop(
+        &self,
+        session_id: Uuid,
+        grace: Duration,
+        force: bool,
+    ) -> Result<StopOutcome> {
+        let Some(instance) = self.instances.write().await.remove(&session_id) else {
+            return Ok(StopOutcome::NotRunning);
+        };
+        let mut inst = instance.lock().await;
+        let running = matches!(inst.child.try_wait(), Ok(None));
+        let outcome = match inst.group.take() {
+            // Even if claude has exited, for what it left running
+            Some(group) => {
+                group
+                    .stop(grace, force, || {
+                        let _ = inst.child.try_wait();
+                    })
+                    .await
+            }
+            // Killing a process that already exited fails with ESRCH
+            None if running => {
+                inst.child.kill()?;
+                StopOutcome::Forced
+            }
+            None => StopOutcome::NotRunning,
+        };
+        Ok(if running {
+            outcome
+        } else {
+            StopOutcome::NotRunning
+        })
+    }
+
+    /// Wait for a just-spawned process to write its first output and keep
+    /// running for START_SETTLE, up to daemon.start_timeout_secs
+    pub async fn wait_started(&self, session_id: Uuid) -> Started {
+        let timeout = Duration::from_secs(self.config.read().await.daemon.start_timeout_secs);
+        let started = Instant::now();
+        let mut output_at: Option<Instant> = None;
+        loop {
+            {
+                let instances = self.instances.read().await;
+                let Some(instance) = instances.get(&session_id) else {
+                    return Started::Exited(None);
+                };
+                let mut inst = instance.lock().await;
+                match inst.child.try_wait() {
+                    Ok(Some(status)) => return Started::Exited(Some(status.exit_code())),
+                    Ok(None) => {}
+                    Err(_) => return Started::Exited(None),
+                }
+                if inst.output_seen.load(Ordering::Relaxed) {
+                    let at = *output_at.get_or_insert_with(Instant::now);
+                    if at.elapsed() >= START_SETTLE {
+                        return Started::Output;
+                    }
+                }
+            }
+            if output_at.is_none() && started.elapsed() >= timeout {
+                return Started::TimedOut;
+            }
+            tokio::time::sleep(START_POLL_INTERVAL).await;
+        }
+    }
+
+    /// Reap exited children every REAP_INTERVAL so they don't linger as zombies
+    pub async fn run_reaper(self: Arc<Self>) {
+        let mut interval = tokio::time::interval(REAP_INTERVAL);
+        loop {
+            interval.tick().await;
+            self.reap().await;
         }
-        Ok(())
+    }
+
+    /// Collect the exit status of exited children and drop their instances,
+    /// reporting exits the reader hasn't (a pass later, so it can send the
+    /// last output first)
+    async fn reap(&self) {
+        let mut instances = self.instances.write().await;
+        instances.retain(|&session_id, instance| {
+            // In use (being written to or killed); next time
+            let Ok(mut inst) = instance.try_lock() else {
+                return true;
+            };
+            let status = match inst.child.try_wait() {
+                Ok(Some(status)) => status,
+                Ok(None) | Err(_) => return true,
+            };
+            if inst.exit_reported.load(Ordering::SeqCst) {
+                return false;
+            }
+            if !inst.exited {
+                inst.exited = true;
+                return true;
+            }
+            if !inst.exit_reported.swap(true, Ordering::SeqCst) {
+                info!("Reaped session {}'s exited process", session_id);
+                let _ = self.exit_tx.send(PtyExit {
+                    session_id,
+                    pid: inst.child.process_id(),
+                    exit_code: Some(status.exit_code()),
+                });
+            }
+            false
+        });
     }
 
     pub async fn is_alive(&self, session_id: Uuid) -> bool {
@@ -289,3 +635,214 @@ impl PtyManager {
         }
     }
 }
+
+/// The writes of a bracketed paste of `data`; paste markers already in it are
+/// dropped, so it can't end the paste early
+fn paste_chunks(data: &[u8]) -> Vec<Vec<u8>> {
+    let (text, enter) = match data.strip_suffix(b"\r") {
+        Some(text) => (text, true),
+        None => (data, false),
+    };
+    let mut pasted = PASTE_START.to_vec();
+    let mut rest = text;
+    while !rest.is_empty() {
+        if rest.starts_with(PASTE_START) || rest.starts_with(PASTE_END) {
+            rest = &rest[PASTE_START.len()..];
+        } else {
+            pasted.push(rest[0]);
+            rest = &rest[1..];
+        }
+    }
+    pasted.extend_from_slice(PASTE_END);
+
+    let mut chunks: Vec<Vec<u8>> = pasted
+        .chunks(PASTE_CHUNK_BYTES)
+        .map(<[u8]>::to_vec)
+        .collect();
+    if enter {
+        chunks.push(b"\r".to_vec());
+    }
+    chunks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_paste_chunks() {
+        assert_eq!(
+            paste_chunks(b"line 1\nline 2\r"),
+            [b"\x1b[200~line 1\nline 2\x1b[201~".to_vec(), b"\r".to_vec()]
+        );
+        assert_eq!(
+            paste_chunks(b"a\x1b[201~b"),
+            [b"\x1b[200~ab\x1b[201~".to_vec()]
+        );
+
+        let big = vec![b'x'; PASTE_CHUNK_BYTES * 2];
+        let chunks = paste_chunks(&big);
+        assert_eq!(chunks.len(), 3);
+        assert_eq!(chunks[0].len(), PASTE_CHUNK_BYTES);
+        assert!(chunks[2].ends_with(PASTE_END));
+    }
+
+    #[test]
+    fn test_shell_quote() {
+        assert_eq!(shell_quote("abc-123"), "'abc-123'");
+        assert_eq!(shell_quote("it's"), "'it'\\''s'");
+    }
+
+    /// A manager whose claude binaries are the given shell scripts, by name
+    #[cfg(unix)]
+    fn script_manager(
+        dir: &Path,
+        scripts: &[(&str, &str)],
+    ) -> (PtyManager, mpsc::UnboundedReceiver<PtyExit>) {
+        use crate::config::Config;
+        use std::os::unix::fs::PermissionsExt;
+
+        std::fs::create_dir_all(dir).unwrap();
+        let mut config = Config::default();
+        for (name, script) in scripts {
+            let path = dir.join(name);
+            std::fs::write(&path, format!("#!/bin/sh\n{}\n", script)).unwrap();
+            std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+            config.claude.binaries.insert(name.to_string(), path);
+        }
+        let (exit_tx, exit_rx) = mpsc::unbounded_channel();
+        let manager = PtyManager::new(
+            Arc::new(RwLock::new(config)),
+            exit_tx,
+            Arc::new(FlowControl::new(1024)),
+        );
+        (manager, exit_rx)
+    }
+
+    #[cfg(unix)]
+    async fn spawn_script(
+        manager: &PtyManager,
+        dir: &Path,
+        session_id: Uuid,
+        name: &str,
+        output_tx: mpsc::Sender<(Uuid, Vec<u8>)>,
+    ) {
+        manager
+            .spawn_with_resume(
+                session_id,
+                dir,
+                24,
+                80,
+                output_tx,
+                None,
+                &[],
+                Vec::new(),
+                None,
+                Some(name),
+            )
+            .await
+            .unwrap();
+    }
+
+    #[cfg(unix)]
+    async fn wait_exited(manager: &PtyManager, session_id: Uuid) {
+        for _ in 0..100 {
+            if !manager.is_alive(session_id).await {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        panic!("session {} never exited", session_id);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_write_needs_a_running_process() {
+        let dir = std::env::temp_dir().join(format!("pty-write-{}", Uuid::new_v4()));
+        let (manager, _exit_rx) = script_manager(&dir, &[("cat", "exec cat"), ("exits", "exit 0")]);
+        let (output_tx, _output_rx) = mpsc::channel(64);
+
+        // No PTY at all
+        let err = manager.write(Uuid::new_v4(), b"hi").await.unwrap_err();
+        assert!(err.is::<PtyDead>());
+
+        let running = Uuid::new_v4();
+        spawn_script(&manager, &dir, running, "cat", output_tx.clone()).await;
+        manager.write(running, b"hi\r").await.unwrap();
+
+        // A PTY whose process has exited
+        let exited = Uuid::new_v4();
+        spawn_script(&manager, &dir, exited, "exits", output_tx).await;
+        wait_exited(&manager, exited).await;
+        let err = manager.write(exited, b"hi").await.unwrap_err();
+        assert!(err.is::<PtyDead>());
+        assert!(manager
+            .paste(exited, b"hi")
+            .await
+            .unwrap_err()
+            .is::<PtyDead>());
+
+        manager.kill(running).await.unwrap();
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_paste_doesnt_hold_up_other_sessions() {
+        let dir = std::env::temp_dir().join(format!("pty-paste-{}", Uuid::new_v4()));
+        let (manager, _exit_rx) =
+            script_manager(&dir, &[("sink", "stty raw -echo; exec cat >/dev/null")]);
+        let manager = Arc::new(manager);
+        let (output_tx, mut output_rx) = mpsc::channel(64);
+        tokio::spawn(async move { while output_rx.recv().await.is_some() {} });
+
+        let pasting = Uuid::new_v4();
+        spawn_script(&manager, &dir, pasting, "sink", output_tx.clone()).await;
+        // 100 chunks, at least half a second of paste
+        let paste = tokio::spawn({
+            let manager = manager.clone();
+            async move { manager.paste(pasting, &[b'x'; 100 * 1024]).await }
+        });
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        let other = Uuid::new_v4();
+        spawn_script(&manager, &dir, other, "sink", output_tx).await;
+        manager.kill(other).await.unwrap();
+        assert!(!paste.is_finished());
+
+        paste.await.unwrap().unwrap();
+        manager.kill(pasting).await.unwrap();
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_reap_reports_missed_exits() {
+        let dir = std::env::temp_dir().join(format!("pty-reap-{}", Uuid::new_v4()));
+        let (manager, mut exit_rx) = script_manager(&dir, &[("exits", "echo bye; exit 4")]);
+        // A full output queue holds the reader up, as a background process
+        // keeping the PTY open would
+        let (output_tx, output_rx) = mpsc::channel(1);
+        output_tx.try_send((Uuid::nil(), Vec::new())).unwrap();
+
+        let id = Uuid::new_v4();
+        spawn_script(&manager, &dir, id, "exits", output_tx).await;
+        wait_exited(&manager, id).await;
+
+        // The first pass gives the reader a chance, the second reports the exit
+        manager.reap().await;
+        assert!(exit_rx.try_recv().is_err());
+        assert!(manager.size(id).await.is_some());
+        manager.reap().await;
+        let exit = exit_rx.try_recv().unwrap();
+        assert_eq!(exit.session_id, id);
+        assert_eq!(exit.exit_code, Some(4));
+        assert!(manager.size(id).await.is_none());
+
+        // Once the reader gets going, it doesn't report it again
+        drop(output_rx);
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert!(exit_rx.try_recv().is_err());
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,264 @@
+// Session recordings - PTY output with timing written as asciinema v2 cast files
+// under recordings/<session_id>/<recording_id>.cast, playable with `asciinema play`
+// Started and stopped with session.recording.start/stop; a recording keeps going
+// across restarts of its session until it is stopped or the session is deleted
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde_json::json;
+use shared::RecordingInfo;
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Instant;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+struct Recording {
+    info: RecordingInfo,
+    file: File,
+    started: Instant,
+    /// Trailing bytes of a UTF-8 character split across PTY reads
+    pending: Vec<u8>,
+}
+
+/// Recordings in progress, by session
+pub struct Recorder {
+    active: Mutex<HashMap<Uuid, Recording>>,
+}
+
+fn recordings_dir() -> Result<PathBuf> {
+    let dir = shared::get_data_dir()?.join("recordings");
+    fs::create_dir_all(&dir)?;
+    Ok(dir)
+}
+
+/// Take the longest valid UTF-8 prefix of `buf`, leaving an incomplete
+/// trailing character in place for the next read. Invalid bytes are replaced.
+fn take_utf8(buf: &mut Vec<u8>) -> String {
+    let split = match std::str::from_utf8(buf) {
+        Err(e) if e.error_len().is_none() => e.valid_up_to(),
+        _ => buf.len(),
+    };
+    let rest = buf.split_off(split);
+    let text = String::from_utf8_lossy(buf).into_owned();
+    *buf = rest;
+    text
+}
+
+/// Header line of a cast file
+fn header(rows: u16, cols: u16, started_at: DateTime<Utc>, title: &str) -> String {
+    json!({
+        "version": 2,
+        "width": cols,
+        "height": rows,
+        "timestamp": started_at.timestamp(),
+        "title": title,
+        "env": {"TERM": "xterm-256color"},
+    })
+    .to_string()
+}
+
+/// Start time from the header of the cast file at `path`
+fn read_header(path: &Path) -> Option<DateTime<Utc>> {
+    let mut line = String::new();
+    BufReader::new(File::open(path).ok()?)
+        .read_line(&mut line)
+        .ok()?;
+    let header: serde_json::Value = serde_json::from_str(&line).ok()?;
+    DateTime::from_timestamp(header.get("timestamp")?.as_i64()?, 0)
+}
+
+impl Recording {
+    fn event(&mut self, code: &str, data: &str) -> Result<()> {
+        let line = json!([self.started.elapsed().as_secs_f64(), code, data]).to_string() + "\n";
+        self.file.write_all(line.as_bytes())?;
+        Ok(())
+    }
+}
+
+impl Recorder {
+    pub fn new() -> Self {
+        Self {
+            active: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Start recording a session whose terminal is `rows` x `cols`
+    pub fn start(
+        &self,
+        session_id: Uuid,
+        rows: u16,
+        cols: u16,
+        title: &str,
+    ) -> Result<RecordingInfo> {
+        let mut active = self.active.lock().unwrap();
+        if active.contains_key(&session_id) {
+            anyhow::bail!("Session is already being recorded");
+        }
+
+        let id = Uuid::new_v4();
+        let dir = recordings_dir()?.join(session_id.to_string());
+        fs::create_dir_all(&dir)?;
+        let path = dir.join(format!("{}.cast", id));
+        let started_at = Utc::now();
+
+        let mut file = OpenOptions::new()
+            .create_new(true)
+            .write(true)
+            .open(&path)?;
+        file.write_all((header(rows, cols, started_at, title) + "\n").as_bytes())?;
+
+        let info = RecordingInfo {
+            id,
+            session_id,
+            path: path.to_string_lossy().to_string(),
+            started_at,
+            active: true,
+        };
+        info!("Recording session {} to {}", session_id, info.path);
+        active.insert(
+            session_id,
+            Recording {
+                info: info.clone(),
+                file,
+                started: Instant::now(),
+                pending: Vec::new(),
+            },
+        );
+        Ok(info)
+    }
+
+    /// Stop recording a session
+    pub fn stop(&self, session_id: Uuid) -> Result<RecordingInfo> {
+        let mut recording = self
+            .active
+            .lock()
+            .unwrap()
+            .remove(&session_id)
+            .context("Session is not being recorded")?;
+        if !recording.pending.is_empty() {
+            let text = String::from_utf8_lossy(&recording.pending).into_owned();
+            recording.event("o", &text)?;
+        }
+        recording.info.active = false;
+        info!("Stopped recording session {}", session_id);
+        Ok(recording.info)
+    }
+
+    /// Append PTY output to the session's recording, if there is one
+    /// Recording is best-effort: a failed write stops the recording
+    pub fn write(&self, session_id: Uuid, data: &[u8]) {
+        let mut active = self.active.lock().unwrap();
+        let Some(recording) = active.get_mut(&session_id) else {
+            return;
+        };
+        recording.pending.extend_from_slice(data);
+        let text = take_utf8(&mut recording.pending);
+        if text.is_empty() {
+            return;
+        }
+        if let Err(e) = recording.event("o", &text) {
+            warn!(
+                "Failed to write recording for session {}: {}",
+                session_id, e
+            );
+            active.remove(&session_id);
+        }
+    }
+
+    /// Note a terminal resize in the session's recording, if there is one
+    pub fn resize(&self, session_id: Uuid, rows: u16, cols: u16) {
+        let mut active = self.active.lock().unwrap();
+        let Some(recording) = active.get_mut(&session_id) else {
+            return;
+        };
+        if let Err(e) = recording.event("r", &format!("{}x{}", cols, rows)) {
+            warn!(
+                "Failed to write recording for session {}: {}",
+                session_id, e
+            );
+            active.remove(&session_id);
+        }
+    }
+
+    /// Recordings on disk, newest first
+    pub fn list(&self, session_id: Option<Uuid>) -> Result<Vec<RecordingInfo>> {
+        let active = self.active.lock().unwrap();
+        let mut recordings = Vec::new();
+
+        for session_dir in fs::read_dir(recordings_dir()?)?.flatten() {
+            let Ok(owner) = Uuid::parse_str(&session_dir.file_name().to_string_lossy()) else {
+                continue;
+            };
+            if session_id.is_some_and(|id| id != owner) {
+                continue;
+            }
+            for entry in fs::read_dir(session_dir.path())?.flatten() {
+                let path = entry.path();
+                let Some(id) = path
+                    .file_stem()
+                    .filter(|_| path.extension().is_some_and(|ext| ext == "cast"))
+                    .and_then(|stem| Uuid::parse_str(&stem.to_string_lossy()).ok())
+                else {
+                    continue;
+                };
+                let Some(started_at) = read_header(&path) else {
+                    continue;
+                };
+                recordings.push(RecordingInfo {
+                    id,
+                    session_id: owner,
+                    path: path.to_string_lossy().to_string(),
+                    started_at,
+                    active: active.get(&owner).is_some_and(|r| r.info.id == id),
+                });
+            }
+        }
+
+        recordings.sort_by_key(|r| std::cmp::Reverse(r.started_at));
+        Ok(recordings)
+    }
+
+    /// Copy a recording to `dest`, returning it
+    pub fn export(&self, recording_id: Uuid, dest: &Path) -> Result<RecordingInfo> {
+        let recording = self
+            .list(None)?
+            .into_iter()
+            .find(|r| r.id == recording_id)
+            .context("Recording not found")?;
+        fs::copy(&recording.path, dest)
+            .with_context(|| format!("Failed to write {}", dest.display()))?;
+        Ok(recording)
+    }
+}
+
+impl Default for Recorder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_take_utf8_keeps_split_character() {
+        // "é" is two bytes; the second arrives in the next read
+        let mut buf = b"caf\xc3".to_vec();
+        assert_eq!(take_utf8(&mut buf), "caf");
+        assert_eq!(buf, b"\xc3");
+
+        buf.extend_from_slice(b"\xa9!");
+        assert_eq!(take_utf8(&mut buf), "é!");
+        assert!(buf.is_empty());
+
+        // Invalid bytes are replaced rather than held back
+        let mut buf = b"a\xffb".to_vec();
+        assert_eq!(take_utf8(&mut buf), "a\u{fffd}b");
+        assert!(buf.is_empty());
+    }
+}
@@ -0,0 +1,151 @@
+// Session replay - plays a session's output log back over the event channel as
+// replay:output events, with the original timing or sped up, so a terminal can
+// show what happened while nobody was watching. Ends with replay:finished.
+
+use anyhow::Result;
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use shared::{Event, ReplayFinishedData, ReplayOutputData, SessionReplayParams};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{broadcast, Mutex};
+use tokio::task::AbortHandle;
+use tracing::{debug, warn};
+use uuid::Uuid;
+
+use crate::output_log;
+
+/// Pauses shorter than this are saved up rather than slept, so bursts of small
+/// writes go out as one event
+const MIN_PAUSE: Duration = Duration::from_millis(15);
+/// Largest replay:output payload before it is sent regardless of timing
+const MAX_EVENT_BYTES: usize = 32 * 1024;
+
+/// Replays in progress
+pub struct Replays {
+    event_tx: broadcast::Sender<Event>,
+    running: Mutex<HashMap<Uuid, (Uuid, AbortHandle)>>,
+}
+
+/// Pause before a chunk logged `delta_ms` after the previous one
+fn scaled_gap(delta_ms: i64, speed: f64, max_gap_ms: Option<u64>) -> Duration {
+    let ms = (delta_ms.max(0) as f64 / speed) as u64;
+    Duration::from_millis(max_gap_ms.map_or(ms, |max| ms.min(max)))
+}
+
+impl Replays {
+    pub fn new(event_tx: broadcast::Sender<Event>) -> Self {
+        Self {
+            event_tx,
+            running: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Start playing back a session's output log, returning the replay id
+    pub async fn start(self: &Arc<Self>, params: SessionReplayParams) -> Uuid {
+        let replay_id = Uuid::new_v4();
+        let session_id = params.session_id;
+        // Held until the task is registered so it can't finish first
+        let mut running = self.running.lock().await;
+
+        let replays = self.clone();
+        let handle = tokio::spawn(async move {
+            if let Err(e) = replays.play(replay_id, &params).await {
+                warn!(
+                    "Replay {} of session {} failed: {}",
+                    replay_id, session_id, e
+                );
+            }
+            replays.finish(replay_id, false).await;
+        });
+        running.insert(replay_id, (session_id, handle.abort_handle()));
+        debug!("Started replay {} of session {}", replay_id, session_id);
+        replay_id
+    }
+
+    /// Stop a replay, returning whether it was still running
+    pub async fn cancel(&self, replay_id: Uuid) -> bool {
+        self.finish(replay_id, true).await
+    }
+
+    async fn finish(&self, replay_id: Uuid, cancelled: bool) -> bool {
+        let Some((session_id, handle)) = self.running.lock().await.remove(&replay_id) else {
+            return false;
+        };
+        if cancelled {
+            handle.abort();
+        }
+        let data = ReplayFinishedData {
+            replay_id,
+            session_id,
+            cancelled,
+        };
+        let _ = self.event_tx.send(Event::ReplayFinished(data));
+        true
+    }
+
+    async fn play(&self, replay_id: Uuid, params: &SessionReplayParams) -> Result<()> {
+        let speed = params.speed.unwrap_or(1.0);
+        let since = params.since.map(|since| since.timestamp_millis());
+        let mut last_at: Option<i64> = None;
+        let mut owed = Duration::ZERO;
+        let mut pending = Vec::new();
+
+        for path in output_log::segments(params.session_id)? {
+            let chunks =
+                tokio::task::spawn_blocking(move || output_log::read_segment(&path)).await??;
+            for chunk in chunks {
+                // Untimed output can't be placed relative to `since`
+                if since.is_some_and(|since| chunk.at.is_none_or(|at| at < since)) {
+                    continue;
+                }
+                if let (Some(prev), Some(at)) = (last_at, chunk.at) {
+                    owed += scaled_gap(at - prev, speed, params.max_gap_ms);
+                }
+                last_at = chunk.at.or(last_at);
+
+                if owed >= MIN_PAUSE {
+                    self.send(replay_id, params.session_id, &mut pending);
+                    tokio::time::sleep(owed).await;
+                    owed = Duration::ZERO;
+                }
+                pending.extend_from_slice(&chunk.data);
+                if pending.len() >= MAX_EVENT_BYTES {
+                    self.send(replay_id, params.session_id, &mut pending);
+                }
+            }
+        }
+        self.send(replay_id, params.session_id, &mut pending);
+        Ok(())
+    }
+
+    fn send(&self, replay_id: Uuid, session_id: Uuid, pending: &mut Vec<u8>) {
+        if pending.is_empty() {
+            return;
+        }
+        let data = ReplayOutputData {
+            replay_id,
+            session_id,
+            output: BASE64.encode(&pending),
+        };
+        pending.clear();
+        let _ = self.event_tx.send(Event::ReplayOutput(data));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scaled_gap() {
+        assert_eq!(scaled_gap(1000, 1.0, None), Duration::from_millis(1000));
+        assert_eq!(scaled_gap(1000, 4.0, None), Duration::from_millis(250));
+        assert_eq!(
+            scaled_gap(60_000, 2.0, Some(2000)),
+            Duration::from_millis(2000)
+        );
+        // Clock steps backwards don't produce a pause
+        assert_eq!(scaled_gap(-500, 1.0, None), Duration::ZERO);
+    }
+}
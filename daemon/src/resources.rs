@@ -0,0 +1,212 @@
+// Resource usage - samples the CPU and resident memory of each session's claude
+// process together with everything it started (tool runs, shells, dev servers),
+// for session.stats and, every daemon.resource_interval_secs, the
+// session:resource_usage event, so a runaway session can be found and stopped.
+
+use shared::{Event, ResourceUsageData, SessionResourceUsage};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use sysinfo::{ProcessRefreshKind, ProcessesToUpdate, System, MINIMUM_CPU_UPDATE_INTERVAL};
+use tokio::sync::{broadcast, Mutex};
+use uuid::Uuid;
+
+use crate::config::SharedConfig;
+use crate::state::SharedState;
+
+/// How often to look at the config again while the event is turned off
+const DISABLED_CHECK_SECS: u64 = 30;
+
+/// One process from a sample
+struct ProcessSample {
+    parent: Option<u32>,
+    cpu_percent: f32,
+    rss_bytes: u64,
+}
+
+struct Sampler {
+    system: System,
+    /// CPU usage is measured between two refreshes, so the first needs a second one
+    refreshed: Option<Instant>,
+}
+
+pub struct ResourceMonitor {
+    state: SharedState,
+    config: SharedConfig,
+    event_tx: broadcast::Sender<Event>,
+    sampler: Mutex<Sampler>,
+}
+
+impl ResourceMonitor {
+    pub fn new(
+        state: SharedState,
+        config: SharedConfig,
+        event_tx: broadcast::Sender<Event>,
+    ) -> Self {
+        Self {
+            state,
+            config,
+            event_tx,
+            sampler: Mutex::new(Sampler {
+                system: System::new(),
+                refreshed: None,
+            }),
+        }
+    }
+
+    /// Usage of the sessions with a running process (or just `session_id`),
+    /// heaviest memory user first
+    pub async fn sample(&self, session_id: Option<Uuid>) -> Vec<SessionResourceUsage> {
+        let pids: Vec<(Uuid, u32)> = self
+            .state
+            .read()
+            .await
+            .sessions
+            .values()
+            .filter(|session| session_id.is_none_or(|id| id == session.id))
+            .filter_map(|session| Some((session.id, session.pid?)))
+            .collect();
+        if pids.is_empty() {
+            return Vec::new();
+        }
+
+        let processes = {
+            let mut sampler = self.sampler.lock().await;
+            match sampler.refreshed {
+                None => {
+                    refresh(&mut sampler.system);
+                    tokio::time::sleep(MINIMUM_CPU_UPDATE_INTERVAL).await;
+                    refresh(&mut sampler.system);
+                    sampler.refreshed = Some(Instant::now());
+                }
+                // Too soon after the last refresh for a CPU reading; reuse it
+                Some(at) if at.elapsed() < MINIMUM_CPU_UPDATE_INTERVAL => {}
+                Some(_) => {
+                    refresh(&mut sampler.system);
+                    sampler.refreshed = Some(Instant::now());
+                }
+            }
+            sampler
+                .system
+                .processes()
+                .iter()
+                // Linux lists threads too; they share their process's memory
+                .filter(|(_, process)| process.thread_kind().is_none())
+                .map(|(pid, process)| {
+                    let sample = ProcessSample {
+                        parent: process.parent().map(|parent| parent.as_u32()),
+                        cpu_percent: process.cpu_usage(),
+                        rss_bytes: process.memory(),
+                    };
+                    (pid.as_u32(), sample)
+                })
+                .collect::<HashMap<u32, ProcessSample>>()
+        };
+
+        let mut usage: Vec<SessionResourceUsage> = pids
+            .into_iter()
+            .filter_map(|(session_id, pid)| tree_usage(&processes, session_id, pid))
+            .collect();
+        usage.sort_by_key(|u| std::cmp::Reverse(u.rss_bytes));
+        usage
+    }
+
+    /// Emit session:resource_usage every daemon.resource_interval_secs
+    pub async fn run(self: Arc<Self>) {
+        loop {
+            let interval_secs = self.config.read().await.daemon.resource_interval_secs;
+            if interval_secs == 0 {
+                tokio::time::sleep(Duration::from_secs(DISABLED_CHECK_SECS)).await;
+                continue;
+            }
+            tokio::time::sleep(Duration::from_secs(interval_secs)).await;
+
+            let sessions = self.sample(None).await;
+            if !sessions.is_empty() {
+                let _ = self
+                    .event_tx
+                    .send(Event::ResourceUsage(ResourceUsageData { sessions }));
+            }
+        }
+    }
+}
+
+fn refresh(system: &mut System) {
+    system.refresh_processes_specifics(
+        ProcessesToUpdate::All,
+        true,
+        ProcessRefreshKind::nothing().with_cpu().with_memory(),
+    );
+}
+
+/// `pid` and its descendants added up; None if `pid` is gone
+fn tree_usage(
+    processes: &HashMap<u32, ProcessSample>,
+    session_id: Uuid,
+    pid: u32,
+) -> Option<SessionResourceUsage> {
+    processes.get(&pid)?;
+    let mut children: HashMap<u32, Vec<u32>> = HashMap::new();
+    for (&child, process) in processes {
+        if let Some(parent) = process.parent {
+            children.entry(parent).or_default().push(child);
+        }
+    }
+
+    let mut usage = SessionResourceUsage {
+        session_id,
+        pid,
+        cpu_percent: 0.0,
+        rss_bytes: 0,
+        processes: 0,
+    };
+    let mut seen = HashSet::new();
+    let mut pending = vec![pid];
+    while let Some(pid) = pending.pop() {
+        if !seen.insert(pid) {
+            continue;
+        }
+        let Some(process) = processes.get(&pid) else {
+            continue;
+        };
+        usage.cpu_percent += process.cpu_percent;
+        usage.rss_bytes += process.rss_bytes;
+        usage.processes += 1;
+        pending.extend(children.get(&pid).into_iter().flatten());
+    }
+    Some(usage)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tree_usage() {
+        let process = |parent, cpu_percent, rss_bytes| ProcessSample {
+            parent,
+            cpu_percent,
+            rss_bytes,
+        };
+        let processes = HashMap::from([
+            (1, process(None, 0.5, 10)),
+            // claude, a tool it runs and that tool's child
+            (100, process(Some(1), 12.0, 400)),
+            (101, process(Some(100), 80.0, 8000)),
+            (102, process(Some(101), 1.5, 50)),
+            // Not under claude
+            (200, process(Some(1), 99.0, 1 << 30)),
+        ]);
+        let id = Uuid::new_v4();
+
+        let usage = tree_usage(&processes, id, 100).unwrap();
+        assert_eq!(usage.session_id, id);
+        assert_eq!(usage.processes, 3);
+        assert_eq!(usage.rss_bytes, 8450);
+        assert!((usage.cpu_percent - 93.5).abs() < 0.01);
+
+        let usage = tree_usage(&processes, id, 102).unwrap();
+        assert_eq!(usage.processes, 1);
+        assert!(tree_usage(&processes, id, 300).is_none());
+    }
+}
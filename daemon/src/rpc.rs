@@ -0,0 +1,265 @@
+// RPC routing - every IPC method is an `RpcHandler` with typed params and output,
+// registered by name on a `Router`. The router decodes params (left out or null
+// counts as `{}`), runs the handler and turns its output or `RpcError` into the
+// Response, so handlers never build Responses or parse JSON themselves.
+
+use serde::de::{DeserializeOwned, Deserializer, IgnoredAny};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use shared::{ErrorCode, ErrorInfo, Request, Response};
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::future::Future;
+use std::pin::Pin;
+
+use crate::pty::PtyDead;
+use crate::session_manager::SessionCreateError;
+use crate::state::{GroupNotFound, SessionNotFound};
+
+pub type RpcResult<T> = Result<T, RpcError>;
+
+type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RpcError {
+    pub code: ErrorCode,
+    pub message: String,
+}
+
+impl RpcError {
+    pub fn new(code: ErrorCode, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+
+    pub fn invalid_params(reason: impl Display) -> Self {
+        Self::new(
+            ErrorCode::InvalidParams,
+            format!("Invalid params: {}", reason),
+        )
+    }
+
+    pub fn failed(message: impl Display) -> Self {
+        Self::new(ErrorCode::Failed, message.to_string())
+    }
+
+    /// Map an error with `context` in front of its message, e.g.
+    /// `.map_err(RpcError::context("Failed to stop session"))`
+    /// A dead PTY keeps its own message so clients can offer a restart.
+    pub fn context(context: &'static str) -> impl FnOnce(anyhow::Error) -> Self {
+        move |e| match error_code(&e) {
+            ErrorCode::PtyDead => Self::new(ErrorCode::PtyDead, e.to_string()),
+            code => Self::new(code, format!("{}: {}", context, e)),
+        }
+    }
+}
+
+impl From<anyhow::Error> for RpcError {
+    fn from(e: anyhow::Error) -> Self {
+        Self::new(error_code(&e), e.to_string())
+    }
+}
+
+/// The code for a daemon error, from the typed errors it may carry
+fn error_code(e: &anyhow::Error) -> ErrorCode {
+    if e.is::<PtyDead>() {
+        ErrorCode::PtyDead
+    } else if e.is::<SessionNotFound>() {
+        ErrorCode::SessionNotFound
+    } else if e.is::<GroupNotFound>() {
+        ErrorCode::GroupNotFound
+    } else if let Some(e) = e.downcast_ref::<SessionCreateError>() {
+        e.code()
+    } else {
+        ErrorCode::Failed
+    }
+}
+
+/// Params of a method that takes none; anything sent is ignored
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoParams;
+
+impl<'de> Deserialize<'de> for NoParams {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        IgnoredAny::deserialize(deserializer)?;
+        Ok(NoParams)
+    }
+}
+
+/// One IPC method, run against the daemon context `C`
+pub trait RpcHandler<C>: Send + Sync + 'static {
+    type Params: DeserializeOwned + Send;
+    type Output: Serialize;
+
+    fn handle(
+        &self,
+        ctx: &C,
+        params: Self::Params,
+    ) -> impl Future<Output = RpcResult<Self::Output>> + Send;
+}
+
+/// `RpcHandler` with its types erased, so handlers can share a map
+trait ErasedHandler<C>: Send + Sync {
+    fn call<'a>(&'a self, ctx: &'a C, params: Value) -> BoxFuture<'a, RpcResult<Value>>;
+}
+
+impl<C: Sync, H: RpcHandler<C>> ErasedHandler<C> for H {
+    fn call<'a>(&'a self, ctx: &'a C, params: Value) -> BoxFuture<'a, RpcResult<Value>> {
+        Box::pin(async move {
+            let params = decode::<H::Params>(params)?;
+            let output = self.handle(ctx, params).await?;
+            serde_json::to_value(output)
+                .map_err(|e| RpcError::failed(format!("Failed to encode result: {}", e)))
+        })
+    }
+}
+
+fn decode<P: DeserializeOwned>(params: Value) -> RpcResult<P> {
+    let params = if params.is_null() {
+        Value::Object(Default::default())
+    } else {
+        params
+    };
+    serde_json::from_value(params).map_err(RpcError::invalid_params)
+}
+
+/// Method name to handler
+pub struct Router<C> {
+    handlers: HashMap<&'static str, Box<dyn ErasedHandler<C>>>,
+}
+
+impl<C: Sync + 'static> Router<C> {
+    pub fn new() -> Self {
+        Self {
+            handlers: HashMap::new(),
+        }
+    }
+
+    pub fn route(mut self, method: &'static str, handler: impl RpcHandler<C>) -> Self {
+        let previous = self.handlers.insert(method, Box::new(handler));
+        assert!(previous.is_none(), "{} routed twice", method);
+        self
+    }
+
+    /// Every routed method, sorted
+    #[cfg(test)]
+    pub fn methods(&self) -> Vec<&'static str> {
+        let mut methods: Vec<_> = self.handlers.keys().copied().collect();
+        methods.sort_unstable();
+        methods
+    }
+
+    pub async fn dispatch(&self, ctx: &C, request: Request) -> Response {
+        let result = match self.handlers.get(request.method.as_str()) {
+            Some(handler) => handler.call(ctx, request.params).await,
+            None => Err(RpcError::new(
+                ErrorCode::MethodNotFound,
+                format!("Method not found: {}", request.method),
+            )),
+        };
+        match result {
+            Ok(result) => Response {
+                id: request.id,
+                result: Some(result),
+                error: None,
+            },
+            Err(e) => Response {
+                id: request.id,
+                result: None,
+                error: Some(ErrorInfo::new(e.code, e.message)),
+            },
+        }
+    }
+}
+
+impl<C: Sync + 'static> Default for Router<C> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[derive(Deserialize)]
+    struct AddParams {
+        a: i64,
+        #[serde(default)]
+        b: i64,
+    }
+
+    /// Adds to the context's base, failing on negative sums
+    struct Add;
+
+    impl RpcHandler<i64> for Add {
+        type Params = AddParams;
+        type Output = i64;
+
+        async fn handle(&self, base: &i64, params: AddParams) -> RpcResult<i64> {
+            let sum = base + params.a + params.b;
+            if sum < 0 {
+                return Err(anyhow::anyhow!("negative").into());
+            }
+            Ok(sum)
+        }
+    }
+
+    struct Ping;
+
+    impl RpcHandler<i64> for Ping {
+        type Params = NoParams;
+        type Output = &'static str;
+
+        async fn handle(&self, _: &i64, _: NoParams) -> RpcResult<&'static str> {
+            Ok("pong")
+        }
+    }
+
+    async fn call(router: &Router<i64>, method: &str, params: Value) -> Response {
+        let request = Request {
+            id: 7,
+            method: method.to_string(),
+            params,
+        };
+        router.dispatch(&10, request).await
+    }
+
+    #[tokio::test]
+    async fn test_dispatch() {
+        let router = Router::new().route("math.add", Add).route("ping", Ping);
+        assert_eq!(router.methods(), vec!["math.add", "ping"]);
+
+        let response = call(&router, "math.add", json!({"a": 1, "b": 2})).await;
+        assert_eq!(response.id, 7);
+        assert_eq!(response.result, Some(json!(13)));
+
+        let response = call(&router, "ping", json!([1, "x"])).await;
+        assert_eq!(response.result, Some(json!("pong")));
+        let response = call(&router, "ping", Value::Null).await;
+        assert_eq!(response.result, Some(json!("pong")));
+
+        let error = |response: Response| response.error.map(|e| (e.kind(), e.message));
+        assert_eq!(
+            error(call(&router, "math.add", Value::Null).await),
+            Some((
+                Some(ErrorCode::InvalidParams),
+                "Invalid params: missing field `a`".to_string()
+            ))
+        );
+        assert_eq!(
+            error(call(&router, "math.add", json!({"a": -20})).await),
+            Some((Some(ErrorCode::Failed), "negative".to_string()))
+        );
+        assert_eq!(
+            error(call(&router, "math.sub", json!({})).await),
+            Some((
+                Some(ErrorCode::MethodNotFound),
+                "Method not found: math.sub".to_string()
+            ))
+        );
+    }
+}
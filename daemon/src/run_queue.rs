@@ -0,0 +1,330 @@
+// Run queue - holds session starts while daemon.max_concurrent_running sessions
+// are already active (Starting, Running or Waiting), and starts them in order
+// as others go Idle, stop or error. Queued sessions show the Queued status.
+// The queue lives in memory only; queued sessions are Stopped after a daemon restart.
+
+use anyhow::Result;
+use shared::{Event, Session, SessionStatus, TerminalSize};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{broadcast, mpsc, Mutex};
+use tracing::{debug, info, warn};
+use uuid::Uuid;
+
+use crate::config::SharedConfig;
+use crate::hook_manager::HookManager;
+use crate::pty::PtyManager;
+use crate::session_manager::SessionManager;
+use crate::state::{SessionNotFound, SharedState};
+
+/// Safety net in case a status event was missed
+const RETRY_INTERVAL_SECS: u64 = 2;
+
+/// A start waiting for a slot, with the terminal size to spawn at (None = the
+/// session's last known size)
+struct QueuedStart {
+    session_id: Uuid,
+    size: Option<TerminalSize>,
+    resume: bool,
+}
+
+pub struct RunQueue {
+    state: SharedState,
+    config: SharedConfig,
+    pty_manager: Arc<PtyManager>,
+    output_tx: mpsc::Sender<(Uuid, Vec<u8>)>,
+    event_tx: broadcast::Sender<Event>,
+    hook_manager: Arc<HookManager>,
+    items: Mutex<Vec<QueuedStart>>,
+    /// Starts taken off the queue that aren't Starting yet; they hold a slot
+    starting: AtomicUsize,
+}
+
+/// Sessions holding a slot: coming up, busy with a turn or blocked on the user mid-turn
+fn is_active(status: SessionStatus) -> bool {
+    matches!(
+        status,
+        SessionStatus::Starting | SessionStatus::Running | SessionStatus::Waiting
+    )
+}
+
+impl RunQueue {
+    pub fn new(
+        state: SharedState,
+        config: SharedConfig,
+        pty_manager: Arc<PtyManager>,
+        output_tx: mpsc::Sender<(Uuid, Vec<u8>)>,
+        event_tx: broadcast::Sender<Event>,
+        hook_manager: Arc<HookManager>,
+    ) -> Self {
+        Self {
+            state,
+            config,
+            pty_manager,
+            output_tx,
+            event_tx,
+            hook_manager,
+            items: Mutex::new(Vec::new()),
+            starting: AtomicUsize::new(0),
+        }
+    }
+
+    /// Start a session now if a slot is free, otherwise queue it
+    /// Enqueueing a session that is already queued just updates its size.
+    pub async fn enqueue(
+        &self,
+        session_id: Uuid,
+        size: Option<TerminalSize>,
+        resume: bool,
+    ) -> Result<Session> {
+        if !self.state.read().await.sessions.contains_key(&session_id) {
+            return Err(SessionNotFound(session_id).into());
+        }
+        if self.pty_manager.is_alive(session_id).await {
+            anyhow::bail!("Session is already running");
+        }
+
+        // Queued before it is listed, so a concurrent dispatch doesn't drop it
+        SessionManager::apply_status(
+            &self.state,
+            &self.event_tx,
+            session_id,
+            SessionStatus::Queued,
+            None,
+        )
+        .await;
+        {
+            let mut items = self.items.lock().await;
+            match items.iter_mut().find(|item| item.session_id == session_id) {
+                Some(item) => {
+                    item.size = size;
+                    item.resume = resume;
+                }
+                None => items.push(QueuedStart {
+                    session_id,
+                    size,
+                    resume,
+                }),
+            }
+        }
+        self.dispatch().await;
+
+        self.state
+            .read()
+            .await
+            .sessions
+            .get(&session_id)
+            .cloned()
+            .ok_or_else(|| SessionNotFound(session_id).into())
+    }
+
+    /// Start queued sessions whenever a slot frees up
+    pub async fn run(self: Arc<Self>) {
+        let mut event_rx = self.event_tx.subscribe();
+
+        loop {
+            tokio::select! {
+                event = event_rx.recv() => match event {
+                    Ok(Event::StatusChanged(_) | Event::SessionDeleted { .. }) => {}
+                    Ok(_) => continue,
+                    Err(broadcast::error::RecvError::Lagged(n)) => {
+                        warn!("Run queue lagged, skipped {} events", n);
+                    }
+                    Err(broadcast::error::RecvError::Closed) => return,
+                },
+                _ = tokio::time::sleep(Duration::from_secs(RETRY_INTERVAL_SECS)) => {}
+            }
+
+            self.dispatch().await;
+        }
+    }
+
+    /// Start as many queued sessions as the free slots allow
+    /// The starts are taken off the queue under its lock and made after, so
+    /// enqueueing doesn't wait for them.
+    async fn dispatch(&self) {
+        let max_running = self.config.read().await.daemon.max_concurrent_running;
+        let starts: Vec<QueuedStart> = {
+            let mut items = self.items.lock().await;
+            let s = self.state.read().await;
+
+            // Sessions that were started by hand, stopped or deleted leave the queue
+            items.retain(|item| {
+                s.sessions
+                    .get(&item.session_id)
+                    .is_some_and(|session| session.status == SessionStatus::Queued)
+            });
+            let active = s
+                .sessions
+                .values()
+                .filter(|session| is_active(session.status))
+                .count()
+                + self.starting.load(Ordering::SeqCst);
+            let free = match max_running {
+                0 => items.len(),
+                max => max.saturating_sub(active).min(items.len()),
+            };
+            if free > 0 {
+                debug!("Starting {} queued sessions ({} active)", free, active);
+            }
+            self.starting.fetch_add(free, Ordering::SeqCst);
+            items.drain(..free).collect()
+        };
+
+        for item in starts {
+            // A start that fails leaves the session in Error, saying why
+            if let Err(e) = SessionManager::restart_session(
+                &self.state,
+                &self.pty_manager,
+                self.output_tx.clone(),
+                &self.event_tx,
+                &self.hook_manager,
+                item.session_id,
+                item.size,
+                item.resume,
+            )
+            .await
+            {
+                info!("Could not start queued session {}: {}", item.session_id, e);
+            }
+            self.starting.fetch_sub(1, Ordering::SeqCst);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::flow::FlowControl;
+    use std::path::Path;
+    use tokio::sync::RwLock;
+
+    /// A run queue with `max_running` slots whose sessions run the "busy"
+    /// script, written to `dir`
+    #[cfg(unix)]
+    fn script_queue(dir: &Path, max_running: usize) -> RunQueue {
+        use std::os::unix::fs::PermissionsExt;
+
+        std::fs::create_dir_all(dir).unwrap();
+        let path = dir.join("busy");
+        std::fs::write(&path, "#!/bin/sh\necho ready\nexec sleep 30\n").unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        let mut config = Config::default();
+        config.claude.binaries.insert("busy".to_string(), path);
+        config.daemon.max_concurrent_running = max_running;
+        let config = Arc::new(RwLock::new(config));
+
+        let (exit_tx, _exit_rx) = mpsc::unbounded_channel();
+        let pty_manager =
+            PtyManager::new(config.clone(), exit_tx, Arc::new(FlowControl::new(1024)));
+        let (output_tx, mut output_rx) = mpsc::channel(64);
+        tokio::spawn(async move { while output_rx.recv().await.is_some() {} });
+        let (event_tx, _) = broadcast::channel(64);
+        RunQueue::new(
+            crate::state::new_shared_state(),
+            config,
+            Arc::new(pty_manager),
+            output_tx,
+            event_tx,
+            Arc::new(HookManager::new(dir.join("hooks"), dir.join("hooks.sock"))),
+        )
+    }
+
+    async fn add_session(queue: &RunQueue, dir: &Path) -> Uuid {
+        let mut session = Session::new("s".into(), dir.to_path_buf(), None);
+        session.claude_binary = Some("busy".to_string());
+        let id = session.id;
+        queue.state.write().await.sessions.insert(id, session);
+        id
+    }
+
+    async fn status(queue: &RunQueue, id: Uuid) -> SessionStatus {
+        queue.state.read().await.sessions[&id].status
+    }
+
+    async fn stop(queue: &RunQueue, id: Uuid) {
+        queue.pty_manager.kill(id).await.unwrap();
+        SessionManager::apply_status(
+            &queue.state,
+            &queue.event_tx,
+            id,
+            SessionStatus::Stopped,
+            None,
+        )
+        .await;
+    }
+
+    async fn queued(queue: &RunQueue) -> Vec<Uuid> {
+        let items = queue.items.lock().await;
+        items.iter().map(|item| item.session_id).collect()
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_starts_wait_for_a_slot() {
+        crate::state::discard_saves();
+        let dir = std::env::temp_dir().join(format!("queue-slots-{}", Uuid::new_v4()));
+        let queue = script_queue(&dir, 1);
+        let (a, b, c) = (
+            add_session(&queue, &dir).await,
+            add_session(&queue, &dir).await,
+            add_session(&queue, &dir).await,
+        );
+
+        assert!(is_active(
+            queue.enqueue(a, None, false).await.unwrap().status
+        ));
+        let session = queue.enqueue(b, None, false).await.unwrap();
+        assert_eq!(session.status, SessionStatus::Queued);
+        queue.enqueue(c, None, false).await.unwrap();
+        assert_eq!(queued(&queue).await, vec![b, c]);
+
+        // A freed slot goes to the first in line only
+        stop(&queue, a).await;
+        queue.dispatch().await;
+        assert!(is_active(status(&queue, b).await));
+        assert!(queue.pty_manager.is_alive(b).await);
+        assert_eq!(status(&queue, c).await, SessionStatus::Queued);
+        assert_eq!(queued(&queue).await, vec![c]);
+
+        stop(&queue, b).await;
+        queue.dispatch().await;
+        assert!(is_active(status(&queue, c).await));
+        assert!(queued(&queue).await.is_empty());
+
+        stop(&queue, c).await;
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_queue_drops_started_and_deleted_sessions() {
+        crate::state::discard_saves();
+        let dir = std::env::temp_dir().join(format!("queue-drop-{}", Uuid::new_v4()));
+        let queue = script_queue(&dir, 1);
+        let (a, b, c) = (
+            add_session(&queue, &dir).await,
+            add_session(&queue, &dir).await,
+            add_session(&queue, &dir).await,
+        );
+        queue.enqueue(a, None, false).await.unwrap();
+        queue.enqueue(b, None, false).await.unwrap();
+        queue.enqueue(c, None, false).await.unwrap();
+
+        // b is started by hand and c deleted while they wait
+        SessionManager::apply_status(&queue.state, &queue.event_tx, b, SessionStatus::Idle, None)
+            .await;
+        queue.state.write().await.sessions.remove(&c);
+
+        stop(&queue, a).await;
+        queue.dispatch().await;
+        assert!(queued(&queue).await.is_empty());
+        assert!(!queue.pty_manager.is_alive(b).await);
+        assert_eq!(status(&queue, b).await, SessionStatus::Idle);
+        assert!(!queue.pty_manager.is_alive(c).await);
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,438 @@
+// Scheduled sessions - schedule.create stores a cron expression with the
+// session to create (persisted in schedules.json). When one comes due the
+// scheduler creates that session, starts it through the run queue and queues
+// the schedule's prompt, emitting schedule:fired; schedule:finished follows once
+// the prompt has been answered or the session stops. Runs missed while the
+// daemon was down or the machine asleep are skipped, as cron does.
+
+use anyhow::{Context, Result};
+use chrono::{
+    DateTime, Datelike, Duration, Local, NaiveDate, NaiveDateTime, TimeZone, Timelike, Utc,
+};
+use shared::{
+    CreateScheduleParams, Event, PromptPriority, Schedule, ScheduleFinishedData, ScheduleFiredData,
+    SessionStatus,
+};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::{broadcast, mpsc};
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::notifications::Notifier;
+use crate::prompt_queue::PromptQueue;
+use crate::pty::PtyManager;
+use crate::run_queue::RunQueue;
+use crate::session_manager::{check_working_dir, SessionManager};
+use crate::state::{save_state, SharedState};
+
+/// How often due schedules are looked for
+const TICK_SECS: u64 = 20;
+
+/// A run this late is treated as missed rather than started
+const MISSED_AFTER_MINS: i64 = 5;
+
+/// How far ahead next_run looks before deciding an expression never matches
+/// (e.g. "0 0 30 2 *")
+const SEARCH_DAYS: i64 = 366 * 5;
+
+/// A parsed five-field cron expression
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cron {
+    minutes: u64,
+    hours: u32,
+    days: u32,
+    months: u16,
+    weekdays: u8,
+    /// Day of month and day of week were both restricted: either may match
+    either_day: bool,
+}
+
+impl Cron {
+    pub fn parse(expr: &str) -> Result<Cron> {
+        let expr = match expr.trim() {
+            "@hourly" => "0 * * * *",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@weekly" => "0 0 * * 0",
+            "@monthly" => "0 0 1 * *",
+            "@yearly" | "@annually" => "0 0 1 1 *",
+            expr => expr,
+        };
+        let fields: Vec<&str> = expr.split_whitespace().collect();
+        let [minute, hour, day, month, weekday] = fields[..] else {
+            anyhow::bail!(
+                "Expected 5 fields (minute hour day month weekday), got {}",
+                fields.len()
+            );
+        };
+        // Sunday is 0 or 7
+        let weekdays = field(weekday, 0, 7, "weekday")?;
+        let weekdays = (weekdays | weekdays >> 7) & 0x7f;
+        Ok(Cron {
+            minutes: field(minute, 0, 59, "minute")?,
+            hours: field(hour, 0, 23, "hour")? as u32,
+            days: field(day, 1, 31, "day")? as u32,
+            months: field(month, 1, 12, "month")? as u16,
+            weekdays: weekdays as u8,
+            either_day: day != "*" && weekday != "*",
+        })
+    }
+
+    fn matches_day(&self, date: NaiveDate) -> bool {
+        let day = self.days & 1 << date.day() != 0;
+        let weekday = self.weekdays & 1 << date.weekday().num_days_from_sunday() != 0;
+        let day = if self.either_day {
+            day || weekday
+        } else {
+            day && weekday
+        };
+        day && self.months & 1 << date.month() != 0
+    }
+
+    /// The first matching minute after `after`
+    pub fn next_after(&self, after: NaiveDateTime) -> Option<NaiveDateTime> {
+        let start =
+            after.date().and_hms_opt(after.hour(), after.minute(), 0)? + Duration::minutes(1);
+        let end = start + Duration::days(SEARCH_DAYS);
+        let mut time = start;
+        while time < end {
+            if !self.matches_day(time.date()) {
+                time = time.date().succ_opt()?.and_hms_opt(0, 0, 0)?;
+            } else if self.hours & 1 << time.hour() == 0 {
+                time = time.date().and_hms_opt(time.hour(), 0, 0)? + Duration::hours(1);
+            } else if self.minutes & 1 << time.minute() == 0 {
+                time += Duration::minutes(1);
+            } else {
+                return Some(time);
+            }
+        }
+        None
+    }
+}
+
+/// Bits for one cron field: "*", "5", "1-5", "*/15", "1-30/2" and lists of them
+fn field(spec: &str, min: u32, max: u32, name: &str) -> Result<u64> {
+    let mut bits = 0u64;
+    for part in spec.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => {
+                let step: u32 = step
+                    .parse()
+                    .ok()
+                    .filter(|&step| step > 0)
+                    .with_context(|| format!("Invalid {} step '{}'", name, part))?;
+                (range, step)
+            }
+            None => (part, 1),
+        };
+        let value = |s: &str| -> Result<u32> {
+            s.parse()
+                .ok()
+                .filter(|v| (min..=max).contains(v))
+                .with_context(|| format!("Invalid {} '{}' (expected {}-{})", name, s, min, max))
+        };
+        let (start, end) = match range {
+            "*" => (min, max),
+            range => match range.split_once('-') {
+                Some((start, end)) => (value(start)?, value(end)?),
+                // "5/10" runs from 5 to the end
+                None if step > 1 => (value(range)?, max),
+                None => (value(range)?, value(range)?),
+            },
+        };
+        if start > end {
+            anyhow::bail!("Invalid {} range '{}'", name, range);
+        }
+        for v in (start..=end).step_by(step as usize) {
+            bits |= 1 << v;
+        }
+    }
+    Ok(bits)
+}
+
+/// When `cron` fires next after `after`, skipping local times a DST change
+/// leaves out
+fn next_run(cron: &Cron, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    let mut time = after.with_timezone(&Local).naive_local();
+    loop {
+        time = cron.next_after(time)?;
+        if let Some(local) = Local.from_local_datetime(&time).earliest() {
+            return Some(local.with_timezone(&Utc));
+        }
+    }
+}
+
+/// Add a schedule; fails if its cron expression doesn't parse
+pub async fn create(state: &SharedState, params: CreateScheduleParams) -> Result<Schedule> {
+    let cron = Cron::parse(&params.cron)
+        .with_context(|| format!("Invalid cron expression '{}'", params.cron))?;
+    let prompt = params.prompt.filter(|prompt| !prompt.trim().is_empty());
+    // A directory to be created is made when the schedule fires
+    if !params.session.create_dir {
+        check_working_dir(Path::new(&params.session.dir), false)?;
+    }
+    let mut schedule = Schedule::new(params.name, params.cron, params.session, prompt);
+    schedule.next_run = next_run(&cron, Utc::now());
+    state
+        .write()
+        .await
+        .schedules
+        .insert(schedule.id, schedule.clone());
+    save_state(state).await?;
+    Ok(schedule)
+}
+
+pub async fn delete(state: &SharedState, schedule_id: Uuid) -> Result<()> {
+    if state.write().await.schedules.remove(&schedule_id).is_none() {
+        anyhow::bail!("Schedule not found: {}", schedule_id);
+    }
+    save_state(state).await
+}
+
+/// A run whose session hasn't finished yet
+struct Run {
+    schedule_id: Uuid,
+    name: String,
+    /// Finished once the prompt is answered; otherwise when the session stops
+    prompted: bool,
+}
+
+pub struct Scheduler {
+    state: SharedState,
+    pty_manager: Arc<PtyManager>,
+    output_tx: mpsc::Sender<(Uuid, Vec<u8>)>,
+    event_tx: broadcast::Sender<Event>,
+    run_queue: Arc<RunQueue>,
+    prompt_queue: Arc<PromptQueue>,
+    notifier: Arc<Notifier>,
+}
+
+impl Scheduler {
+    pub fn new(
+        state: SharedState,
+        pty_manager: Arc<PtyManager>,
+        output_tx: mpsc::Sender<(Uuid, Vec<u8>)>,
+        event_tx: broadcast::Sender<Event>,
+        run_queue: Arc<RunQueue>,
+        prompt_queue: Arc<PromptQueue>,
+        notifier: Arc<Notifier>,
+    ) -> Self {
+        Self {
+            state,
+            pty_manager,
+            output_tx,
+            event_tx,
+            run_queue,
+            prompt_queue,
+            notifier,
+        }
+    }
+
+    /// Fire schedules as they come due and report when their runs finish
+    pub async fn run(self) {
+        let mut event_rx = self.event_tx.subscribe();
+        let mut tick = tokio::time::interval(std::time::Duration::from_secs(TICK_SECS));
+        let mut runs: HashMap<Uuid, Run> = HashMap::new();
+
+        loop {
+            tokio::select! {
+                _ = tick.tick() => {
+                    for (session_id, run) in self.fire_due().await {
+                        runs.insert(session_id, run);
+                    }
+                }
+                event = event_rx.recv() => {
+                    let (session_id, status) = match event {
+                        Ok(Event::TurnCompleted(data)) => (data.session_id, SessionStatus::Idle),
+                        Ok(Event::StatusChanged(data)) => (data.session_id, data.status),
+                        Ok(_) => continue,
+                        Err(broadcast::error::RecvError::Lagged(n)) => {
+                            warn!("Scheduler lagged, skipped {} events", n);
+                            continue;
+                        }
+                        Err(broadcast::error::RecvError::Closed) => return,
+                    };
+                    let finished = runs.get(&session_id).is_some_and(|run| match status {
+                        SessionStatus::Stopped | SessionStatus::Error => true,
+                        SessionStatus::Idle => run.prompted,
+                        _ => false,
+                    });
+                    if finished {
+                        if let Some(run) = runs.remove(&session_id) {
+                            self.finished(session_id, run, status).await;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Start the runs of schedules that are due and move them to their next time
+    async fn fire_due(&self) -> Vec<(Uuid, Run)> {
+        let now = Utc::now();
+        let due: Vec<(Schedule, bool)> = {
+            let mut s = self.state.write().await;
+            s.schedules
+                .values_mut()
+                .filter(|schedule| schedule.next_run.is_some_and(|next| next <= now))
+                .map(|schedule| {
+                    let missed = schedule
+                        .next_run
+                        .is_some_and(|next| now - next > Duration::minutes(MISSED_AFTER_MINS));
+                    schedule.next_run = Cron::parse(&schedule.cron)
+                        .ok()
+                        .and_then(|cron| next_run(&cron, now));
+                    (schedule.clone(), missed)
+                })
+                .collect()
+        };
+        if due.is_empty() {
+            return Vec::new();
+        }
+
+        let mut runs = Vec::new();
+        for (schedule, missed) in due {
+            if missed {
+                info!("Skipping missed run of schedule '{}'", schedule.name);
+                continue;
+            }
+            match self.fire(&schedule).await {
+                Ok(session_id) => {
+                    if let Some(stored) = self.state.write().await.schedules.get_mut(&schedule.id) {
+                        stored.last_run = Some(now);
+                        stored.last_session_id = Some(session_id);
+                    }
+                    runs.push((
+                        session_id,
+                        Run {
+                            schedule_id: schedule.id,
+                            name: schedule.name.clone(),
+                            prompted: schedule.prompt.is_some(),
+                        },
+                    ));
+                }
+                Err(e) => warn!("Schedule '{}' failed to start: {:#}", schedule.name, e),
+            }
+        }
+        if let Err(e) = save_state(&self.state).await {
+            warn!("Failed to save state after running schedules: {}", e);
+        }
+        runs
+    }
+
+    /// Create and start the schedule's session; returns its id
+    async fn fire(&self, schedule: &Schedule) -> Result<Uuid> {
+        info!("Schedule '{}' is due, creating its session", schedule.name);
+        let params = &schedule.session;
+        let name = format!("{} {}", params.name, Local::now().format("%Y-%m-%d %H:%M"));
+        let session = SessionManager::create_session(
+            &self.state,
+            &self.pty_manager,
+            self.output_tx.clone(),
+            &self.event_tx,
+            name,
+            PathBuf::from(&params.dir),
+            params.group_id,
+            params.spawn_mode,
+            params.claude_binary.clone(),
+            params.flags.clone(),
+            params.create_dir,
+            params.size,
+        )
+        .await?;
+        self.run_queue.enqueue(session.id, None, false).await?;
+        if let Some(prompt) = &schedule.prompt {
+            self.prompt_queue
+                .enqueue(session.id, prompt.clone(), PromptPriority::Scheduled)
+                .await?;
+        }
+
+        let _ = self.event_tx.send(Event::ScheduleFired(ScheduleFiredData {
+            schedule_id: schedule.id,
+            name: schedule.name.clone(),
+            session_id: session.id,
+        }));
+        Ok(session.id)
+    }
+
+    async fn finished(&self, session_id: Uuid, run: Run, status: SessionStatus) {
+        info!(
+            "Scheduled run of '{}' finished ({:?}) in session {}",
+            run.name, status, session_id
+        );
+        let group_id = {
+            let s = self.state.read().await;
+            s.sessions
+                .get(&session_id)
+                .and_then(|session| session.group_id)
+        };
+        let muted = self
+            .notifier
+            .settings()
+            .await
+            .is_muted(session_id, group_id);
+        let _ = self
+            .event_tx
+            .send(Event::ScheduleFinished(ScheduleFinishedData {
+                schedule_id: run.schedule_id,
+                name: run.name,
+                session_id,
+                status,
+                muted,
+            }));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(s: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M").unwrap()
+    }
+
+    #[test]
+    fn test_cron_next_after() {
+        let next = |expr: &str, after: &str| {
+            Cron::parse(expr)
+                .unwrap()
+                .next_after(at(after))
+                .map(|time| time.format("%Y-%m-%d %H:%M").to_string())
+        };
+
+        // Nightly at 2am, from before and after it
+        assert_eq!(
+            next("0 2 * * *", "2026-03-01 01:59").as_deref(),
+            Some("2026-03-01 02:00")
+        );
+        assert_eq!(
+            next("0 2 * * *", "2026-03-01 02:00").as_deref(),
+            Some("2026-03-02 02:00")
+        );
+        assert_eq!(
+            next("@daily", "2026-12-31 23:30").as_deref(),
+            Some("2027-01-01 00:00")
+        );
+        assert_eq!(
+            next("*/15 9-17 * * 1-5", "2026-10-16 17:50").as_deref(),
+            Some("2026-10-19 09:00")
+        );
+        // Day of month or day of week once both are given; Sunday is 0 or 7
+        assert_eq!(
+            next("0 0 13 * 5", "2026-10-16 00:00").as_deref(),
+            Some("2026-10-23 00:00")
+        );
+        assert_eq!(
+            next("30 6 * * 7", "2026-10-16 00:00").as_deref(),
+            Some("2026-10-18 06:30")
+        );
+        assert_eq!(next("0 0 30 2 *", "2026-01-01 00:00"), None);
+
+        assert!(Cron::parse("0 2 * *").is_err());
+        assert!(Cron::parse("60 * * * *").is_err());
+        assert!(Cron::parse("*/0 * * * *").is_err());
+        assert!(Cron::parse("0 5-1 * * *").is_err());
+        assert!(Cron::parse("0,30 1,13 1-15/2 jan *").is_err());
+    }
+}
@@ -0,0 +1,156 @@
+// Screen model - a VT parser per session follows its PTY output, so the daemon
+// knows what the terminal shows: screen contents, cursor and whether the
+// alternate screen is in use. session.get_screen returns it, letting a client
+// restore a terminal at once instead of replaying output.
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use shared::SessionScreen;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use uuid::Uuid;
+
+/// Switches a terminal to the alternate screen
+const ENTER_ALTERNATE_SCREEN: &[u8] = b"\x1b[?1049h";
+
+struct Screen {
+    parser: vt100::Parser,
+    /// The process that drew this screen has exited
+    exited: bool,
+}
+
+/// Screen of every session that has written output
+#[derive(Default)]
+pub struct Screens {
+    screens: Mutex<HashMap<Uuid, Screen>>,
+}
+
+impl Screens {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether output of the session needs a fresh screen (`start`) first
+    pub fn needs_start(&self, session_id: Uuid) -> bool {
+        self.screens
+            .lock()
+            .unwrap()
+            .get(&session_id)
+            .is_none_or(|screen| screen.exited)
+    }
+
+    /// Begin a blank screen for a newly spawned process
+    pub fn start(&self, session_id: Uuid, rows: u16, cols: u16) {
+        let screen = Screen {
+            parser: vt100::Parser::new(rows, cols, 0),
+            exited: false,
+        };
+        self.screens.lock().unwrap().insert(session_id, screen);
+    }
+
+    pub fn write(&self, session_id: Uuid, data: &[u8]) {
+        if let Some(screen) = self.screens.lock().unwrap().get_mut(&session_id) {
+            screen.parser.process(data);
+        }
+    }
+
+    pub fn resize(&self, session_id: Uuid, rows: u16, cols: u16) {
+        if let Some(screen) = self.screens.lock().unwrap().get_mut(&session_id) {
+            screen.parser.screen_mut().set_size(rows, cols);
+        }
+    }
+
+    /// Keep the last screen of an exited process until the session restarts
+    pub fn exited(&self, session_id: Uuid) {
+        if let Some(screen) = self.screens.lock().unwrap().get_mut(&session_id) {
+            screen.exited = true;
+        }
+    }
+
+    pub fn remove(&self, session_id: Uuid) {
+        self.screens.lock().unwrap().remove(&session_id);
+    }
+
+    /// Whether the session's program has put the cursor keys in application mode
+    pub fn application_cursor(&self, session_id: Uuid) -> bool {
+        self.screens
+            .lock()
+            .unwrap()
+            .get(&session_id)
+            .is_some_and(|screen| screen.parser.screen().application_cursor())
+    }
+
+    /// Text of the last `count` non-blank rows of the screen, top to bottom
+    pub fn bottom_lines(&self, session_id: Uuid, count: usize) -> Option<String> {
+        let screens = self.screens.lock().unwrap();
+        let contents = screens.get(&session_id)?.parser.screen().contents();
+        let lines: Vec<&str> = contents
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .collect();
+        Some(lines[lines.len().saturating_sub(count)..].join("\n"))
+    }
+
+    /// What the session's terminal shows now, if it has written anything
+    pub fn get(&self, session_id: Uuid) -> Option<SessionScreen> {
+        let screens = self.screens.lock().unwrap();
+        let screen = screens.get(&session_id)?;
+        let vt = screen.parser.screen();
+        let (rows, cols) = vt.size();
+        let (cursor_row, cursor_col) = vt.cursor_position();
+
+        let mut formatted = Vec::new();
+        if vt.alternate_screen() {
+            formatted.extend_from_slice(ENTER_ALTERNATE_SCREEN);
+        }
+        formatted.extend(vt.state_formatted());
+
+        Some(SessionScreen {
+            session_id,
+            rows,
+            cols,
+            contents: vt.contents(),
+            formatted: BASE64.encode(formatted),
+            cursor_row,
+            cursor_col,
+            cursor_hidden: vt.hide_cursor(),
+            alternate_screen: vt.alternate_screen(),
+            exited: screen.exited,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_screen_model() {
+        let id = Uuid::new_v4();
+        let screens = Screens::new();
+        assert!(screens.needs_start(id));
+        screens.start(id, 4, 20);
+        screens.write(id, b"hello\r\nwor");
+        screens.write(id, b"ld\x1b[?25l");
+
+        let screen = screens.get(id).unwrap();
+        assert_eq!(screen.contents, "hello\nworld");
+        assert_eq!((screen.cursor_row, screen.cursor_col), (1, 5));
+        assert!(screen.cursor_hidden);
+        assert!(!screen.alternate_screen);
+        assert_eq!(screens.bottom_lines(id, 1).unwrap(), "world");
+
+        // A full-screen program draws on the alternate screen
+        screens.write(id, b"\x1b[?1049h\x1b[Hmenu");
+        screens.resize(id, 10, 40);
+        let screen = screens.get(id).unwrap();
+        assert!(screen.alternate_screen);
+        assert_eq!((screen.rows, screen.cols), (10, 40));
+        assert_eq!(screen.contents, "menu");
+        let formatted = BASE64.decode(&screen.formatted).unwrap();
+        assert!(formatted.starts_with(ENTER_ALTERNATE_SCREEN));
+
+        screens.exited(id);
+        assert!(screens.get(id).unwrap().exited);
+        assert!(screens.needs_start(id));
+    }
+}
@@ -0,0 +1,232 @@
+// Global search - greps every session's output log and Claude transcript, live
+// or archived, for search.query, with regex, time range and context lines
+// This is a full scan of everything on disk, so the caller runs it on a blocking thread
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use regex::{Regex, RegexBuilder};
+use shared::{SearchHit, SearchQueryParams, SearchSource};
+use std::fs;
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+use crate::output_log;
+use crate::transcript;
+
+/// Most matches search.query returns when the client doesn't say
+pub const DEFAULT_LIMIT: usize = 200;
+/// Context lines around each match when the client doesn't say
+pub const DEFAULT_CONTEXT: usize = 2;
+
+/// A session to search
+pub struct Target {
+    pub session_id: Uuid,
+    pub name: String,
+    pub archived: bool,
+    pub transcript: Option<PathBuf>,
+}
+
+/// A line of searchable text and when it was written
+type Line = (Option<DateTime<Utc>>, String);
+
+/// A matching line within one source
+struct Match {
+    offset: usize,
+    at: Option<DateTime<Utc>>,
+    text: String,
+    before: Vec<String>,
+    after: Vec<String>,
+}
+
+/// Compile the query; plain-text queries are escaped
+pub fn matcher(params: &SearchQueryParams) -> Result<Regex> {
+    let pattern = if params.regex {
+        params.query.clone()
+    } else {
+        regex::escape(&params.query)
+    };
+    Ok(RegexBuilder::new(&pattern)
+        .case_insensitive(!params.case_sensitive)
+        .build()?)
+}
+
+fn output_lines(session_id: Uuid) -> Result<Vec<Line>> {
+    Ok(output_log::timed_lines(session_id)?
+        .into_iter()
+        .map(|(at, line)| (at.and_then(DateTime::from_timestamp_millis), line))
+        .collect())
+}
+
+fn transcript_lines(path: &Path) -> Result<Vec<Line>> {
+    let (messages, _) = transcript::parse(&fs::read_to_string(path)?);
+    Ok(messages
+        .into_iter()
+        .flat_map(|message| {
+            let at = message.timestamp;
+            message
+                .text
+                .lines()
+                .map(|line| (at, line.to_string()))
+                .collect::<Vec<_>>()
+        })
+        .collect())
+}
+
+/// Matching lines with their context, at most `limit` of them, and whether more matched
+fn find(
+    lines: &[Line],
+    matcher: &Regex,
+    since: Option<DateTime<Utc>>,
+    until: Option<DateTime<Utc>>,
+    context: usize,
+    limit: usize,
+) -> (Vec<Match>, bool) {
+    let in_range = |at: Option<DateTime<Utc>>| match at {
+        Some(at) => since.is_none_or(|since| at >= since) && until.is_none_or(|until| at < until),
+        // Untimed lines can only match when no range was asked for
+        None => since.is_none() && until.is_none(),
+    };
+    let text = |range: &[Line]| range.iter().map(|(_, line)| line.clone()).collect();
+
+    let mut matches = lines
+        .iter()
+        .enumerate()
+        .filter(|(_, (at, line))| in_range(*at) && matcher.is_match(line));
+    let found = matches
+        .by_ref()
+        .take(limit)
+        .map(|(i, (at, line))| {
+            let before = &lines[i.saturating_sub(context)..i];
+            let after = &lines[i + 1..(i + 1 + context).min(lines.len())];
+            Match {
+                offset: i + 1,
+                at: *at,
+                text: line.clone(),
+                before: text(before),
+                after: text(after),
+            }
+        })
+        .collect();
+    (found, matches.next().is_some())
+}
+
+/// Search every target, returning at most `limit` hits and whether more matched
+pub fn run(
+    targets: &[Target],
+    params: &SearchQueryParams,
+    matcher: &Regex,
+) -> (Vec<SearchHit>, bool) {
+    let limit = params.limit.unwrap_or(DEFAULT_LIMIT);
+    let context = params.context.unwrap_or(DEFAULT_CONTEXT);
+    let searches = |source: SearchSource| {
+        params
+            .sources
+            .as_ref()
+            .is_none_or(|sources| sources.contains(&source))
+    };
+
+    let mut hits = Vec::new();
+    for target in targets {
+        for source in [SearchSource::Output, SearchSource::Transcript] {
+            if !searches(source) {
+                continue;
+            }
+            // Missing logs and transcripts just have nothing to match
+            let lines = match (source, &target.transcript) {
+                (SearchSource::Output, _) => output_lines(target.session_id),
+                (SearchSource::Transcript, Some(path)) => transcript_lines(path),
+                (SearchSource::Transcript, None) => continue,
+            };
+            let Ok(lines) = lines else {
+                continue;
+            };
+
+            let (found, more) = find(
+                &lines,
+                matcher,
+                params.since,
+                params.until,
+                context,
+                limit - hits.len(),
+            );
+            hits.extend(found.into_iter().map(|found| SearchHit {
+                session_id: target.session_id,
+                session_name: target.name.clone(),
+                archived: target.archived,
+                source,
+                offset: found.offset,
+                at: found.at,
+                text: found.text,
+                before: found.before,
+                after: found.after,
+            }));
+            if more || hits.len() >= limit {
+                return (hits, true);
+            }
+        }
+    }
+    (hits, false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_with_range_and_context() {
+        let t = |secs| DateTime::from_timestamp(secs, 0);
+        let lines: Vec<Line> = vec![
+            (t(100), "cargo build".to_string()),
+            (t(100), "error[E0308]: mismatched types".to_string()),
+            (t(200), "fixed".to_string()),
+            (t(300), "error: linker failed".to_string()),
+            (None, "error: from an untimed log".to_string()),
+        ];
+        let params = SearchQueryParams {
+            query: r"^error(\[E\d+\])?:".to_string(),
+            regex: true,
+            case_sensitive: false,
+            sources: None,
+            session_ids: None,
+            since: None,
+            until: None,
+            context: None,
+            limit: None,
+        };
+        let matcher = matcher(&params).unwrap();
+
+        let (found, more) = find(&lines, &matcher, None, None, 1, 10);
+        assert_eq!(found.len(), 3);
+        assert!(!more);
+        assert_eq!(found[0].offset, 2);
+        assert_eq!(found[0].before, vec!["cargo build"]);
+        assert_eq!(found[0].after, vec!["fixed"]);
+
+        // Only the second error is in range; the untimed line is excluded
+        let (found, _) = find(&lines, &matcher, t(150), None, 0, 10);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].offset, 4);
+
+        let (found, more) = find(&lines, &matcher, None, None, 0, 1);
+        assert_eq!(found.len(), 1);
+        assert!(more);
+    }
+
+    #[test]
+    fn test_plain_query_is_escaped() {
+        let params = SearchQueryParams {
+            query: "a.b(".to_string(),
+            regex: false,
+            case_sensitive: true,
+            sources: None,
+            session_ids: None,
+            since: None,
+            until: None,
+            context: None,
+            limit: None,
+        };
+        let matcher = matcher(&params).unwrap();
+        assert!(matcher.is_match("x a.b( y"));
+        assert!(!matcher.is_match("axb("));
+    }
+}
@@ -0,0 +1,236 @@
+// Service installation - `--install-service` registers the daemon with the OS
+// service manager (a systemd user unit on Linux, a LaunchAgent on macOS) so it
+// starts at login without the GUI; `--uninstall-service` removes it again.
+// Each profile gets its own service. Run the GUI in external daemon mode
+// (CLAUDE_MASTER_EXTERNAL_DAEMON=1) so it doesn't launch a second daemon.
+
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// What to do with the service instead of running the daemon
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ServiceAction {
+    Install,
+    Uninstall,
+}
+
+/// Service name, distinct from the LaunchAgent the GUI manages
+const SERVICE_NAME: &str = "claude-master-daemon";
+const LAUNCHD_LABEL: &str = "com.claudemaster.daemon-service";
+
+/// Install or uninstall the current profile's service, reporting on stdout
+pub fn run(action: ServiceAction, profile: Option<&str>) -> Result<()> {
+    match action {
+        ServiceAction::Install => install(profile),
+        ServiceAction::Uninstall => uninstall(profile),
+    }
+}
+
+fn install(profile: Option<&str>) -> Result<()> {
+    let exe = std::env::current_exe()?
+        .canonicalize()
+        .context("Failed to resolve the daemon binary")?;
+    let log = shared::get_logs_dir()?.join("daemon.log");
+    let path = service_path(profile)?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    if cfg!(target_os = "macos") {
+        fs::write(&path, launchd_plist(profile, &exe, &log))?;
+        // Reloaded so a reinstall picks up a new binary path
+        let _ = launchctl(&["unload", &path.to_string_lossy()]);
+        launchctl(&["load", "-w", &path.to_string_lossy()])?;
+    } else {
+        fs::write(&path, systemd_unit(profile, &exe, &log))?;
+        systemctl(&["daemon-reload"])?;
+        systemctl(&["enable", "--now", &unit_name(profile)])?;
+    }
+
+    println!("Installed service {:?}", path);
+    println!("Socket: {:?}", shared::get_socket_path()?);
+    println!("Log: {:?}", log);
+    Ok(())
+}
+
+fn uninstall(profile: Option<&str>) -> Result<()> {
+    let path = service_path(profile)?;
+    if !path.exists() {
+        println!("No service installed at {:?}", path);
+        return Ok(());
+    }
+
+    if cfg!(target_os = "macos") {
+        let _ = launchctl(&["unload", "-w", &path.to_string_lossy()]);
+        fs::remove_file(&path)?;
+    } else {
+        let _ = systemctl(&["disable", "--now", &unit_name(profile)]);
+        fs::remove_file(&path)?;
+        systemctl(&["daemon-reload"])?;
+    }
+
+    println!("Removed service {:?}", path);
+    Ok(())
+}
+
+/// systemd unit name of a profile's service
+fn unit_name(profile: Option<&str>) -> String {
+    match profile {
+        Some(name) => format!("{}-{}.service", SERVICE_NAME, name),
+        None => format!("{}.service", SERVICE_NAME),
+    }
+}
+
+/// launchd label of a profile's service
+fn launchd_label(profile: Option<&str>) -> String {
+    match profile {
+        Some(name) => format!("{}.{}", LAUNCHD_LABEL, name),
+        None => LAUNCHD_LABEL.to_string(),
+    }
+}
+
+/// Where the unit file or plist of a profile's service goes
+fn service_path(profile: Option<&str>) -> Result<PathBuf> {
+    let home = dirs::home_dir().context("Could not find home directory")?;
+    Ok(if cfg!(target_os = "macos") {
+        home.join("Library/LaunchAgents")
+            .join(format!("{}.plist", launchd_label(profile)))
+    } else {
+        dirs::config_dir()
+            .unwrap_or_else(|| home.join(".config"))
+            .join("systemd/user")
+            .join(unit_name(profile))
+    })
+}
+
+/// Daemon arguments for a profile (none for the default profile)
+fn daemon_args(profile: Option<&str>) -> Vec<String> {
+    match profile {
+        Some(name) => vec!["--profile".to_string(), name.to_string()],
+        None => Vec::new(),
+    }
+}
+
+fn systemd_unit(profile: Option<&str>, exe: &Path, log: &Path) -> String {
+    let mut exec_start = format!("\"{}\"", exe.display());
+    for arg in daemon_args(profile) {
+        exec_start.push(' ');
+        exec_start.push_str(&arg);
+    }
+    let description = match profile {
+        Some(name) => format!("Claude Master daemon ({})", name),
+        None => "Claude Master daemon".to_string(),
+    };
+    format!(
+        "[Unit]
+Description={}
+
+[Service]
+ExecStart={}
+Restart=on-failure
+RestartSec=2
+Environment=RUST_LOG=info
+StandardOutput=append:{}
+StandardError=append:{}
+
+[Install]
+WantedBy=default.target
+",
+        description,
+        exec_start,
+        log.display(),
+        log.display()
+    )
+}
+
+fn launchd_plist(profile: Option<&str>, exe: &Path, log: &Path) -> String {
+    let arguments: String = std::iter::once(exe.display().to_string())
+        .chain(daemon_args(profile))
+        .map(|arg| format!("\n        <string>{}</string>", arg))
+        .collect();
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+    <key>Label</key>
+    <string>{}</string>
+
+    <key>ProgramArguments</key>
+    <array>{}
+    </array>
+
+    <key>RunAtLoad</key>
+    <true/>
+
+    <key>KeepAlive</key>
+    <true/>
+
+    <key>StandardOutPath</key>
+    <string>{}</string>
+
+    <key>StandardErrorPath</key>
+    <string>{}</string>
+
+    <key>EnvironmentVariables</key>
+    <dict>
+        <key>RUST_LOG</key>
+        <string>info</string>
+    </dict>
+</dict>
+</plist>
+"#,
+        launchd_label(profile),
+        arguments,
+        log.display(),
+        log.display()
+    )
+}
+
+fn systemctl(args: &[&str]) -> Result<()> {
+    let status = Command::new("systemctl")
+        .arg("--user")
+        .args(args)
+        .status()
+        .context("Failed to run systemctl")?;
+    if !status.success() {
+        anyhow::bail!("systemctl --user {} failed", args.join(" "));
+    }
+    Ok(())
+}
+
+fn launchctl(args: &[&str]) -> Result<()> {
+    let status = Command::new("launchctl")
+        .args(args)
+        .status()
+        .context("Failed to run launchctl")?;
+    if !status.success() {
+        anyhow::bail!("launchctl {} failed", args.join(" "));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_service_files() {
+        let exe = Path::new("/opt/cm/claude-master-daemon");
+        let log = Path::new("/tmp/logs/daemon.log");
+
+        let unit = systemd_unit(Some("work"), exe, log);
+        assert!(unit.contains("ExecStart=\"/opt/cm/claude-master-daemon\" --profile work\n"));
+        assert!(unit.contains("StandardOutput=append:/tmp/logs/daemon.log\n"));
+        assert_eq!(unit_name(Some("work")), "claude-master-daemon-work.service");
+        assert!(!systemd_unit(None, exe, log).contains("--profile"));
+
+        let plist = launchd_plist(Some("work"), exe, log);
+        assert!(plist.contains("<string>com.claudemaster.daemon-service.work</string>"));
+        assert!(plist.contains(
+            "<string>/opt/cm/claude-master-daemon</string>\n        <string>--profile</string>\n        <string>work</string>\n    </array>"
+        ));
+    }
+}
@@ -1,20 +1,107 @@
 use anyhow::Result;
 use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
-use chrono::Utc;
-use shared::{Event, Group, PtyOutputData, Session, SessionStatus, StatusChangedData};
-use std::collections::HashMap;
-use std::path::PathBuf;
+use chrono::{DateTime, Utc};
+use shared::{
+    ApprovalPolicy, ApprovalRequestedData, AutoApprovedData, ClaudeFlags, ErrorCode, Event,
+    GitChangedData, Group, GroupDeleteMode, GroupDeleteResult, HistoryKind, IdleTimeoutData,
+    PtyOutputData, Session, SessionMovedData, SessionStatus, SpawnMode, StatusChangedData,
+    StopOutcome, TerminalSize, TranscriptState, UnreadData,
+};
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::{broadcast, mpsc, RwLock};
-use tracing::{debug, info};
+use tokio::time::Instant;
+use tracing::{debug, info, warn};
 use uuid::Uuid;
 
+use crate::approvals::{self, Approvals, HookInput};
+use crate::archive;
 use crate::claude;
+use crate::config::SharedConfig;
+use crate::detach::Detached;
+use crate::flow::FlowControl;
+use crate::git;
+use crate::history;
 use crate::hook_listener::HookEvent;
 use crate::hook_manager::HookManager;
-use crate::pty::PtyManager;
-use crate::state::{save_state, SharedState};
+use crate::orphans;
+use crate::output_log::OutputLogger;
+use crate::policy::{self, ApprovalRequest};
+use crate::process_group::KILL_GRACE;
+use crate::pty::{PtyExit, PtyManager, Started};
+use crate::recording::Recorder;
+use crate::screen::Screens;
+use crate::state::{save_state, AppState, GroupNotFound, SessionNotFound, SharedState};
 use crate::status_tracker::StatusTracker;
+use crate::transcript;
+use crate::turns::TurnTracker;
+use crate::watches::{Watch, WatchTarget};
+use crate::worktree;
+
+/// Rows at the bottom of the screen that show Claude's input box or dialogs
+const SCREEN_STATUS_LINES: usize = 8;
+
+/// PTY output of a session is batched for this long before it is broadcast
+const OUTPUT_FRAME: Duration = Duration::from_millis(16);
+/// A batch this large is sent without waiting for the frame to end
+const MAX_BATCH_BYTES: usize = 64 * 1024;
+
+/// Why a session couldn't be created or started
+#[derive(Debug, thiserror::Error)]
+pub enum SessionCreateError {
+    #[error("Working directory {} does not exist", .0.display())]
+    DirNotFound(PathBuf),
+    #[error("{} is not a directory", .0.display())]
+    NotADirectory(PathBuf),
+    #[error("Working directory {} is not readable: {1}", .0.display())]
+    DirUnreadable(PathBuf, std::io::Error),
+    #[error("Failed to create working directory {}: {1}", .0.display())]
+    CreateDir(PathBuf, std::io::Error),
+    #[error("Failed to start claude: {0:#}")]
+    Spawn(anyhow::Error),
+    #[error("claude {0} before it started")]
+    ExitedEarly(String),
+}
+
+impl SessionCreateError {
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            SessionCreateError::Spawn(_) | SessionCreateError::ExitedEarly(_) => {
+                ErrorCode::SpawnFailed
+            }
+            _ => ErrorCode::InvalidWorkingDir,
+        }
+    }
+}
+
+/// How a process ended, for a session's error_reason
+fn describe_exit(exit_code: Option<u32>) -> String {
+    match exit_code {
+        // What shells exit with for a command they can't find
+        Some(127) => "was not found (exit code 127)".to_string(),
+        Some(code) => format!("exited with code {}", code),
+        None => "exited".to_string(),
+    }
+}
+
+/// Check that a session can run in `dir`, creating it first if `create` is set
+pub fn check_working_dir(dir: &Path, create: bool) -> Result<(), SessionCreateError> {
+    if !dir.exists() {
+        if !create {
+            return Err(SessionCreateError::DirNotFound(dir.to_path_buf()));
+        }
+        std::fs::create_dir_all(dir)
+            .map_err(|e| SessionCreateError::CreateDir(dir.to_path_buf(), e))?;
+    }
+    if !dir.is_dir() {
+        return Err(SessionCreateError::NotADirectory(dir.to_path_buf()));
+    }
+    std::fs::read_dir(dir).map_err(|e| SessionCreateError::DirUnreadable(dir.to_path_buf(), e))?;
+    Ok(())
+}
 
 pub struct SessionManager {
     state: SharedState,
@@ -26,22 +113,50 @@ pub struct SessionManager {
     hook_manager: Arc<HookManager>,
     /// Status trackers per session (using velocity-based detection)
     status_trackers: Arc<RwLock<HashMap<Uuid, StatusTracker>>>,
+    turn_tracker: Arc<TurnTracker>,
+    output_log: Arc<OutputLogger>,
+    recorder: Arc<Recorder>,
+    screens: Arc<Screens>,
+    approvals: Arc<Approvals>,
+    detached: Arc<Detached>,
+    config: SharedConfig,
+    /// Process exits reported by PTY reader threads
+    exit_rx: mpsc::UnboundedReceiver<PtyExit>,
 }
 
 impl SessionManager {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         state: SharedState,
         event_tx: broadcast::Sender<Event>,
         hook_manager: Arc<HookManager>,
+        turn_tracker: Arc<TurnTracker>,
+        output_log: Arc<OutputLogger>,
+        recorder: Arc<Recorder>,
+        screens: Arc<Screens>,
+        approvals: Arc<Approvals>,
+        detached: Arc<Detached>,
+        config: SharedConfig,
+        flow: Arc<FlowControl>,
     ) -> (Self, mpsc::Receiver<(Uuid, Vec<u8>)>) {
-        let (output_tx, output_rx) = mpsc::channel(1000);
+        // Bounded so a runaway command pauses its reader instead of growing memory
+        let (output_tx, output_rx) = mpsc::channel(flow.queue_capacity());
+        let (exit_tx, exit_rx) = mpsc::unbounded_channel();
         let manager = Self {
             state,
-            pty_manager: Arc::new(PtyManager::new()),
+            pty_manager: Arc::new(PtyManager::new(config.clone(), exit_tx, flow)),
             event_tx,
             output_tx,
             hook_manager,
             status_trackers: Arc::new(RwLock::new(HashMap::new())),
+            turn_tracker,
+            output_log,
+            recorder,
+            screens,
+            approvals,
+            detached,
+            config,
+            exit_rx,
         };
         (manager, output_rx)
     }
@@ -53,7 +168,7 @@ impl SessionManager {
     }
 
     pub async fn run(
-        self,
+        mut self,
         mut output_rx: mpsc::Receiver<(Uuid, Vec<u8>)>,
         mut hook_rx: mpsc::Receiver<HookEvent>,
     ) {
@@ -66,10 +181,60 @@ impl SessionManager {
             Self::idle_checker(idle_state, idle_event_tx).await;
         });
 
+        // Spawn background task to apply daemon.idle_timeout_mins
+        let inactivity_state = self.state.clone();
+        let inactivity_event_tx = self.event_tx.clone();
+        let inactivity_pty_manager = self.pty_manager.clone();
+        let inactivity_config = self.config.clone();
+        tokio::spawn(async move {
+            Self::inactivity_checker(
+                inactivity_state,
+                inactivity_event_tx,
+                inactivity_pty_manager,
+                inactivity_config,
+            )
+            .await;
+        });
+
+        // Spawn background task to follow Claude transcripts for turn state
+        let transcript_state = self.state.clone();
+        let transcript_event_tx = self.event_tx.clone();
+        let transcript_turns = self.turn_tracker.clone();
+        tokio::spawn(async move {
+            Self::transcript_watcher(transcript_state, transcript_event_tx, transcript_turns).await;
+        });
+
+        // Spawn background task to keep per-session git status current
+        let git_state = self.state.clone();
+        let git_event_tx = self.event_tx.clone();
+        tokio::spawn(async move {
+            Self::git_poller(git_state, git_event_tx).await;
+        });
+
+        // TUI redraws arrive as many tiny writes; they go out as one event per frame
+        let mut coalescer = OutputCoalescer::default();
+        let frame = tokio::time::sleep(OUTPUT_FRAME);
+        tokio::pin!(frame);
+
         loop {
             tokio::select! {
                 // Handle PTY output
                 Some((session_id, data)) = output_rx.recv() => {
+                    self.output_log.write(session_id, &data).await;
+                    self.recorder.write(session_id, &data);
+                    if self.screens.needs_start(session_id) {
+                        let (rows, cols) = self.pty_manager.size(session_id).await.unwrap_or((24, 80));
+                        self.screens.start(session_id, rows, cols);
+                    }
+                    self.screens.write(session_id, &data);
+                    if let Some(event) = self
+                        .screens
+                        .bottom_lines(session_id, approvals::DIALOG_LINES)
+                        .and_then(|bottom| self.approvals.update(session_id, &bottom))
+                    {
+                        self.publish_approval(event).await;
+                    }
+
                     // Convert to string for status detection (lossy is fine for pattern matching)
                     let text = String::from_utf8_lossy(&data);
 
@@ -86,8 +251,22 @@ impl SessionManager {
                     );
 
                     // Detect status changes with debouncing
-                    if let Some(detected_status) = claude::detect_status(&text) {
-                        self.handle_status_detection(session_id, detected_status)
+                    let detected_status = match claude::detect_status(&text) {
+                        // A chunk without busy indicators may just be output scrolling
+                        // past; only the input box or a dialog on screen means Waiting
+                        Some(SessionStatus::Waiting) => self
+                            .screens
+                            .bottom_lines(session_id, SCREEN_STATUS_LINES)
+                            .and_then(|bottom| claude::detect_screen_status(&bottom)),
+                        detected => detected,
+                    };
+                    if let Some(detected_status) = detected_status {
+                        let detail = match detected_status {
+                            SessionStatus::Error => claude::error_detail(&text),
+                            SessionStatus::Waiting => self.approvals.detail(session_id),
+                            _ => None,
+                        };
+                        self.handle_status_detection(session_id, detected_status, detail)
                             .await;
                     }
 
@@ -97,13 +276,17 @@ impl SessionManager {
                             .await;
                     }
 
-                    // Forward output as event
-                    let output = BASE64.encode(&data);
-                    let event = Event {
-                        event: "pty:output".to_string(),
-                        data: serde_json::to_value(PtyOutputData { session_id, output }).unwrap(),
-                    };
-                    let _ = self.event_tx.send(event);
+                    // Forward output as event once the frame ends
+                    if coalescer.is_empty() {
+                        frame.as_mut().reset(Instant::now() + OUTPUT_FRAME);
+                    }
+                    if let Some(batch) = coalescer.push(session_id, data) {
+                        self.flush_output(vec![(session_id, batch)]).await;
+                    }
+                }
+
+                () = &mut frame, if !coalescer.is_empty() => {
+                    self.flush_output(coalescer.drain()).await;
                 }
 
                 // Handle hook events (authoritative status from Claude hooks)
@@ -111,6 +294,14 @@ impl SessionManager {
                     self.handle_hook_event(hook_event).await;
                 }
 
+                Some(exit) = self.exit_rx.recv() => {
+                    // The last output goes out before the exit is reported
+                    if let Some(batch) = coalescer.take(exit.session_id) {
+                        self.flush_output(vec![(exit.session_id, batch)]).await;
+                    }
+                    self.handle_exit(exit).await;
+                }
+
                 // Both channels closed - exit
                 else => {
                     info!("Session manager channels closed, shutting down");
@@ -120,14 +311,147 @@ impl SessionManager {
         }
     }
 
-    async fn update_session_status(&self, session_id: Uuid, new_status: SessionStatus) {
+    /// Send an approval event, first answering a new dialog the session's
+    /// approval policy allows (sending session:auto_approved instead)
+    async fn publish_approval(&self, event: Event) {
+        if let Event::ApprovalRequested(data) = &event {
+            // A dialog the hook didn't describe is left to a person
+            let rule = if self
+                .approvals
+                .hook_described(data.session_id, data.approval_id)
+            {
+                self.auto_approve_rule(data).await
+            } else {
+                None
+            };
+            if let Some(rule) = rule {
+                if self.approvals.answer(data.session_id, data.approval_id)
+                    && self
+                        .pty_manager
+                        .write(data.session_id, approvals::APPROVE_KEYS)
+                        .await
+                        .is_ok()
+                {
+                    info!(
+                        "Auto-approved {} in session {} ({})",
+                        data.tool, data.session_id, rule
+                    );
+                    history::record(
+                        data.session_id,
+                        HistoryKind::AutoApproved {
+                            tool: data.tool.clone(),
+                            rule: rule.clone(),
+                        },
+                    );
+                    let _ = self.event_tx.send(Event::AutoApproved(AutoApprovedData {
+                        session_id: data.session_id,
+                        approval_id: data.approval_id,
+                        tool: data.tool.clone(),
+                        summary: data.summary.clone(),
+                        path: data.path.clone(),
+                        rule,
+                    }));
+                    return;
+                }
+            }
+        }
+        let _ = self.event_tx.send(event);
+    }
+
+    async fn auto_approve_rule(&self, data: &ApprovalRequestedData) -> Option<String> {
+        let s = self.state.read().await;
+        let session = s.sessions.get(&data.session_id)?;
+        let policies = policy::effective(&s, session);
+        let request = ApprovalRequest {
+            tool: &data.tool,
+            command: data.command.as_deref(),
+            path: data.path.as_deref(),
+        };
+        policy::matching_rule(&policies, &request, &session.working_dir)
+    }
+
+    /// Replace the approval policy of a session or group, once it parses
+    pub async fn set_policy(
+        state: &SharedState,
+        event_tx: &broadcast::Sender<Event>,
+        target: WatchTarget,
+        approval_policy: ApprovalPolicy,
+    ) -> Result<ApprovalPolicy> {
+        policy::validate(&approval_policy)?;
+        let event = {
+            let mut s = state.write().await;
+            match target {
+                WatchTarget::Session(id) => {
+                    let session = s.sessions.get_mut(&id).ok_or(SessionNotFound(id))?;
+                    session.approval_policy = approval_policy.clone();
+                    Event::SessionUpdated(session.clone())
+                }
+                WatchTarget::Group(id) => {
+                    let group = s.groups.get_mut(&id).ok_or(GroupNotFound(id))?;
+                    group.approval_policy = approval_policy.clone();
+                    Event::GroupUpdated(group.clone())
+                }
+            }
+        };
+        save_state(state).await?;
+        let _ = event_tx.send(event);
+        Ok(approval_policy)
+    }
+
+    fn send_output(&self, session_id: Uuid, data: &[u8]) {
+        if self.detached.spool(session_id, data) {
+            return;
+        }
+        let output = BASE64.encode(data);
+        let event = Event::PtyOutput(PtyOutputData { session_id, output });
+        let _ = self.event_tx.send(event);
+    }
+
+    /// Send a frame's output batches, then count them as unread with one
+    /// state lock for the frame rather than one per PTY chunk
+    async fn flush_output(&self, batches: Vec<(Uuid, Vec<u8>)>) {
+        for (session_id, batch) in &batches {
+            self.send_output(*session_id, batch);
+        }
+        let became_unread = add_unread(
+            &mut *self.state.write().await,
+            batches
+                .iter()
+                .map(|(session_id, batch)| (*session_id, batch.len())),
+        );
+        for data in became_unread {
+            let _ = self.event_tx.send(Event::Unread(data));
+        }
+    }
+
+    async fn update_session_status(
+        &self,
+        session_id: Uuid,
+        new_status: SessionStatus,
+        detail: Option<String>,
+    ) {
+        Self::apply_status(&self.state, &self.event_tx, session_id, new_status, detail).await;
+    }
+
+    /// Set a session's status and emit session:status_changed if it changed
+    ///
+    /// A status change replaces the session's status_detail with `detail`; a
+    /// new detail for the same status is sent too, while None leaves it be.
+    pub async fn apply_status(
+        state: &SharedState,
+        event_tx: &broadcast::Sender<Event>,
+        session_id: Uuid,
+        new_status: SessionStatus,
+        detail: Option<String>,
+    ) {
+        let changes = |session: &Session| {
+            session.status != new_status || (detail.is_some() && session.status_detail != detail)
+        };
+
         // First check with read lock to avoid write lock contention
         let needs_update = {
-            let s = self.state.read().await;
-            s.sessions
-                .get(&session_id)
-                .map(|session| session.status != new_status)
-                .unwrap_or(false)
+            let s = state.read().await;
+            s.sessions.get(&session_id).is_some_and(changes)
         };
 
         if !needs_update {
@@ -137,31 +461,38 @@ impl SessionManager {
         // Only acquire write lock if we actually need to update
         let mut status_changed = false;
         {
-            let mut s = self.state.write().await;
+            let mut s = state.write().await;
             if let Some(session) = s.sessions.get_mut(&session_id) {
+                if changes(session) {
+                    session.status_detail = detail.clone();
+                    status_changed = true;
+                }
                 if session.status != new_status {
                     debug!(
                         "Session {} status: {:?} -> {:?}",
                         session_id, session.status, new_status
                     );
+                    history::record(
+                        session_id,
+                        HistoryKind::StatusChanged {
+                            from: session.status,
+                            to: new_status,
+                        },
+                    );
                     session.status = new_status;
                     session.last_activity = Utc::now();
-                    status_changed = true;
                 }
             }
         }
 
         if status_changed {
             // Emit status change event
-            let event = Event {
-                event: "session:status_changed".to_string(),
-                data: serde_json::to_value(StatusChangedData {
-                    session_id,
-                    status: new_status,
-                })
-                .unwrap(),
-            };
-            let _ = self.event_tx.send(event);
+            let event = Event::StatusChanged(StatusChangedData {
+                session_id,
+                status: new_status,
+                detail,
+            });
+            let _ = event_tx.send(event);
         }
     }
 
@@ -171,7 +502,14 @@ impl SessionManager {
     /// - Transition TO Running is IMMEDIATE (user should see activity right away)
     /// - Transition FROM Running has a 2 second cooldown (prevent flapping during TUI updates)
     /// - This handles interleaved chunks where some have "esc to interrupt" and some don't
-    async fn handle_status_detection(&self, session_id: Uuid, detected_status: SessionStatus) {
+    ///
+    /// `detail` goes along only with the status it was found for.
+    async fn handle_status_detection(
+        &self,
+        session_id: Uuid,
+        detected_status: SessionStatus,
+        detail: Option<String>,
+    ) {
         // Get current session status
         let current_status = {
             let s = self.state.read().await;
@@ -188,9 +526,20 @@ impl SessionManager {
             .entry(session_id)
             .or_insert_with(|| StatusTracker::new(current_status));
 
-        if let Some(new_status) = tracker.handle_detected_status(current_status, detected_status) {
-            drop(trackers); // Release lock before async call
-            self.update_session_status(session_id, new_status).await;
+        match tracker.handle_detected_status(current_status, detected_status) {
+            Some(new_status) => {
+                drop(trackers); // Release lock before async call
+                let detail = detail.filter(|_| new_status == detected_status);
+                self.update_session_status(session_id, new_status, detail)
+                    .await;
+            }
+            // Same status, but maybe a new reason for it (another dialog)
+            None if detected_status == current_status && detail.is_some() => {
+                drop(trackers);
+                self.update_session_status(session_id, current_status, detail)
+                    .await;
+            }
+            None => {}
         }
     }
 
@@ -222,8 +571,88 @@ impl SessionManager {
             session_id, event.state, event.event
         );
 
+        let mut detail = None;
+        if event.event == "tool_approval" {
+            if let Some(tool) = &event.tool {
+                detail = Some(approvals::permission_detail(
+                    tool,
+                    event.command.as_deref().or(event.path.as_deref()),
+                ));
+            }
+            history::record(session_id, HistoryKind::ApprovalRequested);
+            self.approvals.hook_input(
+                session_id,
+                HookInput {
+                    tool: event.tool,
+                    path: event.path,
+                    command: event.command,
+                },
+            );
+        } else if event.event == "tool_complete" || event.event == "stopped" {
+            self.approvals.clear_hook_input(session_id);
+        }
+
         // Hook events are authoritative - bypass debouncing
-        self.update_session_status(session_id, new_status).await;
+        self.update_session_status(session_id, new_status, detail)
+            .await;
+    }
+
+    /// A session's process exited on its own or was killed
+    async fn handle_exit(&self, exit: PtyExit) {
+        info!(
+            "Session {} process {:?} exited with {:?}",
+            exit.session_id, exit.pid, exit.exit_code
+        );
+        self.output_log.close(exit.session_id);
+        self.screens.exited(exit.session_id);
+        self.approvals.remove(exit.session_id);
+        history::record(
+            exit.session_id,
+            HistoryKind::Exited {
+                exit_code: exit.exit_code,
+            },
+        );
+
+        // Ignore processes that were already stopped or replaced by a restart
+        let current = {
+            let mut s = self.state.write().await;
+            match s.sessions.get_mut(&exit.session_id) {
+                Some(session) if exit.pid.is_some() && session.pid == exit.pid => {
+                    session.pid = None;
+                    session.pid_start_time = None;
+                    true
+                }
+                _ => false,
+            }
+        };
+        if !current {
+            return;
+        }
+
+        let status = match exit.exit_code {
+            Some(0) => SessionStatus::Stopped,
+            _ => SessionStatus::Error,
+        };
+        let mut detail = None;
+        if status == SessionStatus::Error {
+            let reason = format!("claude {}", describe_exit(exit.exit_code));
+            let session = {
+                let mut s = self.state.write().await;
+                s.sessions.get_mut(&exit.session_id).map(|session| {
+                    session.error_reason = Some(reason.clone());
+                    session.clone()
+                })
+            };
+            if let Some(session) = session {
+                let _ = self.event_tx.send(Event::SessionUpdated(session));
+            }
+            detail = Some(reason);
+        }
+        self.update_session_status(exit.session_id, status, detail)
+            .await;
+        if let Err(e) = save_state(&self.state).await {
+            warn!("Failed to save state after session exit: {}", e);
+        }
     }
 
     async fn update_claude_session_id(&self, session_id: Uuid, claude_session_id: String) {
@@ -289,16 +718,14 @@ impl SessionManager {
                             session_id, IDLE_TIMEOUT_SECS
                         );
                         session.status = SessionStatus::Idle;
+                        session.status_detail = None;
 
                         // Emit status change event
-                        let event = Event {
-                            event: "session:status_changed".to_string(),
-                            data: serde_json::to_value(StatusChangedData {
-                                session_id,
-                                status: SessionStatus::Idle,
-                            })
-                            .unwrap(),
-                        };
+                        let event = Event::StatusChanged(StatusChangedData {
+                            session_id,
+                            status: SessionStatus::Idle,
+                            detail: None,
+                        });
                         let _ = event_tx.send(event);
                     }
                 }
@@ -306,6 +733,241 @@ impl SessionManager {
         }
     }
 
+    /// Background task that times out sessions with no PTY output or input for
+    /// daemon.idle_timeout_mins: they go Idle, are stopped if daemon.idle_stop is
+    /// set, and session:idle_timeout is emitted once per quiet spell
+    async fn inactivity_checker(
+        state: SharedState,
+        event_tx: broadcast::Sender<Event>,
+        pty_manager: Arc<PtyManager>,
+        config: SharedConfig,
+    ) {
+        const CHECK_INTERVAL_SECS: u64 = 30;
+
+        // Sessions already timed out, until they see output or input again
+        let mut timed_out: HashSet<Uuid> = HashSet::new();
+
+        loop {
+            tokio::time::sleep(tokio::time::Duration::from_secs(CHECK_INTERVAL_SECS)).await;
+
+            let (timeout_mins, stop) = {
+                let config = config.read().await;
+                (config.daemon.idle_timeout_mins, config.daemon.idle_stop)
+            };
+            if timeout_mins == 0 {
+                timed_out.clear();
+                continue;
+            }
+
+            Self::time_out_idle(
+                &state,
+                &event_tx,
+                &pty_manager,
+                timeout_mins * 60,
+                stop,
+                &mut timed_out,
+                Utc::now(),
+            )
+            .await;
+        }
+    }
+
+    /// One pass of inactivity_checker: time out the live sessions with no PTY
+    /// output or input for `timeout_secs` as of `now`, except those in
+    /// `timed_out` already
+    async fn time_out_idle(
+        state: &SharedState,
+        event_tx: &broadcast::Sender<Event>,
+        pty_manager: &PtyManager,
+        timeout_secs: u64,
+        stop: bool,
+        timed_out: &mut HashSet<Uuid>,
+        now: DateTime<Utc>,
+    ) {
+        let live: Vec<Uuid> = {
+            let s = state.read().await;
+            s.sessions
+                .values()
+                .filter(|session| {
+                    matches!(
+                        session.status,
+                        SessionStatus::Running | SessionStatus::Waiting | SessionStatus::Idle
+                    )
+                })
+                .map(|session| session.id)
+                .collect()
+        };
+        timed_out.retain(|id| live.contains(id));
+
+        for session_id in live {
+            let Some(last_io) = pty_manager.last_io(session_id).await else {
+                continue;
+            };
+            let idle_secs = now.signed_duration_since(last_io).num_seconds().max(0) as u64;
+            if idle_secs < timeout_secs {
+                timed_out.remove(&session_id);
+                continue;
+            }
+            if !timed_out.insert(session_id) {
+                continue;
+            }
+
+            info!(
+                "Session {} idle for {}s, past idle_timeout_mins",
+                session_id, idle_secs
+            );
+            Self::apply_status(state, event_tx, session_id, SessionStatus::Idle, None).await;
+            let stopped = stop
+                && match Self::stop_session(
+                    state,
+                    pty_manager,
+                    event_tx,
+                    session_id,
+                    KILL_GRACE,
+                    false,
+                )
+                .await
+                {
+                    Ok(_) => true,
+                    Err(e) => {
+                        warn!("Failed to stop idle session {}: {}", session_id, e);
+                        false
+                    }
+                };
+
+            let _ = event_tx.send(Event::IdleTimeout(IdleTimeoutData {
+                session_id,
+                idle_secs,
+                stopped,
+            }));
+        }
+    }
+
+    /// Background task that follows the Claude transcript of each live session
+    /// Turn state from the transcript is authoritative, like hook events, so it
+    /// is applied directly whenever it changes
+    async fn transcript_watcher(
+        state: SharedState,
+        event_tx: broadcast::Sender<Event>,
+        turn_tracker: Arc<TurnTracker>,
+    ) {
+        const POLL_INTERVAL_MS: u64 = 1000;
+
+        struct Watch {
+            claude_session_id: String,
+            path: PathBuf,
+            len: u64,
+            state: Option<TranscriptState>,
+        }
+        let mut watches: HashMap<Uuid, Watch> = HashMap::new();
+
+        loop {
+            tokio::time::sleep(tokio::time::Duration::from_millis(POLL_INTERVAL_MS)).await;
+
+            let live: Vec<(Uuid, String)> = {
+                let s = state.read().await;
+                s.sessions
+                    .values()
+                    .filter(|session| session.status != SessionStatus::Stopped)
+                    .filter_map(|session| {
+                        session
+                            .claude_session_id
+                            .clone()
+                            .map(|claude_id| (session.id, claude_id))
+                    })
+                    .collect()
+            };
+            watches.retain(|id, watch| {
+                live.iter().any(|(live_id, claude_id)| {
+                    live_id == id && *claude_id == watch.claude_session_id
+                })
+            });
+
+            for (session_id, claude_session_id) in live {
+                let watch = match watches.entry(session_id) {
+                    Entry::Occupied(entry) => entry.into_mut(),
+                    Entry::Vacant(entry) => {
+                        let Some(path) = transcript::find_transcript(&claude_session_id) else {
+                            continue;
+                        };
+                        debug!("Session {} transcript: {:?}", session_id, path);
+                        entry.insert(Watch {
+                            claude_session_id,
+                            path,
+                            len: 0,
+                            state: None,
+                        })
+                    }
+                };
+
+                let Ok(meta) = std::fs::metadata(&watch.path) else {
+                    continue;
+                };
+                if meta.len() == watch.len {
+                    continue;
+                }
+                watch.len = meta.len();
+
+                let tail = transcript::tail(&watch.path);
+                if let Some((message, _)) = tail.as_ref().filter(|(m, _)| m.role == "assistant") {
+                    turn_tracker
+                        .first_output(session_id, message.timestamp)
+                        .await;
+                }
+                let new_state = tail.map(|(_, state)| state);
+                if new_state != watch.state {
+                    debug!("Session {} transcript state: {:?}", session_id, new_state);
+                    watch.state = new_state;
+                    if let Some(status) = new_state.and_then(transcript::status_for) {
+                        Self::apply_status(&state, &event_tx, session_id, status, None).await;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Background task that polls each session's working directory for git state
+    /// Emits session:git_changed when branch, dirty count or ahead/behind changes
+    async fn git_poller(state: SharedState, event_tx: broadcast::Sender<Event>) {
+        const POLL_INTERVAL_SECS: u64 = 5;
+
+        loop {
+            tokio::time::sleep(tokio::time::Duration::from_secs(POLL_INTERVAL_SECS)).await;
+
+            let dirs: Vec<(Uuid, PathBuf)> = {
+                let s = state.read().await;
+                s.sessions
+                    .values()
+                    .map(|session| (session.id, session.working_dir.clone()))
+                    .collect()
+            };
+
+            for (session_id, dir) in dirs {
+                let git_status = git::status(&dir).await;
+
+                let changed = {
+                    let mut s = state.write().await;
+                    match s.sessions.get_mut(&session_id) {
+                        Some(session) if session.git != git_status => {
+                            session.git = git_status.clone();
+                            true
+                        }
+                        _ => false,
+                    }
+                };
+
+                if changed {
+                    debug!("Session {} git status: {:?}", session_id, git_status);
+                    let event = Event::GitChanged(GitChangedData {
+                        session_id,
+                        git: git_status,
+                    });
+                    let _ = event_tx.send(event);
+                }
+            }
+        }
+    }
+
     pub fn pty_manager(&self) -> Arc<PtyManager> {
         self.pty_manager.clone()
     }
@@ -324,6 +986,7 @@ impl SessionManager {
         self.event_tx.clone()
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub async fn create_session(
         state: &SharedState,
         _pty_manager: &PtyManager,
@@ -332,11 +995,29 @@ impl SessionManager {
         name: String,
         working_dir: PathBuf,
         group_id: Option<Uuid>,
+        spawn_mode: Option<SpawnMode>,
+        claude_binary: Option<String>,
+        flags: ClaudeFlags,
+        create_dir: bool,
+        size: Option<TerminalSize>,
     ) -> Result<Session> {
-        let session = Session::new(name, working_dir.clone(), group_id);
+        check_working_dir(&working_dir, create_dir)?;
+        let mut session = Session::new(name, working_dir.clone(), group_id);
+        session.spawn_mode = spawn_mode;
+        session.claude_binary = claude_binary;
+        session.flags = flags;
+        session.size = size;
+        history::record(
+            session.id,
+            HistoryKind::Created {
+                name: session.name.clone(),
+                forked_from: None,
+            },
+        );
         // Note: Session is created in "stopped" state by default
         // The PTY is NOT spawned here - it will be spawned when the terminal
-        // is ready and calls restart_session with proper dimensions
+        // is ready and calls restart_session with proper dimensions (or at
+        // `size` when the start doesn't give any)
 
         // Save to state
         {
@@ -346,42 +1027,106 @@ impl SessionManager {
         save_state(state).await?;
 
         // Emit event
-        let event = Event {
-            event: "session:created".to_string(),
-            data: serde_json::to_value(&session)?,
-        };
+        let event = Event::SessionCreated(session.clone());
+        let _ = event_tx.send(event);
+
+        Ok(session)
+    }
+
+    /// Create a git worktree for `branch` and a session whose working directory is
+    /// that worktree. Like `create_session`, the PTY is spawned later by restart.
+    pub async fn create_worktree_session(
+        state: &SharedState,
+        event_tx: &broadcast::Sender<Event>,
+        name: String,
+        repo_path: PathBuf,
+        branch: String,
+        worktree_path: Option<PathBuf>,
+        group_id: Option<Uuid>,
+    ) -> Result<Session> {
+        let info = worktree::add(&repo_path, &branch, worktree_path).await?;
+
+        let mut session = Session::new(name, info.path.clone(), group_id);
+        session.worktree = Some(info);
+        history::record(
+            session.id,
+            HistoryKind::Created {
+                name: session.name.clone(),
+                forked_from: None,
+            },
+        );
+
+        {
+            let mut s = state.write().await;
+            s.sessions.insert(session.id, session.clone());
+        }
+        save_state(state).await?;
+
+        let event = Event::SessionCreated(session.clone());
         let _ = event_tx.send(event);
 
         Ok(session)
     }
 
+    /// Stop the session's process, giving it `grace` to exit on SIGTERM before
+    /// it is killed (no time with `force`)
     pub async fn stop_session(
         state: &SharedState,
         pty_manager: &PtyManager,
         event_tx: &broadcast::Sender<Event>,
         session_id: Uuid,
-    ) -> Result<()> {
-        pty_manager.kill(session_id).await?;
+        grace: Duration,
+        force: bool,
+    ) -> Result<StopOutcome> {
+        let outcome = pty_manager.stop(session_id, grace, force).await?;
 
         {
             let mut s = state.write().await;
             if let Some(session) = s.sessions.get_mut(&session_id) {
+                if session.status != SessionStatus::Stopped {
+                    history::record(
+                        session_id,
+                        HistoryKind::StatusChanged {
+                            from: session.status,
+                            to: SessionStatus::Stopped,
+                        },
+                    );
+                }
                 session.status = SessionStatus::Stopped;
+                session.status_detail = None;
                 session.pid = None;
+                session.pid_start_time = None;
             }
         }
         save_state(state).await?;
 
-        let event = Event {
-            event: "session:status_changed".to_string(),
-            data: serde_json::to_value(StatusChangedData {
-                session_id,
-                status: SessionStatus::Stopped,
-            })?,
-        };
+        let event = Event::StatusChanged(StatusChangedData {
+            session_id,
+            status: SessionStatus::Stopped,
+            detail: None,
+        });
         let _ = event_tx.send(event);
 
-        Ok(())
+        Ok(outcome)
+    }
+
+    /// Stop every session that has a running process (used on daemon shutdown)
+    pub async fn stop_all(
+        state: &SharedState,
+        pty_manager: &PtyManager,
+        event_tx: &broadcast::Sender<Event>,
+    ) {
+        let session_ids: Vec<Uuid> = state.read().await.sessions.keys().copied().collect();
+        for session_id in session_ids {
+            if pty_manager.is_alive(session_id).await {
+                if let Err(e) =
+                    Self::stop_session(state, pty_manager, event_tx, session_id, KILL_GRACE, false)
+                        .await
+                {
+                    warn!("Failed to stop session {}: {}", session_id, e);
+                }
+            }
+        }
     }
 
     #[allow(clippy::too_many_arguments)]
@@ -394,16 +1139,29 @@ impl SessionManager {
         source_session_id: Uuid,
         new_name: Option<String>,
         new_group_id: Option<Uuid>,
-        rows: u16,
-        cols: u16,
+        size: Option<TerminalSize>,
+        worktree_branch: Option<String>,
+        worktree_path: Option<PathBuf>,
     ) -> Result<Session> {
         // Get source session info
-        let (working_dir, claude_session_id, group_id, source_name) = {
+        let (
+            working_dir,
+            claude_session_id,
+            group_id,
+            source_name,
+            spawn_mode,
+            tags,
+            watches,
+            approval_policy,
+            flags,
+            claude_binary,
+            source_size,
+        ) = {
             let s = state.read().await;
             let source = s
                 .sessions
                 .get(&source_session_id)
-                .ok_or_else(|| anyhow::anyhow!("Source session not found"))?;
+                .ok_or(SessionNotFound(source_session_id))?;
 
             let claude_id = source.claude_session_id.clone().ok_or_else(|| {
                 anyhow::anyhow!("Source session has no Claude session ID - cannot fork")
@@ -414,20 +1172,53 @@ impl SessionManager {
                 claude_id,
                 source.group_id,
                 source.name.clone(),
+                source.spawn_mode,
+                source.tags.clone(),
+                source.watches.clone(),
+                source.approval_policy.clone(),
+                source.flags.clone(),
+                source.claude_binary.clone(),
+                source.size,
             )
         };
 
+        // A worktree fork continues the conversation in its own checkout, which
+        // needs its own copy of the transcript for --resume to find
+        let worktree = match worktree_branch {
+            Some(branch) => {
+                let info = worktree::fork(&working_dir, &branch, worktree_path).await?;
+                if let Err(e) = transcript::copy_to_project(&claude_session_id, &info.path) {
+                    worktree::discard(&info).await;
+                    return Err(e);
+                }
+                Some(info)
+            }
+            None => None,
+        };
+        let working_dir = worktree
+            .as_ref()
+            .map_or(working_dir, |info| info.path.clone());
+
         // Create new session with forked name
         let name = new_name.unwrap_or_else(|| format!("{} (Fork)", source_name));
 
         let mut session = Session::new(name, working_dir.clone(), new_group_id.or(group_id));
+        session.worktree = worktree;
+        session.spawn_mode = spawn_mode;
+        session.tags = tags;
+        session.watches = watches;
+        session.approval_policy = approval_policy;
+        session.flags = flags;
+        session.claude_binary = claude_binary;
+        let TerminalSize { rows, cols } = size.or(source_size).unwrap_or_default();
+        session.size = Some(TerminalSize { rows, cols });
 
         // Get hook environment variables for this session
         let hook_env = hook_manager.get_env_vars(&session.id.to_string());
 
         // Spawn PTY with --resume flag using provided dimensions
         info!("Spawning forked PTY with size {}x{}", cols, rows);
-        pty_manager
+        let spawned = pty_manager
             .spawn_with_resume(
                 session.id,
                 &working_dir,
@@ -435,11 +1226,39 @@ impl SessionManager {
                 cols,
                 output_tx,
                 Some(&claude_session_id),
+                &session.flags.args(),
                 hook_env,
+                spawn_mode,
+                session.claude_binary.as_deref(),
             )
-            .await?;
+            .await;
+        let pid = match spawned {
+            Ok(pid) => pid,
+            Err(e) => {
+                if let Some(info) = &session.worktree {
+                    worktree::discard(info).await;
+                }
+                return Err(e);
+            }
+        };
 
+        history::record(
+            session.id,
+            HistoryKind::Created {
+                name: session.name.clone(),
+                forked_from: Some(source_session_id),
+            },
+        );
+        history::record(
+            session.id,
+            HistoryKind::StatusChanged {
+                from: session.status,
+                to: SessionStatus::Running,
+            },
+        );
         session.status = SessionStatus::Running;
+        session.pid = pid;
+        session.pid_start_time = pid.and_then(orphans::start_time);
         session.claude_session_id = Some(claude_session_id);
         session.last_activity = Utc::now();
 
@@ -451,10 +1270,7 @@ impl SessionManager {
         save_state(state).await?;
 
         // Emit event
-        let event = Event {
-            event: "session:created".to_string(),
-            data: serde_json::to_value(&session)?,
-        };
+        let event = Event::SessionCreated(session.clone());
         let _ = event_tx.send(event);
 
         info!(
@@ -467,6 +1283,10 @@ impl SessionManager {
         Ok(session)
     }
 
+    /// (Re)spawn a session's PTY at `size`, or else its last known size; with
+    /// `resume` the previous Claude conversation is continued via --resume
+    /// when one is known
+    #[allow(clippy::too_many_arguments)]
     pub async fn restart_session(
         state: &SharedState,
         pty_manager: &PtyManager,
@@ -474,22 +1294,45 @@ impl SessionManager {
         event_tx: &broadcast::Sender<Event>,
         hook_manager: &HookManager,
         session_id: Uuid,
-        rows: u16,
-        cols: u16,
+        size: Option<TerminalSize>,
+        resume: bool,
     ) -> Result<Session> {
         // Get session info
-        let working_dir = {
+        let (working_dir, claude_session_id, old_pid, spawn_mode, flags, claude_binary, size) = {
             let s = state.read().await;
             let session = s
                 .sessions
                 .get(&session_id)
-                .ok_or_else(|| anyhow::anyhow!("Session not found"))?;
-            session.working_dir.clone()
+                .ok_or(SessionNotFound(session_id))?;
+            (
+                session.working_dir.clone(),
+                session.claude_session_id.clone(),
+                session.pid.zip(session.pid_start_time),
+                session.spawn_mode,
+                session.flags.clone(),
+                session.claude_binary.clone(),
+                size.or(session.size).unwrap_or_default(),
+            )
         };
+        let TerminalSize { rows, cols } = size;
+        // Only resume when asked to and there is a conversation to resume
+        let resume_session_id = claude_session_id.filter(|_| resume);
+        // The directory may have gone since the session was created; claude
+        // would exit at once and leave a dead session behind
+        if let Err(e) = check_working_dir(&working_dir, false) {
+            Self::start_failed(state, event_tx, session_id, e.to_string()).await;
+            return Err(e.into());
+        }
 
         // Stop if running
         if pty_manager.is_alive(session_id).await {
             pty_manager.kill(session_id).await?;
+        } else if let Some((pid, started)) = old_pid {
+            // An adopted orphan from a previous daemon run would otherwise keep
+            // working on the same conversation
+            if orphans::kill(pid, started).await {
+                info!("Killed adopted orphan {} for session {}", pid, session_id);
+            }
         }
 
         // Get hook environment variables for this session
@@ -499,77 +1342,228 @@ impl SessionManager {
         // This is critical - Claude Code checks terminal size at startup
         // to decide whether to use full TUI mode with alternate screen buffer
         info!("Spawning PTY with size {}x{}", cols, rows);
-        pty_manager
-            .spawn(session_id, &working_dir, rows, cols, output_tx, hook_env)
-            .await?;
-
-        // Update session state
-        let session = {
+        Self::apply_status(state, event_tx, session_id, SessionStatus::Starting, None).await;
+        let spawned = pty_manager
+            .spawn_with_resume(
+                session_id,
+                &working_dir,
+                rows,
+                cols,
+                output_tx,
+                resume_session_id.as_deref(),
+                &flags.args(),
+                hook_env,
+                spawn_mode,
+                claude_binary.as_deref(),
+            )
+            .await;
+        let pid = match spawned {
+            Ok(pid) => pid,
+            Err(e) => {
+                let e = SessionCreateError::Spawn(e);
+                Self::start_failed(state, event_tx, session_id, e.to_string()).await;
+                return Err(e.into());
+            }
+        };
+
+        {
             let mut s = state.write().await;
             let session = s
                 .sessions
                 .get_mut(&session_id)
-                .ok_or_else(|| anyhow::anyhow!("Session not found"))?;
-            session.status = SessionStatus::Running;
+                .ok_or(SessionNotFound(session_id))?;
+            session.pid = pid;
+            session.pid_start_time = pid.and_then(orphans::start_time);
             session.last_activity = Utc::now();
-            session.clone()
-        };
+            session.size = Some(size);
+            session.error_reason = None;
+        }
         save_state(state).await?;
 
-        // Emit status changed event
-        let event = Event {
-            event: "session:status_changed".to_string(),
-            data: serde_json::to_value(StatusChangedData {
-                session_id,
-                status: SessionStatus::Running,
-            })?,
-        };
-        let _ = event_tx.send(event);
+        // Report it Running once claude is up, unless its output already moved
+        // it on (e.g. to Waiting)
+        match pty_manager.wait_started(session_id).await {
+            Started::Output => {}
+            Started::TimedOut => warn!(
+                "Session {} has written nothing since it was spawned; reporting it Running",
+                session_id
+            ),
+            Started::Exited(exit_code) => {
+                let e = SessionCreateError::ExitedEarly(describe_exit(exit_code));
+                Self::start_failed(state, event_tx, session_id, e.to_string()).await;
+                return Err(e.into());
+            }
+        }
+        let starting = state
+            .read()
+            .await
+            .sessions
+            .get(&session_id)
+            .is_some_and(|session| session.status == SessionStatus::Starting);
+        if starting {
+            Self::apply_status(state, event_tx, session_id, SessionStatus::Running, None).await;
+        }
 
         info!("Restarted session {}", session_id);
-
+        let session = state
+            .read()
+            .await
+            .sessions
+            .get(&session_id)
+            .cloned()
+            .ok_or(SessionNotFound(session_id))?;
         Ok(session)
     }
 
+    /// Put a session that failed to start in Error, saying why
+    async fn start_failed(
+        state: &SharedState,
+        event_tx: &broadcast::Sender<Event>,
+        session_id: Uuid,
+        reason: String,
+    ) {
+        warn!("Session {} failed to start: {}", session_id, reason);
+        let session = {
+            let mut s = state.write().await;
+            s.sessions.get_mut(&session_id).map(|session| {
+                session.pid = None;
+                session.pid_start_time = None;
+                session.error_reason = Some(reason.clone());
+                session.clone()
+            })
+        };
+        if let Some(session) = session {
+            let _ = event_tx.send(Event::SessionUpdated(session));
+        }
+        Self::apply_status(
+            state,
+            event_tx,
+            session_id,
+            SessionStatus::Error,
+            Some(reason),
+        )
+        .await;
+        if let Err(e) = save_state(state).await {
+            warn!("Failed to save state after a failed start: {}", e);
+        }
+    }
+
     pub async fn delete_session(
         state: &SharedState,
         pty_manager: &PtyManager,
         event_tx: &broadcast::Sender<Event>,
         session_id: Uuid,
+        remove_worktree: bool,
     ) -> Result<()> {
         // Stop first if running
         if pty_manager.is_alive(session_id).await {
             pty_manager.kill(session_id).await?;
         }
 
-        {
-            let mut s = state.write().await;
-            s.sessions.remove(&session_id);
+        // Remove the worktree before forgetting the session, so a failure
+        // (e.g. uncommitted changes) leaves the session around to retry
+        if remove_worktree {
+            let info = {
+                let s = state.read().await;
+                s.sessions.get(&session_id).and_then(|s| s.worktree.clone())
+            };
+            if let Some(info) = info {
+                worktree::remove(&info).await?;
+            }
         }
-        save_state(state).await?;
 
-        let event = Event {
-            event: "session:deleted".to_string(),
-            data: serde_json::json!({"session_id": session_id}),
+        let removed = {
+            let mut s = state.write().await;
+            s.input_history.remove(&session_id);
+            s.sessions.remove(&session_id)
         };
+        save_state(state).await?;
+        if let Some(session) = removed {
+            // Reads its history and appends to the archive file
+            tokio::task::spawn_blocking(move || archive::record(&session));
+        }
+
+        let event = Event::SessionDeleted { session_id };
         let _ = event_tx.send(event);
 
         Ok(())
     }
 
+    /// Move one or more sessions into a group (None = root level)
+    /// Moved sessions are appended after the group's existing sessions, keeping
+    /// their relative order. The PTYs are untouched, so running sessions keep going.
+    pub async fn move_sessions(
+        state: &SharedState,
+        event_tx: &broadcast::Sender<Event>,
+        session_ids: Vec<Uuid>,
+        group_id: Option<Uuid>,
+    ) -> Result<Vec<Session>> {
+        let mut moved = Vec::new();
+        {
+            let mut s = state.write().await;
+
+            // Validate everything up front so a bad id doesn't leave a partial move
+            if let Some(gid) = group_id {
+                if !s.groups.contains_key(&gid) {
+                    return Err(GroupNotFound(gid).into());
+                }
+            }
+            if let Some(missing) = session_ids.iter().find(|id| !s.sessions.contains_key(id)) {
+                return Err(SessionNotFound(*missing).into());
+            }
+
+            let mut next_order = s
+                .sessions
+                .values()
+                .filter(|sess| sess.group_id == group_id && !session_ids.contains(&sess.id))
+                .map(|sess| sess.order + 1)
+                .max()
+                .unwrap_or(0);
+
+            for session_id in &session_ids {
+                if let Some(session) = s.sessions.get_mut(session_id) {
+                    let from_group_id = session.group_id;
+                    session.group_id = group_id;
+                    session.order = next_order;
+                    next_order += 1;
+                    moved.push((session.clone(), from_group_id));
+                }
+            }
+        }
+        save_state(state).await?;
+
+        for (session, from_group_id) in &moved {
+            history::record(
+                session.id,
+                HistoryKind::Moved {
+                    from_group_id: *from_group_id,
+                    to_group_id: group_id,
+                },
+            );
+            let event = Event::SessionMoved(SessionMovedData {
+                session: session.clone(),
+                from_group_id: *from_group_id,
+            });
+            let _ = event_tx.send(event);
+        }
+
+        Ok(moved.into_iter().map(|(session, _)| session).collect())
+    }
+
     pub async fn update_session(
         state: &SharedState,
         event_tx: &broadcast::Sender<Event>,
         session_id: Uuid,
         name: Option<String>,
         group_id: Option<Option<Uuid>>, // None = don't change, Some(None) = remove from group, Some(Some(id)) = set group
+        spawn_mode: Option<Option<SpawnMode>>, // None = don't change, Some(None) = use the config default
     ) -> Result<Session> {
         let session = {
             let mut s = state.write().await;
             let session = s
                 .sessions
                 .get_mut(&session_id)
-                .ok_or_else(|| anyhow::anyhow!("Session not found"))?;
+                .ok_or(SessionNotFound(session_id))?;
 
             if let Some(new_name) = name {
                 session.name = new_name;
@@ -577,20 +1571,182 @@ impl SessionManager {
             if let Some(new_group_id) = group_id {
                 session.group_id = new_group_id;
             }
+            if let Some(new_spawn_mode) = spawn_mode {
+                session.spawn_mode = new_spawn_mode;
+            }
 
             session.clone()
         };
         save_state(state).await?;
 
-        let event = Event {
-            event: "session:updated".to_string(),
-            data: serde_json::to_value(&session)?,
+        let event = Event::SessionUpdated(session.clone());
+        let _ = event_tx.send(event);
+
+        Ok(session)
+    }
+
+    /// Tag a session; tags are trimmed and a session carries each at most once
+    pub async fn add_tag(
+        state: &SharedState,
+        event_tx: &broadcast::Sender<Event>,
+        session_id: Uuid,
+        tag: &str,
+    ) -> Result<Session> {
+        let tag = tag.trim();
+        if tag.is_empty() {
+            anyhow::bail!("Tag is empty");
+        }
+        Self::edit_tags(state, event_tx, session_id, |tags| {
+            if !tags.iter().any(|t| t == tag) {
+                tags.push(tag.to_string());
+            }
+        })
+        .await
+    }
+
+    pub async fn remove_tag(
+        state: &SharedState,
+        event_tx: &broadcast::Sender<Event>,
+        session_id: Uuid,
+        tag: &str,
+    ) -> Result<Session> {
+        let tag = tag.trim();
+        Self::edit_tags(state, event_tx, session_id, |tags| {
+            tags.retain(|t| t != tag)
+        })
+        .await
+    }
+
+    async fn edit_tags(
+        state: &SharedState,
+        event_tx: &broadcast::Sender<Event>,
+        session_id: Uuid,
+        edit: impl FnOnce(&mut Vec<String>),
+    ) -> Result<Session> {
+        let session = {
+            let mut s = state.write().await;
+            let session = s
+                .sessions
+                .get_mut(&session_id)
+                .ok_or(SessionNotFound(session_id))?;
+            edit(&mut session.tags);
+            session.clone()
         };
+        save_state(state).await?;
+
+        let event = Event::SessionUpdated(session.clone());
         let _ = event_tx.send(event);
 
         Ok(session)
     }
 
+    /// Flag a session as detached (or attached again) for clients
+    pub async fn set_detached(
+        state: &SharedState,
+        event_tx: &broadcast::Sender<Event>,
+        session_id: Uuid,
+        detached: bool,
+    ) -> Result<Session> {
+        let session = {
+            let mut s = state.write().await;
+            let session = s
+                .sessions
+                .get_mut(&session_id)
+                .ok_or(SessionNotFound(session_id))?;
+            session.detached = detached;
+            session.clone()
+        };
+        save_state(state).await?;
+        let _ = event_tx.send(Event::SessionUpdated(session.clone()));
+        Ok(session)
+    }
+
+    /// A client has shown the session's output: clear its unread_bytes in
+    /// every client
+    pub async fn mark_viewed(
+        state: &SharedState,
+        event_tx: &broadcast::Sender<Event>,
+        session_id: Uuid,
+    ) -> Result<Session> {
+        let (session, was_unread) = {
+            let mut s = state.write().await;
+            let session = s
+                .sessions
+                .get_mut(&session_id)
+                .ok_or(SessionNotFound(session_id))?;
+            let was_unread = session.unread_bytes > 0;
+            session.unread_bytes = 0;
+            (session.clone(), was_unread)
+        };
+        if was_unread {
+            save_state(state).await?;
+            let _ = event_tx.send(Event::Unread(UnreadData {
+                session_id,
+                unread_bytes: 0,
+            }));
+        }
+        Ok(session)
+    }
+
+    /// Attach a watch expression to a session or group, once it parses
+    pub async fn add_watch(
+        state: &SharedState,
+        event_tx: &broadcast::Sender<Event>,
+        target: WatchTarget,
+        expr: &str,
+    ) -> Result<Vec<String>> {
+        let expr = expr.trim();
+        Watch::parse(expr).map_err(|e| anyhow::anyhow!("Invalid watch expression: {}", e))?;
+        Self::edit_watches(state, event_tx, target, |watches| {
+            if !watches.iter().any(|w| w == expr) {
+                watches.push(expr.to_string());
+            }
+        })
+        .await
+    }
+
+    pub async fn remove_watch(
+        state: &SharedState,
+        event_tx: &broadcast::Sender<Event>,
+        target: WatchTarget,
+        expr: &str,
+    ) -> Result<Vec<String>> {
+        let expr = expr.trim();
+        Self::edit_watches(state, event_tx, target, |watches| {
+            watches.retain(|w| w != expr)
+        })
+        .await
+    }
+
+    async fn edit_watches(
+        state: &SharedState,
+        event_tx: &broadcast::Sender<Event>,
+        target: WatchTarget,
+        edit: impl FnOnce(&mut Vec<String>),
+    ) -> Result<Vec<String>> {
+        let (watches, event) = {
+            let mut s = state.write().await;
+            match target {
+                WatchTarget::Session(id) => {
+                    let session = s.sessions.get_mut(&id).ok_or(SessionNotFound(id))?;
+                    edit(&mut session.watches);
+                    let event = Event::SessionUpdated(session.clone());
+                    (session.watches.clone(), event)
+                }
+                WatchTarget::Group(id) => {
+                    let group = s.groups.get_mut(&id).ok_or(GroupNotFound(id))?;
+                    edit(&mut group.watches);
+                    let event = Event::GroupUpdated(group.clone());
+                    (group.watches.clone(), event)
+                }
+            }
+        };
+        save_state(state).await?;
+        let _ = event_tx.send(event);
+
+        Ok(watches)
+    }
+
     pub async fn create_group(
         state: &SharedState,
         event_tx: &broadcast::Sender<Event>,
@@ -605,20 +1761,122 @@ impl SessionManager {
         }
         save_state(state).await?;
 
-        let event = Event {
-            event: "group:created".to_string(),
-            data: serde_json::to_value(&group)?,
-        };
+        let event = Event::GroupCreated(group.clone());
         let _ = event_tx.send(event);
 
         Ok(group)
     }
 
+    /// Top-level group named after the repository containing `dir`, created if
+    /// needed (None if `dir` isn't in a git repository)
+    pub async fn repo_group(
+        state: &SharedState,
+        event_tx: &broadcast::Sender<Event>,
+        dir: &Path,
+    ) -> Result<Option<Uuid>> {
+        let Some(root) = git::toplevel(dir).await else {
+            return Ok(None);
+        };
+        let Some(name) = root.file_name().map(|n| n.to_string_lossy().to_string()) else {
+            return Ok(None);
+        };
+
+        // Look up and insert under one lock so concurrent creates share a group
+        let group = {
+            let mut s = state.write().await;
+            if let Some(group) = s
+                .groups
+                .values()
+                .find(|group| group.parent_id.is_none() && group.name == name)
+            {
+                return Ok(Some(group.id));
+            }
+            let group = Group::new(name, None);
+            s.groups.insert(group.id, group.clone());
+            group
+        };
+        save_state(state).await?;
+        info!("Created group {:?} for repository {:?}", group.name, root);
+
+        let event = Event::GroupCreated(group.clone());
+        let _ = event_tx.send(event);
+
+        Ok(Some(group.id))
+    }
+
+    /// Delete a group, moving its contents out or deleting them with it (see
+    /// `GroupDeleteMode`); with `dry_run` only report what that would affect
     pub async fn delete_group(
         state: &SharedState,
+        pty_manager: &PtyManager,
         event_tx: &broadcast::Sender<Event>,
         group_id: Uuid,
-    ) -> Result<()> {
+        mode: GroupDeleteMode,
+        dry_run: bool,
+    ) -> Result<GroupDeleteResult> {
+        let (groups, sessions) = {
+            let s = state.read().await;
+            if !s.groups.contains_key(&group_id) {
+                return Err(GroupNotFound(group_id).into());
+            }
+            // Parents before their subgroups
+            let mut groups = vec![group_id];
+            if mode == GroupDeleteMode::Cascade {
+                let mut i = 0;
+                while i < groups.len() {
+                    let parent = groups[i];
+                    let children: Vec<Uuid> = s
+                        .groups
+                        .values()
+                        .filter(|g| g.parent_id == Some(parent) && !groups.contains(&g.id))
+                        .map(|g| g.id)
+                        .collect();
+                    groups.extend(children);
+                    i += 1;
+                }
+            }
+            let sessions: Vec<Uuid> = s
+                .sessions
+                .values()
+                .filter(|session| session.group_id.is_some_and(|id| groups.contains(&id)))
+                .map(|session| session.id)
+                .collect();
+            (groups, sessions)
+        };
+        let mut stopped = Vec::new();
+        if mode == GroupDeleteMode::Cascade {
+            for &session_id in &sessions {
+                if pty_manager.is_alive(session_id).await {
+                    stopped.push(session_id);
+                }
+            }
+        }
+        let result = GroupDeleteResult {
+            groups,
+            sessions,
+            stopped,
+            dry_run,
+        };
+        if dry_run {
+            return Ok(result);
+        }
+
+        if mode == GroupDeleteMode::Cascade {
+            for &session_id in &result.sessions {
+                Self::delete_session(state, pty_manager, event_tx, session_id, false).await?;
+            }
+            state
+                .write()
+                .await
+                .groups
+                .retain(|id, _| !result.groups.contains(id));
+            save_state(state).await?;
+            for &group_id in result.groups.iter().rev() {
+                let _ = event_tx.send(Event::GroupDeleted { group_id });
+            }
+            return Ok(result);
+        }
+
         {
             let mut s = state.write().await;
             // Move sessions in this group to root
@@ -638,13 +1896,10 @@ impl SessionManager {
         }
         save_state(state).await?;
 
-        let event = Event {
-            event: "group:deleted".to_string(),
-            data: serde_json::json!({"group_id": group_id}),
-        };
+        let event = Event::GroupDeleted { group_id };
         let _ = event_tx.send(event);
 
-        Ok(())
+        Ok(result)
     }
 
     pub async fn update_group(
@@ -656,10 +1911,7 @@ impl SessionManager {
     ) -> Result<Group> {
         let group = {
             let mut s = state.write().await;
-            let group = s
-                .groups
-                .get_mut(&group_id)
-                .ok_or_else(|| anyhow::anyhow!("Group not found"))?;
+            let group = s.groups.get_mut(&group_id).ok_or(GroupNotFound(group_id))?;
 
             if let Some(new_name) = name {
                 group.name = new_name;
@@ -676,12 +1928,497 @@ impl SessionManager {
         };
         save_state(state).await?;
 
-        let event = Event {
-            event: "group:updated".to_string(),
-            data: serde_json::to_value(&group)?,
-        };
+        let event = Event::GroupUpdated(group.clone());
         let _ = event_tx.send(event);
 
         Ok(group)
     }
 }
+
+/// Add output to the sessions' unread_bytes; returns session:unread data for
+/// those that were read up to now
+fn add_unread(
+    s: &mut AppState,
+    output: impl IntoIterator<Item = (Uuid, usize)>,
+) -> Vec<UnreadData> {
+    let mut became_unread = Vec::new();
+    for (session_id, bytes) in output {
+        let Some(session) = s.sessions.get_mut(&session_id) else {
+            continue;
+        };
+        if session.unread_bytes == 0 && bytes > 0 {
+            became_unread.push(UnreadData {
+                session_id,
+                unread_bytes: bytes as u64,
+            });
+        }
+        session.unread_bytes += bytes as u64;
+    }
+    became_unread
+}
+
+/// PTY output waiting for the end of the current frame, per session in the
+/// order the sessions first wrote during it
+#[derive(Default)]
+struct OutputCoalescer {
+    pending: Vec<(Uuid, Vec<u8>)>,
+}
+
+impl OutputCoalescer {
+    fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    /// Add output; returns the session's batch if it has to be sent right away
+    fn push(&mut self, session_id: Uuid, data: Vec<u8>) -> Option<Vec<u8>> {
+        match self.pending.iter_mut().find(|(id, _)| *id == session_id) {
+            Some((_, batch)) => batch.extend_from_slice(&data),
+            None => self.pending.push((session_id, data)),
+        }
+        let full = self
+            .pending
+            .iter()
+            .any(|(id, batch)| *id == session_id && batch.len() >= MAX_BATCH_BYTES);
+        if full {
+            self.take(session_id)
+        } else {
+            None
+        }
+    }
+
+    fn take(&mut self, session_id: Uuid) -> Option<Vec<u8>> {
+        let i = self.pending.iter().position(|(id, _)| *id == session_id)?;
+        Some(self.pending.remove(i).1)
+    }
+
+    fn drain(&mut self) -> Vec<(Uuid, Vec<u8>)> {
+        std::mem::take(&mut self.pending)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+
+    /// A PTY manager whose claude binaries are the given shell scripts, by
+    /// name, written to `dir`
+    #[cfg(unix)]
+    fn script_pty_manager(dir: &Path, mut config: Config, scripts: &[(&str, &str)]) -> PtyManager {
+        use std::os::unix::fs::PermissionsExt;
+
+        std::fs::create_dir_all(dir).unwrap();
+        for (name, script) in scripts {
+            let path = dir.join(name);
+            std::fs::write(&path, format!("#!/bin/sh\n{}\n", script)).unwrap();
+            std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+            config.claude.binaries.insert(name.to_string(), path);
+        }
+        let (exit_tx, _exit_rx) = mpsc::unbounded_channel();
+        PtyManager::new(
+            Arc::new(RwLock::new(config)),
+            exit_tx,
+            Arc::new(FlowControl::new(1024)),
+        )
+    }
+
+    #[test]
+    fn test_output_coalescer() {
+        let (a, b) = (Uuid::new_v4(), Uuid::new_v4());
+        let mut coalescer = OutputCoalescer::default();
+        assert!(coalescer.push(a, b"he".to_vec()).is_none());
+        assert!(coalescer.push(b, b"x".to_vec()).is_none());
+        assert!(coalescer.push(a, b"llo".to_vec()).is_none());
+        assert_eq!(coalescer.take(b), Some(b"x".to_vec()));
+        assert_eq!(coalescer.drain(), vec![(a, b"hello".to_vec())]);
+        assert!(coalescer.is_empty());
+
+        // A full batch is handed back instead of waiting for the frame
+        assert!(coalescer.push(a, vec![0; MAX_BATCH_BYTES - 1]).is_none());
+        let batch = coalescer.push(a, vec![1]).unwrap();
+        assert_eq!(batch.len(), MAX_BATCH_BYTES);
+        assert!(coalescer.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_unread_counting() {
+        crate::state::discard_saves();
+        let state = crate::state::new_shared_state();
+        let (event_tx, mut event_rx) = broadcast::channel(16);
+        let (a, b) = (
+            Session::new("a".into(), PathBuf::from("/"), None),
+            Session::new("b".into(), PathBuf::from("/"), None),
+        );
+        let (a, b) = {
+            let mut s = state.write().await;
+            let ids = (a.id, b.id);
+            s.sessions.insert(a.id, a);
+            s.sessions.insert(b.id, b);
+            ids
+        };
+
+        // Only output to a session that was read up to now is announced
+        let became_unread = add_unread(&mut *state.write().await, [(a, 5), (b, 0)]);
+        assert_eq!(became_unread.len(), 1);
+        assert_eq!(became_unread[0].session_id, a);
+        assert_eq!(became_unread[0].unread_bytes, 5);
+        let became_unread = add_unread(&mut *state.write().await, [(a, 3), (Uuid::new_v4(), 1)]);
+        assert!(became_unread.is_empty());
+        assert_eq!(state.read().await.sessions[&a].unread_bytes, 8);
+        assert_eq!(state.read().await.sessions[&b].unread_bytes, 0);
+
+        let session = SessionManager::mark_viewed(&state, &event_tx, a)
+            .await
+            .unwrap();
+        assert_eq!(session.unread_bytes, 0);
+        match event_rx.try_recv() {
+            Ok(Event::Unread(data)) => assert_eq!((data.session_id, data.unread_bytes), (a, 0)),
+            other => panic!("expected session:unread, got {:?}", other),
+        }
+        // Nothing to clear, nothing sent
+        SessionManager::mark_viewed(&state, &event_tx, a)
+            .await
+            .unwrap();
+        assert!(event_rx.try_recv().is_err());
+        assert_eq!(add_unread(&mut *state.write().await, [(a, 1)]).len(), 1);
+    }
+
+    #[test]
+    fn test_check_working_dir() {
+        let dir = std::env::temp_dir().join(format!("workdir-test-{}", Uuid::new_v4()));
+        let nested = dir.join("a").join("b");
+        assert!(matches!(
+            check_working_dir(&nested, false),
+            Err(SessionCreateError::DirNotFound(_))
+        ));
+        check_working_dir(&nested, true).unwrap();
+        assert!(nested.is_dir());
+
+        let file = dir.join("file");
+        std::fs::write(&file, "").unwrap();
+        let err = check_working_dir(&file, true).unwrap_err();
+        assert!(matches!(err, SessionCreateError::NotADirectory(_)));
+        assert_eq!(err.code(), ErrorCode::InvalidWorkingDir);
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_failed_starts() {
+        crate::state::discard_saves();
+        let dir = std::env::temp_dir().join(format!("start-test-{}", Uuid::new_v4()));
+        let mut config = Config::default();
+        config.daemon.start_timeout_secs = 1;
+        let pty_manager = script_pty_manager(
+            &dir,
+            config,
+            &[("exits", "exit 3"), ("silent", "exec sleep 30")],
+        );
+        let hook_manager = HookManager::new(dir.join("hooks"), dir.join("hooks.sock"));
+        let (event_tx, _) = broadcast::channel(64);
+        let (output_tx, _output_rx) = mpsc::channel(64);
+
+        let state = crate::state::new_shared_state();
+        let mut session = Session::new("s".into(), dir.clone(), None);
+        session.claude_binary = Some("exits".to_string());
+        let id = session.id;
+        state.write().await.sessions.insert(id, session);
+        let start = || {
+            SessionManager::restart_session(
+                &state,
+                &pty_manager,
+                output_tx.clone(),
+                &event_tx,
+                &hook_manager,
+                id,
+                None,
+                false,
+            )
+        };
+
+        // Exiting before it started leaves the session in Error, saying why
+        let err = start().await.unwrap_err();
+        assert!(err.to_string().contains("exited with code 3"), "{}", err);
+        {
+            let s = state.read().await;
+            let session = &s.sessions[&id];
+            assert_eq!(session.status, SessionStatus::Error);
+            assert_eq!(
+                session.error_reason.as_deref(),
+                Some("claude exited with code 3 before it started")
+            );
+            assert!(session.pid.is_none());
+        }
+
+        // Running but silent past start_timeout_secs is reported Running anyway,
+        // and the earlier error is cleared
+        state
+            .write()
+            .await
+            .sessions
+            .get_mut(&id)
+            .unwrap()
+            .claude_binary = Some("silent".to_string());
+        let session = start().await.unwrap();
+        assert_eq!(session.status, SessionStatus::Running);
+        assert!(session.error_reason.is_none());
+        assert!(session.pid.is_some());
+        pty_manager.kill(id).await.unwrap();
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[tokio::test]
+    async fn test_delete_group() {
+        crate::state::discard_saves();
+        let (exit_tx, _exit_rx) = mpsc::unbounded_channel();
+        let pty_manager = PtyManager::new(
+            Arc::new(RwLock::new(Config::default())),
+            exit_tx,
+            Arc::new(FlowControl::new(1024)),
+        );
+        let (event_tx, _) = broadcast::channel(64);
+        let state = crate::state::new_shared_state();
+
+        // top > child > grandchild, and other beside them, with a session each
+        let (top, child, grandchild, other, sessions) = {
+            let mut s = state.write().await;
+            let top = Group::new("top".to_string(), None);
+            let child = Group::new("child".to_string(), Some(top.id));
+            let grandchild = Group::new("grandchild".to_string(), Some(child.id));
+            let other = Group::new("other".to_string(), None);
+            let mut sessions = Vec::new();
+            for group in [&top, &child, &grandchild, &other] {
+                let session =
+                    Session::new(group.name.clone(), PathBuf::from("/tmp"), Some(group.id));
+                sessions.push(session.id);
+                s.sessions.insert(session.id, session);
+            }
+            let ids = (top.id, child.id, grandchild.id, other.id, sessions);
+            for group in [top, child, grandchild, other] {
+                s.groups.insert(group.id, group);
+            }
+            ids
+        };
+        let delete = |group_id, mode, dry_run| {
+            SessionManager::delete_group(&state, &pty_manager, &event_tx, group_id, mode, dry_run)
+        };
+
+        // A cascade dry run lists the subtree and changes nothing
+        let result = delete(top, GroupDeleteMode::Cascade, true).await.unwrap();
+        assert!(result.dry_run);
+        assert_eq!(result.groups, vec![top, child, grandchild]);
+        let mut deleted = result.sessions.clone();
+        deleted.sort();
+        let mut expected = sessions[..3].to_vec();
+        expected.sort();
+        assert_eq!(deleted, expected);
+        assert!(result.stopped.is_empty());
+        {
+            let s = state.read().await;
+            assert_eq!(s.groups.len(), 4);
+            assert_eq!(s.sessions.len(), 4);
+        }
+
+        // Reparenting moves the sessions to the top level and the subgroups up
+        let result = delete(child, GroupDeleteMode::Reparent, false)
+            .await
+            .unwrap();
+        assert!(!result.dry_run);
+        assert_eq!(result.groups, vec![child]);
+        assert_eq!(result.sessions, vec![sessions[1]]);
+        {
+            let s = state.read().await;
+            assert!(!s.groups.contains_key(&child));
+            assert_eq!(s.groups[&grandchild].parent_id, Some(top));
+            assert_eq!(s.sessions[&sessions[1]].group_id, None);
+            assert_eq!(s.sessions[&sessions[2]].group_id, Some(grandchild));
+            assert_eq!(s.sessions[&sessions[3]].group_id, Some(other));
+        }
+
+        let err = delete(child, GroupDeleteMode::Reparent, false)
+            .await
+            .unwrap_err();
+        assert!(err.is::<GroupNotFound>());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_terminal_size_is_remembered() {
+        crate::state::discard_saves();
+        let dir = std::env::temp_dir().join(format!("size-test-{}", Uuid::new_v4()));
+        let pty_manager = script_pty_manager(
+            &dir,
+            Config::default(),
+            &[("ready", "echo ready; exec sleep 30")],
+        );
+        let hook_manager = HookManager::new(dir.join("hooks"), dir.join("hooks.sock"));
+        let (event_tx, _) = broadcast::channel(64);
+        let (output_tx, _output_rx) = mpsc::channel(64);
+
+        let state = crate::state::new_shared_state();
+        let mut session = Session::new("s".into(), dir.clone(), None);
+        session.claude_binary = Some("ready".to_string());
+        let id = session.id;
+        state.write().await.sessions.insert(id, session);
+        let start = |size| {
+            SessionManager::restart_session(
+                &state,
+                &pty_manager,
+                output_tx.clone(),
+                &event_tx,
+                &hook_manager,
+                id,
+                size,
+                false,
+            )
+        };
+
+        let size = TerminalSize {
+            rows: 40,
+            cols: 120,
+        };
+        assert_eq!(start(Some(size)).await.unwrap().size, Some(size));
+        assert_eq!(pty_manager.size(id).await, Some((40, 120)));
+
+        // A start without a size uses the last one
+        assert_eq!(start(None).await.unwrap().size, Some(size));
+        assert_eq!(pty_manager.size(id).await, Some((40, 120)));
+
+        // With none known, the default
+        state.write().await.sessions.get_mut(&id).unwrap().size = None;
+        let session = start(None).await.unwrap();
+        assert_eq!(session.size, Some(TerminalSize::default()));
+        assert_eq!(pty_manager.size(id).await, Some((24, 80)));
+
+        pty_manager.kill(id).await.unwrap();
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_time_out_idle() {
+        crate::state::discard_saves();
+        let dir = std::env::temp_dir().join(format!("idle-test-{}", Uuid::new_v4()));
+        let pty_manager =
+            script_pty_manager(&dir, Config::default(), &[("silent", "exec sleep 30")]);
+        let (event_tx, mut event_rx) = broadcast::channel(64);
+        let (output_tx, _output_rx) = mpsc::channel(64);
+
+        let state = crate::state::new_shared_state();
+        let mut ids = Vec::new();
+        for name in ["a", "b"] {
+            let mut session = Session::new(name.into(), dir.clone(), None);
+            session.status = SessionStatus::Running;
+            ids.push(session.id);
+            pty_manager
+                .spawn_with_resume(
+                    session.id,
+                    &dir,
+                    24,
+                    80,
+                    output_tx.clone(),
+                    None,
+                    &[],
+                    Vec::new(),
+                    None,
+                    Some("silent"),
+                )
+                .await
+                .unwrap();
+            state.write().await.sessions.insert(session.id, session);
+        }
+        let mut timed_out = HashSet::new();
+        let mut timeouts = || {
+            let mut timeouts = Vec::new();
+            while let Ok(event) = event_rx.try_recv() {
+                if let Event::IdleTimeout(data) = event {
+                    timeouts.push((data.session_id, data.stopped));
+                }
+            }
+            timeouts.sort();
+            timeouts
+        };
+        let later = |secs| Utc::now() + chrono::Duration::seconds(secs);
+        let expected = |stopped| {
+            let mut expected: Vec<(Uuid, bool)> = ids.iter().map(|id| (*id, stopped)).collect();
+            expected.sort();
+            expected
+        };
+
+        SessionManager::time_out_idle(
+            &state,
+            &event_tx,
+            &pty_manager,
+            60,
+            false,
+            &mut timed_out,
+            Utc::now(),
+        )
+        .await;
+        assert!(timeouts().is_empty());
+
+        // Quiet past the timeout: Idle, and reported once per quiet spell
+        SessionManager::time_out_idle(
+            &state,
+            &event_tx,
+            &pty_manager,
+            60,
+            false,
+            &mut timed_out,
+            later(120),
+        )
+        .await;
+        assert_eq!(timeouts(), expected(false));
+        assert!(state
+            .read()
+            .await
+            .sessions
+            .values()
+            .all(|session| session.status == SessionStatus::Idle));
+        SessionManager::time_out_idle(
+            &state,
+            &event_tx,
+            &pty_manager,
+            60,
+            false,
+            &mut timed_out,
+            later(180),
+        )
+        .await;
+        assert!(timeouts().is_empty());
+
+        // Seen active again, a new quiet spell times out again; with idle_stop
+        // they are stopped this time
+        pty_manager.write(ids[0], b"x").await.unwrap();
+        SessionManager::time_out_idle(
+            &state,
+            &event_tx,
+            &pty_manager,
+            60,
+            false,
+            &mut timed_out,
+            Utc::now(),
+        )
+        .await;
+        SessionManager::time_out_idle(
+            &state,
+            &event_tx,
+            &pty_manager,
+            60,
+            true,
+            &mut timed_out,
+            later(120),
+        )
+        .await;
+        assert_eq!(timeouts(), expected(true));
+        for id in &ids {
+            assert!(!pty_manager.is_alive(*id).await);
+            assert_eq!(
+                state.read().await.sessions[id].status,
+                SessionStatus::Stopped
+            );
+        }
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
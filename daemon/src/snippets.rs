@@ -0,0 +1,148 @@
+// Snippets - reusable prompts kept in the state (snippets.json) and shared by
+// every session. A snippet's text may use {{dir}}, {{name}}, {{branch}} and
+// {{group}}, filled in from the session it is sent to by session.send_snippet,
+// plus any variables the caller passes along.
+
+use anyhow::Result;
+use chrono::Utc;
+use shared::{CreateSnippetParams, Session, Snippet, UpdateSnippetParams};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+use crate::state::{save_state, AppState, SharedState};
+
+pub async fn create(state: &SharedState, params: CreateSnippetParams) -> Result<Snippet> {
+    let snippet = Snippet::new(params.name, params.text);
+    state
+        .write()
+        .await
+        .snippets
+        .insert(snippet.id, snippet.clone());
+    save_state(state).await?;
+    Ok(snippet)
+}
+
+pub async fn update(state: &SharedState, params: UpdateSnippetParams) -> Result<Snippet> {
+    let snippet = {
+        let mut s = state.write().await;
+        let Some(snippet) = s.snippets.get_mut(&params.snippet_id) else {
+            anyhow::bail!("Snippet not found: {}", params.snippet_id);
+        };
+        if let Some(name) = params.name {
+            snippet.name = name;
+        }
+        if let Some(text) = params.text {
+            snippet.text = text;
+        }
+        snippet.updated_at = Utc::now();
+        snippet.clone()
+    };
+    save_state(state).await?;
+    Ok(snippet)
+}
+
+pub async fn delete(state: &SharedState, snippet_id: Uuid) -> Result<()> {
+    if state.write().await.snippets.remove(&snippet_id).is_none() {
+        anyhow::bail!("Snippet not found: {}", snippet_id);
+    }
+    save_state(state).await
+}
+
+/// The variables a session fills in; {{branch}} and {{group}} only when it has one
+pub fn session_vars(s: &AppState, session: &Session) -> HashMap<String, String> {
+    let mut vars = HashMap::from([
+        ("dir".to_string(), session.working_dir.display().to_string()),
+        ("name".to_string(), session.name.clone()),
+    ]);
+    let branch = match &session.worktree {
+        Some(worktree) => Some(worktree.branch.clone()),
+        None => session.git.as_ref().and_then(|git| git.branch.clone()),
+    };
+    if let Some(branch) = branch {
+        vars.insert("branch".to_string(), branch);
+    }
+    if let Some(group) = session.group_id.and_then(|id| s.groups.get(&id)) {
+        vars.insert("group".to_string(), group.name.clone());
+    }
+    vars
+}
+
+fn is_var_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+/// Fill in the {{variables}} of `text`; fails naming any without a value
+/// Braces around anything but a plain name (e.g. code in the prompt) are kept.
+pub fn expand(text: &str, vars: &HashMap<String, String>) -> Result<String> {
+    let mut expanded = String::with_capacity(text.len());
+    let mut missing: Vec<&str> = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find("{{") {
+        let after = &rest[start + 2..];
+        let Some(end) = after.find("}}") else {
+            break;
+        };
+        let name = after[..end].trim();
+        expanded.push_str(&rest[..start]);
+        if !is_var_name(name) {
+            expanded.push_str(&rest[start..start + 2 + end + 2]);
+        } else if let Some(value) = vars.get(name) {
+            expanded.push_str(value);
+        } else if !missing.contains(&name) {
+            missing.push(name);
+        }
+        rest = &after[end + 2..];
+    }
+    expanded.push_str(rest);
+
+    if !missing.is_empty() {
+        anyhow::bail!("No value for {{{{{}}}}}", missing.join("}}, {{"));
+    }
+    Ok(expanded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shared::{GitStatus, Group};
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_expand() {
+        let mut s = AppState::default();
+        let group = Group::new("backend".to_string(), None);
+        let mut session = Session::new("api".into(), PathBuf::from("/src/api"), Some(group.id));
+        s.groups.insert(group.id, group);
+        let vars = session_vars(&s, &session);
+        assert!(!vars.contains_key("branch"));
+        assert!(expand("rebase {{branch}} and {{ base }}", &vars)
+            .unwrap_err()
+            .to_string()
+            .contains("{{branch}}, {{base}}"));
+
+        session.git = Some(GitStatus {
+            branch: Some("fix-login".into()),
+            dirty: 0,
+            ahead: 0,
+            behind: 0,
+        });
+        let mut vars = session_vars(&s, &session);
+        vars.insert("base".into(), "main".into());
+        assert_eq!(
+            expand(
+                "In {{dir}} ({{group}}), rebase {{ branch }} on {{base}}",
+                &vars
+            )
+            .unwrap(),
+            "In /src/api (backend), rebase fix-login on main"
+        );
+        // Not variables
+        assert_eq!(
+            expand("use {{ a.b }} and {{x", &vars).unwrap(),
+            "use {{ a.b }} and {{x"
+        );
+    }
+}
@@ -1,10 +1,17 @@
 use anyhow::Result;
-use shared::{Group, Session};
+use serde::de::DeserializeOwned;
+use shared::{
+    Group, InputHistoryEntry, Schedule, Session, SessionSummary, Snippet, SummaryResult, TreeGroup,
+    TreeSession, TreeSnapshot,
+};
 use std::collections::HashMap;
 use std::fs;
-use std::path::PathBuf;
-use std::sync::Arc;
-use tokio::sync::RwLock;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+use tokio::sync::{Mutex, Notify, RwLock};
+use tracing::error;
 use uuid::Uuid;
 
 use crate::config::get_state_dir;
@@ -13,10 +20,33 @@ use crate::config::get_state_dir;
 pub struct AppState {
     pub sessions: HashMap<Uuid, Session>,
     pub groups: HashMap<Uuid, Group>,
+    pub schedules: HashMap<Uuid, Schedule>,
+    pub snippets: HashMap<Uuid, Snippet>,
+    /// Prompts sent with session.input, oldest first (see input_history.rs)
+    pub input_history: HashMap<Uuid, Vec<InputHistoryEntry>>,
 }
 
 pub type SharedState = Arc<RwLock<AppState>>;
 
+/// How long a save waits for more changes, so a burst of mutations is written once
+const SAVE_DEBOUNCE_MS: u64 = 500;
+
+/// Wakes the persistence task; unset until it starts, and saves are written at once
+static SAVE_REQUESTED: OnceLock<Arc<Notify>> = OnceLock::new();
+
+/// Held while writing, so a flush on shutdown can't interleave with the task's
+static WRITING: Mutex<()> = Mutex::const_new(());
+
+/// No session with this id
+#[derive(Debug, thiserror::Error)]
+#[error("Session not found: {0}")]
+pub struct SessionNotFound(pub Uuid);
+
+/// No group with this id
+#[derive(Debug, thiserror::Error)]
+#[error("Group not found: {0}")]
+pub struct GroupNotFound(pub Uuid);
+
 pub fn new_shared_state() -> SharedState {
     Arc::new(RwLock::new(AppState::default()))
 }
@@ -29,6 +59,18 @@ fn groups_path() -> Result<PathBuf> {
     Ok(get_state_dir()?.join("groups.json"))
 }
 
+fn schedules_path() -> Result<PathBuf> {
+    Ok(get_state_dir()?.join("schedules.json"))
+}
+
+fn snippets_path() -> Result<PathBuf> {
+    Ok(get_state_dir()?.join("snippets.json"))
+}
+
+fn input_history_path() -> Result<PathBuf> {
+    Ok(get_state_dir()?.join("input_history.json"))
+}
+
 pub async fn load_state(state: &SharedState) -> Result<()> {
     let mut s = state.write().await;
 
@@ -45,11 +87,15 @@ pub async fn load_state(state: &SharedState) -> Result<()> {
                 shared::SessionStatus::Running
                 | shared::SessionStatus::Waiting
                 | shared::SessionStatus::Idle
-                | shared::SessionStatus::Error => {
+                | shared::SessionStatus::Error
+                | shared::SessionStatus::Queued
+                | shared::SessionStatus::Starting => {
                     // Reset all active/error states to Stopped on daemon restart
-                    // PTY processes don't survive daemon restarts
+                    // (the run queue is not persisted, so queued sessions too)
+                    // PTY processes don't survive daemon restarts; the pid is kept
+                    // so the orphan reaper can check for a leftover process
                     session.status = shared::SessionStatus::Stopped;
-                    session.pid = None;
+                    session.status_detail = None;
                 }
                 shared::SessionStatus::Stopped => {
                     // Already stopped, no change needed
@@ -69,165 +115,335 @@ pub async fn load_state(state: &SharedState) -> Result<()> {
         }
     }
 
+    // Load schedules
+    let schedules_file = schedules_path()?;
+    if schedules_file.exists() {
+        let content = fs::read_to_string(&schedules_file)?;
+        let schedules: Vec<Schedule> = serde_json::from_str(&content)?;
+        for schedule in schedules {
+            s.schedules.insert(schedule.id, schedule);
+        }
+    }
+
+    // Load snippets
+    let snippets_file = snippets_path()?;
+    if snippets_file.exists() {
+        let content = fs::read_to_string(&snippets_file)?;
+        let snippets: Vec<Snippet> = serde_json::from_str(&content)?;
+        for snippet in snippets {
+            s.snippets.insert(snippet.id, snippet);
+        }
+    }
+
+    // Load input history
+    let input_history_file = input_history_path()?;
+    if input_history_file.exists() {
+        let content = fs::read_to_string(&input_history_file)?;
+        let entries: Vec<InputHistoryEntry> = serde_json::from_str(&content)?;
+        for entry in entries {
+            s.input_history
+                .entry(entry.session_id)
+                .or_default()
+                .push(entry);
+        }
+    }
+
     Ok(())
 }
 
+/// Save the state after a change
+/// Once the persistence task runs this only schedules a write, debounced by
+/// SAVE_DEBOUNCE_MS; before that it writes immediately.
 pub async fn save_state(state: &SharedState) -> Result<()> {
-    let s = state.read().await;
+    match SAVE_REQUESTED.get() {
+        Some(requested) => {
+            requested.notify_one();
+            Ok(())
+        }
+        None => flush_state(state).await,
+    }
+}
 
-    // Save sessions
-    let sessions: Vec<&Session> = s.sessions.values().collect();
-    let sessions_json = serde_json::to_string_pretty(&sessions)?;
+/// Write the state to disk now (on shutdown, so pending changes aren't lost)
+pub async fn flush_state(state: &SharedState) -> Result<()> {
+    let _writing = WRITING.lock().await;
+    // Serialize under one read guard, so the files agree with each other, and
+    // write without it
+    let (
+        (sessions_json, session_count),
+        (groups_json, group_count),
+        (schedules_json, schedule_count),
+        (snippets_json, snippet_count),
+        (input_history_json, input_count),
+    ) = {
+        let s = state.read().await;
+        let sessions: Vec<&Session> = s.sessions.values().collect();
+        let groups: Vec<&Group> = s.groups.values().collect();
+        let schedules: Vec<&Schedule> = s.schedules.values().collect();
+        let snippets: Vec<&Snippet> = s.snippets.values().collect();
+        let entries: Vec<&InputHistoryEntry> = s.input_history.values().flatten().collect();
+        (
+            (serde_json::to_string_pretty(&sessions)?, sessions.len()),
+            (serde_json::to_string_pretty(&groups)?, groups.len()),
+            (serde_json::to_string_pretty(&schedules)?, schedules.len()),
+            (serde_json::to_string_pretty(&snippets)?, snippets.len()),
+            (serde_json::to_string(&entries)?, entries.len()),
+        )
+    };
     let sessions_file = sessions_path()?;
+    let groups_file = groups_path()?;
+    let schedules_file = schedules_path()?;
+    let snippets_file = snippets_path()?;
+    let input_history_file = input_history_path()?;
+
+    tokio::task::spawn_blocking(move || {
+        // Backup before writing
+        if sessions_file.exists() {
+            let backup = sessions_file.with_extension("json.bak");
+            fs::copy(&sessions_file, backup)?;
+        }
+        replace_file::<Session>(&sessions_file, &sessions_json, session_count)?;
+        replace_file::<Group>(&groups_file, &groups_json, group_count)?;
+        replace_file::<Schedule>(&schedules_file, &schedules_json, schedule_count)?;
+        replace_file::<Snippet>(&snippets_file, &snippets_json, snippet_count)?;
+        replace_file::<InputHistoryEntry>(&input_history_file, &input_history_json, input_count)
+    })
+    .await?
+}
 
-    // Backup before writing
-    if sessions_file.exists() {
-        let backup = sessions_file.with_extension("json.bak");
-        fs::copy(&sessions_file, backup)?;
+/// Keep tests off the real state dir: saves only wake a task that isn't running
+#[cfg(test)]
+pub fn discard_saves() {
+    let _ = SAVE_REQUESTED.set(Arc::new(Notify::new()));
+}
+
+/// Start the task that writes the state after save_state requests
+pub fn spawn_persister(state: SharedState) {
+    let requested = SAVE_REQUESTED
+        .get_or_init(|| Arc::new(Notify::new()))
+        .clone();
+    tokio::spawn(async move {
+        loop {
+            requested.notified().await;
+            // Changes during the wait (or the write) are picked up by this or the next write
+            tokio::time::sleep(Duration::from_millis(SAVE_DEBOUNCE_MS)).await;
+            if let Err(e) = flush_state(&state).await {
+                error!("Failed to save state: {}", e);
+            }
+        }
+    });
+}
+
+/// Replace `path` with `json` (a list of `count` items) atomically: write a temp
+/// file, check it reads back as the same list, then rename it over the original.
+/// A crash leaves either the old file or the new one, never a torn write.
+fn replace_file<T: DeserializeOwned>(path: &Path, json: &str, count: usize) -> Result<()> {
+    let tmp = path.with_extension("json.tmp");
+    {
+        let mut file = fs::File::create(&tmp)?;
+        file.write_all(json.as_bytes())?;
+        file.sync_all()?;
     }
-    fs::write(&sessions_file, sessions_json)?;
 
-    // Save groups
-    let groups: Vec<&Group> = s.groups.values().collect();
-    let groups_json = serde_json::to_string_pretty(&groups)?;
-    let groups_file = groups_path()?;
-    fs::write(&groups_file, groups_json)?;
+    let written: Vec<T> = serde_json::from_str(&fs::read_to_string(&tmp)?)
+        .map_err(|e| anyhow::anyhow!("{:?} doesn't read back: {}", tmp, e))?;
+    if written.len() != count {
+        anyhow::bail!(
+            "{:?} read back {} entries, expected {}",
+            tmp,
+            written.len(),
+            count
+        );
+    }
 
+    fs::rename(&tmp, path)?;
     Ok(())
 }
 
-/// Reorder a session: move to a new group and/or position
-/// - `group_id`: Target group (None = root level)
-/// - `after_session_id`: Insert after this session (None = insert at beginning)
-pub async fn reorder_session(
+/// Where to put sessions (or groups) in `session.reorder` / `group.reorder`
+pub enum Placement {
+    /// Move one after a sibling (None = to the front)
+    After(Uuid, Option<Uuid>),
+    /// These, in this order, ahead of the siblings not listed
+    Order(Vec<Uuid>),
+}
+
+impl Placement {
+    /// What the placement moves
+    fn moved(&self) -> Vec<Uuid> {
+        match self {
+            Placement::After(id, _) => vec![*id],
+            Placement::Order(ids) => ids.clone(),
+        }
+    }
+}
+
+/// The new order of a container's children; `siblings` are the ones the
+/// placement doesn't move, in their current order
+fn place(siblings: Vec<Uuid>, placement: &Placement) -> Result<Vec<Uuid>> {
+    match placement {
+        Placement::After(id, after) => {
+            let mut order = siblings;
+            let pos = after
+                .and_then(|after| order.iter().position(|s| *s == after).map(|p| p + 1))
+                .unwrap_or(0);
+            order.insert(pos, *id);
+            Ok(order)
+        }
+        Placement::Order(ids) => {
+            if !ids.iter().all_unique() {
+                anyhow::bail!("The order lists an id more than once");
+            }
+            Ok(ids.iter().copied().chain(siblings).collect())
+        }
+    }
+}
+
+/// Reorder sessions within `group_id` (None = root level), moving them into
+/// it first; returns every session whose group or order changed
+pub async fn reorder_sessions(
     state: &SharedState,
-    session_id: Uuid,
     group_id: Option<Uuid>,
-    after_session_id: Option<Uuid>,
-) -> Result<Session> {
+    placement: Placement,
+) -> Result<Vec<Session>> {
     let mut s = state.write().await;
 
-    // Verify session exists
-    if !s.sessions.contains_key(&session_id) {
-        anyhow::bail!("Session not found: {}", session_id);
+    let moved = placement.moved();
+    if let Some(id) = moved.iter().find(|id| !s.sessions.contains_key(id)) {
+        return Err(SessionNotFound(*id).into());
     }
-
-    // Verify target group exists if specified
     if let Some(gid) = group_id {
         if !s.groups.contains_key(&gid) {
-            anyhow::bail!("Group not found: {}", gid);
+            return Err(GroupNotFound(gid).into());
         }
     }
 
-    // Update session's group_id
-    if let Some(session) = s.sessions.get_mut(&session_id) {
-        session.group_id = group_id;
-    }
-
-    // Get all sessions in the target group, sorted by current order
-    let mut siblings: Vec<Uuid> = s
+    let siblings: Vec<Uuid> = s
         .sessions
         .values()
-        .filter(|sess| sess.group_id == group_id && sess.id != session_id)
-        .map(|sess| (sess.id, sess.order))
-        .collect::<Vec<_>>()
-        .into_iter()
-        .sorted_by_key(|(_, order)| *order)
-        .map(|(id, _)| id)
+        .filter(|sess| sess.group_id == group_id && !moved.contains(&sess.id))
+        .sorted_by_key(|sess| sess.order)
+        .map(|sess| sess.id)
         .collect();
+    let order = place(siblings, &placement)?;
 
-    // Find insertion position
-    let insert_pos = if let Some(after_id) = after_session_id {
-        siblings
-            .iter()
-            .position(|id| *id == after_id)
-            .map(|p| p + 1)
-            .unwrap_or(0)
-    } else {
-        0
-    };
-
-    // Insert the moved session at the correct position
-    siblings.insert(insert_pos, session_id);
-
-    // Renumber all siblings
-    for (idx, id) in siblings.iter().enumerate() {
+    let mut changed = Vec::new();
+    for (idx, id) in order.iter().enumerate() {
         if let Some(sess) = s.sessions.get_mut(id) {
-            sess.order = idx as u32;
+            if sess.group_id != group_id || sess.order != idx as u32 {
+                sess.group_id = group_id;
+                sess.order = idx as u32;
+                changed.push(sess.clone());
+            }
         }
     }
-
-    let session = s.sessions.get(&session_id).cloned().unwrap();
-    Ok(session)
+    Ok(changed)
 }
 
-/// Reorder a group: move to a new parent and/or position
-/// - `parent_id`: Target parent group (None = root level)
-/// - `after_group_id`: Insert after this group (None = insert at beginning)
-pub async fn reorder_group(
+/// Reorder groups within `parent_id` (None = root level), moving them under it
+/// first; returns every group whose parent or order changed
+pub async fn reorder_groups(
     state: &SharedState,
-    group_id: Uuid,
     parent_id: Option<Uuid>,
-    after_group_id: Option<Uuid>,
-) -> Result<Group> {
+    placement: Placement,
+) -> Result<Vec<Group>> {
     let mut s = state.write().await;
 
-    // Verify group exists
-    if !s.groups.contains_key(&group_id) {
-        anyhow::bail!("Group not found: {}", group_id);
+    let moved = placement.moved();
+    if let Some(id) = moved.iter().find(|id| !s.groups.contains_key(id)) {
+        return Err(GroupNotFound(*id).into());
     }
-
-    // Verify target parent exists if specified
     if let Some(pid) = parent_id {
         if !s.groups.contains_key(&pid) {
-            anyhow::bail!("Parent group not found: {}", pid);
+            return Err(GroupNotFound(pid).into());
         }
         // Check for cycle: can't make a group a child of its own descendant
-        if would_create_cycle(&s.groups, group_id, pid) {
+        if moved
+            .iter()
+            .any(|&id| would_create_cycle(&s.groups, id, pid))
+        {
             anyhow::bail!("Cannot move group into its own descendant");
         }
     }
 
-    // Update group's parent_id
-    if let Some(group) = s.groups.get_mut(&group_id) {
-        group.parent_id = parent_id;
-    }
-
-    // Get all groups with the same parent, sorted by current order
-    let mut siblings: Vec<Uuid> = s
+    let siblings: Vec<Uuid> = s
         .groups
         .values()
-        .filter(|g| g.parent_id == parent_id && g.id != group_id)
-        .map(|g| (g.id, g.order))
-        .collect::<Vec<_>>()
-        .into_iter()
-        .sorted_by_key(|(_, order)| *order)
-        .map(|(id, _)| id)
+        .filter(|g| g.parent_id == parent_id && !moved.contains(&g.id))
+        .sorted_by_key(|g| g.order)
+        .map(|g| g.id)
         .collect();
+    let order = place(siblings, &placement)?;
 
-    // Find insertion position
-    let insert_pos = if let Some(after_id) = after_group_id {
-        siblings
-            .iter()
-            .position(|id| *id == after_id)
-            .map(|p| p + 1)
-            .unwrap_or(0)
-    } else {
-        0
-    };
-
-    // Insert the moved group at the correct position
-    siblings.insert(insert_pos, group_id);
-
-    // Renumber all siblings
-    for (idx, id) in siblings.iter().enumerate() {
+    let mut changed = Vec::new();
+    for (idx, id) in order.iter().enumerate() {
         if let Some(g) = s.groups.get_mut(id) {
-            g.order = idx as u32;
+            if g.parent_id != parent_id || g.order != idx as u32 {
+                g.parent_id = parent_id;
+                g.order = idx as u32;
+                changed.push(g.clone());
+            }
         }
     }
+    Ok(changed)
+}
 
-    let group = s.groups.get(&group_id).cloned().unwrap();
-    Ok(group)
+/// Build the nested group/session hierarchy for `tree.snapshot`
+/// Sessions and groups whose parent no longer exists are placed at the top level.
+pub fn tree_snapshot(s: &AppState) -> TreeSnapshot {
+    let mut child_groups: HashMap<Option<Uuid>, Vec<&Group>> = HashMap::new();
+    for group in s.groups.values() {
+        let parent = group.parent_id.filter(|pid| s.groups.contains_key(pid));
+        child_groups.entry(parent).or_default().push(group);
+    }
+
+    let mut group_sessions: HashMap<Option<Uuid>, Vec<&Session>> = HashMap::new();
+    for session in s.sessions.values() {
+        let group = session.group_id.filter(|gid| s.groups.contains_key(gid));
+        group_sessions.entry(group).or_default().push(session);
+    }
+
+    fn sessions_in(
+        group_sessions: &HashMap<Option<Uuid>, Vec<&Session>>,
+        group_id: Option<Uuid>,
+    ) -> Vec<TreeSession> {
+        group_sessions
+            .get(&group_id)
+            .into_iter()
+            .flatten()
+            .sorted_by_key(|sess| sess.order)
+            .map(|sess| TreeSession {
+                id: sess.id,
+                name: sess.name.clone(),
+                status: sess.status,
+            })
+            .collect()
+    }
+
+    fn groups_in(
+        child_groups: &HashMap<Option<Uuid>, Vec<&Group>>,
+        group_sessions: &HashMap<Option<Uuid>, Vec<&Session>>,
+        parent_id: Option<Uuid>,
+    ) -> Vec<TreeGroup> {
+        child_groups
+            .get(&parent_id)
+            .into_iter()
+            .flatten()
+            .sorted_by_key(|g| g.order)
+            .map(|g| TreeGroup {
+                id: g.id,
+                name: g.name.clone(),
+                collapsed: g.collapsed,
+                groups: groups_in(child_groups, group_sessions, Some(g.id)),
+                sessions: sessions_in(group_sessions, Some(g.id)),
+            })
+            .collect()
+    }
+
+    TreeSnapshot {
+        groups: groups_in(&child_groups, &group_sessions, None),
+        sessions: sessions_in(&group_sessions, None),
+    }
 }
 
 /// Check if making `group_id` a child of `potential_parent` would create a cycle
@@ -246,5 +462,171 @@ fn would_create_cycle(
     false
 }
 
+/// Every session's name, status, unread count and last activity for
+/// `summary.get`, most recently active first
+pub fn summary(s: &AppState) -> SummaryResult {
+    let mut sessions: Vec<SessionSummary> = s
+        .sessions
+        .values()
+        .map(|session| SessionSummary {
+            id: session.id,
+            name: session.name.clone(),
+            status: session.status,
+            unread_bytes: session.unread_bytes,
+            last_activity: session.last_activity,
+        })
+        .collect();
+    sessions.sort_by_key(|session| std::cmp::Reverse(session.last_activity));
+    SummaryResult { sessions }
+}
+
 // Helper trait for sorting
 use itertools::Itertools;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shared::SessionStatus;
+
+    #[test]
+    fn test_replace_file() {
+        let dir = std::env::temp_dir().join(format!("state-test-{}", Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("groups.json");
+        fs::write(&path, "[]").unwrap();
+
+        let groups = vec![Group::new("a".to_string(), None)];
+        let json = serde_json::to_string_pretty(&groups).unwrap();
+        replace_file::<Group>(&path, &json, 1).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), json);
+        assert!(!path.with_extension("json.tmp").exists());
+
+        // A write that doesn't round-trip leaves the file alone
+        assert!(replace_file::<Group>(&path, "[{\"id\": 1}]", 1).is_err());
+        assert!(replace_file::<Group>(&path, &json, 2).is_err());
+        assert_eq!(fs::read_to_string(&path).unwrap(), json);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_reorder_sessions() {
+        let state = new_shared_state();
+        let group = Group::new("g".to_string(), None);
+        let [a, b, c] = ["a", "b", "c"]
+            .map(|name| Session::new(name.into(), PathBuf::from("/"), Some(group.id)));
+        let loose = Session::new("loose".into(), PathBuf::from("/"), None);
+        let loose_id = loose.id;
+        {
+            let mut s = state.write().await;
+            s.groups.insert(group.id, group.clone());
+            for (order, mut sess) in [a.clone(), b.clone(), c.clone(), loose]
+                .into_iter()
+                .enumerate()
+            {
+                sess.order = order as u32;
+                s.sessions.insert(sess.id, sess);
+            }
+        }
+        let names = |sessions: Vec<Session>| -> Vec<String> {
+            sessions.into_iter().map(|sess| sess.name).collect()
+        };
+        let order = || async {
+            let s = state.read().await;
+            let sessions = s
+                .sessions
+                .values()
+                .filter(|sess| sess.group_id == Some(group.id))
+                .sorted_by_key(|sess| sess.order)
+                .cloned()
+                .collect();
+            names(sessions)
+        };
+
+        // An ordered list goes first, moving the loose session in
+        let changed = reorder_sessions(
+            &state,
+            Some(group.id),
+            Placement::Order(vec![c.id, loose_id]),
+        )
+        .await
+        .unwrap();
+        assert_eq!(names(changed).len(), 4);
+        assert_eq!(order().await, ["c", "loose", "a", "b"]);
+
+        // Only what actually moved is reported
+        let changed = reorder_sessions(&state, Some(group.id), Placement::After(a.id, Some(b.id)))
+            .await
+            .unwrap();
+        assert_eq!(names(changed), ["b", "a"]);
+        assert_eq!(order().await, ["c", "loose", "b", "a"]);
+
+        assert!(
+            reorder_sessions(&state, None, Placement::Order(vec![a.id, a.id]))
+                .await
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_tree_snapshot_nests_and_orders() {
+        let mut s = AppState::default();
+        let parent = Group::new("parent".to_string(), None);
+        let mut child = Group::new("child".to_string(), Some(parent.id));
+        child.order = 1;
+        let orphan_group = Group::new("orphan".to_string(), Some(Uuid::new_v4()));
+
+        let mut second = Session::new("second".into(), PathBuf::from("/"), Some(child.id));
+        second.order = 1;
+        let first = Session::new("first".into(), PathBuf::from("/"), Some(child.id));
+        let loose = Session::new("loose".into(), PathBuf::from("/"), Some(Uuid::new_v4()));
+
+        for g in [parent.clone(), child.clone(), orphan_group.clone()] {
+            s.groups.insert(g.id, g);
+        }
+        for sess in [second, first, loose] {
+            s.sessions.insert(sess.id, sess);
+        }
+
+        let tree = tree_snapshot(&s);
+        assert_eq!(tree.groups.len(), 2);
+        assert_eq!(tree.sessions.len(), 1);
+        assert_eq!(tree.sessions[0].name, "loose");
+
+        let parent_node = tree.groups.iter().find(|g| g.id == parent.id).unwrap();
+        assert_eq!(parent_node.groups.len(), 1);
+        let names: Vec<_> = parent_node.groups[0]
+            .sessions
+            .iter()
+            .map(|sess| sess.name.as_str())
+            .collect();
+        assert_eq!(names, vec!["first", "second"]);
+    }
+
+    #[test]
+    fn test_summary() {
+        let mut s = AppState::default();
+        let mut quiet = Session::new("quiet".into(), PathBuf::from("/"), None);
+        quiet.last_activity -= chrono::Duration::minutes(5);
+        let mut busy = Session::new("busy".into(), PathBuf::from("/"), None);
+        busy.status = SessionStatus::Waiting;
+        busy.unread_bytes = 1234;
+        for session in [quiet, busy] {
+            s.sessions.insert(session.id, session);
+        }
+
+        let summary = summary(&s);
+        let listed: Vec<_> = summary
+            .sessions
+            .iter()
+            .map(|session| (session.name.as_str(), session.status, session.unread_bytes))
+            .collect();
+        assert_eq!(
+            listed,
+            vec![
+                ("busy", SessionStatus::Waiting, 1234),
+                ("quiet", SessionStatus::Stopped, 0)
+            ]
+        );
+    }
+}
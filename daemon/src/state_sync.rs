@@ -0,0 +1,279 @@
+// State sync - state.sync returns the sessions and groups with a generation
+// number, and given that generation back returns only what was created,
+// changed or removed since, so a client with hundreds of idle sessions doesn't
+// download them all on every refresh. Changes are found by comparing each
+// session and group with what the previous call saw (a hash of its JSON), so
+// nothing that changes state has to report to it. Generations go up by one per
+// call that finds a change and belong to one daemon run: each result carries
+// the run's tracker_id, and a `since` sent with another one gets everything.
+// (They also start at the start time in milliseconds, so a generation from an
+// earlier run without its id is older than any of these.) A session's
+// unread_bytes counts only as read or unread: the exact count moves with every
+// chunk of output and reaches clients as session:unread instead.
+
+use chrono::Utc;
+use serde::Serialize;
+use shared::{Session, StateSyncResult};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, VecDeque};
+use std::hash::Hasher;
+use std::sync::Mutex;
+use uuid::Uuid;
+
+use crate::group_status;
+use crate::state::AppState;
+
+/// Removals remembered; a client further behind than that gets everything
+const MAX_REMOVED: usize = 1000;
+
+pub struct SyncTracker {
+    id: Uuid,
+    state: Mutex<SyncState>,
+}
+
+struct SyncState {
+    generation: u64,
+    /// A `since` before this can't be answered with changes only
+    oldest: u64,
+    sessions: Seen,
+    groups: Seen,
+}
+
+/// What the last call saw of one kind of entity
+#[derive(Default)]
+struct Seen {
+    /// Hash of each entity's JSON and the generation it last changed in
+    entities: HashMap<Uuid, (u64, u64)>,
+    /// Removed entities and the generation they went in, oldest first
+    removed: VecDeque<(Uuid, u64)>,
+}
+
+impl Seen {
+    /// Compare with the current entities, marking changes with `generation`;
+    /// returns whether anything changed and the generation of any removal
+    /// that had to be forgotten
+    fn update(
+        &mut self,
+        current: impl Iterator<Item = (Uuid, u64)>,
+        generation: u64,
+    ) -> (bool, Option<u64>) {
+        let mut changed = false;
+        let mut present = HashMap::new();
+        for (id, hash) in current {
+            match self.entities.get(&id) {
+                Some((seen, _)) if *seen == hash => {}
+                Some(_) => changed = true,
+                None => {
+                    changed = true;
+                    // Back again (state.import): it's listed as changed instead
+                    self.removed.retain(|(removed, _)| *removed != id);
+                }
+            }
+            present.insert(id, hash);
+        }
+
+        let gone: Vec<Uuid> = self
+            .entities
+            .keys()
+            .filter(|id| !present.contains_key(id))
+            .copied()
+            .collect();
+        for id in gone {
+            self.entities.remove(&id);
+            self.removed.push_back((id, generation));
+            changed = true;
+        }
+        let mut forgotten = None;
+        while self.removed.len() > MAX_REMOVED {
+            forgotten = self.removed.pop_front().map(|(_, generation)| generation);
+        }
+
+        for (id, hash) in present {
+            let entry = self.entities.entry(id).or_insert((hash, generation));
+            if entry.0 != hash {
+                *entry = (hash, generation);
+            }
+        }
+        (changed, forgotten)
+    }
+
+    /// Ids changed after `since` (all of them for None)
+    fn changed_since(&self, since: Option<u64>) -> impl Iterator<Item = &Uuid> {
+        self.entities
+            .iter()
+            .filter(move |(_, (_, changed))| since.is_none_or(|since| *changed > since))
+            .map(|(id, _)| id)
+    }
+
+    fn removed_since(&self, since: u64) -> Vec<Uuid> {
+        self.removed
+            .iter()
+            .filter(|(_, removed)| *removed > since)
+            .map(|(id, _)| *id)
+            .collect()
+    }
+}
+
+fn hash_json<T: Serialize>(entity: &T) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    hasher.write(&serde_json::to_vec(entity).unwrap_or_default());
+    hasher.finish()
+}
+
+/// The session's hash with unread_bytes as just 0 or 1
+fn hash_session(session: &Session) -> u64 {
+    if session.unread_bytes <= 1 {
+        return hash_json(session);
+    }
+    let mut session = session.clone();
+    session.unread_bytes = 1;
+    hash_json(&session)
+}
+
+impl SyncTracker {
+    pub fn new() -> Self {
+        let start = Utc::now().timestamp_millis().max(0) as u64;
+        Self {
+            id: Uuid::new_v4(),
+            state: Mutex::new(SyncState {
+                generation: start,
+                oldest: start,
+                sessions: Seen::default(),
+                groups: Seen::default(),
+            }),
+        }
+    }
+
+    /// What changed in `s` after generation `since` of tracker `tracker_id`,
+    /// or everything
+    pub fn sync(
+        &self,
+        s: &AppState,
+        since: Option<u64>,
+        tracker_id: Option<Uuid>,
+    ) -> StateSyncResult {
+        let mut state = self.state.lock().unwrap();
+        let next = state.generation + 1;
+        let sessions = s
+            .sessions
+            .iter()
+            .map(|(id, session)| (*id, hash_session(session)));
+        let (sessions_changed, sessions_forgot) = state.sessions.update(sessions, next);
+        let groups = s.groups.iter().map(|(id, group)| (*id, hash_json(group)));
+        let (groups_changed, groups_forgot) = state.groups.update(groups, next);
+        if sessions_changed || groups_changed {
+            state.generation = next;
+        }
+        if let Some(forgot) = sessions_forgot.max(groups_forgot) {
+            state.oldest = state.oldest.max(forgot);
+        }
+
+        let since = since
+            .filter(|since| (state.oldest..=state.generation).contains(since))
+            .filter(|_| tracker_id.is_none_or(|tracker_id| tracker_id == self.id));
+        StateSyncResult {
+            generation: state.generation,
+            tracker_id: self.id,
+            full: since.is_none(),
+            sessions: state
+                .sessions
+                .changed_since(since)
+                .filter_map(|id| s.sessions.get(id).cloned())
+                .collect(),
+            groups: state
+                .groups
+                .changed_since(since)
+                .filter_map(|id| s.groups.get(id).cloned())
+                .collect(),
+            removed_sessions: since
+                .map_or_else(Vec::new, |since| state.sessions.removed_since(since)),
+            removed_groups: since.map_or_else(Vec::new, |since| state.groups.removed_since(since)),
+            statuses: group_status::group_statuses(s).into_values().collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn add_session(s: &mut AppState, name: &str) -> Uuid {
+        let session = Session::new(name.to_string(), PathBuf::from("/tmp"), None);
+        let id = session.id;
+        s.sessions.insert(id, session);
+        id
+    }
+
+    #[test]
+    fn test_delta_sync() {
+        let tracker = SyncTracker::new();
+        let mut s = AppState::default();
+        let (a, b) = (add_session(&mut s, "a"), add_session(&mut s, "b"));
+
+        let first = tracker.sync(&s, None, None);
+        assert!(first.full);
+        assert_eq!(first.sessions.len(), 2);
+
+        // Nothing changed: nothing listed, same generation
+        let again = tracker.sync(&s, Some(first.generation), Some(first.tracker_id));
+        assert!(!again.full);
+        assert!(again.sessions.is_empty());
+        assert_eq!(again.generation, first.generation);
+
+        s.sessions.get_mut(&a).unwrap().name = "renamed".to_string();
+        s.sessions.remove(&b);
+        let c = add_session(&mut s, "c");
+        let delta = tracker.sync(&s, Some(first.generation), Some(first.tracker_id));
+        assert!(!delta.full);
+        assert!(delta.generation > first.generation);
+        let mut ids: Vec<Uuid> = delta.sessions.iter().map(|session| session.id).collect();
+        ids.sort();
+        let mut expected = vec![a, c];
+        expected.sort();
+        assert_eq!(ids, expected);
+        assert_eq!(delta.removed_sessions, vec![b]);
+
+        // A client still at the first generation gets the same changes
+        let late = tracker.sync(&s, Some(first.generation), Some(first.tracker_id));
+        assert_eq!(late.sessions.len(), 2);
+        assert_eq!(late.removed_sessions, vec![b]);
+
+        // Generations this tracker never handed out get everything
+        assert!(tracker.sync(&s, Some(1), None).full);
+        assert!(tracker.sync(&s, Some(delta.generation + 1), None).full);
+        // So do generations from another daemon run
+        let restarted = SyncTracker::new();
+        assert_ne!(restarted.id, tracker.id);
+        assert!(
+            restarted
+                .sync(&s, Some(delta.generation), Some(tracker.id))
+                .full
+        );
+    }
+
+    #[test]
+    fn test_unread_count_is_not_a_change() {
+        let tracker = SyncTracker::new();
+        let mut s = AppState::default();
+        let a = add_session(&mut s, "a");
+        let first = tracker.sync(&s, None, None);
+        let since = |result: &StateSyncResult| (Some(result.generation), Some(result.tracker_id));
+
+        // Turning unread is a change, more output after that isn't
+        s.sessions.get_mut(&a).unwrap().unread_bytes = 10;
+        let (generation, id) = since(&first);
+        let unread = tracker.sync(&s, generation, id);
+        assert_eq!(unread.sessions.len(), 1);
+        assert_eq!(unread.sessions[0].unread_bytes, 10);
+        s.sessions.get_mut(&a).unwrap().unread_bytes = 5000;
+        let (generation, id) = since(&unread);
+        let more = tracker.sync(&s, generation, id);
+        assert!(more.sessions.is_empty());
+        assert_eq!(more.generation, unread.generation);
+
+        s.sessions.get_mut(&a).unwrap().unread_bytes = 0;
+        let (generation, id) = since(&more);
+        assert_eq!(tracker.sync(&s, generation, id).sessions.len(), 1);
+    }
+}
@@ -183,7 +183,10 @@ impl StatusTracker {
     /// Perform a status transition
     fn transition_to(&mut self, new_status: SessionStatus) -> Option<SessionStatus> {
         if self.last_status != new_status {
-            debug!("Status transition: {:?} -> {:?}", self.last_status, new_status);
+            debug!(
+                "Status transition: {:?} -> {:?}",
+                self.last_status, new_status
+            );
             self.last_status = new_status;
             self.last_change = Instant::now();
             Some(new_status)
@@ -203,7 +206,8 @@ impl StatusTracker {
 #[allow(dead_code)]
 fn strip_ansi(text: &str) -> String {
     lazy_static! {
-        static ref ANSI_RE: Regex = Regex::new(r"\x1b\[[0-9;]*[a-zA-Z]|\x1b\][^\x07]*\x07").unwrap();
+        static ref ANSI_RE: Regex =
+            Regex::new(r"\x1b\[[0-9;]*[a-zA-Z]|\x1b\][^\x07]*\x07").unwrap();
     }
     ANSI_RE.replace_all(text, "").to_string()
 }
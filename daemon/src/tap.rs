@@ -0,0 +1,131 @@
+// Debug tap - mirrors every client's requests and responses, and the event
+// stream, to inspector connections that called debug.tap, as debug:tap events
+// Secret-looking fields and typed input are redacted before anything is mirrored
+
+use chrono::Utc;
+use serde::Serialize;
+use serde_json::Value;
+use shared::{Event, TapDirection, TapRecord};
+use std::sync::atomic::{AtomicU64, Ordering};
+use tokio::sync::broadcast;
+
+/// Records buffered per inspector before it starts missing them
+const TAP_CAPACITY: usize = 1000;
+
+/// Field names (lowercased substrings) whose values are never mirrored
+const SECRET_KEYS: &[&str] = &[
+    "token",
+    "secret",
+    "password",
+    "passwd",
+    "api_key",
+    "apikey",
+    "authorization",
+    "cookie",
+    "credential",
+];
+
+/// Fields holding what was typed into a terminal, which can include passwords
+const INPUT_KEYS: &[&str] = &["input"];
+
+pub struct Tap {
+    tx: broadcast::Sender<TapRecord>,
+    next_client: AtomicU64,
+}
+
+impl Tap {
+    pub fn new() -> Self {
+        let (tx, _) = broadcast::channel(TAP_CAPACITY);
+        Self {
+            tx,
+            next_client: AtomicU64::new(1),
+        }
+    }
+
+    /// Id for a new connection, used to tell clients apart in tap records
+    pub fn client_id(&self) -> u64 {
+        self.next_client.fetch_add(1, Ordering::Relaxed)
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<TapRecord> {
+        self.tx.subscribe()
+    }
+
+    /// Mirror a message to inspectors (a no-op when nobody is tapping)
+    pub fn record<T: Serialize>(&self, client_id: u64, direction: TapDirection, message: &T) {
+        if self.tx.receiver_count() == 0 {
+            return;
+        }
+        let _ = self.tx.send(record(Some(client_id), direction, message));
+    }
+}
+
+impl Default for Tap {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A redacted tap record for `message`
+pub fn record<T: Serialize>(
+    client_id: Option<u64>,
+    direction: TapDirection,
+    message: &T,
+) -> TapRecord {
+    let mut message = serde_json::to_value(message).unwrap_or(Value::Null);
+    redact(&mut message);
+    TapRecord {
+        client_id,
+        direction,
+        at: Utc::now(),
+        message,
+    }
+}
+
+/// Events carrying terminal output, only mirrored when the inspector asks for them
+pub fn is_output_event(event: &Event) -> bool {
+    matches!(event, Event::PtyOutput(_) | Event::ReplayOutput(_))
+}
+
+fn redact(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map.iter_mut() {
+                let key = key.to_lowercase();
+                if SECRET_KEYS.iter().any(|secret| key.contains(secret)) {
+                    *value = Value::String("[redacted]".to_string());
+                } else if INPUT_KEYS.contains(&key.as_str()) && value.is_string() {
+                    let len = value.as_str().map_or(0, str::len);
+                    *value = Value::String(format!("[redacted {} bytes]", len));
+                } else {
+                    redact(value);
+                }
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(redact),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_redact() {
+        let mut message = json!({
+            "method": "session.input",
+            "params": {"session_id": "abc", "input": "aHVudGVyMg=="},
+            "env": [{"ANTHROPIC_API_KEY": "sk-1", "Hook_Token": "t"}],
+            "name": "tokenizer",
+        });
+        redact(&mut message);
+        assert_eq!(message["params"]["input"], "[redacted 12 bytes]");
+        assert_eq!(message["params"]["session_id"], "abc");
+        assert_eq!(message["env"][0]["ANTHROPIC_API_KEY"], "[redacted]");
+        assert_eq!(message["env"][0]["Hook_Token"], "[redacted]");
+        // Only keys are matched, not values
+        assert_eq!(message["name"], "tokenizer");
+    }
+}
@@ -0,0 +1,290 @@
+// Task launcher - finds Makefile targets, justfile recipes and package.json
+// scripts in a session's working directory, and runs them either in the session
+// (typed into claude's `!` bash mode) or headlessly, with the outcome recorded
+// in the session's history.
+
+use anyhow::Result;
+use shared::{Event, HistoryKind, TaskFinishedData, TaskInfo, TaskRunner};
+use std::fs;
+use std::path::Path;
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::process::Command;
+use tokio::sync::broadcast;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::claude_resolver::ClaudeResolver;
+use crate::history;
+
+const MAKEFILES: &[&str] = &["GNUmakefile", "makefile", "Makefile"];
+const JUSTFILES: &[&str] = &["justfile", "Justfile", ".justfile"];
+
+/// Lockfile that picks the package manager for package.json scripts (npm otherwise)
+const LOCKFILES: &[(&str, &str)] = &[
+    ("pnpm-lock.yaml", "pnpm"),
+    ("yarn.lock", "yarn"),
+    ("bun.lockb", "bun"),
+    ("bun.lock", "bun"),
+];
+
+/// Headless tasks are killed after this long (dev servers never exit on their own)
+const HEADLESS_TIMEOUT_SECS: u64 = 30 * 60;
+
+/// Output kept in history and session:task_finished
+const MAX_OUTPUT_BYTES: usize = 16 * 1024;
+
+/// Every task defined in `dir`: make targets, then just recipes, then scripts
+pub fn detect(dir: &Path) -> Vec<TaskInfo> {
+    let mut tasks = Vec::new();
+
+    if let Some(text) = read_first(dir, MAKEFILES) {
+        tasks.extend(make_targets(&text).into_iter().map(|name| TaskInfo {
+            command: format!("make {}", quote(&name)),
+            name,
+            runner: TaskRunner::Make,
+        }));
+    }
+    if let Some(text) = read_first(dir, JUSTFILES) {
+        tasks.extend(just_recipes(&text).into_iter().map(|name| TaskInfo {
+            command: format!("just {}", quote(&name)),
+            name,
+            runner: TaskRunner::Just,
+        }));
+    }
+    if let Some(text) = read_first(dir, &["package.json"]) {
+        let manager = LOCKFILES
+            .iter()
+            .find(|(lockfile, _)| dir.join(lockfile).exists())
+            .map_or("npm", |(_, manager)| manager);
+        tasks.extend(package_scripts(&text).into_iter().map(|name| TaskInfo {
+            command: format!("{} run {}", manager, quote(&name)),
+            name,
+            runner: TaskRunner::Package,
+        }));
+    }
+
+    tasks
+}
+
+/// The task called `name` in `dir`; `runner` settles names defined more than once
+pub fn find(dir: &Path, name: &str, runner: Option<TaskRunner>) -> Result<TaskInfo> {
+    let mut matches: Vec<TaskInfo> = detect(dir)
+        .into_iter()
+        .filter(|task| task.name == name && runner.is_none_or(|r| task.runner == r))
+        .collect();
+    match matches.len() {
+        0 => anyhow::bail!("Task not found: {}", name),
+        1 => Ok(matches.remove(0)),
+        _ => anyhow::bail!(
+            "Task '{}' is defined by several runners; pass runner to pick one",
+            name
+        ),
+    }
+}
+
+/// Run a task in the background, then record it in history and emit
+/// session:task_finished
+pub async fn run_headless(
+    session_id: Uuid,
+    task: TaskInfo,
+    dir: &Path,
+    event_tx: broadcast::Sender<Event>,
+) {
+    info!("Running task '{}' for session {}", task.command, session_id);
+    let env = ClaudeResolver::new().build_env();
+    let mut cmd = Command::new("sh");
+    cmd.arg("-c")
+        .arg(&task.command)
+        .current_dir(dir)
+        .stdin(Stdio::null())
+        .kill_on_drop(true);
+    if let Some(path) = env.get("PATH") {
+        cmd.env("PATH", path);
+    }
+
+    let (exit_code, output) = match tokio::time::timeout(
+        Duration::from_secs(HEADLESS_TIMEOUT_SECS),
+        cmd.output(),
+    )
+    .await
+    {
+        Ok(Ok(output)) => {
+            let mut combined = output.stdout;
+            combined.extend_from_slice(&output.stderr);
+            (output.status.code(), tail(&combined))
+        }
+        Ok(Err(e)) => (None, format!("Failed to run task: {}", e)),
+        Err(_) => (
+            None,
+            format!("Task killed after {} minutes", HEADLESS_TIMEOUT_SECS / 60),
+        ),
+    };
+    if exit_code != Some(0) {
+        warn!(
+            "Task '{}' for session {} failed ({:?})",
+            task.command, session_id, exit_code
+        );
+    }
+
+    history::record(
+        session_id,
+        HistoryKind::TaskRun {
+            name: task.name.clone(),
+            command: task.command.clone(),
+            headless: true,
+            exit_code,
+            output: Some(output.clone()),
+        },
+    );
+    let event = Event::TaskFinished(TaskFinishedData {
+        session_id,
+        name: task.name,
+        command: task.command,
+        exit_code,
+        output,
+    });
+    let _ = event_tx.send(event);
+}
+
+fn read_first(dir: &Path, names: &[&str]) -> Option<String> {
+    names
+        .iter()
+        .find_map(|name| fs::read_to_string(dir.join(name)).ok())
+}
+
+/// Quote a task name for the shell only when it needs it
+fn quote(name: &str) -> String {
+    if name
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || "_-.:/".contains(c))
+    {
+        name.to_string()
+    } else {
+        format!("'{}'", name.replace('\'', "'\\''"))
+    }
+}
+
+/// Last MAX_OUTPUT_BYTES of output, as text
+fn tail(output: &[u8]) -> String {
+    let start = output.len().saturating_sub(MAX_OUTPUT_BYTES);
+    String::from_utf8_lossy(&output[start..]).into_owned()
+}
+
+/// Explicit targets, skipping special (.PHONY), pattern and variable-built ones
+fn make_targets(text: &str) -> Vec<String> {
+    let mut targets = Vec::new();
+    for line in text.lines() {
+        if line.starts_with(['\t', ' ', '#', '.']) {
+            continue;
+        }
+        let Some((names, rest)) = line.split_once(':') else {
+            continue;
+        };
+        // `VAR := value` and `VAR = a:b` are assignments
+        if rest.starts_with('=') || names.contains(['=', '$', '%']) {
+            continue;
+        }
+        for name in names.split_whitespace() {
+            if !targets.iter().any(|t| t == name) {
+                targets.push(name.to_string());
+            }
+        }
+    }
+    targets
+}
+
+/// Public recipes (a leading `_` or a `[private]` attribute hides one)
+fn just_recipes(text: &str) -> Vec<String> {
+    const KEYWORDS: &[&str] = &["alias", "export", "import", "mod", "set"];
+
+    let mut recipes = Vec::new();
+    // Attributes apply to the recipe that follows them
+    let mut private = false;
+    for line in text.lines() {
+        if line.starts_with('[') {
+            private |= line.contains("private");
+            continue;
+        }
+        if line.starts_with([' ', '\t', '#']) || line.trim().is_empty() {
+            continue;
+        }
+        let Some((head, rest)) = line.trim_start_matches('@').split_once(':') else {
+            continue;
+        };
+        if rest.starts_with('=') {
+            continue;
+        }
+        let Some(name) = head.split_whitespace().next() else {
+            continue;
+        };
+        let valid = name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+        if valid && !name.starts_with('_') && !private && !KEYWORDS.contains(&name) {
+            recipes.push(name.to_string());
+        }
+        private = false;
+    }
+    recipes
+}
+
+fn package_scripts(text: &str) -> Vec<String> {
+    let Ok(package) = serde_json::from_str::<serde_json::Value>(text) else {
+        return Vec::new();
+    };
+    package
+        .get("scripts")
+        .and_then(|scripts| scripts.as_object())
+        .map(|scripts| scripts.keys().cloned().collect())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_tasks() {
+        let dir = std::env::temp_dir().join(format!("tasks-test-{}", Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("Makefile"),
+            "CC := gcc\nFLAGS = -a:b\n.PHONY: build test\n\nbuild test: deps\n\tcargo build\n%.o: %.c\n\t$(CC)\n$(OUT): x\nlint:\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.join("justfile"),
+            "set shell := [\"bash\", \"-c\"]\nversion := \"1\"\nalias b := build\n\n# Build it\nbuild target='all':\n    cargo build\n@fmt:\n    cargo fmt\n_helper:\n    true\n[private]\nhidden:\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.join("package.json"),
+            r#"{"name": "x", "scripts": {"dev": "vite", "test:unit": "vitest", "odd name": "x"}}"#,
+        )
+        .unwrap();
+        fs::write(dir.join("pnpm-lock.yaml"), "").unwrap();
+
+        let commands: Vec<String> = detect(&dir).into_iter().map(|t| t.command).collect();
+        assert_eq!(
+            commands,
+            vec![
+                "make build",
+                "make test",
+                "make lint",
+                "just build",
+                "just fmt",
+                "pnpm run dev",
+                "pnpm run 'odd name'",
+                "pnpm run test:unit",
+            ]
+        );
+
+        assert!(find(&dir, "build", None).is_err());
+        let build = find(&dir, "build", Some(TaskRunner::Just)).unwrap();
+        assert_eq!(build.command, "just build");
+        assert!(find(&dir, "deploy", None).is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,212 @@
+// TLS for the HTTP API - with http.tls the listener serves HTTPS, using the
+// certificate in http.tls_cert / http.tls_key or else a self-signed one made in
+// the data dir (tls.crt, tls.key) on first use. The self-signed certificate is
+// kept across restarts, so clients can trust it once: pass it to them (e.g.
+// `curl --cacert tls.crt`), or check the SHA-256 fingerprint logged on every
+// start against what a browser shows. Handshakes run off the accept loop, so a
+// client that stalls in one doesn't hold up the others.
+
+use anyhow::{Context, Result};
+use axum::serve::Listener;
+use sha2::{Digest, Sha256};
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc;
+use tokio_rustls::rustls::crypto::ring;
+use tokio_rustls::rustls::pki_types::pem::PemObject;
+use tokio_rustls::rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use tokio_rustls::rustls::ServerConfig;
+use tokio_rustls::server::TlsStream;
+use tokio_rustls::TlsAcceptor;
+use tracing::{debug, info, warn};
+
+use crate::auth;
+use crate::config::HttpConfig;
+
+/// How long a client gets to finish the TLS handshake
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Handshaken connections waiting for the server to take them
+const ACCEPT_BACKLOG: usize = 64;
+
+/// Server config for `http`'s certificate, and that certificate's fingerprint
+pub fn server_config(http: &HttpConfig) -> Result<(Arc<ServerConfig>, String)> {
+    let (cert_path, key_path) = match (&http.tls_cert, &http.tls_key) {
+        (Some(cert), Some(key)) => (cert.clone(), key.clone()),
+        _ => self_signed()?,
+    };
+    let certs = CertificateDer::pem_file_iter(&cert_path)
+        .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+        .with_context(|| format!("Reading TLS certificate {:?}", cert_path))?;
+    let Some(leaf) = certs.first() else {
+        anyhow::bail!("No certificate in {:?}", cert_path);
+    };
+    let fingerprint = fingerprint(leaf);
+    let key = PrivateKeyDer::from_pem_file(&key_path)
+        .with_context(|| format!("Reading TLS key {:?}", key_path))?;
+
+    let mut config = ServerConfig::builder_with_provider(Arc::new(ring::default_provider()))
+        .with_safe_default_protocol_versions()?
+        .with_no_client_auth()
+        .with_single_cert(certs, key)?;
+    config.alpn_protocols = vec![b"http/1.1".to_vec()];
+    Ok((Arc::new(config), fingerprint))
+}
+
+/// SHA-256 fingerprint of a DER certificate, written like
+/// `openssl x509 -fingerprint -sha256` does, e.g. `AB:12:...`
+fn fingerprint(cert: &[u8]) -> String {
+    Sha256::digest(cert)
+        .iter()
+        .map(|b| format!("{:02X}", b))
+        .collect::<Vec<_>>()
+        .join(":")
+}
+
+/// Paths of the self-signed certificate and key, made if they don't exist
+fn self_signed() -> Result<(PathBuf, PathBuf)> {
+    let dir = shared::get_data_dir()?;
+    let (cert_path, key_path) = (dir.join("tls.crt"), dir.join("tls.key"));
+    if cert_path.exists() && key_path.exists() {
+        return Ok((cert_path, key_path));
+    }
+    generate(&cert_path, &key_path)?;
+    info!("Created a self-signed TLS certificate in {:?}", cert_path);
+    Ok((cert_path, key_path))
+}
+
+fn generate(cert_path: &Path, key_path: &Path) -> Result<()> {
+    let mut names = vec!["localhost".to_string(), "127.0.0.1".to_string()];
+    if let Ok(hostname) = whoami::fallible::hostname() {
+        names.push(hostname);
+    }
+    let certified = rcgen::generate_simple_self_signed(names)?;
+    auth::write_private(key_path, &certified.key_pair.serialize_pem())?;
+    std::fs::write(cert_path, certified.cert.pem())?;
+    Ok(())
+}
+
+/// A TCP listener that hands axum connections after their TLS handshake
+pub struct TlsListener {
+    connections: mpsc::Receiver<(TlsStream<TcpStream>, SocketAddr)>,
+    local_addr: SocketAddr,
+}
+
+impl TlsListener {
+    pub fn new(listener: TcpListener, config: Arc<ServerConfig>) -> Result<Self> {
+        let local_addr = listener.local_addr()?;
+        let (tx, connections) = mpsc::channel(ACCEPT_BACKLOG);
+        let acceptor = TlsAcceptor::from(config);
+        tokio::spawn(async move {
+            loop {
+                let (stream, addr) = tokio::select! {
+                    accepted = listener.accept() => match accepted {
+                        Ok(accepted) => accepted,
+                        Err(e) => {
+                            warn!("HTTPS accept error: {}", e);
+                            tokio::time::sleep(Duration::from_millis(100)).await;
+                            continue;
+                        }
+                    },
+                    // The server is gone
+                    _ = tx.closed() => break,
+                };
+                let (acceptor, tx) = (acceptor.clone(), tx.clone());
+                tokio::spawn(async move {
+                    match tokio::time::timeout(HANDSHAKE_TIMEOUT, acceptor.accept(stream)).await {
+                        Ok(Ok(stream)) => {
+                            let _ = tx.send((stream, addr)).await;
+                        }
+                        Ok(Err(e)) => debug!("TLS handshake with {} failed: {}", addr, e),
+                        Err(_) => debug!("TLS handshake with {} timed out", addr),
+                    }
+                });
+            }
+        });
+        Ok(Self {
+            connections,
+            local_addr,
+        })
+    }
+}
+
+impl Listener for TlsListener {
+    type Io = TlsStream<TcpStream>;
+    type Addr = SocketAddr;
+
+    async fn accept(&mut self) -> (Self::Io, Self::Addr) {
+        match self.connections.recv().await {
+            Some(connection) => connection,
+            // Only if the accept loop ended, which it doesn't while we're here
+            None => std::future::pending().await,
+        }
+    }
+
+    fn local_addr(&self) -> std::io::Result<Self::Addr> {
+        Ok(self.local_addr)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio_rustls::rustls::pki_types::ServerName;
+    use tokio_rustls::rustls::{ClientConfig, RootCertStore};
+    use tokio_rustls::TlsConnector;
+
+    #[tokio::test]
+    async fn test_self_signed_handshake() {
+        let dir = std::env::temp_dir().join(format!("cm-tls-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let cert_path = dir.join("tls.crt");
+        let http = HttpConfig {
+            tls: true,
+            tls_cert: Some(cert_path.clone()),
+            tls_key: Some(dir.join("tls.key")),
+            ..Default::default()
+        };
+        generate(&cert_path, &dir.join("tls.key")).unwrap();
+        let (config, printed) = server_config(&http).unwrap();
+        let cert = CertificateDer::from_pem_file(&cert_path).unwrap();
+        assert_eq!(printed, fingerprint(&cert));
+        assert_eq!(printed.len(), 32 * 3 - 1);
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let mut listener = TlsListener::new(listener, config).unwrap();
+        tokio::spawn(async move {
+            loop {
+                let (mut stream, _) = Listener::accept(&mut listener).await;
+                let _ = stream.write_all(b"hi").await;
+                let _ = stream.shutdown().await;
+            }
+        });
+
+        // A client given the certificate trusts it, as curl --cacert would
+        let connect = |roots: RootCertStore| async move {
+            let config = ClientConfig::builder_with_provider(Arc::new(ring::default_provider()))
+                .with_safe_default_protocol_versions()
+                .unwrap()
+                .with_root_certificates(roots)
+                .with_no_client_auth();
+            let connector = TlsConnector::from(Arc::new(config));
+            let tcp = TcpStream::connect(addr).await.unwrap();
+            let name = ServerName::try_from("localhost").unwrap();
+            let mut stream = connector.connect(name, tcp).await?;
+            let mut reply = String::new();
+            stream.read_to_string(&mut reply).await?;
+            Ok::<_, std::io::Error>(reply)
+        };
+        let mut roots = RootCertStore::empty();
+        roots.add(cert).unwrap();
+        assert_eq!(connect(roots).await.unwrap(), "hi");
+        // Without it the certificate is just self-signed
+        assert!(connect(RootCertStore::empty()).await.is_err());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,266 @@
+// Transcript reader - derives message history and turn state from Claude Code's
+// own session logs (~/.claude/projects/<project>/<claude_session_id>.jsonl)
+// More reliable than scraping terminal output, which depends on TUI rendering
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use serde_json::Value;
+use shared::{SessionStatus, TranscriptMessage, TranscriptState};
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+/// How much of the end of the file to read when only the latest state matters
+const TAIL_BYTES: u64 = 64 * 1024;
+
+/// One line of a transcript file (only the fields we use)
+#[derive(Debug, Deserialize)]
+struct RawEntry {
+    #[serde(rename = "type")]
+    kind: String,
+    message: Option<RawMessage>,
+    timestamp: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RawMessage {
+    role: Option<String>,
+    #[serde(default)]
+    content: Value,
+    stop_reason: Option<String>,
+}
+
+/// Locate the transcript for a Claude session id under ~/.claude/projects
+/// A fork into a worktree leaves a copy in another project; the one still
+/// being written (the most recently modified) wins.
+pub fn find_transcript(claude_session_id: &str) -> Option<PathBuf> {
+    let projects = dirs::home_dir()?.join(".claude").join("projects");
+    let pattern = projects
+        .join("*")
+        .join(format!("{}.jsonl", claude_session_id));
+    glob::glob(&pattern.to_string_lossy())
+        .ok()?
+        .flatten()
+        .max_by_key(|path| fs::metadata(path).and_then(|m| m.modified()).ok())
+}
+
+/// Claude's name for the project of a working directory: the path with
+/// everything but ASCII letters and digits turned into '-'
+fn project_name(working_dir: &Path) -> String {
+    working_dir
+        .to_string_lossy()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect()
+}
+
+/// Copy a Claude session's transcript into the project of `working_dir`, so
+/// `claude --resume` started there finds the conversation
+pub fn copy_to_project(claude_session_id: &str, working_dir: &Path) -> Result<()> {
+    let source =
+        find_transcript(claude_session_id).context("Transcript of the Claude session not found")?;
+    let dir = dirs::home_dir()
+        .context("No home directory")?
+        .join(".claude")
+        .join("projects")
+        .join(project_name(working_dir));
+    fs::create_dir_all(&dir)?;
+    fs::copy(&source, dir.join(format!("{}.jsonl", claude_session_id)))
+        .context("Failed to copy transcript")?;
+    Ok(())
+}
+
+/// Parse a single line into a message, skipping non-message entries
+fn parse_line(line: &str) -> Option<(TranscriptMessage, Option<String>)> {
+    let entry: RawEntry = serde_json::from_str(line).ok()?;
+    if entry.kind != "user" && entry.kind != "assistant" {
+        return None;
+    }
+    let message = entry.message?;
+
+    let mut text = Vec::new();
+    let mut tool_calls = Vec::new();
+    let mut tool_result = false;
+    match &message.content {
+        Value::String(s) => text.push(s.clone()),
+        Value::Array(blocks) => {
+            for block in blocks {
+                match block.get("type").and_then(Value::as_str) {
+                    Some("text") => {
+                        if let Some(t) = block.get("text").and_then(Value::as_str) {
+                            text.push(t.to_string());
+                        }
+                    }
+                    Some("tool_use") => {
+                        if let Some(name) = block.get("name").and_then(Value::as_str) {
+                            tool_calls.push(name.to_string());
+                        }
+                    }
+                    Some("tool_result") => tool_result = true,
+                    _ => {}
+                }
+            }
+        }
+        _ => {}
+    }
+
+    let role = message.role.unwrap_or(entry.kind);
+    Some((
+        TranscriptMessage {
+            role,
+            text: text.join("\n"),
+            tool_calls,
+            tool_result,
+            timestamp: entry.timestamp,
+        },
+        message.stop_reason,
+    ))
+}
+
+/// Derive the turn state from the latest message
+fn state_of(message: &TranscriptMessage, stop_reason: Option<&str>) -> TranscriptState {
+    if message.role != "assistant" {
+        // A prompt or tool result was just sent - Claude is working on it
+        return TranscriptState::Working;
+    }
+    match stop_reason {
+        Some("end_turn") | Some("stop_sequence") => TranscriptState::Complete,
+        Some("tool_use") => TranscriptState::ToolUse,
+        _ if !message.tool_calls.is_empty() => TranscriptState::ToolUse,
+        _ => TranscriptState::Working,
+    }
+}
+
+/// Parse a whole transcript into messages and the current turn state
+pub fn parse(content: &str) -> (Vec<TranscriptMessage>, Option<TranscriptState>) {
+    let mut messages = Vec::new();
+    let mut state = None;
+    for line in content.lines() {
+        if let Some((message, stop_reason)) = parse_line(line) {
+            state = Some(state_of(&message, stop_reason.as_deref()));
+            messages.push(message);
+        }
+    }
+    (messages, state)
+}
+
+/// Read the latest message and current turn state from the end of a transcript file
+pub fn tail(path: &Path) -> Option<(TranscriptMessage, TranscriptState)> {
+    let mut file = File::open(path).ok()?;
+    let len = file.metadata().ok()?.len();
+    let start = len.saturating_sub(TAIL_BYTES);
+    file.seek(SeekFrom::Start(start)).ok()?;
+    let mut buf = Vec::new();
+    file.read_to_end(&mut buf).ok()?;
+    let text = String::from_utf8_lossy(&buf);
+
+    // The first line may be cut off when starting mid-file; parse_line skips it
+    text.lines().rev().find_map(|line| {
+        parse_line(line).map(|(message, stop_reason)| {
+            let state = state_of(&message, stop_reason.as_deref());
+            (message, state)
+        })
+    })
+}
+
+/// Total (input, output) tokens billed for a transcript
+/// Input includes cache reads and writes. A response split over several lines
+/// repeats its usage on each, so messages are counted once by id.
+pub fn token_usage(content: &str) -> (u64, u64) {
+    let mut seen = HashSet::new();
+    let (mut input, mut output) = (0, 0);
+    for line in content.lines() {
+        let Ok(entry) = serde_json::from_str::<Value>(line) else {
+            continue;
+        };
+        let Some(message) = entry.get("message") else {
+            continue;
+        };
+        let Some(usage) = message.get("usage") else {
+            continue;
+        };
+        if let Some(id) = message.get("id").and_then(Value::as_str) {
+            if !seen.insert(id.to_string()) {
+                continue;
+            }
+        }
+        let count = |key: &str| usage.get(key).and_then(Value::as_u64).unwrap_or(0);
+        input += count("input_tokens")
+            + count("cache_creation_input_tokens")
+            + count("cache_read_input_tokens");
+        output += count("output_tokens");
+    }
+    (input, output)
+}
+
+/// Map a transcript state to a session status
+/// ToolUse is left to hooks, which know whether approval is needed
+pub fn status_for(state: TranscriptState) -> Option<SessionStatus> {
+    match state {
+        TranscriptState::Working => Some(SessionStatus::Running),
+        TranscriptState::Complete => Some(SessionStatus::Waiting),
+        TranscriptState::ToolUse => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TRANSCRIPT: &str = r#"{"type":"summary","summary":"Fix tests"}
+{"type":"user","message":{"role":"user","content":"fix the tests"},"timestamp":"2026-01-01T00:00:00Z"}
+{"type":"assistant","message":{"role":"assistant","content":[{"type":"text","text":"Running them"},{"type":"tool_use","id":"t1","name":"Bash","input":{}}],"stop_reason":"tool_use"}}
+{"type":"user","message":{"role":"user","content":[{"type":"tool_result","tool_use_id":"t1","content":"ok"}]}}
+{"type":"assistant","message":{"role":"assistant","content":[{"type":"text","text":"All green"}],"stop_reason":"end_turn"}}"#;
+
+    #[test]
+    fn test_parse_transcript() {
+        let (messages, state) = parse(TRANSCRIPT);
+        assert_eq!(messages.len(), 4);
+        assert_eq!(messages[0].text, "fix the tests");
+        assert!(messages[0].timestamp.is_some());
+        assert_eq!(messages[1].tool_calls, vec!["Bash".to_string()]);
+        assert!(messages[2].tool_result);
+        assert_eq!(messages[3].text, "All green");
+        assert_eq!(state, Some(TranscriptState::Complete));
+    }
+
+    #[test]
+    fn test_state_progression() {
+        let lines: Vec<&str> = TRANSCRIPT.lines().collect();
+        let state_after = |n: usize| parse(&lines[..n].join("\n")).1;
+        assert_eq!(state_after(1), None);
+        assert_eq!(state_after(2), Some(TranscriptState::Working));
+        assert_eq!(state_after(3), Some(TranscriptState::ToolUse));
+        assert_eq!(state_after(4), Some(TranscriptState::Working));
+    }
+
+    #[test]
+    fn test_token_usage() {
+        let content = r#"{"type":"assistant","message":{"id":"m1","role":"assistant","content":[],"usage":{"input_tokens":10,"cache_read_input_tokens":90,"output_tokens":5}}}
+{"type":"assistant","message":{"id":"m1","role":"assistant","content":[],"usage":{"input_tokens":10,"cache_read_input_tokens":90,"output_tokens":5}}}
+{"type":"user","message":{"role":"user","content":"next"}}
+{"type":"assistant","message":{"id":"m2","role":"assistant","content":[],"usage":{"input_tokens":20,"output_tokens":7}}}"#;
+        assert_eq!(token_usage(content), (120, 12));
+        assert_eq!(token_usage(TRANSCRIPT), (0, 0));
+    }
+
+    #[test]
+    fn test_project_name() {
+        assert_eq!(
+            project_name(Path::new("/home/me/my.app-feature_x")),
+            "-home-me-my-app-feature-x"
+        );
+    }
+
+    #[test]
+    fn test_tail() {
+        let path = std::env::temp_dir().join(format!("{}.jsonl", uuid::Uuid::new_v4()));
+        std::fs::write(&path, TRANSCRIPT).unwrap();
+        let (message, state) = tail(&path).unwrap();
+        assert_eq!(message.text, "All green");
+        assert_eq!(state, TranscriptState::Complete);
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,128 @@
+// Tunnel - with tunnel.enabled the daemon also speaks its socket protocol on
+// 127.0.0.1:tunnel.port, so a GUI on another machine can attach through an SSH
+// port forward (Tailscale SSH works the same) without the daemon listening on
+// the network. Tunnel connections must send daemon.auth with the token in
+// tunnel.token first. `claude-master-daemon --tunnel` prints the recipe.
+
+use anyhow::Result;
+use shared::{REMOTE_DAEMON_ENV, REMOTE_TOKEN_ENV};
+use std::net::{Ipv4Addr, SocketAddr};
+use std::sync::Arc;
+use tokio::net::TcpListener;
+use tracing::{error, info};
+
+use crate::auth;
+use crate::config::{get_config_path, load_config};
+use crate::ipc::{self, IpcContext, Origin};
+
+fn tunnel_addr(port: u16) -> SocketAddr {
+    (Ipv4Addr::LOCALHOST, port).into()
+}
+
+/// Accept tunnel connections until the daemon shuts down
+pub async fn serve(port: u16, ctx: Arc<IpcContext>) -> Result<()> {
+    let listener = TcpListener::bind(tunnel_addr(port)).await?;
+    info!("Tunnel listening on {}", tunnel_addr(port));
+
+    let mut shutdown_rx = ctx.shutdown_tx.subscribe();
+    loop {
+        let accepted = tokio::select! {
+            accepted = listener.accept() => accepted,
+            _ = shutdown_rx.wait_for(Option::is_some) => break,
+        };
+        match accepted {
+            Ok((stream, _)) => {
+                // Terminal output is many small writes; don't batch them up
+                let _ = stream.set_nodelay(true);
+                let ctx = ctx.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = ipc::serve_connection(stream, &ctx, Origin::Tunnel).await {
+                        error!("Tunnel connection error: {}", e);
+                    }
+                });
+            }
+            Err(e) => error!("Tunnel accept error: {}", e),
+        }
+    }
+    Ok(())
+}
+
+/// Print how to attach a GUI on another machine (for `--tunnel`)
+pub fn print_recipe() -> Result<()> {
+    let config = load_config()?;
+    let port = config.tunnel.port;
+    let user = whoami::username();
+    let host = whoami::fallible::hostname().unwrap_or_else(|_| "this-machine".to_string());
+
+    if !config.tunnel.enabled {
+        println!(
+            "The tunnel is off. Turn it on in {:?} and restart the daemon:",
+            get_config_path()?
+        );
+        println!();
+        println!("    [tunnel]");
+        println!("    enabled = true");
+        println!();
+    }
+    println!(
+        "The daemon takes remote GUIs on {} (this machine only).",
+        tunnel_addr(port)
+    );
+    println!("On the machine with the GUI, forward a local port to it:");
+    println!();
+    println!("    ssh -N -L {port}:127.0.0.1:{port} {user}@{host}");
+    println!();
+    println!(
+        "(On a tailnet, {host} can be this machine's Tailscale name.) Then start the GUI with:"
+    );
+    println!();
+    println!(
+        "    {}=127.0.0.1:{} {}={}",
+        REMOTE_DAEMON_ENV,
+        port,
+        REMOTE_TOKEN_ENV,
+        auth::tunnel_token()?
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::TokenAuth;
+    use crate::config::TokenConfig;
+    use shared::Role;
+
+    #[test]
+    fn test_tunnel_connections_authenticate() {
+        let tokens = [TokenConfig {
+            name: "dashboard".to_string(),
+            token: "viewer-token-0123".to_string(),
+            role: Role::Viewer,
+        }];
+        let role = |auth: &TokenAuth, token| Origin::Tunnel.token_role(auth, token, &tokens);
+
+        // Even with daemon.require_token off, only the socket is let straight in
+        let open = TokenAuth::with_tokens(None, Some("tunnel-token-4567"));
+        assert_eq!(Origin::Socket.initial_role(&open), Some(Role::Admin));
+        assert_eq!(Origin::Tunnel.initial_role(&open), None);
+        assert_eq!(role(&open, "tunnel-token-4567"), Some(Role::Admin));
+        assert_eq!(role(&open, "viewer-token-0123"), Some(Role::Viewer));
+        assert_eq!(role(&open, "tunnel-token-456"), None);
+        assert_eq!(role(&open, ""), None);
+
+        // The socket's token doesn't work over the tunnel, nor the other way round
+        let required = TokenAuth::with_tokens(Some("socket-token-89ab"), Some("tunnel-token-4567"));
+        assert_eq!(Origin::Tunnel.initial_role(&required), None);
+        assert_eq!(role(&required, "socket-token-89ab"), None);
+        assert_eq!(
+            Origin::Socket.token_role(&required, "tunnel-token-4567", &tokens),
+            None
+        );
+
+        // Without tunnel.enabled there is no tunnel token to match
+        let off = TokenAuth::with_tokens(None, None);
+        assert_eq!(role(&off, ""), None);
+        assert_eq!(role(&off, "tunnel-token-4567"), None);
+    }
+}
@@ -0,0 +1,212 @@
+// Turn latency tracking - times each prompt sent through session.input from
+// dispatch to Claude's first reply and to the end of the turn
+// Finished turns go to the session timeline and session:turn_completed
+
+use chrono::{DateTime, Utc};
+use shared::{
+    Event, HistoryEntry, HistoryKind, SessionStatus, TurnMetrics, TurnMetricsData, TurnRecord,
+    TurnStats,
+};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Instant;
+use tokio::sync::{broadcast, Mutex};
+use tracing::{debug, warn};
+use uuid::Uuid;
+
+use crate::config::SharedConfig;
+use crate::history;
+
+/// A prompt that is still being worked on
+struct Turn {
+    started: Instant,
+    started_at: DateTime<Utc>,
+    first_output_ms: Option<u64>,
+    /// Whether the session has gone Running since the prompt was sent; a Waiting
+    /// status before that is the previous turn's (or an empty prompt)
+    saw_running: bool,
+}
+
+pub struct TurnTracker {
+    config: SharedConfig,
+    inflight: Mutex<HashMap<Uuid, Turn>>,
+}
+
+impl TurnTracker {
+    pub fn new(config: SharedConfig) -> Self {
+        Self {
+            config,
+            inflight: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// A prompt was submitted; records it on the timeline and starts a turn
+    /// unless one is already running
+    pub async fn prompt_sent(&self, session_id: Uuid, text: Option<String>) {
+        history::record(session_id, HistoryKind::PromptSent { text });
+        self.inflight
+            .lock()
+            .await
+            .entry(session_id)
+            .or_insert_with(|| Turn {
+                started: Instant::now(),
+                started_at: Utc::now(),
+                first_output_ms: None,
+                saw_running: false,
+            });
+    }
+
+    /// Claude wrote a reply (seen in the transcript at `written_at`)
+    /// Replies written before the prompt was sent belong to an earlier turn.
+    pub async fn first_output(&self, session_id: Uuid, written_at: Option<DateTime<Utc>>) {
+        if let Some(turn) = self.inflight.lock().await.get_mut(&session_id) {
+            let current = written_at.is_none_or(|ts| ts >= turn.started_at);
+            if current && turn.first_output_ms.is_none() {
+                turn.first_output_ms = Some(turn.started.elapsed().as_millis() as u64);
+            }
+        }
+    }
+
+    /// Follow status changes to find where turns end
+    pub async fn run(self: Arc<Self>, event_tx: broadcast::Sender<Event>) {
+        let mut event_rx = event_tx.subscribe();
+
+        loop {
+            let event = match event_rx.recv().await {
+                Ok(event) => event,
+                Err(broadcast::error::RecvError::Lagged(n)) => {
+                    warn!("Turn tracker lagged, skipped {} events", n);
+                    continue;
+                }
+                Err(broadcast::error::RecvError::Closed) => return,
+            };
+            let Event::StatusChanged(data) = event else {
+                continue;
+            };
+
+            let finished = {
+                let mut inflight = self.inflight.lock().await;
+                match data.status {
+                    SessionStatus::Running => {
+                        if let Some(turn) = inflight.get_mut(&data.session_id) {
+                            turn.saw_running = true;
+                        }
+                        None
+                    }
+                    SessionStatus::Waiting | SessionStatus::Idle => {
+                        match inflight.get(&data.session_id) {
+                            Some(turn) if turn.saw_running => inflight.remove(&data.session_id),
+                            _ => None,
+                        }
+                    }
+                    SessionStatus::Queued | SessionStatus::Starting => None,
+                    // The turn was cut short, so its timing would be misleading
+                    SessionStatus::Error | SessionStatus::Stopped => {
+                        inflight.remove(&data.session_id);
+                        None
+                    }
+                }
+            };
+
+            if let Some(turn) = finished {
+                self.complete(&event_tx, data.session_id, turn).await;
+            }
+        }
+    }
+
+    async fn complete(&self, event_tx: &broadcast::Sender<Event>, session_id: Uuid, turn: Turn) {
+        let metrics = TurnMetrics {
+            first_output_ms: turn.first_output_ms,
+            duration_ms: turn.started.elapsed().as_millis() as u64,
+        };
+        debug!("Session {} turn finished: {:?}", session_id, metrics);
+        history::record(session_id, HistoryKind::Turn(metrics.clone()));
+
+        let slow_turn_secs = self.config.read().await.daemon.slow_turn_secs;
+        let slow = slow_turn_secs > 0 && metrics.duration_ms >= slow_turn_secs * 1000;
+
+        let data = TurnMetricsData {
+            session_id,
+            metrics,
+        };
+        if slow {
+            let _ = event_tx.send(Event::SlowTurn(data.clone()));
+        }
+        let _ = event_tx.send(Event::TurnCompleted(data));
+    }
+}
+
+/// Summarize the turns in a session timeline, keeping the last `limit` turns
+pub fn stats(entries: &[HistoryEntry], limit: usize) -> TurnStats {
+    let turns: Vec<TurnRecord> = entries
+        .iter()
+        .filter_map(|entry| match &entry.kind {
+            HistoryKind::Turn(metrics) => Some(TurnRecord {
+                ts: entry.ts,
+                metrics: metrics.clone(),
+            }),
+            _ => None,
+        })
+        .collect();
+    if turns.is_empty() {
+        return TurnStats::default();
+    }
+
+    let average = |values: &[u64]| -> Option<u64> {
+        (!values.is_empty()).then(|| values.iter().sum::<u64>() / values.len() as u64)
+    };
+    let first_outputs: Vec<u64> = turns
+        .iter()
+        .filter_map(|t| t.metrics.first_output_ms)
+        .collect();
+    let mut durations: Vec<u64> = turns.iter().map(|t| t.metrics.duration_ms).collect();
+    durations.sort_unstable();
+    // Nearest-rank percentile
+    let p95_index = (durations.len() * 95).div_ceil(100).saturating_sub(1);
+
+    TurnStats {
+        count: turns.len(),
+        avg_first_output_ms: average(&first_outputs),
+        avg_duration_ms: average(&durations),
+        p95_duration_ms: durations.get(p95_index).copied(),
+        turns: turns[turns.len().saturating_sub(limit)..].to_vec(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn turn(first_output_ms: Option<u64>, duration_ms: u64) -> HistoryEntry {
+        HistoryEntry::new(HistoryKind::Turn(TurnMetrics {
+            first_output_ms,
+            duration_ms,
+        }))
+    }
+
+    #[test]
+    fn test_stats() {
+        let mut entries: Vec<HistoryEntry> = (1..=20).map(|i| turn(None, i * 1000)).collect();
+        entries[0] = turn(Some(400), 1000);
+        entries[1] = turn(Some(600), 2000);
+        entries.push(HistoryEntry::new(HistoryKind::Moved {
+            from_group_id: None,
+            to_group_id: None,
+        }));
+
+        let stats = stats(&entries, 5);
+        assert_eq!(stats.count, 20);
+        assert_eq!(stats.avg_first_output_ms, Some(500));
+        assert_eq!(stats.avg_duration_ms, Some(10500));
+        assert_eq!(stats.p95_duration_ms, Some(19000));
+        assert_eq!(stats.turns.len(), 5);
+        assert_eq!(stats.turns[4].metrics.duration_ms, 20000);
+    }
+
+    #[test]
+    fn test_stats_empty() {
+        let stats = stats(&[], 10);
+        assert_eq!(stats.count, 0);
+        assert_eq!(stats.avg_duration_ms, None);
+    }
+}
@@ -0,0 +1,171 @@
+// Update checker - opt-in polling for new daemon releases and hook script drift
+// Results are surfaced via daemon.diagnostics and the daemon:update_status event
+// Each check goes through the egress gate, so daemon.local_only stops them live
+
+use anyhow::Result;
+use chrono::Utc;
+use serde::Deserialize;
+use shared::{Event, UpdateInfo, UpdateStatusData};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{broadcast, RwLock};
+use tracing::{info, warn};
+
+use crate::config::SharedConfig;
+use crate::egress::{self, Egress};
+use crate::hook_manager::HookManager;
+
+/// Version of the running daemon
+pub const CURRENT_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Subset of the GitHub release API response we care about
+#[derive(Debug, Deserialize)]
+struct Release {
+    tag_name: String,
+    html_url: Option<String>,
+}
+
+pub struct UpdateChecker {
+    config: SharedConfig,
+    latest: RwLock<Option<UpdateInfo>>,
+}
+
+impl UpdateChecker {
+    pub fn new(config: SharedConfig) -> Self {
+        Self {
+            config,
+            latest: RwLock::new(None),
+        }
+    }
+
+    /// Result of the most recent successful check
+    pub async fn latest(&self) -> Option<UpdateInfo> {
+        self.latest.read().await.clone()
+    }
+
+    /// Fetch the latest release and compare it with the running version
+    async fn check(&self, release_url: &str) -> Result<UpdateInfo> {
+        egress::check(&*self.config.read().await, Egress::UpdateCheck)?;
+        let client = reqwest::Client::builder()
+            .user_agent(format!("claude-master-daemon/{}", CURRENT_VERSION))
+            .timeout(Duration::from_secs(30))
+            .build()?;
+        let release: Release = client
+            .get(release_url)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        let latest_version = release.tag_name.trim_start_matches('v').to_string();
+        Ok(UpdateInfo {
+            update_available: is_newer(&latest_version, CURRENT_VERSION),
+            latest_version,
+            release_url: release.html_url,
+            checked_at: Utc::now(),
+        })
+    }
+
+    /// Periodically check for updates and hook drift, emitting
+    /// daemon:update_status whenever either changes. No-op unless enabled.
+    /// [updates] is read once here; changing it needs a daemon restart.
+    pub async fn run(
+        self: Arc<Self>,
+        hook_manager: Arc<HookManager>,
+        event_tx: broadcast::Sender<Event>,
+    ) {
+        let settings = self.config.read().await.updates.clone();
+        if !settings.enabled {
+            return;
+        }
+        info!(
+            "Update checks enabled (every {}h)",
+            settings.check_interval_hours
+        );
+
+        let interval = Duration::from_secs(settings.check_interval_hours.max(1) * 3600);
+        let mut last_reported: Option<(Option<UpdateInfo>, bool)> = None;
+
+        loop {
+            match self.check(&settings.release_url).await {
+                Ok(update) => {
+                    if update.update_available {
+                        info!(
+                            "Update available: {} -> {}",
+                            CURRENT_VERSION, update.latest_version
+                        );
+                    }
+                    *self.latest.write().await = Some(update);
+                }
+                Err(e) => warn!("Update check failed: {}", e),
+            }
+
+            let update = self.latest().await;
+            let hook_script_current = hook_manager.hook_script_current();
+            if !hook_script_current {
+                warn!("Installed hook script differs from the embedded version");
+            }
+
+            // Ignore checked_at when deciding whether anything changed
+            let summary = (
+                update.as_ref().map(|u| UpdateInfo {
+                    checked_at: Default::default(),
+                    ..u.clone()
+                }),
+                hook_script_current,
+            );
+            if last_reported.as_ref() != Some(&summary) {
+                last_reported = Some(summary);
+                let event = Event::UpdateStatus(UpdateStatusData {
+                    update,
+                    hook_script_current,
+                });
+                let _ = event_tx.send(event);
+            }
+
+            tokio::time::sleep(interval).await;
+        }
+    }
+}
+
+/// Parse "1.2.3" (ignoring any pre-release/build suffix) into comparable parts
+fn parse_version(version: &str) -> Option<(u64, u64, u64)> {
+    let core = version.split(['-', '+']).next()?;
+    let mut parts = core.split('.').map(|p| p.parse::<u64>().ok());
+    Some((
+        parts.next()??,
+        parts.next().unwrap_or(Some(0))?,
+        parts.next().unwrap_or(Some(0))?,
+    ))
+}
+
+/// Whether `latest` is a strictly newer version than `current`
+pub fn is_newer(latest: &str, current: &str) -> bool {
+    match (parse_version(latest), parse_version(current)) {
+        (Some(l), Some(c)) => l > c,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_version() {
+        assert_eq!(parse_version("1.2.3"), Some((1, 2, 3)));
+        assert_eq!(parse_version("0.4"), Some((0, 4, 0)));
+        assert_eq!(parse_version("1.0.0-beta.1"), Some((1, 0, 0)));
+        assert_eq!(parse_version("nightly"), None);
+    }
+
+    #[test]
+    fn test_is_newer() {
+        assert!(is_newer("0.2.0", "0.1.9"));
+        assert!(is_newer("1.0.0", "0.9.9"));
+        assert!(!is_newer("0.1.0", "0.1.0"));
+        assert!(!is_newer("0.0.9", "0.1.0"));
+        assert!(!is_newer("garbage", "0.1.0"));
+    }
+}
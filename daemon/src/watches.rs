@@ -0,0 +1,467 @@
+// Watch expressions - small boolean expressions attached to sessions or groups,
+// e.g. `status == waiting && unread > 1000 && minutes_since_activity > 10`
+// The watcher evaluates them on session updates and on a timer, and emits
+// session:needs_attention (naming the expression) each time one becomes true.
+// A group's watches cover every session under it, including subgroups.
+
+use anyhow::Result;
+use chrono::Utc;
+use shared::{Event, NeedsAttentionData, Session, SessionStatus};
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tracing::{debug, warn};
+use uuid::Uuid;
+
+use crate::notifications::Notifier;
+use crate::pty::PtyManager;
+use crate::state::SharedState;
+
+/// Re-evaluate this often even without events, since time and output move on
+const EVAL_INTERVAL_SECS: u64 = 15;
+
+/// Names an expression can read; any other bare word is a string (`status == waiting`)
+const VARIABLES: &[&str] = &[
+    "status",
+    "unread",
+    "minutes_since_activity",
+    "alive",
+    "name",
+];
+
+#[derive(Debug, Clone, PartialEq)]
+enum Value {
+    Num(f64),
+    Str(String),
+    Bool(bool),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Num(f64),
+    Str(String),
+    Ident(String),
+    Op(&'static str),
+}
+
+/// Longest first, so `<=` isn't read as `<` then `=`
+const OPERATORS: &[&str] = &["&&", "||", "==", "!=", "<=", ">=", "<", ">", "!", "(", ")"];
+
+#[derive(Debug, Clone)]
+enum Expr {
+    Lit(Value),
+    Var(String),
+    Not(Box<Expr>),
+    Binary(Box<Expr>, &'static str, Box<Expr>),
+}
+
+/// What a watch is attached to
+pub enum WatchTarget {
+    Session(Uuid),
+    Group(Uuid),
+}
+
+/// What a watch can see about one session
+pub struct WatchContext {
+    pub status: SessionStatus,
+    /// Bytes of output since input was last sent
+    pub unread: u64,
+    pub minutes_since_activity: i64,
+    /// Has a running process
+    pub alive: bool,
+    pub name: String,
+}
+
+impl WatchContext {
+    fn get(&self, name: &str) -> Value {
+        match name {
+            "status" => Value::Str(
+                serde_json::to_value(self.status)
+                    .ok()
+                    .and_then(|v| v.as_str().map(str::to_string))
+                    .unwrap_or_default(),
+            ),
+            "unread" => Value::Num(self.unread as f64),
+            "minutes_since_activity" => Value::Num(self.minutes_since_activity as f64),
+            "alive" => Value::Bool(self.alive),
+            _ => Value::Str(self.name.clone()),
+        }
+    }
+}
+
+/// A parsed watch expression
+#[derive(Debug, Clone)]
+pub struct Watch {
+    expr: Expr,
+}
+
+impl Watch {
+    /// Parse an expression, also rejecting ones that can't evaluate to true or false
+    pub fn parse(source: &str) -> Result<Self> {
+        let tokens = tokenize(source)?;
+        let mut parser = Parser { tokens, pos: 0 };
+        let expr = parser.or()?;
+        if let Some(token) = parser.tokens.get(parser.pos) {
+            anyhow::bail!("Unexpected {:?}", token);
+        }
+
+        let watch = Self { expr };
+        let sample = WatchContext {
+            status: SessionStatus::Running,
+            unread: 0,
+            minutes_since_activity: 0,
+            alive: true,
+            name: String::new(),
+        };
+        watch.evaluate(&sample)?;
+        Ok(watch)
+    }
+
+    pub fn evaluate(&self, ctx: &WatchContext) -> Result<bool> {
+        match eval(&self.expr, ctx)? {
+            Value::Bool(b) => Ok(b),
+            other => anyhow::bail!("Expression is {:?}, not true or false", other),
+        }
+    }
+}
+
+fn tokenize(source: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut rest = source.trim_start();
+
+    while let Some(c) = rest.chars().next() {
+        let len = if c.is_ascii_digit() {
+            let len = rest
+                .find(|c: char| !c.is_ascii_digit() && c != '.')
+                .unwrap_or(rest.len());
+            let number = &rest[..len];
+            tokens.push(Token::Num(
+                number
+                    .parse()
+                    .map_err(|_| anyhow::anyhow!("Bad number '{}'", number))?,
+            ));
+            len
+        } else if c.is_ascii_alphabetic() || c == '_' {
+            let len = rest
+                .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+                .unwrap_or(rest.len());
+            tokens.push(Token::Ident(rest[..len].to_string()));
+            len
+        } else if c == '"' || c == '\'' {
+            let end = rest[1..]
+                .find(c)
+                .ok_or_else(|| anyhow::anyhow!("Unterminated string"))?;
+            tokens.push(Token::Str(rest[1..1 + end].to_string()));
+            end + 2
+        } else if let Some(op) = OPERATORS.iter().find(|op| rest.starts_with(**op)) {
+            tokens.push(Token::Op(op));
+            op.len()
+        } else {
+            anyhow::bail!("Unexpected '{}'", c);
+        };
+        rest = rest[len..].trim_start();
+    }
+
+    Ok(tokens)
+}
+
+/// Recursive descent, loosest binding first: `||`, `&&`, `!`, comparisons
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn eat(&mut self, op: &str) -> bool {
+        let matched = matches!(self.tokens.get(self.pos), Some(Token::Op(o)) if *o == op);
+        if matched {
+            self.pos += 1;
+        }
+        matched
+    }
+
+    fn or(&mut self) -> Result<Expr> {
+        let mut left = self.and()?;
+        while self.eat("||") {
+            left = Expr::Binary(Box::new(left), "||", Box::new(self.and()?));
+        }
+        Ok(left)
+    }
+
+    fn and(&mut self) -> Result<Expr> {
+        let mut left = self.not()?;
+        while self.eat("&&") {
+            left = Expr::Binary(Box::new(left), "&&", Box::new(self.not()?));
+        }
+        Ok(left)
+    }
+
+    fn not(&mut self) -> Result<Expr> {
+        if self.eat("!") {
+            return Ok(Expr::Not(Box::new(self.not()?)));
+        }
+        self.comparison()
+    }
+
+    fn comparison(&mut self) -> Result<Expr> {
+        let left = self.atom()?;
+        match self.tokens.get(self.pos) {
+            Some(Token::Op(op @ ("==" | "!=" | "<" | "<=" | ">" | ">="))) => {
+                let op = *op;
+                self.pos += 1;
+                Ok(Expr::Binary(Box::new(left), op, Box::new(self.atom()?)))
+            }
+            _ => Ok(left),
+        }
+    }
+
+    fn atom(&mut self) -> Result<Expr> {
+        let token = self
+            .tokens
+            .get(self.pos)
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("Unexpected end of expression"))?;
+        self.pos += 1;
+        Ok(match token {
+            Token::Num(n) => Expr::Lit(Value::Num(n)),
+            Token::Str(s) => Expr::Lit(Value::Str(s)),
+            Token::Ident(word) => match word.as_str() {
+                "true" => Expr::Lit(Value::Bool(true)),
+                "false" => Expr::Lit(Value::Bool(false)),
+                _ if VARIABLES.contains(&word.as_str()) => Expr::Var(word),
+                _ => Expr::Lit(Value::Str(word)),
+            },
+            Token::Op("(") => {
+                let inner = self.or()?;
+                if !self.eat(")") {
+                    anyhow::bail!("Missing ')'");
+                }
+                inner
+            }
+            Token::Op(op) => anyhow::bail!("Unexpected '{}'", op),
+        })
+    }
+}
+
+fn eval(expr: &Expr, ctx: &WatchContext) -> Result<Value> {
+    Ok(match expr {
+        Expr::Lit(value) => value.clone(),
+        Expr::Var(name) => ctx.get(name),
+        Expr::Not(inner) => Value::Bool(!truth(eval(inner, ctx)?)?),
+        Expr::Binary(left, "&&", right) => {
+            Value::Bool(truth(eval(left, ctx)?)? && truth(eval(right, ctx)?)?)
+        }
+        Expr::Binary(left, "||", right) => {
+            Value::Bool(truth(eval(left, ctx)?)? || truth(eval(right, ctx)?)?)
+        }
+        Expr::Binary(left, op, right) => {
+            let (left, right) = (eval(left, ctx)?, eval(right, ctx)?);
+            Value::Bool(match (*op, &left, &right) {
+                ("==", _, _) if same_type(&left, &right) => left == right,
+                ("!=", _, _) if same_type(&left, &right) => left != right,
+                ("<", Value::Num(a), Value::Num(b)) => a < b,
+                ("<=", Value::Num(a), Value::Num(b)) => a <= b,
+                (">", Value::Num(a), Value::Num(b)) => a > b,
+                (">=", Value::Num(a), Value::Num(b)) => a >= b,
+                _ => anyhow::bail!("Can't compare {:?} {} {:?}", left, op, right),
+            })
+        }
+    })
+}
+
+fn truth(value: Value) -> Result<bool> {
+    match value {
+        Value::Bool(b) => Ok(b),
+        other => anyhow::bail!("{:?} is not true or false", other),
+    }
+}
+
+fn same_type(a: &Value, b: &Value) -> bool {
+    std::mem::discriminant(a) == std::mem::discriminant(b)
+}
+
+/// Evaluates every session's watches and raises attention when one turns true
+pub struct Watcher {
+    state: SharedState,
+    pty_manager: Arc<PtyManager>,
+    notifier: Arc<Notifier>,
+    event_tx: broadcast::Sender<Event>,
+}
+
+impl Watcher {
+    pub fn new(
+        state: SharedState,
+        pty_manager: Arc<PtyManager>,
+        notifier: Arc<Notifier>,
+        event_tx: broadcast::Sender<Event>,
+    ) -> Self {
+        Self {
+            state,
+            pty_manager,
+            notifier,
+            event_tx,
+        }
+    }
+
+    pub async fn run(self) {
+        let mut event_rx = self.event_tx.subscribe();
+        // (session, expression) pairs that are currently true, so each fires once
+        let mut firing: HashSet<(Uuid, String)> = HashSet::new();
+
+        loop {
+            tokio::select! {
+                event = event_rx.recv() => match event {
+                    Ok(Event::NeedsAttention(_)) => continue,
+                    Ok(event)
+                        if event.name().starts_with("session:")
+                            || matches!(event, Event::GroupUpdated(_)) => {}
+                    Ok(_) => continue,
+                    Err(broadcast::error::RecvError::Lagged(n)) => {
+                        warn!("Watcher lagged, skipped {} events", n);
+                    }
+                    Err(broadcast::error::RecvError::Closed) => return,
+                },
+                _ = tokio::time::sleep(Duration::from_secs(EVAL_INTERVAL_SECS)) => {}
+            }
+
+            self.evaluate(&mut firing).await;
+        }
+    }
+
+    async fn evaluate(&self, firing: &mut HashSet<(Uuid, String)>) {
+        let targets: Vec<(Session, Vec<String>)> = {
+            let s = self.state.read().await;
+            s.sessions
+                .values()
+                .filter_map(|session| {
+                    let mut watches = session.watches.clone();
+                    let mut group_id = session.group_id;
+                    // Bounded in case of a parent cycle
+                    for _ in 0..s.groups.len() {
+                        let Some(group) = group_id.and_then(|id| s.groups.get(&id)) else {
+                            break;
+                        };
+                        watches.extend(group.watches.iter().cloned());
+                        group_id = group.parent_id;
+                    }
+                    (!watches.is_empty()).then(|| (session.clone(), watches))
+                })
+                .collect()
+        };
+
+        let now = Utc::now();
+        let mut still_firing = HashSet::new();
+        for (session, watches) in targets {
+            let last_activity = self
+                .pty_manager
+                .last_io(session.id)
+                .await
+                .unwrap_or(session.last_activity);
+            let ctx = WatchContext {
+                status: session.status,
+                unread: self.pty_manager.unread(session.id).await,
+                minutes_since_activity: now.signed_duration_since(last_activity).num_minutes(),
+                alive: self.pty_manager.is_alive(session.id).await,
+                name: session.name.clone(),
+            };
+
+            for source in watches {
+                match Watch::parse(&source).and_then(|watch| watch.evaluate(&ctx)) {
+                    Ok(true) => {
+                        let key = (session.id, source);
+                        if !firing.contains(&key) {
+                            self.raise(&session, &key.1).await;
+                        }
+                        still_firing.insert(key);
+                    }
+                    Ok(false) => {}
+                    Err(e) => debug!("Watch '{}' on {} failed: {}", source, session.id, e),
+                }
+            }
+        }
+        *firing = still_firing;
+    }
+
+    async fn raise(&self, session: &Session, source: &str) {
+        let muted = self
+            .notifier
+            .settings()
+            .await
+            .is_muted(session.id, session.group_id);
+        debug!(
+            "Watch '{}' triggered on session {} (muted: {})",
+            source, session.id, muted
+        );
+
+        let event = Event::NeedsAttention(NeedsAttentionData {
+            session_id: session.id,
+            name: session.name.clone(),
+            group_id: session.group_id,
+            status: session.status,
+            muted,
+            watch: Some(source.to_string()),
+        });
+        let _ = self.event_tx.send(event);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ctx(status: SessionStatus, unread: u64, minutes: i64) -> WatchContext {
+        WatchContext {
+            status,
+            unread,
+            minutes_since_activity: minutes,
+            alive: true,
+            name: "api".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_watch_expressions() {
+        let watch =
+            Watch::parse("status == waiting && unread > 1000 && minutes_since_activity > 10")
+                .unwrap();
+        assert!(watch
+            .evaluate(&ctx(SessionStatus::Waiting, 1001, 11))
+            .unwrap());
+        assert!(!watch
+            .evaluate(&ctx(SessionStatus::Waiting, 1000, 11))
+            .unwrap());
+        assert!(!watch
+            .evaluate(&ctx(SessionStatus::Running, 5000, 60))
+            .unwrap());
+
+        let watch = Watch::parse("!(alive && name == 'api') || status != \"idle\"").unwrap();
+        assert!(!watch.evaluate(&ctx(SessionStatus::Idle, 0, 0)).unwrap());
+        assert!(watch.evaluate(&ctx(SessionStatus::Error, 0, 0)).unwrap());
+
+        // || binds looser than &&
+        let watch = Watch::parse("true || false && false").unwrap();
+        assert!(watch.evaluate(&ctx(SessionStatus::Idle, 0, 0)).unwrap());
+    }
+
+    #[test]
+    fn test_invalid_watch_expressions() {
+        for source in [
+            "",
+            "status ==",
+            "(unread > 1",
+            "unread > 1)",
+            "unread",
+            "status > 3",
+            "unread >= 'x'",
+            "'open",
+            "unread # 3",
+        ] {
+            assert!(
+                Watch::parse(source).is_err(),
+                "{:?} should not parse",
+                source
+            );
+        }
+    }
+}
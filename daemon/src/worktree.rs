@@ -0,0 +1,200 @@
+// Git worktree helpers - lets several sessions work on one repository in parallel
+// Each session gets its own worktree so agents don't trample each other's checkouts
+
+use anyhow::Result;
+use shared::WorktreeInfo;
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+
+use crate::git::run as git;
+
+/// Check whether a local branch exists in the repository
+async fn branch_exists(repo: &Path, branch: &str) -> bool {
+    let ref_name = format!("refs/heads/{}", branch);
+    git(repo, &["rev-parse", "--verify", "--quiet", &ref_name])
+        .await
+        .is_ok()
+}
+
+/// Fail unless `branch` is a name git accepts for a new branch, which also
+/// rules out anything it could take for an option
+async fn check_branch(repo: &Path, branch: &str) -> Result<()> {
+    match git(repo, &["check-ref-format", "--branch", branch]).await {
+        // It prints the name back, expanding things like @{-1}
+        Ok(name) if name == branch => Ok(()),
+        _ => anyhow::bail!("Invalid branch name: {}", branch),
+    }
+}
+
+/// Turn a branch name into something safe to use as a directory name
+fn sanitize_branch(branch: &str) -> String {
+    branch
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.' {
+                c
+            } else {
+                '-'
+            }
+        })
+        .collect()
+}
+
+/// Default location for a new worktree: a sibling of the repository
+/// named `<repo>-<branch>`
+pub fn default_worktree_path(repo: &Path, branch: &str) -> Result<PathBuf> {
+    let repo_name = repo
+        .file_name()
+        .ok_or_else(|| anyhow::anyhow!("Repository path has no directory name"))?
+        .to_string_lossy();
+    let parent = repo
+        .parent()
+        .ok_or_else(|| anyhow::anyhow!("Repository path has no parent directory"))?;
+    Ok(parent.join(format!("{}-{}", repo_name, sanitize_branch(branch))))
+}
+
+/// Create a worktree for `branch`, creating the branch from HEAD if it doesn't exist
+pub async fn add(repo: &Path, branch: &str, path: Option<PathBuf>) -> Result<WorktreeInfo> {
+    // Resolve the top-level directory so subdirectories of a repo work too
+    let repo_path = PathBuf::from(git(repo, &["rev-parse", "--show-toplevel"]).await?);
+    check_branch(&repo_path, branch).await?;
+
+    let path = match path {
+        Some(p) => p,
+        None => default_worktree_path(&repo_path, branch)?,
+    };
+    if path.exists() {
+        anyhow::bail!("Worktree path already exists: {}", path.display());
+    }
+
+    let path_str = path.to_string_lossy().to_string();
+    if branch_exists(&repo_path, branch).await {
+        git(&repo_path, &["worktree", "add", "--", &path_str, branch]).await?;
+    } else {
+        git(
+            &repo_path,
+            &["worktree", "add", "-b", branch, "--", &path_str],
+        )
+        .await?;
+    }
+
+    info!("Created worktree {:?} for branch {}", path, branch);
+    Ok(WorktreeInfo {
+        repo_path,
+        path,
+        branch: branch.to_string(),
+    })
+}
+
+/// Create a worktree on a new `branch` from the checkout at `source` (which may
+/// itself be a worktree): its HEAD, plus uncommitted changes to tracked files
+pub async fn fork(source: &Path, branch: &str, path: Option<PathBuf>) -> Result<WorktreeInfo> {
+    let checkout = PathBuf::from(git(source, &["rev-parse", "--show-toplevel"]).await?);
+    // The main repository owns every worktree; its .git is the common dir
+    let common_dir = checkout.join(git(&checkout, &["rev-parse", "--git-common-dir"]).await?);
+    let repo_path = common_dir
+        .parent()
+        .ok_or_else(|| anyhow::anyhow!("Repository has no parent directory"))?
+        .to_path_buf();
+    check_branch(&repo_path, branch).await?;
+    if branch_exists(&repo_path, branch).await {
+        anyhow::bail!("Branch already exists: {}", branch);
+    }
+
+    let path = match path {
+        Some(p) => p,
+        None => default_worktree_path(&repo_path, branch)?,
+    };
+    if path.exists() {
+        anyhow::bail!("Worktree path already exists: {}", path.display());
+    }
+
+    let head = git(&checkout, &["rev-parse", "HEAD"]).await?;
+    // A commit of the working tree that leaves it alone; empty when it's clean
+    let changes = git(&checkout, &["stash", "create"]).await?;
+
+    let path_str = path.to_string_lossy().to_string();
+    git(
+        &repo_path,
+        &["worktree", "add", "-b", branch, "--", &path_str, &head],
+    )
+    .await?;
+    if !changes.is_empty() {
+        if let Err(e) = git(&path, &["stash", "apply", &changes]).await {
+            warn!("Uncommitted changes not carried into {:?}: {}", path, e);
+        }
+    }
+
+    info!(
+        "Forked worktree {:?} on branch {} from {:?}",
+        path, branch, checkout
+    );
+    Ok(WorktreeInfo {
+        repo_path,
+        path,
+        branch: branch.to_string(),
+    })
+}
+
+/// Remove a worktree created by `add`
+/// Fails if the worktree has uncommitted changes, so work isn't silently lost
+pub async fn remove(worktree: &WorktreeInfo) -> Result<()> {
+    let path_str = worktree.path.to_string_lossy().to_string();
+    git(
+        &worktree.repo_path,
+        &["worktree", "remove", "--", &path_str],
+    )
+    .await?;
+    info!("Removed worktree {:?}", worktree.path);
+    Ok(())
+}
+
+/// Undo `fork` after a later step failed: drop the worktree, changes and all,
+/// and the branch made for it
+pub async fn discard(worktree: &WorktreeInfo) {
+    let path_str = worktree.path.to_string_lossy().to_string();
+    let removed = git(
+        &worktree.repo_path,
+        &["worktree", "remove", "--force", "--", &path_str],
+    )
+    .await;
+    let result = match removed {
+        Ok(_) => {
+            git(
+                &worktree.repo_path,
+                &["branch", "-D", "--", &worktree.branch],
+            )
+            .await
+        }
+        Err(e) => Err(e),
+    };
+    if let Err(e) = result {
+        warn!("Failed to discard worktree {:?}: {}", worktree.path, e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sanitize_branch() {
+        assert_eq!(sanitize_branch("feature/login"), "feature-login");
+        assert_eq!(sanitize_branch("fix_bug-1.2"), "fix_bug-1.2");
+    }
+
+    #[tokio::test]
+    async fn test_check_branch() {
+        let dir = std::env::temp_dir();
+        assert!(check_branch(&dir, "feature/login").await.is_ok());
+        for branch in ["-b", "--upload-pack=touch x", "a..b", "a b", "@{-1}", ""] {
+            assert!(check_branch(&dir, branch).await.is_err(), "{}", branch);
+        }
+    }
+
+    #[test]
+    fn test_default_worktree_path() {
+        let path = default_worktree_path(Path::new("/src/app"), "feature/x").unwrap();
+        assert_eq!(path, PathBuf::from("/src/app-feature-x"));
+    }
+}
@@ -1,27 +1,253 @@
 //! Tauri commands that bridge the frontend to the daemon
 
 use serde_json::json;
-use shared::{Group, Session};
-use tauri::State;
-use tracing::{error, info};
+use shared::{
+    current_profile, get_socket_path, set_profile, ApprovalParams, ApprovalPolicy, ArchivedSession,
+    ClaudeCapabilities, ClaudeFlags, ClaudeInfo, ClientInfo, ClientsResult, CompletionWatch,
+    ConflictChoice, CreateScheduleParams, CreateSessionParams, DaemonIdentity,
+    DaemonLogsTailParams, DaemonLogsTailResult, DaemonMetrics, DaemonMode, DaemonModeInfo,
+    DaemonStats, DeleteGroupParams, DiagnosticsResult, ErrorCode, EventSubscription,
+    ExportOutputResult, Group, GroupDeleteMode, GroupDeleteResult, GroupListResult, HelloResult,
+    HistoryEntry, InputHistoryEntry, KeepAwakeStatus, Key, McpListResult, McpServer, MetricsFormat,
+    MetricsParams, NetworkAuditResult, NotificationSettings, OrphanInfo, PolicySetParams,
+    ProfileList, ProjectCandidate, PromptPriority, QueuedPrompt, RecordingInfo, ScanProjectsResult,
+    Schedule, ScheduleListResult, SearchOutputResult, SearchQueryResult, SearchSource,
+    SendKeyParams, SendSnippetResult, Session, SessionAttachResult, SessionFilter,
+    SessionListResult, SessionScreen, SessionStatsResult, SetCompletionWatchParams,
+    SetDependencyParams, Snippet, SnippetListResult, StateExportParams, StateExportResult,
+    StateImportParams, StateImportResult, StateSyncResult, StopOutcome, StopSessionResult,
+    TailLogResult, TaskInfo, TaskListResult, TaskRunner, TerminalSize, ThemeResult,
+    TranscriptResult, TreeSnapshot, TurnStats, UpdateGroupParams, UpdateSessionParams,
+    WatchesResult,
+};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+use tauri::{AppHandle, Manager, State, WebviewUrl, WebviewWindowBuilder, WindowEvent, Wry};
+use tauri_plugin_dialog::{DialogExt, FileDialogBuilder, FilePath};
+use tracing::{error, info, warn};
 use uuid::Uuid;
 
 use crate::DaemonState;
 
-/// Connect to the daemon
+/// Connect to the daemon and check it is the one this app launched
 #[tauri::command]
-pub async fn connect_daemon(state: State<'_, DaemonState>) -> Result<(), String> {
+pub async fn connect_daemon(state: State<'_, DaemonState>) -> Result<DaemonIdentity, String> {
     info!("connect_daemon command called");
     match state.client.connect().await {
         Ok(()) => {
             info!("connect_daemon: successfully connected to daemon");
-            Ok(())
         }
         Err(e) => {
             error!("connect_daemon: failed to connect: {}", e);
-            Err(e)
+            return Err(e);
         }
     }
+
+    let identity = daemon_identity(&state).await?;
+    if let DaemonIdentity::Conflict { hello, .. } = &identity {
+        warn!(
+            "connect_daemon: socket is owned by another daemon: {:?}",
+            hello
+        );
+    }
+    Ok(identity)
+}
+
+/// Compare the daemon's daemon.hello with the instance id we launch it with
+async fn daemon_identity(state: &DaemonState) -> Result<DaemonIdentity, String> {
+    let expected = crate::daemon_launcher::instance_id().map_err(|e| e.to_string())?;
+    let hello = match state.client.call("daemon.hello", json!({})).await {
+        Ok(result) => {
+            Some(serde_json::from_value::<HelloResult>(result).map_err(|e| e.to_string())?)
+        }
+        // Daemons from before daemon.hello can't be ours
+        Err(e) if e.code == Some(ErrorCode::MethodNotFound) => None,
+        Err(e) => return Err(e.into()),
+    };
+
+    Ok(match hello {
+        Some(hello) if hello.instance_id == Some(expected) => DaemonIdentity::Owned { hello },
+        // Whatever runs on the socket is the daemon to use in external mode
+        hello if state.external || state.attached.load(Ordering::Relaxed) => {
+            DaemonIdentity::Attached { hello }
+        }
+        hello => DaemonIdentity::Conflict { expected, hello },
+    })
+}
+
+/// Check whether the connected daemon is the one this app launched
+#[tauri::command]
+pub async fn check_daemon_identity(
+    state: State<'_, DaemonState>,
+) -> Result<DaemonIdentity, String> {
+    daemon_identity(&state).await
+}
+
+/// Whether the GUI manages its daemon or connects to an external one, and the
+/// PID and version of the daemon it is connected to
+#[tauri::command]
+pub async fn get_daemon_mode(state: State<'_, DaemonState>) -> Result<DaemonModeInfo, String> {
+    let connected = state.client.is_connected().await;
+    let hello = connected.then(|| state.client.hello()).flatten();
+    Ok(DaemonModeInfo {
+        mode: if state.external {
+            DaemonMode::External
+        } else {
+            DaemonMode::Managed
+        },
+        connected,
+        pid: hello.as_ref().map(|hello| hello.pid),
+        version: hello.map(|hello| hello.version),
+    })
+}
+
+/// Resolve a conflict with another daemon on the socket: keep using it, or
+/// shut it down and start this app's daemon in its place
+#[tauri::command]
+pub async fn resolve_daemon_conflict(
+    app: AppHandle,
+    state: State<'_, DaemonState>,
+    choice: ConflictChoice,
+) -> Result<DaemonIdentity, String> {
+    match choice {
+        ConflictChoice::Attach => {
+            info!("Attaching to a daemon this app didn't launch");
+            state.attached.store(true, Ordering::Relaxed);
+        }
+        ConflictChoice::Takeover => {
+            info!("Taking over the daemon socket");
+            state.attached.store(false, Ordering::Relaxed);
+            let socket_path = get_socket_path().map_err(|e| e.to_string())?;
+
+            // Its sessions are killed with it (the default pty_policy)
+            if let Err(e) = state.client.call("daemon.shutdown", json!({})).await {
+                warn!("Other daemon did not accept shutdown: {}", e);
+            }
+            state.client.disconnect().await;
+            wait_for_socket(&socket_path, false).await;
+
+            crate::daemon_launcher::restart_own_daemon(&app)
+                .await
+                .map_err(|e| e.to_string())?;
+            wait_for_socket(&socket_path, true).await;
+            state.client.connect().await?;
+        }
+    }
+    daemon_identity(&state).await
+}
+
+/// Profiles the GUI can switch to, and the one in use
+#[tauri::command]
+pub async fn list_profiles() -> Result<ProfileList, String> {
+    Ok(ProfileList {
+        current: current_profile(),
+        profiles: shared::list_profiles().map_err(|e| e.to_string())?,
+    })
+}
+
+/// Switch to another profile's daemon (None is the default profile), starting
+/// it if needed; a new name creates the profile
+/// The previous profile's daemon keeps running with its sessions
+#[tauri::command]
+pub async fn switch_profile(
+    app: AppHandle,
+    state: State<'_, DaemonState>,
+    profile: Option<String>,
+) -> Result<DaemonIdentity, String> {
+    if profile != current_profile() {
+        info!("Switching to profile {:?}", profile);
+        set_profile(profile.as_deref()).map_err(|e| e.to_string())?;
+        if let Err(e) = crate::daemon_launcher::save_profile(profile.as_deref()) {
+            warn!("Failed to remember profile: {}", e);
+        }
+
+        state.attached.store(false, Ordering::Relaxed);
+        // The event listener reconnects along with the client
+        state.client.disconnect().await;
+
+        crate::daemon_launcher::ensure_daemon_running(&app)
+            .await
+            .map_err(|e| e.to_string())?;
+        let socket_path = get_socket_path().map_err(|e| e.to_string())?;
+        wait_for_socket(&socket_path, true).await;
+        state.client.connect().await?;
+    }
+    daemon_identity(&state).await
+}
+
+/// Poll for the daemon socket to appear or disappear, giving up after 5s
+async fn wait_for_socket(socket_path: &Path, present: bool) {
+    for _ in 0..50 {
+        if socket_path.exists() == present {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    warn!(
+        "Daemon socket {:?} still {} after 5s",
+        socket_path,
+        if present { "missing" } else { "present" }
+    );
+}
+
+/// Label of the pop-out window showing a session
+fn session_window_label(session_id: Uuid) -> String {
+    format!("session-{}", session_id)
+}
+
+/// Open a window dedicated to one session's terminal (or focus it if it is
+/// already open); the event listener only sends it that session's events
+#[tauri::command]
+pub async fn open_session_window(
+    app: AppHandle,
+    state: State<'_, DaemonState>,
+    session_id: Uuid,
+) -> Result<(), String> {
+    let label = session_window_label(session_id);
+    if let Some(window) = app.get_webview_window(&label) {
+        return window.set_focus().map_err(|e| e.to_string());
+    }
+
+    let result = state.client.call("session.list", json!({})).await?;
+    let sessions: Vec<Session> =
+        serde_json::from_value(result.get("sessions").cloned().unwrap_or_default())
+            .map_err(|e| e.to_string())?;
+    let session = sessions
+        .into_iter()
+        .find(|s| s.id == session_id)
+        .ok_or_else(|| format!("Session not found: {}", session_id))?;
+
+    // Bound before the window exists, so its first events are already routed
+    state
+        .session_windows
+        .lock()
+        .unwrap()
+        .insert(label.clone(), session_id);
+    let url = WebviewUrl::App(format!("index.html?session={}", session_id).into());
+    let window = match WebviewWindowBuilder::new(&app, &label, url)
+        .title(format!("{} - Claude Master", session.name))
+        .inner_size(900.0, 600.0)
+        .min_inner_size(400.0, 300.0)
+        .build()
+    {
+        Ok(window) => window,
+        Err(e) => {
+            state.session_windows.lock().unwrap().remove(&label);
+            return Err(e.to_string());
+        }
+    };
+
+    let handle = app.clone();
+    window.on_window_event(move |event| {
+        if let WindowEvent::Destroyed = event {
+            let state = handle.state::<DaemonState>();
+            state.session_windows.lock().unwrap().remove(&label);
+        }
+    });
+    info!("Opened window for session {}", session_id);
+    Ok(())
 }
 
 /// Check if connected to daemon
@@ -37,25 +263,124 @@ pub async fn ping_daemon(state: State<'_, DaemonState>) -> Result<String, String
     Ok(result.to_string())
 }
 
-/// List all sessions
+/// Get daemon diagnostics (version, hook script and update status)
 #[tauri::command]
-pub async fn list_sessions(state: State<'_, DaemonState>) -> Result<Vec<Session>, String> {
-    let result = state.client.call("session.list", json!({})).await?;
-    let sessions = result
-        .get("sessions")
-        .ok_or("Missing sessions field")?
-        .clone();
-    serde_json::from_value(sessions).map_err(|e| e.to_string())
+pub async fn get_diagnostics(state: State<'_, DaemonState>) -> Result<DiagnosticsResult, String> {
+    let result = state.client.call("daemon.diagnostics", json!({})).await?;
+    serde_json::from_value(result).map_err(|e| e.to_string())
 }
 
-/// Create a new session
+/// List claude processes left running by a previous daemon and what was done with them
 #[tauri::command]
-pub async fn create_session(
+pub async fn list_orphans(state: State<'_, DaemonState>) -> Result<Vec<OrphanInfo>, String> {
+    let result = state.client.call("daemon.orphans", json!({})).await?;
+    serde_json::from_value(result).map_err(|e| e.to_string())
+}
+
+/// List daemon subsystems that can reach the network and whether each may connect
+#[tauri::command]
+pub async fn get_network_audit(
     state: State<'_, DaemonState>,
-    name: String,
-    dir: String,
+) -> Result<NetworkAuditResult, String> {
+    let result = state.client.call("daemon.network_audit", json!({})).await?;
+    serde_json::from_value(result).map_err(|e| e.to_string())
+}
+
+/// Get PTY output queue and client lag statistics
+#[tauri::command]
+pub async fn get_daemon_stats(state: State<'_, DaemonState>) -> Result<DaemonStats, String> {
+    let result = state.client.call("daemon.stats", json!({})).await?;
+    serde_json::from_value(result).map_err(|e| e.to_string())
+}
+
+/// Get the daemon's counters since it started, optionally also as Prometheus text
+#[tauri::command]
+pub async fn get_daemon_metrics(
+    state: State<'_, DaemonState>,
+    format: Option<MetricsFormat>,
+) -> Result<DaemonMetrics, String> {
+    let params = MetricsParams {
+        format: format.unwrap_or_default(),
+    };
+    let result = state
+        .client
+        .call(
+            "daemon.metrics",
+            serde_json::to_value(params).map_err(|e| e.to_string())?,
+        )
+        .await?;
+    serde_json::from_value(result).map_err(|e| e.to_string())
+}
+
+/// Start mirroring all GUI<->daemon traffic to the frontend as debug:tap events
+/// (secrets and typed input are redacted by the daemon)
+#[tauri::command]
+pub async fn start_debug_tap(
+    state: State<'_, DaemonState>,
+    include_output: Option<bool>,
+) -> Result<(), String> {
+    // The event listener applies it, and again after every reconnect
+    state
+        .debug_tap
+        .send_replace(Some(include_output.unwrap_or(false)));
+    Ok(())
+}
+
+/// Stop the debug tap started by start_debug_tap
+#[tauri::command]
+pub async fn stop_debug_tap(state: State<'_, DaemonState>) -> Result<(), String> {
+    state.debug_tap.send_replace(None);
+    Ok(())
+}
+
+/// Choose which events reach the frontend, e.g. only pty:output for the sessions
+/// on screen (None = everything)
+#[tauri::command]
+pub async fn set_event_subscription(
+    state: State<'_, DaemonState>,
+    subscription: Option<EventSubscription>,
+) -> Result<(), String> {
+    state
+        .event_subscription
+        .send_replace(subscription.unwrap_or_default());
+    Ok(())
+}
+
+/// Get the keep-awake status, or turn it on or off when `enabled` is given;
+/// changes also arrive as daemon:keep_awake events
+#[tauri::command]
+pub async fn keep_awake(
+    state: State<'_, DaemonState>,
+    enabled: Option<bool>,
+) -> Result<KeepAwakeStatus, String> {
+    let result = state
+        .client
+        .call("daemon.keep_awake", json!({ "enabled": enabled }))
+        .await?;
+    serde_json::from_value(result).map_err(|e| e.to_string())
+}
+
+/// Get desktop notification settings (enabled flag and mutes)
+#[tauri::command]
+pub async fn get_notification_settings(
+    state: State<'_, DaemonState>,
+) -> Result<NotificationSettings, String> {
+    let result = state.client.call("notifications.get", json!({})).await?;
+    serde_json::from_value(result).map_err(|e| e.to_string())
+}
+
+/// Mute or unmute notifications for a session or a group (pass exactly one)
+#[tauri::command]
+pub async fn set_notification_mute(
+    state: State<'_, DaemonState>,
+    session_id: Option<String>,
     group_id: Option<String>,
-) -> Result<Session, String> {
+    muted: bool,
+) -> Result<NotificationSettings, String> {
+    let session_uuid = session_id
+        .map(|id| Uuid::parse_str(&id))
+        .transpose()
+        .map_err(|e| format!("Invalid session_id: {}", e))?;
     let group_uuid = group_id
         .map(|id| Uuid::parse_str(&id))
         .transpose()
@@ -64,311 +389,1636 @@ pub async fn create_session(
     let result = state
         .client
         .call(
-            "session.create",
+            "notifications.set_mute",
             json!({
-                "name": name,
-                "dir": dir,
+                "session_id": session_uuid,
                 "group_id": group_uuid,
+                "muted": muted,
             }),
         )
         .await?;
+    serde_json::from_value(result).map_err(|e| e.to_string())
+}
 
-    let session = result
-        .get("session")
-        .ok_or("Missing session field")?
+/// Start the first-run bootstrap; progress arrives as daemon:bootstrap_progress
+/// events and the outcome as daemon:bootstrap_complete
+#[tauri::command]
+pub async fn run_bootstrap(
+    state: State<'_, DaemonState>,
+    install_claude: bool,
+    starter_group: Option<String>,
+) -> Result<(), String> {
+    state
+        .client
+        .call(
+            "daemon.bootstrap",
+            json!({
+                "install_claude": install_claude,
+                "starter_group": starter_group,
+            }),
+        )
+        .await?;
+    Ok(())
+}
+
+/// Get theme settings and status colors from the daemon config
+#[tauri::command]
+pub async fn get_ui_theme(state: State<'_, DaemonState>) -> Result<ThemeResult, String> {
+    let result = state.client.call("ui.theme", json!({})).await?;
+    serde_json::from_value(result).map_err(|e| e.to_string())
+}
+
+/// List all sessions
+#[tauri::command]
+pub async fn list_sessions(
+    state: State<'_, DaemonState>,
+    filter: Option<SessionFilter>,
+) -> Result<Vec<Session>, String> {
+    let result = state
+        .client
+        .call("session.list", json!({ "filter": filter }))
+        .await?;
+    let sessions = result
+        .get("sessions")
+        .ok_or("Missing sessions field")?
         .clone();
-    serde_json::from_value(session).map_err(|e| e.to_string())
+    serde_json::from_value(sessions).map_err(|e| e.to_string())
 }
 
-/// Stop a session
+/// Get the Claude transcript (message history and turn state) for a session
 #[tauri::command]
-pub async fn stop_session(
+pub async fn get_transcript(
     state: State<'_, DaemonState>,
     session_id: String,
-) -> Result<bool, String> {
-    let uuid = Uuid::parse_str(&session_id).map_err(|e| format!("Invalid session_id: {}", e))?;
+    limit: Option<usize>,
+) -> Result<TranscriptResult, String> {
+    let session_uuid =
+        Uuid::parse_str(&session_id).map_err(|e| format!("Invalid session_id: {}", e))?;
 
     let result = state
         .client
-        .call("session.stop", json!({ "session_id": uuid }))
+        .call(
+            "session.transcript",
+            json!({
+                "session_id": session_uuid,
+                "limit": limit,
+            }),
+        )
         .await?;
-
-    result
-        .get("success")
-        .and_then(|v| v.as_bool())
-        .ok_or("Missing success field".to_string())
+    serde_json::from_value(result).map_err(|e| e.to_string())
 }
 
-/// Delete a session
+/// Get a session's activity timeline (creation, status changes, prompts, approvals, exits)
 #[tauri::command]
-pub async fn delete_session(
+pub async fn get_session_history(
     state: State<'_, DaemonState>,
     session_id: String,
-) -> Result<bool, String> {
-    let uuid = Uuid::parse_str(&session_id).map_err(|e| format!("Invalid session_id: {}", e))?;
+    limit: Option<usize>,
+) -> Result<Vec<HistoryEntry>, String> {
+    let session_uuid =
+        Uuid::parse_str(&session_id).map_err(|e| format!("Invalid session_id: {}", e))?;
 
     let result = state
         .client
-        .call("session.delete", json!({ "session_id": uuid }))
+        .call(
+            "session.history",
+            json!({
+                "session_id": session_uuid,
+                "limit": limit,
+            }),
+        )
         .await?;
+    serde_json::from_value(result).map_err(|e| e.to_string())
+}
 
-    result
-        .get("success")
-        .and_then(|v| v.as_bool())
-        .ok_or("Missing success field".to_string())
+/// Get prompts sent to a session (or to any session, each text once), most
+/// recent first, for input recall and the prompt library
+#[tauri::command]
+pub async fn get_input_history(
+    state: State<'_, DaemonState>,
+    session_id: Option<String>,
+    query: Option<String>,
+    limit: Option<usize>,
+) -> Result<Vec<InputHistoryEntry>, String> {
+    let session_uuid = session_id
+        .map(|id| Uuid::parse_str(&id).map_err(|e| format!("Invalid session_id: {}", e)))
+        .transpose()?;
+
+    let result = state
+        .client
+        .call(
+            "session.input_history",
+            json!({
+                "session_id": session_uuid,
+                "query": query,
+                "limit": limit,
+            }),
+        )
+        .await?;
+    serde_json::from_value(result).map_err(|e| e.to_string())
 }
 
-/// Fork a session
-/// rows and cols are required for proper terminal sizing at startup
+/// Get the CPU and memory of each running session's process tree (or just
+/// one session's), heaviest memory user first
 #[tauri::command]
-pub async fn fork_session(
+pub async fn get_session_stats(
+    state: State<'_, DaemonState>,
+    session_id: Option<String>,
+) -> Result<SessionStatsResult, String> {
+    let session_uuid = session_id
+        .map(|id| Uuid::parse_str(&id).map_err(|e| format!("Invalid session_id: {}", e)))
+        .transpose()?;
+
+    let result = state
+        .client
+        .call("session.stats", json!({ "session_id": session_uuid }))
+        .await?;
+    serde_json::from_value(result).map_err(|e| e.to_string())
+}
+
+/// Tell the daemon which session's terminal is shown (None for none), so other
+/// clients can see who is looking at it
+#[tauri::command]
+pub async fn view_session(
+    state: State<'_, DaemonState>,
+    session_id: Option<String>,
+) -> Result<ClientInfo, String> {
+    let session_uuid = session_id
+        .map(|id| Uuid::parse_str(&id).map_err(|e| format!("Invalid session_id: {}", e)))
+        .transpose()?;
+    Ok(state.client.view(session_uuid).await?)
+}
+
+/// List the clients connected to the daemon
+#[tauri::command]
+pub async fn list_clients(state: State<'_, DaemonState>) -> Result<ClientsResult, String> {
+    let result = state.client.call("daemon.clients", json!({})).await?;
+    serde_json::from_value(result).map_err(|e| e.to_string())
+}
+
+/// Get the latest records of the daemon's own log, optionally only those at
+/// `level` or more severe
+#[tauri::command]
+pub async fn tail_daemon_log(
+    state: State<'_, DaemonState>,
+    lines: Option<usize>,
+    level: Option<String>,
+) -> Result<DaemonLogsTailResult, String> {
+    let result = state
+        .client
+        .call(
+            "daemon.logs.tail",
+            serde_json::to_value(DaemonLogsTailParams { lines, level })
+                .map_err(|e| e.to_string())?,
+        )
+        .await?;
+    serde_json::from_value(result).map_err(|e| e.to_string())
+}
+
+/// Get the end of a session's on-disk output log (base64 raw PTY output)
+#[tauri::command]
+pub async fn tail_session_log(
     state: State<'_, DaemonState>,
     session_id: String,
-    new_name: Option<String>,
-    group_id: Option<String>,
-    rows: u16,
-    cols: u16,
-) -> Result<Session, String> {
-    info!(
-        "fork_session called with session_id: {}, size: {}x{}",
-        session_id, cols, rows
-    );
+    max_bytes: Option<usize>,
+) -> Result<TailLogResult, String> {
+    let session_uuid =
+        Uuid::parse_str(&session_id).map_err(|e| format!("Invalid session_id: {}", e))?;
+
+    let result = state
+        .client
+        .call(
+            "session.tail_log",
+            json!({
+                "session_id": session_uuid,
+                "max_bytes": max_bytes,
+            }),
+        )
+        .await?;
+    serde_json::from_value(result).map_err(|e| e.to_string())
+}
 
+/// Get what a session's terminal shows now, to restore it without replaying output
+#[tauri::command]
+pub async fn get_session_screen(
+    state: State<'_, DaemonState>,
+    session_id: String,
+) -> Result<SessionScreen, String> {
     let session_uuid =
         Uuid::parse_str(&session_id).map_err(|e| format!("Invalid session_id: {}", e))?;
 
-    let group_uuid = group_id
-        .map(|id| Uuid::parse_str(&id))
-        .transpose()
-        .map_err(|e| format!("Invalid group_id: {}", e))?;
+    let result = state
+        .client
+        .call("session.get_screen", json!({ "session_id": session_uuid }))
+        .await?;
+    serde_json::from_value(result).map_err(|e| e.to_string())
+}
+
+/// Search output logs and transcripts of every session, live or archived
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn search_all(
+    state: State<'_, DaemonState>,
+    query: String,
+    regex: Option<bool>,
+    case_sensitive: Option<bool>,
+    sources: Option<Vec<SearchSource>>,
+    session_ids: Option<Vec<String>>,
+    since: Option<String>,
+    until: Option<String>,
+    context: Option<usize>,
+    limit: Option<usize>,
+) -> Result<SearchQueryResult, String> {
+    let session_uuids = session_ids
+        .map(|ids| {
+            ids.iter()
+                .map(|id| Uuid::parse_str(id))
+                .collect::<Result<Vec<_>, _>>()
+        })
+        .transpose()
+        .map_err(|e| format!("Invalid session_id: {}", e))?;
+
+    let result = state
+        .client
+        .call(
+            "search.query",
+            json!({
+                "query": query,
+                "regex": regex.unwrap_or(false),
+                "case_sensitive": case_sensitive.unwrap_or(false),
+                "sources": sources,
+                "session_ids": session_uuids,
+                "since": since,
+                "until": until,
+                "context": context,
+                "limit": limit,
+            }),
+        )
+        .await?;
+    serde_json::from_value(result).map_err(|e| e.to_string())
+}
+
+/// Find lines in a session's logged output so the terminal can search beyond its scrollback
+#[tauri::command]
+pub async fn search_session_output(
+    state: State<'_, DaemonState>,
+    session_id: String,
+    query: String,
+    case_sensitive: Option<bool>,
+    limit: Option<usize>,
+) -> Result<SearchOutputResult, String> {
+    let session_uuid =
+        Uuid::parse_str(&session_id).map_err(|e| format!("Invalid session_id: {}", e))?;
+
+    let result = state
+        .client
+        .call(
+            "session.search_output",
+            json!({
+                "session_id": session_uuid,
+                "query": query,
+                "case_sensitive": case_sensitive.unwrap_or(false),
+                "limit": limit,
+            }),
+        )
+        .await?;
+    serde_json::from_value(result).map_err(|e| e.to_string())
+}
+
+/// Save a session's logged output to `dest`, as plain text unless `raw`
+#[tauri::command]
+pub async fn export_session_output(
+    state: State<'_, DaemonState>,
+    session_id: String,
+    dest: String,
+    raw: Option<bool>,
+) -> Result<ExportOutputResult, String> {
+    let session_uuid =
+        Uuid::parse_str(&session_id).map_err(|e| format!("Invalid session_id: {}", e))?;
+
+    let result = state
+        .client
+        .call(
+            "session.export_output",
+            json!({
+                "session_id": session_uuid,
+                "dest": dest,
+                "raw": raw.unwrap_or(false),
+            }),
+        )
+        .await?;
+    serde_json::from_value(result).map_err(|e| e.to_string())
+}
+
+/// Play back a session's output log as replay:output events; returns the replay id
+#[tauri::command]
+pub async fn replay_session(
+    state: State<'_, DaemonState>,
+    session_id: String,
+    since: Option<String>,
+    speed: Option<f64>,
+    max_gap_ms: Option<u64>,
+) -> Result<String, String> {
+    let session_uuid =
+        Uuid::parse_str(&session_id).map_err(|e| format!("Invalid session_id: {}", e))?;
+
+    let result = state
+        .client
+        .call(
+            "session.replay",
+            json!({
+                "session_id": session_uuid,
+                "since": since,
+                "speed": speed,
+                "max_gap_ms": max_gap_ms,
+            }),
+        )
+        .await?;
+    result
+        .get("replay_id")
+        .and_then(|id| id.as_str())
+        .map(String::from)
+        .ok_or_else(|| "Missing replay_id field".to_string())
+}
+
+/// Stop a running replay
+#[tauri::command]
+pub async fn cancel_replay(
+    state: State<'_, DaemonState>,
+    replay_id: String,
+) -> Result<bool, String> {
+    let replay_uuid =
+        Uuid::parse_str(&replay_id).map_err(|e| format!("Invalid replay_id: {}", e))?;
+
+    let result = state
+        .client
+        .call("session.replay_cancel", json!({ "replay_id": replay_uuid }))
+        .await?;
+    Ok(result
+        .get("cancelled")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false))
+}
+
+/// Start recording a session's terminal output to an asciinema cast file
+#[tauri::command]
+pub async fn start_recording(
+    state: State<'_, DaemonState>,
+    session_id: String,
+) -> Result<RecordingInfo, String> {
+    let session_uuid =
+        Uuid::parse_str(&session_id).map_err(|e| format!("Invalid session_id: {}", e))?;
+
+    let result = state
+        .client
+        .call(
+            "session.recording.start",
+            json!({ "session_id": session_uuid }),
+        )
+        .await?;
+    serde_json::from_value(result).map_err(|e| e.to_string())
+}
+
+/// Stop recording a session
+#[tauri::command]
+pub async fn stop_recording(
+    state: State<'_, DaemonState>,
+    session_id: String,
+) -> Result<RecordingInfo, String> {
+    let session_uuid =
+        Uuid::parse_str(&session_id).map_err(|e| format!("Invalid session_id: {}", e))?;
+
+    let result = state
+        .client
+        .call(
+            "session.recording.stop",
+            json!({ "session_id": session_uuid }),
+        )
+        .await?;
+    serde_json::from_value(result).map_err(|e| e.to_string())
+}
+
+/// List recordings, newest first, optionally for one session
+#[tauri::command]
+pub async fn list_recordings(
+    state: State<'_, DaemonState>,
+    session_id: Option<String>,
+) -> Result<Vec<RecordingInfo>, String> {
+    let session_uuid = session_id
+        .map(|id| Uuid::parse_str(&id))
+        .transpose()
+        .map_err(|e| format!("Invalid session_id: {}", e))?;
+
+    let result = state
+        .client
+        .call(
+            "session.recording.list",
+            json!({ "session_id": session_uuid }),
+        )
+        .await?;
+    serde_json::from_value(result).map_err(|e| e.to_string())
+}
+
+/// Save a copy of a recording as a .cast file at `dest`
+#[tauri::command]
+pub async fn export_recording(
+    state: State<'_, DaemonState>,
+    recording_id: String,
+    dest: String,
+) -> Result<RecordingInfo, String> {
+    let recording_uuid =
+        Uuid::parse_str(&recording_id).map_err(|e| format!("Invalid recording_id: {}", e))?;
+
+    let result = state
+        .client
+        .call(
+            "session.recording.export",
+            json!({
+                "recording_id": recording_uuid,
+                "dest": dest,
+            }),
+        )
+        .await?;
+    serde_json::from_value(result).map_err(|e| e.to_string())
+}
+
+/// List deleted sessions from the archive, newest first, optionally under a directory
+#[tauri::command]
+pub async fn list_archived_sessions(
+    state: State<'_, DaemonState>,
+    working_dir: Option<String>,
+    limit: Option<usize>,
+) -> Result<Vec<ArchivedSession>, String> {
+    let result = state
+        .client
+        .call(
+            "history.list",
+            json!({
+                "working_dir": working_dir,
+                "limit": limit,
+            }),
+        )
+        .await?;
+    serde_json::from_value(result).map_err(|e| e.to_string())
+}
+
+/// Get per-turn latency stats (time to first output, turn duration) for a session
+#[tauri::command]
+pub async fn get_turn_stats(
+    state: State<'_, DaemonState>,
+    session_id: String,
+    limit: Option<usize>,
+) -> Result<TurnStats, String> {
+    let session_uuid =
+        Uuid::parse_str(&session_id).map_err(|e| format!("Invalid session_id: {}", e))?;
+
+    let result = state
+        .client
+        .call(
+            "session.turn_stats",
+            json!({
+                "session_id": session_uuid,
+                "limit": limit,
+            }),
+        )
+        .await?;
+    serde_json::from_value(result).map_err(|e| e.to_string())
+}
+
+/// Queue a prompt to be sent once the session is ready (default lane: interactive)
+#[tauri::command]
+pub async fn enqueue_prompt(
+    state: State<'_, DaemonState>,
+    session_id: String,
+    prompt: String,
+    priority: Option<PromptPriority>,
+) -> Result<QueuedPrompt, String> {
+    let session_uuid =
+        Uuid::parse_str(&session_id).map_err(|e| format!("Invalid session_id: {}", e))?;
+
+    let result = state
+        .client
+        .call(
+            "queue.enqueue",
+            json!({
+                "session_id": session_uuid,
+                "prompt": prompt,
+                "priority": priority.unwrap_or_default(),
+            }),
+        )
+        .await?;
+    serde_json::from_value(result).map_err(|e| e.to_string())
+}
+
+/// List queued prompts in dispatch order, optionally for one session
+#[tauri::command]
+pub async fn list_queue(
+    state: State<'_, DaemonState>,
+    session_id: Option<String>,
+) -> Result<Vec<QueuedPrompt>, String> {
+    let session_uuid = session_id
+        .map(|id| Uuid::parse_str(&id))
+        .transpose()
+        .map_err(|e| format!("Invalid session_id: {}", e))?;
+
+    let result = state
+        .client
+        .call("queue.list", json!({ "session_id": session_uuid }))
+        .await?;
+    serde_json::from_value(result).map_err(|e| e.to_string())
+}
+
+/// Remove a prompt from the queue before it is sent
+#[tauri::command]
+pub async fn cancel_queued_prompt(state: State<'_, DaemonState>, id: String) -> Result<(), String> {
+    let uuid = Uuid::parse_str(&id).map_err(|e| format!("Invalid id: {}", e))?;
+    state
+        .client
+        .call("queue.cancel", json!({ "id": uuid }))
+        .await?;
+    Ok(())
+}
+
+/// Get the nested group/session hierarchy with statuses in one call
+#[tauri::command]
+pub async fn get_tree_snapshot(state: State<'_, DaemonState>) -> Result<TreeSnapshot, String> {
+    let result = state.client.call("tree.snapshot", json!({})).await?;
+    serde_json::from_value(result).map_err(|e| e.to_string())
+}
+
+/// Create a new session
+#[tauri::command]
+pub async fn create_session(
+    state: State<'_, DaemonState>,
+    name: String,
+    dir: String,
+    group_id: Option<String>,
+    spawn_mode: Option<String>,
+    create_dir: Option<bool>,
+    flags: Option<ClaudeFlags>,
+    claude_binary: Option<String>,
+    size: Option<TerminalSize>,
+) -> Result<Session, String> {
+    let flags = flags.unwrap_or_default();
+    let group_uuid = group_id
+        .map(|id| Uuid::parse_str(&id))
+        .transpose()
+        .map_err(|e| format!("Invalid group_id: {}", e))?;
+
+    let result = state
+        .client
+        .call(
+            "session.create",
+            json!({
+                "name": name,
+                "dir": dir,
+                "group_id": group_uuid,
+                "spawn_mode": spawn_mode,
+                "create_dir": create_dir.unwrap_or(false),
+                "claude_binary": claude_binary,
+                "model": flags.model,
+                "permission_mode": flags.permission_mode,
+                "extra_args": flags.extra_args,
+                "size": size,
+            }),
+        )
+        .await?;
+
+    let session = result
+        .get("session")
+        .ok_or("Missing session field")?
+        .clone();
+    serde_json::from_value(session).map_err(|e| e.to_string())
+}
+
+/// Create a session in a new git worktree for `branch`
+#[tauri::command]
+pub async fn create_worktree_session(
+    state: State<'_, DaemonState>,
+    name: String,
+    repo_path: String,
+    branch: String,
+    worktree_path: Option<String>,
+    group_id: Option<String>,
+) -> Result<Session, String> {
+    let group_uuid = group_id
+        .map(|id| Uuid::parse_str(&id))
+        .transpose()
+        .map_err(|e| format!("Invalid group_id: {}", e))?;
+
+    let result = state
+        .client
+        .call(
+            "session.create_worktree",
+            json!({
+                "name": name,
+                "repo_path": repo_path,
+                "branch": branch,
+                "worktree_path": worktree_path,
+                "group_id": group_uuid,
+            }),
+        )
+        .await?;
+
+    let session = result
+        .get("session")
+        .ok_or("Missing session field")?
+        .clone();
+    serde_json::from_value(session).map_err(|e| e.to_string())
+}
+
+/// Stop a session, giving claude `timeout_ms` to exit before it is killed
+/// (or killing it at once with `force`); returns how it ended
+#[tauri::command]
+pub async fn stop_session(
+    state: State<'_, DaemonState>,
+    session_id: String,
+    timeout_ms: Option<u64>,
+    force: Option<bool>,
+) -> Result<StopOutcome, String> {
+    let uuid = Uuid::parse_str(&session_id).map_err(|e| format!("Invalid session_id: {}", e))?;
+
+    let result = state
+        .client
+        .call(
+            "session.stop",
+            json!({
+                "session_id": uuid,
+                "timeout_ms": timeout_ms,
+                "force": force.unwrap_or(false),
+            }),
+        )
+        .await?;
+    let result: StopSessionResult = serde_json::from_value(result).map_err(|e| e.to_string())?;
+    Ok(result.outcome)
+}
+
+/// Stop the turn a session is generating, keeping its process
+#[tauri::command]
+pub async fn interrupt_session(
+    state: State<'_, DaemonState>,
+    session_id: String,
+) -> Result<(), String> {
+    let uuid = Uuid::parse_str(&session_id).map_err(|e| format!("Invalid session_id: {}", e))?;
+    state
+        .client
+        .call("session.interrupt", json!({ "session_id": uuid }))
+        .await?;
+    Ok(())
+}
+
+/// Press a key by name (enter, esc, up, ctrl_c, shift_tab, ...) in a session,
+/// `repeat` times
+#[tauri::command]
+pub async fn send_key(
+    state: State<'_, DaemonState>,
+    session_id: String,
+    key: Key,
+    repeat: Option<u32>,
+) -> Result<(), String> {
+    let uuid = Uuid::parse_str(&session_id).map_err(|e| format!("Invalid session_id: {}", e))?;
+    let params = SendKeyParams {
+        session_id: uuid,
+        key,
+        repeat,
+    };
+    state
+        .client
+        .call(
+            "session.send_key",
+            serde_json::to_value(params).map_err(|e| e.to_string())?,
+        )
+        .await?;
+    Ok(())
+}
+
+/// Resume an interrupted session with a prompt ("continue" if none)
+#[tauri::command]
+pub async fn continue_session(
+    state: State<'_, DaemonState>,
+    session_id: String,
+    prompt: Option<String>,
+) -> Result<(), String> {
+    let uuid = Uuid::parse_str(&session_id).map_err(|e| format!("Invalid session_id: {}", e))?;
+    state
+        .client
+        .call(
+            "session.continue",
+            json!({ "session_id": uuid, "prompt": prompt }),
+        )
+        .await?;
+    Ok(())
+}
+
+/// Keep a session running but stop streaming its output; the daemon spools it
+#[tauri::command]
+pub async fn detach_session(
+    state: State<'_, DaemonState>,
+    session_id: String,
+) -> Result<Session, String> {
+    let uuid = Uuid::parse_str(&session_id).map_err(|e| format!("Invalid session_id: {}", e))?;
+    let result = state
+        .client
+        .call("session.detach", json!({ "session_id": uuid }))
+        .await?;
+    serde_json::from_value(result).map_err(|e| e.to_string())
+}
+
+/// Resume streaming a detached session; returns the output it missed
+#[tauri::command]
+pub async fn attach_session(
+    state: State<'_, DaemonState>,
+    session_id: String,
+) -> Result<SessionAttachResult, String> {
+    let uuid = Uuid::parse_str(&session_id).map_err(|e| format!("Invalid session_id: {}", e))?;
+    let result = state
+        .client
+        .call("session.attach", json!({ "session_id": uuid }))
+        .await?;
+    serde_json::from_value(result).map_err(|e| e.to_string())
+}
+
+/// Clear a session's unread output in every window
+#[tauri::command]
+pub async fn mark_session_viewed(
+    state: State<'_, DaemonState>,
+    session_id: String,
+) -> Result<Session, String> {
+    let uuid = Uuid::parse_str(&session_id).map_err(|e| format!("Invalid session_id: {}", e))?;
+    let result = state
+        .client
+        .call("session.mark_viewed", json!({ "session_id": uuid }))
+        .await?;
+    serde_json::from_value(result).map_err(|e| e.to_string())
+}
+
+/// Delete a session
+/// If remove_worktree is true, the session's git worktree is removed as well
+#[tauri::command]
+pub async fn delete_session(
+    state: State<'_, DaemonState>,
+    session_id: String,
+    remove_worktree: Option<bool>,
+) -> Result<bool, String> {
+    let uuid = Uuid::parse_str(&session_id).map_err(|e| format!("Invalid session_id: {}", e))?;
+
+    let result = state
+        .client
+        .call(
+            "session.delete",
+            json!({
+                "session_id": uuid,
+                "remove_worktree": remove_worktree.unwrap_or(false),
+            }),
+        )
+        .await?;
+
+    result
+        .get("success")
+        .and_then(|v| v.as_bool())
+        .ok_or("Missing success field".to_string())
+}
+
+/// Fork a session
+/// rows and cols are required for proper terminal sizing at startup
+#[tauri::command]
+pub async fn fork_session(
+    state: State<'_, DaemonState>,
+    session_id: String,
+    new_name: Option<String>,
+    group_id: Option<String>,
+    rows: u16,
+    cols: u16,
+    worktree_branch: Option<String>,
+) -> Result<Session, String> {
+    info!(
+        "fork_session called with session_id: {}, size: {}x{}",
+        session_id, cols, rows
+    );
+
+    let session_uuid =
+        Uuid::parse_str(&session_id).map_err(|e| format!("Invalid session_id: {}", e))?;
+
+    let group_uuid = group_id
+        .map(|id| Uuid::parse_str(&id))
+        .transpose()
+        .map_err(|e| format!("Invalid group_id: {}", e))?;
+
+    let result = state
+        .client
+        .call(
+            "session.fork",
+            json!({
+                "session_id": session_uuid,
+                "new_name": new_name,
+                "group_id": group_uuid,
+                "rows": rows,
+                "cols": cols,
+                "worktree_branch": worktree_branch,
+            }),
+        )
+        .await?;
+
+    let session = result
+        .get("session")
+        .ok_or("Missing session field")?
+        .clone();
+    serde_json::from_value(session).map_err(|e| e.to_string())
+}
+
+/// Send input to a session; `paste` sends it as a bracketed paste in chunks
+#[tauri::command]
+pub async fn send_input(
+    state: State<'_, DaemonState>,
+    session_id: String,
+    input: String,
+    paste: Option<bool>,
+) -> Result<bool, String> {
+    let uuid = Uuid::parse_str(&session_id).map_err(|e| format!("Invalid session_id: {}", e))?;
+
+    let result = state
+        .client
+        .call(
+            "session.input",
+            json!({
+                "session_id": uuid,
+                "input": input,
+                "paste": paste.unwrap_or(false),
+            }),
+        )
+        .await?;
+
+    result
+        .get("success")
+        .and_then(|v| v.as_bool())
+        .ok_or("Missing success field".to_string())
+}
+
+/// Approve the permission dialog a session is showing
+#[tauri::command]
+pub async fn approve_session(
+    state: State<'_, DaemonState>,
+    session_id: String,
+    approval_id: String,
+) -> Result<(), String> {
+    answer_approval(&state, "session.approve", &session_id, &approval_id).await
+}
+
+/// Deny the permission dialog a session is showing
+#[tauri::command]
+pub async fn deny_session(
+    state: State<'_, DaemonState>,
+    session_id: String,
+    approval_id: String,
+) -> Result<(), String> {
+    answer_approval(&state, "session.deny", &session_id, &approval_id).await
+}
+
+async fn answer_approval(
+    state: &DaemonState,
+    method: &str,
+    session_id: &str,
+    approval_id: &str,
+) -> Result<(), String> {
+    let params = ApprovalParams {
+        session_id: Uuid::parse_str(session_id)
+            .map_err(|e| format!("Invalid session_id: {}", e))?,
+        approval_id: Uuid::parse_str(approval_id)
+            .map_err(|e| format!("Invalid approval_id: {}", e))?,
+    };
+    state
+        .client
+        .call(
+            method,
+            serde_json::to_value(params).map_err(|e| e.to_string())?,
+        )
+        .await?;
+    Ok(())
+}
+
+/// Resize a session's PTY
+#[tauri::command]
+pub async fn resize_session(
+    state: State<'_, DaemonState>,
+    session_id: String,
+    rows: u16,
+    cols: u16,
+) -> Result<bool, String> {
+    let uuid = Uuid::parse_str(&session_id).map_err(|e| format!("Invalid session_id: {}", e))?;
+
+    let result = state
+        .client
+        .call(
+            "session.resize",
+            json!({
+                "session_id": uuid,
+                "rows": rows,
+                "cols": cols,
+            }),
+        )
+        .await?;
+
+    result
+        .get("success")
+        .and_then(|v| v.as_bool())
+        .ok_or("Missing success field".to_string())
+}
+
+/// List all groups
+#[tauri::command]
+pub async fn list_groups(state: State<'_, DaemonState>) -> Result<GroupListResult, String> {
+    let result = state.client.call("group.list", json!({})).await?;
+    serde_json::from_value(result).map_err(|e| e.to_string())
+}
+
+/// Sessions and groups changed since generation `since` of tracker
+/// `tracker_id` (everything for None)
+#[tauri::command]
+pub async fn sync_state(
+    state: State<'_, DaemonState>,
+    since: Option<u64>,
+    tracker_id: Option<Uuid>,
+) -> Result<StateSyncResult, String> {
+    match state
+        .client
+        .call(
+            "state.sync",
+            json!({ "since": since, "tracker_id": tracker_id }),
+        )
+        .await
+    {
+        Ok(result) => serde_json::from_value(result).map_err(|e| e.to_string()),
+        // Daemons from before state.sync send everything, every time
+        Err(e) if e.code == Some(ErrorCode::MethodNotFound) => {
+            let sessions = state.client.call("session.list", json!({})).await?;
+            let sessions: SessionListResult =
+                serde_json::from_value(sessions).map_err(|e| e.to_string())?;
+            let groups = state.client.call("group.list", json!({})).await?;
+            let groups: GroupListResult =
+                serde_json::from_value(groups).map_err(|e| e.to_string())?;
+            Ok(StateSyncResult {
+                generation: 0,
+                tracker_id: Uuid::nil(),
+                full: true,
+                sessions: sessions.sessions,
+                groups: groups.groups,
+                removed_sessions: Vec::new(),
+                removed_groups: Vec::new(),
+                statuses: groups.statuses,
+            })
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Create a new group
+#[tauri::command]
+pub async fn create_group(
+    state: State<'_, DaemonState>,
+    name: String,
+    parent_id: Option<String>,
+) -> Result<Group, String> {
+    let parent_uuid = parent_id
+        .map(|id| Uuid::parse_str(&id))
+        .transpose()
+        .map_err(|e| format!("Invalid parent_id: {}", e))?;
+
+    let result = state
+        .client
+        .call(
+            "group.create",
+            json!({
+                "name": name,
+                "parent_id": parent_uuid,
+            }),
+        )
+        .await?;
+
+    let group = result.get("group").ok_or("Missing group field")?.clone();
+    serde_json::from_value(group).map_err(|e| e.to_string())
+}
+
+/// Delete a group, by default moving its contents out (with dry_run only report
+/// what would be affected)
+#[tauri::command]
+pub async fn delete_group(
+    state: State<'_, DaemonState>,
+    group_id: String,
+    mode: Option<GroupDeleteMode>,
+    dry_run: Option<bool>,
+) -> Result<GroupDeleteResult, String> {
+    let uuid = Uuid::parse_str(&group_id).map_err(|e| format!("Invalid group_id: {}", e))?;
+
+    let params = DeleteGroupParams {
+        group_id: uuid,
+        mode: mode.unwrap_or_default(),
+        dry_run: dry_run.unwrap_or(false),
+    };
+    let result = state
+        .client
+        .call(
+            "group.delete",
+            serde_json::to_value(params).map_err(|e| e.to_string())?,
+        )
+        .await?;
+
+    serde_json::from_value(result).map_err(|e| e.to_string())
+}
+
+/// Restart a session (kill and respawn PTY)
+/// rows and cols are required for proper terminal sizing at startup
+#[tauri::command]
+pub async fn restart_session(
+    state: State<'_, DaemonState>,
+    session_id: String,
+    rows: u16,
+    cols: u16,
+) -> Result<Session, String> {
+    info!(
+        "restart_session called with session_id: {}, size: {}x{}",
+        session_id, cols, rows
+    );
+
+    let uuid = Uuid::parse_str(&session_id).map_err(|e| {
+        error!("Invalid session_id: {}", e);
+        format!("Invalid session_id: {}", e)
+    })?;
+
+    info!(
+        "Sending session.restart IPC request for {} with size {}x{}",
+        uuid, cols, rows
+    );
+    let result = state
+        .client
+        .call(
+            "session.restart",
+            json!({
+                "session_id": uuid,
+                "rows": rows,
+                "cols": cols
+            }),
+        )
+        .await
+        .map_err(|e| {
+            error!("session.restart IPC failed: {}", e);
+            e
+        })?;
+
+    info!("session.restart IPC response received");
+    let session = result
+        .get("session")
+        .ok_or_else(|| {
+            error!("Missing session field in response");
+            "Missing session field".to_string()
+        })?
+        .clone();
+
+    let session: Session = serde_json::from_value(session).map_err(|e| {
+        error!("Failed to parse session: {}", e);
+        e.to_string()
+    })?;
+
+    info!(
+        "Session {} restarted successfully, status: {:?}",
+        session.id, session.status
+    );
+    Ok(session)
+}
+
+/// Shutdown the daemon gracefully
+/// pty_policy: "kill" (default) stops all sessions, "detach" leaves them running
+#[tauri::command]
+pub async fn shutdown_daemon(
+    state: State<'_, DaemonState>,
+    pty_policy: Option<String>,
+) -> Result<String, String> {
+    let result = state
+        .client
+        .call(
+            "daemon.shutdown",
+            json!({ "pty_policy": pty_policy.unwrap_or_else(|| "kill".to_string()) }),
+        )
+        .await?;
+    Ok(result.to_string())
+}
+
+/// Restart the daemon cleanly: sessions are handled per pty_policy and state is
+/// saved before a fresh daemon process takes over the socket
+#[tauri::command]
+pub async fn restart_daemon(
+    state: State<'_, DaemonState>,
+    pty_policy: Option<String>,
+) -> Result<(), String> {
+    state
+        .client
+        .call(
+            "daemon.restart",
+            json!({ "pty_policy": pty_policy.unwrap_or_else(|| "kill".to_string()) }),
+        )
+        .await?;
+    Ok(())
+}
+
+/// Start a session, or queue it until daemon.max_concurrent_running allows
+#[tauri::command]
+pub async fn enqueue_session_start(
+    state: State<'_, DaemonState>,
+    session_id: String,
+    rows: u16,
+    cols: u16,
+    resume: Option<bool>,
+) -> Result<Session, String> {
+    let session_uuid =
+        Uuid::parse_str(&session_id).map_err(|e| format!("Invalid session_id: {}", e))?;
+    let result = state
+        .client
+        .call(
+            "session.enqueue_start",
+            json!({
+                "session_id": session_uuid,
+                "rows": rows,
+                "cols": cols,
+                "resume": resume.unwrap_or(false),
+            }),
+        )
+        .await?;
+    let session = result
+        .get("session")
+        .ok_or("Missing session field")?
+        .clone();
+    serde_json::from_value(session).map_err(|e| e.to_string())
+}
+
+/// Update a session (name and/or group)
+/// For group_id: None = don't change, Some("") = remove from group, Some("uuid") = set group
+#[tauri::command]
+pub async fn update_session(
+    state: State<'_, DaemonState>,
+    session_id: String,
+    name: Option<String>,
+    group_id: Option<String>,
+) -> Result<Session, String> {
+    let session_uuid =
+        Uuid::parse_str(&session_id).map_err(|e| format!("Invalid session_id: {}", e))?;
+
+    // Convert: None = don't change, Some("") = remove group, Some("uuid") = set group
+    let group_uuid: Option<Option<Uuid>> = match group_id {
+        None => None,                                // Don't change
+        Some(ref id) if id.is_empty() => Some(None), // Remove from group
+        Some(id) => Some(Some(
+            Uuid::parse_str(&id).map_err(|e| format!("Invalid group_id: {}", e))?,
+        )),
+    };
+
+    let params = UpdateSessionParams {
+        session_id: session_uuid,
+        name,
+        group_id: group_uuid,
+        spawn_mode: None,
+    };
+    let result = state
+        .client
+        .call(
+            "session.update",
+            serde_json::to_value(params).map_err(|e| e.to_string())?,
+        )
+        .await?;
+
+    serde_json::from_value(result).map_err(|e| e.to_string())
+}
+
+/// Add a tag to a session
+#[tauri::command]
+pub async fn add_session_tag(
+    state: State<'_, DaemonState>,
+    session_id: String,
+    tag: String,
+) -> Result<Session, String> {
+    let session_uuid =
+        Uuid::parse_str(&session_id).map_err(|e| format!("Invalid session_id: {}", e))?;
+    let result = state
+        .client
+        .call(
+            "session.add_tag",
+            json!({ "session_id": session_uuid, "tag": tag }),
+        )
+        .await?;
+    serde_json::from_value(result).map_err(|e| e.to_string())
+}
+
+/// Remove a tag from a session
+#[tauri::command]
+pub async fn remove_session_tag(
+    state: State<'_, DaemonState>,
+    session_id: String,
+    tag: String,
+) -> Result<Session, String> {
+    let session_uuid =
+        Uuid::parse_str(&session_id).map_err(|e| format!("Invalid session_id: {}", e))?;
+    let result = state
+        .client
+        .call(
+            "session.remove_tag",
+            json!({ "session_id": session_uuid, "tag": tag }),
+        )
+        .await?;
+    serde_json::from_value(result).map_err(|e| e.to_string())
+}
+
+/// List the make/just/package.json tasks in a session's working directory
+#[tauri::command]
+pub async fn list_session_tasks(
+    state: State<'_, DaemonState>,
+    session_id: String,
+) -> Result<Vec<TaskInfo>, String> {
+    let session_uuid =
+        Uuid::parse_str(&session_id).map_err(|e| format!("Invalid session_id: {}", e))?;
+    let result = state
+        .client
+        .call("session.tasks.list", json!({ "session_id": session_uuid }))
+        .await?;
+    let result: TaskListResult = serde_json::from_value(result).map_err(|e| e.to_string())?;
+    Ok(result.tasks)
+}
+
+/// Run a task in the session's terminal, or headlessly (the outcome arrives as
+/// session:task_finished and in the session's history)
+#[tauri::command]
+pub async fn run_session_task(
+    state: State<'_, DaemonState>,
+    session_id: String,
+    name: String,
+    runner: Option<TaskRunner>,
+    headless: Option<bool>,
+) -> Result<TaskInfo, String> {
+    let session_uuid =
+        Uuid::parse_str(&session_id).map_err(|e| format!("Invalid session_id: {}", e))?;
+    let result = state
+        .client
+        .call(
+            "session.tasks.run",
+            json!({
+                "session_id": session_uuid,
+                "name": name,
+                "runner": runner,
+                "headless": headless.unwrap_or(false),
+            }),
+        )
+        .await?;
+    serde_json::from_value(result).map_err(|e| e.to_string())
+}
+
+/// Add or remove a watch expression on a session or a group (pass exactly one);
+/// returns the target's watches after the change
+#[tauri::command]
+pub async fn set_watch(
+    state: State<'_, DaemonState>,
+    session_id: Option<String>,
+    group_id: Option<String>,
+    expr: String,
+    enabled: bool,
+) -> Result<Vec<String>, String> {
+    let session_uuid = session_id
+        .map(|id| Uuid::parse_str(&id))
+        .transpose()
+        .map_err(|e| format!("Invalid session_id: {}", e))?;
+    let group_uuid = group_id
+        .map(|id| Uuid::parse_str(&id))
+        .transpose()
+        .map_err(|e| format!("Invalid group_id: {}", e))?;
+
+    let method = if enabled { "watch.add" } else { "watch.remove" };
+    let result = state
+        .client
+        .call(
+            method,
+            json!({
+                "session_id": session_uuid,
+                "group_id": group_uuid,
+                "expr": expr,
+            }),
+        )
+        .await?;
+    let result: WatchesResult = serde_json::from_value(result).map_err(|e| e.to_string())?;
+    Ok(result.watches)
+}
+
+/// Replace the auto-approve policy of a session or a group (pass exactly one)
+#[tauri::command]
+pub async fn set_approval_policy(
+    state: State<'_, DaemonState>,
+    session_id: Option<String>,
+    group_id: Option<String>,
+    policy: ApprovalPolicy,
+) -> Result<ApprovalPolicy, String> {
+    let params = PolicySetParams {
+        session_id: session_id
+            .map(|id| Uuid::parse_str(&id))
+            .transpose()
+            .map_err(|e| format!("Invalid session_id: {}", e))?,
+        group_id: group_id
+            .map(|id| Uuid::parse_str(&id))
+            .transpose()
+            .map_err(|e| format!("Invalid group_id: {}", e))?,
+        policy,
+    };
+    let result = state
+        .client
+        .call(
+            "policy.set",
+            serde_json::to_value(params).map_err(|e| e.to_string())?,
+        )
+        .await?;
+    serde_json::from_value(result).map_err(|e| e.to_string())
+}
+
+/// Create a session every time `cron` comes due, queuing `prompt` once it starts
+#[tauri::command]
+pub async fn create_schedule(
+    state: State<'_, DaemonState>,
+    name: String,
+    cron: String,
+    dir: String,
+    group_id: Option<String>,
+    prompt: Option<String>,
+    create_dir: Option<bool>,
+    flags: Option<ClaudeFlags>,
+    claude_binary: Option<String>,
+) -> Result<Schedule, String> {
+    let params = CreateScheduleParams {
+        session: CreateSessionParams {
+            name: name.clone(),
+            dir,
+            group_id: group_id
+                .map(|id| Uuid::parse_str(&id))
+                .transpose()
+                .map_err(|e| format!("Invalid group_id: {}", e))?,
+            spawn_mode: None,
+            claude_binary,
+            create_dir: create_dir.unwrap_or(false),
+            size: None,
+            flags: flags.unwrap_or_default(),
+        },
+        name,
+        cron,
+        prompt,
+    };
+    let result = state
+        .client
+        .call(
+            "schedule.create",
+            serde_json::to_value(params).map_err(|e| e.to_string())?,
+        )
+        .await?;
+    serde_json::from_value(result).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn list_schedules(state: State<'_, DaemonState>) -> Result<Vec<Schedule>, String> {
+    let result = state.client.call("schedule.list", json!({})).await?;
+    let result: ScheduleListResult = serde_json::from_value(result).map_err(|e| e.to_string())?;
+    Ok(result.schedules)
+}
+
+#[tauri::command]
+pub async fn delete_schedule(
+    state: State<'_, DaemonState>,
+    schedule_id: String,
+) -> Result<(), String> {
+    let schedule_id =
+        Uuid::parse_str(&schedule_id).map_err(|e| format!("Invalid schedule_id: {}", e))?;
+    state
+        .client
+        .call("schedule.delete", json!({ "schedule_id": schedule_id }))
+        .await?;
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn list_snippets(state: State<'_, DaemonState>) -> Result<Vec<Snippet>, String> {
+    let result = state.client.call("snippet.list", json!({})).await?;
+    let result: SnippetListResult = serde_json::from_value(result).map_err(|e| e.to_string())?;
+    Ok(result.snippets)
+}
+
+#[tauri::command]
+pub async fn create_snippet(
+    state: State<'_, DaemonState>,
+    name: String,
+    text: String,
+) -> Result<Snippet, String> {
+    let result = state
+        .client
+        .call("snippet.create", json!({ "name": name, "text": text }))
+        .await?;
+    serde_json::from_value(result).map_err(|e| e.to_string())
+}
 
+#[tauri::command]
+pub async fn update_snippet(
+    state: State<'_, DaemonState>,
+    snippet_id: String,
+    name: Option<String>,
+    text: Option<String>,
+) -> Result<Snippet, String> {
+    let snippet_id =
+        Uuid::parse_str(&snippet_id).map_err(|e| format!("Invalid snippet_id: {}", e))?;
     let result = state
         .client
         .call(
-            "session.fork",
-            json!({
-                "session_id": session_uuid,
-                "new_name": new_name,
-                "group_id": group_uuid,
-                "rows": rows,
-                "cols": cols,
-            }),
+            "snippet.update",
+            json!({ "snippet_id": snippet_id, "name": name, "text": text }),
         )
         .await?;
+    serde_json::from_value(result).map_err(|e| e.to_string())
+}
 
-    let session = result
-        .get("session")
-        .ok_or("Missing session field")?
-        .clone();
-    serde_json::from_value(session).map_err(|e| e.to_string())
+#[tauri::command]
+pub async fn delete_snippet(
+    state: State<'_, DaemonState>,
+    snippet_id: String,
+) -> Result<(), String> {
+    let snippet_id =
+        Uuid::parse_str(&snippet_id).map_err(|e| format!("Invalid snippet_id: {}", e))?;
+    state
+        .client
+        .call("snippet.delete", json!({ "snippet_id": snippet_id }))
+        .await?;
+    Ok(())
 }
 
-/// Send input to a session
+/// Send a snippet to a session as a prompt, with its variables filled in from
+/// the session and `vars`; returns the text that was sent
 #[tauri::command]
-pub async fn send_input(
+pub async fn send_snippet(
     state: State<'_, DaemonState>,
     session_id: String,
-    input: String,
-) -> Result<bool, String> {
-    let uuid = Uuid::parse_str(&session_id).map_err(|e| format!("Invalid session_id: {}", e))?;
-
+    snippet_id: String,
+    vars: Option<HashMap<String, String>>,
+) -> Result<String, String> {
+    let session_id =
+        Uuid::parse_str(&session_id).map_err(|e| format!("Invalid session_id: {}", e))?;
+    let snippet_id =
+        Uuid::parse_str(&snippet_id).map_err(|e| format!("Invalid snippet_id: {}", e))?;
     let result = state
         .client
         .call(
-            "session.input",
+            "session.send_snippet",
             json!({
-                "session_id": uuid,
-                "input": input,
+                "session_id": session_id,
+                "snippet_id": snippet_id,
+                "vars": vars.unwrap_or_default(),
             }),
         )
         .await?;
+    let result: SendSnippetResult = serde_json::from_value(result).map_err(|e| e.to_string())?;
+    Ok(result.text)
+}
 
-    result
-        .get("success")
-        .and_then(|v| v.as_bool())
-        .ok_or("Missing success field".to_string())
+/// Check the daemon's claude binary again: where it is, its version and any warning
+#[tauri::command]
+pub async fn claude_info(state: State<'_, DaemonState>) -> Result<ClaudeInfo, String> {
+    let result = state.client.call("daemon.claude_info", json!({})).await?;
+    serde_json::from_value(result).map_err(|e| e.to_string())
 }
 
-/// Resize a session's PTY
+/// Flags and permission modes a claude binary supports (None = the default)
 #[tauri::command]
-pub async fn resize_session(
+pub async fn claude_capabilities(
     state: State<'_, DaemonState>,
-    session_id: String,
-    rows: u16,
-    cols: u16,
-) -> Result<bool, String> {
-    let uuid = Uuid::parse_str(&session_id).map_err(|e| format!("Invalid session_id: {}", e))?;
-
+    binary: Option<String>,
+) -> Result<ClaudeCapabilities, String> {
     let result = state
         .client
-        .call(
-            "session.resize",
-            json!({
-                "session_id": uuid,
-                "rows": rows,
-                "cols": cols,
-            }),
-        )
+        .call("claude.capabilities", json!({ "binary": binary }))
         .await?;
-
-    result
-        .get("success")
-        .and_then(|v| v.as_bool())
-        .ok_or("Missing success field".to_string())
+    serde_json::from_value(result).map_err(|e| e.to_string())
 }
 
-/// List all groups
+/// The MCP servers configured for the project in `dir`
 #[tauri::command]
-pub async fn list_groups(state: State<'_, DaemonState>) -> Result<Vec<Group>, String> {
-    let result = state.client.call("group.list", json!({})).await?;
-    let groups = result.get("groups").ok_or("Missing groups field")?.clone();
-    serde_json::from_value(groups).map_err(|e| e.to_string())
+pub async fn list_mcp_servers(
+    state: State<'_, DaemonState>,
+    dir: String,
+) -> Result<Vec<McpServer>, String> {
+    let result = state.client.call("mcp.list", json!({ "dir": dir })).await?;
+    let result: McpListResult = serde_json::from_value(result).map_err(|e| e.to_string())?;
+    Ok(result.servers)
 }
 
-/// Create a new group
+/// Add an MCP server to the project in `dir`; `config` is its .mcp.json entry
 #[tauri::command]
-pub async fn create_group(
+pub async fn add_mcp_server(
     state: State<'_, DaemonState>,
+    dir: String,
     name: String,
-    parent_id: Option<String>,
-) -> Result<Group, String> {
-    let parent_uuid = parent_id
-        .map(|id| Uuid::parse_str(&id))
-        .transpose()
-        .map_err(|e| format!("Invalid parent_id: {}", e))?;
-
-    let result = state
+    config: serde_json::Value,
+) -> Result<(), String> {
+    state
         .client
         .call(
-            "group.create",
-            json!({
-                "name": name,
-                "parent_id": parent_uuid,
-            }),
+            "mcp.add",
+            json!({ "dir": dir, "name": name, "config": config }),
         )
         .await?;
-
-    let group = result.get("group").ok_or("Missing group field")?.clone();
-    serde_json::from_value(group).map_err(|e| e.to_string())
+    Ok(())
 }
 
-/// Delete a group
 #[tauri::command]
-pub async fn delete_group(state: State<'_, DaemonState>, group_id: String) -> Result<bool, String> {
-    let uuid = Uuid::parse_str(&group_id).map_err(|e| format!("Invalid group_id: {}", e))?;
-
-    // Note: the daemon uses session_id field for group_id in delete
-    let result = state
+pub async fn remove_mcp_server(
+    state: State<'_, DaemonState>,
+    dir: String,
+    name: String,
+) -> Result<(), String> {
+    state
         .client
-        .call("group.delete", json!({ "session_id": uuid }))
+        .call("mcp.remove", json!({ "dir": dir, "name": name }))
         .await?;
-
-    result
-        .get("success")
-        .and_then(|v| v.as_bool())
-        .ok_or("Missing success field".to_string())
+    Ok(())
 }
 
-/// Restart a session (kill and respawn PTY)
-/// rows and cols are required for proper terminal sizing at startup
+/// Let an MCP server run in the project's sessions, or stop it from running
 #[tauri::command]
-pub async fn restart_session(
+pub async fn set_mcp_server_enabled(
     state: State<'_, DaemonState>,
-    session_id: String,
-    rows: u16,
-    cols: u16,
-) -> Result<Session, String> {
-    info!(
-        "restart_session called with session_id: {}, size: {}x{}",
-        session_id, cols, rows
-    );
-
-    let uuid = Uuid::parse_str(&session_id).map_err(|e| {
-        error!("Invalid session_id: {}", e);
-        format!("Invalid session_id: {}", e)
-    })?;
-
-    info!(
-        "Sending session.restart IPC request for {} with size {}x{}",
-        uuid, cols, rows
-    );
-    let result = state
+    dir: String,
+    name: String,
+    enabled: bool,
+) -> Result<(), String> {
+    state
         .client
         .call(
-            "session.restart",
-            json!({
-                "session_id": uuid,
-                "rows": rows,
-                "cols": cols
-            }),
+            "mcp.set_enabled",
+            json!({ "dir": dir, "name": name, "enabled": enabled }),
         )
-        .await
-        .map_err(|e| {
-            error!("session.restart IPC failed: {}", e);
-            e
-        })?;
-
-    info!("session.restart IPC response received");
-    let session = result
-        .get("session")
-        .ok_or_else(|| {
-            error!("Missing session field in response");
-            "Missing session field".to_string()
-        })?
-        .clone();
-
-    let session: Session = serde_json::from_value(session).map_err(|e| {
-        error!("Failed to parse session: {}", e);
-        e.to_string()
-    })?;
-
-    info!(
-        "Session {} restarted successfully, status: {:?}",
-        session.id, session.status
-    );
-    Ok(session)
+        .await?;
+    Ok(())
 }
 
-/// Shutdown the daemon gracefully
+/// Suggested working directories for a new session, most recently used first
 #[tauri::command]
-pub async fn shutdown_daemon(state: State<'_, DaemonState>) -> Result<String, String> {
-    let result = state.client.call("daemon.shutdown", json!({})).await?;
-    Ok(result.to_string())
+pub async fn scan_projects(
+    state: State<'_, DaemonState>,
+    limit: Option<usize>,
+) -> Result<Vec<ProjectCandidate>, String> {
+    let params = match limit {
+        Some(limit) => json!({ "limit": limit }),
+        None => json!({}),
+    };
+    let result = state.client.call("projects.scan", params).await?;
+    let result: ScanProjectsResult = serde_json::from_value(result).map_err(|e| e.to_string())?;
+    Ok(result.projects)
 }
 
-/// Update a session (name and/or group)
-/// For group_id: None = don't change, Some("") = remove from group, Some("uuid") = set group
+/// Start a session after another finishes, or clear that with no parent_id
 #[tauri::command]
-pub async fn update_session(
+pub async fn set_dependency(
     state: State<'_, DaemonState>,
     session_id: String,
-    name: Option<String>,
-    group_id: Option<String>,
+    parent_id: Option<String>,
+    prompt: Option<String>,
 ) -> Result<Session, String> {
-    let session_uuid =
-        Uuid::parse_str(&session_id).map_err(|e| format!("Invalid session_id: {}", e))?;
-
-    // Convert: None = don't change, Some("") = remove group, Some("uuid") = set group
-    let group_uuid: Option<Option<Uuid>> = match group_id {
-        None => None,                                // Don't change
-        Some(ref id) if id.is_empty() => Some(None), // Remove from group
-        Some(id) => Some(Some(
-            Uuid::parse_str(&id).map_err(|e| format!("Invalid group_id: {}", e))?,
-        )),
+    let params = SetDependencyParams {
+        session_id: Uuid::parse_str(&session_id)
+            .map_err(|e| format!("Invalid session_id: {}", e))?,
+        parent_id: parent_id
+            .map(|id| Uuid::parse_str(&id))
+            .transpose()
+            .map_err(|e| format!("Invalid parent_id: {}", e))?,
+        prompt,
     };
-
     let result = state
         .client
         .call(
-            "session.update",
-            json!({
-                "session_id": session_uuid,
-                "name": name,
-                "group_id": group_uuid,
-            }),
+            "session.set_dependency",
+            serde_json::to_value(params).map_err(|e| e.to_string())?,
         )
         .await?;
+    serde_json::from_value(result).map_err(|e| e.to_string())
+}
 
+/// Notify when every session in a group has finished, optionally also POSTing
+/// to `webhook_url`; `enabled: false` removes the watch
+#[tauri::command]
+pub async fn set_completion_watch(
+    state: State<'_, DaemonState>,
+    group_id: String,
+    enabled: bool,
+    webhook_url: Option<String>,
+) -> Result<Group, String> {
+    let params = SetCompletionWatchParams {
+        group_id: Uuid::parse_str(&group_id).map_err(|e| format!("Invalid group_id: {}", e))?,
+        watch: enabled.then(|| CompletionWatch {
+            webhook_url: webhook_url.filter(|url| !url.trim().is_empty()),
+        }),
+    };
+    let result = state
+        .client
+        .call(
+            "group.set_completion_watch",
+            serde_json::to_value(params).map_err(|e| e.to_string())?,
+        )
+        .await?;
     serde_json::from_value(result).map_err(|e| e.to_string())
 }
 
@@ -392,29 +2042,67 @@ pub async fn update_group(
         )),
     };
 
+    let params = UpdateGroupParams {
+        group_id: group_uuid,
+        name,
+        parent_id: parent_uuid,
+    };
     let result = state
         .client
         .call(
             "group.update",
+            serde_json::to_value(params).map_err(|e| e.to_string())?,
+        )
+        .await?;
+
+    serde_json::from_value(result).map_err(|e| e.to_string())
+}
+
+/// Move one or more sessions into a group (None = root level)
+#[tauri::command]
+pub async fn move_sessions(
+    state: State<'_, DaemonState>,
+    session_ids: Vec<String>,
+    group_id: Option<String>,
+) -> Result<Vec<Session>, String> {
+    let session_uuids = session_ids
+        .iter()
+        .map(|id| Uuid::parse_str(id))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Invalid session_id: {}", e))?;
+
+    let group_uuid = group_id
+        .map(|id| Uuid::parse_str(&id))
+        .transpose()
+        .map_err(|e| format!("Invalid group_id: {}", e))?;
+
+    let result = state
+        .client
+        .call(
+            "session.move",
             json!({
+                "session_ids": session_uuids,
                 "group_id": group_uuid,
-                "name": name,
-                "parent_id": parent_uuid,
             }),
         )
         .await?;
 
-    serde_json::from_value(result).map_err(|e| e.to_string())
+    let sessions = result
+        .get("sessions")
+        .ok_or("Missing sessions field")?
+        .clone();
+    serde_json::from_value(sessions).map_err(|e| e.to_string())
 }
 
-/// Reorder a session (move to new position/group via drag and drop)
+/// Reorder a session (move to new position/group via drag and drop); returns
+/// every session whose position changed
 #[tauri::command]
 pub async fn reorder_session(
     state: State<'_, DaemonState>,
     session_id: String,
     group_id: Option<String>,
     after_session_id: Option<String>,
-) -> Result<Session, String> {
+) -> Result<Vec<Session>, String> {
     let session_uuid =
         Uuid::parse_str(&session_id).map_err(|e| format!("Invalid session_id: {}", e))?;
 
@@ -443,14 +2131,15 @@ pub async fn reorder_session(
     serde_json::from_value(result).map_err(|e| e.to_string())
 }
 
-/// Reorder a group (move to new position/parent via drag and drop)
+/// Reorder a group (move to new position/parent via drag and drop); returns
+/// every group whose position changed
 #[tauri::command]
 pub async fn reorder_group(
     state: State<'_, DaemonState>,
     group_id: String,
     parent_id: Option<String>,
     after_group_id: Option<String>,
-) -> Result<Group, String> {
+) -> Result<Vec<Group>, String> {
     let group_uuid = Uuid::parse_str(&group_id).map_err(|e| format!("Invalid group_id: {}", e))?;
 
     let parent_uuid = parent_id
@@ -478,6 +2167,85 @@ pub async fn reorder_group(
     serde_json::from_value(result).map_err(|e| e.to_string())
 }
 
+/// Ask where to save a state export, then write it there (None if cancelled)
+#[tauri::command]
+pub async fn export_state(
+    app: AppHandle,
+    state: State<'_, DaemonState>,
+    include_logs: Option<bool>,
+) -> Result<Option<StateExportResult>, String> {
+    let dialog = app
+        .dialog()
+        .file()
+        .set_title("Export sessions and groups")
+        .set_file_name("claude-master-export.json")
+        .add_filter("JSON", &["json"]);
+    let Some(path) = choose_file(dialog, true).await? else {
+        return Ok(None);
+    };
+
+    let params = StateExportParams {
+        path: path.to_string_lossy().into_owned(),
+        include_logs: include_logs.unwrap_or(false),
+    };
+    let result = state
+        .client
+        .call(
+            "state.export",
+            serde_json::to_value(params).map_err(|e| e.to_string())?,
+        )
+        .await?;
+    serde_json::from_value(result).map_err(|e| e.to_string())
+}
+
+/// Ask for a state export, then add its sessions and groups (None if cancelled)
+#[tauri::command]
+pub async fn import_state(
+    app: AppHandle,
+    state: State<'_, DaemonState>,
+) -> Result<Option<StateImportResult>, String> {
+    let dialog = app
+        .dialog()
+        .file()
+        .set_title("Import sessions and groups")
+        .add_filter("JSON", &["json"]);
+    let Some(path) = choose_file(dialog, false).await? else {
+        return Ok(None);
+    };
+
+    let params = StateImportParams {
+        path: path.to_string_lossy().into_owned(),
+    };
+    let result = state
+        .client
+        .call(
+            "state.import",
+            serde_json::to_value(params).map_err(|e| e.to_string())?,
+        )
+        .await?;
+    serde_json::from_value(result).map_err(|e| e.to_string())
+}
+
+/// Show a save or open dialog and wait for the chosen path
+async fn choose_file(
+    dialog: FileDialogBuilder<Wry>,
+    save: bool,
+) -> Result<Option<PathBuf>, String> {
+    let (tx, rx) = tokio::sync::oneshot::channel();
+    let chosen = move |path: Option<FilePath>| {
+        let _ = tx.send(path);
+    };
+    if save {
+        dialog.save_file(chosen);
+    } else {
+        dialog.pick_file(chosen);
+    }
+    rx.await
+        .map_err(|e| e.to_string())?
+        .map(|path| path.into_path().map_err(|e| e.to_string()))
+        .transpose()
+}
+
 /// Uninstall the daemon completely (removes LaunchAgent and all data)
 /// Use this before uninstalling the app for a clean removal
 #[tauri::command]
@@ -5,35 +5,179 @@
 //! - It stays running when the GUI closes
 //! - Sessions persist across GUI restarts
 //! - It restarts automatically if it crashes
+//!
+//! Each profile gets its own LaunchAgent (and daemon), so profiles can run
+//! side by side; the default profile keeps the original label.
+//!
+//! While the GUI runs, `supervise` also pings the daemon and restarts it when
+//! it stops answering (a hung daemon looks loaded to launchd).
 
 use anyhow::{Context, Result};
+use serde::Serialize;
+use serde_json::json;
 use sha2::{Digest, Sha256};
+use shared::{EXTERNAL_DAEMON_ENV, INSTANCE_ID_ENV, REMOTE_DAEMON_ENV, REMOTE_TOKEN_ENV};
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::process::Command;
+use std::sync::atomic::Ordering;
 use std::time::Duration;
-use tauri::Manager;
-use tracing::{info, warn};
+use tauri::{Emitter, Manager};
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+use crate::ipc_client::RemoteDaemon;
+use crate::DaemonState;
 
 const LAUNCHAGENT_LABEL: &str = "com.claudemaster.daemon";
 const DAEMON_BINARY_NAME: &str = "claude-master-daemon";
+/// Time between the supervisor's pings
+const HEALTH_CHECK_INTERVAL: Duration = Duration::from_secs(10);
+/// Pings in a row that may fail before the daemon is restarted
+const MAX_FAILED_PINGS: u32 = 3;
+/// Longest wait between restarts of a daemon that keeps failing
+const MAX_RESTART_BACKOFF: Duration = Duration::from_secs(300);
+
+/// LaunchAgent label of a profile's daemon
+fn launchagent_label(profile: Option<&str>) -> String {
+    match profile {
+        Some(name) => format!("{}.{}", LAUNCHAGENT_LABEL, name),
+        None => LAUNCHAGENT_LABEL.to_string(),
+    }
+}
 
-/// Get the path to the LaunchAgent plist
-fn get_plist_path() -> Result<PathBuf> {
+/// Get the path to a profile's LaunchAgent plist
+fn get_plist_path(profile: Option<&str>) -> Result<PathBuf> {
     let home = dirs::home_dir().context("Could not find home directory")?;
-    Ok(home.join("Library/LaunchAgents/com.claudemaster.daemon.plist"))
+    Ok(home.join(format!(
+        "Library/LaunchAgents/{}.plist",
+        launchagent_label(profile)
+    )))
 }
 
 /// Get the path where we install the daemon binary
 fn get_installed_daemon_path() -> Result<PathBuf> {
     let home = dirs::home_dir().context("Could not find home directory")?;
-    Ok(home.join("Library/Application Support/com.claudemaster.claude-master/bin/claude-master-daemon"))
+    Ok(home.join(
+        "Library/Application Support/com.claudemaster.claude-master/bin/claude-master-daemon",
+    ))
+}
+
+/// Get the path of the instance id given to the daemon we launch
+fn get_instance_id_path() -> Result<PathBuf> {
+    let home = dirs::home_dir().context("Could not find home directory")?;
+    Ok(home.join("Library/Application Support/com.claudemaster.claude-master/instance_id"))
+}
+
+/// Instance id of the daemon this app launches (created on first use)
+/// The daemon reports it in daemon.hello, so the GUI can tell its own daemon
+/// from another one that took over the socket
+pub fn instance_id() -> Result<Uuid> {
+    let path = get_instance_id_path()?;
+    if let Some(id) = fs::read_to_string(&path)
+        .ok()
+        .and_then(|id| Uuid::parse_str(id.trim()).ok())
+    {
+        return Ok(id);
+    }
+
+    let id = Uuid::new_v4();
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).context("Failed to create app support directory")?;
+    }
+    fs::write(&path, id.to_string()).context("Failed to write instance id")?;
+    info!("Created daemon instance id {}", id);
+    Ok(id)
+}
+
+/// Get the path of the file that puts the GUI in external daemon mode
+fn get_external_marker_path() -> Result<PathBuf> {
+    let home = dirs::home_dir().context("Could not find home directory")?;
+    Ok(home.join("Library/Application Support/com.claudemaster.claude-master/external_daemon"))
+}
+
+/// Whether the daemon is started outside the GUI (e.g. by systemd), so the GUI
+/// only connects to it: set with CLAUDE_MASTER_EXTERNAL_DAEMON=1 (0 turns it
+/// off), or an `external_daemon` file in the app support directory; a remote
+/// daemon is always external
+pub fn external_mode() -> bool {
+    if remote_daemon().is_some() {
+        return true;
+    }
+    match std::env::var(EXTERNAL_DAEMON_ENV) {
+        Ok(value) => !matches!(
+            value.trim().to_ascii_lowercase().as_str(),
+            "" | "0" | "false" | "no"
+        ),
+        Err(_) => get_external_marker_path().is_ok_and(|path| path.exists()),
+    }
+}
+
+/// The daemon on another machine to attach to, if CLAUDE_MASTER_REMOTE names
+/// the local end of a tunnel to it (with its CLAUDE_MASTER_REMOTE_TOKEN)
+pub fn remote_daemon() -> Option<RemoteDaemon> {
+    let addr = std::env::var(REMOTE_DAEMON_ENV).ok()?;
+    let addr = addr.trim();
+    if addr.is_empty() {
+        return None;
+    }
+    let token = std::env::var(REMOTE_TOKEN_ENV).unwrap_or_default();
+    if token.trim().is_empty() {
+        warn!("{} is set without {}", REMOTE_DAEMON_ENV, REMOTE_TOKEN_ENV);
+    }
+    Some(RemoteDaemon {
+        addr: addr.to_string(),
+        token: token.trim().to_string(),
+    })
+}
+
+/// Get the path of the profile the GUI used last
+fn get_saved_profile_path() -> Result<PathBuf> {
+    let home = dirs::home_dir().context("Could not find home directory")?;
+    Ok(home.join("Library/Application Support/com.claudemaster.claude-master/profile"))
+}
+
+/// The profile the GUI used last (None is the default profile)
+pub fn saved_profile() -> Option<String> {
+    let name = fs::read_to_string(get_saved_profile_path().ok()?).ok()?;
+    let name = name.trim();
+    shared::validate_profile_name(name)
+        .is_ok()
+        .then(|| name.to_string())
+}
+
+/// Remember the profile for the next launch of the GUI
+pub fn save_profile(profile: Option<&str>) -> Result<()> {
+    let path = get_saved_profile_path()?;
+    match profile {
+        Some(name) => {
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent).context("Failed to create app support directory")?;
+            }
+            fs::write(&path, name).context("Failed to save profile")?;
+        }
+        None if path.exists() => fs::remove_file(&path).context("Failed to save profile")?,
+        None => {}
+    }
+    Ok(())
 }
 
-/// Get the log file path
-fn get_log_path() -> Result<PathBuf> {
+/// Get a profile's daemon log file path
+fn get_log_path(profile: Option<&str>) -> Result<PathBuf> {
     let home = dirs::home_dir().context("Could not find home directory")?;
-    Ok(home.join("Library/Logs/claude-master-daemon.log"))
+    let name = match profile {
+        Some(name) => format!("claude-master-daemon-{}.log", name),
+        None => "claude-master-daemon.log".to_string(),
+    };
+    Ok(home.join("Library/Logs").join(name))
+}
+
+/// The default profile followed by every named one
+fn all_profiles() -> Vec<Option<String>> {
+    let named = shared::list_profiles().unwrap_or_default();
+    std::iter::once(None)
+        .chain(named.into_iter().map(Some))
+        .collect()
 }
 
 /// Get the bundled daemon path from the app bundle
@@ -88,7 +232,19 @@ fn needs_update(installed: &Path, bundled: &Path) -> Result<bool> {
 }
 
 /// Generate the LaunchAgent plist content
-fn generate_plist(bin_path: &Path, log_path: &Path) -> String {
+fn generate_plist(
+    profile: Option<&str>,
+    bin_path: &Path,
+    log_path: &Path,
+    instance_id: Uuid,
+) -> String {
+    let profile_args = match profile {
+        Some(name) => format!(
+            "\n        <string>--profile</string>\n        <string>{}</string>",
+            name
+        ),
+        None => String::new(),
+    };
     format!(
         r#"<?xml version="1.0" encoding="UTF-8"?>
 <!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
@@ -99,7 +255,7 @@ fn generate_plist(bin_path: &Path, log_path: &Path) -> String {
 
     <key>ProgramArguments</key>
     <array>
-        <string>{}</string>
+        <string>{}</string>{}
     </array>
 
     <key>RunAtLoad</key>
@@ -118,21 +274,24 @@ fn generate_plist(bin_path: &Path, log_path: &Path) -> String {
     <dict>
         <key>RUST_LOG</key>
         <string>info</string>
+        <key>{}</key>
+        <string>{}</string>
     </dict>
 </dict>
 </plist>
 "#,
-        LAUNCHAGENT_LABEL,
+        launchagent_label(profile),
         bin_path.display(),
+        profile_args,
         log_path.display(),
-        log_path.display()
+        log_path.display(),
+        INSTANCE_ID_ENV,
+        instance_id
     )
 }
 
 /// Install the LaunchAgent plist
-fn install_launch_agent(plist_path: &Path, bin_path: &Path, log_path: &Path) -> Result<()> {
-    let plist_content = generate_plist(bin_path, log_path);
-
+fn install_launch_agent(plist_path: &Path, plist_content: &str) -> Result<()> {
     // Create LaunchAgents directory if needed
     if let Some(parent) = plist_path.parent() {
         fs::create_dir_all(parent).context("Failed to create LaunchAgents directory")?;
@@ -169,16 +328,19 @@ fn unload_launch_agent(plist_path: &Path) -> Result<()> {
 
     if !status.success() {
         // This is often expected if the agent wasn't loaded
-        info!("launchctl unload returned non-zero status (may be expected): {:?}", status);
+        info!(
+            "launchctl unload returned non-zero status (may be expected): {:?}",
+            status
+        );
     }
 
     Ok(())
 }
 
-/// Check if the daemon is running by checking launchctl
-fn is_launchagent_loaded() -> bool {
+/// Check if a profile's daemon is running by checking launchctl
+fn is_launchagent_loaded(profile: Option<&str>) -> bool {
     let output = Command::new("launchctl")
-        .args(["list", LAUNCHAGENT_LABEL])
+        .args(["list", &launchagent_label(profile)])
         .output();
 
     match output {
@@ -210,11 +372,18 @@ fn install_daemon_binary(bundled: &Path, installed: &Path) -> Result<()> {
     Ok(())
 }
 
-/// Ensure the daemon is running, installing/updating as needed
+/// Ensure the current profile's daemon is running, installing/updating as needed
 pub async fn ensure_daemon_running(app: &tauri::AppHandle) -> Result<()> {
-    let plist_path = get_plist_path()?;
+    if app.state::<DaemonState>().external {
+        info!("Daemon is started externally, not launching it");
+        return Ok(());
+    }
+
+    let profile = shared::current_profile();
+    let profile = profile.as_deref();
+    let plist_path = get_plist_path(profile)?;
     let installed_path = get_installed_daemon_path()?;
-    let log_path = get_log_path()?;
+    let log_path = get_log_path(profile)?;
     let bundled_path = get_bundled_daemon_path(app)?;
 
     info!("Checking daemon status...");
@@ -228,26 +397,44 @@ pub async fn ensure_daemon_running(app: &tauri::AppHandle) -> Result<()> {
     if update_needed {
         info!("Daemon binary needs update");
 
-        // Stop the daemon if it's running
-        if plist_path.exists() && is_launchagent_loaded() {
-            info!("Stopping existing daemon for update...");
-            unload_launch_agent(&plist_path)?;
-            // Give it time to shut down gracefully
+        // Stop every profile's daemon, since they all run the installed binary
+        let mut stopped = Vec::new();
+        for other in all_profiles() {
+            let other_plist = get_plist_path(other.as_deref())?;
+            if other_plist.exists() && is_launchagent_loaded(other.as_deref()) {
+                info!("Stopping existing daemon for update...");
+                unload_launch_agent(&other_plist)?;
+                stopped.push(other);
+            }
+        }
+        if !stopped.is_empty() {
+            // Give them time to shut down gracefully
             tokio::time::sleep(Duration::from_millis(500)).await;
         }
 
         // Install the new binary
         install_daemon_binary(&bundled_path, &installed_path)?;
+
+        // The current profile is started below; bring the others back now
+        for other in stopped.iter().filter(|other| other.as_deref() != profile) {
+            load_launch_agent(&get_plist_path(other.as_deref())?)?;
+        }
     }
 
-    // Install LaunchAgent if missing
-    if !plist_path.exists() {
+    // Install LaunchAgent if missing or out of date (e.g. written before instance ids)
+    let plist_content = generate_plist(profile, &installed_path, &log_path, instance_id()?);
+    if fs::read_to_string(&plist_path).ok().as_deref() != Some(plist_content.as_str()) {
+        if plist_path.exists() && is_launchagent_loaded(profile) {
+            info!("Stopping daemon to update LaunchAgent...");
+            unload_launch_agent(&plist_path)?;
+            tokio::time::sleep(Duration::from_millis(500)).await;
+        }
         info!("Installing LaunchAgent...");
-        install_launch_agent(&plist_path, &installed_path, &log_path)?;
+        install_launch_agent(&plist_path, &plist_content)?;
     }
 
     // Load the LaunchAgent if not loaded
-    if !is_launchagent_loaded() {
+    if !is_launchagent_loaded(profile) {
         info!("Loading LaunchAgent...");
         load_launch_agent(&plist_path)?;
         // Give daemon time to start
@@ -258,21 +445,122 @@ pub async fn ensure_daemon_running(app: &tauri::AppHandle) -> Result<()> {
     Ok(())
 }
 
+/// Restart our daemon after another daemon was shut down to free the socket
+/// Ours may still be running, but the other daemon replaced its socket file,
+/// so it has to start over to listen again
+pub async fn restart_own_daemon(app: &tauri::AppHandle) -> Result<()> {
+    if app.state::<DaemonState>().external {
+        anyhow::bail!("The daemon is started externally; restart it where it was started");
+    }
+    let profile = shared::current_profile();
+    let plist_path = get_plist_path(profile.as_deref())?;
+    if plist_path.exists() && is_launchagent_loaded(profile.as_deref()) {
+        unload_launch_agent(&plist_path)?;
+        tokio::time::sleep(Duration::from_millis(500)).await;
+    }
+    ensure_daemon_running(app).await
+}
+
+/// Payload of the daemon:health event
+#[derive(Clone, Serialize)]
+pub struct DaemonHealth {
+    pub healthy: bool,
+    pub version: Option<String>,
+    pub uptime_secs: Option<i64>,
+    /// Restarts by the supervisor since the GUI started
+    pub restart_count: u32,
+    pub error: Option<String>,
+}
+
+/// Start the daemon, then keep it healthy: ping it every few seconds, restart
+/// it (backing off exponentially while it keeps failing) once it stops
+/// answering, and report each check to the frontend as daemon:health
+pub async fn supervise(app: tauri::AppHandle) {
+    info!("Ensuring daemon is running...");
+    if let Err(e) = ensure_daemon_running(&app).await {
+        error!("Failed to ensure daemon is running: {}", e);
+    }
+
+    let mut failed_pings = 0u32;
+    let mut restart_count = 0u32;
+    // Restarts since the daemon last answered, for the backoff
+    let mut failed_restarts = 0u32;
+    let mut next_restart = tokio::time::Instant::now();
+
+    loop {
+        tokio::time::sleep(HEALTH_CHECK_INTERVAL).await;
+        let state = app.state::<DaemonState>();
+
+        let error = match state.client.call("daemon.ping", json!({})).await {
+            Ok(_) => {
+                failed_pings = 0;
+                failed_restarts = 0;
+                None
+            }
+            Err(e) => {
+                failed_pings += 1;
+                warn!("Daemon health check failed ({}): {}", failed_pings, e);
+                Some(e.to_string())
+            }
+        };
+
+        let hello = error.is_none().then(|| state.client.hello()).flatten();
+        let health = DaemonHealth {
+            healthy: error.is_none(),
+            version: hello.as_ref().map(|hello| hello.version.clone()),
+            uptime_secs: hello
+                .as_ref()
+                .map(|hello| (chrono::Utc::now() - hello.started_at).num_seconds()),
+            restart_count,
+            error,
+        };
+        if let Err(e) = app.emit("daemon:health", &health) {
+            error!("Failed to emit daemon health: {}", e);
+        }
+
+        // A daemon this app didn't launch isn't ours to restart
+        if failed_pings < MAX_FAILED_PINGS
+            || state.external
+            || state.attached.load(Ordering::Relaxed)
+            || tokio::time::Instant::now() < next_restart
+        {
+            continue;
+        }
+
+        warn!(
+            "Daemon unresponsive after {} health checks, restarting",
+            failed_pings
+        );
+        restart_count += 1;
+        let backoff = HEALTH_CHECK_INTERVAL * 2u32.saturating_pow(failed_restarts);
+        next_restart = tokio::time::Instant::now() + backoff.min(MAX_RESTART_BACKOFF);
+        failed_restarts = failed_restarts.saturating_add(1);
+        failed_pings = 0;
+        state.client.disconnect().await;
+        if let Err(e) = restart_own_daemon(&app).await {
+            error!("Failed to restart daemon: {}", e);
+        }
+    }
+}
+
 /// Uninstall the daemon completely (for clean app removal)
 pub fn uninstall_daemon() -> Result<()> {
-    let plist_path = get_plist_path()?;
+    // Read before the app support directory (and the profiles in it) is removed
+    let profiles = all_profiles();
     let app_support = dirs::home_dir()
         .context("Could not find home directory")?
         .join("Library/Application Support/com.claudemaster.claude-master");
-    let log_path = get_log_path()?;
 
     info!("Uninstalling daemon...");
 
-    // Stop and unload service
-    if plist_path.exists() {
-        let _ = unload_launch_agent(&plist_path);
-        fs::remove_file(&plist_path).ok();
-        info!("Removed LaunchAgent plist");
+    // Stop and unload every profile's service
+    for profile in &profiles {
+        let plist_path = get_plist_path(profile.as_deref())?;
+        if plist_path.exists() {
+            let _ = unload_launch_agent(&plist_path);
+            fs::remove_file(&plist_path).ok();
+            info!("Removed LaunchAgent plist {:?}", plist_path);
+        }
     }
 
     // Remove app support directory (bin, socket, state)
@@ -281,10 +569,13 @@ pub fn uninstall_daemon() -> Result<()> {
         info!("Removed app support directory");
     }
 
-    // Remove log file
-    if log_path.exists() {
-        fs::remove_file(&log_path).ok();
-        info!("Removed log file");
+    // Remove log files
+    for profile in &profiles {
+        let log_path = get_log_path(profile.as_deref())?;
+        if log_path.exists() {
+            fs::remove_file(&log_path).ok();
+            info!("Removed log file {:?}", log_path);
+        }
     }
 
     info!("Daemon uninstalled");
@@ -294,5 +585,5 @@ pub fn uninstall_daemon() -> Result<()> {
 /// Check if daemon is running (for status display)
 #[allow(dead_code)]
 pub fn is_daemon_running() -> bool {
-    is_launchagent_loaded()
+    is_launchagent_loaded(shared::current_profile().as_deref())
 }
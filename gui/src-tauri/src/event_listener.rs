@@ -1,49 +1,131 @@
 //! Event listener for streaming events from daemon to frontend
 
-use interprocess::local_socket::{
-    tokio::{prelude::*, Stream},
-    GenericFilePath,
-};
 use serde::Serialize;
-use shared::{get_socket_path, Event};
-use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::Arc;
-use tauri::{AppHandle, Emitter};
-use tokio::io::{AsyncBufReadExt, BufReader};
+use shared::{
+    DebugTapParams, Event, GroupCompletedData, NeedsAttentionData, ScheduleFinishedData,
+    SessionStatus,
+};
+use tauri::{AppHandle, Emitter, EventTarget, Manager};
+use tauri_plugin_notification::NotificationExt;
+use tokio::sync::broadcast;
 use tracing::{error, info, warn};
 
+use crate::DaemonState;
+
 /// Connection state payload for frontend
 #[derive(Clone, Serialize)]
 pub struct ConnectionState {
     pub connected: bool,
     pub error: Option<String>,
+    /// Events missed while disconnected were replayed, so no reload is needed
+    pub caught_up: bool,
 }
 
 /// Emit connection state to frontend
 fn emit_connection_state(app: &AppHandle, connected: bool, error: Option<String>) {
-    let state = ConnectionState { connected, error };
+    let caught_up = connected && app.state::<DaemonState>().client.caught_up();
+    let state = ConnectionState {
+        connected,
+        error,
+        caught_up,
+    };
     if let Err(e) = app.emit("daemon:connection_state", &state) {
         error!("Failed to emit connection state: {}", e);
     }
 }
 
+/// Show an OS notification for a session that needs attention, unless muted
+fn notify_needs_attention(app: &AppHandle, data: &NeedsAttentionData) {
+    if data.muted {
+        return;
+    }
+
+    let body = match (&data.watch, data.status) {
+        (Some(watch), _) => format!("Watch triggered: {}", watch),
+        (None, SessionStatus::Error) => "Session hit an error".to_string(),
+        (None, _) => "Waiting for your input".to_string(),
+    };
+    if let Err(e) = app
+        .notification()
+        .builder()
+        .title(&data.name)
+        .body(body)
+        .show()
+    {
+        warn!("Failed to show notification: {}", e);
+    }
+}
+
+/// Show an OS notification with how a scheduled run ended, unless muted
+fn notify_schedule_finished(app: &AppHandle, data: &ScheduleFinishedData) {
+    if data.muted {
+        return;
+    }
+
+    let body = match data.status {
+        SessionStatus::Error => "Scheduled run hit an error",
+        SessionStatus::Stopped => "Scheduled run stopped",
+        _ => "Scheduled run finished",
+    };
+    if let Err(e) = app
+        .notification()
+        .builder()
+        .title(&data.name)
+        .body(body)
+        .show()
+    {
+        warn!("Failed to show notification: {}", e);
+    }
+}
+
+/// Show one OS notification summing up a group whose sessions all finished
+fn notify_group_completed(app: &AppHandle, data: &GroupCompletedData) {
+    if data.muted {
+        return;
+    }
+
+    let errors: Vec<&str> = data
+        .sessions
+        .iter()
+        .filter(|s| s.status == SessionStatus::Error)
+        .map(|s| s.name.as_str())
+        .collect();
+    let body = if errors.is_empty() {
+        format!("All {} sessions finished", data.sessions.len())
+    } else {
+        format!(
+            "{} sessions finished, {} with errors: {}",
+            data.sessions.len(),
+            errors.len(),
+            errors.join(", ")
+        )
+    };
+    if let Err(e) = app
+        .notification()
+        .builder()
+        .title(&data.name)
+        .body(body)
+        .show()
+    {
+        warn!("Failed to show notification: {}", e);
+    }
+}
+
 /// Start the event listener in a background task
-/// This creates a separate connection to the daemon for receiving events
+/// Events arrive on the IPC client's connection; this forwards them to the
+/// frontend and reconnects whenever that connection drops
 pub fn start_event_listener(app: AppHandle) {
-    let running = Arc::new(AtomicBool::new(true));
-    let running_clone = running.clone();
-
     // Use Tauri's async runtime to spawn the task
     tauri::async_runtime::spawn(async move {
         let mut reconnect_attempts = 0u32;
         let max_backoff = 30; // Maximum 30 seconds between attempts
 
-        while running_clone.load(Ordering::Relaxed) {
+        loop {
             match run_event_loop(&app).await {
                 Ok(()) => {
                     info!("Event loop ended normally");
                     emit_connection_state(&app, false, None);
-                    reconnect_attempts = 0;
+                    return;
                 }
                 Err(e) => {
                     warn!("Event loop error: {}, reconnecting...", e);
@@ -60,59 +142,106 @@ pub fn start_event_listener(app: AppHandle) {
     });
 }
 
+/// Forward events until the connection drops (an error) or the client is gone
 async fn run_event_loop(app: &AppHandle) -> Result<(), String> {
-    let socket_path = get_socket_path().map_err(|e| e.to_string())?;
-
-    // Wait for socket to exist with timeout
-    let mut wait_attempts = 0;
-    while !socket_path.exists() {
-        wait_attempts += 1;
-        if wait_attempts > 60 {
-            // 30 seconds max wait
-            return Err("Daemon socket not found after 30s".to_string());
-        }
-        tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
-    }
-
-    let name = socket_path
-        .to_fs_name::<GenericFilePath>()
-        .map_err(|e| e.to_string())?;
-
-    let stream = Stream::connect(name)
-        .await
-        .map_err(|e| format!("Failed to connect: {}", e))?;
+    let state = app.state::<DaemonState>();
+    // Subscribed before connecting, so nothing sent after the connect is missed
+    let mut events = state.client.subscribe_events();
+    state.client.connect().await?;
+    let mut connection = state.client.connection();
+    let Some(connection_id) = *connection.borrow_and_update() else {
+        return Err("Connection closed".to_string());
+    };
 
     info!("Event listener connected to daemon");
     emit_connection_state(app, true, None);
 
-    let (recv_half, _send_half) = stream.split();
-    let mut reader = BufReader::new(recv_half);
-    let mut line = String::new();
+    // Re-apply the frontend's subscription and an open inspector on every (re)connect
+    let mut subscription = state.event_subscription.subscribe();
+    if !subscription.borrow().is_all() {
+        subscription.mark_changed();
+    }
+    let mut debug_tap = state.debug_tap.subscribe();
+    if debug_tap.borrow().is_some() {
+        debug_tap.mark_changed();
+    }
 
     loop {
-        line.clear();
-        match reader.read_line(&mut line).await {
-            Ok(0) => {
-                // Connection closed
-                return Err("Connection closed".to_string());
-            }
-            Ok(_) => {
-                // Try to parse as Event
-                if let Ok(event) = serde_json::from_str::<Event>(&line) {
-                    // Log PTY output events (truncated)
-                    if event.event == "pty:output" {
-                        info!("Forwarding pty:output event to frontend");
+        tokio::select! {
+            event = events.recv() => match event {
+                Ok(event) => {
+                    match &event {
+                        Event::PtyOutput(_) => info!("Forwarding pty:output event to frontend"),
+                        Event::NeedsAttention(data) => notify_needs_attention(app, data),
+                        Event::ScheduleFinished(data) => notify_schedule_finished(app, data),
+                        Event::GroupCompleted(data) => notify_group_completed(app, data),
+                        _ => {}
                     }
                     // Emit to frontend
-                    if let Err(e) = app.emit(&event.event, &event.data) {
+                    if let Err(e) = emit_event(app, &event) {
                         error!("Failed to emit event: {}", e);
                     }
                 }
-                // Ignore responses (they have "id" field) - those are handled by the command connection
+                Err(broadcast::error::RecvError::Lagged(n)) => {
+                    warn!("Event listener lagged, missed {} events", n);
+                }
+                Err(broadcast::error::RecvError::Closed) => return Ok(()),
+            },
+            changed = subscription.changed() => {
+                if changed.is_err() {
+                    return Ok(());
+                }
+                let params = subscription.borrow_and_update().clone();
+                let params = serde_json::to_value(params).map_err(|e| e.to_string())?;
+                if let Err(e) = state.client.call("events.subscribe", params).await {
+                    warn!("Failed to update event subscription: {}", e);
+                }
             }
-            Err(e) => {
-                return Err(format!("Read error: {}", e));
+            changed = debug_tap.changed() => {
+                if changed.is_err() {
+                    return Ok(());
+                }
+                let include_output = *debug_tap.borrow_and_update();
+                set_debug_tap(app, include_output).await;
+            }
+            _ = async {
+                connection.wait_for(|current| *current != Some(connection_id)).await.is_ok()
+            } => {
+                return Err("Connection closed".to_string());
             }
         }
     }
 }
+
+/// Turn the daemon's mirror of this connection's traffic on (Some, with or
+/// without terminal output) or off; records arrive as debug:tap events
+async fn set_debug_tap(app: &AppHandle, include_output: Option<bool>) {
+    let params = DebugTapParams {
+        enabled: include_output.is_some(),
+        include_output: include_output.unwrap_or(false),
+    };
+    let params = serde_json::to_value(params).expect("DebugTapParams serializes");
+    let client = &app.state::<DaemonState>().client;
+    match client.call("debug.tap", params).await {
+        Ok(_) => info!("Debug tap {:?}", include_output),
+        Err(e) => warn!("Failed to set debug tap: {}", e),
+    }
+}
+
+/// Emit a daemon event to the frontend windows; session pop-out windows only
+/// get events of their own session (and those not about any session)
+fn emit_event(app: &AppHandle, event: &Event) -> tauri::Result<()> {
+    let session_id = event.session_id();
+    let state = app.state::<DaemonState>();
+    let session_windows = state.session_windows.lock().unwrap();
+    if session_windows.is_empty() {
+        return app.emit(event.name(), event.data());
+    }
+    app.emit_filter(event.name(), event.data(), |target| match target {
+        EventTarget::WebviewWindow { label } => match session_windows.get(label) {
+            Some(shown) => session_id.is_none_or(|id| id == *shown),
+            None => true,
+        },
+        _ => true,
+    })
+}
@@ -1,144 +1,419 @@
 //! IPC client for connecting to the daemon
+//!
+//! One connection carries both commands and events: a reader task matches each
+//! response to the waiting call by id and broadcasts events to subscribers (the
+//! event listener), so nothing the daemon sends is dropped. After a reconnect
+//! the events the daemon journaled meanwhile are fetched with `events.since`
+//! and passed on first, so subscribers see them in order without a reload.
+//!
+//! The connection is normally the daemon socket. For a daemon on another
+//! machine it is TCP to a local port forwarded to that daemon's tunnel port
+//! (see `claude-master-daemon --tunnel`); the protocol is the same.
 
 use interprocess::local_socket::{
-    tokio::{prelude::*, RecvHalf, SendHalf, Stream},
+    tokio::{prelude::*, Stream},
     GenericFilePath,
 };
 use serde_json::Value;
-use shared::{get_socket_path, Request, Response};
+use shared::{
+    get_socket_path, get_token_path, ChunkAssembler, ClientInfo, ClientKind, ErrorCode, ErrorInfo,
+    Event, EventsSinceResult, HelloResult, Request, Response, ResponseChunk, SequencedEvent,
+    MAX_FRAME_BYTES,
+};
+use std::collections::HashMap;
+use std::fmt;
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 use std::time::Duration;
-use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
-use tokio::sync::Mutex;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+use tokio::sync::{broadcast, oneshot, watch, Mutex};
+use tokio::task::JoinHandle;
 use tokio::time::timeout;
+use tracing::{info, warn};
+use uuid::Uuid;
 
 /// Default request timeout in seconds
 const REQUEST_TIMEOUT_SECS: u64 = 30;
 
+/// Events buffered for a slow subscriber before it starts missing them
+const EVENT_BUFFER: usize = 1024;
+
+/// The halves of a connection, socket or TCP
+type Reader = Box<dyn AsyncRead + Send + Unpin>;
+type Writer = Box<dyn AsyncWrite + Send + Unpin>;
+
+/// Calls waiting for their response, by request id
+type Pending = Arc<std::sync::Mutex<HashMap<u64, oneshot::Sender<Result<Response, String>>>>>;
+
+/// Where the app is in the daemon's event journal
+#[derive(Default)]
+struct JournalPosition {
+    /// Journal the sequence number is from
+    journal_id: Option<Uuid>,
+    /// Last journaled event passed on (0 for none)
+    seq: u64,
+    /// Events held back while a new connection catches up
+    held: Option<Vec<SequencedEvent>>,
+    /// Whether the live connection caught up on what the app missed
+    caught_up: bool,
+}
+
+impl JournalPosition {
+    /// Broadcast the event unless the catch-up already passed it on
+    fn pass_on(&mut self, sequenced: SequencedEvent, events: &broadcast::Sender<Event>) {
+        if let Some(seq) = sequenced.seq {
+            if seq <= self.seq {
+                return;
+            }
+            self.seq = seq;
+        }
+        let _ = events.send(sequenced.event);
+    }
+}
+
+/// Token for daemon.auth, present while the daemon has daemon.require_token on
+pub fn daemon_token() -> Option<String> {
+    let token = std::fs::read_to_string(get_token_path().ok()?).ok()?;
+    Some(token.trim().to_string())
+}
+
+/// A daemon on another machine, reached through a forwarded port
+#[derive(Debug, Clone)]
+pub struct RemoteDaemon {
+    /// Local end of the tunnel, e.g. 127.0.0.1:7879
+    pub addr: String,
+    /// Contents of the remote daemon's tunnel.token
+    pub token: String,
+}
+
+/// A failed call: an error from the daemon, or a connection problem (no code)
+#[derive(Debug, Clone)]
+pub struct CallError {
+    pub code: Option<ErrorCode>,
+    pub message: String,
+}
+
+impl fmt::Display for CallError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl From<String> for CallError {
+    fn from(message: String) -> Self {
+        Self {
+            code: None,
+            message,
+        }
+    }
+}
+
+impl From<ErrorInfo> for CallError {
+    fn from(error: ErrorInfo) -> Self {
+        Self {
+            code: error.kind(),
+            message: error.message,
+        }
+    }
+}
+
+/// Commands report errors to the frontend as strings
+impl From<CallError> for String {
+    fn from(error: CallError) -> Self {
+        error.message
+    }
+}
+
+/// One connection to the daemon
+struct Connection {
+    id: u64,
+    writer: Writer,
+    pending: Pending,
+    reader: JoinHandle<()>,
+}
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        // Dropping the reader's pending map fails any calls still waiting
+        self.reader.abort();
+    }
+}
+
 /// IPC client for communicating with the daemon
 pub struct IpcClient {
-    reader: Arc<Mutex<Option<BufReader<RecvHalf>>>>,
-    writer: Arc<Mutex<Option<SendHalf>>>,
+    connection: Mutex<Option<Connection>>,
+    /// Id of the live connection; cleared when the daemon closes it
+    connected: watch::Sender<Option<u64>>,
+    /// Events from every connection, in order
+    events: broadcast::Sender<Event>,
     request_id: AtomicU64,
+    connection_id: AtomicU64,
+    /// daemon.hello from the current connection (None for daemons without it)
+    hello: std::sync::Mutex<Option<HelloResult>>,
+    /// Session whose terminal the app shows (session.view), told to the
+    /// daemon again after a reconnect
+    viewing: std::sync::Mutex<Option<Uuid>>,
+    /// Set to connect over a tunnel instead of the local socket
+    remote: Option<RemoteDaemon>,
+    journal: Arc<std::sync::Mutex<JournalPosition>>,
 }
 
 impl IpcClient {
-    pub fn new() -> Self {
+    pub fn new(remote: Option<RemoteDaemon>) -> Self {
         Self {
-            reader: Arc::new(Mutex::new(None)),
-            writer: Arc::new(Mutex::new(None)),
+            connection: Mutex::new(None),
+            connected: watch::Sender::new(None),
+            events: broadcast::Sender::new(EVENT_BUFFER),
             request_id: AtomicU64::new(1),
+            connection_id: AtomicU64::new(1),
+            hello: std::sync::Mutex::new(None),
+            viewing: std::sync::Mutex::new(None),
+            remote,
+            journal: Arc::default(),
         }
     }
 
-    /// Connect to the daemon socket
+    /// Events the daemon sends, across reconnects
+    pub fn subscribe_events(&self) -> broadcast::Receiver<Event> {
+        self.events.subscribe()
+    }
+
+    /// Id of the live connection (None while disconnected); changes when the
+    /// client reconnects, so per-connection settings can be applied again
+    pub fn connection(&self) -> watch::Receiver<Option<u64>> {
+        self.connected.subscribe()
+    }
+
+    /// Connect to the daemon socket, or the tunnel to a remote daemon
     /// This is idempotent - calling it when already connected is a no-op
     pub async fn connect(&self) -> Result<(), String> {
-        // Check if already connected
-        {
-            let writer_guard = self.writer.lock().await;
-            if writer_guard.is_some() {
-                return Ok(()); // Already connected
-            }
+        // Held throughout, so concurrent callers don't open two connections
+        let mut connection = self.connection.lock().await;
+        if self.connected.borrow().is_some() {
+            return Ok(()); // Already connected
         }
 
-        let socket_path = get_socket_path().map_err(|e| e.to_string())?;
+        let (recv_half, send_half) = match &self.remote {
+            Some(remote) => connect_tcp(&remote.addr).await?,
+            None => connect_socket().await?,
+        };
+        let id = self.connection_id.fetch_add(1, Ordering::Relaxed);
+        let pending = Pending::default();
+        self.journal.lock().unwrap().held = Some(Vec::new());
+        let reader = tokio::spawn(read_loop(
+            id,
+            BufReader::new(recv_half),
+            pending.clone(),
+            self.events.clone(),
+            self.journal.clone(),
+            self.connected.clone(),
+        ));
+        *connection = Some(Connection {
+            id,
+            writer: send_half,
+            pending,
+            reader,
+        });
 
-        if !socket_path.exists() {
-            return Err("Daemon socket not found. Is the daemon running?".to_string());
+        // A daemon that requires a token refuses everything else until it gets
+        // it; one on the other end of a tunnel always does
+        let token = match &self.remote {
+            Some(remote) => Some(remote.token.clone()),
+            None => daemon_token(),
+        };
+        if let Some(token) = token {
+            let auth = self
+                .request(
+                    connection.as_mut(),
+                    "daemon.auth",
+                    serde_json::json!({ "token": token }),
+                )
+                .await;
+            if let Err(e) = auth {
+                if e.code == Some(ErrorCode::Unauthorized) {
+                    *connection = None;
+                    return Err(format!("Daemon rejected the auth token: {}", e));
+                }
+            }
         }
 
-        let name = socket_path
-            .to_fs_name::<GenericFilePath>()
-            .map_err(|e| e.to_string())?;
-
-        let stream = Stream::connect(name)
+        // Learn what this daemon supports, so calls to methods it lacks fail clearly
+        let hello = self
+            .request(connection.as_mut(), "daemon.hello", serde_json::json!({}))
             .await
-            .map_err(|e| format!("Failed to connect to daemon: {}", e))?;
+            .ok()
+            .and_then(|result| serde_json::from_value::<HelloResult>(result).ok());
+        *self.hello.lock().unwrap() = hello;
+
+        // Let other clients see this is the app, and which session it shows
+        if self.supports("client.identify") {
+            let identify = serde_json::json!({ "kind": ClientKind::Gui });
+            if let Err(e) = self
+                .request(connection.as_mut(), "client.identify", identify)
+                .await
+            {
+                warn!("client.identify failed: {}", e);
+            }
+            let viewing = *self.viewing.lock().unwrap();
+            if viewing.is_some() {
+                let view = serde_json::json!({ "session_id": viewing });
+                if let Err(e) = self
+                    .request(connection.as_mut(), "session.view", view)
+                    .await
+                {
+                    warn!("session.view failed: {}", e);
+                }
+            }
+        }
+
+        self.catch_up(connection.as_mut()).await;
+        self.connected.send_replace(Some(id));
+        Ok(())
+    }
 
-        let (recv_half, send_half) = stream.split();
+    /// Pass on what the daemon journaled since the last event the app saw,
+    /// then the events held back while asking
+    async fn catch_up(&self, connection: Option<&mut Connection>) {
+        let journal_id = self.hello().and_then(|hello| hello.journal_id);
+        let since = {
+            let mut position = self.journal.lock().unwrap();
+            // Numbers from another journal (another daemon, or a new journal) mean nothing here
+            let same = journal_id.is_some() && position.journal_id == journal_id;
+            if !same {
+                position.journal_id = journal_id;
+                position.seq = 0;
+            }
+            (same && position.seq > 0).then_some(position.seq)
+        };
 
-        {
-            let mut reader_guard = self.reader.lock().await;
-            *reader_guard = Some(BufReader::new(recv_half));
+        let mut missed = None;
+        if let Some(seq) = since {
+            let result = self
+                .request(
+                    connection,
+                    "events.since",
+                    serde_json::json!({ "seq": seq }),
+                )
+                .await
+                .and_then(|result| {
+                    serde_json::from_value::<EventsSinceResult>(result)
+                        .map_err(|e| CallError::from(e.to_string()))
+                });
+            match result {
+                Ok(result) if result.complete => missed = Some(result.events),
+                Ok(_) => info!("Missed events are no longer journaled, reloading"),
+                Err(e) => warn!("events.since failed: {}", e),
+            }
         }
 
-        {
-            let mut writer_guard = self.writer.lock().await;
-            *writer_guard = Some(send_half);
+        let mut position = self.journal.lock().unwrap();
+        position.caught_up = missed.is_some();
+        let held = position.held.take().unwrap_or_default();
+        for event in missed.unwrap_or_default().into_iter().chain(held) {
+            position.pass_on(event, &self.events);
         }
+    }
 
-        Ok(())
+    /// Whether the live connection caught up on the events missed while the
+    /// app was disconnected; if not, state has to be reloaded
+    pub fn caught_up(&self) -> bool {
+        self.journal.lock().unwrap().caught_up
+    }
+
+    /// Tell the daemon which session's terminal the app shows
+    pub async fn view(&self, session_id: Option<Uuid>) -> Result<ClientInfo, CallError> {
+        *self.viewing.lock().unwrap() = session_id;
+        let result = self
+            .call(
+                "session.view",
+                serde_json::json!({ "session_id": session_id }),
+            )
+            .await?;
+        serde_json::from_value(result).map_err(|e| CallError::from(e.to_string()))
+    }
+
+    /// The connected daemon's daemon.hello, if it answered one
+    pub fn hello(&self) -> Option<HelloResult> {
+        self.hello.lock().unwrap().clone()
+    }
+
+    /// Whether the connected daemon answers `method` (assumed for daemons too
+    /// old to say)
+    pub fn supports(&self, method: &str) -> bool {
+        self.hello
+            .lock()
+            .unwrap()
+            .as_ref()
+            .is_none_or(|hello| hello.supports(method))
     }
 
     /// Check if connected to the daemon
     pub async fn is_connected(&self) -> bool {
-        let writer_guard = self.writer.lock().await;
-        writer_guard.is_some()
+        self.connected.borrow().is_some()
     }
 
     /// Disconnect from the daemon
     pub async fn disconnect(&self) {
-        {
-            let mut reader_guard = self.reader.lock().await;
-            *reader_guard = None;
-        }
-        {
-            let mut writer_guard = self.writer.lock().await;
-            *writer_guard = None;
-        }
+        let mut connection = self.connection.lock().await;
+        *connection = None;
+        self.connected.send_replace(None);
     }
 
     /// Send a request and wait for the response with timeout
     /// Auto-reconnects if not connected
-    pub async fn call(&self, method: &str, params: Value) -> Result<Value, String> {
+    pub async fn call(&self, method: &str, params: Value) -> Result<Value, CallError> {
         // Auto-reconnect if not connected
         if !self.is_connected().await {
             self.connect().await?;
         }
+        if !self.supports(method) {
+            let version = self.hello().map(|hello| hello.version).unwrap_or_default();
+            return Err(CallError {
+                code: Some(ErrorCode::MethodNotFound),
+                message: format!(
+                    "Method not found: {} (the running daemon is version {}; restart it to update)",
+                    method, version
+                ),
+            });
+        }
 
-        let result = timeout(
-            Duration::from_secs(REQUEST_TIMEOUT_SECS),
-            self.call_inner(method, params.clone()),
-        )
-        .await;
-
-        match result {
-            Ok(inner_result) => {
-                // If there was a connection error, disconnect and retry once
-                if let Err(ref e) = inner_result {
-                    if e.contains("Failed to send")
-                        || e.contains("Failed to read")
-                        || e.contains("Not connected")
-                        || e.contains("Connection closed")
-                    {
-                        self.disconnect().await;
-                        // Try to reconnect and retry once
-                        if self.connect().await.is_ok() {
-                            return timeout(
-                                Duration::from_secs(REQUEST_TIMEOUT_SECS),
-                                self.call_inner(method, params),
-                            )
-                            .await
-                            .map_err(|_| {
-                                format!("Request timed out after {}s", REQUEST_TIMEOUT_SECS)
-                            })?;
-                        }
-                    }
-                }
-                inner_result
-            }
-            Err(_) => {
-                // Timeout - disconnect and return error
-                self.disconnect().await;
-                Err(format!("Request timed out after {}s", REQUEST_TIMEOUT_SECS))
+        let result = self.call_once(method, params.clone()).await;
+        // If the connection dropped under us, reconnect and retry once
+        if let Err(ref e) = result {
+            if e.code.is_none() && !self.is_connected().await && self.connect().await.is_ok() {
+                return self.call_once(method, params).await;
             }
         }
+        result
+    }
+
+    async fn call_once(&self, method: &str, params: Value) -> Result<Value, CallError> {
+        let response = {
+            let mut connection = self.connection.lock().await;
+            self.send(connection.as_mut(), method, params).await?
+        };
+        wait(response).await
     }
 
-    /// Internal call implementation without timeout
-    async fn call_inner(&self, method: &str, params: Value) -> Result<Value, String> {
+    /// Send a request on `connection` and wait for its response
+    async fn request(
+        &self,
+        connection: Option<&mut Connection>,
+        method: &str,
+        params: Value,
+    ) -> Result<Value, CallError> {
+        wait(self.send(connection, method, params).await?).await
+    }
+
+    /// Send a request, returning where its response will arrive
+    async fn send(
+        &self,
+        connection: Option<&mut Connection>,
+        method: &str,
+        params: Value,
+    ) -> Result<PendingResponse, CallError> {
+        let connection = connection.ok_or_else(|| "Not connected to daemon".to_string())?;
         let id = self.request_id.fetch_add(1, Ordering::SeqCst);
 
         let request = Request {
@@ -148,61 +423,176 @@ impl IpcClient {
         };
 
         let request_json = serde_json::to_string(&request).map_err(|e| e.to_string())? + "\n";
+        // The daemon rejects longer lines without an id to match them to
+        if request_json.len() > MAX_FRAME_BYTES {
+            return Err(
+                format!("Request exceeds max frame size ({} bytes)", MAX_FRAME_BYTES).into(),
+            );
+        }
 
-        // Send request
-        {
-            let mut writer_guard = self.writer.lock().await;
-            let writer = writer_guard
-                .as_mut()
-                .ok_or_else(|| "Not connected to daemon".to_string())?;
-
-            writer
-                .write_all(request_json.as_bytes())
-                .await
-                .map_err(|e| format!("Failed to send request: {}", e))?;
+        let (tx, rx) = oneshot::channel();
+        connection.pending.lock().unwrap().insert(id, tx);
+        if let Err(e) = connection.writer.write_all(request_json.as_bytes()).await {
+            connection.pending.lock().unwrap().remove(&id);
+            // The reader notices too, but callers shouldn't reuse a broken writer
+            self.connected
+                .send_if_modified(|current| clear_if(current, connection.id));
+            return Err(format!("Failed to send request: {}", e).into());
         }
+        Ok(PendingResponse {
+            id,
+            rx,
+            pending: connection.pending.clone(),
+        })
+    }
+}
 
-        // Read response - skip any event messages until we get our response
-        loop {
-            let mut line = String::new();
-            {
-                let mut reader_guard = self.reader.lock().await;
-                let reader = reader_guard
-                    .as_mut()
-                    .ok_or_else(|| "Not connected to daemon".to_string())?;
+impl Default for IpcClient {
+    fn default() -> Self {
+        Self::new(None)
+    }
+}
 
-                let bytes_read = reader
-                    .read_line(&mut line)
-                    .await
-                    .map_err(|e| format!("Failed to read response: {}", e))?;
+async fn connect_socket() -> Result<(Reader, Writer), String> {
+    let socket_path = get_socket_path().map_err(|e| e.to_string())?;
 
-                if bytes_read == 0 {
-                    return Err("Connection closed by daemon".to_string());
-                }
-            }
+    if !socket_path.exists() {
+        return Err("Daemon socket not found. Is the daemon running?".to_string());
+    }
 
-            // Try to parse as Response (has "id" field)
-            if let Ok(response) = serde_json::from_str::<Response>(&line) {
-                if response.id != id {
-                    // Not our response, could be a late response from a previous request
-                    continue;
-                }
+    let name = socket_path
+        .to_fs_name::<GenericFilePath>()
+        .map_err(|e| e.to_string())?;
 
-                if let Some(error) = response.error {
-                    return Err(error.message);
-                }
+    let stream = Stream::connect(name)
+        .await
+        .map_err(|e| format!("Failed to connect to daemon: {}", e))?;
 
-                return response.result.ok_or_else(|| "Empty response".to_string());
-            }
+    let (recv_half, send_half) = stream.split();
+    Ok((Box::new(recv_half), Box::new(send_half)))
+}
+
+async fn connect_tcp(addr: &str) -> Result<(Reader, Writer), String> {
+    let stream = TcpStream::connect(addr).await.map_err(|e| {
+        format!(
+            "Failed to connect to the remote daemon at {}: {} (is the tunnel up?)",
+            addr, e
+        )
+    })?;
+    // Requests are single lines; don't hold them back for more
+    let _ = stream.set_nodelay(true);
+    let (recv_half, send_half) = stream.into_split();
+    Ok((Box::new(recv_half), Box::new(send_half)))
+}
 
-            // If it doesn't parse as a Response, it might be an Event - skip it
-            // In a real app, you'd want to queue these events for processing
+/// A response the read loop will route here by request id, in whatever order
+/// responses arrive; dropping it (timeout or a cancelled command) removes the
+/// entry so a late response is discarded rather than left in the map
+struct PendingResponse {
+    id: u64,
+    rx: oneshot::Receiver<Result<Response, String>>,
+    pending: Pending,
+}
+
+impl Drop for PendingResponse {
+    fn drop(&mut self) {
+        self.pending.lock().unwrap().remove(&self.id);
+    }
+}
+
+/// Wait for a response sent with `IpcClient::send`
+async fn wait(mut response: PendingResponse) -> Result<Value, CallError> {
+    let response = match timeout(Duration::from_secs(REQUEST_TIMEOUT_SECS), &mut response.rx).await
+    {
+        Ok(Ok(response)) => response?,
+        Ok(Err(_)) => return Err("Connection closed by daemon".to_string().into()),
+        Err(_) => {
+            return Err(format!("Request timed out after {}s", REQUEST_TIMEOUT_SECS).into());
         }
+    };
+    if let Some(error) = response.error {
+        return Err(error.into());
     }
+    response
+        .result
+        .ok_or_else(|| "Empty response".to_string().into())
 }
 
-impl Default for IpcClient {
-    fn default() -> Self {
-        Self::new()
+/// Mark connection `id` as gone, unless a newer one replaced it already
+fn clear_if(current: &mut Option<u64>, id: u64) -> bool {
+    if *current == Some(id) {
+        *current = None;
+        true
+    } else {
+        false
+    }
+}
+
+/// Read everything the daemon sends on one connection: responses go to the
+/// call waiting for them, events to the broadcast
+async fn read_loop(
+    id: u64,
+    mut reader: BufReader<Reader>,
+    pending: Pending,
+    events: broadcast::Sender<Event>,
+    journal: Arc<std::sync::Mutex<JournalPosition>>,
+    connected: watch::Sender<Option<u64>>,
+) {
+    let mut chunks = ChunkAssembler::new();
+    let mut line = String::new();
+    loop {
+        line.clear();
+        match reader.read_line(&mut line).await {
+            Ok(0) => break,
+            Ok(_) => dispatch(&line, &pending, &events, &journal, &mut chunks),
+            Err(e) => {
+                warn!("Failed to read from daemon: {}", e);
+                break;
+            }
+        }
+    }
+
+    connected.send_if_modified(|current| clear_if(current, id));
+    // Dropping the senders fails the calls still waiting
+    pending.lock().unwrap().clear();
+}
+
+fn dispatch(
+    line: &str,
+    pending: &Pending,
+    events: &broadcast::Sender<Event>,
+    journal: &std::sync::Mutex<JournalPosition>,
+    chunks: &mut ChunkAssembler,
+) {
+    // Large responses arrive in chunks; checked first since a chunk
+    // line would also parse as a bare Response
+    if let Ok(chunk) = serde_json::from_str::<ResponseChunk>(line) {
+        let id = chunk.id;
+        let response = match chunks.push(chunk) {
+            Some(response) => response.map_err(|e| format!("Bad chunked response: {}", e)),
+            None => return,
+        };
+        if let Some(tx) = pending.lock().unwrap().remove(&id) {
+            let _ = tx.send(response);
+        }
+        return;
+    }
+
+    if let Ok(response) = serde_json::from_str::<Response>(line) {
+        // No one waiting: a call that timed out, or an error the daemon
+        // couldn't match to a request (id 0)
+        if let Some(tx) = pending.lock().unwrap().remove(&response.id) {
+            let _ = tx.send(Ok(response));
+        }
+        return;
+    }
+
+    // Events newer than this build don't parse and are dropped
+    if let Ok(sequenced) = serde_json::from_str::<SequencedEvent>(line) {
+        let mut position = journal.lock().unwrap();
+        match position.held.as_mut() {
+            Some(held) => held.push(sequenced),
+            None => position.pass_on(sequenced, events),
+        }
     }
 }
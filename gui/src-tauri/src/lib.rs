@@ -4,12 +4,27 @@ mod event_listener;
 mod ipc_client;
 
 use ipc_client::IpcClient;
+use shared::EventSubscription;
+use std::collections::HashMap;
+use std::sync::atomic::AtomicBool;
+use std::sync::Mutex;
 use tracing::{error, info};
 use tracing_subscriber::EnvFilter;
 
 /// Global state for the daemon connection
 pub struct DaemonState {
     pub client: IpcClient,
+    /// The user chose to keep using a daemon this app didn't launch
+    pub attached: AtomicBool,
+    /// The daemon is started outside the GUI, which never launches or restarts it
+    pub external: bool,
+    /// Set by start_debug_tap: whether the daemon mirrors this app's traffic
+    /// (Some, with include_output) until stop_debug_tap
+    pub debug_tap: tokio::sync::watch::Sender<Option<bool>>,
+    /// Events the frontend wants; the event listener sends changes to the daemon
+    pub event_subscription: tokio::sync::watch::Sender<EventSubscription>,
+    /// Pop-out windows (by label) and the session each one shows
+    pub session_windows: Mutex<HashMap<String, uuid::Uuid>>,
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
@@ -18,22 +33,38 @@ pub fn run() {
     let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
     tracing_subscriber::fmt().with_env_filter(filter).init();
 
+    // Pick up the profile the GUI used last
+    let profile = daemon_launcher::saved_profile();
+    if let Err(e) = shared::set_profile(profile.as_deref()) {
+        error!("Failed to use profile {:?}: {}", profile, e);
+    } else if let Some(ref name) = profile {
+        info!("Using profile {}", name);
+    }
+
+    let external = daemon_launcher::external_mode();
+    let remote = daemon_launcher::remote_daemon();
+    if let Some(ref remote) = remote {
+        info!("Remote daemon mode: connecting through the tunnel at {}", remote.addr);
+    } else if external {
+        info!("External daemon mode: connecting to a daemon started elsewhere");
+    }
+
     tauri::Builder::default()
         .plugin(tauri_plugin_shell::init())
         .plugin(tauri_plugin_dialog::init())
+        .plugin(tauri_plugin_notification::init())
         .manage(DaemonState {
-            client: IpcClient::new(),
+            client: IpcClient::new(remote),
+            attached: AtomicBool::new(false),
+            external,
+            debug_tap: tokio::sync::watch::Sender::new(None),
+            event_subscription: tokio::sync::watch::Sender::new(EventSubscription::default()),
+            session_windows: Mutex::new(HashMap::new()),
         })
         .setup(|app| {
-            let handle = app.handle().clone();
-
-            // Ensure daemon is running (installs LaunchAgent if needed)
-            tauri::async_runtime::spawn(async move {
-                info!("Ensuring daemon is running...");
-                if let Err(e) = daemon_launcher::ensure_daemon_running(&handle).await {
-                    error!("Failed to ensure daemon is running: {}", e);
-                }
-            });
+            // Ensure daemon is running (installs LaunchAgent if needed) and
+            // restart it whenever it stops answering
+            tauri::async_runtime::spawn(daemon_launcher::supervise(app.handle().clone()));
 
             // Start event listener in background
             event_listener::start_event_listener(app.handle().clone());
@@ -42,23 +73,104 @@ pub fn run() {
         .invoke_handler(tauri::generate_handler![
             commands::connect_daemon,
             commands::is_daemon_connected,
+            commands::check_daemon_identity,
+            commands::get_daemon_mode,
+            commands::resolve_daemon_conflict,
+            commands::list_profiles,
+            commands::switch_profile,
+            commands::open_session_window,
             commands::ping_daemon,
+            commands::get_ui_theme,
+            commands::get_diagnostics,
+            commands::list_orphans,
+            commands::get_network_audit,
+            commands::get_daemon_stats,
+            commands::get_daemon_metrics,
+            commands::tail_daemon_log,
+            commands::start_debug_tap,
+            commands::stop_debug_tap,
+            commands::set_event_subscription,
+            commands::run_bootstrap,
+            commands::keep_awake,
+            commands::get_notification_settings,
+            commands::set_notification_mute,
             commands::list_sessions,
+            commands::get_tree_snapshot,
             commands::create_session,
+            commands::get_transcript,
+            commands::get_session_history,
+            commands::get_input_history,
+            commands::get_session_stats,
+            commands::tail_session_log,
+            commands::get_session_screen,
+            commands::search_all,
+            commands::search_session_output,
+            commands::export_session_output,
+            commands::replay_session,
+            commands::cancel_replay,
+            commands::start_recording,
+            commands::stop_recording,
+            commands::list_recordings,
+            commands::export_recording,
+            commands::list_archived_sessions,
+            commands::get_turn_stats,
+            commands::enqueue_prompt,
+            commands::list_queue,
+            commands::cancel_queued_prompt,
+            commands::create_worktree_session,
             commands::stop_session,
+            commands::interrupt_session,
+            commands::send_key,
+            commands::continue_session,
+            commands::detach_session,
+            commands::attach_session,
+            commands::mark_session_viewed,
+            commands::view_session,
+            commands::list_clients,
             commands::delete_session,
             commands::fork_session,
             commands::restart_session,
+            commands::enqueue_session_start,
             commands::send_input,
+            commands::approve_session,
+            commands::deny_session,
             commands::resize_session,
             commands::update_session,
+            commands::add_session_tag,
+            commands::remove_session_tag,
+            commands::set_watch,
+            commands::set_completion_watch,
+            commands::set_approval_policy,
+            commands::set_dependency,
+            commands::create_schedule,
+            commands::list_schedules,
+            commands::delete_schedule,
+            commands::list_snippets,
+            commands::create_snippet,
+            commands::update_snippet,
+            commands::delete_snippet,
+            commands::send_snippet,
+            commands::scan_projects,
+            commands::claude_capabilities,
+            commands::claude_info,
+            commands::list_mcp_servers,
+            commands::add_mcp_server,
+            commands::remove_mcp_server,
+            commands::set_mcp_server_enabled,
+            commands::list_session_tasks,
+            commands::run_session_task,
             commands::reorder_session,
+            commands::move_sessions,
             commands::list_groups,
+            commands::sync_state,
             commands::create_group,
             commands::delete_group,
             commands::update_group,
             commands::reorder_group,
+            commands::export_state,
+            commands::import_state,
             commands::shutdown_daemon,
+            commands::restart_daemon,
             commands::uninstall_daemon_service,
         ])
         .run(tauri::generate_context!())
@@ -1,4 +1,4 @@
-import { Show, onMount, createSignal, For } from "solid-js";
+import { Show, onMount, createSignal, createEffect, For } from "solid-js";
 import { Sidebar } from "./components/Sidebar";
 import { Terminal } from "./components/Terminal";
 import { StatusBar } from "./components/StatusBar";
@@ -6,6 +6,8 @@ import { NewSessionDialog } from "./components/NewSessionDialog";
 import { NewGroupDialog } from "./components/NewGroupDialog";
 import { SettingsModal } from "./components/SettingsModal";
 import { ToastContainer } from "./components/Toast";
+import { ApprovalBar } from "./components/ApprovalBar";
+import { PresenceBar } from "./components/PresenceBar";
 import { appStore } from "./stores/appStore";
 import { terminalStore } from "./stores/terminalStore";
 import {
@@ -40,6 +42,20 @@ function App() {
     },
   });
 
+  // Let other clients see which session this app shows
+  createEffect(() => {
+    appStore.viewSession(appStore.selectedSessionId());
+  });
+
+  // The shown session's output is read, including what arrives while it is shown
+  createEffect(() => {
+    const id = appStore.selectedSessionId();
+    const session = appStore.sessions().find((s) => s.id === id);
+    if (session && session.unread_bytes > 0) {
+      appStore.markSessionViewed(session.id);
+    }
+  });
+
   // Auto-connect on mount
   onMount(() => {
     appStore.connectToDaemon();
@@ -47,6 +63,40 @@ function App() {
 
   return (
     <div class="h-screen bg-gray-900 text-white flex flex-col">
+      {/* Another daemon owns the socket - let the user take over or keep it */}
+      <Show when={appStore.daemonConflict()}>
+        {(conflict) => (
+          <div class="px-3 py-1.5 bg-amber-900/60 border-b border-amber-700 flex items-center justify-between gap-2 text-xs text-amber-200">
+            <span>
+              {(() => {
+                const c = conflict();
+                const hello = c.state === "conflict" ? c.hello : null;
+                return hello
+                  ? `Connected to a daemon this app didn't start (v${hello.version}, pid ${hello.pid}).`
+                  : "Connected to an older daemon this app didn't start.";
+              })()}
+            </span>
+            <div class="flex items-center gap-1.5 flex-shrink-0">
+              <button
+                class="px-2 py-0.5 bg-amber-600 hover:bg-amber-700 rounded text-white"
+                onClick={() => {
+                  if (confirm("Shut down the other daemon and its sessions, and start this app's daemon?")) {
+                    appStore.resolveDaemonConflict("takeover");
+                  }
+                }}
+              >
+                Take over
+              </button>
+              <button
+                class="px-2 py-0.5 bg-gray-700 hover:bg-gray-600 rounded text-white"
+                onClick={() => appStore.resolveDaemonConflict("attach")}
+              >
+                Keep using it
+              </button>
+            </div>
+          </div>
+        )}
+      </Show>
       <div class="flex-1 flex overflow-hidden">
       {/* Sidebar */}
       <Sidebar />
@@ -61,9 +111,36 @@ function App() {
                 <div class="flex items-center gap-2">
                   <h2 class="text-sm font-semibold truncate">{session().name}</h2>
                   <span class="text-xs text-gray-500 truncate hidden sm:block">{session().working_dir}</span>
+                  <Show when={session().detached}>
+                    <button
+                      class="px-1.5 py-0.5 text-xs bg-gray-700 hover:bg-gray-600 rounded text-gray-300"
+                      onClick={() => appStore.attachSession(session().id)}
+                      title="Output is held by the daemon; attach to see it"
+                    >
+                      Detached · Attach
+                    </button>
+                  </Show>
                 </div>
               </div>
               <div class="flex items-center gap-1.5 flex-shrink-0">
+                <Show when={session().status === "running"}>
+                  <button
+                    class="px-2 py-0.5 text-xs bg-gray-700 hover:bg-gray-600 rounded"
+                    onClick={() => appStore.interruptSession(session().id)}
+                    title="Stop generating (Esc), keeping the session"
+                  >
+                    Interrupt
+                  </button>
+                </Show>
+                <Show when={session().status === "waiting" || session().status === "idle"}>
+                  <button
+                    class="px-2 py-0.5 text-xs bg-gray-700 hover:bg-gray-600 rounded"
+                    onClick={() => appStore.continueSession(session().id)}
+                    title='Send "continue" to pick up where it stopped'
+                  >
+                    Continue
+                  </button>
+                </Show>
                 <Show when={session().status === "running"}>
                   <button
                     class="px-2 py-0.5 text-xs bg-yellow-600 hover:bg-yellow-700 rounded"
@@ -89,6 +166,13 @@ function App() {
                     Restart
                   </button>
                 </Show>
+                <button
+                  class="px-2 py-0.5 text-xs bg-gray-700 hover:bg-gray-600 rounded"
+                  onClick={() => appStore.openSessionWindow(session().id)}
+                  title="Open in a new window"
+                >
+                  Pop out
+                </button>
                 <Show when={session().claude_session_id}>
                   <button
                     class="px-2 py-0.5 text-xs bg-indigo-600 hover:bg-indigo-700 rounded"
@@ -126,6 +210,15 @@ function App() {
           )}
         </Show>
 
+        <Show when={appStore.selectedSessionId()}>
+          {(id) => (
+            <>
+              <PresenceBar sessionId={id()} />
+              <ApprovalBar sessionId={id()} />
+            </>
+          )}
+        </Show>
+
         {/* Terminal container - render ALL session terminals, show/hide based on selection */}
         {/* This preserves each terminal's buffer when switching between sessions */}
         <div class="flex-1 overflow-hidden relative">
@@ -0,0 +1,46 @@
+// Approval bar - Approve/Deny buttons for a permission dialog Claude is showing
+// in the session's terminal (session:approval_requested)
+
+import { Show } from "solid-js";
+import { appStore } from "../stores/appStore";
+
+interface ApprovalBarProps {
+  sessionId: string;
+}
+
+export function ApprovalBar(props: ApprovalBarProps) {
+  const approval = () => appStore.approvals[props.sessionId];
+
+  return (
+    <Show when={approval()}>
+      {(a) => (
+        <div class="px-3 py-2 border-b border-amber-700 bg-amber-900/30 flex items-start gap-3">
+          <div class="flex-1 min-w-0">
+            <div class="text-sm text-amber-300">
+              <span class="font-semibold">{a().tool}</span> {a().question}
+            </div>
+            <Show when={a().summary}>
+              <pre class="text-xs text-gray-300 whitespace-pre-wrap truncate max-h-16 overflow-hidden">
+                {a().summary}
+              </pre>
+            </Show>
+          </div>
+          <div class="flex items-center gap-1.5 flex-shrink-0">
+            <button
+              class="px-2 py-0.5 text-xs bg-green-600 hover:bg-green-700 rounded"
+              onClick={() => appStore.answerApproval(a(), true)}
+            >
+              Approve
+            </button>
+            <button
+              class="px-2 py-0.5 text-xs bg-red-600 hover:bg-red-700 rounded"
+              onClick={() => appStore.answerApproval(a(), false)}
+            >
+              Deny
+            </button>
+          </div>
+        </div>
+      )}
+    </Show>
+  );
+}
@@ -0,0 +1,62 @@
+// Auto-approve policy fields for the edit session and edit group dialogs,
+// one entry per line
+
+import { For } from "solid-js";
+import type { ApprovalPolicy } from "../types";
+
+export type PolicyText = Record<keyof ApprovalPolicy, string>;
+
+export function policyToText(policy?: ApprovalPolicy): PolicyText {
+  return {
+    tools: policy?.tools.join("\n") ?? "",
+    paths: policy?.paths.join("\n") ?? "",
+    commands: policy?.commands.join("\n") ?? "",
+  };
+}
+
+export function textToPolicy(text: PolicyText): ApprovalPolicy {
+  const lines = (value: string) =>
+    value.split("\n").map((line) => line.trim()).filter((line) => line.length > 0);
+  return {
+    tools: lines(text.tools),
+    paths: lines(text.paths),
+    commands: lines(text.commands),
+  };
+}
+
+const FIELDS: { key: keyof ApprovalPolicy; label: string; placeholder: string }[] = [
+  { key: "tools", label: "Tools", placeholder: "Read\nGrep" },
+  { key: "paths", label: "File globs", placeholder: "src/**\ndocs/*.md" },
+  { key: "commands", label: "Bash command regexes", placeholder: "^cargo (build|test)\\b" },
+];
+
+interface ApprovalPolicyFieldsProps {
+  value: PolicyText;
+  onChange: (value: PolicyText) => void;
+}
+
+export function ApprovalPolicyFields(props: ApprovalPolicyFieldsProps) {
+  return (
+    <div>
+      <label class="block text-sm font-medium text-gray-300 mb-1">
+        Auto-approve
+      </label>
+      <div class="space-y-2">
+        <For each={FIELDS}>
+          {(field) => (
+            <div>
+              <span class="block text-xs text-gray-400 mb-1">{field.label}</span>
+              <textarea
+                rows={2}
+                value={props.value[field.key]}
+                onInput={(e) => props.onChange({ ...props.value, [field.key]: e.currentTarget.value })}
+                class="w-full px-3 py-2 bg-gray-700 border border-gray-600 rounded-md text-white text-sm font-mono placeholder-gray-500 focus:outline-none focus:ring-2 focus:ring-indigo-500 focus:border-transparent"
+                placeholder={field.placeholder}
+              />
+            </div>
+          )}
+        </For>
+      </div>
+    </div>
+  );
+}
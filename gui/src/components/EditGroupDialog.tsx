@@ -1,7 +1,9 @@
-// Dialog for editing a group (name and parent)
+// Dialog for editing a group (name, parent, auto-approve policy and the
+// notification when all its sessions finish)
 
 import { createSignal, Show, For, createEffect } from "solid-js";
 import { appStore } from "../stores/appStore";
+import { ApprovalPolicyFields, policyToText, textToPolicy } from "./ApprovalPolicyFields";
 import type { Group } from "../types";
 
 interface EditGroupDialogProps {
@@ -13,6 +15,9 @@ interface EditGroupDialogProps {
 export function EditGroupDialog(props: EditGroupDialogProps) {
   const [name, setName] = createSignal("");
   const [parentId, setParentId] = createSignal<string | null>(null);
+  const [policy, setPolicy] = createSignal(policyToText());
+  const [notifyOnCompletion, setNotifyOnCompletion] = createSignal(false);
+  const [webhookUrl, setWebhookUrl] = createSignal("");
   const [isUpdating, setIsUpdating] = createSignal(false);
   const [error, setError] = createSignal<string | null>(null);
 
@@ -20,7 +25,10 @@ export function EditGroupDialog(props: EditGroupDialogProps) {
   createEffect(() => {
     if (props.group) {
       setName(props.group.name);
+      setPolicy(policyToText(props.group.approval_policy));
       setParentId(props.group.parent_id || null);
+      setNotifyOnCompletion(!!props.group.completion_watch);
+      setWebhookUrl(props.group.completion_watch?.webhook_url ?? "");
     }
   });
 
@@ -61,6 +69,12 @@ export function EditGroupDialog(props: EditGroupDialogProps) {
         name().trim(),
         parentId()
       );
+      await appStore.setApprovalPolicy({ groupId: props.group.id }, textToPolicy(policy()));
+      const watch = props.group.completion_watch;
+      const url = webhookUrl().trim();
+      if (notifyOnCompletion() !== !!watch || (notifyOnCompletion() && url !== (watch?.webhook_url ?? ""))) {
+        await appStore.setCompletionWatch(props.group.id, notifyOnCompletion(), url);
+      }
       props.onClose();
     } catch (err) {
       setError(String(err));
@@ -118,6 +132,30 @@ export function EditGroupDialog(props: EditGroupDialogProps) {
                 </select>
               </div>
 
+              <ApprovalPolicyFields value={policy()} onChange={setPolicy} />
+
+              {/* Completion watch */}
+              <div>
+                <label class="flex items-center gap-2 text-sm text-gray-300">
+                  <input
+                    type="checkbox"
+                    checked={notifyOnCompletion()}
+                    onChange={(e) => setNotifyOnCompletion(e.currentTarget.checked)}
+                    class="rounded border-gray-600 bg-gray-700 text-indigo-500 focus:ring-indigo-500"
+                  />
+                  Notify when all sessions have finished
+                </label>
+                <Show when={notifyOnCompletion()}>
+                  <input
+                    type="url"
+                    value={webhookUrl()}
+                    onInput={(e) => setWebhookUrl(e.currentTarget.value)}
+                    class="mt-2 w-full px-3 py-2 bg-gray-700 border border-gray-600 rounded-md text-white text-sm placeholder-gray-500 focus:outline-none focus:ring-2 focus:ring-indigo-500 focus:border-transparent"
+                    placeholder="Webhook URL to POST the summary to (optional)"
+                  />
+                </Show>
+              </div>
+
               {/* Error message */}
               <Show when={error()}>
                 <p class="text-sm text-red-400">{error()}</p>
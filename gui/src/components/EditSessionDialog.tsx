@@ -1,8 +1,10 @@
-// Dialog for editing a session (name and group)
+// Dialog for editing a session (name, group, auto-approve policy, the
+// session it starts after and the MCP servers of its directory)
 
 import { createSignal, Show, For, createEffect } from "solid-js";
 import { appStore } from "../stores/appStore";
-import type { Session } from "../types";
+import { ApprovalPolicyFields, policyToText, textToPolicy } from "./ApprovalPolicyFields";
+import type { McpServer, Session } from "../types";
 
 interface EditSessionDialogProps {
   isOpen: boolean;
@@ -13,14 +15,66 @@ interface EditSessionDialogProps {
 export function EditSessionDialog(props: EditSessionDialogProps) {
   const [name, setName] = createSignal("");
   const [groupId, setGroupId] = createSignal<string | null>(null);
+  const [policy, setPolicy] = createSignal(policyToText());
+  const [parentId, setParentId] = createSignal<string | null>(null);
+  const [dependencyPrompt, setDependencyPrompt] = createSignal("");
   const [isUpdating, setIsUpdating] = createSignal(false);
   const [error, setError] = createSignal<string | null>(null);
+  const [mcpServers, setMcpServers] = createSignal<McpServer[]>([]);
+  const [mcpName, setMcpName] = createSignal("");
+  const [mcpCommand, setMcpCommand] = createSignal("");
+
+  const loadMcpServers = async (dir: string) => {
+    try {
+      setMcpServers(await appStore.listMcpServers(dir));
+    } catch (err) {
+      setMcpServers([]);
+      setError(String(err));
+    }
+  };
+
+  // MCP changes are written straight away; they apply when the session next starts
+  const updateMcp = async (change: (dir: string) => Promise<void>) => {
+    const dir = props.session?.working_dir;
+    if (!dir) return;
+    setError(null);
+    try {
+      await change(dir);
+    } catch (err) {
+      setError(String(err));
+    }
+    await loadMcpServers(dir);
+  };
+
+  const handleAddMcp = () => {
+    const name = mcpName().trim();
+    const command = mcpCommand().trim();
+    if (!name || !command) return;
+    // A URL is a remote server, anything else a command line to run
+    const config = /^https?:\/\//.test(command)
+      ? { type: "http", url: command }
+      : { command: command.split(/\s+/)[0], args: command.split(/\s+/).slice(1) };
+    updateMcp(async (dir) => {
+      await appStore.addMcpServer(dir, name, config);
+      setMcpName("");
+      setMcpCommand("");
+    });
+  };
 
   // Update form when session changes
+  createEffect(() => {
+    if (props.isOpen && props.session) {
+      loadMcpServers(props.session.working_dir);
+    }
+  });
+
   createEffect(() => {
     if (props.session) {
       setName(props.session.name);
+      setPolicy(policyToText(props.session.approval_policy));
       setGroupId(props.session.group_id || null);
+      setParentId(props.session.dependency?.parent_id ?? null);
+      setDependencyPrompt(props.session.dependency?.prompt ?? "");
     }
   });
 
@@ -41,6 +95,12 @@ export function EditSessionDialog(props: EditSessionDialogProps) {
         name().trim(),
         groupId()
       );
+      await appStore.setApprovalPolicy({ sessionId: props.session.id }, textToPolicy(policy()));
+      const prompt = dependencyPrompt().trim();
+      const dependency = props.session.dependency;
+      if (parentId() !== (dependency?.parent_id ?? null) || prompt !== (dependency?.prompt ?? "")) {
+        await appStore.setDependency(props.session.id, parentId(), parentId() && prompt ? prompt : null);
+      }
       props.onClose();
     } catch (err) {
       setError(String(err));
@@ -98,6 +158,98 @@ export function EditSessionDialog(props: EditSessionDialogProps) {
                 </select>
               </div>
 
+              <ApprovalPolicyFields value={policy()} onChange={setPolicy} />
+
+              {/* Dependency */}
+              <div>
+                <label class="block text-sm font-medium text-gray-300 mb-1">
+                  Start after
+                </label>
+                <select
+                  value={parentId() || ""}
+                  onChange={(e) => setParentId(e.currentTarget.value || null)}
+                  class="w-full px-3 py-2 bg-gray-700 border border-gray-600 rounded-md text-white focus:outline-none focus:ring-2 focus:ring-indigo-500 focus:border-transparent"
+                >
+                  <option value="">Nothing</option>
+                  <For each={appStore.sessions().filter((s) => s.id !== props.session?.id)}>
+                    {(session) => (
+                      <option value={session.id}>{session.name}</option>
+                    )}
+                  </For>
+                </select>
+                <Show when={parentId()}>
+                  <textarea
+                    rows={2}
+                    value={dependencyPrompt()}
+                    onInput={(e) => setDependencyPrompt(e.currentTarget.value)}
+                    class="mt-2 w-full px-3 py-2 bg-gray-700 border border-gray-600 rounded-md text-white text-sm placeholder-gray-500 focus:outline-none focus:ring-2 focus:ring-indigo-500 focus:border-transparent"
+                    placeholder="Prompt to send once it starts (optional)"
+                  />
+                </Show>
+              </div>
+
+              {/* MCP servers */}
+              <div>
+                <label class="block text-sm font-medium text-gray-300 mb-1">
+                  MCP servers
+                </label>
+                <div class="space-y-1">
+                  <For each={mcpServers()}>
+                    {(server) => (
+                      <div class="flex items-center gap-2 text-sm">
+                        <input
+                          type="checkbox"
+                          checked={server.enabled}
+                          onChange={(e) => {
+                            const enabled = e.currentTarget.checked;
+                            updateMcp((dir) => appStore.setMcpServerEnabled(dir, server.name, enabled));
+                          }}
+                          class="rounded border-gray-600 bg-gray-700 text-indigo-500 focus:ring-indigo-500"
+                        />
+                        <span class="text-gray-200">{server.name}</span>
+                        <span class="flex-1 truncate text-xs text-gray-500">
+                          {String(server.config.url ?? server.config.command ?? "")}
+                        </span>
+                        <button
+                          type="button"
+                          onClick={() => updateMcp((dir) => appStore.removeMcpServer(dir, server.name))}
+                          class="text-xs text-gray-400 hover:text-red-400 transition-colors"
+                        >
+                          Remove
+                        </button>
+                      </div>
+                    )}
+                  </For>
+                </div>
+                <div class="mt-2 flex gap-2">
+                  <input
+                    type="text"
+                    value={mcpName()}
+                    onInput={(e) => setMcpName(e.currentTarget.value)}
+                    class="w-28 px-2 py-1 bg-gray-700 border border-gray-600 rounded-md text-white text-sm placeholder-gray-500 focus:outline-none focus:ring-2 focus:ring-indigo-500 focus:border-transparent"
+                    placeholder="Name"
+                  />
+                  <input
+                    type="text"
+                    value={mcpCommand()}
+                    onInput={(e) => setMcpCommand(e.currentTarget.value)}
+                    class="flex-1 min-w-0 px-2 py-1 bg-gray-700 border border-gray-600 rounded-md text-white text-sm placeholder-gray-500 focus:outline-none focus:ring-2 focus:ring-indigo-500 focus:border-transparent"
+                    placeholder="Command or URL"
+                  />
+                  <button
+                    type="button"
+                    onClick={handleAddMcp}
+                    disabled={!mcpName().trim() || !mcpCommand().trim()}
+                    class="px-3 py-1 text-sm bg-gray-700 hover:bg-gray-600 disabled:opacity-50 rounded-md text-white transition-colors"
+                  >
+                    Add
+                  </button>
+                </div>
+                <p class="mt-1 text-xs text-gray-500">
+                  Saved in the project's .mcp.json; takes effect when the session restarts
+                </p>
+              </div>
+
               {/* Error message */}
               <Show when={error()}>
                 <p class="text-sm text-red-400">{error()}</p>
@@ -1,8 +1,18 @@
 // New Session Dialog Component
+// With a cron expression it creates a schedule instead, which creates and
+// starts the session each time it comes due
+// Recently used project directories are offered as suggestions, and model and
+// permission mode are only offered when the installed claude supports them
 
-import { createSignal, Show, For } from "solid-js";
+import { createSignal, createEffect, untrack, Show, For } from "solid-js";
 import { open } from "@tauri-apps/plugin-dialog";
 import { appStore } from "../stores/appStore";
+import type { ClaudeCapabilities, ClaudeFlags, ProjectCandidate } from "../types";
+
+// Suggestions shown as buttons under an empty directory field
+const RECENT_PROJECTS = 5;
+
+const baseName = (path: string) => path.split(/[\\/]/).filter(Boolean).pop() ?? path;
 
 interface NewSessionDialogProps {
   isOpen: boolean;
@@ -14,8 +24,54 @@ export function NewSessionDialog(props: NewSessionDialogProps) {
   const [name, setName] = createSignal("");
   const [directory, setDirectory] = createSignal("");
   const [selectedGroupId, setSelectedGroupId] = createSignal<string | undefined>(props.groupId);
+  const [cron, setCron] = createSignal("");
+  const [prompt, setPrompt] = createSignal("");
+  const [createDir, setCreateDir] = createSignal(false);
   const [isCreating, setIsCreating] = createSignal(false);
   const [error, setError] = createSignal<string | null>(null);
+  const [projects, setProjects] = createSignal<ProjectCandidate[]>([]);
+  const [capabilities, setCapabilities] = createSignal<ClaudeCapabilities | null>(null);
+  const [showAdvanced, setShowAdvanced] = createSignal(false);
+  const [model, setModel] = createSignal("");
+  const [permissionMode, setPermissionMode] = createSignal("");
+  const [extraArgs, setExtraArgs] = createSignal("");
+  const [claudeBinary, setClaudeBinary] = createSignal("");
+  // Names from [claude] binaries; kept when capabilities are fetched for one of them
+  const [binaries, setBinaries] = createSignal<string[]>([]);
+
+  const loadCapabilities = (binary?: string) =>
+    appStore
+      .getClaudeCapabilities(binary)
+      .then((result) => {
+        setCapabilities(result);
+        setBinaries(result.binaries);
+      })
+      .catch(() => setCapabilities(null));
+
+  createEffect(() => {
+    if (props.isOpen) {
+      appStore
+        .scanProjects()
+        .then(setProjects)
+        .catch(() => setProjects([]));
+      loadCapabilities(untrack(claudeBinary) || undefined);
+    }
+  });
+
+  const supports = (flag: string) => capabilities()?.flags.includes(flag) ?? false;
+
+  const flags = (): ClaudeFlags => ({
+    model: model().trim() || undefined,
+    permission_mode: permissionMode() || undefined,
+    extra_args: extraArgs().trim() ? extraArgs().trim().split(/\s+/) : undefined,
+  });
+
+  const pickProject = (path: string) => {
+    setDirectory(path);
+    if (!name().trim()) {
+      setName(baseName(path));
+    }
+  };
 
   const handleBrowse = async () => {
     try {
@@ -46,11 +102,39 @@ export function NewSessionDialog(props: NewSessionDialogProps) {
     setError(null);
 
     try {
-      await appStore.createSession(name().trim(), directory().trim(), selectedGroupId());
+      if (cron().trim()) {
+        await appStore.createSchedule(
+          name().trim(),
+          cron().trim(),
+          directory().trim(),
+          selectedGroupId(),
+          prompt().trim(),
+          createDir(),
+          flags(),
+          claudeBinary() || undefined
+        );
+      } else {
+        await appStore.createSession(
+          name().trim(),
+          directory().trim(),
+          selectedGroupId(),
+          createDir(),
+          flags(),
+          claudeBinary() || undefined
+        );
+      }
       // Reset and close
       setName("");
       setDirectory("");
       setSelectedGroupId(undefined);
+      setCron("");
+      setPrompt("");
+      setCreateDir(false);
+      setModel("");
+      setPermissionMode("");
+      setExtraArgs("");
+      setClaudeBinary("");
+      setShowAdvanced(false);
       props.onClose();
     } catch (e) {
       setError(String(e));
@@ -107,6 +191,7 @@ export function NewSessionDialog(props: NewSessionDialogProps) {
                   value={directory()}
                   onInput={(e) => setDirectory(e.currentTarget.value)}
                   placeholder="/path/to/project"
+                  list="project-suggestions"
                   class="flex-1 px-3 py-2 bg-gray-700 border border-gray-600 rounded-md text-white placeholder-gray-400 focus:outline-none focus:ring-2 focus:ring-indigo-500 focus:border-transparent"
                 />
                 <button
@@ -117,6 +202,34 @@ export function NewSessionDialog(props: NewSessionDialogProps) {
                   Browse
                 </button>
               </div>
+              <label class="mt-2 flex items-center gap-2 text-sm text-gray-300">
+                <input
+                  type="checkbox"
+                  checked={createDir()}
+                  onChange={(e) => setCreateDir(e.currentTarget.checked)}
+                  class="rounded bg-gray-700 border-gray-600"
+                />
+                Create the directory if it doesn't exist
+              </label>
+              <datalist id="project-suggestions">
+                <For each={projects()}>{(project) => <option value={project.path} />}</For>
+              </datalist>
+              <Show when={!directory().trim() && projects().length > 0}>
+                <div class="mt-2 flex flex-wrap gap-1">
+                  <For each={projects().slice(0, RECENT_PROJECTS)}>
+                    {(project) => (
+                      <button
+                        type="button"
+                        onClick={() => pickProject(project.path)}
+                        title={`${project.path} (last used ${new Date(project.last_used).toLocaleString()})`}
+                        class="px-2 py-0.5 text-xs bg-gray-700 hover:bg-gray-600 rounded text-gray-300 truncate max-w-[12rem]"
+                      >
+                        {baseName(project.path)}
+                      </button>
+                    )}
+                  </For>
+                </div>
+              </Show>
             </div>
 
             {/* Group Selection */}
@@ -140,6 +253,93 @@ export function NewSessionDialog(props: NewSessionDialogProps) {
               </div>
             </Show>
 
+            {/* Schedule */}
+            <div>
+              <label class="block text-sm font-medium text-gray-300 mb-1">
+                Schedule (optional)
+              </label>
+              <input
+                type="text"
+                value={cron()}
+                onInput={(e) => setCron(e.currentTarget.value)}
+                placeholder="0 2 * * *"
+                class="w-full px-3 py-2 bg-gray-700 border border-gray-600 rounded-md text-white font-mono placeholder-gray-400 focus:outline-none focus:ring-2 focus:ring-indigo-500 focus:border-transparent"
+              />
+              <p class="mt-1 text-xs text-gray-400">
+                Cron expression; a new session starts each time it comes due
+              </p>
+              <Show when={cron().trim()}>
+                <textarea
+                  rows={2}
+                  value={prompt()}
+                  onInput={(e) => setPrompt(e.currentTarget.value)}
+                  class="mt-2 w-full px-3 py-2 bg-gray-700 border border-gray-600 rounded-md text-white text-sm placeholder-gray-400 focus:outline-none focus:ring-2 focus:ring-indigo-500 focus:border-transparent"
+                  placeholder="Prompt to send once it starts (optional)"
+                />
+              </Show>
+            </div>
+
+            {/* Claude flags */}
+            <div>
+              <button
+                type="button"
+                onClick={() => setShowAdvanced(!showAdvanced())}
+                class="text-sm text-gray-400 hover:text-white"
+              >
+                {showAdvanced() ? "▾" : "▸"} Claude options
+              </button>
+              <Show when={showAdvanced()}>
+                <div class="mt-2 space-y-2">
+                  <Show when={binaries().length > 0}>
+                    <select
+                      value={claudeBinary()}
+                      onChange={(e) => {
+                        setClaudeBinary(e.currentTarget.value);
+                        loadCapabilities(e.currentTarget.value || undefined);
+                      }}
+                      class="w-full px-3 py-2 bg-gray-700 border border-gray-600 rounded-md text-white text-sm focus:outline-none focus:ring-2 focus:ring-indigo-500 focus:border-transparent"
+                    >
+                      <option value="">Default claude</option>
+                      <For each={binaries()}>
+                        {(binary) => <option value={binary}>{binary}</option>}
+                      </For>
+                    </select>
+                  </Show>
+                  <Show when={supports("--model")}>
+                    <input
+                      type="text"
+                      value={model()}
+                      onInput={(e) => setModel(e.currentTarget.value)}
+                      placeholder="Model (e.g. opus, sonnet)"
+                      class="w-full px-3 py-2 bg-gray-700 border border-gray-600 rounded-md text-white text-sm placeholder-gray-400 focus:outline-none focus:ring-2 focus:ring-indigo-500 focus:border-transparent"
+                    />
+                  </Show>
+                  <Show when={supports("--permission-mode")}>
+                    <select
+                      value={permissionMode()}
+                      onChange={(e) => setPermissionMode(e.currentTarget.value)}
+                      class="w-full px-3 py-2 bg-gray-700 border border-gray-600 rounded-md text-white text-sm focus:outline-none focus:ring-2 focus:ring-indigo-500 focus:border-transparent"
+                    >
+                      <option value="">Default permission mode</option>
+                      <For each={capabilities()?.permission_modes ?? []}>
+                        {(mode) => <option value={mode}>{mode}</option>}
+                      </For>
+                    </select>
+                  </Show>
+                  <input
+                    type="text"
+                    value={extraArgs()}
+                    onInput={(e) => setExtraArgs(e.currentTarget.value)}
+                    placeholder="Extra arguments"
+                    class="w-full px-3 py-2 bg-gray-700 border border-gray-600 rounded-md text-white text-sm font-mono placeholder-gray-400 focus:outline-none focus:ring-2 focus:ring-indigo-500 focus:border-transparent"
+                  />
+                  <Show when={capabilities()?.version}>
+                    <p class="text-xs text-gray-400">claude {capabilities()?.version}</p>
+                  </Show>
+                </div>
+              </Show>
+            </div>
+
             {/* Error Message */}
             <Show when={error()}>
               <p class="text-sm text-red-400">{error()}</p>
@@ -162,7 +362,7 @@ export function NewSessionDialog(props: NewSessionDialogProps) {
               disabled={isCreating()}
               class="px-4 py-2 text-sm bg-indigo-600 hover:bg-indigo-700 rounded-md text-white transition-colors disabled:opacity-50 disabled:cursor-not-allowed"
             >
-              {isCreating() ? "Creating..." : "Create Session"}
+              {isCreating() ? "Creating..." : cron().trim() ? "Create Schedule" : "Create Session"}
             </button>
           </div>
         </div>
@@ -0,0 +1,42 @@
+// Presence bar - other clients (another window, the CLI, a web page) showing
+// the session's terminal, and which of them is typing in it (session:presence)
+
+import { Show } from "solid-js";
+import { appStore } from "../stores/appStore";
+import type { ClientInfo } from "../types";
+
+interface PresenceBarProps {
+  sessionId: string;
+}
+
+function label(client: ClientInfo) {
+  return `${client.name ?? client.kind} #${client.client_id}`;
+}
+
+export function PresenceBar(props: PresenceBarProps) {
+  const presence = () => appStore.presence[props.sessionId];
+  const others = () =>
+    (presence()?.viewers ?? []).filter((c) => c.client_id !== appStore.clientId());
+  const typist = () => {
+    const typing = presence()?.typing;
+    if (typing == null || typing === appStore.clientId()) {
+      return null;
+    }
+    // A client can type without showing the terminal (e.g. the CLI)
+    const client = presence()?.viewers.find((c) => c.client_id === typing);
+    return client ? label(client) : `client #${typing}`;
+  };
+
+  return (
+    <Show when={others().length > 0 || typist()}>
+      <div class="px-3 py-1 border-b border-gray-700 bg-gray-800 text-xs text-gray-400 flex items-center gap-3">
+        <Show when={others().length > 0}>
+          <span>Also viewing: {others().map(label).join(", ")}</span>
+        </Show>
+        <Show when={typist()}>
+          {(name) => <span class="text-amber-400">{name()} is typing…</span>}
+        </Show>
+      </div>
+    </Show>
+  );
+}
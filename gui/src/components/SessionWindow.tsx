@@ -0,0 +1,46 @@
+// Pop-out window showing one session's terminal (opened with open_session_window)
+
+import { Show, onMount } from "solid-js";
+import { Terminal } from "./Terminal";
+import { ToastContainer } from "./Toast";
+import { ApprovalBar } from "./ApprovalBar";
+import { appStore } from "../stores/appStore";
+
+interface SessionWindowProps {
+  sessionId: string;
+}
+
+export function SessionWindow(props: SessionWindowProps) {
+  const session = () => appStore.sessions().find((s) => s.id === props.sessionId);
+
+  onMount(() => {
+    appStore.connectToDaemon();
+  });
+
+  return (
+    <div class="h-screen bg-gray-900 text-white flex flex-col">
+      <div class="px-3 py-1.5 border-b border-gray-700 flex items-center gap-2">
+        <h2 class="text-sm font-semibold truncate">{session()?.name ?? "Session"}</h2>
+        <span class="text-xs text-gray-500 truncate">{session()?.status}</span>
+      </div>
+      <ApprovalBar sessionId={props.sessionId} />
+      <div class="flex-1 overflow-hidden relative">
+        <Show
+          when={session()}
+          fallback={
+            <div class="absolute inset-0 flex items-center justify-center text-gray-500">
+              {appStore.isConnected() ? "This session no longer exists" : "Connecting..."}
+            </div>
+          }
+        >
+          {(s) => (
+            <div class="absolute inset-0">
+              <Terminal sessionId={props.sessionId} sessionStatus={s().status} />
+            </div>
+          )}
+        </Show>
+      </div>
+      <ToastContainer />
+    </div>
+  );
+}
@@ -21,6 +21,7 @@ import { EditGroupDialog } from "./EditGroupDialog";
 import { SettingsModal } from "./SettingsModal";
 import { ContextMenu, ContextMenuItem, MenuIcons } from "./ContextMenu";
 import { StatusPill } from "./StatusPill";
+import { showToast } from "./Toast";
 import type { Group, GroupNode, Session, SessionStatus } from "../types";
 
 // Type for navigable items in the sidebar
@@ -117,8 +118,21 @@ function SessionItem(props: {
       onContextMenu={handleContextMenu}
       tabIndex={-1}
     >
-      <span class="truncate flex-1">{props.session.name}</span>
-      <StatusPill status={props.session.status} />
+      <span class="truncate flex-1">
+        {props.session.name}
+        {/* Why it needs attention, e.g. the tool waiting for permission */}
+        {props.session.status_detail && (
+          <span class="ml-1.5 text-xs opacity-60">{props.session.status_detail}</span>
+        )}
+      </span>
+      {/* New output nobody has looked at */}
+      <Show when={props.session.unread_bytes > 0 && !isSelected()}>
+        <span class="w-1.5 h-1.5 rounded-full bg-indigo-400 flex-shrink-0" title="New output" />
+      </Show>
+      <StatusPill
+        status={props.session.status}
+        title={props.session.status_detail ?? props.session.error_reason}
+      />
     </div>
   );
 }
@@ -165,8 +179,21 @@ function SortableSession(props: {
       tabIndex={-1}
       {...sortable.dragActivators}
     >
-      <span class="truncate flex-1">{props.session.name}</span>
-      <StatusPill status={props.session.status} />
+      <span class="truncate flex-1">
+        {props.session.name}
+        {/* Why it needs attention, e.g. the tool waiting for permission */}
+        {props.session.status_detail && (
+          <span class="ml-1.5 text-xs opacity-60">{props.session.status_detail}</span>
+        )}
+      </span>
+      {/* New output nobody has looked at */}
+      <Show when={props.session.unread_bytes > 0 && !isSelected()}>
+        <span class="w-1.5 h-1.5 rounded-full bg-indigo-400 flex-shrink-0" title="New output" />
+      </Show>
+      <StatusPill
+        status={props.session.status}
+        title={props.session.status_detail ?? props.session.error_reason}
+      />
     </div>
   );
 }
@@ -185,6 +212,13 @@ function SortableGroup(props: {
 }) {
   const sortable = createSortable(makeDragId("group", props.group.id));
   const paddingLeft = `${props.depth * 12 + 8}px`;
+  // Collapsed groups badge sessions that need attention
+  const attention = () => {
+    const status = appStore.groupStatuses[props.group.id];
+    return props.group.collapsed && (status?.status === "error" || status?.status === "waiting")
+      ? status
+      : undefined;
+  };
 
   const handleEditGroup = (e: MouseEvent) => {
     e.stopPropagation();
@@ -244,6 +278,9 @@ function SortableGroup(props: {
         />
       </svg>
       <span class="truncate flex-1 font-medium">{props.group.name}</span>
+      <Show when={attention()}>
+        {(status) => <StatusPill status={status().status} count={status().counts[status().status]} />}
+      </Show>
       <span class="text-xs text-gray-500 flex-shrink-0">
         {props.group.sessions.length + props.group.children.length}
       </span>
@@ -449,6 +486,12 @@ export function Sidebar() {
           }
         },
       },
+      {
+        label: session.detached ? "Attach" : "Detach",
+        icon: session.detached ? MenuIcons.play : MenuIcons.detach,
+        onClick: () =>
+          session.detached ? appStore.attachSession(session.id) : appStore.detachSession(session.id),
+      },
       { label: "", separator: true, onClick: () => {} },
       {
         label: "Fork",
@@ -456,6 +499,19 @@ export function Sidebar() {
         onClick: () => appStore.forkSession(session.id),
         disabled: !hasClaudeSession,
       },
+      {
+        label: "Fork to Worktree",
+        icon: MenuIcons.fork,
+        onClick: () => {
+          const branch = prompt("New branch for the worktree:");
+          if (branch?.trim()) {
+            appStore
+              .forkSession(session.id, `${session.name} (${branch.trim()})`, undefined, 24, 80, branch.trim())
+              .catch((e) => showToast(`Failed to fork: ${e}`, "error"));
+          }
+        },
+        disabled: !hasClaudeSession,
+      },
       {
         label: "Edit",
         icon: MenuIcons.edit,
@@ -494,6 +550,28 @@ export function Sidebar() {
         },
         danger: true,
       },
+      {
+        label: "Delete with Contents",
+        icon: MenuIcons.delete,
+        onClick: async () => {
+          try {
+            const preview = await appStore.previewGroupDelete(group.id, "cascade");
+            const subgroups = preview.groups.length - 1;
+            const running = preview.stopped.length > 0 ? ` (${preview.stopped.length} running)` : "";
+            if (
+              confirm(
+                `Delete group "${group.name}" with ${subgroups} subgroup(s) and ` +
+                  `${preview.sessions.length} session(s)${running}? Sessions are stopped and archived.`
+              )
+            ) {
+              await appStore.deleteGroup(group.id, "cascade");
+            }
+          } catch (e) {
+            showToast(`Failed to delete group: ${e}`, "error");
+          }
+        },
+        danger: true,
+      },
     ];
   };
 
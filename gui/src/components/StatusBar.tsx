@@ -12,6 +12,8 @@ export function StatusBar() {
       running: sessions.filter((s) => s.status === "running").length,
       waiting: sessions.filter((s) => s.status === "waiting").length,
       idle: sessions.filter((s) => s.status === "idle").length,
+      queued: sessions.filter((s) => s.status === "queued").length,
+      starting: sessions.filter((s) => s.status === "starting").length,
       stopped: sessions.filter((s) => s.status === "stopped").length,
       error: sessions.filter((s) => s.status === "error").length,
     };
@@ -39,6 +41,18 @@ export function StatusBar() {
             <span>{sessionCounts().idle} idle</span>
           </span>
         )}
+        {sessionCounts().starting > 0 && (
+          <span class="flex items-center gap-1">
+            <span class="w-2 h-2 rounded-full bg-cyan-500" />
+            <span>{sessionCounts().starting} starting</span>
+          </span>
+        )}
+        {sessionCounts().queued > 0 && (
+          <span class="flex items-center gap-1">
+            <span class="w-2 h-2 rounded-full bg-violet-500" />
+            <span>{sessionCounts().queued} queued</span>
+          </span>
+        )}
         {sessionCounts().error > 0 && (
           <span class="flex items-center gap-1">
             <span class="w-2 h-2 rounded-full bg-red-500" />
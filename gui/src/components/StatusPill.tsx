@@ -9,11 +9,17 @@ const styles: Record<SessionStatus, { bg: string; text: string }> = {
   running: { bg: "bg-blue-900/50", text: "text-blue-400" },
   idle: { bg: "bg-gray-700", text: "text-gray-400" },
   error: { bg: "bg-red-900/50", text: "text-red-400" },
+  queued: { bg: "bg-violet-900/50", text: "text-violet-400" },
+  starting: { bg: "bg-cyan-900/50", text: "text-cyan-400" },
   stopped: { bg: "bg-gray-800", text: "text-gray-500" },
 };
 
 interface StatusPillProps {
   status: SessionStatus;
+  // Shown after the label, e.g. how many sessions of a group have the status
+  count?: number;
+  // Hover text, e.g. why a session is in error
+  title?: string;
 }
 
 export function StatusPill(props: StatusPillProps) {
@@ -23,8 +29,10 @@ export function StatusPill(props: StatusPillProps) {
     <span
       class={`text-[10px] px-1.5 py-0.5 rounded uppercase font-medium tracking-wide
               flex-shrink-0 ${style().bg} ${style().text}`}
+      title={props.title}
     >
       {props.status}
+      {props.count !== undefined ? ` ${props.count}` : ""}
     </span>
   );
 }
@@ -11,6 +11,10 @@ import { terminalStore } from "../stores/terminalStore";
 import { appStore } from "../stores/appStore";
 import "@xterm/xterm/css/xterm.css";
 
+// Bracketed-paste markers xterm puts around pasted text
+const PASTE_START = "\x1b[200~";
+const PASTE_END = "\x1b[201~";
+
 interface TerminalProps {
   sessionId: string;
   sessionStatus?: string;
@@ -111,6 +115,12 @@ export function Terminal(props: TerminalProps) {
     // Handle input - send to daemon
     terminal.onData(async (data) => {
       try {
+        // xterm wraps pastes in bracketed-paste markers when the TUI asks for
+        // them; the daemon adds its own and writes large pastes in chunks
+        const paste = data.startsWith(PASTE_START) && data.endsWith(PASTE_END);
+        if (paste) {
+          data = data.slice(PASTE_START.length, -PASTE_END.length);
+        }
         // Base64 encode the input using proper UTF-8 handling
         const encoder = new TextEncoder();
         const bytes = encoder.encode(data);
@@ -119,6 +129,7 @@ export function Terminal(props: TerminalProps) {
         await invoke("send_input", {
           sessionId: sessionId,
           input: encoded,
+          paste,
         });
       } catch (e) {
         console.error("Failed to send input:", e);
@@ -207,7 +218,9 @@ export function Terminal(props: TerminalProps) {
                 setIsStarting(false);
               }
             } else {
-              // Session is already running - send resize to sync dimensions
+              // Session is already running - show its current screen, then
+              // send resize to sync dimensions
+              await appStore.restoreScreen(sessionId);
               invoke("resize_session", {
                 sessionId: sessionId,
                 rows,
@@ -1,5 +1,12 @@
 import { render } from "solid-js/web";
 import App from "./App";
+import { SessionWindow } from "./components/SessionWindow";
 import "./index.css";
 
-render(() => <App />, document.getElementById("root")!);
+// Pop-out windows are opened with ?session=<id> and show just that terminal
+const sessionId = new URLSearchParams(window.location.search).get("session");
+
+render(
+  () => (sessionId ? <SessionWindow sessionId={sessionId} /> : <App />),
+  document.getElementById("root")!
+);
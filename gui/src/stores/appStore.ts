@@ -3,21 +3,41 @@
 import { createSignal } from "solid-js";
 import { createStore, produce, reconcile } from "solid-js/store";
 import { invoke } from "@tauri-apps/api/core";
-import { listen } from "@tauri-apps/api/event";
-import type { Session, Group, GroupNode, PtyOutputData, StatusChangedData, ConnectionStateData } from "../types";
+import { getCurrentWebviewWindow } from "@tauri-apps/api/webviewWindow";
+import type { Session, Group, GroupNode, PtyOutputData, PtyResyncData, StatusChangedData, SessionMovedData, ConnectionStateData, DaemonHealth, DaemonIdentity, ConflictChoice, ProfileList, SessionScreen, SessionAttachResult, ApprovalRequestedData, ApprovalResolvedData, AutoApprovedData, ApprovalPolicy, Schedule, ScheduleFiredData, GroupStatus, GroupDeleteMode, GroupDeleteResult, ProjectCandidate, ClaudeFlags, ClaudeCapabilities, ClaudeInfo, McpServer, InputHistoryEntry, Snippet, Key, SessionStatsResult, StopOutcome, UnreadData, ClientInfo, PresenceData, StateSyncResult } from "../types";
 import { terminalStore } from "./terminalStore";
 import { showToast } from "../components/Toast";
 
+// Listen on this window only, so the Rust side can send a session's pop-out
+// window just the events of that session
+const appWindow = getCurrentWebviewWindow();
+
 // Use createStore for sessions to enable fine-grained updates
 // This preserves object references when updating individual session properties,
 // which prevents SolidJS's <For> from recreating Terminal components on status changes
 const [sessions, setSessions] = createStore<Session[]>([]);
 const [groups, setGroups] = createStore<Group[]>([]);
+// Aggregate status of each group's sessions, by group id
+const [groupStatuses, setGroupStatuses] = createStore<Record<string, GroupStatus | undefined>>({});
 const [selectedSessionId, setSelectedSessionId] = createSignal<string | null>(
   null
 );
 const [isConnected, setIsConnected] = createSignal(false);
 const [connectionError, setConnectionError] = createSignal<string | null>(null);
+// Set when another daemon (not the one this app launched) owns the socket
+const [daemonConflict, setDaemonConflict] = createSignal<DaemonIdentity | null>(null);
+// Profile in use and the named profiles to switch to (null = default profile)
+const [profiles, setProfiles] = createSignal<ProfileList>({ current: null, profiles: [] });
+// Permission dialogs open in session terminals, by session id
+const [approvals, setApprovals] = createStore<Record<string, ApprovalRequestedData | undefined>>({});
+// Other clients viewing or typing in each session, by session id
+const [presence, setPresence] = createStore<Record<string, PresenceData | undefined>>({});
+// This app's client id on its daemon connection (from session.view)
+const [clientId, setClientId] = createSignal<number | null>(null);
+// How long "typing" shows after another client's input
+const TYPING_DISPLAY_MS = 3000;
+// Latest health check by the daemon supervisor (null until the first one)
+const [daemonHealth, setDaemonHealth] = createSignal<DaemonHealth | null>(null);
 
 // Computed: build tree structure from flat groups
 // Exported for use in components that need to access stores reactively
@@ -89,23 +109,89 @@ async function setupEventListeners() {
   console.log("[AppStore] Setting up event listeners");
 
   // Listen for PTY output
-  const unlistenPty = await listen<PtyOutputData>("pty:output", (event) => {
+  const unlistenPty = await appWindow.listen<PtyOutputData>("pty:output", (event) => {
     terminalStore.writeBase64ToTerminal(event.payload.session_id, event.payload.output);
   });
   unlistenFunctions.push(unlistenPty);
 
+  // Output was dropped while this window fell behind: redraw the terminal
+  // from the daemon's copy of the screen
+  const unlistenResync = await appWindow.listen<PtyResyncData>("pty:resync", (event) => {
+    restoreScreen(event.payload.session_id);
+  });
+  unlistenFunctions.push(unlistenResync);
+
+  // Permission dialogs shown in session terminals
+  const unlistenApprovalRequested = await appWindow.listen<ApprovalRequestedData>("session:approval_requested", (event) => {
+    setApprovals(event.payload.session_id, event.payload);
+  });
+  unlistenFunctions.push(unlistenApprovalRequested);
+
+  const unlistenApprovalResolved = await appWindow.listen<ApprovalResolvedData>("session:approval_resolved", (event) => {
+    if (approvals[event.payload.session_id]?.approval_id === event.payload.approval_id) {
+      setApprovals(event.payload.session_id, undefined);
+    }
+  });
+  unlistenFunctions.push(unlistenApprovalResolved);
+
+  // Answered by the daemon under an approval policy
+  const unlistenAutoApproved = await appWindow.listen<AutoApprovedData>("session:auto_approved", (event) => {
+    const { session_id, tool, rule } = event.payload;
+    const session = sessions.find((s) => s.id === session_id);
+    showToast(`Auto-approved ${tool} in ${session?.name ?? "a session"} (${rule})`, "info", 3000);
+  });
+  unlistenFunctions.push(unlistenAutoApproved);
+
+  // The claude binary is missing or too old; sessions would fail to start
+  const unlistenClaudeWarning = await appWindow.listen<ClaudeInfo>("daemon:claude_warning", (event) => {
+    if (event.payload.warning) {
+      showToast(event.payload.warning, "error");
+    }
+  });
+  unlistenFunctions.push(unlistenClaudeWarning);
+
+  // A schedule started its session (the OS notification comes when it finishes)
+  const unlistenScheduleFired = await appWindow.listen<ScheduleFiredData>("schedule:fired", (event) => {
+    showToast(`Scheduled run of ${event.payload.name} started`, "info", 3000);
+  });
+  unlistenFunctions.push(unlistenScheduleFired);
+
   // Listen for status changes - use fine-grained store update to preserve object reference
-  const unlistenStatus = await listen<StatusChangedData>("session:status_changed", (event) => {
+  const unlistenStatus = await appWindow.listen<StatusChangedData>("session:status_changed", (event) => {
     const index = sessions.findIndex((s) => s.id === event.payload.session_id);
     if (index !== -1) {
-      // Update only the status property, preserving the object reference
+      // Update only the status properties, preserving the object reference
       setSessions(index, "status", event.payload.status);
+      setSessions(index, "status_detail", event.payload.detail);
     }
   });
   unlistenFunctions.push(unlistenStatus);
 
+  // Other clients looking at or typing in a session
+  const unlistenPresence = await appWindow.listen<PresenceData>("session:presence", (event) => {
+    const { session_id, typing } = event.payload;
+    setPresence(session_id, event.payload);
+    if (typing !== null) {
+      setTimeout(() => {
+        if (presence[session_id]?.typing === typing) {
+          setPresence(session_id, "typing", null);
+        }
+      }, TYPING_DISPLAY_MS);
+    }
+  });
+  unlistenFunctions.push(unlistenPresence);
+
+  // Unread output, kept by the daemon so every window shows the same badge
+  const unlistenUnread = await appWindow.listen<UnreadData>("session:unread", (event) => {
+    const index = sessions.findIndex((s) => s.id === event.payload.session_id);
+    if (index !== -1) {
+      setSessions(index, "unread_bytes", event.payload.unread_bytes);
+    }
+  });
+  unlistenFunctions.push(unlistenUnread);
+
   // Listen for session created
-  const unlistenSessionCreated = await listen<Session>("session:created", (event) => {
+  const unlistenSessionCreated = await appWindow.listen<Session>("session:created", (event) => {
     // Avoid duplicates
     if (!sessions.find((s) => s.id === event.payload.id)) {
       setSessions(produce((draft) => draft.push(event.payload)));
@@ -114,7 +200,7 @@ async function setupEventListeners() {
   unlistenFunctions.push(unlistenSessionCreated);
 
   // Listen for session deleted
-  const unlistenSessionDeleted = await listen<{ session_id: string }>("session:deleted", (event) => {
+  const unlistenSessionDeleted = await appWindow.listen<{ session_id: string }>("session:deleted", (event) => {
     const index = sessions.findIndex((s) => s.id === event.payload.session_id);
     if (index !== -1) {
       setSessions(produce((draft) => draft.splice(index, 1)));
@@ -126,7 +212,7 @@ async function setupEventListeners() {
   unlistenFunctions.push(unlistenSessionDeleted);
 
   // Listen for group created
-  const unlistenGroupCreated = await listen<Group>("group:created", (event) => {
+  const unlistenGroupCreated = await appWindow.listen<Group>("group:created", (event) => {
     if (!groups.find((g) => g.id === event.payload.id)) {
       setGroups(produce((draft) => draft.push(event.payload)));
     }
@@ -134,16 +220,17 @@ async function setupEventListeners() {
   unlistenFunctions.push(unlistenGroupCreated);
 
   // Listen for group deleted
-  const unlistenGroupDeleted = await listen<{ group_id: string }>("group:deleted", (event) => {
+  const unlistenGroupDeleted = await appWindow.listen<{ group_id: string }>("group:deleted", (event) => {
     const index = groups.findIndex((g) => g.id === event.payload.group_id);
     if (index !== -1) {
       setGroups(produce((draft) => draft.splice(index, 1)));
     }
+    setGroupStatuses(event.payload.group_id, undefined);
   });
   unlistenFunctions.push(unlistenGroupDeleted);
 
   // Listen for session updated - use reconcile to update while preserving reference if possible
-  const unlistenSessionUpdated = await listen<Session>("session:updated", (event) => {
+  const unlistenSessionUpdated = await appWindow.listen<Session>("session:updated", (event) => {
     const index = sessions.findIndex((s) => s.id === event.payload.id);
     if (index !== -1) {
       setSessions(index, reconcile(event.payload));
@@ -151,8 +238,23 @@ async function setupEventListeners() {
   });
   unlistenFunctions.push(unlistenSessionUpdated);
 
+  // Listen for session moved between groups
+  const unlistenSessionMoved = await appWindow.listen<SessionMovedData>("session:moved", (event) => {
+    const index = sessions.findIndex((s) => s.id === event.payload.session.id);
+    if (index !== -1) {
+      setSessions(index, reconcile(event.payload.session));
+    }
+  });
+  unlistenFunctions.push(unlistenSessionMoved);
+
+  // Listen for group aggregate status changes
+  const unlistenGroupStatus = await appWindow.listen<GroupStatus>("group:status_changed", (event) => {
+    setGroupStatuses(event.payload.group_id, reconcile(event.payload));
+  });
+  unlistenFunctions.push(unlistenGroupStatus);
+
   // Listen for group updated
-  const unlistenGroupUpdated = await listen<Group>("group:updated", (event) => {
+  const unlistenGroupUpdated = await appWindow.listen<Group>("group:updated", (event) => {
     const index = groups.findIndex((g) => g.id === event.payload.id);
     if (index !== -1) {
       setGroups(index, reconcile(event.payload));
@@ -161,14 +263,14 @@ async function setupEventListeners() {
   unlistenFunctions.push(unlistenGroupUpdated);
 
   // Listen for connection state changes from event listener
-  const unlistenConnectionState = await listen<ConnectionStateData>("daemon:connection_state", async (event) => {
+  const unlistenConnectionState = await appWindow.listen<ConnectionStateData>("daemon:connection_state", async (event) => {
     const wasConnected = isConnected();
 
     if (event.payload.connected) {
       // Event listener connected - ensure command client is also connected
       try {
         // Try to reconnect the command IPC client if needed
-        await invoke("connect_daemon");
+        trackIdentity(await invoke<DaemonIdentity>("connect_daemon"));
         setIsConnected(true);
         setConnectionError(null);
         if (reconnectTimeout) {
@@ -177,8 +279,11 @@ async function setupEventListeners() {
         }
         if (!wasConnected) {
           showToast("Connected to daemon", "success");
-          // Refresh data on reconnection
-          refreshData().catch(console.error);
+          // Refresh data on reconnection, unless the missed events were replayed
+          if (!event.payload.caught_up) {
+            refreshData().catch(console.error);
+          }
+          checkClaude().catch(console.error);
         }
       } catch (e) {
         // Failed to connect command client
@@ -197,44 +302,149 @@ async function setupEventListeners() {
     }
   });
   unlistenFunctions.push(unlistenConnectionState);
+
+  // Listen for the supervisor's health checks
+  const unlistenHealth = await appWindow.listen<DaemonHealth>("daemon:health", (event) => {
+    const previous = daemonHealth();
+    if (previous && event.payload.restart_count > previous.restart_count) {
+      showToast("Daemon stopped responding and was restarted", "error");
+    }
+    setDaemonHealth(event.payload);
+  });
+  unlistenFunctions.push(unlistenHealth);
+}
+
+function trackIdentity(identity: DaemonIdentity) {
+  setDaemonConflict(identity.state === "conflict" ? identity : null);
+}
+
+// Take over the socket from another daemon, or keep using it
+async function resolveDaemonConflict(choice: ConflictChoice) {
+  try {
+    trackIdentity(await invoke<DaemonIdentity>("resolve_daemon_conflict", { choice }));
+    if (choice === "takeover") {
+      await refreshData();
+    }
+  } catch (e) {
+    showToast(`Failed to resolve daemon conflict: ${e}`, "error");
+  }
+}
+
+async function loadProfiles() {
+  try {
+    setProfiles(await invoke<ProfileList>("list_profiles"));
+  } catch (e) {
+    console.error("Failed to list profiles:", e);
+  }
+}
+
+// Switch to another profile's daemon (started if needed); a new name creates the profile
+async function switchProfile(profile: string | null) {
+  try {
+    trackIdentity(await invoke<DaemonIdentity>("switch_profile", { profile }));
+    setSelectedSessionId(null);
+    terminalStore.clearAllBuffers();
+    // Generations are per daemon
+    stateGeneration = null;
+    stateTrackerId = null;
+    await Promise.all([refreshData(), loadProfiles()]);
+    showToast(`Switched to ${profile ?? "the default"} profile`, "success");
+  } catch (e) {
+    showToast(`Failed to switch profile: ${e}`, "error");
+  }
+}
+
+// Pop a session's terminal out into its own window
+async function openSessionWindow(sessionId: string) {
+  try {
+    await invoke("open_session_window", { sessionId });
+  } catch (e) {
+    showToast(`Failed to open window: ${e}`, "error");
+  }
 }
 
 async function connectToDaemon() {
   try {
     setConnectionError(null);
-    await invoke("connect_daemon");
+    trackIdentity(await invoke<DaemonIdentity>("connect_daemon"));
     setIsConnected(true);
     await setupEventListeners();
-    await refreshData();
+    await Promise.all([refreshData(), loadProfiles()]);
   } catch (e) {
     setConnectionError(String(e));
     setIsConnected(false);
   }
 }
 
+// Generation and tracker of the last sync_state, so a refresh only fetches
+// what changed
+let stateGeneration: number | null = null;
+let stateTrackerId: string | null = null;
+
+// Take the removed entries out of `list`, then replace or add the changed ones
+function applyChanges<T extends { id: string }>(list: T[], changed: T[], removed: string[]) {
+  const gone = new Set(removed);
+  for (let i = list.length - 1; i >= 0; i--) {
+    if (gone.has(list[i].id)) list.splice(i, 1);
+  }
+  for (const item of changed) {
+    const index = list.findIndex((existing) => existing.id === item.id);
+    if (index === -1) list.push(item);
+    else list[index] = item;
+  }
+}
+
 async function refreshData() {
   try {
     console.log("[AppStore] Refreshing data...");
-    const [sessionList, groupList] = await Promise.all([
-      invoke<Session[]>("list_sessions"),
-      invoke<Group[]>("list_groups"),
-    ]);
-    console.log("[AppStore] Received sessions:", sessionList.length, "groups:", groupList.length);
-    // Use reconcile to intelligently update while preserving references where possible
-    setSessions(reconcile(sessionList));
-    setGroups(reconcile(groupList));
+    const result = await invoke<StateSyncResult>("sync_state", {
+      since: stateGeneration,
+      trackerId: stateTrackerId,
+    });
+    console.log(
+      "[AppStore] Received sessions:",
+      result.sessions.length,
+      "groups:",
+      result.groups.length,
+      result.full ? "(everything)" : "(changes)"
+    );
+    if (result.full) {
+      // Use reconcile to intelligently update while preserving references where possible
+      setSessions(reconcile(result.sessions));
+      setGroups(reconcile(result.groups));
+    } else {
+      setSessions(produce((list) => applyChanges(list, result.sessions, result.removed_sessions)));
+      setGroups(produce((list) => applyChanges(list, result.groups, result.removed_groups)));
+    }
+    setGroupStatuses(reconcile(Object.fromEntries(result.statuses.map((status) => [status.group_id, status]))));
+    stateGeneration = result.generation;
+    stateTrackerId = result.tracker_id;
     console.log("[AppStore] Stores updated - sessions:", sessions.length, "groups:", groups.length);
   } catch (e) {
     console.error("Failed to refresh data:", e);
   }
 }
 
-async function createSession(name: string, dir: string, groupId?: string) {
+async function createSession(
+  name: string,
+  dir: string,
+  groupId?: string,
+  createDir = false,
+  flags?: ClaudeFlags,
+  claudeBinary?: string
+) {
   try {
+    // The new session takes the selected one's place, so it starts at that size
+    const selected = selectedSessionId();
+    const size = selected ? terminalStore.getTerminalDimensions(selected) : null;
     const session = await invoke<Session>("create_session", {
       name,
       dir,
       groupId: groupId || null,
+      createDir,
+      flags: flags ?? null,
+      claudeBinary: claudeBinary || null,
+      size,
     });
     // Don't add to store here - the session:created event will do it
     // This prevents duplicate entries
@@ -246,21 +456,95 @@ async function createSession(name: string, dir: string, groupId?: string) {
   }
 }
 
-async function stopSession(sessionId: string) {
+// Ask the session's claude to exit, killing it after timeoutMs (or at once with force)
+async function stopSession(sessionId: string, options?: { timeoutMs?: number; force?: boolean }) {
   try {
-    await invoke("stop_session", { sessionId });
+    const outcome = await invoke<StopOutcome>("stop_session", {
+      sessionId,
+      timeoutMs: options?.timeoutMs ?? null,
+      force: options?.force ?? null,
+    });
     // Clear the terminal screen and buffers
     terminalStore.clearTerminal(sessionId);
     const index = sessions.findIndex((s) => s.id === sessionId);
     if (index !== -1) {
       setSessions(index, "status", "stopped");
     }
+    return outcome;
   } catch (e) {
     console.error("Failed to stop session:", e);
     throw e;
   }
 }
 
+// Stop generation without killing the session's process
+async function interruptSession(sessionId: string) {
+  try {
+    await invoke("interrupt_session", { sessionId });
+  } catch (e) {
+    showToast(`Failed to interrupt session: ${e}`, "error");
+  }
+}
+
+// Press a key in a session without knowing its escape sequence
+async function sendKey(sessionId: string, key: Key, repeat = 1) {
+  try {
+    await invoke("send_key", { sessionId, key, repeat });
+  } catch (e) {
+    console.error("Failed to send key:", e);
+    throw e;
+  }
+}
+
+async function continueSession(sessionId: string, prompt?: string) {
+  try {
+    await invoke("continue_session", { sessionId, prompt: prompt ?? null });
+  } catch (e) {
+    showToast(`Failed to continue session: ${e}`, "error");
+  }
+}
+
+async function detachSession(sessionId: string) {
+  try {
+    await invoke("detach_session", { sessionId });
+  } catch (e) {
+    showToast(`Failed to detach session: ${e}`, "error");
+  }
+}
+
+// Resume a detached session's output, catching the terminal up on what it missed
+async function attachSession(sessionId: string) {
+  try {
+    const result = await invoke<SessionAttachResult>("attach_session", { sessionId });
+    if (result.from_screen) {
+      terminalStore.restoreScreen(sessionId, result.output);
+    } else if (result.output) {
+      terminalStore.writeBase64ToTerminal(sessionId, result.output);
+    }
+  } catch (e) {
+    showToast(`Failed to attach session: ${e}`, "error");
+  }
+}
+
+// Tell the daemon which session's terminal this app shows
+async function viewSession(sessionId: string | null) {
+  try {
+    const info = await invoke<ClientInfo>("view_session", { sessionId });
+    setClientId(info.client_id);
+  } catch (e) {
+    console.error("Failed to report the viewed session:", e);
+  }
+}
+
+// The session's output has been seen; clears its badge in every window
+async function markSessionViewed(sessionId: string) {
+  try {
+    await invoke("mark_session_viewed", { sessionId });
+  } catch (e) {
+    console.error("Failed to mark session viewed:", e);
+  }
+}
+
 async function deleteSession(sessionId: string) {
   try {
     await invoke("delete_session", { sessionId });
@@ -277,6 +561,31 @@ async function deleteSession(sessionId: string) {
   }
 }
 
+// Answer a permission dialog; the bar goes away on session:approval_resolved
+async function answerApproval(approval: ApprovalRequestedData, approved: boolean) {
+  try {
+    await invoke(approved ? "approve_session" : "deny_session", {
+      sessionId: approval.session_id,
+      approvalId: approval.approval_id,
+    });
+  } catch (e) {
+    // Most likely answered in the terminal already
+    setApprovals(approval.session_id, undefined);
+    showToast(`Failed to answer approval: ${e}`, "error");
+  }
+}
+
+// Draw a running session's current screen, e.g. in a newly mounted terminal
+async function restoreScreen(sessionId: string) {
+  try {
+    const screen = await invoke<SessionScreen>("get_session_screen", { sessionId });
+    terminalStore.restoreScreen(sessionId, screen.formatted);
+  } catch (e) {
+    // Sessions that haven't written anything yet have no screen
+    console.warn(`[AppStore] No screen to restore for ${sessionId}:`, e);
+  }
+}
+
 async function restartSession(sessionId: string, rows: number = 24, cols: number = 80) {
   try {
     console.log(`[AppStore] Restarting session ${sessionId} with size ${cols}x${rows}`);
@@ -303,7 +612,8 @@ async function forkSession(
   newName?: string,
   groupId?: string,
   rows: number = 24,
-  cols: number = 80
+  cols: number = 80,
+  worktreeBranch?: string // fork into a new worktree on this branch
 ) {
   try {
     console.log(`[AppStore] Forking session ${sessionId} with size ${cols}x${rows}`);
@@ -313,6 +623,7 @@ async function forkSession(
       groupId: groupId || null,
       rows,
       cols,
+      worktreeBranch: worktreeBranch || null,
     });
     // Don't add to store here - the session:created event will do it
     setSelectedSessionId(session.id);
@@ -337,21 +648,29 @@ async function createGroup(name: string, parentId?: string) {
   }
 }
 
-async function deleteGroup(groupId: string) {
+async function deleteGroup(groupId: string, mode: GroupDeleteMode = "reparent") {
   try {
-    await invoke("delete_group", { groupId });
-    const index = groups.findIndex((g) => g.id === groupId);
-    if (index !== -1) {
-      setGroups(produce((draft) => draft.splice(index, 1)));
-    }
-    // Sessions in this group are now orphaned - refresh to get updated data
+    const result = await invoke<GroupDeleteResult>("delete_group", { groupId, mode });
+    setGroups(produce((draft) => {
+      for (const id of result.groups) {
+        const index = draft.findIndex((g) => g.id === id);
+        if (index !== -1) draft.splice(index, 1);
+      }
+    }));
+    // Sessions in this group are now orphaned (or deleted) - refresh to get updated data
     await refreshData();
+    return result;
   } catch (e) {
     console.error("Failed to delete group:", e);
     throw e;
   }
 }
 
+// What deleting the group would affect, to confirm it first
+async function previewGroupDelete(groupId: string, mode: GroupDeleteMode) {
+  return await invoke<GroupDeleteResult>("delete_group", { groupId, mode, dryRun: true });
+}
+
 async function updateSession(
   sessionId: string,
   name?: string,
@@ -400,6 +719,221 @@ async function updateGroup(
   }
 }
 
+// Replace the auto-approve policy of a session or a group; the daemon sends
+// session:updated / group:updated with it
+async function setApprovalPolicy(
+  target: { sessionId: string } | { groupId: string },
+  policy: ApprovalPolicy
+) {
+  try {
+    return await invoke<ApprovalPolicy>("set_approval_policy", { ...target, policy });
+  } catch (e) {
+    console.error("Failed to set approval policy:", e);
+    throw e;
+  }
+}
+
+async function setCompletionWatch(groupId: string, enabled: boolean, webhookUrl?: string) {
+  try {
+    return await invoke<Group>("set_completion_watch", {
+      groupId,
+      enabled,
+      webhookUrl: webhookUrl || null,
+    });
+  } catch (e) {
+    console.error("Failed to set completion watch:", e);
+    throw e;
+  }
+}
+
+async function createSchedule(
+  name: string,
+  cron: string,
+  dir: string,
+  groupId?: string,
+  prompt?: string,
+  createDir = false,
+  flags?: ClaudeFlags,
+  claudeBinary?: string
+) {
+  try {
+    return await invoke<Schedule>("create_schedule", {
+      name,
+      cron,
+      dir,
+      groupId: groupId || null,
+      prompt: prompt || null,
+      createDir,
+      flags: flags ?? null,
+      claudeBinary: claudeBinary || null,
+    });
+  } catch (e) {
+    console.error("Failed to create schedule:", e);
+    throw e;
+  }
+}
+
+async function listSchedules() {
+  try {
+    return await invoke<Schedule[]>("list_schedules");
+  } catch (e) {
+    console.error("Failed to list schedules:", e);
+    throw e;
+  }
+}
+
+// Check the daemon's claude binary; a problem comes back as daemon:claude_warning
+async function checkClaude() {
+  return await invoke<ClaudeInfo>("claude_info");
+}
+
+async function getClaudeCapabilities(binary?: string) {
+  try {
+    return await invoke<ClaudeCapabilities>("claude_capabilities", { binary: binary || null });
+  } catch (e) {
+    console.error("Failed to get claude capabilities:", e);
+    throw e;
+  }
+}
+
+// Prompts sent to a session (or, without one, to any session), most recent first
+async function getInputHistory(sessionId?: string, query?: string, limit?: number) {
+  try {
+    return await invoke<InputHistoryEntry[]>("get_input_history", {
+      sessionId: sessionId ?? null,
+      query: query ?? null,
+      limit: limit ?? null,
+    });
+  } catch (e) {
+    console.error("Failed to get input history:", e);
+    throw e;
+  }
+}
+
+async function getSessionStats(sessionId?: string) {
+  try {
+    return await invoke<SessionStatsResult>("get_session_stats", {
+      sessionId: sessionId ?? null,
+    });
+  } catch (e) {
+    console.error("Failed to get session stats:", e);
+    throw e;
+  }
+}
+
+async function listMcpServers(dir: string) {
+  try {
+    return await invoke<McpServer[]>("list_mcp_servers", { dir });
+  } catch (e) {
+    console.error("Failed to list MCP servers:", e);
+    throw e;
+  }
+}
+
+async function addMcpServer(dir: string, name: string, config: Record<string, unknown>) {
+  try {
+    await invoke("add_mcp_server", { dir, name, config });
+  } catch (e) {
+    console.error("Failed to add MCP server:", e);
+    throw e;
+  }
+}
+
+async function removeMcpServer(dir: string, name: string) {
+  try {
+    await invoke("remove_mcp_server", { dir, name });
+  } catch (e) {
+    console.error("Failed to remove MCP server:", e);
+    throw e;
+  }
+}
+
+async function setMcpServerEnabled(dir: string, name: string, enabled: boolean) {
+  try {
+    await invoke("set_mcp_server_enabled", { dir, name, enabled });
+  } catch (e) {
+    console.error("Failed to update MCP server:", e);
+    throw e;
+  }
+}
+
+async function scanProjects(limit?: number) {
+  try {
+    return await invoke<ProjectCandidate[]>("scan_projects", { limit });
+  } catch (e) {
+    console.error("Failed to scan projects:", e);
+    throw e;
+  }
+}
+
+async function deleteSchedule(scheduleId: string) {
+  try {
+    await invoke("delete_schedule", { scheduleId });
+  } catch (e) {
+    console.error("Failed to delete schedule:", e);
+    throw e;
+  }
+}
+
+async function listSnippets() {
+  try {
+    return await invoke<Snippet[]>("list_snippets");
+  } catch (e) {
+    console.error("Failed to list snippets:", e);
+    throw e;
+  }
+}
+
+async function createSnippet(name: string, text: string) {
+  try {
+    return await invoke<Snippet>("create_snippet", { name, text });
+  } catch (e) {
+    console.error("Failed to create snippet:", e);
+    throw e;
+  }
+}
+
+async function updateSnippet(snippetId: string, changes: { name?: string; text?: string }) {
+  try {
+    return await invoke<Snippet>("update_snippet", {
+      snippetId,
+      name: changes.name ?? null,
+      text: changes.text ?? null,
+    });
+  } catch (e) {
+    console.error("Failed to update snippet:", e);
+    throw e;
+  }
+}
+
+async function deleteSnippet(snippetId: string) {
+  try {
+    await invoke("delete_snippet", { snippetId });
+  } catch (e) {
+    console.error("Failed to delete snippet:", e);
+    throw e;
+  }
+}
+
+// Send a snippet as a prompt; resolves to the text sent, with its variables filled in
+async function sendSnippet(sessionId: string, snippetId: string, vars?: Record<string, string>) {
+  try {
+    return await invoke<string>("send_snippet", { sessionId, snippetId, vars: vars ?? null });
+  } catch (e) {
+    console.error("Failed to send snippet:", e);
+    throw e;
+  }
+}
+
+async function setDependency(sessionId: string, parentId: string | null, prompt: string | null) {
+  try {
+    return await invoke<Session>("set_dependency", { sessionId, parentId, prompt });
+  } catch (e) {
+    console.error("Failed to set dependency:", e);
+    throw e;
+  }
+}
+
 function toggleGroupCollapse(groupId: string) {
   const index = groups.findIndex((g) => g.id === groupId);
   if (index !== -1) {
@@ -413,14 +947,13 @@ async function reorderSession(
   afterSessionId: string | null // null = insert at beginning
 ) {
   try {
-    const session = await invoke<Session>("reorder_session", {
+    // Every renumbered session comes back as session:updated, here and in
+    // other clients
+    return await invoke<Session[]>("reorder_session", {
       sessionId,
       groupId: groupId || null,
       afterSessionId: afterSessionId || null,
     });
-    // Refresh all data since multiple sessions may have had their order updated
-    await refreshData();
-    return session;
   } catch (e) {
     console.error("Failed to reorder session:", e);
     throw e;
@@ -433,14 +966,12 @@ async function reorderGroup(
   afterGroupId: string | null // null = insert at beginning
 ) {
   try {
-    const group = await invoke<Group>("reorder_group", {
+    // Every renumbered group comes back as group:updated
+    return await invoke<Group[]>("reorder_group", {
       groupId,
       parentId: parentId || null,
       afterGroupId: afterGroupId || null,
     });
-    // Refresh all data since multiple groups may have had their order updated
-    await refreshData();
-    return group;
   } catch (e) {
     console.error("Failed to reorder group:", e);
     throw e;
@@ -458,6 +989,13 @@ export const appStore = {
   selectedSessionId,
   isConnected,
   connectionError,
+  daemonConflict,
+  profiles,
+  daemonHealth,
+  approvals,
+  presence,
+  clientId,
+  groupStatuses,
 
   // Computed
   get groupTree() {
@@ -471,18 +1009,52 @@ export const appStore = {
 
   // Actions
   connectToDaemon,
+  resolveDaemonConflict,
+  loadProfiles,
+  switchProfile,
+  openSessionWindow,
   refreshData,
   setSelectedSessionId,
   createSession,
   stopSession,
+  interruptSession,
+  sendKey,
+  continueSession,
+  detachSession,
+  attachSession,
+  markSessionViewed,
+  viewSession,
   deleteSession,
   restartSession,
+  restoreScreen,
+  answerApproval,
   forkSession,
   updateSession,
   reorderSession,
   createGroup,
   deleteGroup,
+  previewGroupDelete,
   updateGroup,
+  setApprovalPolicy,
+  setDependency,
+  createSchedule,
+  listSchedules,
+  deleteSchedule,
+  listSnippets,
+  createSnippet,
+  updateSnippet,
+  deleteSnippet,
+  sendSnippet,
+  scanProjects,
+  getClaudeCapabilities,
+  checkClaude,
+  setCompletionWatch,
+  getInputHistory,
+  getSessionStats,
+  listMcpServers,
+  addMcpServer,
+  removeMcpServer,
+  setMcpServerEnabled,
   reorderGroup,
   toggleGroupCollapse,
 };
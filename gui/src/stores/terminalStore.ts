@@ -191,6 +191,16 @@ export function clearTerminal(sessionId: string) {
   resetDecoder(sessionId);
 }
 
+// Replace the terminal's contents with a screen from the daemon (base64 escape sequences)
+export function restoreScreen(sessionId: string, formatted: string) {
+  const entry = terminalInstances.get(sessionId);
+  if (!entry) return;
+  entry.terminal.reset();
+  outputBuffers.delete(sessionId);
+  resetDecoder(sessionId);
+  writeBase64ToTerminal(sessionId, formatted);
+}
+
 // Get terminal dimensions for a session
 // Forces a re-fit to ensure dimensions are accurate for current container size
 // Returns { rows, cols } or null if terminal not found
@@ -219,6 +229,7 @@ export const terminalStore = {
   clearBuffer,
   clearAllBuffers,
   clearTerminal,
+  restoreScreen,
   getTerminalDimensions,
   resetDecoder,
 };
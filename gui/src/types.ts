@@ -6,8 +6,55 @@ export type SessionStatus =
   | "running"
   | "waiting"
   | "error"
+  | "queued"
+  | "starting"
   | "idle";
 
+// How the claude process is launched (null on a session = daemon default)
+export type SpawnMode = "direct" | "shell";
+
+// How session.stop ended a session's process
+export type StopOutcome = "not_running" | "exited" | "killed" | "forced";
+
+// A key session.send_key can press by name
+export type Key =
+  | "enter"
+  | "esc"
+  | "tab"
+  | "shift_tab"
+  | "backspace"
+  | "delete"
+  | "up"
+  | "down"
+  | "left"
+  | "right"
+  | "home"
+  | "end"
+  | "page_up"
+  | "page_down"
+  | "ctrl_c"
+  | "ctrl_d"
+  | "ctrl_l"
+  | "ctrl_r";
+
+export interface WorktreeInfo {
+  repo_path: string;
+  path: string;
+  branch: string;
+}
+
+export interface GitStatus {
+  branch: string | null;
+  dirty: number;
+  ahead: number;
+  behind: number;
+}
+
+export interface TerminalSize {
+  rows: number;
+  cols: number;
+}
+
 export interface Session {
   id: string;
   name: string;
@@ -15,10 +62,92 @@ export interface Session {
   group_id: string | null;
   status: SessionStatus;
   pid: number | null;
+  pid_start_time: number | null;
   claude_session_id: string | null;
   created_at: string;
   last_activity: string;
   order: number;
+  worktree: WorktreeInfo | null;
+  git: GitStatus | null;
+  spawn_mode: SpawnMode | null;
+  tags: string[];
+  // Watch expressions that raise session:needs_attention when true
+  watches: string[];
+  approval_policy?: ApprovalPolicy; // absent when empty
+  // Still running, but its output is spooled by the daemon (session.detach)
+  detached: boolean;
+  // Started by the daemon once the parent session finishes
+  dependency: SessionDependency | null;
+  size?: TerminalSize; // last known terminal size, spawned at when a start gives none
+  error_reason?: string; // why it failed to start or exited with an error
+  // Why it has its status, e.g. "permission for Bash(rm -rf build)"; cleared on the next change
+  status_detail?: string;
+  unread_bytes: number; // output since a client last marked it viewed
+  claude_binary?: string; // named binary from [claude] binaries
+  // Flags claude is started with
+  model?: string;
+  permission_mode?: string;
+  extra_args?: string[];
+}
+
+export interface ClaudeFlags {
+  model?: string;
+  permission_mode?: string;
+  extra_args?: string[];
+}
+
+// The daemon's claude binary, from daemon.claude_info and daemon:claude_warning
+export interface ClaudeInfo {
+  path: string | null;
+  version: string | null;
+  min_version: string | null;
+  warning: string | null; // why sessions are likely to fail to start
+  checked_at: string;
+}
+
+// An MCP server in a project's .mcp.json, from mcp.list
+export interface McpServer {
+  name: string;
+  config: Record<string, unknown>; // e.g. { command, args } or { type: "http", url }
+  enabled: boolean; // approved in the project's .claude/settings.local.json
+}
+
+// What the daemon's claude binary supports, from claude.capabilities
+export interface ClaudeCapabilities {
+  path: string | null;
+  version: string | null;
+  flags: string[]; // long flags from claude --help, e.g. "--model"
+  permission_modes: string[];
+  binaries: string[]; // names configured in [claude] binaries
+}
+
+export interface SessionDependency {
+  parent_id: string;
+  prompt: string | null; // queued once the session has started
+}
+
+// Result of attach_session
+export interface SessionAttachResult {
+  session: Session;
+  output: string; // base64; the session's screen when from_screen
+  from_screen: boolean;
+}
+
+// Permission dialogs the daemon answers itself. Each list allows on its own:
+// tool names ("*" for any), file globs (relative to the working directory)
+// and regexes for a single Bash command
+export interface ApprovalPolicy {
+  tools: string[];
+  paths: string[];
+  commands: string[];
+}
+
+// Filter for session.list; every field that is set must match
+export interface SessionFilter {
+  tags?: string[];
+  status?: SessionStatus;
+  group_id?: string;
+  working_dir?: string;
 }
 
 export interface Group {
@@ -27,6 +156,335 @@ export interface Group {
   parent_id: string | null;
   collapsed: boolean;
   order: number;
+  // Watch expressions applied to every session in the group and its subgroups
+  watches: string[];
+  // Applies to every session in the group and its subgroups
+  approval_policy?: ApprovalPolicy;
+  // Notify once every session in the group and its subgroups has finished
+  completion_watch?: CompletionWatch;
+}
+
+export interface CompletionWatch {
+  webhook_url?: string; // also POST the summary here
+}
+
+// Derived from the sessions in a group and its subgroups; status is the most
+// urgent of theirs (error > waiting > running > starting > queued > idle > stopped)
+export interface GroupStatus {
+  group_id: string;
+  status: SessionStatus;
+  total: number;
+  counts: Partial<Record<SessionStatus, number>>;
+}
+
+// What delete_group does with the group's contents: reparent moves sessions to
+// the top level and subgroups to its parent, cascade deletes them all
+export type GroupDeleteMode = "reparent" | "cascade";
+
+// Result of delete_group: what it affected, or would have with dry_run
+export interface GroupDeleteResult {
+  groups: string[];
+  sessions: string[];
+  stopped: string[]; // running sessions among them
+  dry_run: boolean;
+}
+
+// Result of list_groups
+export interface GroupListResult {
+  groups: Group[];
+  statuses: GroupStatus[];
+}
+
+// Result of sync_state: apply the removals, then replace the listed sessions
+// and groups; when full, whatever isn't listed is gone
+export interface StateSyncResult {
+  generation: number;
+  // Send back with generation; a new one each time the daemon starts
+  tracker_id: string;
+  full: boolean;
+  sessions: Session[];
+  groups: Group[];
+  removed_sessions: string[];
+  removed_groups: string[];
+  statuses: GroupStatus[];
+}
+
+// Result of ui.theme - status colors are CSS hex strings
+export interface StatusColors {
+  running: string;
+  waiting: string;
+  idle: string;
+  error: string;
+  queued: string;
+  starting: string;
+  stopped: string;
+}
+
+export interface ThemeResult {
+  theme: string;
+  font_family: string;
+  font_size: number;
+  palette: "default" | "color_blind";
+  status_colors: StatusColors;
+}
+
+export type TranscriptState = "working" | "tool_use" | "complete";
+
+export interface TranscriptMessage {
+  role: string;
+  text: string;
+  tool_calls: string[];
+  tool_result: boolean;
+  timestamp: string | null;
+}
+
+export interface TranscriptResult {
+  path: string | null;
+  state: TranscriptState | null;
+  messages: TranscriptMessage[];
+}
+
+// daemon.keep_awake result and daemon:keep_awake event payload
+export interface KeepAwakeStatus {
+  enabled: boolean;
+  active: boolean;
+}
+
+export interface NotificationSettings {
+  enabled: boolean;
+  muted_sessions: string[];
+  muted_groups: string[];
+}
+
+// Deleted session from history.list
+export interface SessionUsage {
+  prompts: number;
+  turns: number;
+  busy_ms: number;
+  input_tokens: number;
+  output_tokens: number;
+}
+
+export interface ArchivedSession {
+  id: string;
+  name: string;
+  working_dir: string;
+  claude_session_id: string | null;
+  transcript_path: string | null;
+  created_at: string;
+  last_activity: string;
+  deleted_at: string;
+  usage: SessionUsage;
+}
+
+// asciinema v2 recording from session.recording.*
+export interface RecordingInfo {
+  id: string;
+  session_id: string;
+  path: string;
+  started_at: string;
+  active: boolean;
+}
+
+// End of a session's output log from session.tail_log
+export interface TailLogResult {
+  path: string;
+  output: string; // base64 raw PTY output
+  truncated: boolean;
+}
+
+// Result of get_session_screen - what a session's terminal shows now
+export interface SessionScreen {
+  session_id: string;
+  rows: number;
+  cols: number;
+  contents: string;
+  formatted: string; // base64 escape sequences redrawing the screen
+  cursor_row: number;
+  cursor_col: number;
+  cursor_hidden: boolean;
+  alternate_screen: boolean;
+  exited: boolean;
+}
+
+// One line of the daemon's JSON log
+export interface LogRecord {
+  timestamp: string;
+  level: "ERROR" | "WARN" | "INFO" | "DEBUG" | "TRACE";
+  target: string; // module, e.g. "claude_master_daemon::ipc"
+  message: string;
+  fields?: Record<string, unknown>;
+}
+
+// Latest daemon log records from daemon.logs.tail (oldest first)
+export interface DaemonLogsTailResult {
+  path: string;
+  records: LogRecord[];
+  truncated: boolean;
+}
+
+// Session timeline entry from session.history
+export type HistoryEntry = { ts: string } & (
+  | { kind: "created"; name: string; forked_from: string | null }
+  | { kind: "status_changed"; from: SessionStatus; to: SessionStatus }
+  | { kind: "prompt_sent"; text: string | null }
+  | { kind: "interrupted" }
+  | { kind: "approval_requested" }
+  | { kind: "auto_approved"; tool: string; rule: string }
+  | { kind: "exited"; exit_code: number | null }
+  | { kind: "moved"; from_group_id: string | null; to_group_id: string | null }
+  | { kind: "dependency_met"; parent_id: string }
+  | ({ kind: "turn" } & TurnMetrics)
+  | {
+      kind: "task_run";
+      name: string;
+      command: string;
+      headless: boolean;
+      exit_code: number | null;
+      output: string | null;
+    }
+);
+
+// Prompt sent with session.input, from session.input_history
+export interface InputHistoryEntry {
+  session_id: string;
+  text: string;
+  last_used: string;
+  uses: number;
+}
+
+export interface TurnMetrics {
+  first_output_ms: number | null;
+  duration_ms: number;
+}
+
+// Task found by session.tasks.list
+export type TaskRunner = "make" | "just" | "package";
+
+export interface TaskInfo {
+  name: string;
+  runner: TaskRunner;
+  command: string;
+}
+
+// Payload of session:task_finished (headless runs)
+export interface TaskFinishedData {
+  session_id: string;
+  name: string;
+  command: string;
+  exit_code: number | null;
+  output: string;
+}
+
+export interface TurnRecord extends TurnMetrics {
+  ts: string;
+}
+
+export interface TurnStats {
+  count: number;
+  avg_first_output_ms: number | null;
+  avg_duration_ms: number | null;
+  p95_duration_ms: number | null;
+  turns: TurnRecord[];
+}
+
+// Prompt queue lanes, highest priority first
+export type PromptPriority = "interactive" | "scheduled" | "batch";
+
+export interface QueuedPrompt {
+  id: string;
+  session_id: string;
+  prompt: string;
+  priority: PromptPriority;
+  enqueued_at: string;
+}
+
+// Claude process that outlived the daemon that spawned it (daemon.orphans)
+export interface OrphanInfo {
+  session_id: string;
+  name: string;
+  pid: number;
+  started_at: string;
+  action: "killed" | "adopted" | "kill_failed";
+  alive: boolean;
+}
+
+// From daemon.network_audit
+export interface EgressSubsystem {
+  name: string;
+  description: string;
+  destination: string;
+  enabled: boolean;
+  gated: boolean; // blocked by daemon.local_only
+  allowed: boolean;
+}
+
+export interface NetworkAuditResult {
+  local_only: boolean;
+  subsystems: EgressSubsystem[];
+}
+
+// PTY flow control and client lag from daemon.stats
+export interface SessionFlowStats {
+  session_id: string;
+  bytes_read: number;
+  pauses: number;
+  paused_ms: number;
+  paused: boolean;
+}
+
+// CPU and memory of a session's claude process and what it started, from
+// session.stats and the session:resource_usage event
+export interface SessionResourceUsage {
+  session_id: string;
+  pid: number;
+  cpu_percent: number;
+  rss_bytes: number;
+  processes: number;
+}
+
+export interface SessionStatsResult {
+  sessions: SessionResourceUsage[];
+}
+
+export interface DaemonStats {
+  output_queue_capacity: number;
+  output_queue_len: number;
+  clients: number;
+  client_lags: number;
+  events_dropped: number;
+  sessions: SessionFlowStats[];
+}
+
+// Counters since the daemon started, from daemon.metrics
+export interface DaemonMetrics {
+  uptime_secs: number;
+  sessions_created: number;
+  pty_output_bytes: number;
+  events_dropped: number;
+  requests: Record<string, number>; // by method; unknown methods are "other"
+  status_transitions: { from: SessionStatus; to: SessionStatus; count: number }[];
+  prometheus?: string; // with format "prometheus"
+}
+
+// Compact hierarchy from tree.snapshot
+export interface TreeSession {
+  id: string;
+  name: string;
+  status: SessionStatus;
+}
+
+export interface TreeGroup {
+  id: string;
+  name: string;
+  collapsed: boolean;
+  groups: TreeGroup[];
+  sessions: TreeSession[];
+}
+
+export interface TreeSnapshot {
+  groups: TreeGroup[];
+  sessions: TreeSession[];
 }
 
 // Event types from daemon
@@ -35,14 +493,323 @@ export interface PtyOutputData {
   output: string; // base64 encoded
 }
 
+// Emitted as session:approval_requested when Claude shows a permission dialog
+export interface ApprovalRequestedData {
+  session_id: string;
+  approval_id: string; // pass to approve_session / deny_session
+  tool: string;
+  title: string;
+  summary: string;
+  question: string;
+  path: string | null; // the file, for file tools
+  command: string | null; // the command, for Bash
+}
+
+// Emitted as session:auto_approved when a policy answered the dialog
+export interface AutoApprovedData {
+  session_id: string;
+  approval_id: string;
+  tool: string;
+  summary: string;
+  path: string | null;
+  rule: string; // e.g. "tool:Read", "path:src/**", "command:^cargo test"
+}
+
+// Emitted as session:approval_resolved when the dialog closes
+export interface ApprovalResolvedData {
+  session_id: string;
+  approval_id: string;
+  approved: boolean | null; // null if answered in the terminal
+}
+
+// Sent when output for the session was dropped because this client fell behind
+export interface PtyResyncData {
+  session_id: string;
+}
+
+// search.query across every session's output log and transcript
+export type SearchSource = "output" | "transcript";
+
+export interface SearchHit {
+  session_id: string;
+  session_name: string;
+  archived: boolean;
+  source: SearchSource;
+  offset: number; // line number within the source, from 1
+  at: string | null;
+  text: string;
+  before: string[];
+  after: string[];
+}
+
+export interface SearchQueryResult {
+  hits: SearchHit[];
+  truncated: boolean;
+}
+
+// session.search_output over the on-disk output log
+export interface OutputMatch {
+  line: number;
+  text: string;
+}
+
+export interface SearchOutputResult {
+  matches: OutputMatch[];
+  truncated: boolean;
+}
+
+export interface ExportOutputResult {
+  path: string;
+  bytes: number;
+}
+
+// Result of the export_state command (state.export)
+export interface StateExportResult {
+  path: string;
+  sessions: number;
+  groups: number;
+  logs: number; // sessions whose output log was included
+  bytes: number;
+}
+
+// Result of the import_state command (state.import); ids that already
+// exist are skipped
+export interface StateImportResult {
+  sessions: number;
+  groups: number;
+  logs: number;
+  skipped: number;
+}
+
+// replay:output while session.replay plays back a session's output log
+export interface ReplayOutputData {
+  replay_id: string;
+  session_id: string;
+  output: string; // base64 encoded
+}
+
+export interface ReplayFinishedData {
+  replay_id: string;
+  session_id: string;
+  cancelled: boolean;
+}
+
+// What kind of program a daemon connection is (client.identify)
+export type ClientKind = "gui" | "cli" | "web" | "mcp" | "other";
+
+// A connection to the daemon, from daemon.clients and session.view
+export interface ClientInfo {
+  client_id: number;
+  kind: ClientKind;
+  name: string | null;
+  connected_at: string;
+  viewing: string | null; // session whose terminal it shows
+  last_input_at: string | null;
+}
+
+export interface ClientsResult {
+  clients: ClientInfo[];
+}
+
+// session:presence - who views a session's terminal, and who just typed in it
+export interface PresenceData {
+  session_id: string;
+  viewers: ClientInfo[];
+  typing: number | null; // client id
+}
+
+// session:unread - a session turned unread, or was marked viewed (0)
+export interface UnreadData {
+  session_id: string;
+  unread_bytes: number;
+}
+
 export interface StatusChangedData {
   session_id: string;
   status: SessionStatus;
+  detail?: string; // the session's new status_detail
+}
+
+export interface SessionMovedData {
+  session: Session;
+  from_group_id: string | null;
+}
+
+export interface GitChangedData {
+  session_id: string;
+  git: GitStatus | null;
+}
+
+// A session created on a cron schedule; see create_schedule
+export interface Schedule {
+  id: string;
+  name: string;
+  cron: string; // five fields in the daemon's local time, or @daily etc.
+  session: { name: string; dir: string; group_id: string | null };
+  prompt: string | null;
+  created_at: string;
+  next_run: string | null;
+  last_run: string | null;
+  last_session_id: string | null;
+}
+
+// A reusable prompt; {{dir}}, {{name}}, {{branch}} and {{group}} in its text
+// are filled in from the session it is sent to (see send_snippet)
+export interface Snippet {
+  id: string;
+  name: string;
+  text: string;
+  created_at: string;
+  updated_at: string;
+}
+
+// A suggested working directory from projects.scan
+export interface ProjectCandidate {
+  path: string;
+  last_used: string;
+  // Claude Code's project registry, a session's working directory, or the git repo around one
+  source: "claude" | "session" | "git";
+}
+
+// Emitted as schedule:fired when a schedule has created and started its session
+export interface ScheduleFiredData {
+  schedule_id: string;
+  name: string;
+  session_id: string;
+}
+
+// Emitted as schedule:finished when the run answered its prompt, stopped or failed
+export interface ScheduleFinishedData {
+  schedule_id: string;
+  name: string;
+  session_id: string;
+  status: SessionStatus;
+  muted: boolean;
+}
+
+export interface SessionOutcome {
+  session_id: string;
+  name: string;
+  status: SessionStatus; // idle, stopped or error
+}
+
+// group:completed - every session under a watched group has finished
+export interface GroupCompletedData {
+  group_id: string;
+  name: string;
+  sessions: SessionOutcome[];
+  muted: boolean;
+}
+
+export interface NeedsAttentionData {
+  session_id: string;
+  name: string;
+  group_id: string | null;
+  status: SessionStatus;
+  muted: boolean;
+  // The watch expression that fired (null for a status change)
+  watch: string | null;
+}
+
+export type BootstrapStep =
+  | "check_claude"
+  | "install_claude"
+  | "install_hooks"
+  | "create_group";
+
+export interface BootstrapProgressData {
+  step: BootstrapStep;
+  state: "running" | "done" | "skipped" | "failed";
+  message: string | null;
+}
+
+export interface BootstrapResult {
+  claude_path: string | null;
+  install_command: string;
+  hooks_installed: boolean;
+  group: Group | null;
+}
+
+export interface TurnMetricsData extends TurnMetrics {
+  session_id: string;
+}
+
+// session:idle_timeout - no output or input for daemon.idle_timeout_mins
+export interface IdleTimeoutData {
+  session_id: string;
+  idle_secs: number;
+  stopped: boolean;
+}
+
+export interface ConfigReloadedData {
+  error: string | null;
 }
 
 export interface ConnectionStateData {
   connected: boolean;
   error: string | null;
+  // The events missed while disconnected were replayed, so nothing needs reloading
+  caught_up?: boolean;
+}
+
+// Sent by the GUI's daemon supervisor after every health check
+export interface DaemonHealth {
+  healthy: boolean;
+  version: string | null;
+  uptime_secs: number | null;
+  restart_count: number;
+  error: string | null;
+}
+
+// Result of daemon.hello
+export interface HelloResult {
+  version: string;
+  pid: number;
+  instance_id: string | null;
+  started_at: string;
+  protocol_version: number;
+  methods: string[];
+  capabilities: string[];
+  profile: string | null;
+}
+
+// Whether the daemon on the socket is the one this app launched
+export type DaemonIdentity =
+  | { state: "owned"; hello: HelloResult }
+  | { state: "attached"; hello: HelloResult | null }
+  | { state: "conflict"; expected: string; hello: HelloResult | null };
+
+export type ConflictChoice = "takeover" | "attach";
+
+// Returned by get_daemon_mode; "external" = started outside the GUI (e.g. systemd)
+export interface DaemonModeInfo {
+  mode: "managed" | "external";
+  connected: boolean;
+  pid: number | null;
+  version: string | null;
+}
+
+// Returned by list_profiles and switch_profile; null is the default profile
+export interface ProfileList {
+  current: string | null;
+  profiles: string[];
+}
+
+// debug:tap - one request, response or event mirrored by the daemon (redacted)
+export interface TapRecord {
+  client_id: number | null;
+  direction: "request" | "response" | "event";
+  at: string;
+  message: unknown;
+}
+
+// Passed to set_event_subscription; session and group filters only narrow
+// events that name a session
+export interface EventSubscription {
+  // Event names; a trailing "*" matches a prefix ("session:*"), [] means none
+  events: string[];
+  session_ids: string[];
+  group_ids: string[];
 }
 
 // Tree structure for rendering sidebar
@@ -0,0 +1,137 @@
+//! Line framing limits for the daemon socket
+//!
+//! Every message is one line of JSON no longer than `MAX_FRAME_BYTES`. A response
+//! that would be longer is sent as `ResponseChunk` lines carrying slices of its
+//! JSON text, which clients put back together with `ChunkAssembler`.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::protocol::Response;
+
+/// Longest line either side sends, newline included
+pub const MAX_FRAME_BYTES: usize = 1024 * 1024;
+
+/// Bytes of response JSON per chunk; escaping can double them inside the
+/// chunk's string, so this leaves room to stay under `MAX_FRAME_BYTES`
+const CHUNK_DATA_BYTES: usize = MAX_FRAME_BYTES / 2 - 1024;
+
+/// One slice of a response too large for a single frame
+/// `chunks` is required, which keeps chunk lines from parsing as anything else.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResponseChunk {
+    pub id: u64,
+    /// Position of this slice, from 0
+    pub chunk: usize,
+    pub chunks: usize,
+    pub data: String,
+}
+
+/// Serialize a response as the lines to send (without newlines)
+pub fn response_frames(response: &Response) -> serde_json::Result<Vec<String>> {
+    let json = serde_json::to_string(response)?;
+    if json.len() < MAX_FRAME_BYTES {
+        return Ok(vec![json]);
+    }
+
+    let mut slices = Vec::new();
+    let mut rest = json.as_str();
+    while !rest.is_empty() {
+        let mut end = rest.len().min(CHUNK_DATA_BYTES);
+        while !rest.is_char_boundary(end) {
+            end -= 1;
+        }
+        let (slice, tail) = rest.split_at(end);
+        slices.push(slice);
+        rest = tail;
+    }
+
+    let chunks = slices.len();
+    slices
+        .into_iter()
+        .enumerate()
+        .map(|(chunk, data)| {
+            serde_json::to_string(&ResponseChunk {
+                id: response.id,
+                chunk,
+                chunks,
+                data: data.to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Collects `ResponseChunk`s until a whole response has arrived
+#[derive(Debug, Default)]
+pub struct ChunkAssembler {
+    partial: HashMap<u64, Vec<String>>,
+}
+
+impl ChunkAssembler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a chunk, returning the response once its last chunk is in
+    /// Chunks must arrive in order, as they do on a single connection.
+    pub fn push(&mut self, chunk: ResponseChunk) -> Option<serde_json::Result<Response>> {
+        let parts = self.partial.entry(chunk.id).or_default();
+        if parts.len() != chunk.chunk {
+            // A chunk went missing; start over with whatever comes next
+            parts.clear();
+            if chunk.chunk != 0 {
+                return None;
+            }
+        }
+        parts.push(chunk.data);
+        if parts.len() < chunk.chunks {
+            return None;
+        }
+
+        let json = self.partial.remove(&chunk.id)?.concat();
+        Some(serde_json::from_str(&json))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_large_response_round_trip() {
+        // Multi-byte characters and quotes to exercise char boundaries and escaping
+        let text = "é\"x".repeat(MAX_FRAME_BYTES / 2);
+        let response = Response {
+            id: 42,
+            result: Some(json!({ "text": text })),
+            error: None,
+        };
+
+        let frames = response_frames(&response).unwrap();
+        assert!(frames.len() > 1);
+        assert!(frames.iter().all(|frame| frame.len() < MAX_FRAME_BYTES));
+
+        let mut assembler = ChunkAssembler::new();
+        let mut assembled = None;
+        for frame in &frames {
+            let chunk: ResponseChunk = serde_json::from_str(frame).unwrap();
+            assembled = assembler.push(chunk);
+        }
+        let assembled = assembled.unwrap().unwrap();
+        assert_eq!(assembled.id, 42);
+        assert_eq!(assembled.result.unwrap()["text"], text);
+    }
+
+    #[test]
+    fn test_small_response_is_one_frame() {
+        let response = Response {
+            id: 1,
+            result: Some(json!({"status": "ok"})),
+            error: None,
+        };
+        let frames = response_frames(&response).unwrap();
+        assert_eq!(frames.len(), 1);
+        assert!(serde_json::from_str::<ResponseChunk>(&frames[0]).is_err());
+    }
+}
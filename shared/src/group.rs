@@ -1,6 +1,8 @@
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
+use crate::session::ApprovalPolicy;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Group {
     pub id: Uuid,
@@ -10,6 +12,24 @@ pub struct Group {
     pub collapsed: bool,
     #[serde(default)]
     pub order: u32,
+    /// Watch expressions applied to every session in the group and its subgroups
+    #[serde(default)]
+    pub watches: Vec<String>,
+    /// Permission prompts to answer automatically in every session under the group
+    #[serde(default, skip_serializing_if = "ApprovalPolicy::is_empty")]
+    pub approval_policy: ApprovalPolicy,
+    /// Send one summary once every session under the group has finished
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub completion_watch: Option<CompletionWatch>,
+}
+
+/// Set with group.set_completion_watch; fires group:completed each time the
+/// group's sessions go from working to all finished
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CompletionWatch {
+    /// Also POST the summary here as JSON
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub webhook_url: Option<String>,
 }
 
 impl Group {
@@ -20,6 +40,9 @@ impl Group {
             parent_id,
             collapsed: false,
             order: 0,
+            watches: Vec::new(),
+            approval_policy: ApprovalPolicy::default(),
+            completion_watch: None,
         }
     }
 }
@@ -0,0 +1,84 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::session::SessionStatus;
+
+/// One entry in a session's timeline
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    pub ts: DateTime<Utc>,
+    #[serde(flatten)]
+    pub kind: HistoryKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum HistoryKind {
+    /// Session created (forked_from is the source session for forks)
+    Created {
+        name: String,
+        forked_from: Option<Uuid>,
+    },
+    StatusChanged {
+        from: SessionStatus,
+        to: SessionStatus,
+    },
+    /// Input submitted with Enter (text is None when the prompt was typed key by key)
+    PromptSent { text: Option<String> },
+    /// The turn was stopped with session.interrupt
+    Interrupted,
+    /// Claude asked for permission to use a tool
+    ApprovalRequested,
+    /// A permission prompt was answered by the session's approval policy
+    AutoApproved { tool: String, rule: String },
+    /// The claude process exited (exit_code is None if it could not be read)
+    Exited { exit_code: Option<u32> },
+    /// Session moved between groups (None = root level)
+    Moved {
+        from_group_id: Option<Uuid>,
+        to_group_id: Option<Uuid>,
+    },
+    /// Started because the session it depended on finished
+    DependencyMet { parent_id: Uuid },
+    /// A prompt finished being answered
+    Turn(TurnMetrics),
+    /// A project task was run from the task launcher (exit_code and output are
+    /// only known for headless runs)
+    TaskRun {
+        name: String,
+        command: String,
+        headless: bool,
+        exit_code: Option<i32>,
+        output: Option<String>,
+    },
+}
+
+/// Latency of one prompt/response turn
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TurnMetrics {
+    /// From sending the prompt to Claude's first reply (None if no transcript was found)
+    pub first_output_ms: Option<u64>,
+    /// From sending the prompt to Claude waiting for input again
+    pub duration_ms: u64,
+}
+
+/// A prompt written to a session with `session.input`, for recall
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InputHistoryEntry {
+    pub session_id: Uuid,
+    pub text: String,
+    /// When it was last sent
+    pub last_used: DateTime<Utc>,
+    /// How many times it was sent
+    pub uses: u32,
+}
+
+impl HistoryEntry {
+    pub fn new(kind: HistoryKind) -> Self {
+        Self {
+            ts: Utc::now(),
+            kind,
+        }
+    }
+}
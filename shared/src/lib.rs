@@ -1,11 +1,22 @@
 //! Shared types between daemon and GUI
 
+pub mod framing;
 pub mod group;
+pub mod history;
 pub mod paths;
 pub mod protocol;
+pub mod schedule;
 pub mod session;
+pub mod snippet;
 
-pub use group::Group;
+pub use framing::{response_frames, ChunkAssembler, ResponseChunk, MAX_FRAME_BYTES};
+pub use group::{CompletionWatch, Group};
+pub use history::{HistoryEntry, HistoryKind, InputHistoryEntry, TurnMetrics};
 pub use paths::*;
 pub use protocol::*;
-pub use session::{Session, SessionStatus};
+pub use schedule::Schedule;
+pub use session::{
+    ApprovalPolicy, ClaudeFlags, GitStatus, Session, SessionDependency, SessionStatus, SpawnMode,
+    TerminalSize, WorktreeInfo,
+};
+pub use snippet::Snippet;
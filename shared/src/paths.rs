@@ -3,17 +3,88 @@
 use anyhow::Result;
 use directories::ProjectDirs;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
 
-/// Get the application data directory
+/// Longest accepted profile name
+pub const MAX_PROFILE_NAME_LEN: usize = 32;
+
+/// Profile used by the path helpers in this process (None is the default profile)
+static PROFILE: RwLock<Option<String>> = RwLock::new(None);
+
+/// Check that a profile name is usable as a directory and socket name
+pub fn validate_profile_name(name: &str) -> Result<()> {
+    if name.is_empty() || name.len() > MAX_PROFILE_NAME_LEN {
+        anyhow::bail!(
+            "Profile name must be 1 to {} characters",
+            MAX_PROFILE_NAME_LEN
+        );
+    }
+    if !name
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        anyhow::bail!(
+            "Profile name {:?} may only contain letters, digits, '-' and '_'",
+            name
+        );
+    }
+    Ok(())
+}
+
+/// Switch this process to another profile; every path below follows it
+pub fn set_profile(profile: Option<&str>) -> Result<()> {
+    if let Some(name) = profile {
+        validate_profile_name(name)?;
+    }
+    *PROFILE.write().unwrap() = profile.map(str::to_string);
+    Ok(())
+}
+
+/// The profile this process uses (None is the default profile)
+pub fn current_profile() -> Option<String> {
+    PROFILE.read().unwrap().clone()
+}
+
+/// Get the application data directory of the current profile
 pub fn get_data_dir() -> Result<PathBuf> {
+    get_profile_data_dir(current_profile().as_deref())
+}
+
+/// Get the data directory of `profile`
+/// The default profile uses the top-level data dir; named profiles live in
+/// its `profiles` subdirectory, each with its own config, state and socket
+pub fn get_profile_data_dir(profile: Option<&str>) -> Result<PathBuf> {
     let proj_dirs = ProjectDirs::from("com", "claudemaster", "claude-master")
         .ok_or_else(|| anyhow::anyhow!("Could not determine data directory"))?;
-    let data_dir = proj_dirs.data_dir().to_path_buf();
+    let data_dir = profile_dir(proj_dirs.data_dir(), profile);
     fs::create_dir_all(&data_dir)?;
     Ok(data_dir)
 }
 
+fn profile_dir(base: &Path, profile: Option<&str>) -> PathBuf {
+    match profile {
+        Some(name) => base.join("profiles").join(name),
+        None => base.to_path_buf(),
+    }
+}
+
+/// Names of the profiles that have a data directory, sorted
+pub fn list_profiles() -> Result<Vec<String>> {
+    let dir = get_profile_data_dir(None)?.join("profiles");
+    let Ok(entries) = fs::read_dir(&dir) else {
+        return Ok(Vec::new());
+    };
+    let mut profiles: Vec<String> = entries
+        .flatten()
+        .filter(|entry| entry.file_type().is_ok_and(|t| t.is_dir()))
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .filter(|name| validate_profile_name(name).is_ok())
+        .collect();
+    profiles.sort();
+    Ok(profiles)
+}
+
 /// Get the path to the config file
 pub fn get_config_path() -> Result<PathBuf> {
     Ok(get_data_dir()?.join("config.toml"))
@@ -26,23 +97,34 @@ pub fn get_state_dir() -> Result<PathBuf> {
     Ok(state_dir)
 }
 
-/// Get the daemon socket path
+/// Get the daemon socket path of the current profile
+pub fn get_socket_path() -> Result<PathBuf> {
+    get_profile_socket_path(current_profile().as_deref())
+}
+
+/// Get the daemon socket path of `profile`
 /// On Unix: returns a path to a Unix socket file
 /// On Windows: returns a path that will be used as a named pipe identifier
-pub fn get_socket_path() -> Result<PathBuf> {
+pub fn get_profile_socket_path(profile: Option<&str>) -> Result<PathBuf> {
     #[cfg(unix)]
     {
-        Ok(get_data_dir()?.join("daemon.sock"))
+        Ok(get_profile_data_dir(profile)?.join("daemon.sock"))
     }
     #[cfg(windows)]
     {
         // On Windows, we use the data dir path to create a unique named pipe
         // The actual pipe path will be \\.\pipe\claude-master-daemon
         // But we return a file path that the interprocess crate can convert
-        Ok(get_data_dir()?.join("daemon.sock"))
+        Ok(get_profile_data_dir(profile)?.join("daemon.sock"))
     }
 }
 
+/// Get the path of the token clients send with daemon.auth
+/// Only written while daemon.require_token is on
+pub fn get_token_path() -> Result<PathBuf> {
+    Ok(get_data_dir()?.join("daemon.token"))
+}
+
 /// Get the logs directory
 pub fn get_logs_dir() -> Result<PathBuf> {
     let logs_dir = get_data_dir()?.join("logs");
@@ -57,9 +139,130 @@ pub fn get_hooks_dir() -> Result<PathBuf> {
     Ok(hooks_dir)
 }
 
+/// Maximum Unix socket path length (sun_path is 104 bytes on macOS, 108 on Linux)
+#[cfg(unix)]
+pub const MAX_SOCKET_PATH_LEN: usize = 104;
+
 /// Get the hook events socket path
 /// Claude hooks communicate status via this Unix socket
+///
+/// Lives in a short per-user runtime dir rather than the data dir, whose
+/// path can exceed the socket length limit for long usernames
+/// Named profiles get their own socket so their daemons can run side by side
 #[cfg(unix)]
 pub fn get_hook_socket_path() -> Result<PathBuf> {
-    Ok(get_data_dir()?.join("hooks.sock"))
+    let name = match current_profile() {
+        Some(profile) => format!("hooks-{}.sock", profile),
+        None => "hooks.sock".to_string(),
+    };
+    let path = get_runtime_dir()?.join(name);
+    check_socket_path_len(&path)?;
+    Ok(path)
+}
+
+/// Get the per-user runtime directory for sockets
+/// Uses /tmp/claude-master-<uid> (mode 0700). If that name is taken by another
+/// user or isn't a real directory, falls back to a numbered suffix.
+#[cfg(unix)]
+pub fn get_runtime_dir() -> Result<PathBuf> {
+    // SAFETY: getuid has no preconditions and cannot fail
+    let uid = unsafe { libc::getuid() };
+    let base = PathBuf::from("/tmp");
+
+    for attempt in 0..16 {
+        let name = if attempt == 0 {
+            format!("claude-master-{}", uid)
+        } else {
+            format!("claude-master-{}-{}", uid, attempt)
+        };
+        let dir = base.join(name);
+        if ensure_private_dir(&dir, uid).is_ok() {
+            return Ok(dir);
+        }
+    }
+
+    Err(anyhow::anyhow!(
+        "Could not create a private runtime directory under {:?}",
+        base
+    ))
+}
+
+/// Create `dir` with user-only permissions, or verify an existing one is ours
+#[cfg(unix)]
+fn ensure_private_dir(dir: &Path, uid: u32) -> Result<()> {
+    use std::os::unix::fs::{DirBuilderExt, MetadataExt, PermissionsExt};
+
+    match fs::DirBuilder::new().mode(0o700).create(dir) {
+        Ok(()) => return Ok(()),
+        Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {}
+        Err(e) => return Err(e.into()),
+    }
+
+    // Don't follow symlinks - another user could point us somewhere else
+    let meta = fs::symlink_metadata(dir)?;
+    if !meta.file_type().is_dir() {
+        anyhow::bail!("{:?} exists and is not a directory", dir);
+    }
+    if meta.uid() != uid {
+        anyhow::bail!("{:?} is owned by another user", dir);
+    }
+    if meta.mode() & 0o077 != 0 {
+        fs::set_permissions(dir, fs::Permissions::from_mode(0o700))?;
+    }
+    Ok(())
+}
+
+/// Fail early with a clear message if a socket path is too long to bind
+#[cfg(unix)]
+pub fn check_socket_path_len(path: &Path) -> Result<()> {
+    let len = path.as_os_str().len();
+    if len >= MAX_SOCKET_PATH_LEN {
+        anyhow::bail!(
+            "Socket path is {} bytes, exceeding the {} byte limit: {:?}",
+            len,
+            MAX_SOCKET_PATH_LEN,
+            path
+        );
+    }
+    Ok(())
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_socket_path_len() {
+        assert!(check_socket_path_len(Path::new("/tmp/claude-master-501/hooks.sock")).is_ok());
+        let long = PathBuf::from("/").join("x".repeat(MAX_SOCKET_PATH_LEN));
+        assert!(check_socket_path_len(&long).is_err());
+    }
+
+    #[test]
+    fn test_profiles() {
+        assert!(validate_profile_name("work").is_ok());
+        assert!(validate_profile_name("client_a-2").is_ok());
+        assert!(validate_profile_name("").is_err());
+        assert!(validate_profile_name("../state").is_err());
+        assert!(validate_profile_name("my work").is_err());
+        assert!(validate_profile_name(&"x".repeat(MAX_PROFILE_NAME_LEN + 1)).is_err());
+
+        let base = Path::new("/data/claude-master");
+        assert_eq!(profile_dir(base, None), base);
+        assert_eq!(
+            profile_dir(base, Some("work")),
+            Path::new("/data/claude-master/profiles/work")
+        );
+    }
+
+    #[test]
+    fn test_runtime_dir_is_private() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = get_runtime_dir().unwrap();
+        let mode = fs::metadata(&dir).unwrap().permissions().mode();
+        assert_eq!(mode & 0o077, 0);
+        // Stable across calls
+        assert_eq!(get_runtime_dir().unwrap(), dir);
+    }
 }
@@ -1,9 +1,17 @@
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
 use uuid::Uuid;
 
-use crate::group::Group;
-use crate::session::{Session, SessionStatus};
+use crate::group::{CompletionWatch, Group};
+use crate::history::TurnMetrics;
+use crate::schedule::Schedule;
+use crate::session::{
+    ApprovalPolicy, ClaudeFlags, GitStatus, Session, SessionStatus, SpawnMode, TerminalSize,
+};
+use crate::snippet::Snippet;
 
 /// Request from GUI to daemon
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -30,11 +38,266 @@ pub struct ErrorInfo {
     pub message: String,
 }
 
+impl ErrorInfo {
+    pub fn new(code: ErrorCode, message: impl Into<String>) -> Self {
+        Self {
+            code: code.code(),
+            message: message.into(),
+        }
+    }
+
+    /// The code as an `ErrorCode` (None for codes from a newer daemon)
+    pub fn kind(&self) -> Option<ErrorCode> {
+        ErrorCode::from_code(self.code)
+    }
+}
+
+/// What went wrong with a request, sent as the number in `ErrorInfo::code`
+/// The numbers are part of the protocol and never change meaning; clients
+/// should treat codes they don't know like `Failed`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ErrorCode {
+    /// The request line isn't valid JSON
+    ParseError,
+    /// The request is malformed, e.g. over MAX_FRAME_BYTES
+    InvalidRequest,
+    MethodNotFound,
+    /// Params are missing, mistyped or make no sense
+    InvalidParams,
+    /// The method ran and failed for some other reason
+    Failed,
+    /// The session's claude process isn't running
+    PtyDead,
+    SessionNotFound,
+    GroupNotFound,
+    /// The daemon is already doing this (e.g. a bootstrap is in progress)
+    DaemonBusy,
+    /// The connection has to send daemon.auth with a valid token first
+    Unauthorized,
+    /// A session's working directory is missing, not a directory or unreadable
+    InvalidWorkingDir,
+    /// claude couldn't be started in the session's terminal
+    SpawnFailed,
+    /// The connection's token doesn't have a role allowed to call the method
+    Forbidden,
+}
+
+impl ErrorCode {
+    pub const ALL: [ErrorCode; 13] = [
+        ErrorCode::ParseError,
+        ErrorCode::InvalidRequest,
+        ErrorCode::MethodNotFound,
+        ErrorCode::InvalidParams,
+        ErrorCode::Failed,
+        ErrorCode::PtyDead,
+        ErrorCode::SessionNotFound,
+        ErrorCode::GroupNotFound,
+        ErrorCode::DaemonBusy,
+        ErrorCode::Unauthorized,
+        ErrorCode::InvalidWorkingDir,
+        ErrorCode::SpawnFailed,
+        ErrorCode::Forbidden,
+    ];
+
+    pub const fn code(self) -> i32 {
+        match self {
+            ErrorCode::ParseError => -32700,
+            ErrorCode::InvalidRequest => -32600,
+            ErrorCode::MethodNotFound => -32601,
+            ErrorCode::InvalidParams => -32602,
+            ErrorCode::Failed => -32000,
+            ErrorCode::PtyDead => -32001,
+            ErrorCode::SessionNotFound => -32002,
+            ErrorCode::GroupNotFound => -32003,
+            ErrorCode::DaemonBusy => -32004,
+            ErrorCode::Unauthorized => -32005,
+            ErrorCode::InvalidWorkingDir => -32006,
+            ErrorCode::SpawnFailed => -32007,
+            ErrorCode::Forbidden => -32008,
+        }
+    }
+
+    pub fn from_code(code: i32) -> Option<Self> {
+        Self::ALL.into_iter().find(|c| c.code() == code)
+    }
+}
+
 /// Event from daemon to GUI (no id, push-based)
+/// On the wire it is `{"event": "<name>", "data": <payload>}`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "event", content = "data")]
+pub enum Event {
+    #[serde(rename = "pty:output")]
+    PtyOutput(PtyOutputData),
+    /// Output of the session was dropped because this client fell behind; the
+    /// terminal should be reset and redrawn rather than trusted
+    #[serde(rename = "pty:resync")]
+    PtyResync { session_id: Uuid },
+    #[serde(rename = "session:created")]
+    SessionCreated(Session),
+    #[serde(rename = "session:updated")]
+    SessionUpdated(Session),
+    #[serde(rename = "session:deleted")]
+    SessionDeleted { session_id: Uuid },
+    #[serde(rename = "session:moved")]
+    SessionMoved(SessionMovedData),
+    #[serde(rename = "session:status_changed")]
+    StatusChanged(StatusChangedData),
+    #[serde(rename = "session:idle_timeout")]
+    IdleTimeout(IdleTimeoutData),
+    #[serde(rename = "session:git_changed")]
+    GitChanged(GitChangedData),
+    #[serde(rename = "session:needs_attention")]
+    NeedsAttention(NeedsAttentionData),
+    #[serde(rename = "session:task_finished")]
+    TaskFinished(TaskFinishedData),
+    #[serde(rename = "session:turn_completed")]
+    TurnCompleted(TurnMetricsData),
+    #[serde(rename = "session:slow_turn")]
+    SlowTurn(TurnMetricsData),
+    #[serde(rename = "session:approval_requested")]
+    ApprovalRequested(ApprovalRequestedData),
+    #[serde(rename = "session:approval_resolved")]
+    ApprovalResolved(ApprovalResolvedData),
+    #[serde(rename = "session:auto_approved")]
+    AutoApproved(AutoApprovedData),
+    #[serde(rename = "session:resource_usage")]
+    ResourceUsage(ResourceUsageData),
+    /// A session got output nobody has viewed yet, or was marked viewed
+    #[serde(rename = "session:unread")]
+    Unread(UnreadData),
+    /// Who is viewing a session's terminal changed, or one of them typed in it
+    #[serde(rename = "session:presence")]
+    Presence(PresenceData),
+    #[serde(rename = "schedule:fired")]
+    ScheduleFired(ScheduleFiredData),
+    #[serde(rename = "schedule:finished")]
+    ScheduleFinished(ScheduleFinishedData),
+    #[serde(rename = "group:created")]
+    GroupCreated(Group),
+    #[serde(rename = "group:updated")]
+    GroupUpdated(Group),
+    #[serde(rename = "group:deleted")]
+    GroupDeleted { group_id: Uuid },
+    #[serde(rename = "group:status_changed")]
+    GroupStatusChanged(GroupStatus),
+    #[serde(rename = "group:completed")]
+    GroupCompleted(GroupCompletedData),
+    #[serde(rename = "replay:output")]
+    ReplayOutput(ReplayOutputData),
+    #[serde(rename = "replay:finished")]
+    ReplayFinished(ReplayFinishedData),
+    #[serde(rename = "daemon:bootstrap_progress")]
+    BootstrapProgress(BootstrapProgressData),
+    #[serde(rename = "daemon:bootstrap_complete")]
+    BootstrapComplete(BootstrapResult),
+    #[serde(rename = "daemon:config_reloaded")]
+    ConfigReloaded(ConfigReloadedData),
+    #[serde(rename = "daemon:keep_awake")]
+    KeepAwake(KeepAwakeStatus),
+    #[serde(rename = "daemon:update_status")]
+    UpdateStatus(UpdateStatusData),
+    /// The claude binary is missing or older than daemon.min_claude_version
+    #[serde(rename = "daemon:claude_warning")]
+    ClaudeWarning(ClaudeInfo),
+    #[serde(rename = "debug:tap")]
+    DebugTap(TapRecord),
+}
+
+impl Event {
+    /// The wire name, e.g. "session:status_changed"
+    pub fn name(&self) -> &'static str {
+        match self {
+            Event::PtyOutput(_) => "pty:output",
+            Event::PtyResync { .. } => "pty:resync",
+            Event::SessionCreated(_) => "session:created",
+            Event::SessionUpdated(_) => "session:updated",
+            Event::SessionDeleted { .. } => "session:deleted",
+            Event::SessionMoved(_) => "session:moved",
+            Event::StatusChanged(_) => "session:status_changed",
+            Event::IdleTimeout(_) => "session:idle_timeout",
+            Event::GitChanged(_) => "session:git_changed",
+            Event::NeedsAttention(_) => "session:needs_attention",
+            Event::TaskFinished(_) => "session:task_finished",
+            Event::TurnCompleted(_) => "session:turn_completed",
+            Event::SlowTurn(_) => "session:slow_turn",
+            Event::ApprovalRequested(_) => "session:approval_requested",
+            Event::ApprovalResolved(_) => "session:approval_resolved",
+            Event::AutoApproved(_) => "session:auto_approved",
+            Event::ResourceUsage(_) => "session:resource_usage",
+            Event::Unread(_) => "session:unread",
+            Event::Presence(_) => "session:presence",
+            Event::ScheduleFired(_) => "schedule:fired",
+            Event::ScheduleFinished(_) => "schedule:finished",
+            Event::GroupCreated(_) => "group:created",
+            Event::GroupUpdated(_) => "group:updated",
+            Event::GroupDeleted { .. } => "group:deleted",
+            Event::GroupStatusChanged(_) => "group:status_changed",
+            Event::GroupCompleted(_) => "group:completed",
+            Event::ReplayOutput(_) => "replay:output",
+            Event::ReplayFinished(_) => "replay:finished",
+            Event::BootstrapProgress(_) => "daemon:bootstrap_progress",
+            Event::BootstrapComplete(_) => "daemon:bootstrap_complete",
+            Event::ConfigReloaded(_) => "daemon:config_reloaded",
+            Event::KeepAwake(_) => "daemon:keep_awake",
+            Event::UpdateStatus(_) => "daemon:update_status",
+            Event::ClaudeWarning(_) => "daemon:claude_warning",
+            Event::DebugTap(_) => "debug:tap",
+        }
+    }
+
+    /// The session the event is about, if any
+    pub fn session_id(&self) -> Option<Uuid> {
+        match self {
+            Event::PtyOutput(data) => Some(data.session_id),
+            Event::PtyResync { session_id } => Some(*session_id),
+            Event::SessionCreated(session) | Event::SessionUpdated(session) => Some(session.id),
+            Event::SessionDeleted { session_id } => Some(*session_id),
+            Event::SessionMoved(data) => Some(data.session.id),
+            Event::StatusChanged(data) => Some(data.session_id),
+            Event::IdleTimeout(data) => Some(data.session_id),
+            Event::GitChanged(data) => Some(data.session_id),
+            Event::NeedsAttention(data) => Some(data.session_id),
+            Event::TaskFinished(data) => Some(data.session_id),
+            Event::TurnCompleted(data) | Event::SlowTurn(data) => Some(data.session_id),
+            Event::ApprovalRequested(data) => Some(data.session_id),
+            Event::ApprovalResolved(data) => Some(data.session_id),
+            Event::AutoApproved(data) => Some(data.session_id),
+            Event::Unread(data) => Some(data.session_id),
+            Event::Presence(data) => Some(data.session_id),
+            Event::ScheduleFired(data) => Some(data.session_id),
+            Event::ScheduleFinished(data) => Some(data.session_id),
+            Event::ReplayOutput(data) => Some(data.session_id),
+            Event::ReplayFinished(data) => Some(data.session_id),
+            _ => None,
+        }
+    }
+
+    /// The payload as it goes out in `data`
+    pub fn data(&self) -> Value {
+        match serde_json::to_value(self) {
+            Ok(Value::Object(mut map)) => map.remove("data").unwrap_or(Value::Null),
+            _ => Value::Null,
+        }
+    }
+}
+
+/// An event as the daemon sends it: `{"event", "data", "seq"}`. Events the
+/// daemon journals carry their sequence number, which only ever increases (also
+/// across restarts), so a client that reconnects can ask for what it missed with
+/// `events.since`. Terminal output and other transient events have none.
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct Event {
-    pub event: String,
-    pub data: Value,
+pub struct SequencedEvent {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seq: Option<u64>,
+    #[serde(flatten)]
+    pub event: Event,
+}
+
+impl From<Event> for SequencedEvent {
+    fn from(event: Event) -> Self {
+        Self { seq: None, event }
+    }
 }
 
 // --- Method Parameters ---
@@ -44,6 +307,34 @@ pub struct CreateSessionParams {
     pub name: String,
     pub dir: String,
     pub group_id: Option<Uuid>,
+    /// Override daemon.spawn_mode for this session
+    #[serde(default)]
+    pub spawn_mode: Option<SpawnMode>,
+    /// Named claude binary from `[claude] binaries` (None = the default)
+    #[serde(default)]
+    pub claude_binary: Option<String>,
+    /// Create `dir` (and its parents) if it doesn't exist, instead of failing
+    #[serde(default)]
+    pub create_dir: bool,
+    /// Terminal size to start at (None = 24x80 until a client resizes it)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size: Option<TerminalSize>,
+    /// `model`, `permission_mode` and `extra_args` for claude
+    #[serde(flatten)]
+    pub flags: ClaudeFlags,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateWorktreeSessionParams {
+    pub name: String,
+    /// Path to the repository (or any directory inside it)
+    pub repo_path: String,
+    /// Branch to check out; created from HEAD if it doesn't exist
+    pub branch: String,
+    /// Where to create the worktree (None = sibling directory `<repo>-<branch>`)
+    #[serde(default)]
+    pub worktree_path: Option<String>,
+    pub group_id: Option<Uuid>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -51,10 +342,111 @@ pub struct SessionIdParams {
     pub session_id: Uuid,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeleteGroupParams {
+    /// Older clients sent the group's id as session_id
+    #[serde(alias = "session_id")]
+    pub group_id: Uuid,
+    #[serde(default)]
+    pub mode: GroupDeleteMode,
+    /// Only report what would be affected, e.g. to confirm with the user first
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+/// What group.delete does with the group's contents
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GroupDeleteMode {
+    /// Sessions move to the top level, subgroups to the group's parent
+    #[default]
+    Reparent,
+    /// Subgroups are deleted too, and every session in them is stopped and
+    /// deleted (deleted sessions are archived)
+    Cascade,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeleteSessionParams {
+    pub session_id: Uuid,
+    /// Also remove the session's git worktree, if it has one
+    #[serde(default)]
+    pub remove_worktree: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StopSessionParams {
+    pub session_id: Uuid,
+    /// How long claude gets to exit after SIGTERM (to flush its transcript)
+    /// before it is killed (default 3000)
+    #[serde(default)]
+    pub timeout_ms: Option<u64>,
+    /// Kill it at once without asking it to exit
+    #[serde(default)]
+    pub force: bool,
+}
+
+/// How `session.stop` ended the session's process
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StopOutcome {
+    /// There was no running process
+    NotRunning,
+    /// It exited on SIGTERM within the timeout
+    Exited,
+    /// It was still running after the timeout and was killed
+    Killed,
+    /// Killed at once with `force` (or where there is no polite signal, on Windows)
+    Forced,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SessionInputParams {
     pub session_id: Uuid,
     pub input: String,
+    /// Send the input as a bracketed paste, written in chunks (for large or
+    /// multi-line prompts); a trailing Enter still submits it
+    #[serde(default)]
+    pub paste: bool,
+    /// Terminal size to use if the session is auto-started
+    #[serde(default)]
+    pub rows: Option<u16>,
+    #[serde(default)]
+    pub cols: Option<u16>,
+}
+
+/// A key sent by name with `session.send_key`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Key {
+    Enter,
+    #[serde(alias = "escape")]
+    Esc,
+    Tab,
+    ShiftTab,
+    Backspace,
+    Delete,
+    Up,
+    Down,
+    Left,
+    Right,
+    Home,
+    End,
+    PageUp,
+    PageDown,
+    CtrlC,
+    CtrlD,
+    CtrlL,
+    CtrlR,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SendKeyParams {
+    pub session_id: Uuid,
+    pub key: Key,
+    /// Press the key this many times (default 1)
+    #[serde(default)]
+    pub repeat: Option<u32>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -64,11 +456,27 @@ pub struct SessionResizeParams {
     pub cols: u16,
 }
 
+/// Without rows and cols a session starts at its last known size
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SessionRestartParams {
     pub session_id: Uuid,
-    pub rows: u16,
-    pub cols: u16,
+    #[serde(default)]
+    pub rows: Option<u16>,
+    #[serde(default)]
+    pub cols: Option<u16>,
+}
+
+/// Start a session now, or queue it until daemon.max_concurrent_running allows
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EnqueueStartParams {
+    pub session_id: Uuid,
+    #[serde(default)]
+    pub rows: Option<u16>,
+    #[serde(default)]
+    pub cols: Option<u16>,
+    /// Continue the session's conversation with --resume
+    #[serde(default)]
+    pub resume: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -76,8 +484,227 @@ pub struct ForkSessionParams {
     pub session_id: Uuid,
     pub new_name: Option<String>,
     pub group_id: Option<Uuid>,
-    pub rows: u16,
-    pub cols: u16,
+    /// Terminal size for the fork (None = the source session's)
+    #[serde(default)]
+    pub rows: Option<u16>,
+    #[serde(default)]
+    pub cols: Option<u16>,
+    /// Fork into a new worktree on this new branch, made from the source's
+    /// checkout including its uncommitted changes
+    #[serde(default)]
+    pub worktree_branch: Option<String>,
+    /// Where to create that worktree (None = sibling directory `<repo>-<branch>`)
+    #[serde(default)]
+    pub worktree_path: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TranscriptParams {
+    pub session_id: Uuid,
+    /// Only return the last N messages (None = all)
+    #[serde(default)]
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TurnStatsParams {
+    pub session_id: Uuid,
+    /// How many of the most recent turns to include in `turns` (default 50)
+    #[serde(default)]
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionHistoryParams {
+    pub session_id: Uuid,
+    /// Only return the last N entries (None = all)
+    #[serde(default)]
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct InputHistoryParams {
+    /// One session's prompts (None = every session's, each text once)
+    #[serde(default)]
+    pub session_id: Option<Uuid>,
+    /// Only prompts containing this text, ignoring case
+    #[serde(default)]
+    pub query: Option<String>,
+    /// Only return the N most recent prompts (None = all)
+    #[serde(default)]
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TailLogParams {
+    pub session_id: Uuid,
+    /// How much of the end of the log to return (default 64 KB)
+    #[serde(default)]
+    pub max_bytes: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchOutputParams {
+    pub session_id: Uuid,
+    pub query: String,
+    #[serde(default)]
+    pub case_sensitive: bool,
+    /// Most matches to return (default 200)
+    #[serde(default)]
+    pub limit: Option<usize>,
+}
+
+/// Where search.query looks
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SearchSource {
+    /// Session output logs (needs [session_logs] enabled)
+    Output,
+    /// Claude transcripts
+    Transcript,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchQueryParams {
+    pub query: String,
+    /// Treat `query` as a regular expression rather than plain text
+    #[serde(default)]
+    pub regex: bool,
+    #[serde(default)]
+    pub case_sensitive: bool,
+    /// Sources to search (None = all)
+    #[serde(default)]
+    pub sources: Option<Vec<SearchSource>>,
+    /// Sessions to search, live or archived (None = all)
+    #[serde(default)]
+    pub session_ids: Option<Vec<Uuid>>,
+    /// Only match lines written at or after this time
+    #[serde(default)]
+    pub since: Option<DateTime<Utc>>,
+    /// Only match lines written before this time
+    #[serde(default)]
+    pub until: Option<DateTime<Utc>>,
+    /// Lines of context before and after each match (default 2)
+    #[serde(default)]
+    pub context: Option<usize>,
+    /// Most matches to return (default 200)
+    #[serde(default)]
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportOutputParams {
+    pub session_id: Uuid,
+    /// File to write the output to
+    pub dest: String,
+    /// Keep escape sequences instead of exporting plain text
+    #[serde(default)]
+    pub raw: bool,
+}
+
+/// Params for `state.sync`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct StateSyncParams {
+    /// Generation from the previous `state.sync` (None = everything)
+    #[serde(default)]
+    pub since: Option<u64>,
+    /// `tracker_id` from the same call; a different one means `since` came
+    /// from another daemon run and everything is sent
+    #[serde(default)]
+    pub tracker_id: Option<Uuid>,
+}
+
+/// Params for `state.export`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StateExportParams {
+    /// File to write the export to
+    pub path: String,
+    /// Also include each session's output log
+    #[serde(default)]
+    pub include_logs: bool,
+}
+
+/// Params for `state.import`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StateImportParams {
+    /// A file written by state.export
+    pub path: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionReplayParams {
+    pub session_id: Uuid,
+    /// Only replay output logged at or after this time (None = everything kept)
+    #[serde(default)]
+    pub since: Option<DateTime<Utc>>,
+    /// Playback speed, 1.0 = original timing (default), 10.0 = ten times faster
+    #[serde(default)]
+    pub speed: Option<f64>,
+    /// Cap on any single pause in ms, after applying speed (None = no cap)
+    #[serde(default)]
+    pub max_gap_ms: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReplayCancelParams {
+    pub replay_id: Uuid,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct HistoryListParams {
+    /// Only list sessions whose working dir is this directory or inside it
+    #[serde(default)]
+    pub working_dir: Option<String>,
+    /// Only return the N most recently deleted sessions (None = all)
+    #[serde(default)]
+    pub limit: Option<usize>,
+}
+
+/// Queue lane for a prompt; lower variants are dispatched first
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum PromptPriority {
+    /// Typed by a human; preempts everything else
+    #[default]
+    Interactive,
+    Scheduled,
+    Batch,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EnqueuePromptParams {
+    pub session_id: Uuid,
+    pub prompt: String,
+    #[serde(default)]
+    pub priority: PromptPriority,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct QueueListParams {
+    /// Only list prompts for this session (None = the whole queue)
+    #[serde(default)]
+    pub session_id: Option<Uuid>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueueCancelParams {
+    pub id: Uuid,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RecordingListParams {
+    /// Only list recordings of this session (None = all)
+    #[serde(default)]
+    pub session_id: Option<Uuid>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordingExportParams {
+    pub recording_id: Uuid,
+    /// File to write the .cast recording to
+    pub dest: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -88,22 +715,130 @@ pub struct CreateGroupParams {
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MoveToGroupParams {
-    pub session_id: Uuid,
+    /// Single session to move
+    #[serde(default)]
+    pub session_id: Option<Uuid>,
+    /// Several sessions to move at once (e.g. a drag-selection in the GUI)
+    #[serde(default)]
+    pub session_ids: Vec<Uuid>,
+    /// Target group (None = root level)
+    pub group_id: Option<Uuid>,
+}
+
+/// Narrows `session.list`; every field that is set must match
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SessionFilter {
+    /// Sessions carrying all of these tags
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub status: Option<SessionStatus>,
+    #[serde(default)]
     pub group_id: Option<Uuid>,
+    /// Sessions whose working directory is this directory or inside it
+    #[serde(default)]
+    pub working_dir: Option<PathBuf>,
+}
+
+impl SessionFilter {
+    pub fn matches(&self, session: &Session) -> bool {
+        self.tags.iter().all(|tag| session.tags.contains(tag))
+            && self.status.is_none_or(|status| session.status == status)
+            && self.group_id.is_none_or(|id| session.group_id == Some(id))
+            && self
+                .working_dir
+                .as_ref()
+                .is_none_or(|dir| session.working_dir.starts_with(dir))
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SessionListParams {
+    #[serde(default)]
+    pub filter: Option<SessionFilter>,
+}
+
+/// Add or remove one tag on a session
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionTagParams {
+    pub session_id: Uuid,
+    pub tag: String,
 }
 
+/// Absent fields are left unchanged; an explicit null clears a nullable one
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UpdateSessionParams {
     pub session_id: Uuid,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub name: Option<String>,
-    pub group_id: Option<Option<Uuid>>, // None = don't change, Some(None) = remove from group, Some(Some(id)) = set group
+    /// Some(None) = remove from group, Some(Some(id)) = set group
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "double_option"
+    )]
+    pub group_id: Option<Option<Uuid>>,
+    /// Some(None) = use the config default
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "double_option"
+    )]
+    pub spawn_mode: Option<Option<SpawnMode>>,
 }
 
+/// Absent fields are left unchanged; an explicit null clears a nullable one
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UpdateGroupParams {
     pub group_id: Uuid,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub name: Option<String>,
-    pub parent_id: Option<Option<Uuid>>, // None = don't change, Some(None) = make root, Some(Some(id)) = set parent
+    /// Some(None) = make root, Some(Some(id)) = set parent
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "double_option"
+    )]
+    pub parent_id: Option<Option<Uuid>>,
+}
+
+/// `Option<Option<T>>` fields where absent (None) and null (Some(None)) differ;
+/// plain serde reads both as None
+mod double_option {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer, T: Serialize>(
+        value: &Option<Option<T>>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match value {
+            Some(inner) => inner.serialize(serializer),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>, T: Deserialize<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Option<T>>, D::Error> {
+        Option::<T>::deserialize(deserializer).map(Some)
+    }
+}
+
+/// `session.reorder` takes either form; both return the sessions whose group or
+/// order changed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum SessionReorder {
+    Order(SessionOrderParams),
+    Move(ReorderSessionParams),
+}
+
+/// Put sessions in a group in this order, ahead of the group's other sessions
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionOrderParams {
+    /// None = root level; listed sessions from other groups move into it
+    pub group_id: Option<Uuid>,
+    pub session_ids: Vec<Uuid>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -115,6 +850,23 @@ pub struct ReorderSessionParams {
     pub after_session_id: Option<Uuid>,
 }
 
+/// `group.reorder` takes either form; both return the groups whose parent or
+/// order changed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum GroupReorder {
+    Order(GroupOrderParams),
+    Move(ReorderGroupParams),
+}
+
+/// Put groups under a parent in this order, ahead of its other subgroups
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GroupOrderParams {
+    /// None = root level; listed groups from elsewhere move under it
+    pub parent_id: Option<Uuid>,
+    pub group_ids: Vec<Uuid>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ReorderGroupParams {
     pub group_id: Uuid,
@@ -124,39 +876,1782 @@ pub struct ReorderGroupParams {
     pub after_group_id: Option<Uuid>,
 }
 
-// --- Event Data ---
+/// Mute or unmute notifications for exactly one of a session or a group
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SetMuteParams {
+    #[serde(default)]
+    pub session_id: Option<Uuid>,
+    #[serde(default)]
+    pub group_id: Option<Uuid>,
+    pub muted: bool,
+}
 
+/// Watch a group for all of its sessions finishing, or stop with no watch
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct StatusChangedData {
-    pub session_id: Uuid,
-    pub status: SessionStatus,
+pub struct SetCompletionWatchParams {
+    pub group_id: Uuid,
+    #[serde(default)]
+    pub watch: Option<CompletionWatch>,
 }
 
+/// Make a session start after another finishes, or clear that with no parent
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct PtyOutputData {
+pub struct SetDependencyParams {
     pub session_id: Uuid,
-    pub output: String, // base64 encoded
+    #[serde(default)]
+    pub parent_id: Option<Uuid>,
+    /// Prompt to queue once the session has started
+    #[serde(default)]
+    pub prompt: Option<String>,
 }
 
+/// Create a session on a cron schedule
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct PtyExitData {
+pub struct CreateScheduleParams {
+    pub name: String,
+    /// e.g. "0 2 * * *" for 2am every night, in the daemon's local time
+    pub cron: String,
+    /// The session each run creates
+    pub session: CreateSessionParams,
+    /// Prompt to queue once the session has started
+    #[serde(default)]
+    pub prompt: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScheduleIdParams {
+    pub schedule_id: Uuid,
+}
+
+/// Save a reusable prompt
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateSnippetParams {
+    pub name: String,
+    /// May use {{branch}}, {{dir}}, {{name}} and {{group}}, filled in from the
+    /// session it is sent to, and variables passed to `session.send_snippet`
+    pub text: String,
+}
+
+/// Change a snippet; fields left out are kept
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpdateSnippetParams {
+    pub snippet_id: Uuid,
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub text: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnippetIdParams {
+    pub snippet_id: Uuid,
+}
+
+/// Send a snippet to a session as a prompt
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SendSnippetParams {
     pub session_id: Uuid,
-    pub exit_code: Option<i32>,
+    pub snippet_id: Uuid,
+    /// Values for variables of the snippet's own; these also override the
+    /// session's (e.g. a different {{branch}})
+    #[serde(default)]
+    pub vars: HashMap<String, String>,
 }
 
-// --- Results ---
+/// What the daemon's claude binary supports, from `claude.capabilities`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClaudeCapabilities {
+    /// None if no claude binary was found; the other fields are then empty
+    pub path: Option<PathBuf>,
+    /// Output of `claude --version`
+    pub version: Option<String>,
+    /// Long flags listed in `claude --help`, e.g. "--model"
+    pub flags: Vec<String>,
+    /// Values `--permission-mode` accepts
+    pub permission_modes: Vec<String>,
+    /// Names of the binaries in `[claude] binaries`, for CreateSessionParams::claude_binary
+    #[serde(default)]
+    pub binaries: Vec<String>,
+}
 
+/// The project directory whose MCP servers `mcp.*` work on
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct SessionListResult {
-    pub sessions: Vec<Session>,
+pub struct McpListParams {
+    pub dir: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct GroupListResult {
-    pub groups: Vec<Group>,
+pub struct McpAddParams {
+    pub dir: String,
+    pub name: String,
+    /// The server's entry for .mcp.json
+    pub config: Value,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct McpServerParams {
+    pub dir: String,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct McpSetEnabledParams {
+    pub dir: String,
+    pub name: String,
+    pub enabled: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ClaudeBinaryParams {
+    /// A name from `[claude] binaries` (None = the default binary)
+    #[serde(default)]
+    pub binary: Option<String>,
+}
+
+fn default_scan_limit() -> usize {
+    50
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScanProjectsParams {
+    #[serde(default = "default_scan_limit")]
+    pub limit: usize,
+}
+
+/// Add or remove a watch expression on exactly one of a session or a group
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WatchParams {
+    #[serde(default)]
+    pub session_id: Option<Uuid>,
+    #[serde(default)]
+    pub group_id: Option<Uuid>,
+    pub expr: String,
+}
+
+/// Result of `watch.add` / `watch.remove`: the target's watches after the change
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WatchesResult {
+    pub watches: Vec<String>,
+}
+
+/// Where a task found by `session.tasks.list` is defined
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskRunner {
+    Make,
+    Just,
+    /// package.json scripts, run with the package manager whose lockfile is present
+    Package,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaskInfo {
+    pub name: String,
+    pub runner: TaskRunner,
+    /// Shell command that runs the task from the session's working directory
+    pub command: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaskListResult {
+    pub tasks: Vec<TaskInfo>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunTaskParams {
+    pub session_id: Uuid,
+    pub name: String,
+    /// Needed only when several runners define a task with this name
+    #[serde(default)]
+    pub runner: Option<TaskRunner>,
+    /// Run in the background instead of typing it into the session's terminal;
+    /// the outcome lands in the session's history and as session:task_finished
+    #[serde(default)]
+    pub headless: bool,
+}
+
+/// Emitted as session:task_finished when a headless task exits
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaskFinishedData {
+    pub session_id: Uuid,
+    pub name: String,
+    pub command: String,
+    /// None if it was killed or timed out
+    pub exit_code: Option<i32>,
+    /// End of the combined stdout and stderr
+    pub output: String,
+}
+
+/// Emitted as session:approval_requested when Claude shows a permission dialog
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApprovalRequestedData {
+    pub session_id: Uuid,
+    /// Pass to session.approve / session.deny
+    pub approval_id: Uuid,
+    /// Tool name from the PreToolUse hook, else the dialog's title
+    pub tool: String,
+    /// First line of the dialog, e.g. "Bash command"
+    pub title: String,
+    /// What the tool will do, as shown in the dialog
+    pub summary: String,
+    pub question: String,
+    /// File the tool works on, from the PreToolUse hook
+    pub path: Option<String>,
+    /// Bash command, from the PreToolUse hook
+    pub command: Option<String>,
+}
+
+/// Emitted as session:approval_resolved when a permission dialog closes
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApprovalResolvedData {
+    pub session_id: Uuid,
+    pub approval_id: Uuid,
+    /// The answer given with session.approve / session.deny; None if it was
+    /// answered in the terminal
+    pub approved: Option<bool>,
+}
+
+/// Emitted as session:auto_approved when the approval policy answered a
+/// permission dialog; an audit trail of what ran unattended
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AutoApprovedData {
+    pub session_id: Uuid,
+    pub approval_id: Uuid,
+    pub tool: String,
+    pub summary: String,
+    pub path: Option<String>,
+    /// The policy entry that matched, e.g. "command:^cargo test"
+    pub rule: String,
+}
+
+/// Replace the approval policy of exactly one of a session or a group
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PolicySetParams {
+    #[serde(default)]
+    pub session_id: Option<Uuid>,
+    #[serde(default)]
+    pub group_id: Option<Uuid>,
+    pub policy: ApprovalPolicy,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionContinueParams {
+    pub session_id: Uuid,
+    /// Prompt to send, "continue" when absent
+    #[serde(default)]
+    pub prompt: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApprovalParams {
+    pub session_id: Uuid,
+    /// From session:approval_requested; stale answers are rejected
+    pub approval_id: Uuid,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BootstrapParams {
+    /// Run the npm install command if claude isn't found
+    #[serde(default)]
+    pub install_claude: bool,
+    /// Name of the starter group created when there are no groups yet
+    #[serde(default)]
+    pub starter_group: Option<String>,
+}
+
+/// What happens to running sessions when the daemon shuts down
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PtyPolicy {
+    /// Kill every session's process and mark it stopped
+    #[default]
+    Kill,
+    /// Leave processes running; they lose their terminal when the daemon exits
+    Detach,
+}
+
+/// What the daemon does at startup with claude processes left running by a previous run
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OrphanPolicy {
+    #[default]
+    Kill,
+    /// Keep tracking the process; it is killed when its session is restarted
+    Adopt,
+}
+
+/// Params for `daemon.shutdown` and `daemon.restart`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ShutdownParams {
+    #[serde(default)]
+    pub pty_policy: PtyPolicy,
+    /// Start a fresh daemon process after shutting down (set by daemon.restart)
+    #[serde(default)]
+    pub restart: bool,
+}
+
+// --- Event Data ---
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StatusChangedData {
+    pub session_id: Uuid,
+    pub status: SessionStatus,
+    /// The session's new `status_detail`, when the daemon knows why
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+/// Data for `session:idle_timeout` - a session went daemon.idle_timeout_mins
+/// without output or input
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IdleTimeoutData {
+    pub session_id: Uuid,
+    /// Seconds since the last output or input
+    pub idle_secs: u64,
+    /// The claude process was stopped (daemon.idle_stop)
+    pub stopped: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GitChangedData {
+    pub session_id: Uuid,
+    pub git: Option<GitStatus>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionMovedData {
+    pub session: Session,
+    pub from_group_id: Option<Uuid>,
+}
+
+/// Emitted as session:needs_attention when a session enters Waiting or Error,
+/// or one of its watch expressions becomes true
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NeedsAttentionData {
+    pub session_id: Uuid,
+    pub name: String,
+    pub group_id: Option<Uuid>,
+    pub status: SessionStatus,
+    /// Notifications are disabled, or the session or its group is muted
+    pub muted: bool,
+    /// The watch expression that fired (None for a status change)
+    #[serde(default)]
+    pub watch: Option<String>,
+}
+
+/// Emitted as schedule:fired when a schedule has created and started its session
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScheduleFiredData {
+    pub schedule_id: Uuid,
+    pub name: String,
+    pub session_id: Uuid,
+}
+
+/// Emitted as schedule:finished when a scheduled run's session has answered its
+/// prompt (Idle), or stopped or hit an error
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScheduleFinishedData {
+    pub schedule_id: Uuid,
+    pub name: String,
+    pub session_id: Uuid,
+    pub status: SessionStatus,
+    /// Notifications are disabled, or the session or its group is muted
+    pub muted: bool,
+}
+
+/// How one session of a completed group ended
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SessionOutcome {
+    pub session_id: Uuid,
+    pub name: String,
+    /// Idle, Stopped or Error
+    pub status: SessionStatus,
+}
+
+/// Emitted as group:completed when every session under a group with a
+/// completion watch has finished; also the body POSTed to its webhook
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GroupCompletedData {
+    pub group_id: Uuid,
+    pub name: String,
+    /// The group's sessions and those of its subgroups
+    pub sessions: Vec<SessionOutcome>,
+    /// Notifications are disabled or the group is muted
+    pub muted: bool,
+}
+
+/// Emitted as daemon:config_reloaded after config.toml changes on disk
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConfigReloadedData {
+    /// Why the new file was rejected (the previous config stays active)
+    pub error: Option<String>,
+}
+
+/// Params for `daemon.keep_awake`; without `enabled` it only reports the status
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct KeepAwakeParams {
+    #[serde(default)]
+    pub enabled: Option<bool>,
+}
+
+/// Result of `daemon.keep_awake`, also emitted as daemon:keep_awake when it changes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeepAwakeStatus {
+    /// The daemon.keep_awake config option
+    pub enabled: bool,
+    /// Whether the host is currently kept from sleeping
+    pub active: bool,
+}
+
+/// A stage of the `daemon.bootstrap` first-run flow
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BootstrapStep {
+    CheckClaude,
+    InstallClaude,
+    InstallHooks,
+    CreateGroup,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BootstrapStepState {
+    Running,
+    Done,
+    Skipped,
+    Failed,
+}
+
+/// Emitted as daemon:bootstrap_progress as each bootstrap step starts and finishes
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BootstrapProgressData {
+    pub step: BootstrapStep,
+    pub state: BootstrapStepState,
+    pub message: Option<String>,
+}
+
+/// Emitted as session:turn_completed, and also as session:slow_turn when the
+/// turn took longer than `daemon.slow_turn_secs`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TurnMetricsData {
+    pub session_id: Uuid,
+    #[serde(flatten)]
+    pub metrics: TurnMetrics,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PtyOutputData {
+    pub session_id: Uuid,
+    pub output: String, // base64 encoded
+}
+
+/// Emitted as replay:output while a session.replay plays back logged output
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReplayOutputData {
+    pub replay_id: Uuid,
+    pub session_id: Uuid,
+    pub output: String, // base64 encoded
+}
+
+/// Emitted as replay:finished when a replay ends or is cancelled
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReplayFinishedData {
+    pub replay_id: Uuid,
+    pub session_id: Uuid,
+    pub cancelled: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PtyExitData {
+    pub session_id: Uuid,
+    pub exit_code: Option<i32>,
+}
+
+/// The claude binary sessions run, from `daemon.claude_info`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClaudeInfo {
+    /// None if no claude binary was found
+    pub path: Option<PathBuf>,
+    /// From `claude --version`, e.g. "2.0.1"
+    pub version: Option<String>,
+    /// daemon.min_claude_version
+    pub min_version: Option<String>,
+    /// Why sessions are likely to fail to start, if there's a reason
+    pub warning: Option<String>,
+    pub checked_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpdateStatusData {
+    pub update: Option<UpdateInfo>,
+    pub hook_script_current: bool,
+}
+
+// --- Results ---
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionListResult {
+    pub sessions: Vec<Session>,
+}
+
+/// Result of `summary.get`: what a widget or phone polling the daemon shows
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SummaryResult {
+    /// Most recently active first
+    pub sessions: Vec<SessionSummary>,
+}
+
+/// A session in `summary.get`, without its output or settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionSummary {
+    pub id: Uuid,
+    pub name: String,
+    pub status: SessionStatus,
+    pub unread_bytes: u64,
+    pub last_activity: DateTime<Utc>,
+}
+
+/// Result of `state.sync`. Apply the removals, then replace the listed
+/// sessions and groups; pass `generation` as `since` on the next call.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StateSyncResult {
+    pub generation: u64,
+    /// New on every daemon start; send it back with `generation`
+    #[serde(default)]
+    pub tracker_id: Uuid,
+    /// Everything is listed (no `since`, or one this daemon can't answer), so
+    /// whatever the client has that isn't listed is gone
+    pub full: bool,
+    /// Sessions created or changed since `since`
+    pub sessions: Vec<Session>,
+    /// Groups created or changed since `since`
+    pub groups: Vec<Group>,
+    pub removed_sessions: Vec<Uuid>,
+    pub removed_groups: Vec<Uuid>,
+    /// Of every group, since they change with their sessions
+    pub statuses: Vec<GroupStatus>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GroupListResult {
+    pub groups: Vec<Group>,
+    /// One per group; empty from daemons before group statuses
+    #[serde(default)]
+    pub statuses: Vec<GroupStatus>,
+}
+
+/// Derived status of the sessions in a group and its subgroups, emitted as
+/// group:status_changed when it changes
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GroupStatus {
+    pub group_id: Uuid,
+    /// The most urgent of their statuses: Error, Waiting, Running, Starting,
+    /// Queued, Idle, then Stopped (also for a group without sessions)
+    pub status: SessionStatus,
+    pub total: u32,
+    /// Sessions by status; statuses no session has are left out
+    pub counts: HashMap<SessionStatus, u32>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SessionCreatedResult {
     pub session: Session,
 }
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GroupCreatedResult {
+    pub group: Group,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScheduleListResult {
+    pub schedules: Vec<Schedule>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnippetListResult {
+    pub snippets: Vec<Snippet>,
+}
+
+/// Result of `session.send_snippet`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SendSnippetResult {
+    /// The prompt as sent, with its variables filled in
+    pub text: String,
+}
+
+/// Where projects.scan found a directory
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProjectSource {
+    /// Claude Code's project registry (~/.claude/projects)
+    Claude,
+    /// The working directory of a session
+    Session,
+    /// The git repository around one of the others
+    Git,
+}
+
+/// A suggested working directory for a new session
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProjectCandidate {
+    pub path: PathBuf,
+    pub last_used: DateTime<Utc>,
+    pub source: ProjectSource,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScanProjectsResult {
+    /// Most recently used first
+    pub projects: Vec<ProjectCandidate>,
+}
+
+/// An MCP server in a project's .mcp.json
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct McpServer {
+    pub name: String,
+    /// As written in .mcp.json, e.g. `{"command": "npx", "args": [...]}` or
+    /// `{"type": "http", "url": "..."}`
+    pub config: Value,
+    /// Approved in the project's .claude/settings.local.json, so sessions start it
+    pub enabled: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct McpListResult {
+    pub servers: Vec<McpServer>,
+}
+
+/// Result of `group.delete`: what it affected, or would have with dry_run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GroupDeleteResult {
+    /// The group and, with cascade, its subgroups
+    pub groups: Vec<Uuid>,
+    /// Sessions deleted (cascade) or moved to the top level (reparent)
+    pub sessions: Vec<Uuid>,
+    /// Those of the sessions that were running and got stopped
+    pub stopped: Vec<Uuid>,
+    pub dry_run: bool,
+}
+
+/// Result of methods with nothing to report beyond having worked
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SuccessResult {
+    pub success: bool,
+}
+
+/// Result of `session.stop`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StopSessionResult {
+    pub success: bool,
+    pub outcome: StopOutcome,
+}
+
+/// Result of `session.input`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionInputResult {
+    pub success: bool,
+    /// The session was stopped and auto_start_on_input restarted it
+    pub restarted: bool,
+}
+
+/// Result of `session.attach`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionAttachResult {
+    pub session: Session,
+    /// Base64 output the session wrote while detached; with `from_screen`, its
+    /// current screen instead (reset the terminal before writing it)
+    pub output: String,
+    pub from_screen: bool,
+}
+
+/// Result of `daemon.ping`, `daemon.shutdown` and `daemon.restart`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StatusResult {
+    pub status: String,
+}
+
+/// Result of `daemon.bootstrap`; progress follows as events
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BootstrapStartedResult {
+    pub started: bool,
+}
+
+/// Result of `session.replay`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReplayStartedResult {
+    pub replay_id: Uuid,
+}
+
+/// Result of `queue.cancel` and `session.replay_cancel`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CancelledResult {
+    /// Whether there was anything to cancel
+    pub cancelled: bool,
+}
+
+/// Semantic color (CSS hex) for each session status
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StatusColors {
+    pub running: String,
+    pub waiting: String,
+    pub idle: String,
+    pub error: String,
+    pub queued: String,
+    pub starting: String,
+    pub stopped: String,
+}
+
+/// Result of `ui.theme` - shared rendering settings for all clients
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ThemeResult {
+    pub theme: String,
+    pub font_family: String,
+    pub font_size: u16,
+    /// Name of the status palette in use ("default" or "color_blind")
+    pub palette: String,
+    pub status_colors: StatusColors,
+}
+
+/// Desktop notification settings, stored in the `[notifications]` config section
+/// Also the result of `notifications.get` and `notifications.set_mute`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NotificationSettings {
+    pub enabled: bool,
+    pub muted_sessions: Vec<Uuid>,
+    pub muted_groups: Vec<Uuid>,
+}
+
+impl Default for NotificationSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            muted_sessions: Vec::new(),
+            muted_groups: Vec::new(),
+        }
+    }
+}
+
+impl NotificationSettings {
+    pub fn is_muted(&self, session_id: Uuid, group_id: Option<Uuid>) -> bool {
+        !self.enabled
+            || self.muted_sessions.contains(&session_id)
+            || group_id.is_some_and(|g| self.muted_groups.contains(&g))
+    }
+}
+
+/// Outcome of `daemon.bootstrap`, emitted as daemon:bootstrap_complete
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BootstrapResult {
+    /// Resolved claude binary (None if still not installed)
+    pub claude_path: Option<String>,
+    /// Command to offer the user when claude is missing
+    pub install_command: String,
+    pub hooks_installed: bool,
+    /// Starter group, if one was created
+    pub group: Option<Group>,
+}
+
+/// A finished turn from the session timeline
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TurnRecord {
+    pub ts: DateTime<Utc>,
+    #[serde(flatten)]
+    pub metrics: TurnMetrics,
+}
+
+/// Result of `session.turn_stats` - latency summary over all recorded turns
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TurnStats {
+    pub count: usize,
+    pub avg_first_output_ms: Option<u64>,
+    pub avg_duration_ms: Option<u64>,
+    pub p95_duration_ms: Option<u64>,
+    /// Most recent turns, oldest first
+    pub turns: Vec<TurnRecord>,
+}
+
+/// Activity totals for a session's lifetime
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SessionUsage {
+    pub prompts: usize,
+    pub turns: usize,
+    /// Time spent answering prompts
+    pub busy_ms: u64,
+    /// Input tokens including cache reads and writes (from the transcript)
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+}
+
+/// Compact record of a deleted session, from `history.list`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchivedSession {
+    pub id: Uuid,
+    pub name: String,
+    pub working_dir: PathBuf,
+    pub claude_session_id: Option<String>,
+    /// Claude transcript at the time of deletion (it may have been cleaned up since)
+    pub transcript_path: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub last_activity: DateTime<Utc>,
+    pub deleted_at: DateTime<Utc>,
+    pub usage: SessionUsage,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DaemonLogsTailParams {
+    /// Most records to return (default 200)
+    #[serde(default)]
+    pub lines: Option<usize>,
+    /// Only records at this level or more severe ("warn" = warnings and errors)
+    #[serde(default)]
+    pub level: Option<String>,
+}
+
+/// One line of the daemon's JSON log
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogRecord {
+    pub timestamp: DateTime<Utc>,
+    /// "ERROR", "WARN", "INFO", "DEBUG" or "TRACE"
+    pub level: String,
+    /// Module that logged it, e.g. "claude_master_daemon::ipc"
+    pub target: String,
+    pub message: String,
+    /// Structured fields other than the message
+    #[serde(default, skip_serializing_if = "serde_json::Map::is_empty")]
+    pub fields: serde_json::Map<String, Value>,
+}
+
+/// Result of `daemon.logs.tail`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DaemonLogsTailResult {
+    pub path: String,
+    /// Oldest first
+    pub records: Vec<LogRecord>,
+    /// Older matching records exist beyond what was returned
+    pub truncated: bool,
+}
+
+/// Result of `session.tail_log`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TailLogResult {
+    pub path: String,
+    /// Raw PTY output, base64 encoded like pty:output
+    pub output: String,
+    /// Older output exists beyond what was returned
+    pub truncated: bool,
+}
+
+/// Result of `session.get_screen` - what the session's terminal shows now
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionScreen {
+    pub session_id: Uuid,
+    pub rows: u16,
+    pub cols: u16,
+    /// Screen text, one line per row with trailing blanks trimmed
+    pub contents: String,
+    /// Escape sequences that redraw the screen, cursor and input modes on a
+    /// blank terminal, base64 encoded like pty:output
+    pub formatted: String,
+    /// Zero-based
+    pub cursor_row: u16,
+    pub cursor_col: u16,
+    pub cursor_hidden: bool,
+    pub alternate_screen: bool,
+    /// The process has exited; this is the last screen it drew
+    pub exited: bool,
+}
+
+/// A line of a session's logged output matching session.search_output
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutputMatch {
+    /// Line number in the plain-text output, from 1
+    pub line: usize,
+    pub text: String,
+}
+
+/// A match from search.query
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchHit {
+    pub session_id: Uuid,
+    pub session_name: String,
+    /// The session has been deleted and was found through the archive
+    pub archived: bool,
+    pub source: SearchSource,
+    /// Line number of the match within the source, from 1
+    pub offset: usize,
+    /// When the line was written, if known
+    pub at: Option<DateTime<Utc>>,
+    pub text: String,
+    pub before: Vec<String>,
+    pub after: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchQueryResult {
+    pub hits: Vec<SearchHit>,
+    /// More lines matched than were returned
+    pub truncated: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchOutputResult {
+    pub matches: Vec<OutputMatch>,
+    /// More lines matched than were returned
+    pub truncated: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportOutputResult {
+    pub path: String,
+    pub bytes: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StateExportResult {
+    pub path: String,
+    pub sessions: usize,
+    pub groups: usize,
+    /// Sessions whose output log was included
+    pub logs: usize,
+    pub bytes: usize,
+}
+
+/// Result of `state.import`; sessions and groups whose id already exists are
+/// left as they are and counted in `skipped`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StateImportResult {
+    pub sessions: usize,
+    pub groups: usize,
+    /// Output logs restored (ones for sessions that already had a log are not)
+    pub logs: usize,
+    pub skipped: usize,
+}
+
+/// A prompt waiting for its session to be ready and a free slot
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueuedPrompt {
+    pub id: Uuid,
+    pub session_id: Uuid,
+    pub prompt: String,
+    pub priority: PromptPriority,
+    pub enqueued_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OrphanAction {
+    Killed,
+    Adopted,
+    KillFailed,
+}
+
+/// A claude process found at startup that outlived the daemon that spawned it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OrphanInfo {
+    pub session_id: Uuid,
+    pub name: String,
+    pub pid: u32,
+    pub started_at: DateTime<Utc>,
+    pub action: OrphanAction,
+    /// Whether the process is still running
+    pub alive: bool,
+}
+
+/// An asciinema v2 recording of a session's terminal output
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordingInfo {
+    pub id: Uuid,
+    pub session_id: Uuid,
+    /// The .cast file, written as output arrives
+    pub path: String,
+    pub started_at: DateTime<Utc>,
+    /// Whether output is still being recorded
+    pub active: bool,
+}
+
+/// Result of the most recent update check
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UpdateInfo {
+    pub latest_version: String,
+    pub update_available: bool,
+    pub release_url: Option<String>,
+    pub checked_at: DateTime<Utc>,
+}
+
+/// Result of `daemon.diagnostics`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiagnosticsResult {
+    /// Version of the running daemon
+    pub version: String,
+    /// Whether the installed hook script matches the one embedded in the daemon
+    pub hook_script_current: bool,
+    /// Latest update check (None if update checks are disabled or haven't run)
+    pub update: Option<UpdateInfo>,
+    /// Most recent recorded panic (requires crash_reports in config)
+    pub last_crash: Option<CrashReport>,
+    /// Last check of the claude binary (None until the startup check is done)
+    #[serde(default)]
+    pub claude: Option<ClaudeInfo>,
+}
+
+/// Environment variable the GUI sets on the daemon it launches, so it can
+/// recognise its own daemon in `daemon.hello`
+pub const INSTANCE_ID_ENV: &str = "CLAUDE_MASTER_INSTANCE_ID";
+
+/// Environment variable that makes the GUI connect to a daemon started
+/// elsewhere (e.g. by systemd) instead of launching its own
+pub const EXTERNAL_DAEMON_ENV: &str = "CLAUDE_MASTER_EXTERNAL_DAEMON";
+
+/// Environment variables that make the GUI attach to a daemon on another
+/// machine through a tunnel to its tunnel port (`host:port`), with the token
+/// from that daemon's tunnel.token
+pub const REMOTE_DAEMON_ENV: &str = "CLAUDE_MASTER_REMOTE";
+pub const REMOTE_TOKEN_ENV: &str = "CLAUDE_MASTER_REMOTE_TOKEN";
+
+/// Bumped when an existing method or event changes incompatibly; new methods
+/// are announced through `METHODS` instead
+/// 2: session.reorder and group.reorder return every session or group they moved
+pub const PROTOCOL_VERSION: u32 = 2;
+
+/// Every IPC method this daemon answers, reported by `daemon.hello`
+pub const METHODS: &[&str] = &[
+    "daemon.ping",
+    "daemon.shutdown",
+    "daemon.restart",
+    "daemon.hello",
+    "daemon.auth",
+    "daemon.diagnostics",
+    "daemon.stats",
+    "daemon.metrics",
+    "daemon.logs.tail",
+    "daemon.network_audit",
+    "daemon.claude_info",
+    "daemon.orphans",
+    "daemon.bootstrap",
+    "daemon.keep_awake",
+    "daemon.clients",
+    "client.identify",
+    "session.view",
+    "debug.tap",
+    "events.subscribe",
+    "events.since",
+    "ui.theme",
+    "notifications.get",
+    "notifications.set_mute",
+    "session.list",
+    "session.create",
+    "session.create_worktree",
+    "session.stop",
+    "session.delete",
+    "session.update",
+    "session.add_tag",
+    "session.remove_tag",
+    "session.tasks.list",
+    "session.tasks.run",
+    "watch.add",
+    "watch.remove",
+    "policy.set",
+    "session.move",
+    "session.transcript",
+    "session.turn_stats",
+    "session.stats",
+    "queue.enqueue",
+    "queue.list",
+    "queue.cancel",
+    "session.history",
+    "session.input_history",
+    "history.list",
+    "search.query",
+    "session.tail_log",
+    "session.get_screen",
+    "session.search_output",
+    "session.export_output",
+    "session.replay",
+    "session.replay_cancel",
+    "session.recording.start",
+    "session.recording.stop",
+    "session.recording.list",
+    "session.recording.export",
+    "session.input",
+    "session.interrupt",
+    "session.send_key",
+    "session.detach",
+    "session.attach",
+    "session.mark_viewed",
+    "session.continue",
+    "session.approve",
+    "session.deny",
+    "session.resize",
+    "session.restart",
+    "session.enqueue_start",
+    "session.set_dependency",
+    "session.fork",
+    "schedule.create",
+    "schedule.list",
+    "schedule.delete",
+    "snippet.list",
+    "snippet.create",
+    "snippet.update",
+    "snippet.delete",
+    "session.send_snippet",
+    "projects.scan",
+    "claude.capabilities",
+    "mcp.list",
+    "mcp.add",
+    "mcp.remove",
+    "mcp.set_enabled",
+    "tree.snapshot",
+    "summary.get",
+    "group.list",
+    "group.create",
+    "group.delete",
+    "group.update",
+    "session.reorder",
+    "group.reorder",
+    "group.set_completion_watch",
+    "state.export",
+    "state.import",
+    "state.sync",
+];
+
+/// Protocol behaviour beyond the method list, reported by `daemon.hello`
+pub const CAPABILITIES: &[&str] = &[
+    // Results over MAX_FRAME_BYTES arrive as ResponseChunk lines
+    "chunked_responses",
+    // session.list accepts a SessionFilter
+    "session_list_filter",
+    // Slow clients get pty:resync instead of silently losing pty:output
+    "pty_resync",
+];
+
+/// What kind of program a connection is, as it told `client.identify`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ClientKind {
+    Gui,
+    Cli,
+    Web,
+    Mcp,
+    /// Hasn't identified itself
+    #[default]
+    Other,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ClientIdentifyParams {
+    #[serde(default)]
+    pub kind: ClientKind,
+    /// Shown to other clients, e.g. the host or user
+    #[serde(default)]
+    pub name: Option<String>,
+}
+
+/// Params of `session.view` - the session whose terminal the calling client
+/// shows (None when it shows none)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SessionViewParams {
+    #[serde(default)]
+    pub session_id: Option<Uuid>,
+}
+
+/// A connection to the daemon, from `daemon.clients` (`client.identify` and
+/// `session.view` return the caller's own)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClientInfo {
+    /// Unique for the daemon's lifetime
+    pub client_id: u64,
+    pub kind: ClientKind,
+    pub name: Option<String>,
+    pub connected_at: DateTime<Utc>,
+    /// Session whose terminal it shows
+    pub viewing: Option<Uuid>,
+    /// When it last sent input to a session
+    pub last_input_at: Option<DateTime<Utc>>,
+}
+
+/// Result of `daemon.clients`, by client id
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClientsResult {
+    pub clients: Vec<ClientInfo>,
+}
+
+/// Data of `session:presence`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PresenceData {
+    pub session_id: Uuid,
+    /// Clients showing the session's terminal
+    pub viewers: Vec<ClientInfo>,
+    /// The client whose input to the session prompted this event, if any
+    pub typing: Option<u64>,
+}
+
+/// Result of `daemon.hello` - identifies the daemon answering on the socket
+/// and what it supports
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HelloResult {
+    pub version: String,
+    pub pid: u32,
+    /// From CLAUDE_MASTER_INSTANCE_ID (None for a daemon started by hand)
+    pub instance_id: Option<Uuid>,
+    pub started_at: DateTime<Utc>,
+    /// 0 for daemons from before protocol versioning
+    #[serde(default)]
+    pub protocol_version: u32,
+    /// Empty for daemons from before protocol versioning (support unknown)
+    #[serde(default)]
+    pub methods: Vec<String>,
+    #[serde(default)]
+    pub capabilities: Vec<String>,
+    /// Profile the daemon was started with (None is the default profile)
+    #[serde(default)]
+    pub profile: Option<String>,
+    /// Identifies the event journal; `seq` numbers from another one mean
+    /// nothing to this daemon (None from daemons before the journal)
+    #[serde(default)]
+    pub journal_id: Option<Uuid>,
+}
+
+impl HelloResult {
+    /// Whether the daemon answers `method` (assumed when it didn't say)
+    pub fn supports(&self, method: &str) -> bool {
+        self.methods.is_empty() || self.methods.iter().any(|m| m == method)
+    }
+}
+
+/// Whether the daemon on the socket is the one the GUI launched
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum DaemonIdentity {
+    /// The GUI's own daemon
+    Owned { hello: HelloResult },
+    /// Another daemon the user chose to keep using
+    Attached { hello: Option<HelloResult> },
+    /// Another daemon owns the socket (no hello = too old to answer daemon.hello)
+    Conflict {
+        expected: Uuid,
+        hello: Option<HelloResult>,
+    },
+}
+
+/// Profiles the GUI can switch between
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ProfileList {
+    /// The profile the GUI is using (None is the default profile)
+    pub current: Option<String>,
+    /// Named profiles with a data directory, sorted
+    pub profiles: Vec<String>,
+}
+
+/// Who starts the daemon the GUI talks to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DaemonMode {
+    /// The GUI installs, starts and restarts its own daemon
+    Managed,
+    /// The daemon is started outside the GUI, which only connects to it
+    External,
+}
+
+/// The GUI's daemon mode and the daemon it is connected to
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DaemonModeInfo {
+    pub mode: DaemonMode,
+    pub connected: bool,
+    /// From daemon.hello; None while disconnected
+    pub pid: Option<u32>,
+    pub version: Option<String>,
+}
+
+/// How to resolve a `DaemonIdentity::Conflict`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictChoice {
+    /// Shut the other daemon down and start the GUI's own
+    Takeover,
+    /// Keep using the other daemon for this run of the GUI
+    Attach,
+}
+
+/// Params for `events.subscribe`, which applies to the connection that sends it
+/// Connections start out subscribed to everything. The session and group filters
+/// only narrow events that name a session; other events pass on their name alone.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EventSubscription {
+    /// Event names to forward; a trailing `*` matches a prefix ("session:*"),
+    /// and an empty list forwards nothing
+    #[serde(default = "all_events")]
+    pub events: Vec<String>,
+    /// Only events for these sessions (empty = any session)
+    #[serde(default)]
+    pub session_ids: Vec<Uuid>,
+    /// Only events for sessions in these groups (empty = any group)
+    #[serde(default)]
+    pub group_ids: Vec<Uuid>,
+}
+
+fn all_events() -> Vec<String> {
+    vec!["*".to_string()]
+}
+
+impl Default for EventSubscription {
+    fn default() -> Self {
+        Self {
+            events: all_events(),
+            session_ids: Vec::new(),
+            group_ids: Vec::new(),
+        }
+    }
+}
+
+impl EventSubscription {
+    pub fn wants_event(&self, event: &str) -> bool {
+        self.events
+            .iter()
+            .any(|pattern| match pattern.strip_suffix('*') {
+                Some(prefix) => event.starts_with(prefix),
+                None => event == pattern,
+            })
+    }
+
+    /// Whether events about a session in `group_id` pass the filters
+    pub fn wants_session(&self, session_id: Uuid, group_id: Option<Uuid>) -> bool {
+        (self.session_ids.is_empty() || self.session_ids.contains(&session_id))
+            && (self.group_ids.is_empty()
+                || group_id.is_some_and(|id| self.group_ids.contains(&id)))
+    }
+
+    /// Nothing filtered out, so matching can be skipped
+    pub fn is_all(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// Params for `events.since`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EventsSinceParams {
+    /// Last sequence number the client saw
+    pub seq: u64,
+}
+
+/// Result of `events.since`: the journaled events after `seq`, oldest first
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EventsSinceResult {
+    pub events: Vec<SequencedEvent>,
+    /// Sequence number of the newest journaled event (0 if there are none)
+    pub latest: u64,
+    /// False if events after `seq` are no longer in the journal (or `seq` is
+    /// from another journal), so the client has to reload everything instead
+    pub complete: bool,
+}
+
+/// Params for `daemon.auth`, which applies to the connection that sends it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuthParams {
+    /// Contents of the daemon.token file, or a token from the [auth] config
+    pub token: String,
+}
+
+/// What a token lets a client do, least to most
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    /// Read sessions, output and state, nothing that changes them
+    Viewer,
+    /// Run sessions: create, type into, approve, stop, queue and organize them
+    Operator,
+    /// Everything, including the daemon itself, its config and debug tools
+    Admin,
+}
+
+/// Result of `daemon.auth`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuthResult {
+    pub authenticated: bool,
+    pub role: Role,
+}
+
+/// Params for `debug.tap`, which applies to the connection that sends it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DebugTapParams {
+    pub enabled: bool,
+    /// Also mirror pty:output and replay:output events (terminal contents)
+    #[serde(default)]
+    pub include_output: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TapDirection {
+    Request,
+    Response,
+    Event,
+}
+
+/// Data for `debug:tap` - one message seen by the daemon, with secrets redacted
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TapRecord {
+    /// Connection the request or response belongs to (None for events)
+    pub client_id: Option<u64>,
+    pub direction: TapDirection,
+    pub at: DateTime<Utc>,
+    pub message: Value,
+}
+
+/// PTY reader counters for one session, from `daemon.stats`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionFlowStats {
+    pub session_id: Uuid,
+    pub bytes_read: u64,
+    /// Times the reader stopped because the output queue was full
+    pub pauses: u64,
+    /// Total time spent paused
+    pub paused_ms: u64,
+    /// Paused right now
+    pub paused: bool,
+}
+
+/// CPU and memory of a session's claude process and everything it started
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionResourceUsage {
+    pub session_id: Uuid,
+    pub pid: u32,
+    /// Summed over the processes, so it can exceed 100 on several cores
+    pub cpu_percent: f32,
+    /// Resident memory of the processes together
+    pub rss_bytes: u64,
+    /// claude plus the tools, shells and servers it is running
+    pub processes: usize,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SessionStatsParams {
+    /// Only this session (None = every running session)
+    #[serde(default)]
+    pub session_id: Option<Uuid>,
+}
+
+/// Result of `session.stats`, heaviest memory user first
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionStatsResult {
+    pub sessions: Vec<SessionResourceUsage>,
+}
+
+/// Data of `session:resource_usage`, every running session, heaviest first
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResourceUsageData {
+    pub sessions: Vec<SessionResourceUsage>,
+}
+
+/// Data of `session:unread`, sent when a session's unread_bytes leaves or
+/// returns to 0 (not for every chunk in between)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UnreadData {
+    pub session_id: Uuid,
+    pub unread_bytes: u64,
+}
+
+/// Result of `daemon.stats`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DaemonStats {
+    /// PTY output queue size and current fill, in chunks of up to 4 KB
+    pub output_queue_capacity: usize,
+    pub output_queue_len: usize,
+    pub clients: usize,
+    /// Times a client fell behind the event stream
+    pub client_lags: u64,
+    /// Events clients missed by falling behind
+    pub events_dropped: u64,
+    pub sessions: Vec<SessionFlowStats>,
+}
+
+/// How `daemon.metrics` also renders its counters
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MetricsFormat {
+    /// The structured counters only
+    #[default]
+    Json,
+    /// Also the Prometheus text exposition format, in `prometheus`
+    Prometheus,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MetricsParams {
+    #[serde(default)]
+    pub format: MetricsFormat,
+}
+
+/// Times sessions went from one status to another, from `daemon.metrics`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StatusTransitionCount {
+    pub from: SessionStatus,
+    pub to: SessionStatus,
+    pub count: u64,
+}
+
+/// Result of `daemon.metrics`; counters run from the daemon's start
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DaemonMetrics {
+    pub uptime_secs: i64,
+    pub sessions_created: u64,
+    pub pty_output_bytes: u64,
+    /// Events clients missed by falling behind
+    pub events_dropped: u64,
+    /// IPC requests by method (unknown methods count as "other")
+    pub requests: BTreeMap<String, u64>,
+    pub status_transitions: Vec<StatusTransitionCount>,
+    /// The same counters in Prometheus text format (with format = "prometheus")
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prometheus: Option<String>,
+}
+
+/// A daemon subsystem that can open outbound network connections
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EgressSubsystem {
+    pub name: String,
+    pub description: String,
+    /// Where it connects (a URL, or the command that does the connecting)
+    pub destination: String,
+    /// Turned on in config (or available on request)
+    pub enabled: bool,
+    /// Whether daemon.local_only blocks it
+    pub gated: bool,
+    /// Whether it may connect right now
+    pub allowed: bool,
+}
+
+/// Result of `daemon.network_audit`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NetworkAuditResult {
+    pub local_only: bool,
+    pub subsystems: Vec<EgressSubsystem>,
+}
+
+/// A daemon panic captured by the crash handler
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CrashReport {
+    pub timestamp: DateTime<Utc>,
+    pub message: String,
+    /// Source location of the panic (file:line:col)
+    pub location: Option<String>,
+    pub thread: Option<String>,
+    pub backtrace: String,
+    /// Whether the panic ended the daemon. One on the main thread does; one in
+    /// a spawned task or thread only ends that task, and the daemon carries on.
+    #[serde(default)]
+    pub fatal: bool,
+}
+
+/// A session within `tree.snapshot` - just enough to render a menu entry
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TreeSession {
+    pub id: Uuid,
+    pub name: String,
+    pub status: SessionStatus,
+}
+
+/// A group within `tree.snapshot`, with its subgroups and sessions nested in order
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TreeGroup {
+    pub id: Uuid,
+    pub name: String,
+    pub collapsed: bool,
+    pub groups: Vec<TreeGroup>,
+    pub sessions: Vec<TreeSession>,
+}
+
+/// Result of `tree.snapshot` - the whole group/session hierarchy in one payload
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TreeSnapshot {
+    /// Top-level groups
+    pub groups: Vec<TreeGroup>,
+    /// Sessions not in any group
+    pub sessions: Vec<TreeSession>,
+}
+
+/// Where Claude is in its current turn, derived from the transcript
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TranscriptState {
+    /// Processing a prompt or tool result
+    Working,
+    /// Requested a tool call (may be awaiting approval)
+    ToolUse,
+    /// Finished its turn and is waiting for the next prompt
+    Complete,
+}
+
+/// A user or assistant message from a Claude transcript
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TranscriptMessage {
+    pub role: String,
+    pub text: String,
+    /// Names of tools the assistant called in this message
+    pub tool_calls: Vec<String>,
+    /// Whether this (user) message carries tool results rather than a prompt
+    pub tool_result: bool,
+    pub timestamp: Option<DateTime<Utc>>,
+}
+
+/// Result of `session.transcript`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TranscriptResult {
+    /// Transcript file, if one was found for the session's claude_session_id
+    pub path: Option<String>,
+    pub state: Option<TranscriptState>,
+    pub messages: Vec<TranscriptMessage>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_session_filter() {
+        let mut session = Session::new("api".to_string(), PathBuf::from("/work/api/src"), None);
+        session.tags = vec!["backend".to_string(), "urgent".to_string()];
+
+        assert!(SessionFilter::default().matches(&session));
+        let filter = SessionFilter {
+            tags: vec!["urgent".to_string()],
+            status: Some(SessionStatus::Stopped),
+            working_dir: Some(PathBuf::from("/work/api")),
+            ..Default::default()
+        };
+        assert!(filter.matches(&session));
+
+        let missing_tag = SessionFilter {
+            tags: vec!["urgent".to_string(), "frontend".to_string()],
+            ..Default::default()
+        };
+        assert!(!missing_tag.matches(&session));
+        // Path components, not string prefixes
+        let sibling_dir = SessionFilter {
+            working_dir: Some(PathBuf::from("/work/ap")),
+            ..Default::default()
+        };
+        assert!(!sibling_dir.matches(&session));
+        let in_group = SessionFilter {
+            group_id: Some(Uuid::new_v4()),
+            ..Default::default()
+        };
+        assert!(!in_group.matches(&session));
+    }
+
+    #[test]
+    fn test_event_subscription() {
+        let all = EventSubscription::default();
+        assert!(all.is_all());
+        assert!(all.wants_event("pty:output"));
+
+        let none: EventSubscription = serde_json::from_str(r#"{"events": []}"#).unwrap();
+        assert!(!none.wants_event("session:created"));
+
+        let session_id = Uuid::new_v4();
+        let group_id = Uuid::new_v4();
+        let sub = EventSubscription {
+            events: vec!["session:*".to_string(), "pty:output".to_string()],
+            session_ids: vec![session_id],
+            group_ids: vec![group_id],
+        };
+        assert!(sub.wants_event("session:status_changed"));
+        assert!(sub.wants_event("pty:output"));
+        assert!(!sub.wants_event("pty:output_more"));
+        assert!(!sub.wants_event("group:created"));
+        assert!(sub.wants_session(session_id, Some(group_id)));
+        assert!(!sub.wants_session(session_id, None));
+        assert!(!sub.wants_session(Uuid::new_v4(), Some(group_id)));
+    }
+
+    #[test]
+    fn test_update_params_null_vs_absent() {
+        let id = Uuid::new_v4();
+        let params: UpdateSessionParams =
+            serde_json::from_value(serde_json::json!({"session_id": id, "group_id": null}))
+                .unwrap();
+        assert_eq!(params.group_id, Some(None));
+        assert_eq!(params.spawn_mode, None);
+        assert_eq!(params.name, None);
+
+        let params = UpdateGroupParams {
+            group_id: id,
+            name: None,
+            parent_id: Some(None),
+        };
+        let value = serde_json::to_value(&params).unwrap();
+        assert_eq!(
+            value,
+            serde_json::json!({"group_id": id, "parent_id": null})
+        );
+        let params: UpdateGroupParams = serde_json::from_value(value).unwrap();
+        assert_eq!(params.parent_id, Some(None));
+
+        let params: DeleteGroupParams =
+            serde_json::from_value(serde_json::json!({"session_id": id})).unwrap();
+        assert_eq!(params.group_id, id);
+        assert_eq!(params.mode, GroupDeleteMode::Reparent);
+    }
+
+    #[test]
+    fn test_error_codes() {
+        for code in ErrorCode::ALL {
+            assert_eq!(ErrorCode::from_code(code.code()), Some(code));
+        }
+        assert_eq!(ErrorCode::PtyDead.code(), -32001);
+        assert_eq!(ErrorCode::from_code(-31999), None);
+
+        let info = ErrorInfo::new(ErrorCode::SessionNotFound, "Session not found");
+        assert_eq!(info.code, -32002);
+        assert_eq!(info.kind(), Some(ErrorCode::SessionNotFound));
+    }
+
+    #[test]
+    fn test_event_wire_format() {
+        let session_id = Uuid::new_v4();
+        let event = Event::StatusChanged(StatusChangedData {
+            session_id,
+            status: SessionStatus::Waiting,
+            detail: None,
+        });
+        let value = serde_json::to_value(&event).unwrap();
+        assert_eq!(
+            value,
+            serde_json::json!({
+                "event": "session:status_changed",
+                "data": {"session_id": session_id, "status": "waiting"},
+            })
+        );
+        assert_eq!(event.name(), "session:status_changed");
+        assert_eq!(event.data(), value["data"]);
+        assert_eq!(event.session_id(), Some(session_id));
+
+        let event: Event = serde_json::from_value(
+            serde_json::json!({"event": "session:deleted", "data": {"session_id": session_id}}),
+        )
+        .unwrap();
+        assert!(matches!(event, Event::SessionDeleted { session_id: id } if id == session_id));
+        // Responses and unknown events don't parse
+        assert!(serde_json::from_str::<Event>(r#"{"id": 1, "result": {}}"#).is_err());
+        assert!(
+            serde_json::from_str::<Event>(r#"{"event": "session:exploded", "data": {}}"#).is_err()
+        );
+
+        // The sequence number rides alongside, and clients that don't know it still parse
+        let sequenced = SequencedEvent {
+            seq: Some(7),
+            event: Event::SessionDeleted { session_id },
+        };
+        let value = serde_json::to_value(&sequenced).unwrap();
+        assert_eq!(value["seq"], 7);
+        assert_eq!(value["event"], "session:deleted");
+        assert!(serde_json::from_value::<Event>(value.clone()).is_ok());
+        let parsed: SequencedEvent = serde_json::from_value(value).unwrap();
+        assert_eq!(parsed.seq, Some(7));
+        let unsequenced = serde_json::to_value(SequencedEvent::from(event)).unwrap();
+        assert!(unsequenced.get("seq").is_none());
+        let parsed: SequencedEvent = serde_json::from_value(unsequenced).unwrap();
+        assert_eq!(parsed.seq, None);
+    }
+}
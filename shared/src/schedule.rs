@@ -0,0 +1,50 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::protocol::CreateSessionParams;
+
+/// A session created and started on a cron schedule (see `schedule.create`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Schedule {
+    pub id: Uuid,
+    pub name: String,
+    /// Five fields (minute hour day-of-month month day-of-week) in local time,
+    /// or @hourly, @daily, @weekly, @monthly, @yearly
+    pub cron: String,
+    /// The session each run creates; its name gets the run's date appended
+    pub session: CreateSessionParams,
+    /// Prompt queued once a run's session has started
+    #[serde(default)]
+    pub prompt: Option<String>,
+    pub created_at: DateTime<Utc>,
+    /// None if the expression never matches
+    #[serde(default)]
+    pub next_run: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub last_run: Option<DateTime<Utc>>,
+    /// The session the last run created
+    #[serde(default)]
+    pub last_session_id: Option<Uuid>,
+}
+
+impl Schedule {
+    pub fn new(
+        name: String,
+        cron: String,
+        session: CreateSessionParams,
+        prompt: Option<String>,
+    ) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            name,
+            cron,
+            session,
+            prompt,
+            created_at: Utc::now(),
+            next_run: None,
+            last_run: None,
+            last_session_id: None,
+        }
+    }
+}
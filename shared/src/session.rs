@@ -3,17 +3,137 @@ use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 use uuid::Uuid;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, Default)]
 #[serde(rename_all = "lowercase")]
 pub enum SessionStatus {
     Running,
     Waiting,
     Idle,
     Error,
+    /// Waiting for a slot under daemon.max_concurrent_running
+    Queued,
+    /// Spawned, waiting for claude's first output
+    Starting,
     #[default]
     Stopped,
 }
 
+/// How the claude process is launched
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SpawnMode {
+    /// Run the resolved claude binary directly (no shell startup noise)
+    #[default]
+    Direct,
+    /// Run `$SHELL -ilc 'exec claude ...'` so rc files (direnv, asdf, ...) apply
+    Shell,
+}
+
+/// Command line flags for a session's claude (see `claude.capabilities` for
+/// what the installed binary accepts)
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ClaudeFlags {
+    /// `--model`, e.g. "opus" or a full model name
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    /// `--permission-mode`, e.g. "plan" or "acceptEdits"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub permission_mode: Option<String>,
+    /// Passed as is after the other flags
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub extra_args: Vec<String>,
+}
+
+impl ClaudeFlags {
+    /// The flags as claude arguments
+    pub fn args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        if let Some(model) = &self.model {
+            args.extend(["--model".to_string(), model.clone()]);
+        }
+        if let Some(mode) = &self.permission_mode {
+            args.extend(["--permission-mode".to_string(), mode.clone()]);
+        }
+        args.extend(self.extra_args.iter().cloned());
+        args
+    }
+}
+
+/// Git worktree created for a session by `session.create_worktree`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WorktreeInfo {
+    /// Main repository the worktree belongs to
+    pub repo_path: PathBuf,
+    /// Worktree checkout directory (also the session's working_dir)
+    pub path: PathBuf,
+    pub branch: String,
+}
+
+/// Permission prompts the daemon answers on its own (see `policy.set`); a
+/// group's policy also covers the sessions under it
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ApprovalPolicy {
+    /// Tools approved outright, e.g. "Read" ("*" for any tool)
+    #[serde(default)]
+    pub tools: Vec<String>,
+    /// Globs of files the file tools (Edit, Write, ...) may touch, absolute or
+    /// relative to the session's working directory
+    #[serde(default)]
+    pub paths: Vec<String>,
+    /// Regexes of Bash commands to approve
+    #[serde(default)]
+    pub commands: Vec<String>,
+}
+
+impl ApprovalPolicy {
+    pub fn is_empty(&self) -> bool {
+        self.tools.is_empty() && self.paths.is_empty() && self.commands.is_empty()
+    }
+}
+
+/// Another session this one starts after (see `session.set_dependency`)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SessionDependency {
+    /// Started once this session goes Idle or Stopped without an error
+    pub parent_id: Uuid,
+    /// Prompt to queue once started
+    #[serde(default)]
+    pub prompt: Option<String>,
+}
+
+/// A terminal's size in character cells
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TerminalSize {
+    pub rows: u16,
+    pub cols: u16,
+}
+
+impl TerminalSize {
+    /// The size from optional rows and cols, if both are given
+    pub fn from_parts(rows: Option<u16>, cols: Option<u16>) -> Option<Self> {
+        rows.zip(cols).map(|(rows, cols)| Self { rows, cols })
+    }
+}
+
+impl Default for TerminalSize {
+    fn default() -> Self {
+        Self { rows: 24, cols: 80 }
+    }
+}
+
+/// Repository state of a session's working directory
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GitStatus {
+    /// Current branch (None when HEAD is detached)
+    pub branch: Option<String>,
+    /// Number of modified, staged and untracked files
+    pub dirty: u32,
+    /// Commits ahead of upstream
+    pub ahead: u32,
+    /// Commits behind upstream
+    pub behind: u32,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Session {
     pub id: Uuid,
@@ -23,8 +143,14 @@ pub struct Session {
 
     #[serde(default)]
     pub status: SessionStatus,
-    #[serde(skip)]
+    /// Process id of the running claude, kept across daemon restarts so
+    /// orphaned processes can be found
+    #[serde(default)]
     pub pid: Option<u32>,
+    /// Start time of `pid` (seconds since the epoch), so a reused pid is not
+    /// mistaken for ours
+    #[serde(default)]
+    pub pid_start_time: Option<u64>,
 
     pub claude_session_id: Option<String>,
 
@@ -32,6 +158,50 @@ pub struct Session {
     pub last_activity: DateTime<Utc>,
     #[serde(default)]
     pub order: u32,
+    #[serde(default)]
+    pub worktree: Option<WorktreeInfo>,
+    /// Git status of working_dir (None if not a repository)
+    #[serde(default)]
+    pub git: Option<GitStatus>,
+    /// Spawn mode for this session (None = daemon.spawn_mode)
+    #[serde(default)]
+    pub spawn_mode: Option<SpawnMode>,
+    /// Named claude binary from `[claude] binaries` in config.toml (None = the default)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub claude_binary: Option<String>,
+    /// Free-form labels for slicing sessions by project or purpose
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Watch expressions that raise attention when true (see `watch.add`)
+    #[serde(default)]
+    pub watches: Vec<String>,
+    /// Permission prompts to answer automatically
+    #[serde(default, skip_serializing_if = "ApprovalPolicy::is_empty")]
+    pub approval_policy: ApprovalPolicy,
+    /// Output is kept on disk instead of sent to clients (see `session.detach`)
+    #[serde(default)]
+    pub detached: bool,
+    /// Waiting for another session to finish before starting
+    #[serde(default)]
+    pub dependency: Option<SessionDependency>,
+    /// Last known terminal size, spawned at when a start doesn't give one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size: Option<TerminalSize>,
+    /// Why the session went to Error when it failed to start or its process
+    /// exited with an error (cleared when it starts again)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error_reason: Option<String>,
+    /// Why the session has its status, e.g. the tool a permission dialog asks
+    /// about or the error claude printed (cleared by the next status change)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status_detail: Option<String>,
+    /// Output since a client last marked the session viewed
+    /// (`session.mark_viewed`), so every window shows the same badge
+    #[serde(default)]
+    pub unread_bytes: u64,
+    /// Flags claude is started with
+    #[serde(flatten)]
+    pub flags: ClaudeFlags,
 }
 
 impl Session {
@@ -44,10 +214,25 @@ impl Session {
             working_dir,
             status: SessionStatus::Stopped,
             pid: None,
+            pid_start_time: None,
             claude_session_id: None,
             created_at: now,
             last_activity: now,
             order: 0,
+            worktree: None,
+            git: None,
+            spawn_mode: None,
+            claude_binary: None,
+            tags: Vec::new(),
+            watches: Vec::new(),
+            approval_policy: ApprovalPolicy::default(),
+            detached: false,
+            dependency: None,
+            size: None,
+            error_reason: None,
+            status_detail: None,
+            unread_bytes: 0,
+            flags: ClaudeFlags::default(),
         }
     }
 }
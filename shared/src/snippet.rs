@@ -0,0 +1,27 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// A reusable prompt (see `snippet.create`); `{{branch}}`, `{{dir}}` and the
+/// other variables in its text are filled in from the session it is sent to
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Snippet {
+    pub id: Uuid,
+    pub name: String,
+    pub text: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl Snippet {
+    pub fn new(name: String, text: String) -> Self {
+        let now = Utc::now();
+        Self {
+            id: Uuid::new_v4(),
+            name,
+            text,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+}